@@ -0,0 +1,59 @@
+//! Measures the time needed to build an R1CS circuit for a multi-block SHA-256 hash, where the
+//! initial hash state is unknown, both with the native word representation of
+//! [`ArithmeticSystem`] and with bitwise emulation. Run with `cargo bench`.
+use bls12_381::Scalar;
+use circus::crypto::hash::*;
+use circus::r1cs::*;
+use circus::*;
+use std::time::{Duration, Instant};
+
+/// Builds the circuit for hashing the given number of blocks, returning the number of
+/// constraints it contains.
+fn build(blocks: usize) -> usize {
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let mut hasher: Abstract<ArithmeticSystem<Scalar>, Sha256> =
+        array_init::array_init(|_| SystemRand::<u32>::rand(&mut sys));
+    for i in 0..blocks {
+        let mut chunk = [0; 16];
+        chunk[0] = i as u32;
+        sys.sha256_update(&mut hasher, chunk);
+    }
+    sys.constraints().len()
+}
+
+/// Like [`build`], but uses bitwise emulation.
+fn build_emulated(blocks: usize) -> usize {
+    type System = BinaryEmulate<ArithmeticSystem<Scalar>>;
+    let mut sys: System = BinaryEmulate::new(ArithmeticSystem::new());
+    let mut hasher: Abstract<System, Sha256> = array_init::array_init(|_| {
+        array_init::array_init(|_| sys.source_mut().declare_bool())
+    });
+    for i in 0..blocks {
+        let mut chunk = [0; 16];
+        chunk[0] = i as u32;
+        sys.sha256_update(&mut hasher, chunk);
+    }
+    sys.source().constraints().len()
+}
+
+fn main() {
+    for (name, build) in [
+        ("native", build as fn(_) -> _),
+        ("emulated", build_emulated),
+    ] {
+        for blocks in [1, 4, 16] {
+            let mut iters = 0;
+            let mut num_constraints = 0;
+            let start = Instant::now();
+            while iters < 3 || start.elapsed() < Duration::from_secs(2) {
+                num_constraints = build(blocks);
+                iters += 1;
+            }
+            let per_iter = start.elapsed() / iters;
+            println!(
+                "sha256 {} ({} blocks): {:?} per build, {} constraints",
+                name, blocks, per_iter, num_constraints
+            );
+        }
+    }
+}
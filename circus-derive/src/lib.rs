@@ -0,0 +1,100 @@
+//! Derive macros for the `circus` crate. These are re-exported by `circus` under its `derive`
+//! feature, and should be used through it rather than depended on directly.
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Ident};
+
+/// Derives the abstract representation of an enum in a system `S`, as defined by
+/// `circus::abstract_enum!`, for an enum defined outside of the macro. Variants may be unit
+/// variants or have named fields, which must be distinct across all variants and have types
+/// implementing `Default`.
+///
+/// The abstract struct is named `Abstract` followed by the name of the enum, unless another name
+/// is given with `#[circus(name = ...)]`. It has the same visibility as the enum.
+#[proc_macro_derive(AbstractEnum, attributes(circus))]
+pub fn derive_abstract_enum(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    abstract_enum(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Expands `#[derive(AbstractEnum)]` into an invocation of `circus::abstract_enum!` which
+/// defines the abstract representation without redefining the enum.
+fn abstract_enum(input: DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "`AbstractEnum` can only be derived for enums",
+        ));
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "`AbstractEnum` can not be derived for generic enums",
+        ));
+    }
+    if data.variants.is_empty() {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "`AbstractEnum` can not be derived for enums without variants",
+        ));
+    }
+    let name = &input.ident;
+    let abs = abstract_name(&input)?;
+    let vis = &input.vis;
+    let variants = (data.variants.iter())
+        .map(|variant| {
+            let ident = &variant.ident;
+            match &variant.fields {
+                Fields::Unit => Ok(quote!(#ident)),
+                Fields::Named(fields) => {
+                    let fields = fields.named.iter().map(|field| {
+                        // Only documentation carries over to the fields of the abstract struct,
+                        // since other attributes may not apply to them
+                        let docs = field
+                            .attrs
+                            .iter()
+                            .filter(|attr| attr.path().is_ident("doc"));
+                        let ident = &field.ident;
+                        let ty = &field.ty;
+                        quote!(#(#docs)* #ident: #ty)
+                    });
+                    Ok(quote!(#ident { #(#fields),* }))
+                }
+                Fields::Unnamed(_) => Err(Error::new_spanned(
+                    variant,
+                    "`AbstractEnum` requires variants to be unit variants or have named fields",
+                )),
+            }
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    Ok(quote! {
+        ::circus::abstract_enum! {
+            @impl #vis #name as #abs {
+                #(#variants),*
+            }
+        }
+    })
+}
+
+/// Gets the name of the abstract struct for a derive input, from its `#[circus(name = ...)]`
+/// attribute if it has one.
+fn abstract_name(input: &DeriveInput) -> syn::Result<Ident> {
+    let mut name = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("circus"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse::<Ident>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported `circus` attribute"))
+            }
+        })?;
+    }
+    Ok(name.unwrap_or_else(|| format_ident!("Abstract{}", input.ident)))
+}
@@ -0,0 +1,285 @@
+//! An arithmetic logic unit for 32-bit words, as found in RISC-style instruction sets.
+use crate::bitset::{add_bits, const_bits, div_rem_bits, mul_bits, sub_bits, Bits};
+use crate::*;
+use alloc::vec::Vec;
+use array_init::array_init;
+
+/// A system in which an [`Alu32`] can be used.
+pub trait SystemAlu: BinarySystem + SystemBits {}
+
+impl<S: BinarySystem + SystemBits + ?Sized> SystemAlu for S {}
+
+/// An operation of an [`Alu32`]. The discriminant of each operation is its opcode. Shift and
+/// rotate amounts are taken from the low 5 bits of the second operand. Division follows RISC-V:
+/// dividing by zero gives a quotient with all bits set and leaves the dividend as the remainder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum AluOp {
+    /// Wrapping addition.
+    Add = 0,
+
+    /// Wrapping subtraction.
+    Sub = 1,
+
+    /// The low 32 bits of the product.
+    Mul = 2,
+
+    /// The high 32 bits of the unsigned product.
+    MulHu = 3,
+
+    /// Unsigned division.
+    DivU = 4,
+
+    /// The remainder of unsigned division.
+    RemU = 5,
+
+    /// Logical shift left.
+    Sll = 6,
+
+    /// Logical shift right.
+    Srl = 7,
+
+    /// Arithmetic shift right.
+    Sra = 8,
+
+    /// Rotate left.
+    Rotl = 9,
+
+    /// Rotate right.
+    Rotr = 10,
+
+    /// Signed comparison, giving 1 if the first operand is less than the second, or 0 otherwise.
+    Slt = 11,
+
+    /// Unsigned comparison, giving 1 if the first operand is less than the second, or 0
+    /// otherwise.
+    SltU = 12,
+
+    /// Bitwise AND.
+    And = 13,
+
+    /// Bitwise OR.
+    Or = 14,
+
+    /// Bitwise XOR.
+    Xor = 15,
+}
+
+impl AluOp {
+    /// All operations, in order of opcode.
+    pub const ALL: [AluOp; 16] = [
+        AluOp::Add,
+        AluOp::Sub,
+        AluOp::Mul,
+        AluOp::MulHu,
+        AluOp::DivU,
+        AluOp::RemU,
+        AluOp::Sll,
+        AluOp::Srl,
+        AluOp::Sra,
+        AluOp::Rotl,
+        AluOp::Rotr,
+        AluOp::Slt,
+        AluOp::SltU,
+        AluOp::And,
+        AluOp::Or,
+        AluOp::Xor,
+    ];
+
+    /// Applies this operation to concrete operands.
+    pub fn apply(self, a: u32, b: u32) -> u32 {
+        let shamt = b & 31;
+        match self {
+            AluOp::Add => a.wrapping_add(b),
+            AluOp::Sub => a.wrapping_sub(b),
+            AluOp::Mul => a.wrapping_mul(b),
+            AluOp::MulHu => ((a as u64 * b as u64) >> 32) as u32,
+            AluOp::DivU => a.checked_div(b).unwrap_or(u32::MAX),
+            AluOp::RemU => a.checked_rem(b).unwrap_or(a),
+            AluOp::Sll => a << shamt,
+            AluOp::Srl => a >> shamt,
+            AluOp::Sra => ((a as i32) >> shamt) as u32,
+            AluOp::Rotl => a.rotate_left(shamt),
+            AluOp::Rotr => a.rotate_right(shamt),
+            AluOp::Slt => ((a as i32) < (b as i32)) as u32,
+            AluOp::SltU => (a < b) as u32,
+            AluOp::And => a & b,
+            AluOp::Or => a | b,
+            AluOp::Xor => a ^ b,
+        }
+    }
+}
+
+/// A gadget which applies any of a set of [`AluOp`]s to a pair of words, chosen by an abstract
+/// opcode. The operands are decomposed into bits once, and operations which need the same
+/// intermediate results, such as [`AluOp::Sub`] and the comparisons, or [`AluOp::Mul`] and
+/// [`AluOp::MulHu`], share them. Restricting the set of operations reduces the cost.
+#[derive(Debug, Clone)]
+pub struct Alu32 {
+    ops: Vec<AluOp>,
+}
+
+impl Default for Alu32 {
+    fn default() -> Self {
+        Self::new(&AluOp::ALL)
+    }
+}
+
+impl Alu32 {
+    /// Constructs an [`Alu32`] supporting the given operations.
+    pub fn new(ops: &[AluOp]) -> Self {
+        let mut ops = ops.to_vec();
+        ops.sort_by_key(|op| *op as u8);
+        ops.dedup();
+        Self { ops }
+    }
+
+    /// The operations supported by this ALU, in order of opcode.
+    pub fn ops(&self) -> &[AluOp] {
+        &self.ops
+    }
+
+    /// Applies the operation with the given opcode to `a` and `b`. If the opcode does not
+    /// identify a supported operation, the result is zero.
+    pub fn apply<S: SystemAlu + ?Sized>(
+        &self,
+        sys: &mut S,
+        opcode: &Abstract<S, u8>,
+        a: &Abstract<S, u32>,
+        b: &Abstract<S, u32>,
+    ) -> Abstract<S, u32> {
+        let has = |op: AluOp| self.ops.contains(&op);
+        let a = sys.bits_of_u32(a);
+        let b = sys.bits_of_u32(b);
+        let zero = sys.constant(false);
+
+        // Shared intermediate results
+        let diff =
+            (has(AluOp::Sub) || has(AluOp::Slt) || has(AluOp::SltU)).then(|| sub_bits(sys, &a, &b));
+        let product = (has(AluOp::Mul) || has(AluOp::MulHu)).then(|| mul_bits(sys, &a, &b));
+        let quot_rem = (has(AluOp::DivU) || has(AluOp::RemU)).then(|| div_rem_bits(sys, &a, &b));
+        let shamt = &b[..5];
+
+        let opcode = sys.bits_of_u8(opcode);
+        let sel = one_hot(sys, &opcode, AluOp::ALL.len());
+        let mut res = const_bits(sys, 0, 32);
+        for op in self.ops.iter() {
+            let bits: Bits<S> = match op {
+                AluOp::Add => add_bits(sys, &a, &b),
+                AluOp::Sub => diff.as_ref().unwrap().0.clone(),
+                AluOp::Mul => product.as_ref().unwrap()[..32].to_vec(),
+                AluOp::MulHu => product.as_ref().unwrap()[32..].to_vec(),
+                AluOp::DivU => quot_rem.as_ref().unwrap().0.clone(),
+                AluOp::RemU => quot_rem.as_ref().unwrap().1.clone(),
+                AluOp::Sll => shift(sys, &a, shamt, |i, n| i.checked_sub(n)),
+                AluOp::Srl => shift(sys, &a, shamt, |i, n| Some(i + n).filter(|j| *j < 32)),
+                AluOp::Sra => shift(sys, &a, shamt, |i, n| Some((i + n).min(31))),
+                AluOp::Rotl => shift(sys, &a, shamt, |i, n| Some((i + 32 - n) % 32)),
+                AluOp::Rotr => shift(sys, &a, shamt, |i, n| Some((i + n) % 32)),
+                AluOp::Slt => {
+                    // If the signs differ, the negative operand is less
+                    let borrow = &diff.as_ref().unwrap().1;
+                    let signs = sys.xor(&a[31], &b[31]);
+                    let lt = select_bit(sys, &signs, &a[31], borrow);
+                    flag(&lt, &zero)
+                }
+                AluOp::SltU => flag(&diff.as_ref().unwrap().1, &zero),
+                AluOp::And => (0..32).map(|i| sys.and(&a[i], &b[i])).collect(),
+                AluOp::Or => (0..32).map(|i| sys.or(&a[i], &b[i])).collect(),
+                AluOp::Xor => (0..32).map(|i| sys.xor(&a[i], &b[i])).collect(),
+            };
+
+            // At most one operation is selected, so the results can be combined with XOR
+            let sel = &sel[*op as usize];
+            res = (0..32)
+                .map(|i| {
+                    let bit = sys.and(sel, &bits[i]);
+                    sys.xor(&res[i], &bit)
+                })
+                .collect();
+        }
+        let res: [_; 32] = array_init(|i| res[i].clone());
+        sys.u32_of_bits(&res)
+    }
+}
+
+/// Shifts a word by an abstract amount using a barrel shifter. `source` gives, for each output
+/// bit and shift amount, the index of the input bit which moves into it, or `None` if it is
+/// filled with zero.
+fn shift<S: SystemAlu + ?Sized>(
+    sys: &mut S,
+    value: &[Abstract<S, bool>],
+    shamt: &[Abstract<S, bool>],
+    source: impl Fn(usize, usize) -> Option<usize>,
+) -> Bits<S> {
+    let zero = sys.constant(false);
+    let mut res = value.to_vec();
+    for (k, bit) in shamt.iter().enumerate() {
+        let n = 1 << k;
+        res = (0..32)
+            .map(|i| {
+                let shifted = source(i, n).map_or(&zero, |j| &res[j]);
+                select_bit(sys, bit, shifted, &res[i])
+            })
+            .collect();
+    }
+    res
+}
+
+/// Converts a boolean into a word which is 1 if it is set, or 0 otherwise.
+fn flag<B: Clone>(value: &B, zero: &B) -> Vec<B> {
+    let mut res = alloc::vec![zero.clone(); 32];
+    res[0] = value.clone();
+    res
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_alu32() {
+    let alu = Alu32::default();
+    Eval::seed_rng(11);
+    let mut operands = alloc::vec![
+        (0, 0),
+        (1, 0),
+        (u32::MAX, 1),
+        (0x8000_0000, 31),
+        (0x8000_0000, 0x7fff_ffff),
+        (12345, 100),
+    ];
+    for _ in 0..20 {
+        let a = SystemRand::<u32>::rand(&mut Eval);
+        let b = SystemRand::<u32>::rand(&mut Eval);
+        operands.push((a, b));
+        operands.push((a, b % 40));
+    }
+    for (a, b) in operands {
+        for op in AluOp::ALL {
+            let res = alu.apply(&mut Eval, &(op as u8), &a, &b);
+            assert_eq!(res, op.apply(a, b), "{:?} {} {}", op, a, b);
+        }
+        assert_eq!(alu.apply(&mut Eval, &16, &a, &b), 0);
+    }
+
+    // Through binary emulation, with a subset of operations
+    let alu = Alu32::new(&[AluOp::Xor, AluOp::Add, AluOp::Sra]);
+    assert_eq!(alu.ops(), [AluOp::Add, AluOp::Sra, AluOp::Xor]);
+    let mut sys = BinaryEmulate::new(Eval);
+    let (a, b) = (0x9000_0001u32, 4u32);
+    for op in AluOp::ALL {
+        let opcode = sys.constant(op as u8);
+        let abs_a = sys.constant(a);
+        let abs_b = sys.constant(b);
+        let res = alu.apply(&mut sys, &opcode, &abs_a, &abs_b);
+        let expected = if alu.ops().contains(&op) {
+            op.apply(a, b)
+        } else {
+            0
+        };
+        assert_eq!(
+            SystemRead::<u32>::read_value(&sys, &res),
+            expected,
+            "{:?}",
+            op
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Implementations of [`proptest::arbitrary::Arbitrary`] and [`quickcheck::Arbitrary`] for the
+//! value types of the crate. Generated values are always structurally valid, and so are all of
+//! the values they shrink to.
+use crate::field::FieldElement;
+use ff::PrimeField;
+
+#[cfg(feature = "sha2")]
+use crate::crypto::hash::Sha256;
+
+#[cfg(feature = "bytes")]
+use crate::{bytes::AbstractBytes, Eval};
+
+/// The maximum capacity of byte strings generated without an explicit capacity.
+#[cfg(all(feature = "bytes", feature = "proptest"))]
+const MAX_CAPACITY: usize = 64;
+
+/// Constructs a field element from big-endian 64-bit limbs, reduced modulo the field order.
+fn from_limbs<F: PrimeField>(limbs: &[u64]) -> F {
+    let shift = F::from(u64::MAX) + F::one();
+    limbs
+        .iter()
+        .fold(F::zero(), |acc, limb| acc * shift + F::from(*limb))
+}
+
+/// The number of limbs needed for [`from_limbs`] to produce a nearly uniform field element.
+fn num_limbs<F: PrimeField>() -> usize {
+    F::NUM_BITS as usize / 64 + 2
+}
+
+/// Constructs a hash state from its difference with the initial state, so that shrinking the
+/// difference towards zero shrinks the state towards [`Sha256::new`].
+#[cfg(feature = "sha2")]
+fn sha256_of_diff(diff: [u32; 8]) -> Sha256 {
+    let init = Sha256::new().0;
+    Sha256(core::array::from_fn(|i| init[i] ^ diff[i]))
+}
+
+/// Constructs a byte string with the given content and capacity.
+#[cfg(feature = "bytes")]
+fn bytes_of(content: &[u8], capacity: usize) -> AbstractBytes<Eval> {
+    AbstractBytes::from_const(&mut Eval, content, capacity)
+}
+
+#[cfg(feature = "proptest")]
+mod proptest_impls {
+    use super::*;
+    use proptest::prelude::*;
+
+    impl<F: PrimeField + 'static> Arbitrary for FieldElement<F> {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            // Small values and their negations are where most edge cases are, and both shrink
+            // towards zero
+            prop_oneof![
+                any::<u64>().prop_map(|x| FieldElement(F::from(x))),
+                any::<u64>().prop_map(|x| FieldElement(-F::from(x))),
+                proptest::collection::vec(any::<u64>(), num_limbs::<F>())
+                    .prop_map(|limbs| FieldElement(from_limbs(&limbs))),
+            ]
+            .boxed()
+        }
+    }
+
+    #[cfg(feature = "sha2")]
+    impl Arbitrary for Sha256 {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            any::<[u32; 8]>().prop_map(sha256_of_diff).boxed()
+        }
+    }
+
+    /// Byte strings are generated with the given capacity, or with an arbitrary capacity of at
+    /// most [`MAX_CAPACITY`] if it is `None`. Shrinking removes and zeroes bytes of the content,
+    /// keeping the bytes beyond the length zero.
+    #[cfg(feature = "bytes")]
+    impl Arbitrary for AbstractBytes<Eval> {
+        type Parameters = Option<usize>;
+        type Strategy = BoxedStrategy<Self>;
+        fn arbitrary_with(capacity: Option<usize>) -> Self::Strategy {
+            match capacity {
+                Some(capacity) => proptest::collection::vec(any::<u8>(), 0..=capacity)
+                    .prop_map(move |content| bytes_of(&content, capacity))
+                    .boxed(),
+                None => (
+                    proptest::collection::vec(any::<u8>(), 0..=MAX_CAPACITY),
+                    0..=MAX_CAPACITY,
+                )
+                    .prop_map(|(content, extra)| {
+                        let capacity = MAX_CAPACITY.min(content.len() + extra);
+                        bytes_of(&content, capacity)
+                    })
+                    .boxed(),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "quickcheck")]
+mod quickcheck_impls {
+    use super::*;
+    use alloc::boxed::Box;
+    use alloc::vec::Vec;
+    use quickcheck::{Arbitrary, Gen};
+
+    /// Converts a field element to an integer, if it fits in a `u64`. This assumes a
+    /// little-endian representation.
+    pub(super) fn to_u64<F: PrimeField>(value: F) -> Option<u64> {
+        let repr = value.to_repr();
+        let bytes = repr.as_ref();
+        let (low, high) = bytes.split_at(bytes.len().min(8));
+        if high.iter().any(|byte| *byte != 0) {
+            return None;
+        }
+        let mut buf = [0; 8];
+        buf[..low.len()].copy_from_slice(low);
+        Some(u64::from_le_bytes(buf))
+    }
+
+    impl<F: PrimeField + 'static> Arbitrary for FieldElement<F> {
+        fn arbitrary(g: &mut Gen) -> Self {
+            match g.choose(&[0, 1, 2]).unwrap() {
+                0 => FieldElement(F::from(u64::arbitrary(g))),
+                1 => FieldElement(-F::from(u64::arbitrary(g))),
+                _ => {
+                    let limbs: Vec<u64> =
+                        (0..num_limbs::<F>()).map(|_| u64::arbitrary(g)).collect();
+                    FieldElement(from_limbs(&limbs))
+                }
+            }
+        }
+
+        fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+            if let Some(x) = to_u64(self.0) {
+                Box::new(x.shrink().map(|x| FieldElement(F::from(x))))
+            } else if let Some(x) = to_u64(-self.0) {
+                Box::new(x.shrink().map(|x| FieldElement(-F::from(x))))
+            } else {
+                Box::new([F::zero(), F::one()].into_iter().map(FieldElement))
+            }
+        }
+    }
+
+    #[cfg(feature = "sha2")]
+    impl Arbitrary for Sha256 {
+        fn arbitrary(g: &mut Gen) -> Self {
+            if bool::arbitrary(g) {
+                Sha256::new()
+            } else {
+                Sha256(core::array::from_fn(|_| u32::arbitrary(g)))
+            }
+        }
+
+        fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+            let init = Sha256::new().0;
+            let diff: [u32; 8] = core::array::from_fn(|i| self.0[i] ^ init[i]);
+            Box::new((0..8).flat_map(move |i| {
+                diff[i].shrink().map(move |word| {
+                    let mut diff = diff;
+                    diff[i] = word;
+                    sha256_of_diff(diff)
+                })
+            }))
+        }
+    }
+
+    /// Byte strings are generated with a capacity of at most [`Gen::size`]. Shrinking first
+    /// reduces the capacity to the length, then shrinks the content within that capacity.
+    #[cfg(feature = "bytes")]
+    impl Arbitrary for AbstractBytes<Eval> {
+        fn arbitrary(g: &mut Gen) -> Self {
+            let capacity = usize::arbitrary(g) % (g.size() + 1);
+            let len = usize::arbitrary(g) % (capacity + 1);
+            let content: Vec<u8> = (0..len).map(|_| u8::arbitrary(g)).collect();
+            bytes_of(&content, capacity)
+        }
+
+        fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+            let len = *self.len() as usize;
+            let content = self.data()[..len].to_vec();
+            let capacity = self.capacity();
+            let tight = (capacity > len).then(|| bytes_of(&content, len));
+            Box::new(
+                tight.into_iter().chain(
+                    content
+                        .shrink()
+                        .map(move |content| bytes_of(&content, capacity)),
+                ),
+            )
+        }
+    }
+}
+
+/// Determines whether a byte string has a length within its capacity and zeros beyond it.
+#[cfg(all(test, feature = "bytes"))]
+fn is_valid(bytes: &AbstractBytes<Eval>) -> bool {
+    let len = *bytes.len() as usize;
+    len <= bytes.capacity() && bytes.data()[len..].iter().all(|byte| *byte == 0)
+}
+
+#[cfg(all(feature = "proptest", feature = "bytes"))]
+#[test]
+fn test_proptest_bytes() {
+    use proptest::prelude::*;
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestRunner;
+    let mut runner = TestRunner::deterministic();
+    for capacity in [None, Some(0), Some(5)] {
+        for _ in 0..32 {
+            let mut tree = any_with::<AbstractBytes<Eval>>(capacity)
+                .new_tree(&mut runner)
+                .unwrap();
+            loop {
+                let bytes = tree.current();
+                assert!(is_valid(&bytes));
+                assert!(bytes.capacity() <= capacity.unwrap_or(MAX_CAPACITY));
+                if !tree.simplify() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "proptest")]
+#[test]
+fn test_proptest_field() {
+    use bls12_381::Scalar;
+    use proptest::prelude::*;
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestRunner;
+    let mut runner = TestRunner::deterministic();
+    let mut tree = any::<FieldElement<Scalar>>().new_tree(&mut runner).unwrap();
+    while tree.simplify() {}
+    assert_eq!(tree.current(), FieldElement(Scalar::zero()));
+}
+
+#[cfg(all(feature = "proptest", feature = "sha2"))]
+#[test]
+fn test_proptest_sha256() {
+    use proptest::prelude::*;
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestRunner;
+    let mut runner = TestRunner::deterministic();
+    let mut tree = any::<Sha256>().new_tree(&mut runner).unwrap();
+    while tree.simplify() {}
+    assert_eq!(tree.current(), Sha256::new());
+}
+
+#[cfg(all(feature = "quickcheck", feature = "bytes"))]
+#[test]
+fn test_quickcheck_bytes() {
+    use quickcheck::{Arbitrary, Gen};
+    let mut g = Gen::new(16);
+    for _ in 0..32 {
+        let bytes = AbstractBytes::<Eval>::arbitrary(&mut g);
+        assert!(is_valid(&bytes));
+        assert!(bytes.capacity() <= 16);
+        for shrunk in bytes.shrink() {
+            assert!(is_valid(&shrunk));
+            assert!(shrunk.capacity() <= bytes.capacity());
+        }
+    }
+}
+
+#[cfg(feature = "quickcheck")]
+#[test]
+fn test_quickcheck_field() {
+    use bls12_381::Scalar;
+    use quickcheck::Arbitrary;
+    use quickcheck_impls::to_u64;
+    let small = FieldElement(Scalar::from(100));
+    assert!(small
+        .shrink()
+        .all(|x| x != small && to_u64(x.0) < Some(100)));
+    let negative = FieldElement(-Scalar::from(100));
+    assert!(negative
+        .shrink()
+        .any(|x| x == FieldElement(-Scalar::from(50))));
+    let large = FieldElement(Scalar::from(u64::MAX) * Scalar::from(u64::MAX));
+    assert_eq!(large.shrink().count(), 2);
+}
+
+#[cfg(all(feature = "quickcheck", feature = "sha2"))]
+#[test]
+fn test_quickcheck_sha256() {
+    use quickcheck::{Arbitrary, Gen};
+    assert_eq!(Sha256::new().shrink().count(), 0);
+    let mut g = Gen::new(16);
+    for _ in 0..8 {
+        let state = Sha256::arbitrary(&mut g);
+        if state != Sha256::new() {
+            // The first candidate for each differing word restores it to the initial state
+            assert!(state.shrink().any(|shrunk| {
+                (0..8)
+                    .filter(|i| shrunk.0[*i] != Sha256::new().0[*i])
+                    .count()
+                    < (0..8)
+                        .filter(|i| state.0[*i] != Sha256::new().0[*i])
+                        .count()
+            }));
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! Reduced ordered binary decision diagrams, which represent boolean functions canonically.
+use crate::*;
+use std::collections::HashMap;
+
+/// Identifies a function represented in a [`Bdd`]. Since the representation is canonical, two
+/// references are equal exactly when their functions are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BddRef(u32);
+
+impl BddRef {
+    /// The constant false function.
+    pub const FALSE: Self = Self(0);
+
+    /// The constant true function.
+    pub const TRUE: Self = Self(1);
+}
+
+/// A decision node of a [`Bdd`], which takes the value of `high` if its variable is set, or
+/// `low` otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct BddNode {
+    var: u32,
+    low: BddRef,
+    high: BddRef,
+}
+
+/// A binary operation between [`BddRef`]s, used as a key for memoization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BddOp {
+    And,
+    Or,
+    Xor,
+}
+
+/// A [`BinarySystem`] which represents each abstract boolean as a binary decision diagram over
+/// the variables introduced by [`Bdd::var`], ordered by when they were introduced. This decides
+/// questions about a circuit over all of its inputs at once, such as whether two circuits are
+/// equivalent, but the size of a diagram may grow exponentially with the number of variables.
+///
+/// To keep that under control, a [`Bdd`] may be given a limit on its number of nodes. Once the
+/// limit is exceeded, operations produce meaningless results and [`SystemRepr::status`] reports
+/// [`BddLimitExceeded`].
+pub struct Bdd {
+    /// The decision nodes, following the two terminal nodes, which have placeholder entries.
+    nodes: Vec<BddNode>,
+    num_vars: u32,
+    limit: usize,
+    exceeded: bool,
+
+    /// The existing node for each decision, used to keep the diagrams reduced.
+    unique: HashMap<BddNode, BddRef>,
+
+    /// The results of operations which have already been computed.
+    cache: HashMap<(BddOp, BddRef, BddRef), BddRef>,
+}
+
+/// Indicates that a [`Bdd`] exceeded its limit on the number of nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BddLimitExceeded {
+    /// The maximum number of nodes allowed.
+    pub limit: usize,
+}
+
+impl std::fmt::Display for BddLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "exceeded limit of {} BDD nodes", self.limit)
+    }
+}
+
+impl std::error::Error for BddLimitExceeded {}
+
+impl Bdd {
+    /// Constructs a new [`Bdd`] with no limit on its number of nodes.
+    pub fn new() -> Self {
+        Self::with_limit(usize::MAX)
+    }
+
+    /// Constructs a new [`Bdd`] which fails once it has more than `limit` nodes.
+    pub fn with_limit(limit: usize) -> Self {
+        let terminal = BddNode {
+            var: u32::MAX,
+            low: BddRef::FALSE,
+            high: BddRef::FALSE,
+        };
+        Self {
+            nodes: vec![terminal; 2],
+            num_vars: 0,
+            limit,
+            exceeded: false,
+            unique: HashMap::new(),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Introduces a new variable, returning the function which is true exactly when it is set.
+    /// Variables are ordered by when they were introduced.
+    pub fn var(&mut self) -> BddRef {
+        let var = self.num_vars;
+        self.num_vars += 1;
+        self.node(var, BddRef::FALSE, BddRef::TRUE)
+    }
+
+    /// The number of variables introduced into this [`Bdd`].
+    pub fn num_vars(&self) -> usize {
+        self.num_vars as usize
+    }
+
+    /// The number of nodes in this [`Bdd`], including the two terminal nodes.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Evaluates a function for the given values of the variables.
+    pub fn eval(&self, func: BddRef, values: &[bool]) -> bool {
+        assert_eq!(values.len(), self.num_vars());
+        let mut func = func;
+        while func.0 > 1 {
+            let node = self.nodes[func.0 as usize];
+            func = if values[node.var as usize] {
+                node.high
+            } else {
+                node.low
+            };
+        }
+        func == BddRef::TRUE
+    }
+
+    /// Finds values of the variables for which the given function is true, or returns `None` if
+    /// there are none. Variables which don't affect the result are false.
+    pub fn satisfy(&self, func: BddRef) -> Option<Vec<bool>> {
+        if func == BddRef::FALSE {
+            return None;
+        }
+
+        // Every decision node has a path to the true terminal, since the diagram is reduced
+        let mut values = vec![false; self.num_vars()];
+        let mut func = func;
+        while func.0 > 1 {
+            let node = self.nodes[func.0 as usize];
+            if node.low == BddRef::FALSE {
+                values[node.var as usize] = true;
+                func = node.high;
+            } else {
+                func = node.low;
+            }
+        }
+        Some(values)
+    }
+
+    /// Gets the node for the given decision, creating it if needed.
+    fn node(&mut self, var: u32, low: BddRef, high: BddRef) -> BddRef {
+        if low == high {
+            return low;
+        }
+        let node = BddNode { var, low, high };
+        if let Some(res) = self.unique.get(&node) {
+            return *res;
+        }
+        if self.nodes.len() >= self.limit {
+            self.exceeded = true;
+            return BddRef::FALSE;
+        }
+        let res = BddRef(u32::try_from(self.nodes.len()).expect("too many nodes"));
+        self.nodes.push(node);
+        self.unique.insert(node, res);
+        res
+    }
+
+    /// Applies a binary operation to two functions.
+    fn apply(&mut self, op: BddOp, a: BddRef, b: BddRef) -> BddRef {
+        match (op, a, b) {
+            (BddOp::And, BddRef::FALSE, _) | (BddOp::And, _, BddRef::FALSE) => {
+                return BddRef::FALSE
+            }
+            (BddOp::And, BddRef::TRUE, x) | (BddOp::And, x, BddRef::TRUE) => return x,
+            (BddOp::Or, BddRef::TRUE, _) | (BddOp::Or, _, BddRef::TRUE) => return BddRef::TRUE,
+            (BddOp::Or, BddRef::FALSE, x) | (BddOp::Or, x, BddRef::FALSE) => return x,
+            (BddOp::Xor, BddRef::FALSE, x) | (BddOp::Xor, x, BddRef::FALSE) => return x,
+            (BddOp::And | BddOp::Or, x, y) if x == y => return x,
+            (BddOp::Xor, x, y) if x == y => return BddRef::FALSE,
+            _ => (),
+        }
+
+        // All of the operations are commutative, so normalize the order of the operands
+        let (a, b) = if a.0 <= b.0 { (a, b) } else { (b, a) };
+        if let Some(res) = self.cache.get(&(op, a, b)) {
+            return *res;
+        }
+        let var = |this: &Self, x: BddRef| match x.0 {
+            0 | 1 => u32::MAX,
+            i => this.nodes[i as usize].var,
+        };
+        let var = var(self, a).min(var(self, b));
+        let cofactors = |this: &Self, x: BddRef| {
+            let node = this.nodes[x.0 as usize];
+            if x.0 > 1 && node.var == var {
+                (node.low, node.high)
+            } else {
+                (x, x)
+            }
+        };
+        let (a_low, a_high) = cofactors(self, a);
+        let (b_low, b_high) = cofactors(self, b);
+        let low = self.apply(op, a_low, b_low);
+        let high = self.apply(op, a_high, b_high);
+        let res = self.node(var, low, high);
+        self.cache.insert((op, a, b), res);
+        res
+    }
+}
+
+impl Default for Bdd {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemRepr<bool> for Bdd {
+    type Abstract = BddRef;
+    type Error = BddLimitExceeded;
+    fn constant(&mut self, value: bool) -> BddRef {
+        if value {
+            BddRef::TRUE
+        } else {
+            BddRef::FALSE
+        }
+    }
+
+    fn status(&self) -> Result<(), Self::Error> {
+        if self.exceeded {
+            Err(BddLimitExceeded { limit: self.limit })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl SystemCapabilities for Bdd {}
+
+impl SystemBitAnd<bool> for Bdd {
+    fn and(&mut self, a: &BddRef, b: &BddRef) -> BddRef {
+        self.apply(BddOp::And, *a, *b)
+    }
+}
+
+impl SystemBitOr<bool> for Bdd {
+    fn or(&mut self, a: &BddRef, b: &BddRef) -> BddRef {
+        self.apply(BddOp::Or, *a, *b)
+    }
+}
+
+impl SystemBitXor<bool> for Bdd {
+    fn xor(&mut self, a: &BddRef, b: &BddRef) -> BddRef {
+        self.apply(BddOp::Xor, *a, *b)
+    }
+}
+
+impl SystemNot<bool> for Bdd {
+    fn not(&mut self, value: &BddRef) -> BddRef {
+        self.apply(BddOp::Xor, *value, BddRef::TRUE)
+    }
+}
+
+impl SystemSelect<bool> for Bdd {
+    fn select(&mut self, cond: &BddRef, a: &BddRef, b: &BddRef) -> BddRef {
+        crate::binary::select_bit(self, cond, a, b)
+    }
+}
+
+#[test]
+fn test_bdd() {
+    let mut bdd = Bdd::new();
+    let vars: Vec<_> = (0..4).map(|_| bdd.var()).collect();
+
+    // Equivalent formulas have the same representation
+    let a = SystemBitXor::<bool>::xor(&mut bdd, &vars[0], &vars[1]);
+    let b = SystemBitOr::<bool>::or(&mut bdd, &vars[0], &vars[1]);
+    let c = SystemBitAnd::<bool>::and(&mut bdd, &vars[0], &vars[1]);
+    let not_c = SystemNot::<bool>::not(&mut bdd, &c);
+    let d = SystemBitAnd::<bool>::and(&mut bdd, &b, &not_c);
+    assert_eq!(a, d);
+    let e = SystemBitXor::<bool>::xor(&mut bdd, &a, &d);
+    assert_eq!(e, BddRef::FALSE);
+
+    // Satisfying assignments are found when they exist
+    let f = SystemBitAnd::<bool>::and(&mut bdd, &a, &vars[3]);
+    let values = bdd.satisfy(f).unwrap();
+    assert!(bdd.eval(f, &values));
+    assert_eq!(values, [false, true, false, true]);
+    assert_eq!(bdd.satisfy(BddRef::FALSE), None);
+    for i in 0..16 {
+        let values: Vec<_> = (0..4).map(|j| (i >> j) & 1 == 1).collect();
+        assert_eq!(bdd.eval(f, &values), (values[0] ^ values[1]) & values[3]);
+    }
+
+    // Exceeding the limit is reported
+    let mut bdd = Bdd::with_limit(6);
+    let vars: Vec<_> = (0..4).map(|_| bdd.var()).collect();
+    assert_eq!(SystemRepr::<bool>::status(&bdd), Ok(()));
+    let a = SystemBitXor::<bool>::xor(&mut bdd, &vars[0], &vars[1]);
+    SystemBitXor::<bool>::xor(&mut bdd, &a, &vars[2]);
+    assert_eq!(
+        SystemRepr::<bool>::status(&bdd),
+        Err(BddLimitExceeded { limit: 6 })
+    );
+}
@@ -0,0 +1,287 @@
+//! Compiles built-in gadgets with the given parameters, prints statistics about them in each
+//! backend, and exports them to the supported formats. For example:
+//!
+//! ```text
+//! circus sha256 --blocks 4 --backend r1cs --export r1cs sha256.r1cs
+//! ```
+//!
+//! Gadgets are captured once as a [`SubcircuitTemplate`] and then lowered to each backend, as in
+//! the [`report`] module. Run `circus --help` for the full set of options.
+use bls12_381::Scalar;
+use circus::crypto::hash::*;
+use circus::graph::{Graph, Node, SubcircuitTemplate};
+use circus::r1cs::StreamingArithmeticSystem;
+use circus::report::{self, Backend, GraphBackend, R1csBackend};
+use circus::*;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+usage: circus <gadget> [options]
+
+gadgets:
+  sha256              SHA-256 compression of unknown message blocks
+  keccak              Keccak-f[1600] permutations of an unknown state
+
+options:
+  --blocks <n>        the number of blocks or permutations (default 1)
+  --backend <name>    graph, r1cs or all (default all)
+  --stats <format>    text, csv or json (default text)
+  --export <format> <path>
+                      writes the gadget as ir, dot or r1cs; may be repeated
+  --help              prints this message
+";
+
+/// A built-in gadget which can be compiled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Gadget {
+    Sha256,
+    Keccak,
+}
+
+/// A format in which statistics can be printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StatsFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+/// A format to which a gadget can be exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    /// The crate's intermediate representation, as written by [`circus::ir::save`].
+    Ir,
+
+    /// The Graphviz DOT language, as produced by [`Graph::to_dot`].
+    Dot,
+
+    /// A constraint stream over the BLS12-381 scalar field, as written by a
+    /// [`StreamingArithmeticSystem`].
+    R1cs,
+}
+
+/// The options given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Options {
+    gadget: Gadget,
+    blocks: usize,
+    graph: bool,
+    r1cs: bool,
+    stats: StatsFormat,
+    exports: Vec<(ExportFormat, String)>,
+}
+
+/// Parses command line arguments, excluding the program name. Returns `Ok(None)` if help was
+/// requested.
+fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut args = args.into_iter();
+    let mut gadget = None;
+    let mut options = Options {
+        gadget: Gadget::Sha256,
+        blocks: 1,
+        graph: true,
+        r1cs: true,
+        stats: StatsFormat::Text,
+        exports: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("missing value for {}", name));
+        match arg.as_str() {
+            "--help" | "-h" => return Ok(None),
+            "--blocks" => {
+                let blocks = value("--blocks")?;
+                options.blocks = match blocks.parse() {
+                    Ok(blocks) if blocks > 0 => blocks,
+                    _ => return Err(format!("invalid number of blocks: {}", blocks)),
+                };
+            }
+            "--backend" => {
+                (options.graph, options.r1cs) = match value("--backend")?.as_str() {
+                    "graph" => (true, false),
+                    "r1cs" => (false, true),
+                    "all" => (true, true),
+                    backend => return Err(format!("unknown backend: {}", backend)),
+                };
+            }
+            "--stats" => {
+                options.stats = match value("--stats")?.as_str() {
+                    "text" => StatsFormat::Text,
+                    "csv" => StatsFormat::Csv,
+                    "json" => StatsFormat::Json,
+                    format => return Err(format!("unknown stats format: {}", format)),
+                };
+            }
+            "--export" => {
+                let format = match value("--export")?.as_str() {
+                    "ir" => ExportFormat::Ir,
+                    "dot" => ExportFormat::Dot,
+                    "r1cs" => ExportFormat::R1cs,
+                    format => return Err(format!("unknown export format: {}", format)),
+                };
+                options.exports.push((format, value("--export")?));
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            _ if gadget.is_some() => return Err(format!("unexpected argument: {}", arg)),
+            "sha256" => gadget = Some(Gadget::Sha256),
+            "keccak" => gadget = Some(Gadget::Keccak),
+            _ => return Err(format!("unknown gadget: {}", arg)),
+        }
+    }
+    options.gadget = gadget.ok_or("no gadget given")?;
+    Ok(Some(options))
+}
+
+/// Captures the given gadget as a template.
+fn capture(gadget: Gadget, blocks: usize) -> SubcircuitTemplate {
+    match gadget {
+        Gadget::Sha256 => SubcircuitTemplate::capture(512 * blocks, |graph, inputs| {
+            emulate(graph, |sys| {
+                let mut hasher = sys.sha256_new();
+                for block in inputs.chunks(512) {
+                    let chunk = words::<32, 16>(block);
+                    sys.sha256_update_abstract(&mut hasher, &chunk);
+                }
+                hasher.concat()
+            })
+        }),
+        Gadget::Keccak => SubcircuitTemplate::capture(1600, |graph, inputs| {
+            emulate(graph, |sys| {
+                let mut state = words::<64, 25>(inputs);
+                for _ in 0..blocks {
+                    sys.keccak_f1600(&mut state);
+                }
+                state.concat()
+            })
+        }),
+    }
+}
+
+/// Runs the given function with a [`BinaryEmulate`] system over the given graph.
+fn emulate(graph: &mut Graph, f: impl FnOnce(&mut BinaryEmulate<Graph>) -> Vec<Node>) -> Vec<Node> {
+    let mut sys = BinaryEmulate::new(std::mem::take(graph));
+    let res = f(&mut sys);
+    *graph = sys.into_source();
+    res
+}
+
+/// Groups input nodes into `N` little-endian words of `B` bits each.
+fn words<const B: usize, const N: usize>(nodes: &[Node]) -> [[Node; B]; N] {
+    array_init::array_init(|i| array_init::array_init(|j| nodes[i * B + j]))
+}
+
+/// Writes the given gadget to a file in the given format.
+fn export(template: &SubcircuitTemplate, format: ExportFormat, path: &str) -> std::io::Result<()> {
+    match format {
+        ExportFormat::Ir => circus::ir::save(path, template),
+        ExportFormat::Dot => fs::write(path, template.graph().to_dot()),
+        ExportFormat::R1cs => {
+            let writer = BufWriter::new(File::create(path)?);
+            let mut sys = StreamingArithmeticSystem::<Scalar, _>::streaming(writer)?;
+            let inputs: Vec<_> = (0..template.num_inputs())
+                .map(|_| sys.declare_bool())
+                .collect();
+            template.instantiate(&mut sys, &inputs);
+            sys.finish()?;
+            Ok(())
+        }
+    }
+}
+
+/// Prints the statistics for a single entry of a [`report::Matrix`] in a readable form.
+fn print_entry(entry: &report::MatrixEntry) {
+    let m = &entry.measurement;
+    let mut size = Vec::new();
+    if let Some(constraints) = m.constraints {
+        size.push(format!("{} constraints", constraints));
+    }
+    if let Some(g) = m.gates {
+        size.push(format!(
+            "{} gates (and {}, or {}, xor {}, not {})",
+            g.total(),
+            g.and,
+            g.or,
+            g.xor,
+            g.not
+        ));
+    }
+    println!(
+        "{} [{}]: {}, synthesis {:?}, witness {:?}",
+        entry.gadget,
+        entry.backend,
+        size.join(", "),
+        m.synth_time,
+        m.witness_time
+    );
+}
+
+fn main() -> ExitCode {
+    let options = match parse(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprint!("error: {}\n\n{}", err, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let template = capture(options.gadget, options.blocks);
+    let name = match options.gadget {
+        Gadget::Sha256 => format!("sha256 x{}", options.blocks),
+        Gadget::Keccak => format!("keccak-f1600 x{}", options.blocks),
+    };
+
+    let r1cs = R1csBackend::<Scalar>::new("r1cs-bls12-381");
+    let mut backends: Vec<&dyn Backend> = Vec::new();
+    if options.graph {
+        backends.push(&GraphBackend);
+    }
+    if options.r1cs {
+        backends.push(&r1cs);
+    }
+    let matrix = report::matrix(&[(&name, &template)], &backends);
+    match options.stats {
+        StatsFormat::Text => matrix.entries.iter().for_each(print_entry),
+        StatsFormat::Csv => print!("{}", matrix.to_csv()),
+        StatsFormat::Json => print!("{}", matrix.to_json()),
+    }
+
+    for (format, path) in options.exports.iter() {
+        if let Err(err) = export(&template, *format, path) {
+            eprintln!("error: failed to write {}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+#[test]
+fn test_parse() {
+    let args = |str: &str| str.split_whitespace().map(String::from).collect::<Vec<_>>();
+    let options = parse(args("sha256 --blocks 4 --backend r1cs --export ir a.ir"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        options,
+        Options {
+            gadget: Gadget::Sha256,
+            blocks: 4,
+            graph: false,
+            r1cs: true,
+            stats: StatsFormat::Text,
+            exports: vec![(ExportFormat::Ir, "a.ir".to_owned())],
+        }
+    );
+    assert_eq!(parse(args("keccak --help")), Ok(None));
+    assert!(parse(args("--blocks 2")).is_err());
+    assert!(parse(args("sha256 --blocks 0")).is_err());
+    assert!(parse(args("sha256 --export r1cs")).is_err());
+    assert!(parse(args("sha256 keccak")).is_err());
+
+    let template = capture(Gadget::Keccak, 2);
+    assert_eq!(template.num_inputs(), 1600);
+    assert_eq!(template.num_outputs(), 1600);
+}
@@ -1,4 +1,6 @@
 use crate::*;
+use alloc::vec;
+use alloc::vec::Vec;
 
 /// A system which allows arbitrary boolean operations.
 pub trait BinarySystem:
@@ -13,58 +15,1366 @@ impl<S: SystemBitAnd<bool> + SystemBitOr<bool> + SystemBitXor<bool> + SystemNot<
 
 /// Augments a [`BinarySystem`] with operations on other types by representing them as strings
 /// of binary values.
-#[repr(transparent)]
-pub struct BinaryEmulate<S>(S);
+pub struct BinaryEmulate<S> {
+    source: S,
+    adder: Adder,
+    multiplier: Multiplier,
+}
+
+/// Identifies a construction for the carry chain of a two-operand binary adder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Adder {
+    /// A ripple-carry adder. This uses the fewest gates, but its depth is linear in the number of
+    /// bits.
+    #[default]
+    RippleCarry,
+
+    /// A Sklansky parallel-prefix adder. This has logarithmic depth, but some intermediate
+    /// carries have high fan-out.
+    Sklansky,
+
+    /// A Kogge-Stone parallel-prefix adder. This has logarithmic depth and bounded fan-out, at the
+    /// cost of more gates than the other constructions.
+    KoggeStone,
+}
+
+/// Identifies a construction for binary multipliers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Multiplier {
+    /// Sums the partial products of each bit of one operand with the other operand.
+    #[default]
+    Schoolbook,
+
+    /// Uses Karatsuba's method to decompose full-width products into three half-width products,
+    /// recursively, until the operands are at most `threshold` bits wide. This only applies to
+    /// full-width products, since wrapping products don't benefit from it.
+    Karatsuba { threshold: usize },
+}
 
 impl<S: BinarySystem> BinaryEmulate<S> {
     /// Constructs a new [`BinaryEmulate`] wrapper over the given [`BinarySystem`].
     pub fn new(source: S) -> Self {
-        Self(source)
+        Self {
+            source,
+            adder: Adder::default(),
+            multiplier: Multiplier::default(),
+        }
+    }
+
+    /// Gets the underlying [`BinarySystem`] for this system.
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Gets the underlying [`BinarySystem`] for this system, mutably. This can be used to
+    /// introduce new variables into it.
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// Gets the underlying [`BinarySystem`] for this system, discarding this wrapper.
+    pub fn into_source(self) -> S {
+        self.source
+    }
+
+    /// Sets the [`Adder`] construction used by this system.
+    pub fn with_adder(mut self, adder: Adder) -> Self {
+        self.adder = adder;
+        self
     }
+
+    /// Sets the [`Multiplier`] construction used by this system.
+    pub fn with_multiplier(mut self, multiplier: Multiplier) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Constructs the `N` least significant bits of the given value, least significant first.
+    /// This uses the same decomposition as [`SystemBits`] does for [`Eval`], so that constants
+    /// can always be round-tripped.
+    fn constant_bits<const N: usize>(&mut self, value: u64) -> [Abstract<S, bool>; N] {
+        crate::system::bits_of(value).map(|bit| self.source.constant(bit))
+    }
+
+    /// Applies a binary operation to each corresponding pair of bits in `a` and `b`.
+    fn zip_bits<const N: usize>(
+        &mut self,
+        a: &[Abstract<S, bool>; N],
+        b: &[Abstract<S, bool>; N],
+        mut op: impl FnMut(&mut S, &Abstract<S, bool>, &Abstract<S, bool>) -> Abstract<S, bool>,
+    ) -> [Abstract<S, bool>; N] {
+        array_init::array_init(|i| op(&mut self.source, &a[i], &b[i]))
+    }
+
+    /// Adds two little-endian bit strings of the same length, discarding the final carry.
+    fn add_bits(
+        &mut self,
+        a: &[Abstract<S, bool>],
+        b: &[Abstract<S, bool>],
+    ) -> Vec<Abstract<S, bool>> {
+        assert_eq!(a.len(), b.len());
+        let p: Vec<_> = (0..a.len())
+            .map(|i| self.source.xor(&a[i], &b[i]))
+            .collect();
+        let carries = match self.adder {
+            Adder::RippleCarry => self.ripple_carries(a, b, &p),
+            Adder::Sklansky => self.prefix_carries(a, b, &p, |i, span| {
+                (i & span != 0).then(|| (i & !(span - 1)) - 1)
+            }),
+            Adder::KoggeStone => self.prefix_carries(a, b, &p, |i, span| i.checked_sub(span)),
+        };
+        (0..p.len())
+            .map(|i| match i {
+                0 => p[0].clone(),
+                i => self.source.xor(&p[i], &carries[i - 1]),
+            })
+            .collect()
+    }
+
+    /// Computes the carries into each bit (other than the first) of the sum of two little-endian
+    /// bit strings by propagating them sequentially. `p` is the bitwise XOR of `a` and `b`.
+    fn ripple_carries(
+        &mut self,
+        a: &[Abstract<S, bool>],
+        b: &[Abstract<S, bool>],
+        p: &[Abstract<S, bool>],
+    ) -> Vec<Abstract<S, bool>> {
+        let mut carries: Vec<Abstract<S, bool>> = Vec::with_capacity(p.len());
+        for i in 0..(p.len().saturating_sub(1)) {
+            let carry = match carries.last() {
+                None => self.source.and(&a[i], &b[i]),
+                Some(c) => self.majority(&a[i], &p[i], c),
+            };
+            carries.push(carry);
+        }
+        carries
+    }
+
+    /// Computes the carries into each bit (other than the first) of the sum of two little-endian
+    /// bit strings using a parallel-prefix network. `p` is the bitwise XOR of `a` and `b`. At
+    /// each level of the network, with power-of-two `span`, `pred(i, span)` identifies the index
+    /// of the group that the group ending at `i` should be combined with, if any.
+    fn prefix_carries(
+        &mut self,
+        a: &[Abstract<S, bool>],
+        b: &[Abstract<S, bool>],
+        p: &[Abstract<S, bool>],
+        pred: impl Fn(usize, usize) -> Option<usize>,
+    ) -> Vec<Abstract<S, bool>> {
+        let len = p.len().saturating_sub(1);
+        let mut g: Vec<_> = (0..len).map(|i| self.source.and(&a[i], &b[i])).collect();
+        let mut p: Vec<_> = p[..len].to_vec();
+        let mut span = 1;
+        while span < len {
+            // Iterate downwards so that the groups being combined with are from the previous
+            // level
+            for i in (0..len).rev() {
+                if let Some(j) = pred(i, span) {
+                    let t = self.source.and(&p[i], &g[j]);
+                    g[i] = self.source.xor(&g[i], &t);
+
+                    // Groups that start at the first bit will never be extended further
+                    if i >= 2 * span {
+                        p[i] = self.source.and(&p[i], &p[j]);
+                    }
+                }
+            }
+            span *= 2;
+        }
+        g
+    }
+
+    /// Computes the majority of `a`, `b` and `c`, given `t = a ^ b`, using a single AND gate.
+    fn majority(
+        &mut self,
+        a: &Abstract<S, bool>,
+        t: &Abstract<S, bool>,
+        c: &Abstract<S, bool>,
+    ) -> Abstract<S, bool> {
+        let u = self.source.xor(a, c);
+        let u = self.source.and(t, &u);
+        self.source.xor(a, &u)
+    }
+
+    /// Reduces three little-endian bit strings of the same length to two with the same wrapping
+    /// sum, without propagating carries.
+    fn carry_save_bits(
+        &mut self,
+        a: &[Abstract<S, bool>],
+        b: &[Abstract<S, bool>],
+        c: &[Abstract<S, bool>],
+    ) -> [Vec<Abstract<S, bool>>; 2] {
+        let t: Vec<_> = (0..a.len())
+            .map(|i| self.source.xor(&a[i], &b[i]))
+            .collect();
+        let sum = (0..a.len())
+            .map(|i| self.source.xor(&t[i], &c[i]))
+            .collect();
+        let carry = (0..a.len())
+            .map(|i| match i {
+                0 => self.source.constant(false),
+                i => self.majority(&a[i - 1], &t[i - 1], &c[i - 1]),
+            })
+            .collect();
+        [sum, carry]
+    }
+
+    /// Adds together any non-zero number of little-endian bit strings of the same length,
+    /// discarding carries beyond the most significant bit. This reduces the operands with
+    /// carry-save adders until only two remain, so that only one carry chain needs to be
+    /// constructed.
+    fn sum_many_bits(&mut self, mut terms: Vec<Vec<Abstract<S, bool>>>) -> Vec<Abstract<S, bool>> {
+        while terms.len() > 2 {
+            let mut next = Vec::with_capacity(terms.len());
+            for chunk in terms.chunks(3) {
+                match chunk {
+                    [a, b, c] => next.extend(self.carry_save_bits(a, b, c)),
+                    rem => next.extend_from_slice(rem),
+                }
+            }
+            terms = next;
+        }
+        match &terms[..] {
+            [a] => a.clone(),
+            [a, b] => self.add_bits(a, b),
+            _ => panic!("sum of no terms"),
+        }
+    }
+
+    /// Computes the `len` least significant bits of the product of two little-endian bit
+    /// strings, plus the given additional terms, by summing partial products.
+    fn schoolbook_mul_bits(
+        &mut self,
+        a: &[Abstract<S, bool>],
+        b: &[Abstract<S, bool>],
+        len: usize,
+        mut terms: Vec<Vec<Abstract<S, bool>>>,
+    ) -> Vec<Abstract<S, bool>> {
+        for (j, b_j) in b.iter().enumerate().take(len) {
+            terms.push(
+                (0..len)
+                    .map(|i| match i.checked_sub(j).and_then(|k| a.get(k)) {
+                        Some(a_k) => self.source.and(a_k, b_j),
+                        None => self.source.constant(false),
+                    })
+                    .collect(),
+            );
+        }
+        self.sum_many_bits(terms)
+    }
+
+    /// Computes the full product of two little-endian bit strings of the same length, shifted
+    /// right by `shift` bits and truncated to the original length. The bit strings are
+    /// interpreted as two's complement integers if `signed` is set.
+    fn mul_shr_bits(
+        &mut self,
+        a: &[Abstract<S, bool>],
+        b: &[Abstract<S, bool>],
+        shift: usize,
+        signed: bool,
+    ) -> Vec<Abstract<S, bool>> {
+        let len = a.len();
+        let extend = |sys: &mut Self, x: &[Abstract<S, bool>]| {
+            let fill = if signed {
+                x[len - 1].clone()
+            } else {
+                sys.source.constant(false)
+            };
+            let mut x = x.to_vec();
+            x.resize(2 * len, fill);
+            x
+        };
+        let a = extend(self, a);
+        let b = extend(self, b);
+
+        // Bits beyond those that are shifted into the result don't need to be computed
+        let prod_len = (shift + len).min(2 * len);
+        let prod = self.schoolbook_mul_bits(&a, &b, prod_len, Vec::new());
+
+        // Bits shifted in from beyond the full product are its sign, or zero if unsigned
+        let fill = if signed {
+            prod[prod_len - 1].clone()
+        } else {
+            self.source.constant(false)
+        };
+        (0..len)
+            .map(|i| prod.get(i + shift).unwrap_or(&fill).clone())
+            .collect()
+    }
+
+    /// Computes the full product of two little-endian bit strings, plus the given additional
+    /// terms, using the configured [`Multiplier`]. The result has as many bits as `a` and `b`
+    /// combined.
+    fn mul_full_bits(
+        &mut self,
+        a: &[Abstract<S, bool>],
+        b: &[Abstract<S, bool>],
+        mut terms: Vec<Vec<Abstract<S, bool>>>,
+    ) -> Vec<Abstract<S, bool>> {
+        let len = a.len() + b.len();
+        let threshold = match self.multiplier {
+            Multiplier::Karatsuba { threshold } => threshold,
+            Multiplier::Schoolbook => usize::MAX,
+        };
+        // Note that operands with fewer than four bits can't be made any smaller by splitting
+        if a.len() != b.len() || a.len() <= threshold.max(3) {
+            return self.schoolbook_mul_bits(a, b, len, terms);
+        }
+
+        // Split each operand into high and low halves, so that the product is
+        // `z_2 * 2^(2 * h) + z_1 * 2^h + z_0` where `z_1 = (a_0 + a_1) * (b_0 + b_1) - z_0 - z_2`.
+        let h = a.len() / 2;
+        let (a_0, a_1) = a.split_at(h);
+        let (b_0, b_1) = b.split_at(h);
+        let z_0 = self.mul_full_bits(a_0, b_0, Vec::new());
+        let z_2 = self.mul_full_bits(a_1, b_1, Vec::new());
+        let a_s = self.add_bits_full(a_0, a_1);
+        let b_s = self.add_bits_full(b_0, b_1);
+        let z_s = self.mul_full_bits(&a_s, &b_s, Vec::new());
+
+        // Subtraction is performed by adding the two's complement, which requires adding 1 for
+        // each subtracted term
+        let shifted = |sys: &mut Self, z: &[Abstract<S, bool>], shift: usize, negate: bool| {
+            (0..len)
+                .map(|i| match i.checked_sub(shift).and_then(|k| z.get(k)) {
+                    Some(bit) if negate => sys.source.not(bit),
+                    Some(bit) => bit.clone(),
+                    None => sys.source.constant(negate),
+                })
+                .collect::<Vec<_>>()
+        };
+        terms.push(shifted(self, &z_0, 0, false));
+        terms.push(shifted(self, &z_2, 2 * h, false));
+        terms.push(shifted(self, &z_s, h, false));
+        terms.push(shifted(self, &z_0, h, true));
+        terms.push(shifted(self, &z_2, h, true));
+        terms.push((0..len).map(|i| self.source.constant(i == 1)).collect());
+        self.sum_many_bits(terms)
+    }
+
+    /// Adds two little-endian bit strings, of lengths differing by at most one, producing a
+    /// result long enough to hold the final carry.
+    fn add_bits_full(
+        &mut self,
+        a: &[Abstract<S, bool>],
+        b: &[Abstract<S, bool>],
+    ) -> Vec<Abstract<S, bool>> {
+        let len = a.len().max(b.len()) + 1;
+        let extend = |sys: &mut Self, x: &[Abstract<S, bool>]| {
+            let mut x = x.to_vec();
+            x.resize_with(len, || sys.source.constant(false));
+            x
+        };
+        let a = extend(self, a);
+        let b = extend(self, b);
+        self.add_bits(&a, &b)
+    }
+
+    /// Shifts a little-endian bit string towards its most significant end, filling with zeros.
+    fn shl_bits<const N: usize>(
+        &mut self,
+        a: &[Abstract<S, bool>; N],
+        b: u8,
+    ) -> [Abstract<S, bool>; N] {
+        let b = b as usize;
+        array_init::array_init(|i| {
+            if i >= b {
+                a[i - b].clone()
+            } else {
+                self.source.constant(false)
+            }
+        })
+    }
+
+    /// Shifts a little-endian bit string towards its least significant end. If `arithmetic` is
+    /// set, vacated bits are filled with the most significant bit of `a`, rather than zero.
+    fn shr_bits<const N: usize>(
+        &mut self,
+        a: &[Abstract<S, bool>; N],
+        b: u8,
+        arithmetic: bool,
+    ) -> [Abstract<S, bool>; N] {
+        let b = b as usize;
+        array_init::array_init(|i| {
+            if i + b < N {
+                a[i + b].clone()
+            } else if arithmetic {
+                a[N - 1].clone()
+            } else {
+                self.source.constant(false)
+            }
+        })
+    }
+
+    /// Determines whether the integer represented by little-endian bit string `a` is less than
+    /// that represented by `b`, interpreting them as two's complement integers if `signed` is
+    /// set. This is the final borrow when subtracting `b` from `a`.
+    fn lt_bits(
+        &mut self,
+        a: &[Abstract<S, bool>],
+        b: &[Abstract<S, bool>],
+        signed: bool,
+    ) -> Abstract<S, bool> {
+        let mut borrow = self.source.constant(false);
+        for i in 0..a.len() {
+            // For the sign bit, the roles of the operands are reversed
+            let (x, y) = if signed && i + 1 == a.len() {
+                (a[i].clone(), self.source.not(&b[i]))
+            } else {
+                (self.source.not(&a[i]), b[i].clone())
+            };
+            borrow = match i {
+                0 => self.source.and(&x, &y),
+                _ => {
+                    let t = self.source.xor(&x, &y);
+                    self.majority(&x, &t, &borrow)
+                }
+            };
+        }
+        borrow
+    }
+
+    /// Divides one little-endian bit string by another of the same length using restoring long
+    /// division, returning the quotient and remainder. If the divisor is zero, the quotient is
+    /// all ones and the remainder is the dividend.
+    fn div_rem_bits<const N: usize>(
+        &mut self,
+        a: &[Abstract<S, bool>; N],
+        b: &[Abstract<S, bool>; N],
+    ) -> [[Abstract<S, bool>; N]; 2] {
+        let zero = self.source.constant(false);
+        let mut quot = array_init::array_init(|_| zero.clone());
+        let mut rem: [Abstract<S, bool>; N] = array_init::array_init(|_| zero.clone());
+        for i in (0..N).rev() {
+            // Bring down the next bit of the dividend, then subtract the divisor if possible. The
+            // shifted remainder may have one more bit than the divisor.
+            let mut shifted = vec![a[i].clone()];
+            shifted.extend_from_slice(&rem);
+            let mut diff = Vec::with_capacity(N);
+            let mut borrow = self.source.constant(false);
+            for (j, x) in shifted.iter().enumerate() {
+                let not_x = self.source.not(x);
+                if j == N {
+                    borrow = self.source.and(&not_x, &borrow);
+                    break;
+                }
+                let t = self.source.xor(&not_x, &b[j]);
+                let xy = self.source.not(&t);
+                diff.push(self.source.xor(&xy, &borrow));
+                borrow = self.majority(&not_x, &t, &borrow);
+            }
+            quot[i] = self.source.not(&borrow);
+            rem = array_init::array_init(|j| {
+                select_bit(&mut self.source, &quot[i], &diff[j], &shifted[j])
+            });
+        }
+        [quot, rem]
+    }
+}
+
+/// Selects `a` if `cond` is true, or `b` otherwise, as `b ^ (cond & (a ^ b))`.
+pub(crate) fn select_bit<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    cond: &Abstract<S, bool>,
+    a: &Abstract<S, bool>,
+    b: &Abstract<S, bool>,
+) -> Abstract<S, bool> {
+    let diff = sys.xor(a, b);
+    let offset = sys.and(cond, &diff);
+    sys.xor(b, &offset)
+}
+
+/// Adds a constant to a word, with wrapping.
+///
+/// This and the comparisons below are shared by the byte-oriented gadgets, so they go unused
+/// when none of those features are enabled.
+#[allow(dead_code)]
+pub(crate) fn add_const<S: SystemWrappingAdd<u32> + ?Sized>(
+    sys: &mut S,
+    value: &Abstract<S, u32>,
+    offset: u32,
+) -> Abstract<S, u32> {
+    let offset = SystemRepr::<u32>::constant(sys, offset);
+    SystemWrappingAdd::<u32>::wrapping_add(sys, value, &offset)
+}
+
+/// Determines whether two little-endian strings of bits are equal.
+#[allow(dead_code)]
+pub(crate) fn eq_bits<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    a: &[Abstract<S, bool>],
+    b: &[Abstract<S, bool>],
+) -> Abstract<S, bool> {
+    let mut res = SystemRepr::<bool>::constant(sys, true);
+    for (a, b) in a.iter().zip(b) {
+        let diff = SystemBitXor::<bool>::xor(sys, a, b);
+        let same = SystemNot::<bool>::not(sys, &diff);
+        res = SystemBitAnd::<bool>::and(sys, &res, &same);
+    }
+    res
+}
+
+/// Determines whether a little-endian string of bits is equal to the given constant.
+#[allow(dead_code)]
+pub(crate) fn eq_bits_const<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    bits: &[Abstract<S, bool>],
+    value: u64,
+) -> Abstract<S, bool> {
+    let mut res = SystemRepr::<bool>::constant(sys, true);
+    for (i, bit) in bits.iter().enumerate() {
+        let eq = match value.checked_shr(i as u32).unwrap_or(0) & 1 {
+            1 => bit.clone(),
+            _ => SystemNot::<bool>::not(sys, bit),
+        };
+        res = SystemBitAnd::<bool>::and(sys, &res, &eq);
+    }
+    res
+}
+
+/// Determines whether two bytes are equal.
+#[allow(dead_code)]
+pub(crate) fn eq_u8<S: BinarySystem + SystemBits + ?Sized>(
+    sys: &mut S,
+    a: &Abstract<S, u8>,
+    b: &Abstract<S, u8>,
+) -> Abstract<S, bool> {
+    let a = sys.bits_of_u8(a);
+    let b = sys.bits_of_u8(b);
+    eq_bits(sys, &a, &b)
+}
+
+/// Determines whether two words are equal.
+#[allow(dead_code)]
+pub(crate) fn eq_u32<S: BinarySystem + SystemBits + ?Sized>(
+    sys: &mut S,
+    a: &Abstract<S, u32>,
+    b: &Abstract<S, u32>,
+) -> Abstract<S, bool> {
+    let a = sys.bits_of_u32(a);
+    let b = sys.bits_of_u32(b);
+    eq_bits(sys, &a, &b)
+}
+
+/// Determines whether a byte is equal to a constant.
+#[allow(dead_code)]
+pub(crate) fn eq_u8_const<S: BinarySystem + SystemBits + ?Sized>(
+    sys: &mut S,
+    a: &Abstract<S, u8>,
+    b: u8,
+) -> Abstract<S, bool> {
+    let a = sys.bits_of_u8(a);
+    eq_bits_const(sys, &a, u64::from(b))
+}
+
+/// Determines whether a word is equal to a constant.
+#[allow(dead_code)]
+pub(crate) fn eq_u32_const<S: BinarySystem + SystemBits + ?Sized>(
+    sys: &mut S,
+    a: &Abstract<S, u32>,
+    b: u32,
+) -> Abstract<S, bool> {
+    let a = sys.bits_of_u32(a);
+    eq_bits_const(sys, &a, u64::from(b))
+}
+
+/// Decodes a little-endian index into a one-hot vector of `len` bits, where only the bit at the
+/// given index is set. If the index is at least `len`, no bits are set. See [`assert_one_hot`]
+/// to enforce that exactly one bit is set.
+pub fn one_hot<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    index: &[Abstract<S, bool>],
+    len: usize,
+) -> Vec<Abstract<S, bool>> {
+    let mut sel = vec![sys.constant(true)];
+    for (i, bit) in index.iter().enumerate() {
+        let not_bit = sys.not(bit);
+        sel = match 1usize.checked_shl(i as u32).filter(|size| *size < len) {
+            Some(size) => (0..(2 * size).min(len))
+                .map(|j| {
+                    let bit = if j & size == 0 { &not_bit } else { bit };
+                    sys.and(&sel[j % size], bit)
+                })
+                .collect(),
+
+            // Higher bits must be zero for the index to be in range
+            None => sel.iter().map(|sel| sys.and(sel, &not_bit)).collect(),
+        };
+    }
+    sel.resize(len, sys.constant(false));
+    sel
+}
+
+/// Determines whether exactly one of the given bits is set. The construction is chosen according
+/// to [`SystemCapabilities::FREE_XOR`].
+pub fn is_one_hot<S: BinarySystem + SystemCapabilities + ?Sized>(
+    sys: &mut S,
+    bits: &[Abstract<S, bool>],
+) -> Abstract<S, bool> {
+    is_one_hot_with(sys, bits, S::FREE_XOR)
+}
+
+/// Implements [`is_one_hot`]. If `free_xor` is set, the running disjunction of the bits reuses
+/// the AND that detects duplicates, since `a | b = a ^ b ^ (a & b)`. This saves a non-linear
+/// operation per bit, but costs an extra gate when XOR isn't free.
+fn is_one_hot_with<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    bits: &[Abstract<S, bool>],
+    free_xor: bool,
+) -> Abstract<S, bool> {
+    let mut any = sys.constant(false);
+    let mut many = sys.constant(false);
+    for bit in bits {
+        let dup = sys.and(&any, bit);
+        many = sys.or(&many, &dup);
+        any = if free_xor {
+            let sum = sys.xor(&any, bit);
+            sys.xor(&sum, &dup)
+        } else {
+            sys.or(&any, bit)
+        };
+    }
+    let single = sys.not(&many);
+    sys.and(&any, &single)
+}
+
+/// Asserts that exactly one of the given bits is set.
+pub fn assert_one_hot<S: BinarySystem + SystemCapabilities + SystemAssert + ?Sized>(
+    sys: &mut S,
+    bits: &[Abstract<S, bool>],
+) {
+    let valid = is_one_hot(sys, bits);
+    sys.assert(&valid)
+}
+
+/// Decodes a one-hot vector into the little-endian index of its set bit, using `width` bits. If
+/// the vector is not one-hot, the result is the bitwise OR of the indices of the set bits. This
+/// is the inverse of [`one_hot`].
+pub fn decode_one_hot<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    bits: &[Abstract<S, bool>],
+    width: usize,
+) -> Vec<Abstract<S, bool>> {
+    (0..width)
+        .map(|i| {
+            let mut res = sys.constant(false);
+            for (j, bit) in bits.iter().enumerate() {
+                if (j >> i) & 1 == 1 {
+                    res = sys.or(&res, bit);
+                }
+            }
+            res
+        })
+        .collect()
+}
+
+/// Converts a bit string produced by one of the variable-length helpers into an array.
+fn into_array<T, const N: usize>(bits: Vec<T>) -> [T; N] {
+    match bits.try_into() {
+        Ok(array) => array,
+        Err(_) => unreachable!(),
+    }
+}
+
+/// Rotates a little-endian bit string towards its most significant end.
+fn rotl_bits<T: Clone, const N: usize>(a: &[T; N], b: u8) -> [T; N] {
+    let b = b as usize % N;
+    array_init::array_init(|i| a[(i + N - b) % N].clone())
+}
+
+/// Rotates a little-endian bit string towards its least significant end.
+fn rotr_bits<T: Clone, const N: usize>(a: &[T; N], b: u8) -> [T; N] {
+    let b = b as usize % N;
+    array_init::array_init(|i| a[(i + b) % N].clone())
+}
+
+/// Bits are stored directly in the source system, so boolean operations cost the same as they do
+/// there. Integers are strings of bits rather than field elements, even if the source system
+/// works over a field.
+impl<S: BinarySystem + SystemCapabilities> SystemCapabilities for BinaryEmulate<S> {
+    const HAS_LOOKUP: bool = S::HAS_LOOKUP;
+    const FREE_XOR: bool = S::FREE_XOR;
 }
 
 impl<S: BinarySystem> SystemRepr<bool> for BinaryEmulate<S> {
     type Abstract = Abstract<S, bool>;
+    type Error = SystemError<S, bool>;
     fn constant(&mut self, value: bool) -> Self::Abstract {
-        self.0.constant(value)
+        self.source.constant(value)
+    }
+
+    fn status(&self) -> Result<(), Self::Error> {
+        SystemRepr::<bool>::status(&self.source)
     }
 }
 
-impl<S: BinarySystem> SystemRepr<u32> for BinaryEmulate<S> {
-    type Abstract = [Abstract<S, bool>; 32];
-    fn constant(&mut self, value: u32) -> Self::Abstract {
-        array_init::array_init(|i| self.constant(value >> i != 0))
+impl<S: BinarySystem + SystemRead<bool>> SystemRead<bool> for BinaryEmulate<S> {
+    fn read_value(&self, value: &Abstract<Self, bool>) -> bool {
+        self.source.read_value(value)
     }
 }
 
 impl<S: BinarySystem> SystemBitAnd<bool> for BinaryEmulate<S> {
     fn and(&mut self, a: &Abstract<Self, bool>, b: &Abstract<Self, bool>) -> Abstract<Self, bool> {
-        self.0.and(a, b)
+        self.source.and(a, b)
+    }
+}
+
+impl<S: BinarySystem> SystemBitOr<bool> for BinaryEmulate<S> {
+    fn or(&mut self, a: &Abstract<Self, bool>, b: &Abstract<Self, bool>) -> Abstract<Self, bool> {
+        self.source.or(a, b)
     }
 }
 
 impl<S: BinarySystem> SystemBitXor<bool> for BinaryEmulate<S> {
     fn xor(&mut self, a: &Abstract<Self, bool>, b: &Abstract<Self, bool>) -> Abstract<Self, bool> {
-        self.0.xor(a, b)
+        self.source.xor(a, b)
+    }
+}
+
+impl<S: BinarySystem> SystemNot<bool> for BinaryEmulate<S> {
+    fn not(&mut self, value: &Abstract<Self, bool>) -> Abstract<Self, bool> {
+        self.source.not(value)
+    }
+}
+
+impl<S: BinarySystem> SystemSelect<bool> for BinaryEmulate<S> {
+    fn select(
+        &mut self,
+        cond: &Abstract<Self, bool>,
+        a: &Abstract<Self, bool>,
+        b: &Abstract<Self, bool>,
+    ) -> Abstract<Self, bool> {
+        select_bit(&mut self.source, cond, a, b)
     }
 }
 
-impl<S: BinarySystem> SystemWrappingAdd<u32> for BinaryEmulate<S> {
-    fn wrapping_add(
+impl<S: BinarySystem + SystemAssert> SystemAssert for BinaryEmulate<S> {
+    fn assert(&mut self, value: &Abstract<Self, bool>) {
+        self.source.assert(value)
+    }
+
+    fn assert_all(&mut self, values: &[Abstract<Self, bool>]) {
+        self.source.assert_all(values)
+    }
+}
+
+impl<S: BinarySystem + SystemDebug<bool>> SystemDebug<bool> for BinaryEmulate<S> {
+    fn probe(&mut self, label: &str, value: &Abstract<Self, bool>) {
+        self.source.probe(label, value)
+    }
+}
+
+impl<S: BinarySystem + SystemAssertIf> SystemAssertIf for BinaryEmulate<S> {
+    fn assert_if(&mut self, cond: &Abstract<Self, bool>, claim: &Abstract<Self, bool>) {
+        self.source.assert_if(cond, claim)
+    }
+}
+
+/// Implements the standard operation set for an integer type which is represented as a
+/// little-endian string of `$bits` binary values, using two's complement if `$signed` is set.
+macro_rules! impl_int {
+    ($t:ty, $bits:literal, $signed:literal) => {
+        impl<S: BinarySystem> SystemRepr<$t> for BinaryEmulate<S> {
+            type Abstract = [Abstract<S, bool>; $bits];
+            type Error = SystemError<S, bool>;
+            fn constant(&mut self, value: $t) -> Self::Abstract {
+                self.constant_bits(value as u64)
+            }
+
+            fn status(&self) -> Result<(), Self::Error> {
+                SystemRepr::<bool>::status(&self.source)
+            }
+        }
+
+        impl<S: BinarySystem + SystemRead<bool>> SystemRead<$t> for BinaryEmulate<S> {
+            fn read_value(&self, value: &Abstract<Self, $t>) -> $t {
+                let bits = value.iter().enumerate();
+                let res = bits.fold(0u64, |acc, (i, bit)| {
+                    acc | (self.source.read_value(bit) as u64) << i
+                });
+                res as $t
+            }
+        }
+
+        impl<S> SystemDebug<$t> for BinaryEmulate<S>
+        where
+            S: BinarySystem
+                + SystemDebug<[bool; $bits]>
+                + SystemRepr<[bool; $bits], Abstract = [Abstract<S, bool>; $bits]>,
+        {
+            fn probe(&mut self, label: &str, value: &Abstract<Self, $t>) {
+                self.source.probe(label, value)
+            }
+        }
+
+        impl<S: BinarySystem> SystemWrappingAdd<$t> for BinaryEmulate<S> {
+            fn wrapping_add(
+                &mut self,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+            ) -> Abstract<Self, $t> {
+                into_array(self.add_bits(a, b))
+            }
+
+            fn sum_many(&mut self, terms: &[Abstract<Self, $t>]) -> Abstract<Self, $t> {
+                into_array(self.sum_many_bits(terms.iter().map(|t| t.to_vec()).collect()))
+            }
+        }
+
+        impl<S: BinarySystem> SystemWrappingMul<$t> for BinaryEmulate<S> {
+            fn wrapping_mul(
+                &mut self,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+            ) -> Abstract<Self, $t> {
+                into_array(self.schoolbook_mul_bits(a, b, $bits, Vec::new()))
+            }
+        }
+
+        impl<S: BinarySystem> SystemMulShr<$t> for BinaryEmulate<S> {
+            fn mul_shr(
+                &mut self,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+                shift: u8,
+            ) -> Abstract<Self, $t> {
+                into_array(self.mul_shr_bits(a, b, shift as usize, $signed))
+            }
+        }
+
+        impl<S: BinarySystem> SystemBitAnd<$t> for BinaryEmulate<S> {
+            fn and(
+                &mut self,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+            ) -> Abstract<Self, $t> {
+                self.zip_bits(a, b, S::and)
+            }
+        }
+
+        impl<S: BinarySystem> SystemBitOr<$t> for BinaryEmulate<S> {
+            fn or(&mut self, a: &Abstract<Self, $t>, b: &Abstract<Self, $t>) -> Abstract<Self, $t> {
+                self.zip_bits(a, b, S::or)
+            }
+        }
+
+        impl<S: BinarySystem> SystemBitXor<$t> for BinaryEmulate<S> {
+            fn xor(
+                &mut self,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+            ) -> Abstract<Self, $t> {
+                self.zip_bits(a, b, S::xor)
+            }
+        }
+
+        impl<S: BinarySystem> SystemSelect<$t> for BinaryEmulate<S> {
+            fn select(
+                &mut self,
+                cond: &Abstract<Self, bool>,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+            ) -> Abstract<Self, $t> {
+                array_init::array_init(|i| select_bit(&mut self.source, cond, &a[i], &b[i]))
+            }
+        }
+
+        impl<S: BinarySystem> SystemBitSelect<$t> for BinaryEmulate<S> {
+            fn bit_select(
+                &mut self,
+                mask: &Abstract<Self, $t>,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+            ) -> Abstract<Self, $t> {
+                array_init::array_init(|i| select_bit(&mut self.source, &mask[i], &a[i], &b[i]))
+            }
+        }
+
+        impl<S: BinarySystem> SystemNot<$t> for BinaryEmulate<S> {
+            fn not(&mut self, value: &Abstract<Self, $t>) -> Abstract<Self, $t> {
+                array_init::array_init(|i| self.source.not(&value[i]))
+            }
+        }
+
+        impl<S: BinarySystem> SystemBitShift<$t, u8> for BinaryEmulate<S> {
+            fn shl(&mut self, a: &Abstract<Self, $t>, b: u8) -> Abstract<Self, $t> {
+                self.shl_bits(a, b)
+            }
+
+            fn shr(&mut self, a: &Abstract<Self, $t>, b: u8) -> Abstract<Self, $t> {
+                self.shr_bits(a, b, $signed)
+            }
+        }
+
+        impl<S: BinarySystem> SystemBitRotate<$t, u8> for BinaryEmulate<S> {
+            fn rotl(&mut self, a: &Abstract<Self, $t>, b: u8) -> Abstract<Self, $t> {
+                rotl_bits(a, b)
+            }
+
+            fn rotr(&mut self, a: &Abstract<Self, $t>, b: u8) -> Abstract<Self, $t> {
+                rotr_bits(a, b)
+            }
+        }
+
+        impl<S: BinarySystem> SystemOrd<$t> for BinaryEmulate<S> {
+            fn lt(&mut self, a: &Abstract<Self, $t>, b: &Abstract<Self, $t>) -> Abstract<S, bool> {
+                self.lt_bits(a, b, $signed)
+            }
+
+            fn le(&mut self, a: &Abstract<Self, $t>, b: &Abstract<Self, $t>) -> Abstract<S, bool> {
+                let gt = self.lt_bits(b, a, $signed);
+                self.source.not(&gt)
+            }
+        }
+    };
+}
+
+impl_int!(u8, 8, false);
+impl_int!(u32, 32, false);
+impl_int!(u64, 64, false);
+impl_int!(i8, 8, true);
+impl_int!(i16, 16, true);
+impl_int!(i32, 32, true);
+impl_int!(i64, 64, true);
+
+impl<S: BinarySystem> SystemMulFull<u32, u64> for BinaryEmulate<S> {
+    fn mul_full(
+        &mut self,
+        a: &Abstract<Self, u32>,
+        b: &Abstract<Self, u32>,
+    ) -> Abstract<Self, u64> {
+        into_array(self.mul_full_bits(a, b, Vec::new()))
+    }
+
+    fn mul_add_full(
         &mut self,
         a: &Abstract<Self, u32>,
         b: &Abstract<Self, u32>,
-    ) -> Abstract<Self, u32> {
-        todo!()
+        c: &Abstract<Self, u32>,
+    ) -> Abstract<Self, u64> {
+        let mut c = c.to_vec();
+        c.resize_with(64, || self.source.constant(false));
+        into_array(self.mul_full_bits(a, b, vec![c]))
+    }
+}
+
+/// Implements [`SystemDivRem`] for an unsigned integer type, asserting that the divisor is
+/// nonzero.
+macro_rules! impl_div_rem {
+    ($t:ty) => {
+        impl<S: BinarySystem + SystemAssert> SystemDivRem<$t> for BinaryEmulate<S> {
+            fn div_rem(
+                &mut self,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+            ) -> (Abstract<Self, $t>, Abstract<Self, $t>) {
+                let nonzero =
+                    (b.iter().skip(1)).fold(b[0].clone(), |acc, bit| self.source.or(&acc, bit));
+                self.source.assert(&nonzero);
+                let [quot, rem] = self.div_rem_bits(a, b);
+                (quot, rem)
+            }
+        }
+    };
+}
+
+impl_div_rem!(u8);
+impl_div_rem!(u32);
+impl_div_rem!(u64);
+
+impl<S: BinarySystem> SystemBits for BinaryEmulate<S> {
+    fn bits_of_u8(&mut self, value: &Abstract<Self, u8>) -> Abstract<Self, [bool; 8]> {
+        value.clone()
+    }
+
+    fn u8_of_bits(&mut self, bits: &Abstract<Self, [bool; 8]>) -> Abstract<Self, u8> {
+        bits.clone()
+    }
+
+    fn bits_of_u32(&mut self, value: &Abstract<Self, u32>) -> Abstract<Self, [bool; 32]> {
+        value.clone()
+    }
+
+    fn u32_of_bits(&mut self, bits: &Abstract<Self, [bool; 32]>) -> Abstract<Self, u32> {
+        bits.clone()
+    }
+
+    fn bits_of_u64(&mut self, value: &Abstract<Self, u64>) -> Abstract<Self, [bool; 64]> {
+        value.clone()
+    }
+
+    fn u64_of_bits(&mut self, bits: &Abstract<Self, [bool; 64]>) -> Abstract<Self, u64> {
+        bits.clone()
+    }
+}
+
+impl<S: BinarySystem> BinaryEmulate<S> {
+    /// Assembles a little-endian bit string from big-endian bytes.
+    fn pack_be_bits<const N: usize, const M: usize>(
+        bytes: &[[Abstract<S, bool>; 8]; N],
+    ) -> [Abstract<S, bool>; M] {
+        array_init::array_init(|i| bytes[N - 1 - i / 8][i % 8].clone())
+    }
+
+    /// Splits a little-endian bit string into big-endian bytes.
+    fn unpack_be_bits<const N: usize, const M: usize>(
+        value: &[Abstract<S, bool>; M],
+    ) -> [[Abstract<S, bool>; 8]; N] {
+        array_init::array_init(|j| array_init::array_init(|k| value[8 * (N - 1 - j) + k].clone()))
+    }
+}
+
+impl<S: BinarySystem> SystemPack for BinaryEmulate<S> {
+    fn pack_be_u32(&mut self, bytes: &Abstract<Self, [u8; 4]>) -> Abstract<Self, u32> {
+        Self::pack_be_bits(bytes)
+    }
+
+    fn unpack_be_u32(&mut self, value: &Abstract<Self, u32>) -> Abstract<Self, [u8; 4]> {
+        Self::unpack_be_bits(value)
+    }
+
+    fn pack_be_u64(&mut self, bytes: &Abstract<Self, [u8; 8]>) -> Abstract<Self, u64> {
+        Self::pack_be_bits(bytes)
+    }
+
+    fn unpack_be_u64(&mut self, value: &Abstract<Self, u64>) -> Abstract<Self, [u8; 8]> {
+        Self::unpack_be_bits(value)
     }
 }
 
 #[test]
 fn test_wrapping_add() {
     let mut sys = BinaryEmulate::new(Eval);
-    let a = sys.constant(1234);
-    let b = sys.constant(5678);
-    let sum = sys.wrapping_add(&a, &b);
-    let target = sys.constant(1234 + 5678);
+    let a = sys.constant(1234u32);
+    let b = sys.constant(5678u32);
+    let sum = SystemWrappingAdd::<u32>::wrapping_add(&mut sys, &a, &b);
+    let target = sys.constant(1234u32 + 5678);
     assert_eq!(sum, target);
 }
+
+#[cfg(test)]
+fn u64_ops<S>(sys: &mut S, a: &Abstract<S, u64>, b: &Abstract<S, u64>) -> [Abstract<S, u64>; 8]
+where
+    S: SystemWrappingAdd<u64>
+        + SystemBitAnd<u64>
+        + SystemBitXor<u64>
+        + SystemNot<u64>
+        + SystemBitShift<u64, u8>
+        + SystemBitRotate<u64, u8>,
+{
+    [
+        sys.wrapping_add(a, b),
+        sys.and(a, b),
+        sys.xor(a, b),
+        sys.not(a),
+        sys.shl(a, 13),
+        sys.shr(a, 13),
+        sys.rotl(a, 41),
+        sys.rotr(a, 41),
+    ]
+}
+
+#[test]
+fn test_u64_ops() {
+    let (x, y) = (0xfedc_ba98_7654_3210u64, 0x0f1e_2d3c_4b5a_6978u64);
+    let target = u64_ops(&mut Eval, &x, &y);
+    let mut sys = BinaryEmulate::new(Eval);
+    let a = sys.constant(x);
+    let b = sys.constant(y);
+    let res = u64_ops(&mut sys, &a, &b);
+    assert_eq!(res, target.map(|v| sys.constant(v)));
+}
+
+#[test]
+fn test_pack() {
+    let mut sys = BinaryEmulate::new(Eval);
+    let bytes = [0x12u8, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0];
+    let a = sys.constant(bytes);
+    let a_u32: [_; 4] = array_init::array_init(|i| a[i]);
+    let be = sys.pack_be_u32(&a_u32);
+    assert_eq!(be, sys.constant(0x12345678u32));
+    let le = sys.pack_le_u32(&a_u32);
+    assert_eq!(le, sys.constant(0x78563412u32));
+    let be = sys.pack_be_u64(&a);
+    assert_eq!(be, sys.constant(u64::from_be_bytes(bytes)));
+    let le = sys.pack_le_u64(&a);
+    assert_eq!(le, sys.constant(u64::from_le_bytes(bytes)));
+    assert_eq!(sys.unpack_be_u64(&be), a);
+    assert_eq!(sys.unpack_le_u64(&le), a);
+    let le = sys.pack_le_u32(&a_u32);
+    assert_eq!(sys.unpack_le_u32(&le), a_u32);
+}
+
+#[test]
+fn test_adders() {
+    let values = [
+        0u32,
+        1,
+        0xffff_ffff,
+        0x8000_0000,
+        0x1234_5678,
+        0xdead_beef,
+        0x7fff_ffff,
+    ];
+    for adder in [Adder::RippleCarry, Adder::Sklansky, Adder::KoggeStone] {
+        let mut sys = BinaryEmulate::new(Eval).with_adder(adder);
+        for x in values {
+            for y in values {
+                let a = sys.constant(x);
+                let b = sys.constant(y);
+                let sum = SystemWrappingAdd::<u32>::wrapping_add(&mut sys, &a, &b);
+                assert_eq!(sum, sys.constant(x.wrapping_add(y)), "{:?}", adder);
+            }
+        }
+        for n in 1..values.len() {
+            let terms: Vec<_> = values[..n].iter().map(|&v| sys.constant(v)).collect();
+            let sum = SystemWrappingAdd::<u32>::sum_many(&mut sys, &terms);
+            let target = values[..n].iter().fold(0u32, |acc, v| acc.wrapping_add(*v));
+            assert_eq!(sum, sys.constant(target), "{:?}", adder);
+        }
+    }
+}
+
+#[test]
+fn test_mul() {
+    let values = [
+        0u32,
+        1,
+        3,
+        0xffff_ffff,
+        0x8000_0000,
+        0x1234_5678,
+        0xdead_beef,
+    ];
+    let multipliers = [
+        Multiplier::Schoolbook,
+        Multiplier::Karatsuba { threshold: 1 },
+        Multiplier::Karatsuba { threshold: 8 },
+    ];
+    for multiplier in multipliers {
+        let mut sys = BinaryEmulate::new(Eval).with_multiplier(multiplier);
+        for x in values {
+            for y in values {
+                let a = sys.constant(x);
+                let b = sys.constant(y);
+                let prod = SystemWrappingMul::<u32>::wrapping_mul(&mut sys, &a, &b);
+                assert_eq!(prod, sys.constant(x.wrapping_mul(y)));
+                let prod = sys.mul_full(&a, &b);
+                assert_eq!(
+                    prod,
+                    sys.constant(u64::from(x) * u64::from(y)),
+                    "{:?}",
+                    multiplier
+                );
+                let prod = sys.mul_add_full(&a, &b, &a);
+                let target = u64::from(x) * u64::from(y) + u64::from(x);
+                assert_eq!(prod, sys.constant(target), "{:?}", multiplier);
+            }
+        }
+    }
+}
+
+/// Generates a deterministic sequence of test values for an integer type, including edge cases.
+#[cfg(test)]
+fn test_values(bits: u32) -> impl Iterator<Item = u64> {
+    let mask = u64::MAX >> (64 - bits);
+    let edges = [
+        0,
+        1,
+        2,
+        mask,
+        mask >> 1,
+        (mask >> 1) + 1,
+        0x5555_5555_5555_5555 & mask,
+    ];
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let random = core::iter::repeat_with(move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state & mask
+    });
+    edges.into_iter().chain(random.take(40))
+}
+
+/// Generates a test which checks each emulated operation on an integer type against
+/// [`Eval`] for every pair of the given values.
+#[cfg(test)]
+macro_rules! test_int_ops {
+    ($name:ident, $t:ty, $values:expr) => {
+        #[test]
+        fn $name() {
+            let mut sys = BinaryEmulate::new(Eval);
+            let values: Vec<$t> = $values.collect();
+            for &x in values.iter() {
+                let a = sys.constant(x);
+                let bits = SystemBits::bits_of_u64(&mut Eval, &(x as u64));
+                for i in 0..<$t>::BITS as usize {
+                    assert_eq!(a[i], bits[i]);
+                }
+                let r = SystemNot::<$t>::not(&mut sys, &a);
+                assert_eq!(r, sys.constant(!x));
+                for k in 0..<$t>::BITS as u8 {
+                    let r = SystemBitShift::<$t, u8>::shl(&mut sys, &a, k);
+                    assert_eq!(r, sys.constant(x << k));
+                    let r = SystemBitShift::<$t, u8>::shr(&mut sys, &a, k);
+                    assert_eq!(r, sys.constant(x >> k));
+                    let r = SystemBitRotate::<$t, u8>::rotl(&mut sys, &a, k);
+                    assert_eq!(r, sys.constant(x.rotate_left(k.into())));
+                    let r = SystemBitRotate::<$t, u8>::rotr(&mut sys, &a, k);
+                    assert_eq!(r, sys.constant(x.rotate_right(k.into())));
+                }
+                for &y in values.iter() {
+                    let b = sys.constant(y);
+                    let r = SystemWrappingAdd::<$t>::wrapping_add(&mut sys, &a, &b);
+                    assert_eq!(r, sys.constant(x.wrapping_add(y)));
+                    let r = SystemWrappingAdd::<$t>::sum_many(&mut sys, &[a, b, a]);
+                    assert_eq!(r, sys.constant(x.wrapping_add(y).wrapping_add(x)));
+                    let r = SystemWrappingMul::<$t>::wrapping_mul(&mut sys, &a, &b);
+                    assert_eq!(r, sys.constant(x.wrapping_mul(y)));
+                    // Cover shifts within the width of the type, within the full product, and
+                    // beyond the full product
+                    let bits = <$t>::BITS as u8;
+                    let shift = match (y as u8) % 3 {
+                        0 => (x as u8) % (bits + 1),
+                        1 => bits + (x as u8) % bits,
+                        _ => 2 * bits + (x as u8) % (u8::MAX - 2 * bits),
+                    };
+                    let r = SystemMulShr::<$t>::mul_shr(&mut sys, &a, &b, shift);
+                    let target = SystemMulShr::<$t>::mul_shr(&mut Eval, &x, &y, shift);
+                    assert_eq!(r, sys.constant(target));
+                    let r = SystemBitAnd::<$t>::and(&mut sys, &a, &b);
+                    assert_eq!(r, sys.constant(x & y));
+                    let r = SystemBitOr::<$t>::or(&mut sys, &a, &b);
+                    assert_eq!(r, sys.constant(x | y));
+                    let r = SystemBitXor::<$t>::xor(&mut sys, &a, &b);
+                    assert_eq!(r, sys.constant(x ^ y));
+                    let r = SystemOrd::<$t>::lt(&mut sys, &a, &b);
+                    assert_eq!(r, x < y);
+                    let r = SystemOrd::<$t>::le(&mut sys, &a, &b);
+                    assert_eq!(r, x <= y);
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+test_int_ops!(test_u8_ops_exhaustive, u8, 0..=u8::MAX);
+#[cfg(test)]
+test_int_ops!(test_u32_ops, u32, test_values(32).map(|v| v as u32));
+#[cfg(test)]
+test_int_ops!(test_u64_ops_sampled, u64, test_values(64));
+#[cfg(test)]
+test_int_ops!(test_i8_ops_exhaustive, i8, i8::MIN..=i8::MAX);
+#[cfg(test)]
+test_int_ops!(test_i16_ops, i16, test_values(16).map(|v| v as i16));
+#[cfg(test)]
+test_int_ops!(test_i64_ops, i64, test_values(64).map(|v| v as i64));
+
+#[test]
+fn test_div_rem() {
+    let mut sys = BinaryEmulate::new(Eval);
+    for x in 0..=u8::MAX {
+        for y in 1..=u8::MAX {
+            let (a, b) = (sys.constant(x), sys.constant(y));
+            let (q, r) = SystemDivRem::<u8>::div_rem(&mut sys, &a, &b);
+            assert_eq!((q, r), (sys.constant(x / y), sys.constant(x % y)));
+        }
+    }
+    for x in test_values(64) {
+        for y in test_values(64).filter(|y| *y != 0) {
+            let (a, b) = (sys.constant(x), sys.constant(y));
+            let (q, r) = SystemDivRem::<u64>::div_rem(&mut sys, &a, &b);
+            assert_eq!((q, r), (sys.constant(x / y), sys.constant(x % y)));
+            let (x, y) = (x as u32, (y as u32).max(1));
+            let (a, b) = (sys.constant(x), sys.constant(y));
+            let (q, r) = SystemDivRem::<u32>::div_rem(&mut sys, &a, &b);
+            assert_eq!((q, r), (sys.constant(x / y), sys.constant(x % y)));
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_div_rem_zero() {
+    // A zero divisor fails the assertion
+    let mut sys = BinaryEmulate::new(Eval);
+    let (a, b) = (sys.constant(5u32), sys.constant(0u32));
+    let res = std::panic::catch_unwind(move || SystemDivRem::<u32>::div_rem(&mut sys, &a, &b));
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_bits() {
+    let mut sys = BinaryEmulate::new(Eval);
+    for x in test_values(64) {
+        let bits = Eval.bits_of_u64(&x);
+        assert_eq!(sys.constant(x), bits);
+        assert_eq!(Eval.u64_of_bits(&bits), x);
+        let x = x as u32;
+        let bits = Eval.bits_of_u32(&x);
+        assert_eq!(sys.constant(x), bits);
+        assert_eq!(Eval.u32_of_bits(&bits), x);
+        let x = x as u8;
+        let bits = Eval.bits_of_u8(&x);
+        assert_eq!(sys.constant(x), bits);
+        assert_eq!(Eval.u8_of_bits(&bits), x);
+    }
+}
+
+#[test]
+fn test_cond_select() {
+    let mut sys = BinaryEmulate::new(Eval);
+    let a = sys.constant([0x1234_5678u32, 7]);
+    let b = sys.constant([0x9abc_def0u32, 3]);
+    for cond in [false, true] {
+        let r = SystemSelect::<[u32; 2]>::cond_select(
+            &mut sys,
+            &cond,
+            |sys| {
+                let sum = SystemWrappingAdd::<u32>::wrapping_add(sys, &a[0], &b[0]);
+                [sum, SystemWrappingAdd::<u32>::wrapping_add(sys, &a[1], &b[1])]
+            },
+            |sys| {
+                let prod = SystemWrappingMul::<u32>::wrapping_mul(sys, &a[0], &b[0]);
+                [prod, SystemNot::<u32>::not(sys, &a[1])]
+            },
+        );
+        let expected = if cond {
+            [0x1234_5678u32.wrapping_add(0x9abc_def0), 10]
+        } else {
+            [0x1234_5678u32.wrapping_mul(0x9abc_def0), !7]
+        };
+        assert_eq!(r, sys.constant(expected));
+        SystemAssertIf::assert_if(&mut sys, &cond, &cond);
+    }
+}
+
+#[test]
+fn test_one_hot() {
+    let bits = |index: u64| crate::system::bits_of::<4>(index);
+    for index in 0..16 {
+        let sel = one_hot(&mut Eval, &bits(index), 11);
+        let expected: Vec<bool> = (0..11).map(|i| i == index).collect();
+        assert_eq!(sel, expected);
+        assert_eq!(is_one_hot(&mut Eval, &sel), index < 11);
+        if index < 11 {
+            assert_one_hot(&mut Eval, &sel);
+            assert_eq!(decode_one_hot(&mut Eval, &sel, 4), bits(index));
+        }
+    }
+    assert!(!is_one_hot(&mut Eval, &[false, true, true]));
+    assert_eq!(decode_one_hot(&mut Eval, &[false, true, true], 2), [true, true]);
+}
+
+#[test]
+fn test_is_one_hot_free_xor() {
+    for value in 0..32u64 {
+        let bits = crate::system::bits_of::<5>(value);
+        let expected = value.count_ones() == 1;
+        assert_eq!(is_one_hot_with(&mut Eval, &bits, false), expected);
+        assert_eq!(is_one_hot_with(&mut Eval, &bits, true), expected);
+    }
+}
@@ -0,0 +1,411 @@
+//! Gadgets for parsing and signing Bitcoin transactions.
+use crate::bytes::AbstractBytes;
+#[cfg(test)]
+use crate::crypto::hash::SystemSha256Bytes;
+use crate::wire::{CompactSize, Reader, SystemWire, Writer};
+use crate::*;
+
+/// Limits on the size of a [`Transaction`], which determine the cost of parsing it.
+#[derive(Clone, Copy, Debug)]
+pub struct TxConfig {
+    /// The maximum number of inputs.
+    pub max_inputs: usize,
+
+    /// The maximum number of outputs.
+    pub max_outputs: usize,
+
+    /// The maximum length of each input and output script, in bytes.
+    pub script_capacity: usize,
+
+    /// The maximum number of items in the witness of each input.
+    pub max_witness_items: usize,
+
+    /// The maximum length of each witness item, in bytes.
+    pub witness_capacity: usize,
+}
+
+/// An input of a [`Transaction`].
+pub struct TxInput<S: SystemWire + ?Sized> {
+    /// Whether this input is present in the transaction. If this is false, the other fields are
+    /// unspecified.
+    pub present: Abstract<S, bool>,
+
+    /// The ID of the transaction whose output is spent, in internal byte order.
+    pub prev_txid: [Abstract<S, u8>; 32],
+
+    /// The index of the output that is spent.
+    pub prev_index: Abstract<S, u32>,
+
+    /// The unlocking script.
+    pub script_sig: AbstractBytes<S>,
+
+    /// The sequence number.
+    pub sequence: Abstract<S, u32>,
+
+    /// The number of items in the witness of this input, which is zero for transactions without
+    /// witness data.
+    pub num_witness_items: Abstract<S, u64>,
+
+    /// The items in the witness of this input. Items beyond [`TxInput::num_witness_items`] are
+    /// unspecified.
+    pub witness: Vec<AbstractBytes<S>>,
+}
+
+/// An output of a [`Transaction`].
+pub struct TxOutput<S: SystemWire + ?Sized> {
+    /// Whether this output is present in the transaction. If this is false, the other fields
+    /// are unspecified.
+    pub present: Abstract<S, bool>,
+
+    /// The amount of the output, in satoshis.
+    pub value: Abstract<S, u64>,
+
+    /// The locking script.
+    pub script_pubkey: AbstractBytes<S>,
+}
+
+/// A parsed Bitcoin transaction, with a number of inputs and outputs bounded by a [`TxConfig`].
+pub struct Transaction<S: SystemWire + ?Sized> {
+    /// The transaction version.
+    pub version: Abstract<S, u32>,
+
+    /// The number of inputs.
+    pub num_inputs: Abstract<S, u64>,
+
+    /// The inputs, of which the first [`Transaction::num_inputs`] are present.
+    pub inputs: Vec<TxInput<S>>,
+
+    /// The number of outputs.
+    pub num_outputs: Abstract<S, u64>,
+
+    /// The outputs, of which the first [`Transaction::num_outputs`] are present.
+    pub outputs: Vec<TxOutput<S>>,
+
+    /// The lock time.
+    pub lock_time: Abstract<S, u32>,
+
+    /// Whether the transaction was serialized with witness data.
+    pub segwit: Abstract<S, bool>,
+
+    /// Whether the transaction was well-formed and within the limits of its [`TxConfig`]. If
+    /// this is false, the other fields are unspecified.
+    pub valid: Abstract<S, bool>,
+}
+
+impl<S: SystemWire + ?Sized> Transaction<S> {
+    /// Parses a serialized transaction, with or without witness data.
+    pub fn parse(sys: &mut S, config: &TxConfig, bytes: &AbstractBytes<S>) -> Self {
+        let mut reader = Reader::new(sys, bytes);
+        let version = reader.read_u32_le(sys);
+
+        // A zero input count is the marker for witness data, and must be followed by a flag of 1
+        let marker = reader.peek_u8(sys);
+        let segwit = eq_u8_const(sys, &marker, 0);
+        let flag = reader.read_if(sys, &segwit, |sys, reader| {
+            reader.read_bytes::<2>(sys)[1].clone()
+        });
+        let flag_valid = eq_u8_const(sys, &flag, 1);
+        let mut valid = implies(sys, &segwit, &flag_valid);
+
+        // Inputs
+        let num_inputs = reader.read_compact_size(sys);
+        let fits = le_const(sys, &num_inputs, config.max_inputs as u64);
+        valid = SystemBitAnd::<bool>::and(sys, &valid, &fits);
+        let mut inputs = Vec::with_capacity(config.max_inputs);
+        for i in 0..config.max_inputs {
+            let present = gt_const(sys, &num_inputs, i as u64);
+            let input = reader.read_if(sys, &present, |sys, reader| {
+                let prev_txid = reader.read_bytes(sys);
+                let prev_index = reader.read_u32_le(sys);
+                let script_sig = reader.read_prefixed::<CompactSize>(sys, config.script_capacity);
+                let sequence = reader.read_u32_le(sys);
+                TxInput {
+                    present: present.clone(),
+                    prev_txid,
+                    prev_index,
+                    script_sig,
+                    sequence,
+                    num_witness_items: sys.constant(0u64),
+                    witness: Vec::new(),
+                }
+            });
+            inputs.push(input);
+        }
+
+        // Outputs
+        let num_outputs = reader.read_compact_size(sys);
+        let fits = le_const(sys, &num_outputs, config.max_outputs as u64);
+        valid = SystemBitAnd::<bool>::and(sys, &valid, &fits);
+        let mut outputs = Vec::with_capacity(config.max_outputs);
+        for i in 0..config.max_outputs {
+            let present = gt_const(sys, &num_outputs, i as u64);
+            let output = reader.read_if(sys, &present, |sys, reader| {
+                let value = reader.read_u64_le(sys);
+                let script_pubkey =
+                    reader.read_prefixed::<CompactSize>(sys, config.script_capacity);
+                TxOutput {
+                    present: present.clone(),
+                    value,
+                    script_pubkey,
+                }
+            });
+            outputs.push(output);
+        }
+
+        // Witnesses
+        for input in inputs.iter_mut() {
+            let enable = SystemBitAnd::<bool>::and(sys, &segwit, &input.present);
+            let num_items = reader.read_if(sys, &enable, |sys, reader| {
+                let num_items = reader.read_compact_size(sys);
+                for j in 0..config.max_witness_items {
+                    let present = gt_const(sys, &num_items, j as u64);
+                    let item = reader.read_if(sys, &present, |sys, reader| {
+                        reader.read_prefixed::<CompactSize>(sys, config.witness_capacity)
+                    });
+                    input.witness.push(item);
+                }
+                num_items
+            });
+            let fits = le_const(sys, &num_items, config.max_witness_items as u64);
+            let fits = implies(sys, &enable, &fits);
+            valid = SystemBitAnd::<bool>::and(sys, &valid, &fits);
+            let zero = SystemRepr::<u64>::constant(sys, 0);
+            input.num_witness_items = SystemSelect::<u64>::select(sys, &enable, &num_items, &zero);
+        }
+
+        let lock_time = reader.read_u32_le(sys);
+        let finished = reader.finish(sys);
+        let valid = SystemBitAnd::<bool>::and(sys, &valid, &finished);
+        Self {
+            version,
+            num_inputs,
+            inputs,
+            num_outputs,
+            outputs,
+            lock_time,
+            segwit,
+            valid,
+        }
+    }
+
+    /// Computes the ID of this transaction, in internal byte order. This is the double SHA-256
+    /// digest of its serialization without witness data.
+    pub fn txid(&self, sys: &mut S) -> [Abstract<S, u8>; 32] {
+        let mut writer = Writer::new(sys);
+        writer.write_u32_le(sys, &self.version);
+        writer.write_compact_size(sys, &self.num_inputs);
+        for input in self.inputs.iter() {
+            let mut entry = Writer::new(sys);
+            entry.write_bytes(sys, &input.prev_txid);
+            entry.write_u32_le(sys, &input.prev_index);
+            entry.write_prefixed::<CompactSize>(sys, &input.script_sig);
+            entry.write_u32_le(sys, &input.sequence);
+            let entry = when(sys, &input.present, &entry.finish());
+            writer.write_slice(sys, &entry);
+        }
+        writer.write_compact_size(sys, &self.num_outputs);
+        let outputs = self.serialize_outputs(sys);
+        writer.write_slice(sys, &outputs);
+        writer.write_u32_le(sys, &self.lock_time);
+        double_sha256(sys, &writer.finish())
+    }
+
+    /// Computes the BIP-143 signature hash for the input at the given index, with the
+    /// `SIGHASH_ALL` type. `script_code` and `amount` describe the output being spent, as
+    /// defined by BIP-143. If the input is not present, the result is unspecified.
+    pub fn sighash(
+        &self,
+        sys: &mut S,
+        index: usize,
+        script_code: &AbstractBytes<S>,
+        amount: &Abstract<S, u64>,
+    ) -> [Abstract<S, u8>; 32] {
+        // Commit to all outpoints and sequence numbers. These have a fixed size, so the entries
+        // for absent inputs can be truncated by length alone.
+        let mut prevouts = Vec::new();
+        let mut sequences = Vec::new();
+        let mut prevouts_len = SystemRepr::<u32>::constant(sys, 0);
+        let mut sequences_len = SystemRepr::<u32>::constant(sys, 0);
+        for (i, input) in self.inputs.iter().enumerate() {
+            prevouts.extend_from_slice(&input.prev_txid);
+            prevouts.extend(sys.unpack_le_u32(&input.prev_index));
+            sequences.extend(sys.unpack_le_u32(&input.sequence));
+            let len = SystemRepr::<u32>::constant(sys, 36 * (i as u32 + 1));
+            prevouts_len = SystemSelect::<u32>::select(sys, &input.present, &len, &prevouts_len);
+            let len = SystemRepr::<u32>::constant(sys, 4 * (i as u32 + 1));
+            sequences_len = SystemSelect::<u32>::select(sys, &input.present, &len, &sequences_len);
+        }
+        let prevouts = AbstractBytes::new(sys, prevouts, prevouts_len);
+        let hash_prevouts = double_sha256(sys, &prevouts);
+        let sequences = AbstractBytes::new(sys, sequences, sequences_len);
+        let hash_sequence = double_sha256(sys, &sequences);
+        let outputs = self.serialize_outputs(sys);
+        let hash_outputs = double_sha256(sys, &outputs);
+
+        // Build preimage
+        let input = &self.inputs[index];
+        let mut writer = Writer::new(sys);
+        writer.write_u32_le(sys, &self.version);
+        writer.write_bytes(sys, &hash_prevouts);
+        writer.write_bytes(sys, &hash_sequence);
+        writer.write_bytes(sys, &input.prev_txid);
+        writer.write_u32_le(sys, &input.prev_index);
+        writer.write_prefixed::<CompactSize>(sys, script_code);
+        writer.write_u64_le(sys, amount);
+        writer.write_u32_le(sys, &input.sequence);
+        writer.write_bytes(sys, &hash_outputs);
+        writer.write_u32_le(sys, &self.lock_time);
+        let sighash_type = SystemRepr::<u32>::constant(sys, SIGHASH_ALL);
+        writer.write_u32_le(sys, &sighash_type);
+        double_sha256(sys, &writer.finish())
+    }
+
+    /// Serializes the outputs of this transaction, without a count prefix.
+    fn serialize_outputs(&self, sys: &mut S) -> AbstractBytes<S> {
+        let mut writer = Writer::new(sys);
+        for output in self.outputs.iter() {
+            let mut entry = Writer::new(sys);
+            entry.write_u64_le(sys, &output.value);
+            entry.write_prefixed::<CompactSize>(sys, &output.script_pubkey);
+            let entry = when(sys, &output.present, &entry.finish());
+            writer.write_slice(sys, &entry);
+        }
+        writer.finish()
+    }
+}
+
+/// The signature hash type which commits to all inputs and outputs.
+const SIGHASH_ALL: u32 = 1;
+
+/// Computes the double SHA-256 digest of a string.
+fn double_sha256<S: SystemWire + ?Sized>(
+    sys: &mut S,
+    bytes: &AbstractBytes<S>,
+) -> [Abstract<S, u8>; 32] {
+    let digest = bytes.sha256(sys);
+    sys.sha256_bytes(&digest)
+}
+
+/// Returns the given string if `cond` is true, or the empty string otherwise.
+fn when<S: SystemWire + ?Sized>(
+    sys: &mut S,
+    cond: &Abstract<S, bool>,
+    bytes: &AbstractBytes<S>,
+) -> AbstractBytes<S> {
+    let zero = SystemRepr::<u32>::constant(sys, 0);
+    let len = SystemSelect::<u32>::select(sys, cond, bytes.len(), &zero);
+    AbstractBytes::new(sys, bytes.data().to_vec(), len)
+}
+
+/// Computes `!a | b`.
+fn implies<S: SystemWire + ?Sized>(
+    sys: &mut S,
+    a: &Abstract<S, bool>,
+    b: &Abstract<S, bool>,
+) -> Abstract<S, bool> {
+    let not_a = SystemNot::<bool>::not(sys, a);
+    SystemBitOr::<bool>::or(sys, &not_a, b)
+}
+
+/// Determines whether a 64-bit integer is at most a constant.
+fn le_const<S: SystemWire + ?Sized>(
+    sys: &mut S,
+    a: &Abstract<S, u64>,
+    b: u64,
+) -> Abstract<S, bool> {
+    let b = SystemRepr::<u64>::constant(sys, b);
+    SystemOrd::<u64>::le(sys, a, &b)
+}
+
+/// Determines whether a 64-bit integer is greater than a constant.
+fn gt_const<S: SystemWire + ?Sized>(
+    sys: &mut S,
+    a: &Abstract<S, u64>,
+    b: u64,
+) -> Abstract<S, bool> {
+    let b = SystemRepr::<u64>::constant(sys, b);
+    SystemOrd::<u64>::lt(sys, &b, a)
+}
+
+#[cfg(test)]
+fn hex(str: &str) -> Vec<u8> {
+    (0..str.len() / 2)
+        .map(|i| u8::from_str_radix(&str[i * 2..i * 2 + 2], 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_bip143() {
+    // Native P2WPKH example from BIP-143
+    let tx = hex(concat!(
+        "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000",
+        "eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ff",
+        "ffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d",
+        "000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000"
+    ));
+    let config = TxConfig {
+        max_inputs: 3,
+        max_outputs: 3,
+        script_capacity: 32,
+        max_witness_items: 2,
+        witness_capacity: 40,
+    };
+    let bytes = AbstractBytes::from_const(&mut Eval, &tx, 256);
+    let parsed = Transaction::parse(&mut Eval, &config, &bytes);
+    assert!(parsed.valid);
+    assert!(!parsed.segwit);
+    assert_eq!(parsed.version, 1);
+    assert_eq!(parsed.num_inputs, 2);
+    assert!(parsed.inputs[1].present && !parsed.inputs[2].present);
+    assert_eq!(parsed.inputs[0].sequence, 0xffffffee);
+    assert_eq!(parsed.inputs[1].prev_index, 1);
+    assert_eq!(parsed.num_outputs, 2);
+    assert_eq!(parsed.outputs[0].value, 112340000);
+    assert_eq!(parsed.outputs[1].value, 223450000);
+    let script = hex("76a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac");
+    let script = AbstractBytes::from_const(&mut Eval, &script, 32);
+    assert!(parsed.outputs[1].script_pubkey.ct_eq(&mut Eval, &script));
+    assert_eq!(parsed.lock_time, 17);
+    let txid = Eval.sha256_bytes(&Eval.sha256_bytes(&tx));
+    assert_eq!(parsed.txid(&mut Eval), txid);
+
+    // Signature hash
+    let script_code = hex("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac");
+    let script_code = AbstractBytes::from_const(&mut Eval, &script_code, 32);
+    let sighash = parsed.sighash(&mut Eval, 1, &script_code, &600000000);
+    let expected = hex("c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670");
+    assert_eq!(sighash[..], expected[..]);
+
+    // Add witness data
+    let mut segwit = tx[..4].to_vec();
+    segwit.extend([0x00, 0x01]);
+    segwit.extend_from_slice(&tx[4..tx.len() - 4]);
+    segwit.extend([0x00, 0x02, 0x03, 0xaa, 0xbb, 0xcc, 0x21]);
+    segwit.extend([0x02; 33]);
+    segwit.extend_from_slice(&tx[tx.len() - 4..]);
+    let bytes = AbstractBytes::from_const(&mut Eval, &segwit, 256);
+    let parsed = Transaction::parse(&mut Eval, &config, &bytes);
+    assert!(parsed.valid);
+    assert!(parsed.segwit);
+    assert_eq!(parsed.inputs[0].num_witness_items, 0);
+    assert_eq!(parsed.inputs[1].num_witness_items, 2);
+    assert_eq!(parsed.inputs[1].witness[0].data()[..3], [0xaa, 0xbb, 0xcc]);
+    assert_eq!(*parsed.inputs[1].witness[1].len(), 33);
+    assert_eq!(parsed.txid(&mut Eval), txid);
+    let sighash = parsed.sighash(&mut Eval, 1, &script_code, &600000000);
+    assert_eq!(sighash[..], expected[..]);
+
+    // Malformed transactions
+    let bytes = AbstractBytes::from_const(&mut Eval, &tx[..tx.len() - 1], 256);
+    assert!(!Transaction::parse(&mut Eval, &config, &bytes).valid);
+    let mut long = tx.clone();
+    long.push(0);
+    let bytes = AbstractBytes::from_const(&mut Eval, &long, 256);
+    assert!(!Transaction::parse(&mut Eval, &config, &bytes).valid);
+    let config = TxConfig {
+        max_inputs: 1,
+        ..config
+    };
+    let bytes = AbstractBytes::from_const(&mut Eval, &tx, 256);
+    assert!(!Transaction::parse(&mut Eval, &config, &bytes).valid);
+}
@@ -0,0 +1,443 @@
+//! Fixed-length vectors of abstract bits.
+use crate::*;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// A vector of abstract booleans of a fixed length within a system of type `S`, supporting bulk
+/// bitwise operations. Each operation produces its result in a single allocation, rather than
+/// one per bit.
+pub struct AbstractBits<S: SystemRepr<bool> + ?Sized> {
+    bits: Vec<Abstract<S, bool>>,
+}
+
+impl<S: SystemRepr<bool> + ?Sized> Clone for AbstractBits<S> {
+    fn clone(&self) -> Self {
+        Self {
+            bits: self.bits.clone(),
+        }
+    }
+}
+
+impl<S: BinarySystem + ?Sized> AbstractBits<S> {
+    /// Constructs an [`AbstractBits`] from the given bits.
+    pub fn new(bits: Vec<Abstract<S, bool>>) -> Self {
+        Self { bits }
+    }
+
+    /// Constructs an [`AbstractBits`] for a constant vector.
+    pub fn from_const(sys: &mut S, value: &[bool]) -> Self {
+        let bits = value.iter().map(|bit| sys.constant(*bit)).collect();
+        Self { bits }
+    }
+
+    /// Constructs an [`AbstractBits`] of the given length whose bits are all zero.
+    pub fn zeros(sys: &mut S, len: usize) -> Self {
+        let zero = sys.constant(false);
+        Self {
+            bits: alloc::vec![zero; len],
+        }
+    }
+
+    /// The number of bits in this vector.
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Determines whether this vector has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    /// The bits of this vector.
+    pub fn bits(&self) -> &[Abstract<S, bool>] {
+        &self.bits
+    }
+
+    /// Gets the bits of this vector, discarding this wrapper.
+    pub fn into_bits(self) -> Vec<Abstract<S, bool>> {
+        self.bits
+    }
+
+    /// Gets the bit at the given index.
+    pub fn get(&self, index: usize) -> &Abstract<S, bool> {
+        &self.bits[index]
+    }
+
+    /// Computes the bitwise AND of this vector with another of the same length.
+    pub fn and(&self, sys: &mut S, other: &Self) -> Self {
+        self.zip(sys, other, |sys, a, b| sys.and(a, b))
+    }
+
+    /// Computes the bitwise OR of this vector with another of the same length.
+    pub fn or(&self, sys: &mut S, other: &Self) -> Self {
+        self.zip(sys, other, |sys, a, b| sys.or(a, b))
+    }
+
+    /// Computes the bitwise XOR of this vector with another of the same length.
+    pub fn xor(&self, sys: &mut S, other: &Self) -> Self {
+        self.zip(sys, other, |sys, a, b| sys.xor(a, b))
+    }
+
+    /// Computes the bitwise NOT of this vector.
+    pub fn not(&self, sys: &mut S) -> Self {
+        let bits = self.bits.iter().map(|bit| sys.not(bit)).collect();
+        Self { bits }
+    }
+
+    /// Returns `a` if `cond` is true, or `b` otherwise. Both vectors must have the same length.
+    pub fn select(sys: &mut S, cond: &Abstract<S, bool>, a: &Self, b: &Self) -> Self {
+        a.zip(sys, b, |sys, a, b| select_bit(sys, cond, a, b))
+    }
+
+    /// Extracts the bits in the given range of indices.
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        Self {
+            bits: self.bits[range].to_vec(),
+        }
+    }
+
+    /// Concatenates this vector with another, with the bits of this vector first.
+    pub fn concat(&self, other: &Self) -> Self {
+        let mut bits = Vec::with_capacity(self.len() + other.len());
+        bits.extend_from_slice(&self.bits);
+        bits.extend_from_slice(&other.bits);
+        Self { bits }
+    }
+
+    /// Determines whether any bit of this vector is set.
+    pub fn any(&self, sys: &mut S) -> Abstract<S, bool> {
+        let mut res = sys.constant(false);
+        for bit in self.bits.iter() {
+            res = sys.or(&res, bit);
+        }
+        res
+    }
+
+    /// Determines whether every bit of this vector is set.
+    pub fn all(&self, sys: &mut S) -> Abstract<S, bool> {
+        let mut res = sys.constant(true);
+        for bit in self.bits.iter() {
+            res = sys.and(&res, bit);
+        }
+        res
+    }
+
+    /// Determines whether this vector is equal to another of the same length.
+    pub fn eq(&self, sys: &mut S, other: &Self) -> Abstract<S, bool> {
+        let diff = self.xor(sys, other);
+        let any = diff.any(sys);
+        sys.not(&any)
+    }
+
+    /// Counts the bits of this vector which are set. The count is given as a little-endian
+    /// vector with just enough bits to represent the length of this vector. This uses a tree of
+    /// adders, each full adder costing a single AND.
+    pub fn popcount(&self, sys: &mut S) -> Self {
+        let width = (usize::BITS - self.len().leading_zeros()) as usize;
+        let mut layer: Vec<Vec<_>> = self
+            .bits
+            .iter()
+            .map(|bit| alloc::vec![bit.clone()])
+            .collect();
+        while layer.len() > 1 {
+            let mut next = Vec::with_capacity(layer.len().div_ceil(2));
+            let mut iter = layer.into_iter();
+            while let Some(a) = iter.next() {
+                next.push(match iter.next() {
+                    Some(b) => add_bits(sys, &a, &b),
+                    None => a,
+                });
+            }
+            layer = next;
+        }
+        let mut bits = layer.pop().unwrap_or_default();
+        bits.resize_with(width, || sys.constant(false));
+        Self { bits }
+    }
+
+    /// Applies a binary operation to each corresponding pair of bits in this vector and another.
+    fn zip(
+        &self,
+        sys: &mut S,
+        other: &Self,
+        mut op: impl FnMut(&mut S, &Abstract<S, bool>, &Abstract<S, bool>) -> Abstract<S, bool>,
+    ) -> Self {
+        assert_eq!(self.len(), other.len(), "vectors must have the same length");
+        let bits = (self.bits.iter().zip(other.bits.iter()))
+            .map(|(a, b)| op(sys, a, b))
+            .collect();
+        Self { bits }
+    }
+}
+
+/// Adds two little-endian unsigned integers of any length, producing a result one bit longer
+/// than the longer of the two.
+pub(crate) fn add_bits<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    a: &[Abstract<S, bool>],
+    b: &[Abstract<S, bool>],
+) -> Vec<Abstract<S, bool>> {
+    let mut res = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry: Option<Abstract<S, bool>> = None;
+    for i in 0..a.len().max(b.len()) {
+        let (x, y) = match (a.get(i), b.get(i)) {
+            (Some(x), Some(y)) => (x, Some(y)),
+            (Some(x), None) | (None, Some(x)) => (x, None),
+            (None, None) => unreachable!(),
+        };
+        let (sum, next) = match (y, carry.take()) {
+            // The majority of `x`, `y` and `c` is `x ^ ((x ^ y) & (x ^ c))`
+            (Some(y), Some(c)) => {
+                let t = sys.xor(x, y);
+                let u = sys.xor(x, &c);
+                let v = sys.and(&t, &u);
+                (sys.xor(&t, &c), Some(sys.xor(x, &v)))
+            }
+            (Some(y), None) => (sys.xor(x, y), Some(sys.and(x, y))),
+            (None, Some(c)) => (sys.xor(x, &c), Some(sys.and(x, &c))),
+            (None, None) => (x.clone(), None),
+        };
+        res.push(sum);
+        carry = next;
+    }
+    res.push(carry.unwrap_or_else(|| sys.constant(false)));
+    res
+}
+
+/// A little-endian vector of abstract bits, representing an unsigned integer.
+pub(crate) type Bits<S> = Vec<Abstract<S, bool>>;
+
+/// The number of bits needed to represent the given integer.
+pub(crate) fn num_bits(value: u128) -> usize {
+    (u128::BITS - value.leading_zeros()) as usize
+}
+
+/// Adds a constant to an integer, producing a result one bit longer than the longer of the two.
+pub(crate) fn add_const<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    value: &[Abstract<S, bool>],
+    k: u128,
+) -> Bits<S> {
+    let len = value.len().max(num_bits(k));
+    let (mut res, carry) = add_const_carry(sys, value, k, len);
+    res.push(carry.unwrap_or_else(|| sys.constant(false)));
+    res
+}
+
+/// Adds a constant to an integer, truncating the result to `len` bits and returning the carry
+/// out of the last bit, or `None` if it is known to be zero.
+pub(crate) fn add_const_carry<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    value: &[Abstract<S, bool>],
+    k: u128,
+    len: usize,
+) -> (Bits<S>, Option<Abstract<S, bool>>) {
+    let mut res = Vec::with_capacity(len);
+    let mut carry: Option<Abstract<S, bool>> = None;
+    for i in 0..len {
+        let k_bit = i < 128 && (k >> i) & 1 == 1;
+        let Some(x) = value.get(i) else {
+            // Beyond the end of `value`, the sum is just the constant plus the carry
+            let (sum, next) = match (k_bit, carry.take()) {
+                (false, None) => (sys.constant(false), None),
+                (true, None) => (sys.constant(true), None),
+                (false, Some(c)) => (c, None),
+                (true, Some(c)) => (sys.not(&c), Some(c)),
+            };
+            res.push(sum);
+            carry = next;
+            continue;
+        };
+        let (sum, next) = match (k_bit, carry.take()) {
+            (false, None) => (x.clone(), None),
+            (true, None) => (sys.not(x), Some(x.clone())),
+            (false, Some(c)) => (sys.xor(x, &c), Some(sys.and(x, &c))),
+            (true, Some(c)) => {
+                let t = sys.xor(x, &c);
+                (sys.not(&t), Some(sys.or(x, &c)))
+            }
+        };
+        res.push(sum);
+        carry = next;
+    }
+    (res, carry)
+}
+
+/// Constructs a constant integer of `len` bits, truncating the given value.
+pub(crate) fn const_bits<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    value: u128,
+    len: usize,
+) -> Bits<S> {
+    (0..len)
+        .map(|i| sys.constant(i < 128 && (value >> i) & 1 == 1))
+        .collect()
+}
+
+/// Subtracts one integer from another, producing a result as long as the longer of the two and
+/// the borrow out of the last bit, which is set exactly when `a < b`.
+pub(crate) fn sub_bits<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    a: &[Abstract<S, bool>],
+    b: &[Abstract<S, bool>],
+) -> (Bits<S>, Abstract<S, bool>) {
+    let len = a.len().max(b.len());
+    let zero = sys.constant(false);
+    let mut res = Vec::with_capacity(len);
+    let mut borrow = zero.clone();
+    for i in 0..len {
+        let x = a.get(i).unwrap_or(&zero);
+        let Some(y) = b.get(i) else {
+            res.push(sys.xor(x, &borrow));
+            let not_x = sys.not(x);
+            borrow = sys.and(&not_x, &borrow);
+            continue;
+        };
+
+        // The borrow is the majority of `!x`, `y` and the previous borrow
+        let not_x = sys.not(x);
+        let t = sys.xor(&not_x, y);
+        let u = sys.xor(&not_x, &borrow);
+        let v = sys.and(&t, &u);
+        let diff = sys.xor(x, y);
+        res.push(sys.xor(&diff, &borrow));
+        borrow = sys.xor(&not_x, &v);
+    }
+    (res, borrow)
+}
+
+/// Multiplies an integer by a constant, producing a result long enough to hold any product.
+pub(crate) fn mul_const<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    value: &[Abstract<S, bool>],
+    k: u128,
+) -> Bits<S> {
+    let len = value.len() + num_bits(k);
+    let mut res: Bits<S> = Vec::new();
+    for i in (0..128).filter(|i| (k >> i) & 1 == 1) {
+        let mut term = Vec::with_capacity(i + value.len());
+        term.resize_with(i, || sys.constant(false));
+        term.extend_from_slice(value);
+        res = add_bits(sys, &res, &term);
+    }
+    res.resize_with(len, || sys.constant(false));
+    res.truncate(len);
+    res
+}
+
+/// Multiplies two integers, producing a result as long as the sum of their lengths.
+pub(crate) fn mul_bits<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    a: &[Abstract<S, bool>],
+    b: &[Abstract<S, bool>],
+) -> Bits<S> {
+    let len = a.len() + b.len();
+    let mut res: Bits<S> = Vec::new();
+    for (i, y) in b.iter().enumerate() {
+        let mut term = Vec::with_capacity(i + a.len());
+        term.resize_with(i, || sys.constant(false));
+        term.extend(a.iter().map(|x| sys.and(x, y)));
+        res = add_bits(sys, &res, &term);
+    }
+    res.resize_with(len, || sys.constant(false));
+    res.truncate(len);
+    res
+}
+
+/// Divides one integer by another using restoring long division, returning the quotient, with
+/// the length of `a`, and the remainder, with the length of `b`. If the divisor is zero, the
+/// quotient is all ones and the remainder is the dividend, truncated.
+pub(crate) fn div_rem_bits<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    a: &[Abstract<S, bool>],
+    b: &[Abstract<S, bool>],
+) -> (Bits<S>, Bits<S>) {
+    let mut quot = Vec::with_capacity(a.len());
+    let mut rem = const_bits(sys, 0, b.len());
+    for bit in a.iter().rev() {
+        // Bring down the next bit of the dividend, then subtract the divisor if it fits. The
+        // shifted remainder may have one more bit than the divisor.
+        let mut shifted = Vec::with_capacity(b.len() + 1);
+        shifted.push(bit.clone());
+        shifted.extend_from_slice(&rem);
+        let (diff, borrow) = sub_bits(sys, &shifted, b);
+        let fits = sys.not(&borrow);
+        rem = (0..b.len())
+            .map(|j| select_bit(sys, &fits, &diff[j], &shifted[j]))
+            .collect();
+        quot.push(fits);
+    }
+    quot.reverse();
+    (quot, rem)
+}
+
+/// Divides an integer by a constant of at least 2 using long division, returning the quotient
+/// and remainder.
+pub(crate) fn div_rem_const<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    value: &[Abstract<S, bool>],
+    divisor: u128,
+) -> (Bits<S>, Bits<S>) {
+    // The remainder always fits in `width` bits, and so does any prefix of fewer bits
+    let width = num_bits(divisor - 1);
+    let top = value.len().min(width - 1);
+    let mut rem: Bits<S> = value[value.len() - top..].to_vec();
+    rem.resize_with(width, || sys.constant(false));
+    let mut quot = Vec::with_capacity(value.len() - top);
+    for bit in value[..value.len() - top].iter().rev() {
+        let mut shifted = Vec::with_capacity(width + 1);
+        shifted.push(bit.clone());
+        shifted.extend_from_slice(&rem);
+
+        // Subtract the divisor by adding its two's complement, so the carry is set if the
+        // divisor fits
+        let neg = (1 << (width + 1)) - divisor;
+        let (diff, fits) = add_const_carry(sys, &shifted, neg, width + 1);
+        let fits = fits.unwrap_or_else(|| sys.constant(false));
+        rem = (0..width)
+            .map(|i| select_bit(sys, &fits, &diff[i], &shifted[i]))
+            .collect();
+        quot.push(fits);
+    }
+    quot.reverse();
+    (quot, rem)
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_abstract_bits() {
+    let to_bits = |value: u64, len: usize| (0..len).map(|i| (value >> i) & 1 == 1).collect();
+    let of_bits = |bits: &AbstractBits<Eval>| {
+        (bits.bits().iter().enumerate()).fold(0u64, |acc, (i, bit)| acc | (*bit as u64) << i)
+    };
+    let sys = &mut Eval;
+    Eval::seed_rng(7);
+    for len in [0, 1, 2, 3, 7, 8, 13, 32] {
+        let mask = (1u64 << len) - 1;
+        for _ in 0..20 {
+            let x = SystemRand::<u64>::rand(sys) & mask;
+            let y = SystemRand::<u64>::rand(sys) & mask;
+            let a = AbstractBits::<Eval>::new(to_bits(x, len));
+            let b = AbstractBits::from_const(sys, &to_bits(y, len));
+            assert_eq!(of_bits(&a.and(sys, &b)), x & y);
+            assert_eq!(of_bits(&a.or(sys, &b)), x | y);
+            assert_eq!(of_bits(&a.xor(sys, &b)), x ^ y);
+            assert_eq!(of_bits(&a.not(sys)), !x & mask);
+            assert_eq!(of_bits(&AbstractBits::select(sys, &false, &a, &b)), y);
+            assert_eq!(of_bits(&a.popcount(sys)), x.count_ones() as u64);
+            assert_eq!(
+                a.popcount(sys).len(),
+                64 - (len as u64).leading_zeros() as usize
+            );
+            assert_eq!(a.eq(sys, &b), x == y);
+            assert!(a.eq(sys, &a));
+            assert_eq!(a.any(sys), x != 0);
+            assert_eq!(a.all(sys), x == mask);
+            let c = a.concat(&b);
+            assert_eq!(c.len(), 2 * len);
+            assert_eq!(of_bits(&c), x | y << len);
+            assert_eq!(of_bits(&c.slice(len..2 * len)), y);
+        }
+    }
+    assert_eq!(AbstractBits::zeros(sys, 5).bits(), [false; 5]);
+}
@@ -0,0 +1,216 @@
+//! Bitsliced evaluation of boolean circuits, where each wire carries 64 independent instances.
+use crate::graph::{Graph, Node, Op, SubcircuitTemplate};
+use crate::*;
+
+/// A [`BinarySystem`] which directly evaluates 64 independent instances of a circuit at once.
+/// Each abstract boolean is a word whose bits are the values of the instances.
+///
+/// Any gadget can be evaluated in bulk by running it in `BinaryEmulate<Bitslice>`.
+pub struct Bitslice;
+
+impl SystemRepr<bool> for Bitslice {
+    type Abstract = u64;
+    type Error = core::convert::Infallible;
+    fn constant(&mut self, value: bool) -> u64 {
+        if value {
+            u64::MAX
+        } else {
+            0
+        }
+    }
+}
+
+impl SystemCapabilities for Bitslice {}
+
+impl SystemBitAnd<bool> for Bitslice {
+    fn and(&mut self, a: &u64, b: &u64) -> u64 {
+        a & b
+    }
+}
+
+impl SystemBitOr<bool> for Bitslice {
+    fn or(&mut self, a: &u64, b: &u64) -> u64 {
+        a | b
+    }
+}
+
+impl SystemBitXor<bool> for Bitslice {
+    fn xor(&mut self, a: &u64, b: &u64) -> u64 {
+        a ^ b
+    }
+}
+
+impl SystemNot<bool> for Bitslice {
+    fn not(&mut self, value: &u64) -> u64 {
+        !value
+    }
+}
+
+impl SystemSelect<bool> for Bitslice {
+    fn select(&mut self, cond: &u64, a: &u64, b: &u64) -> u64 {
+        (cond & a) | (!cond & b)
+    }
+}
+
+/// A [`SubcircuitTemplate`] which has been compiled into a flat sequence of word operations, so
+/// that it can be evaluated on 64 instances at a time with minimal overhead.
+#[derive(Debug, Clone)]
+pub struct BitslicedCircuit {
+    num_inputs: usize,
+    ops: Vec<Op>,
+    outputs: Vec<Node>,
+}
+
+impl BitslicedCircuit {
+    /// Compiles the given template. Calls are flattened.
+    pub fn new(template: &SubcircuitTemplate) -> Self {
+        let mut graph = Graph::new();
+        let inputs: Vec<_> = (0..template.num_inputs()).map(|_| graph.input()).collect();
+        let outputs = template.instantiate(&mut graph, &inputs);
+        Self {
+            num_inputs: inputs.len(),
+            ops: graph.nodes().to_vec(),
+            outputs,
+        }
+    }
+
+    /// The number of inputs this circuit accepts.
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    /// The number of outputs this circuit produces.
+    pub fn num_outputs(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Evaluates 64 instances of this circuit. Bit `i` of each input and output word belongs to
+    /// instance `i`.
+    pub fn run(&self, inputs: &[u64]) -> Vec<u64> {
+        assert_eq!(inputs.len(), self.num_inputs);
+        let mut values: Vec<u64> = Vec::with_capacity(self.ops.len());
+        for op in self.ops.iter() {
+            let value = match *op {
+                Op::Input(i) => inputs[i as usize],
+                Op::Const(value) => Bitslice.constant(value),
+                Op::And(a, b) => values[a.index()] & values[b.index()],
+                Op::Or(a, b) => values[a.index()] | values[b.index()],
+                Op::Xor(a, b) => values[a.index()] ^ values[b.index()],
+                Op::Not(a) => !values[a.index()],
+                Op::Call(_) | Op::Output(_, _) => unreachable!("circuit should be flat"),
+            };
+            values.push(value);
+        }
+        self.outputs
+            .iter()
+            .map(|node| values[node.index()])
+            .collect()
+    }
+
+    /// Evaluates this circuit on any number of instances, given and returned as strings of
+    /// bits.
+    pub fn run_batch(&self, instances: &[Vec<bool>]) -> Vec<Vec<bool>> {
+        let mut res = Vec::with_capacity(instances.len());
+        for chunk in instances.chunks(64) {
+            let mut inputs = vec![0u64; self.num_inputs];
+            for (lane, instance) in chunk.iter().enumerate() {
+                assert_eq!(instance.len(), self.num_inputs);
+                for (word, bit) in inputs.iter_mut().zip(instance) {
+                    *word |= (*bit as u64) << lane;
+                }
+            }
+            let outputs = self.run(&inputs);
+            for lane in 0..chunk.len() {
+                res.push(outputs.iter().map(|word| (word >> lane) & 1 == 1).collect());
+            }
+        }
+        res
+    }
+
+    /// Evaluates this circuit on every possible input. The outputs for an input are found at
+    /// the index given by interpreting the input as a little-endian integer.
+    pub fn run_exhaustive(&self) -> Vec<Vec<bool>> {
+        assert!(
+            self.num_inputs <= 32,
+            "too many inputs for exhaustive evaluation"
+        );
+        let total = 1usize << self.num_inputs;
+        let mut res = Vec::with_capacity(total);
+        for block in 0..total.div_ceil(64) {
+            // The low 6 bits of the input index vary between lanes, and the rest between blocks
+            let inputs: Vec<u64> = (0..self.num_inputs)
+                .map(|i| match i {
+                    0..=5 => LANE_PATTERNS[i],
+                    _ => Bitslice.constant((block >> (i - 6)) & 1 == 1),
+                })
+                .collect();
+            let outputs = self.run(&inputs);
+            for lane in 0..(total - block * 64).min(64) {
+                res.push(outputs.iter().map(|word| (word >> lane) & 1 == 1).collect());
+            }
+        }
+        res
+    }
+}
+
+/// For each of the low 6 bits of a lane index, the word whose bits give the value of that bit in
+/// each lane.
+const LANE_PATTERNS: [u64; 6] = [
+    0xaaaaaaaaaaaaaaaa,
+    0xcccccccccccccccc,
+    0xf0f0f0f0f0f0f0f0,
+    0xff00ff00ff00ff00,
+    0xffff0000ffff0000,
+    0xffffffff00000000,
+];
+
+#[test]
+fn test_bitslice_emulate() {
+    let mut sys = BinaryEmulate::new(Bitslice);
+    let a: [u64; 8] = std::array::from_fn(|i| 0xdeadbeefcafef00d_u64.rotate_right(i as u32 * 5));
+    let b: [u64; 8] = std::array::from_fn(|i| 0x0123456789abcdef_u64.rotate_left(i as u32 * 7));
+    let sum = SystemWrappingAdd::<u8>::wrapping_add(&mut sys, &a, &b);
+    for lane in 0..64 {
+        let get = |bits: &[u64; 8]| {
+            (0..8).fold(0u8, |acc, i| acc | ((((bits[i] >> lane) & 1) as u8) << i))
+        };
+        assert_eq!(get(&sum), get(&a).wrapping_add(get(&b)));
+    }
+}
+
+#[test]
+fn test_bitsliced_circuit() {
+    // An exhaustive check of a 4-bit adder
+    let add = SubcircuitTemplate::capture(8, |graph, inputs| {
+        let mut sys = BinaryEmulate::new(std::mem::take(graph));
+        let a: [Node; 8] = std::array::from_fn(|i| match i {
+            0..=3 => inputs[i],
+            _ => SystemRepr::<bool>::constant(&mut sys, false),
+        });
+        let b: [Node; 8] = std::array::from_fn(|i| match i {
+            0..=3 => inputs[4 + i],
+            _ => SystemRepr::<bool>::constant(&mut sys, false),
+        });
+        let r = SystemWrappingAdd::<u8>::wrapping_add(&mut sys, &a, &b);
+        *graph = sys.into_source();
+        r[..5].to_vec()
+    });
+    let circuit = BitslicedCircuit::new(&add);
+    assert_eq!((circuit.num_inputs(), circuit.num_outputs()), (8, 5));
+    let outputs = circuit.run_exhaustive();
+    assert_eq!(outputs.len(), 256);
+    for (input, output) in outputs.iter().enumerate() {
+        let expected = (input & 15) + (input >> 4);
+        let expected: Vec<bool> = (0..5).map(|i| (expected >> i) & 1 == 1).collect();
+        assert_eq!(*output, expected);
+    }
+
+    // A batch which spans multiple blocks, compared against direct evaluation
+    let instances: Vec<Vec<bool>> = (0..100u64)
+        .map(|i| crate::system::bits_of::<8>(i * 37 + 11).to_vec())
+        .collect();
+    let outputs = circuit.run_batch(&instances);
+    for (instance, output) in instances.iter().zip(outputs.iter()) {
+        assert_eq!(*output, add.instantiate(&mut Eval, instance));
+    }
+}
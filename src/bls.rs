@@ -0,0 +1,107 @@
+//! A gadget for verifying aggregate BLS signatures, such as those produced by Ethereum sync
+//! committees.
+//!
+//! Following Ethereum, public keys are in the first group of the curve and signatures are in the
+//! second. The gadget is generic over [`SystemPairing`], so it inherits its limitations: there
+//! is not yet an implementation of the pairing using non-native field arithmetic. Messages are
+//! given already hashed to the second group, since there is not yet a hash-to-curve gadget.
+use crate::groth16::{Pairing, SystemPairing};
+use crate::*;
+
+/// A system in which aggregate BLS signatures over the curve `E` can be verified.
+pub trait SystemBls<E: Pairing>:
+    SystemPairing<E> + SystemSelect<E::G1> + SystemBitAnd<bool> + SystemBitOr<bool>
+{
+}
+
+impl<
+        E: Pairing,
+        S: SystemPairing<E> + SystemSelect<E::G1> + SystemBitAnd<bool> + SystemBitOr<bool>,
+    > SystemBls<E> for S
+{
+}
+
+/// Determines whether `signature` is a valid aggregate signature of a message, hashed to the
+/// point `message`, by the public keys whose bits are set in `participation`. This checks that
+/// `e(P, H(m)) = e(G, S)`, where `P` is the sum of the participating keys and `G` is the given
+/// generator of the first group. At least one key must participate, since an empty aggregate
+/// would accept the identity as a signature of any message.
+///
+/// The keys are assumed to be valid, with proofs of possession checked outside the circuit, as
+/// is the case for sync committees. Counting the participants, for example to check for a
+/// supermajority, is left to the caller.
+pub fn bls_verify_aggregate<S: SystemBls<E> + ?Sized, E: Pairing>(
+    sys: &mut S,
+    generator: &E::G1,
+    pubkeys: &[Abstract<S, E::G1>],
+    participation: &[Abstract<S, bool>],
+    message: &Abstract<S, E::G2>,
+    signature: &Abstract<S, E::G2>,
+) -> Abstract<S, bool> {
+    assert!(!pubkeys.is_empty(), "there must be at least one public key");
+    assert_eq!(
+        pubkeys.len(),
+        participation.len(),
+        "participation must have one bit per public key"
+    );
+
+    // Start with the first key, then remove it at the end if it doesn't participate. This avoids
+    // needing a constant for the identity.
+    let mut apk = pubkeys[0].clone();
+    for (pubkey, bit) in pubkeys[1..].iter().zip(participation[1..].iter()) {
+        let sum = sys.g1_add(&apk, pubkey);
+        apk = SystemSelect::<E::G1>::select(sys, bit, &sum, &apk);
+    }
+    let neg_first = sys.g1_neg(&pubkeys[0]);
+    let without_first = sys.g1_add(&apk, &neg_first);
+    apk = SystemSelect::<E::G1>::select(sys, &participation[0], &apk, &without_first);
+    let mut any = participation[0].clone();
+    for bit in participation[1..].iter() {
+        any = SystemBitOr::<bool>::or(sys, &any, bit);
+    }
+
+    let generator = SystemRepr::<E::G1>::constant(sys, generator.clone());
+    let g1 = [apk, sys.g1_neg(&generator)];
+    let g2 = [message.clone(), signature.clone()];
+    let valid = sys.pairing_product_is_one(&g1, &g2);
+    SystemBitAnd::<bool>::and(sys, &valid, &any)
+}
+
+#[test]
+fn test_bls_verify_aggregate() {
+    use crate::field::FieldElement;
+    use crate::groth16::{ToyElement, ToyPairing};
+    use bls12_381::Scalar;
+    let x = |n: u64| FieldElement(Scalar::from(n));
+
+    // In the toy curve, a key is its secret times the generator, and a signature is the secret
+    // times the message point
+    let generator = x(3);
+    let message = x(101);
+    let secrets = [5, 7, 11, 13];
+    let pubkeys: Vec<ToyElement> = secrets.iter().map(|sk| &generator * &x(*sk)).collect();
+    let sign = |bits: &[bool]| {
+        (secrets.iter().zip(bits))
+            .filter(|(_, bit)| **bit)
+            .fold(x(0), |acc, (sk, _)| &acc + &(&message * &x(*sk)))
+    };
+    let verify = |bits: &[bool], signature: &ToyElement| {
+        bls_verify_aggregate::<_, ToyPairing>(
+            &mut Eval, &generator, &pubkeys, bits, &message, signature,
+        )
+    };
+    for bits in [
+        [true, true, true, true],
+        [true, false, true, false],
+        [false, true, false, false],
+        [false, false, false, true],
+    ] {
+        let signature = sign(&bits);
+        assert!(verify(&bits, &signature));
+        let mut other = bits;
+        other[1] = !other[1];
+        assert!(!verify(&other, &signature));
+    }
+    assert!(!verify(&[false; 4], &x(0)));
+    assert!(!verify(&[true; 4], &sign(&[true, true, true, false])));
+}
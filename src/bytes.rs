@@ -0,0 +1,557 @@
+//! Byte strings of abstract length.
+use crate::crypto::hash::SystemSha256Bytes;
+use crate::fsm::{Fsm, Guard};
+use crate::*;
+use array_init::array_init;
+
+/// A system in which byte strings of abstract length can be manipulated.
+pub trait SystemBytes:
+    BinarySystem
+    + SystemSha256Bytes
+    + SystemBits
+    + SystemSelect<bool>
+    + SystemSelect<u8>
+    + SystemSelect<u32>
+    + SystemBitXor<u8>
+    + SystemOrd<u32>
+{
+}
+
+impl<
+        S: BinarySystem
+            + SystemSha256Bytes
+            + SystemBits
+            + SystemSelect<bool>
+            + SystemSelect<u8>
+            + SystemSelect<u32>
+            + SystemBitXor<u8>
+            + SystemOrd<u32>,
+    > SystemBytes for S
+{
+}
+
+/// A byte string whose length is abstract, stored in a buffer of fixed capacity within a system
+/// of type `S`. Bytes of the buffer beyond the length are always zero, so strings of the same
+/// length are equal exactly when their buffers are.
+pub struct AbstractBytes<S: SystemRepr<u8> + SystemRepr<u32> + ?Sized> {
+    data: Vec<Abstract<S, u8>>,
+    len: Abstract<S, u32>,
+}
+
+impl<S: SystemRepr<u8> + SystemRepr<u32> + ?Sized> Clone for AbstractBytes<S> {
+    fn clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+            len: self.len.clone(),
+        }
+    }
+}
+
+impl<S: SystemRepr<u8> + SystemRepr<u32> + ?Sized> core::fmt::Debug for AbstractBytes<S>
+where
+    Abstract<S, u8>: core::fmt::Debug,
+    Abstract<S, u32>: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AbstractBytes")
+            .field("data", &self.data)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl<S: SystemBytes + ?Sized> AbstractBytes<S> {
+    /// Constructs an [`AbstractBytes`] from a buffer and a length. Bytes of the buffer beyond the
+    /// length are replaced with zeros. The length should not exceed the capacity of the buffer,
+    /// which can be checked with [`AbstractBytes::in_bounds`].
+    pub fn new(sys: &mut S, data: Vec<Abstract<S, u8>>, len: Abstract<S, u32>) -> Self {
+        let res = Self { data, len };
+        let mask = res.mask(sys);
+        let zero = SystemRepr::<u8>::constant(sys, 0);
+        let data = (res.data.iter().zip(mask.iter()))
+            .map(|(byte, keep)| SystemSelect::<u8>::select(sys, keep, byte, &zero))
+            .collect();
+        Self { data, len: res.len }
+    }
+
+    /// Constructs an [`AbstractBytes`] from a buffer and a length, without clearing the bytes
+    /// beyond the length. The caller must ensure that they are already zero.
+    pub(crate) fn from_raw(data: Vec<Abstract<S, u8>>, len: Abstract<S, u32>) -> Self {
+        Self { data, len }
+    }
+
+    /// Constructs an [`AbstractBytes`] for a constant string, with the given capacity.
+    pub fn from_const(sys: &mut S, value: &[u8], capacity: usize) -> Self {
+        assert!(value.len() <= capacity, "string exceeds capacity");
+        let data = (0..capacity)
+            .map(|i| sys.constant(value.get(i).copied().unwrap_or(0)))
+            .collect();
+        let len = sys.constant(value.len() as u32);
+        Self { data, len }
+    }
+
+    /// The maximum length of this string.
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// The length of this string.
+    pub fn len(&self) -> &Abstract<S, u32> {
+        &self.len
+    }
+
+    /// The underlying buffer of this string, padded with zeros up to its capacity.
+    pub fn data(&self) -> &[Abstract<S, u8>] {
+        &self.data
+    }
+
+    /// Determines whether the length of this string is within its capacity.
+    pub fn in_bounds(&self, sys: &mut S) -> Abstract<S, bool> {
+        let capacity = SystemRepr::<u32>::constant(sys, self.capacity() as u32);
+        SystemOrd::<u32>::le(sys, &self.len, &capacity)
+    }
+
+    /// Determines, for each position of the buffer, whether it is within the string.
+    pub fn mask(&self, sys: &mut S) -> Vec<Abstract<S, bool>> {
+        let bits = sys.bits_of_u32(&self.len);
+        let sel = one_hot(sys, &bits, self.capacity() + 1);
+        let mut ended = SystemRepr::<bool>::constant(sys, false);
+        let mut res = Vec::with_capacity(self.capacity());
+        for sel in &sel[..self.capacity()] {
+            ended = SystemBitOr::<bool>::or(sys, &ended, sel);
+            res.push(SystemNot::<bool>::not(sys, &ended));
+        }
+        res
+    }
+
+    /// Returns a copy of this string with a buffer extended to the given capacity.
+    pub fn with_capacity(&self, sys: &mut S, capacity: usize) -> Self {
+        assert!(capacity >= self.capacity(), "capacity can't be reduced");
+        let mut data = self.data.clone();
+        data.resize_with(capacity, || SystemRepr::<u8>::constant(sys, 0));
+        Self {
+            data,
+            len: self.len.clone(),
+        }
+    }
+
+    /// Extracts the substring of the given length starting at the given offset, with a buffer
+    /// of the given capacity. The result is valid if the substring lies within this string and
+    /// fits within the capacity. If it is not valid, the returned string is unspecified.
+    pub fn slice(
+        &self,
+        sys: &mut S,
+        start: &Abstract<S, u32>,
+        len: &Abstract<S, u32>,
+        capacity: usize,
+    ) -> (Self, Abstract<S, bool>) {
+        let mut data = shift(sys, &self.data, start, false);
+        data.resize_with(capacity, || SystemRepr::<u8>::constant(sys, 0));
+        let res = Self::new(sys, data, len.clone());
+        let end = SystemWrappingAdd::<u32>::wrapping_add(sys, start, len);
+        let no_overflow = SystemOrd::<u32>::le(sys, start, &end);
+        let within = SystemOrd::<u32>::le(sys, &end, &self.len);
+        let fits = res.in_bounds(sys);
+        let valid = SystemBitAnd::<bool>::and(sys, &no_overflow, &within);
+        let valid = SystemBitAnd::<bool>::and(sys, &valid, &fits);
+        (res, valid)
+    }
+
+    /// Concatenates this string with another. The capacity of the result is the sum of the
+    /// capacities of the inputs.
+    pub fn concat(&self, sys: &mut S, other: &Self) -> Self {
+        let capacity = self.capacity() + other.capacity();
+        let mut tail = other.data.clone();
+        tail.resize_with(capacity, || SystemRepr::<u8>::constant(sys, 0));
+        let tail = shift(sys, &tail, &self.len, true);
+        let head = self.with_capacity(sys, capacity).data;
+        let data = (head.iter().zip(tail.iter()))
+            .map(|(a, b)| SystemBitXor::<u8>::xor(sys, a, b))
+            .collect();
+        let len = SystemWrappingAdd::<u32>::wrapping_add(sys, &self.len, &other.len);
+        Self { data, len }
+    }
+
+    /// Determines whether this string is equal to another. The cost of this depends only on the
+    /// capacities of the strings, not their contents.
+    pub fn ct_eq(&self, sys: &mut S, other: &Self) -> Abstract<S, bool> {
+        let a = sys.bits_of_u32(&self.len);
+        let b = sys.bits_of_u32(&other.len);
+        let len_eq = eq_bits(sys, &a, &b);
+        let capacity = self.capacity().max(other.capacity());
+        let a = self.with_capacity(sys, capacity);
+        let b = other.with_capacity(sys, capacity);
+        let data_eq = eq_bytes(sys, &a.data, &b.data);
+        SystemBitAnd::<bool>::and(sys, &len_eq, &data_eq)
+    }
+
+    /// Determines whether this string begins with another. The cost of this depends only on the
+    /// capacities of the strings, not their contents.
+    pub fn starts_with(&self, sys: &mut S, prefix: &Self) -> Abstract<S, bool> {
+        let mut res = SystemOrd::<u32>::le(sys, &prefix.len, &self.len);
+        let mask = prefix.mask(sys);
+        let zero = SystemRepr::<u8>::constant(sys, 0);
+        for (i, (byte, keep)) in prefix.data.iter().zip(mask.iter()).enumerate() {
+            // Beyond the capacity of this string, the prefix must have ended
+            let other = self.data.get(i).unwrap_or(&zero);
+            let a = sys.bits_of_u8(byte);
+            let b = sys.bits_of_u8(other);
+            let eq = eq_bits(sys, &a, &b);
+            let skip = SystemNot::<bool>::not(sys, keep);
+            let ok = SystemBitOr::<bool>::or(sys, &eq, &skip);
+            res = SystemBitAnd::<bool>::and(sys, &res, &ok);
+        }
+        res
+    }
+
+    /// Searches this string for the first occurrence of a constant pattern, returning whether it
+    /// was found and, if so, the position at which it begins. If it was not found, the position
+    /// is zero. The pattern is compared at every offset of the buffer, so the cost of this
+    /// depends only on the capacity of the string and the length of the pattern.
+    pub fn find(&self, sys: &mut S, pattern: &[u8]) -> (Abstract<S, bool>, Abstract<S, u32>) {
+        let mut found = SystemRepr::<bool>::constant(sys, false);
+        let mut pos = SystemRepr::<u32>::constant(sys, 0);
+        if pattern.len() > self.capacity() {
+            return (found, pos);
+        }
+
+        // Search from the end, so that earlier occurrences take precedence
+        let pattern: Vec<_> = (pattern.iter())
+            .map(|byte| SystemRepr::<u8>::constant(sys, *byte))
+            .collect();
+        for i in (0..=self.capacity() - pattern.len()).rev() {
+            let end = SystemRepr::<u32>::constant(sys, (i + pattern.len()) as u32);
+            let within = SystemOrd::<u32>::le(sys, &end, &self.len);
+            let eq = eq_bytes(sys, &self.data[i..i + pattern.len()], &pattern);
+            let here = SystemBitAnd::<bool>::and(sys, &within, &eq);
+            found = SystemBitOr::<bool>::or(sys, &found, &here);
+            let i = SystemRepr::<u32>::constant(sys, i as u32);
+            pos = SystemSelect::<u32>::select(sys, &here, &i, &pos);
+        }
+        (found, pos)
+    }
+
+    /// Determines whether this string contains a constant pattern. See [`AbstractBytes::find`].
+    pub fn contains(&self, sys: &mut S, pattern: &[u8]) -> Abstract<S, bool> {
+        self.find(sys, pattern).0
+    }
+
+    /// Applies SHA-256 padding to this string, appending a `0x80` byte, then zeros, then the
+    /// big-endian bit length of the string, so that the result is a whole number of 64-byte
+    /// blocks.
+    pub fn pad_sha256(&self, sys: &mut S) -> Self {
+        let capacity = (self.capacity() + 9).next_multiple_of(64);
+        let zero = SystemRepr::<u8>::constant(sys, 0);
+
+        // Place the terminator and the bit length at the start of a buffer, then shift them into
+        // position. These never overlap the string, so they can be combined with it using xor.
+        let mut marker = vec![zero.clone(); capacity];
+        marker[0] = sys.constant(0x80u8);
+        let marker = shift(sys, &marker, &self.len, true);
+        let bit_len = sys.shl(&self.len, 3);
+        let high = sys.shr(&self.len, 29);
+        let mut trailer = vec![zero.clone(); capacity];
+        trailer[3] = sys.unpack_be_u32(&high)[3].clone();
+        trailer[4..8].clone_from_slice(&sys.unpack_be_u32(&bit_len));
+        let offset = SystemRepr::<u32>::constant(sys, 72);
+        let len = SystemWrappingAdd::<u32>::wrapping_add(sys, &self.len, &offset);
+        let mask = SystemRepr::<u32>::constant(sys, !63);
+        let len = SystemBitAnd::<u32>::and(sys, &len, &mask);
+        let offset = SystemRepr::<u32>::constant(sys, -8i32 as u32);
+        let start = SystemWrappingAdd::<u32>::wrapping_add(sys, &len, &offset);
+        let trailer = shift(sys, &trailer, &start, true);
+
+        // Combine
+        let head = self.with_capacity(sys, capacity).data;
+        let data = (head.iter().zip(marker.iter()).zip(trailer.iter()))
+            .map(|((a, b), c)| {
+                let t = SystemBitXor::<u8>::xor(sys, a, b);
+                SystemBitXor::<u8>::xor(sys, &t, c)
+            })
+            .collect();
+        Self { data, len }
+    }
+
+    /// Computes the SHA-256 digest of this string. The cost of this depends only on the capacity
+    /// of the string, not its length.
+    pub fn sha256(&self, sys: &mut S) -> [Abstract<S, u8>; 32] {
+        let padded = self.pad_sha256(sys);
+        let num_blocks = sys.shr(&padded.len, 6);
+        let num_blocks = sys.bits_of_u32(&num_blocks);
+        let sel = one_hot(sys, &num_blocks, padded.capacity() / 64 + 1);
+        let mut hasher = sys.sha256_new();
+        let mut digest = hasher.clone();
+        for (i, chunk) in padded.data.chunks(64).enumerate() {
+            let words: [_; 16] = array_init(|j| {
+                let bytes: [_; 4] = array_init(|k| chunk[j * 4 + k].clone());
+                sys.pack_be_u32(&bytes)
+            });
+            sys.sha256_update_abstract(&mut hasher, &words);
+            for (d, h) in digest.iter_mut().zip(hasher.iter()) {
+                *d = SystemSelect::<u32>::select(sys, &sel[i + 1], h, d);
+            }
+        }
+        let mut res = Vec::with_capacity(32);
+        for word in digest.iter() {
+            res.extend(sys.unpack_be_u32(word));
+        }
+        array_init(|i| res[i].clone())
+    }
+}
+
+impl<S: SystemBytes + SystemOrd<u8> + ?Sized> AbstractBytes<S> {
+    /// Determines whether this string is valid UTF-8, rejecting overlong encodings, surrogates
+    /// and code points beyond U+10FFFF. The cost of this depends only on the capacity of the
+    /// string, not its length.
+    pub fn is_utf8(&self, sys: &mut S) -> Abstract<S, bool> {
+        // The padding consists of zeros, which are valid ASCII following a complete string but
+        // invalid following a truncated sequence, so the whole buffer can be checked at once
+        let fsm = utf8_fsm();
+        let state = fsm.run_abstract(sys, &self.data);
+        fsm.accepts(sys, &state)
+    }
+
+    /// Asserts that this string is valid UTF-8. See [`AbstractBytes::is_utf8`].
+    pub fn assert_utf8(&self, sys: &mut S)
+    where
+        S: SystemAssert,
+    {
+        let valid = self.is_utf8(sys);
+        sys.assert(&valid)
+    }
+
+    /// Converts the ASCII letters of this string to lowercase, leaving other bytes unchanged.
+    /// Bytes of multi-byte UTF-8 sequences are never ASCII, so this preserves valid UTF-8, and
+    /// can be used to compare identifiers case-insensitively.
+    pub fn to_ascii_lowercase(&self, sys: &mut S) -> Self {
+        let lo = SystemRepr::<u8>::constant(sys, b'A');
+        let hi = SystemRepr::<u8>::constant(sys, b'Z');
+        let data = (self.data.iter())
+            .map(|byte| {
+                let above = SystemOrd::<u8>::le(sys, &lo, byte);
+                let below = SystemOrd::<u8>::le(sys, byte, &hi);
+                let upper = SystemBitAnd::<bool>::and(sys, &above, &below);
+                let mut bits = sys.bits_of_u8(byte);
+                bits[5] = SystemBitXor::<bool>::xor(sys, &bits[5], &upper);
+                sys.u8_of_bits(&bits)
+            })
+            .collect();
+        Self {
+            data,
+            len: self.len.clone(),
+        }
+    }
+}
+
+/// Constructs a state machine which accepts exactly the valid UTF-8 strings, following the
+/// table of well-formed byte sequences in the Unicode standard.
+fn utf8_fsm() -> Fsm {
+    let mut fsm = Fsm::new();
+    let start = fsm.state("start");
+    let cont_1 = fsm.state("1 continuation");
+    let cont_2 = fsm.state("2 continuations");
+    let cont_3 = fsm.state("3 continuations");
+    let after_e0 = fsm.state("after E0");
+    let after_ed = fsm.state("after ED");
+    let after_f0 = fsm.state("after F0");
+    let after_f4 = fsm.state("after F4");
+    fsm.accept(start);
+    fsm.transition(start, start, 0x00..=0x7f);
+    fsm.transition(start, cont_1, 0xc2..=0xdf);
+    fsm.transition(start, after_e0, 0xe0);
+    fsm.transition(start, cont_2, Guard::from(0xe1..=0xec).or(0xee..=0xef));
+    fsm.transition(start, after_ed, 0xed);
+    fsm.transition(start, after_f0, 0xf0);
+    fsm.transition(start, cont_3, 0xf1..=0xf3);
+    fsm.transition(start, after_f4, 0xf4);
+    fsm.transition(cont_1, start, 0x80..=0xbf);
+    fsm.transition(cont_2, cont_1, 0x80..=0xbf);
+    fsm.transition(cont_3, cont_2, 0x80..=0xbf);
+    fsm.transition(after_e0, cont_1, 0xa0..=0xbf);
+    fsm.transition(after_ed, cont_1, 0x80..=0x9f);
+    fsm.transition(after_f0, cont_2, 0x90..=0xbf);
+    fsm.transition(after_f4, cont_2, 0x80..=0x8f);
+    fsm
+}
+
+/// Shifts a buffer towards higher indices (if `right`) or lower indices by an abstract amount,
+/// filling vacated positions with zeros.
+fn shift<S: SystemBytes + ?Sized>(
+    sys: &mut S,
+    data: &[Abstract<S, u8>],
+    amount: &Abstract<S, u32>,
+    right: bool,
+) -> Vec<Abstract<S, u8>> {
+    let zero = SystemRepr::<u8>::constant(sys, 0);
+    let bits = sys.bits_of_u32(amount);
+    let mut res = data.to_vec();
+    let mut overflow = SystemRepr::<bool>::constant(sys, false);
+    for (i, bit) in bits.iter().enumerate() {
+        let step = 1usize << i;
+        if step >= data.len() {
+            overflow = SystemBitOr::<bool>::or(sys, &overflow, bit);
+            continue;
+        }
+        res = (0..res.len())
+            .map(|j| {
+                let source = if right {
+                    j.checked_sub(step)
+                } else {
+                    j.checked_add(step)
+                };
+                let shifted = source.and_then(|k| res.get(k)).unwrap_or(&zero);
+                SystemSelect::<u8>::select(sys, bit, shifted, &res[j])
+            })
+            .collect();
+    }
+    res.iter()
+        .map(|byte| SystemSelect::<u8>::select(sys, &overflow, &zero, byte))
+        .collect()
+}
+
+/// Determines whether two byte strings of the same length are equal. The cost of this depends
+/// only on the length of the strings, not their contents.
+pub fn eq_bytes<S: SystemBytes + ?Sized>(
+    sys: &mut S,
+    a: &[Abstract<S, u8>],
+    b: &[Abstract<S, u8>],
+) -> Abstract<S, bool> {
+    assert_eq!(a.len(), b.len(), "strings must have the same length");
+    let mut res = SystemRepr::<bool>::constant(sys, true);
+    for (a, b) in a.iter().zip(b) {
+        let a = sys.bits_of_u8(a);
+        let b = sys.bits_of_u8(b);
+        let eq = eq_bits(sys, &a, &b);
+        res = SystemBitAnd::<bool>::and(sys, &res, &eq);
+    }
+    res
+}
+
+#[test]
+fn test_bytes() {
+    let hello = AbstractBytes::from_const(&mut Eval, b"hello", 8);
+    let world = AbstractBytes::from_const(&mut Eval, b", world", 10);
+    let joined = hello.concat(&mut Eval, &world);
+    assert_eq!(joined.capacity(), 18);
+    assert_eq!(*joined.len(), 12);
+    assert_eq!(joined.data()[..12], b"hello, world"[..]);
+    assert!(joined.data()[12..].iter().all(|b| *b == 0));
+    let expected = AbstractBytes::from_const(&mut Eval, b"hello, world", 12);
+    assert!(joined.ct_eq(&mut Eval, &expected));
+    assert!(!joined.ct_eq(&mut Eval, &hello));
+
+    // Slicing
+    let (sub, valid) = joined.slice(&mut Eval, &7, &5, 6);
+    assert!(valid);
+    assert!(sub.ct_eq(
+        &mut Eval,
+        &AbstractBytes::from_const(&mut Eval, b"world", 5)
+    ));
+    let (_, valid) = joined.slice(&mut Eval, &8, &5, 6);
+    assert!(!valid);
+    let (_, valid) = joined.slice(&mut Eval, &0, &7, 6);
+    assert!(!valid);
+
+    // Bytes beyond the length are cleared
+    let raw = b"abcdef".to_vec();
+    let bytes = AbstractBytes::new(&mut Eval, raw, 3);
+    assert_eq!(bytes.data(), b"abc\0\0\0");
+    assert!(bytes.in_bounds(&mut Eval));
+    assert!(!AbstractBytes::new(&mut Eval, b"abc".to_vec(), 4).in_bounds(&mut Eval));
+}
+
+#[test]
+fn test_bytes_sha256() {
+    for len in [0, 3, 55, 56, 64, 100] {
+        let data: Vec<u8> = (0..len as u8).collect();
+        let expected = Eval.sha256_bytes(&data);
+        let bytes = AbstractBytes::from_const(&mut Eval, &data, 120);
+        assert_eq!(bytes.pad_sha256(&mut Eval).capacity(), 192);
+        assert_eq!(bytes.sha256(&mut Eval), expected);
+    }
+    let mut sys = BinaryEmulate::new(Eval);
+    let bytes = AbstractBytes::from_const(&mut sys, b"abc", 70);
+    let digest = bytes.sha256(&mut sys);
+    let expected = Eval.sha256_bytes(b"abc");
+    assert_eq!(digest, expected.map(|b| sys.constant(b)));
+}
+
+#[test]
+fn test_bytes_utf8() {
+    let cases: [&[u8]; 12] = [
+        b"",
+        b"hello",
+        "caf\u{e9}".as_bytes(),
+        "\u{20ac}\u{10348}\u{10ffff}".as_bytes(),
+        b"\xc3",
+        b"\xc0\xaf",
+        b"\xe0\x80\xaf",
+        b"\xed\xa0\x80",
+        b"\xf4\x90\x80\x80",
+        b"\x80",
+        b"ab\xe2\x82",
+        b"\xf0\x9f\x98\x80",
+    ];
+    for case in cases {
+        let expected = core::str::from_utf8(case).is_ok();
+        let bytes = AbstractBytes::from_const(&mut Eval, case, case.len());
+        assert_eq!(bytes.is_utf8(&mut Eval), expected);
+        let bytes = AbstractBytes::from_const(&mut Eval, case, 12);
+        assert_eq!(bytes.is_utf8(&mut Eval), expected);
+        let mut sys = BinaryEmulate::new(Eval);
+        let bytes = AbstractBytes::from_const(&mut sys, case, 12);
+        assert_eq!(bytes.is_utf8(&mut sys), expected);
+        if expected {
+            bytes.assert_utf8(&mut sys);
+        }
+    }
+
+    // Case folding
+    let mixed = "Hello, W\u{d6}RLD@[`z".as_bytes();
+    let bytes = AbstractBytes::from_const(&mut Eval, mixed, 24);
+    let lower = bytes.to_ascii_lowercase(&mut Eval);
+    assert_eq!(*lower.len(), mixed.len() as u32);
+    assert_eq!(lower.data()[..mixed.len()], mixed.to_ascii_lowercase()[..]);
+    assert!(lower.data()[mixed.len()..].iter().all(|b| *b == 0));
+    let mut sys = BinaryEmulate::new(Eval);
+    let bytes = AbstractBytes::from_const(&mut sys, b"ID-42X", 6);
+    let lower = bytes.to_ascii_lowercase(&mut sys);
+    let expected = AbstractBytes::from_const(&mut sys, b"id-42x", 6);
+    assert!(lower.ct_eq(&mut sys, &expected));
+}
+
+#[test]
+fn test_bytes_search() {
+    let text = AbstractBytes::from_const(&mut Eval, b"user=alice;role=admin", 32);
+    let prefix = AbstractBytes::from_const(&mut Eval, b"user=", 8);
+    assert!(text.starts_with(&mut Eval, &prefix));
+    let other = AbstractBytes::from_const(&mut Eval, b"role=", 8);
+    assert!(!text.starts_with(&mut Eval, &other));
+    assert!(text.starts_with(&mut Eval, &text));
+    let empty = AbstractBytes::from_const(&mut Eval, b"", 4);
+    assert!(text.starts_with(&mut Eval, &empty));
+    let short = AbstractBytes::from_const(&mut Eval, b"user", 4);
+    assert!(!short.starts_with(&mut Eval, &prefix));
+    let long = AbstractBytes::from_const(&mut Eval, b"user=alice;role=admin!", 40);
+    assert!(!text.starts_with(&mut Eval, &long));
+    assert!(long.starts_with(&mut Eval, &text));
+
+    assert_eq!(text.find(&mut Eval, b"role="), (true, 11));
+    assert_eq!(text.find(&mut Eval, b"="), (true, 4));
+    assert_eq!(text.find(&mut Eval, b"admin"), (true, 16));
+    assert_eq!(text.find(&mut Eval, b"root"), (false, 0));
+    assert!(text.contains(&mut Eval, b""));
+
+    // Matches must lie within the string, even though the padding is zero
+    let padded = AbstractBytes::from_const(&mut Eval, b"ab", 6);
+    assert!(!padded.contains(&mut Eval, b"b\0"));
+    assert!(!padded.contains(&mut Eval, b"abcdefg"));
+
+    let mut sys = BinaryEmulate::new(Eval);
+    let text = AbstractBytes::from_const(&mut sys, b"x-y-z", 8);
+    let (found, pos) = text.find(&mut sys, b"-z");
+    assert!(found);
+    assert_eq!(SystemRead::<u32>::read_value(&sys, &pos), 3);
+    let prefix = AbstractBytes::from_const(&mut sys, b"x-", 3);
+    assert!(text.starts_with(&mut sys, &prefix));
+}
@@ -0,0 +1,191 @@
+use crate::fingerprint::CircuitFingerprint;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// A circuit which can be stored in a [`CircuitCache`], along with handles into it.
+pub trait CachedCircuit: Sized {
+    /// Identifies a value within the circuit, such as an input or output, which should be
+    /// restored along with it.
+    type Handle;
+
+    /// Writes this circuit and the given handles to a stream.
+    fn write_cached(&self, writer: impl Write, handles: &[Self::Handle]) -> io::Result<()>;
+
+    /// Reads a circuit and its handles from a stream written by
+    /// [`CachedCircuit::write_cached`].
+    fn read_cached(reader: impl Read) -> io::Result<(Self, Vec<Self::Handle>)>;
+}
+
+#[cfg(feature = "graph")]
+impl CachedCircuit for crate::graph::Graph {
+    type Handle = crate::graph::Node;
+    fn write_cached(&self, writer: impl Write, handles: &[Self::Handle]) -> io::Result<()> {
+        self.write_checkpoint(writer, handles)
+    }
+
+    fn read_cached(reader: impl Read) -> io::Result<(Self, Vec<Self::Handle>)> {
+        Self::resume(reader)
+    }
+}
+
+#[cfg(feature = "r1cs")]
+impl<F: ff::PrimeField> CachedCircuit for crate::r1cs::ArithmeticSystem<F> {
+    type Handle = crate::r1cs::Formula;
+    fn write_cached(&self, writer: impl Write, handles: &[Self::Handle]) -> io::Result<()> {
+        self.save(writer, handles)
+    }
+
+    fn read_cached(reader: impl Read) -> io::Result<(Self, Vec<Self::Handle>)> {
+        Self::load(reader)
+    }
+}
+
+/// Identifies a circuit in a [`CircuitCache`] by the gadget which builds it and the parameters
+/// it is built with. Keys also include the version of this crate, so that circuits are rebuilt
+/// whenever the gadgets may have changed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    /// Constructs a [`CacheKey`] for the given gadget, with no parameters.
+    pub fn new(gadget: &str) -> Self {
+        CacheKey(format!("circus {} {}", env!("CARGO_PKG_VERSION"), gadget))
+    }
+
+    /// Adds a parameter to this key. Circuits built with different values for any parameter
+    /// are stored separately.
+    pub fn param(mut self, name: &str, value: impl fmt::Display) -> Self {
+        self.0.push_str(&format!(" {}={}", name, value));
+        self
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A cache of built circuits on disk, so that tools which repeatedly build the same circuits
+/// don't need to synthesize them each time they run. Each circuit is stored in its own file
+/// within a directory, named by a hash of its [`CacheKey`] and its type.
+#[derive(Debug, Clone)]
+pub struct CircuitCache {
+    dir: PathBuf,
+}
+
+impl CircuitCache {
+    /// Constructs a [`CircuitCache`] which stores circuits in the given directory. The
+    /// directory is created when the first circuit is stored.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory where circuits are stored.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Gets the path of the file where the circuit of type `C` with the given key is stored.
+    pub fn path<C: CachedCircuit>(&self, key: &CacheKey) -> PathBuf {
+        let name = format!("{} {}", key, std::any::type_name::<C>());
+        let hash = CircuitFingerprint::of(name.as_bytes());
+        self.dir.join(format!("{}.circuit", hash))
+    }
+
+    /// Gets the circuit with the given key from the cache, or builds it using `build` and
+    /// stores it if it isn't present. Files which can't be read as circuits of the expected type
+    /// are replaced. Returns the circuit along with its handles.
+    pub fn get_or_build<C: CachedCircuit>(
+        &self,
+        key: &CacheKey,
+        build: impl FnOnce() -> (C, Vec<C::Handle>),
+    ) -> io::Result<(C, Vec<C::Handle>)> {
+        let path = self.path::<C>(key);
+        match fs::File::open(&path) {
+            Ok(file) => match C::read_cached(BufReader::new(file)) {
+                Ok(res) => return Ok(res),
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof
+                    ) => {}
+                Err(err) => return Err(err),
+            },
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            Err(_) => (),
+        }
+        let (circuit, handles) = build();
+        self.store(&path, &circuit, &handles)?;
+        Ok((circuit, handles))
+    }
+
+    /// Writes a circuit to the given path. The circuit is written to a temporary file first, so
+    /// that other processes never see a partially-written circuit.
+    fn store<C: CachedCircuit>(
+        &self,
+        path: &Path,
+        circuit: &C,
+        handles: &[C::Handle],
+    ) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let temp = path.with_extension(format!("tmp{}", std::process::id()));
+        let mut writer = BufWriter::new(fs::File::create(&temp)?);
+        circuit.write_cached(&mut writer, handles)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&temp, path)
+    }
+}
+
+#[cfg(all(feature = "graph", feature = "r1cs"))]
+#[test]
+fn test_circuit_cache() {
+    use crate::graph::Graph;
+    use crate::r1cs::ArithmeticSystem;
+    use crate::*;
+    use bls12_381::Scalar;
+
+    let dir = std::env::temp_dir().join(format!("circus-cache-test-{}", std::process::id()));
+    let cache = CircuitCache::new(&dir);
+    let build = |rounds: usize| {
+        let mut graph = Graph::new();
+        let mut x = graph.input();
+        let y = graph.input();
+        for _ in 0..rounds {
+            x = SystemBitXor::<bool>::xor(&mut graph, &x, &y);
+            x = SystemNot::<bool>::not(&mut graph, &x);
+        }
+        (graph, vec![x])
+    };
+
+    // The circuit is only built the first time it is requested
+    let key = CacheKey::new("test").param("rounds", 3);
+    let mut builds = 0;
+    let (graph, handles) = cache
+        .get_or_build(&key, || {
+            builds += 1;
+            build(3)
+        })
+        .unwrap();
+    let (cached, cached_handles) = cache
+        .get_or_build(&key, || -> (Graph, _) { unreachable!() })
+        .unwrap();
+    assert_eq!(builds, 1);
+    assert_eq!(cached.fingerprint(), graph.fingerprint());
+    assert_eq!(cached_handles, handles);
+
+    // Different parameters and circuit types are stored separately
+    let other = CacheKey::new("test").param("rounds", 4);
+    assert_ne!(cache.path::<Graph>(&other), cache.path::<Graph>(&key));
+    let r1cs_path = cache.path::<ArithmeticSystem<Scalar>>(&key);
+    assert_ne!(r1cs_path, cache.path::<Graph>(&key));
+
+    // Corrupted files are rebuilt
+    fs::write(cache.path::<Graph>(&key), b"garbage").unwrap();
+    let (rebuilt, _) = cache.get_or_build(&key, || build(3)).unwrap();
+    assert_eq!(rebuilt.fingerprint(), graph.fingerprint());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,399 @@
+//! A C ABI for generating witnesses of serialized circuits, so that provers written in other
+//! languages can embed the witness generator. A circuit is a [`SubcircuitTemplate`] serialized
+//! with [`SubcircuitTemplate::write`]. To use these functions from C, link this crate into a
+//! `staticlib` or `cdylib`.
+//!
+//! A typical session loads a circuit with [`circus_circuit_load`], creates a witness for it with
+//! [`circus_witness_new`], sets each input with [`circus_witness_set_input`], calls
+//! [`circus_witness_generate`], and then reads outputs and wire values. Every object must be
+//! released with the corresponding `free` function. The wires of a witness are the nodes of the
+//! circuit once fully flattened, in the same order as [`Graph::nodes`](crate::graph::Graph::nodes).
+use crate::graph::{Node, SubcircuitTemplate};
+use crate::*;
+use std::rc::Rc;
+
+/// The result of a fallible C ABI function.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircusStatus {
+    /// The function succeeded.
+    Ok = 0,
+
+    /// A required pointer argument was null.
+    NullPointer = 1,
+
+    /// An input, output or wire index was out of range.
+    OutOfRange = 2,
+
+    /// Outputs or wires were requested from a witness before it was generated, or since an
+    /// input was changed.
+    NotGenerated = 3,
+}
+
+/// A circuit loaded with [`circus_circuit_load`].
+pub struct CircusCircuit {
+    template: Rc<SubcircuitTemplate>,
+}
+
+/// An assignment of inputs to a [`CircusCircuit`], along with the values of its wires once
+/// generated.
+pub struct CircusWitness {
+    template: Rc<SubcircuitTemplate>,
+    inputs: Vec<bool>,
+    wires: Option<Vec<bool>>,
+}
+
+/// Loads a circuit from `len` bytes at `data`, as written by [`SubcircuitTemplate::write`].
+/// Returns null if the data is not a valid circuit, or exceeds the limits on nesting and size
+/// checked by [`Graph::resume`](crate::graph::Graph::resume).
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn circus_circuit_load(data: *const u8, len: usize) -> *mut CircusCircuit {
+    if data.is_null() {
+        return std::ptr::null_mut();
+    }
+    let data = std::slice::from_raw_parts(data, len);
+    match SubcircuitTemplate::read(data) {
+        Ok(template) => Box::into_raw(Box::new(CircusCircuit {
+            template: Rc::new(template.flatten()),
+        })),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Releases a circuit. Witnesses created from it remain valid.
+///
+/// # Safety
+/// `circuit` must be null or a pointer returned by [`circus_circuit_load`] which has not been
+/// released.
+#[no_mangle]
+pub unsafe extern "C" fn circus_circuit_free(circuit: *mut CircusCircuit) {
+    if !circuit.is_null() {
+        drop(Box::from_raw(circuit));
+    }
+}
+
+/// The number of inputs to a circuit.
+///
+/// # Safety
+/// `circuit` must be a valid pointer returned by [`circus_circuit_load`].
+#[no_mangle]
+pub unsafe extern "C" fn circus_circuit_num_inputs(circuit: *const CircusCircuit) -> usize {
+    (*circuit).template.num_inputs()
+}
+
+/// The number of outputs of a circuit.
+///
+/// # Safety
+/// `circuit` must be a valid pointer returned by [`circus_circuit_load`].
+#[no_mangle]
+pub unsafe extern "C" fn circus_circuit_num_outputs(circuit: *const CircusCircuit) -> usize {
+    (*circuit).template.num_outputs()
+}
+
+/// The number of wires in a witness for a circuit.
+///
+/// # Safety
+/// `circuit` must be a valid pointer returned by [`circus_circuit_load`].
+#[no_mangle]
+pub unsafe extern "C" fn circus_circuit_num_wires(circuit: *const CircusCircuit) -> usize {
+    (*circuit).template.graph().nodes().len()
+}
+
+/// Creates a witness for a circuit, with every input initially false. Returns null if `circuit`
+/// is null.
+///
+/// # Safety
+/// `circuit` must be null or a valid pointer returned by [`circus_circuit_load`].
+#[no_mangle]
+pub unsafe extern "C" fn circus_witness_new(circuit: *const CircusCircuit) -> *mut CircusWitness {
+    if circuit.is_null() {
+        return std::ptr::null_mut();
+    }
+    let template = (*circuit).template.clone();
+    Box::into_raw(Box::new(CircusWitness {
+        inputs: vec![false; template.num_inputs()],
+        template,
+        wires: None,
+    }))
+}
+
+/// Releases a witness.
+///
+/// # Safety
+/// `witness` must be null or a pointer returned by [`circus_witness_new`] which has not been
+/// released.
+#[no_mangle]
+pub unsafe extern "C" fn circus_witness_free(witness: *mut CircusWitness) {
+    if !witness.is_null() {
+        drop(Box::from_raw(witness));
+    }
+}
+
+/// Sets the input at `index` to `value`. This invalidates any previously generated values.
+///
+/// # Safety
+/// `witness` must be null or a valid pointer returned by [`circus_witness_new`].
+#[no_mangle]
+pub unsafe extern "C" fn circus_witness_set_input(
+    witness: *mut CircusWitness,
+    index: usize,
+    value: bool,
+) -> CircusStatus {
+    let Some(witness) = witness.as_mut() else {
+        return CircusStatus::NullPointer;
+    };
+    let Some(input) = witness.inputs.get_mut(index) else {
+        return CircusStatus::OutOfRange;
+    };
+    *input = value;
+    witness.wires = None;
+    CircusStatus::Ok
+}
+
+/// Sets every input at once, from `len` bytes at `values`, where each nonzero byte is true.
+/// `len` must be the number of inputs to the circuit.
+///
+/// # Safety
+/// `witness` must be null or a valid pointer returned by [`circus_witness_new`], and `values`
+/// must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn circus_witness_set_inputs(
+    witness: *mut CircusWitness,
+    values: *const u8,
+    len: usize,
+) -> CircusStatus {
+    let Some(witness) = witness.as_mut() else {
+        return CircusStatus::NullPointer;
+    };
+    if values.is_null() {
+        return CircusStatus::NullPointer;
+    }
+    if len != witness.inputs.len() {
+        return CircusStatus::OutOfRange;
+    }
+    let values = std::slice::from_raw_parts(values, len);
+    for (input, value) in witness.inputs.iter_mut().zip(values) {
+        *input = *value != 0;
+    }
+    witness.wires = None;
+    CircusStatus::Ok
+}
+
+/// Computes the value of every wire of the circuit from the current inputs.
+///
+/// # Safety
+/// `witness` must be null or a valid pointer returned by [`circus_witness_new`].
+#[no_mangle]
+pub unsafe extern "C" fn circus_witness_generate(witness: *mut CircusWitness) -> CircusStatus {
+    let Some(witness) = witness.as_mut() else {
+        return CircusStatus::NullPointer;
+    };
+    let graph = witness.template.graph();
+    let nodes: Vec<_> = (0..graph.nodes().len()).map(Node::from_index).collect();
+    witness.wires = Some(graph.replay(&mut Eval, &witness.inputs, &nodes));
+    CircusStatus::Ok
+}
+
+/// Gets the value of the output at `index`, writing it to `value`.
+///
+/// # Safety
+/// `witness` must be null or a valid pointer returned by [`circus_witness_new`], and `value`
+/// must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn circus_witness_get_output(
+    witness: *const CircusWitness,
+    index: usize,
+    value: *mut bool,
+) -> CircusStatus {
+    let Some(witness) = witness.as_ref() else {
+        return CircusStatus::NullPointer;
+    };
+    let Some(node) = witness.template.outputs().get(index) else {
+        return CircusStatus::OutOfRange;
+    };
+    get_wire(witness, node.index(), value)
+}
+
+/// Writes the value of every output, as a byte which is 0 or 1, to `len` bytes at `values`.
+/// `len` must be the number of outputs of the circuit.
+///
+/// # Safety
+/// `witness` must be null or a valid pointer returned by [`circus_witness_new`], and `values`
+/// must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn circus_witness_get_outputs(
+    witness: *const CircusWitness,
+    values: *mut u8,
+    len: usize,
+) -> CircusStatus {
+    let Some(witness) = witness.as_ref() else {
+        return CircusStatus::NullPointer;
+    };
+    if values.is_null() {
+        return CircusStatus::NullPointer;
+    }
+    let Some(wires) = &witness.wires else {
+        return CircusStatus::NotGenerated;
+    };
+    let outputs = witness.template.outputs();
+    if len != outputs.len() {
+        return CircusStatus::OutOfRange;
+    }
+    let values = std::slice::from_raw_parts_mut(values, len);
+    for (value, node) in values.iter_mut().zip(outputs) {
+        *value = wires[node.index()] as u8;
+    }
+    CircusStatus::Ok
+}
+
+/// Gets the value of the wire at `index`, writing it to `value`.
+///
+/// # Safety
+/// `witness` must be null or a valid pointer returned by [`circus_witness_new`], and `value`
+/// must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn circus_witness_get_wire(
+    witness: *const CircusWitness,
+    index: usize,
+    value: *mut bool,
+) -> CircusStatus {
+    let Some(witness) = witness.as_ref() else {
+        return CircusStatus::NullPointer;
+    };
+    get_wire(witness, index, value)
+}
+
+/// Writes the value of the wire at `index` to `value`.
+unsafe fn get_wire(witness: &CircusWitness, index: usize, value: *mut bool) -> CircusStatus {
+    if value.is_null() {
+        return CircusStatus::NullPointer;
+    }
+    let Some(wires) = &witness.wires else {
+        return CircusStatus::NotGenerated;
+    };
+    let Some(wire) = wires.get(index) else {
+        return CircusStatus::OutOfRange;
+    };
+    *value = *wire;
+    CircusStatus::Ok
+}
+
+#[test]
+fn test_capi() {
+    let gadget = SubcircuitTemplate::capture(3, |graph, inputs| {
+        let ab = SystemBitXor::<bool>::xor(graph, &inputs[0], &inputs[1]);
+        let abc = SystemBitXor::<bool>::xor(graph, &ab, &inputs[2]);
+        let maj = SystemBitAnd::<bool>::and(graph, &ab, &inputs[2]);
+        vec![abc, maj]
+    });
+    let mut data = Vec::new();
+    gadget.write(&mut data).unwrap();
+    unsafe {
+        assert!(circus_circuit_load(data.as_ptr(), 3).is_null());
+        let circuit = circus_circuit_load(data.as_ptr(), data.len());
+        assert!(!circuit.is_null());
+        assert_eq!(circus_circuit_num_inputs(circuit), 3);
+        assert_eq!(circus_circuit_num_outputs(circuit), 2);
+        let witness = circus_witness_new(circuit);
+        circus_circuit_free(circuit);
+
+        let mut value = false;
+        assert_eq!(
+            circus_witness_get_output(witness, 0, &mut value),
+            CircusStatus::NotGenerated
+        );
+        assert_eq!(
+            circus_witness_set_input(witness, 3, true),
+            CircusStatus::OutOfRange
+        );
+        assert_eq!(circus_witness_set_input(witness, 0, true), CircusStatus::Ok);
+        assert_eq!(circus_witness_set_input(witness, 2, true), CircusStatus::Ok);
+        assert_eq!(circus_witness_generate(witness), CircusStatus::Ok);
+        let mut outputs = [2; 2];
+        assert_eq!(
+            circus_witness_get_outputs(witness, outputs.as_mut_ptr(), 2),
+            CircusStatus::Ok
+        );
+        assert_eq!(outputs, [0, 1]);
+
+        assert_eq!(
+            circus_witness_set_inputs(witness, [1, 1, 1].as_ptr(), 3),
+            CircusStatus::Ok
+        );
+        assert_eq!(circus_witness_generate(witness), CircusStatus::Ok);
+        assert_eq!(
+            circus_witness_get_output(witness, 0, &mut value),
+            CircusStatus::Ok
+        );
+        assert!(value);
+        assert_eq!(
+            circus_witness_get_output(witness, 1, &mut value),
+            CircusStatus::Ok
+        );
+        assert!(!value);
+        assert_eq!(
+            circus_witness_get_wire(witness, 0, &mut value),
+            CircusStatus::Ok
+        );
+        assert!(value);
+        assert_eq!(
+            circus_witness_get_wire(witness, usize::MAX, &mut value),
+            CircusStatus::OutOfRange
+        );
+        circus_witness_free(witness);
+        assert_eq!(
+            circus_witness_generate(std::ptr::null_mut()),
+            CircusStatus::NullPointer
+        );
+    }
+}
+
+#[test]
+fn test_capi_limits() {
+    use crate::graph::{Graph, MAX_CHECKPOINT_DEPTH};
+
+    // A checkpoint of modules which each define the next, nested `depth` deep
+    let nested = |depth: usize| {
+        let mut data = b"GCKP\x01".to_vec();
+        for _ in 0..depth {
+            // No inputs, one module with an empty name
+            data.extend([0, 1, 0]);
+        }
+        // The innermost graph has no inputs, modules, calls or nodes
+        data.extend([0, 0, 0, 0]);
+        for _ in 0..depth {
+            // No outputs or gates for the module, then no calls or nodes
+            data.extend([0, 0, 0, 0]);
+        }
+        // No handles
+        data.push(0);
+        data
+    };
+    unsafe {
+        let data = nested(MAX_CHECKPOINT_DEPTH);
+        let circuit = circus_circuit_load(data.as_ptr(), data.len());
+        assert!(!circuit.is_null());
+        circus_circuit_free(circuit);
+        let data = nested(MAX_CHECKPOINT_DEPTH + 1);
+        assert!(circus_circuit_load(data.as_ptr(), data.len()).is_null());
+        let data = nested(200_000);
+        assert!(circus_circuit_load(data.as_ptr(), data.len()).is_null());
+    }
+
+    // A loop which would be unrolled into too many gates
+    let step = SubcircuitTemplate::capture(1, |graph, inputs| {
+        vec![SystemNot::<bool>::not(graph, &inputs[0])]
+    });
+    let mut graph = Graph::new();
+    let module = graph.define("step", Rc::new(step));
+    let input = graph.input();
+    let outputs = graph.repeat(u32::MAX, module, &[input], &[]);
+    let mut data = Vec::new();
+    graph.write_checkpoint(&mut data, &outputs).unwrap();
+    unsafe {
+        assert!(circus_circuit_load(data.as_ptr(), data.len()).is_null());
+    }
+}
@@ -0,0 +1,249 @@
+use crate::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::panic::Location;
+
+/// A kind of decision made while evaluating a gadget, whose outcomes are tracked by coverage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CoverageKind {
+    /// A [`SystemSelect::select`]. Outcome 0 is the first option, and outcome 1 the second.
+    Select,
+
+    /// A [`SystemSelect::select_from`] with the given number of options. Each outcome is a row.
+    SelectFrom(usize),
+
+    /// The guard of a [`SystemAssertIf::assert_if`]. Outcome 0 is the guard being true, so that
+    /// the claim was checked, and outcome 1 the guard being false.
+    AssertIf,
+}
+
+impl CoverageKind {
+    /// The number of possible outcomes of this kind of decision.
+    pub fn num_outcomes(self) -> usize {
+        match self {
+            CoverageKind::Select | CoverageKind::AssertIf => 2,
+            CoverageKind::SelectFrom(len) => len,
+        }
+    }
+}
+
+/// A place in gadget code where a decision was made, with the number of times each outcome
+/// occurred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoveragePoint {
+    /// The path of the scope the decision was made in, with the names of nested scopes
+    /// separated by `/`. See [`Eval::push_scope`].
+    pub scope: String,
+
+    /// The source location of the call which made the decision.
+    pub location: &'static Location<'static>,
+    pub kind: CoverageKind,
+    pub counts: Vec<usize>,
+}
+
+impl CoveragePoint {
+    /// The outcomes which never occurred.
+    pub fn missed(&self) -> impl Iterator<Item = usize> + '_ {
+        (self.counts.iter().enumerate())
+            .filter(|(_, count)| **count == 0)
+            .map(|(outcome, _)| outcome)
+    }
+
+    /// Determines whether every outcome occurred at least once.
+    pub fn is_covered(&self) -> bool {
+        self.missed().next().is_none()
+    }
+}
+
+/// The decisions made by [`Eval`] while coverage was being recorded. See
+/// [`Eval::start_coverage`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageReport {
+    /// The points at which decisions were made, ordered by scope and then by location.
+    pub points: Vec<CoveragePoint>,
+}
+
+impl CoverageReport {
+    /// The points at which some outcome never occurred. A branch of a select which is never
+    /// taken by any test may hide a gadget which is under-constrained on that branch.
+    pub fn uncovered(&self) -> impl Iterator<Item = &CoveragePoint> {
+        self.points.iter().filter(|point| !point.is_covered())
+    }
+
+    /// The number of outcomes which occurred, and the total number of outcomes, over all
+    /// points.
+    pub fn totals(&self) -> (usize, usize) {
+        let total = self.points.iter().map(|point| point.counts.len()).sum();
+        let missed: usize = self.points.iter().map(|point| point.missed().count()).sum();
+        (total - missed, total)
+    }
+}
+
+impl std::fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut points = self.points.iter().peekable();
+        while let Some(first) = points.peek() {
+            let scope = first.scope.clone();
+            let mut covered = 0;
+            let mut total = 0;
+            let mut missed = Vec::new();
+            while let Some(point) = points.next_if(|point| point.scope == scope) {
+                total += point.counts.len();
+                covered += point.counts.len() - point.missed().count();
+                if !point.is_covered() {
+                    missed.push(point);
+                }
+            }
+            match scope.as_str() {
+                "" => write!(f, "top level")?,
+                scope => write!(f, "scope {}", scope)?,
+            }
+            writeln!(f, ": {}/{} outcomes covered", covered, total)?;
+            for point in missed {
+                let missed: Vec<_> = point.missed().collect();
+                writeln!(
+                    f,
+                    "  {}: {:?} missed {:?}",
+                    point.location, point.kind, missed
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The state of coverage recording on a thread.
+#[derive(Default)]
+struct Recorder {
+    scope_stack: Vec<String>,
+    counts: HashMap<(String, &'static Location<'static>, CoverageKind), Vec<usize>>,
+}
+
+thread_local! {
+    /// The coverage being recorded by [`Eval`] on the current thread, if any.
+    static COVERAGE: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+impl Eval {
+    /// Starts recording which outcomes of selects, table lookups and assertion guards occur
+    /// when evaluating gadgets with [`Eval`] on the current thread, discarding anything recorded
+    /// previously. Each decision is attributed to the source location of the call which made it,
+    /// within the current scope.
+    pub fn start_coverage() {
+        COVERAGE.with(|coverage| *coverage.borrow_mut() = Some(Recorder::default()))
+    }
+
+    /// Stops recording coverage on the current thread, returning what was recorded. Returns an
+    /// empty report if coverage was not being recorded.
+    pub fn finish_coverage() -> CoverageReport {
+        let Some(recorder) = COVERAGE.with(|coverage| coverage.borrow_mut().take()) else {
+            return CoverageReport::default();
+        };
+        let mut points: Vec<_> = (recorder.counts.into_iter())
+            .map(|((scope, location, kind), counts)| CoveragePoint {
+                scope,
+                location,
+                kind,
+                counts,
+            })
+            .collect();
+        points.sort_by(|a, b| {
+            (a.scope.cmp(&b.scope))
+                .then_with(|| a.location.file().cmp(b.location.file()))
+                .then_with(|| a.location.line().cmp(&b.location.line()))
+                .then_with(|| a.location.column().cmp(&b.location.column()))
+                .then_with(|| a.kind.cmp(&b.kind))
+        });
+        CoverageReport { points }
+    }
+
+    /// Enters a named scope for coverage on the current thread. Decisions made until the
+    /// matching [`Eval::pop_scope`] are reported under it. This has no effect unless coverage is
+    /// being recorded.
+    pub fn push_scope(name: impl Into<String>) {
+        COVERAGE.with(|coverage| {
+            if let Some(recorder) = coverage.borrow_mut().as_mut() {
+                recorder.scope_stack.push(name.into());
+            }
+        })
+    }
+
+    /// Exits the innermost scope entered using [`Eval::push_scope`].
+    pub fn pop_scope() {
+        COVERAGE.with(|coverage| {
+            if let Some(recorder) = coverage.borrow_mut().as_mut() {
+                recorder.scope_stack.pop().expect("no scope to exit");
+            }
+        })
+    }
+}
+
+/// Records the outcome of a decision made by [`Eval`], if coverage is being recorded, at the
+/// location of the caller.
+#[track_caller]
+pub(crate) fn record(kind: CoverageKind, outcome: usize) {
+    let location = Location::caller();
+    COVERAGE.with(|coverage| {
+        if let Some(recorder) = coverage.borrow_mut().as_mut() {
+            let scope = recorder.scope_stack.join("/");
+            let counts = (recorder.counts)
+                .entry((scope, location, kind))
+                .or_insert_with(|| vec![0; kind.num_outcomes()]);
+            counts[outcome] += 1;
+        }
+    })
+}
+
+#[test]
+fn test_coverage() {
+    fn gadget<S: SystemSelect<u32> + SystemAssertIf + ?Sized>(
+        sys: &mut S,
+        cond: &Abstract<S, bool>,
+        index: &[Abstract<S, bool>],
+        table: &[Abstract<S, u32>],
+    ) -> Abstract<S, u32> {
+        let row = sys.select_from(index, table);
+        let one = SystemRepr::<u32>::constant(sys, 1);
+        let res = sys.select(cond, &row, &one);
+        sys.assert_if(cond, cond);
+        res
+    }
+
+    Eval::start_coverage();
+    let table = [10, 20, 30];
+    for (cond, index) in [(true, [false, false]), (false, [true, false])] {
+        Eval::push_scope("gadget");
+        gadget(&mut Eval, &cond, &index, &table);
+        Eval::pop_scope();
+    }
+    Eval::push_scope("other");
+    SystemSelect::<u8>::select(&mut Eval, &true, &1, &2);
+    Eval::pop_scope();
+    let report = Eval::finish_coverage();
+
+    assert_eq!(report.points.len(), 4);
+    assert!(report
+        .points
+        .iter()
+        .all(|point| point.location.file() == file!()));
+    assert_eq!(report.points[0].scope, "gadget");
+    assert_eq!(report.points[0].kind, CoverageKind::SelectFrom(3));
+    assert_eq!(report.points[0].counts, [1, 1, 0]);
+    assert_eq!(report.points[1].kind, CoverageKind::Select);
+    assert!(report.points[1].is_covered());
+    assert_eq!(report.points[2].kind, CoverageKind::AssertIf);
+    assert_eq!(report.points[3].scope, "other");
+    let uncovered: Vec<_> = report.uncovered().map(|point| point.kind).collect();
+    assert_eq!(
+        uncovered,
+        [CoverageKind::SelectFrom(3), CoverageKind::Select]
+    );
+    assert_eq!(report.totals(), (7, 9));
+    let text = report.to_string();
+    assert!(text.contains("scope gadget: 6/7 outcomes covered"));
+    assert!(text.contains("SelectFrom(3) missed [2]"));
+
+    // Nothing is recorded once coverage is finished
+    SystemSelect::<u8>::select(&mut Eval, &true, &1, &2);
+    assert_eq!(Eval::finish_coverage(), CoverageReport::default());
+}
@@ -0,0 +1,187 @@
+//! Gadgets for proving statements about the subject of a credential, such as their age, without
+//! revealing the rest of the credential.
+use crate::bytes::{AbstractBytes, SystemBytes};
+use crate::date::AbstractDate;
+use crate::decimal::parse_decimal;
+use crate::*;
+use alloc::vec::Vec;
+use array_init::array_init;
+
+/// The length of a date in `YYYY-MM-DD` format.
+const DATE_LEN: usize = 10;
+
+/// A system in which credentials can be checked.
+pub trait SystemCredentials: SystemBytes {}
+
+impl<S: SystemBytes> SystemCredentials for S {}
+
+/// A gadget which binds a credential payload to some public commitment, such as its digest or
+/// the signature of its issuer, so that the prover can't substitute a payload of their own.
+/// Closures taking the system and the payload can be used directly.
+pub trait Binding<S: SystemCredentials + ?Sized> {
+    /// Determines whether the given payload is the one committed to.
+    fn verify(&self, sys: &mut S, payload: &AbstractBytes<S>) -> Abstract<S, bool>;
+}
+
+impl<S: SystemCredentials + ?Sized, F> Binding<S> for F
+where
+    F: Fn(&mut S, &AbstractBytes<S>) -> Abstract<S, bool>,
+{
+    fn verify(&self, sys: &mut S, payload: &AbstractBytes<S>) -> Abstract<S, bool> {
+        self(sys, payload)
+    }
+}
+
+/// Binds a credential payload by its SHA-256 digest.
+pub struct Sha256Binding<S: SystemRepr<u8> + ?Sized> {
+    /// The expected digest of the payload.
+    pub digest: [Abstract<S, u8>; 32],
+}
+
+impl<S: SystemCredentials + ?Sized> Binding<S> for Sha256Binding<S> {
+    fn verify(&self, sys: &mut S, payload: &AbstractBytes<S>) -> Abstract<S, bool> {
+        let digest = payload.sha256(sys);
+        let mut res = SystemRepr::<bool>::constant(sys, true);
+        for (a, b) in digest.iter().zip(self.digest.iter()) {
+            let a = sys.bits_of_u8(a);
+            let b = sys.bits_of_u8(b);
+            for (a, b) in a.iter().zip(b.iter()) {
+                let diff = SystemBitXor::<bool>::xor(sys, a, b);
+                let same = SystemNot::<bool>::not(sys, &diff);
+                res = SystemBitAnd::<bool>::and(sys, &res, &same);
+            }
+        }
+        res
+    }
+}
+
+/// Extracts a date in `YYYY-MM-DD` format from a credential payload, immediately following the
+/// given key. `offset` is the position of the key, which is typically provided by the prover.
+/// The date is valid if the key and a well-formed date appear at the given offset within the
+/// payload. The date is not checked against the calendar, so months and days may be out of
+/// range. If it is not valid, the returned date is unspecified.
+pub fn extract_date<S: SystemCredentials + ?Sized>(
+    sys: &mut S,
+    payload: &AbstractBytes<S>,
+    key: &[u8],
+    offset: &Abstract<S, u32>,
+) -> (AbstractDate<S>, Abstract<S, bool>) {
+    let len = key.len() + DATE_LEN;
+    let len_abs = SystemRepr::<u32>::constant(sys, len as u32);
+    let (field, mut valid) = payload.slice(sys, offset, &len_abs, len);
+    let data = field.data();
+    let mut expected = key.to_vec();
+    expected.extend_from_slice(b"0000-00-00");
+    for (i, byte) in data.iter().enumerate() {
+        if i < key.len() || expected[i] == b'-' {
+            let eq = eq_u8_const(sys, byte, expected[i]);
+            valid = SystemBitAnd::<bool>::and(sys, &valid, &eq);
+        }
+    }
+    let date = &data[key.len()..];
+    let (year, year_valid) = parse_fixed(sys, &date[0..4]);
+    let (month, month_valid) = parse_fixed(sys, &date[5..7]);
+    let (day, day_valid) = parse_fixed(sys, &date[8..10]);
+    for part_valid in [year_valid, month_valid, day_valid] {
+        valid = SystemBitAnd::<bool>::and(sys, &valid, &part_valid);
+    }
+    let zero = SystemRepr::<bool>::constant(sys, false);
+    let year: [_; 32] = array_init(|i| year.get(i).unwrap_or(&zero).clone());
+    let month: [_; 8] = array_init(|i| month.get(i).unwrap_or(&zero).clone());
+    let day: [_; 8] = array_init(|i| day.get(i).unwrap_or(&zero).clone());
+    let date = AbstractDate {
+        year: sys.u32_of_bits(&year),
+        month: sys.u8_of_bits(&month),
+        day: sys.u8_of_bits(&day),
+    };
+    (date, valid)
+}
+
+/// Determines whether the subject of a credential was at least `years` old on the date `today`,
+/// where their date of birth appears in the payload in `YYYY-MM-DD` format following the given
+/// key, at the position `offset`. This holds when the payload is accepted by `binding`, the date
+/// of birth is found, and `birth + years <= today`. Subjects born on February 29 reach each age
+/// on March 1 in years which are not leap years.
+pub fn is_of_age<S: SystemCredentials + ?Sized, B: Binding<S> + ?Sized>(
+    sys: &mut S,
+    binding: &B,
+    payload: &AbstractBytes<S>,
+    key: &[u8],
+    offset: &Abstract<S, u32>,
+    years: u32,
+    today: &AbstractDate<S>,
+) -> Abstract<S, bool> {
+    let bound = binding.verify(sys, payload);
+    let (birth, found) = extract_date(sys, payload, key, offset);
+    let threshold = birth.add_years(sys, years);
+    let old_enough = threshold.le(sys, today);
+    let res = SystemBitAnd::<bool>::and(sys, &bound, &found);
+    SystemBitAnd::<bool>::and(sys, &res, &old_enough)
+}
+
+/// Parses a fixed-length string of ASCII decimal digits, returning the little-endian bits of the
+/// value and whether the string consisted only of digits.
+fn parse_fixed<S: SystemCredentials + ?Sized>(
+    sys: &mut S,
+    text: &[Abstract<S, u8>],
+) -> (Vec<Abstract<S, bool>>, Abstract<S, bool>) {
+    let len = SystemRepr::<u32>::constant(sys, text.len() as u32);
+    let text = AbstractBytes::from_raw(text.to_vec(), len);
+    let (value, valid) = parse_decimal(sys, &text);
+    (sys.bits_of_u64(&value).to_vec(), valid)
+}
+
+#[test]
+fn test_is_of_age() {
+    use crate::crypto::hash::SystemSha256Bytes;
+    use crate::date::Date;
+
+    let text = br#"{"name":"Alice","birthdate":"2000-02-29","expiry":"2030-01-01"}"#;
+    let sys = &mut Eval;
+    let payload = AbstractBytes::from_const(sys, text, 80);
+    let binding = Sha256Binding {
+        digest: sys.sha256_bytes(text),
+    };
+    let key = br#""birthdate":""#;
+    let offset = 16;
+    let date = |year, month, day| AbstractDate::constant(&mut Eval, Date { year, month, day });
+
+    // Extracting the date
+    let (birth, valid) = extract_date(sys, &payload, key, &offset);
+    assert!(valid);
+    assert_eq!(
+        birth.read_value(&Eval),
+        Date {
+            year: 2000,
+            month: 2,
+            day: 29
+        }
+    );
+    assert!(!extract_date(sys, &payload, key, &17).1);
+    assert!(!extract_date(sys, &payload, br#""expiry":""#, &offset).1);
+
+    // Comparing against the threshold
+    let cases = [
+        ((2018, 2, 28), 18, false),
+        ((2018, 3, 1), 18, true),
+        ((2017, 12, 31), 18, false),
+        ((2020, 2, 28), 20, false),
+        ((2020, 2, 29), 20, true),
+    ];
+    for ((year, month, day), years, expected) in cases {
+        let today = date(year, month, day);
+        let res = is_of_age(sys, &binding, &payload, key, &offset, years, &today);
+        assert_eq!(res, expected, "{}-{}-{}", year, month, day);
+    }
+
+    // The payload must be bound, and the date must be the date of birth
+    let today = date(2030, 1, 1);
+    let other = AbstractBytes::from_const(sys, br#"{"birthdate":"1990-01-01"}"#, 80);
+    assert!(!is_of_age(sys, &binding, &other, key, &1, 18, &today));
+    let accept = |_: &mut Eval, _: &AbstractBytes<Eval>| true;
+    assert!(is_of_age(sys, &accept, &other, key, &1, 18, &today));
+    let expiry = br#""expiry":""#;
+    assert!(!is_of_age(
+        sys, &binding, &payload, expiry, &offset, 18, &today
+    ));
+}
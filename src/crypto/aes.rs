@@ -0,0 +1,438 @@
+//! Gadgets for the AES block cipher and its CTR and GCM modes of operation.
+use crate::crypto::tables;
+use crate::ram::AbstractRam;
+use crate::*;
+use array_init::array_init;
+
+/// A system in which AES encryption can be performed.
+pub trait SystemAes:
+    BinarySystem
+    + SystemBits
+    + SystemPack
+    + SystemSelect<u8>
+    + SystemBitXor<u8>
+    + SystemWrappingAdd<u32>
+{
+    /// Expands an AES key into its round keys. The key must be 16, 24 or 32 bytes long, for
+    /// AES-128, AES-192 and AES-256 respectively.
+    fn aes_key_schedule(&mut self, key: &[Abstract<Self, u8>]) -> Vec<[Abstract<Self, u8>; 16]> {
+        assert!(
+            matches!(key.len(), 16 | 24 | 32),
+            "AES key must be 16, 24 or 32 bytes long"
+        );
+        let sbox = sbox(self);
+        let nk = key.len() / 4;
+        let rounds = nk + 6;
+        let mut words: Vec<[Abstract<Self, u8>; 4]> = key
+            .chunks(4)
+            .map(|word| array_init(|i| word[i].clone()))
+            .collect();
+        for i in nk..4 * (rounds + 1) {
+            let mut temp = words[i - 1].clone();
+            if i % nk == 0 {
+                temp = array_init(|j| sub_byte(self, &sbox, &temp[(j + 1) % 4]));
+                let rcon = self.constant(RCON[i / nk - 1]);
+                temp[0] = SystemBitXor::<u8>::xor(self, &temp[0], &rcon);
+            } else if nk > 6 && i % nk == 4 {
+                temp = array_init(|j| sub_byte(self, &sbox, &temp[j]));
+            }
+            let prev = &words[i - nk];
+            let word = array_init(|j| SystemBitXor::<u8>::xor(self, &prev[j], &temp[j]));
+            words.push(word);
+        }
+        words
+            .chunks(4)
+            .map(|round| array_init(|i| round[i / 4][i % 4].clone()))
+            .collect()
+    }
+
+    /// Encrypts a single block using the round keys produced by
+    /// [`SystemAes::aes_key_schedule`].
+    fn aes_encrypt_block(
+        &mut self,
+        round_keys: &[[Abstract<Self, u8>; 16]],
+        block: &[Abstract<Self, u8>; 16],
+    ) -> [Abstract<Self, u8>; 16] {
+        let sbox = sbox(self);
+        let rounds = round_keys.len() - 1;
+        let mut state: [_; 16] =
+            array_init(|i| SystemBitXor::<u8>::xor(self, &block[i], &round_keys[0][i]));
+        for (round, round_key) in round_keys.iter().enumerate().skip(1) {
+            // SubBytes and ShiftRows, with the state stored in column-major order
+            let subbed: [_; 16] = array_init(|i| sub_byte(self, &sbox, &state[i]));
+            state = array_init(|i| subbed[i % 4 + 4 * ((i / 4 + i % 4) % 4)].clone());
+
+            // MixColumns
+            if round < rounds {
+                for col in state.chunks_mut(4) {
+                    let a: [_; 4] = array_init(|i| col[i].clone());
+                    let double: [_; 4] = array_init(|i| xtime(self, &a[i]));
+                    for (i, out) in col.iter_mut().enumerate() {
+                        let t = SystemBitXor::<u8>::xor(self, &double[i], &double[(i + 1) % 4]);
+                        let t = SystemBitXor::<u8>::xor(self, &t, &a[(i + 1) % 4]);
+                        let t = SystemBitXor::<u8>::xor(self, &t, &a[(i + 2) % 4]);
+                        *out = SystemBitXor::<u8>::xor(self, &t, &a[(i + 3) % 4]);
+                    }
+                }
+            }
+
+            // AddRoundKey
+            state = array_init(|i| SystemBitXor::<u8>::xor(self, &state[i], &round_key[i]));
+        }
+        state
+    }
+}
+
+impl<
+        S: BinarySystem
+            + SystemBits
+            + SystemPack
+            + SystemSelect<u8>
+            + SystemBitXor<u8>
+            + SystemWrappingAdd<u32>,
+    > SystemAes for S
+{
+}
+
+/// Generates an AES keystream in counter mode, incrementing the last 32 bits of the counter
+/// block as a big-endian integer for each block, as in GCM. Data can be processed in pieces of
+/// any length.
+pub struct AesCtr<S: SystemRepr<u8> + ?Sized> {
+    round_keys: Vec<[Abstract<S, u8>; 16]>,
+    counter: [Abstract<S, u8>; 16],
+    keystream: Vec<Abstract<S, u8>>,
+}
+
+impl<S: SystemAes + ?Sized> AesCtr<S> {
+    /// Constructs an [`AesCtr`] with the given key and initial counter block.
+    pub fn new(sys: &mut S, key: &[Abstract<S, u8>], counter: [Abstract<S, u8>; 16]) -> Self {
+        Self {
+            round_keys: sys.aes_key_schedule(key),
+            counter,
+            keystream: Vec::new(),
+        }
+    }
+
+    /// Gets the next `len` bytes of the keystream.
+    pub fn keystream(&mut self, sys: &mut S, len: usize) -> Vec<Abstract<S, u8>> {
+        while self.keystream.len() < len {
+            let block = sys.aes_encrypt_block(&self.round_keys, &self.counter);
+            self.keystream.extend(block);
+            self.counter = inc32(sys, &self.counter);
+        }
+        self.keystream.drain(..len).collect()
+    }
+
+    /// Encrypts or decrypts the next piece of data by combining it with the keystream.
+    pub fn apply(&mut self, sys: &mut S, data: &[Abstract<S, u8>]) -> Vec<Abstract<S, u8>> {
+        let keystream = self.keystream(sys, data.len());
+        data.iter()
+            .zip(keystream.iter())
+            .map(|(a, b)| SystemBitXor::<u8>::xor(sys, a, b))
+            .collect()
+    }
+}
+
+/// Encrypts or decrypts data using AES in Galois/Counter Mode with a 96-bit IV. Additional
+/// authenticated data must be provided before any plaintext or ciphertext, but each can be
+/// provided in pieces of any length.
+pub struct AesGcm<S: SystemRepr<bool> + SystemRepr<u8> + ?Sized> {
+    ctr: AesCtr<S>,
+    hash_key: [Abstract<S, bool>; 128],
+    tag_mask: [Abstract<S, u8>; 16],
+    acc: [Abstract<S, bool>; 128],
+    pending: Vec<Abstract<S, u8>>,
+    aad_len: u64,
+    text_len: u64,
+}
+
+impl<S: SystemAes + ?Sized> AesGcm<S> {
+    /// Constructs an [`AesGcm`] with the given key and IV.
+    pub fn new(sys: &mut S, key: &[Abstract<S, u8>], iv: &[Abstract<S, u8>; 12]) -> Self {
+        let round_keys = sys.aes_key_schedule(key);
+        let zero: [_; 16] = array_init(|_| sys.constant(0u8));
+        let hash_key = sys.aes_encrypt_block(&round_keys, &zero);
+        let hash_key = block_to_bits(sys, &hash_key);
+        let counter: [_; 16] = array_init(|i| match i {
+            0..=11 => iv[i].clone(),
+            15 => sys.constant(1u8),
+            _ => sys.constant(0u8),
+        });
+        let tag_mask = sys.aes_encrypt_block(&round_keys, &counter);
+        let counter = inc32(sys, &counter);
+        let acc = array_init(|_| SystemRepr::<bool>::constant(sys, false));
+        Self {
+            ctr: AesCtr {
+                round_keys,
+                counter,
+                keystream: Vec::new(),
+            },
+            hash_key,
+            tag_mask,
+            acc,
+            pending: Vec::new(),
+            aad_len: 0,
+            text_len: 0,
+        }
+    }
+
+    /// Provides the next piece of additional authenticated data. Panics if any plaintext or
+    /// ciphertext has already been provided.
+    pub fn update_aad(&mut self, sys: &mut S, data: &[Abstract<S, u8>]) {
+        assert!(
+            self.text_len == 0,
+            "additional data must come before plaintext or ciphertext"
+        );
+        self.aad_len += data.len() as u64;
+        self.absorb(sys, data);
+    }
+
+    /// Encrypts the next piece of plaintext.
+    pub fn encrypt(&mut self, sys: &mut S, plaintext: &[Abstract<S, u8>]) -> Vec<Abstract<S, u8>> {
+        self.begin_text(sys, plaintext.len());
+        let ciphertext = self.ctr.apply(sys, plaintext);
+        self.absorb(sys, &ciphertext);
+        ciphertext
+    }
+
+    /// Decrypts the next piece of ciphertext.
+    pub fn decrypt(&mut self, sys: &mut S, ciphertext: &[Abstract<S, u8>]) -> Vec<Abstract<S, u8>> {
+        self.begin_text(sys, ciphertext.len());
+        self.absorb(sys, ciphertext);
+        self.ctr.apply(sys, ciphertext)
+    }
+
+    /// Computes the authentication tag for the data provided so far.
+    pub fn finish(mut self, sys: &mut S) -> [Abstract<S, u8>; 16] {
+        self.flush(sys);
+        let mut lengths = Vec::with_capacity(16);
+        for len in [self.aad_len, self.text_len] {
+            for byte in (len * 8).to_be_bytes() {
+                lengths.push(sys.constant(byte));
+            }
+        }
+        self.absorb(sys, &lengths);
+        let acc = bits_to_block(sys, &self.acc);
+        array_init(|i| SystemBitXor::<u8>::xor(sys, &acc[i], &self.tag_mask[i]))
+    }
+
+    /// Determines whether the given authentication tag is correct for the data provided so far.
+    pub fn verify(self, sys: &mut S, tag: &[Abstract<S, u8>; 16]) -> Abstract<S, bool> {
+        let expected = self.finish(sys);
+        let mut res = SystemRepr::<bool>::constant(sys, true);
+        for (a, b) in expected.iter().zip(tag.iter()) {
+            let diff = SystemBitXor::<u8>::xor(sys, a, b);
+            for bit in sys.bits_of_u8(&diff) {
+                let same = SystemNot::<bool>::not(sys, &bit);
+                res = SystemBitAnd::<bool>::and(sys, &res, &same);
+            }
+        }
+        res
+    }
+
+    /// Prepares to process plaintext or ciphertext of the given length.
+    fn begin_text(&mut self, sys: &mut S, len: usize) {
+        if self.text_len == 0 {
+            self.flush(sys);
+        }
+        self.text_len += len as u64;
+    }
+
+    /// Adds data to the GHASH input.
+    fn absorb(&mut self, sys: &mut S, data: &[Abstract<S, u8>]) {
+        self.pending.extend_from_slice(data);
+        while self.pending.len() >= 16 {
+            let block: Vec<_> = self.pending.drain(..16).collect();
+            self.ghash_block(sys, &array_init(|i| block[i].clone()));
+        }
+    }
+
+    /// Pads the GHASH input with zeros up to a multiple of the block size.
+    fn flush(&mut self, sys: &mut S) {
+        if !self.pending.is_empty() {
+            let zeros = vec![sys.constant(0u8); 16 - self.pending.len()];
+            self.absorb(sys, &zeros);
+        }
+    }
+
+    /// Updates the GHASH state with a block of input.
+    fn ghash_block(&mut self, sys: &mut S, block: &[Abstract<S, u8>; 16]) {
+        let block = block_to_bits(sys, block);
+        let x: [_; 128] = array_init(|i| SystemBitXor::<bool>::xor(sys, &self.acc[i], &block[i]));
+        self.acc = gf128_mul(sys, &x, &self.hash_key);
+    }
+}
+
+/// Multiplies two elements of GF(2^128), represented as in GCM, where the first bit is the
+/// coefficient of x^0.
+fn gf128_mul<S: SystemAes + ?Sized>(
+    sys: &mut S,
+    a: &[Abstract<S, bool>; 128],
+    b: &[Abstract<S, bool>; 128],
+) -> [Abstract<S, bool>; 128] {
+    let mut res: [_; 128] = array_init(|_| SystemRepr::<bool>::constant(sys, false));
+    let mut v = b.clone();
+    for (i, bit) in a.iter().enumerate() {
+        for (r, v) in res.iter_mut().zip(v.iter()) {
+            let t = SystemBitAnd::<bool>::and(sys, bit, v);
+            *r = SystemBitXor::<bool>::xor(sys, r, &t);
+        }
+        if i < 127 {
+            // Multiply v by x, reducing by x^128 + x^7 + x^2 + x + 1
+            let carry = v[127].clone();
+            v.rotate_right(1);
+            v[0] = carry.clone();
+            for j in [1, 2, 7] {
+                v[j] = SystemBitXor::<bool>::xor(sys, &v[j], &carry);
+            }
+        }
+    }
+    res
+}
+
+/// Converts a block into bits, with the most significant bit of each byte first.
+fn block_to_bits<S: SystemAes + ?Sized>(
+    sys: &mut S,
+    block: &[Abstract<S, u8>; 16],
+) -> [Abstract<S, bool>; 128] {
+    let bits: Vec<_> = block.iter().map(|byte| sys.bits_of_u8(byte)).collect();
+    array_init(|i| bits[i / 8][7 - i % 8].clone())
+}
+
+/// Converts bits into a block, with the most significant bit of each byte first.
+fn bits_to_block<S: SystemAes + ?Sized>(
+    sys: &mut S,
+    bits: &[Abstract<S, bool>; 128],
+) -> [Abstract<S, u8>; 16] {
+    array_init(|i| {
+        let byte: [_; 8] = array_init(|j| bits[i * 8 + 7 - j].clone());
+        sys.u8_of_bits(&byte)
+    })
+}
+
+/// Increments the last 32 bits of a counter block.
+fn inc32<S: SystemAes + ?Sized>(
+    sys: &mut S,
+    counter: &[Abstract<S, u8>; 16],
+) -> [Abstract<S, u8>; 16] {
+    let word: [_; 4] = array_init(|i| counter[12 + i].clone());
+    let word = sys.pack_be_u32(&word);
+    let one = SystemRepr::<u32>::constant(sys, 1);
+    let word = SystemWrappingAdd::<u32>::wrapping_add(sys, &word, &one);
+    let word = sys.unpack_be_u32(&word);
+    array_init(|i| match i {
+        0..=11 => counter[i].clone(),
+        _ => word[i - 12].clone(),
+    })
+}
+
+/// Constructs a lookup table for the AES S-box.
+fn sbox<S: SystemAes + ?Sized>(sys: &mut S) -> AbstractRam<S, u8> {
+    AbstractRam::new(SBOX.iter().map(|x| sys.constant(*x)).collect())
+}
+
+/// Applies the AES S-box to a byte.
+fn sub_byte<S: SystemAes + ?Sized>(
+    sys: &mut S,
+    sbox: &AbstractRam<S, u8>,
+    value: &Abstract<S, u8>,
+) -> Abstract<S, u8> {
+    let bits = sys.bits_of_u8(value);
+    sbox.read(sys, &bits)
+}
+
+/// Multiplies a byte by x in GF(2^8).
+fn xtime<S: SystemAes + ?Sized>(sys: &mut S, value: &Abstract<S, u8>) -> Abstract<S, u8> {
+    let bits = sys.bits_of_u8(value);
+    let high = &bits[7];
+    let res = [
+        high.clone(),
+        SystemBitXor::<bool>::xor(sys, &bits[0], high),
+        bits[1].clone(),
+        SystemBitXor::<bool>::xor(sys, &bits[2], high),
+        SystemBitXor::<bool>::xor(sys, &bits[3], high),
+        bits[4].clone(),
+        bits[5].clone(),
+        bits[6].clone(),
+    ];
+    sys.u8_of_bits(&res)
+}
+
+const RCON: [u8; 10] = tables::aes_rcon();
+
+const SBOX: [u8; 256] = tables::aes_sbox();
+
+#[cfg(test)]
+fn hex(str: &str) -> Vec<u8> {
+    (0..str.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&str[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_aes_block() {
+    let block: [u8; 16] = array_init(|i| hex("00112233445566778899aabbccddeeff")[i]);
+    let key: Vec<u8> = (0..16).collect();
+    let round_keys = Eval.aes_key_schedule(&key);
+    let res = Eval.aes_encrypt_block(&round_keys, &block);
+    assert_eq!(res[..], hex("69c4e0d86a7b0430d8cdb78070b4c55a"));
+    let key: Vec<u8> = (0..32).collect();
+    let round_keys = Eval.aes_key_schedule(&key);
+    let res = Eval.aes_encrypt_block(&round_keys, &block);
+    assert_eq!(res[..], hex("8ea2b7ca516745bfeafc49904b496089"));
+
+    // Check consistency with a binary system
+    let mut sys = BinaryEmulate::new(Eval);
+    let key: Vec<_> = (0..16).map(|i| sys.constant(i as u8)).collect();
+    let round_keys = sys.aes_key_schedule(&key);
+    let abstract_block = block.map(|b| sys.constant(b));
+    let res = sys.aes_encrypt_block(&round_keys, &abstract_block);
+    let expected = hex("69c4e0d86a7b0430d8cdb78070b4c55a");
+    assert_eq!(res, array_init::<_, _, 16>(|i| sys.constant(expected[i])));
+}
+
+#[test]
+fn test_aes_gcm() {
+    let key = hex("feffe9928665731c6d6a8f9467308308");
+    let iv: [u8; 12] = array_init(|i| hex("cafebabefacedbaddecaf888")[i]);
+    let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+    let plaintext = hex(concat!(
+        "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72",
+        "1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39"
+    ));
+    let ciphertext = hex(concat!(
+        "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e",
+        "21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091"
+    ));
+    let tag: [u8; 16] = array_init(|i| hex("5bc94fbc3221a5db94fae95ae7121a47")[i]);
+
+    // Encrypt in uneven pieces
+    let mut gcm = AesGcm::new(&mut Eval, &key, &iv);
+    gcm.update_aad(&mut Eval, &aad[..7]);
+    gcm.update_aad(&mut Eval, &aad[7..]);
+    let mut res = gcm.encrypt(&mut Eval, &plaintext[..5]);
+    res.extend(gcm.encrypt(&mut Eval, &plaintext[5..40]));
+    res.extend(gcm.encrypt(&mut Eval, &plaintext[40..]));
+    assert_eq!(res, ciphertext);
+    assert_eq!(gcm.finish(&mut Eval), tag);
+
+    // Decrypt and verify
+    let mut gcm = AesGcm::new(&mut Eval, &key, &iv);
+    gcm.update_aad(&mut Eval, &aad);
+    assert_eq!(gcm.decrypt(&mut Eval, &ciphertext), plaintext);
+    assert!(gcm.verify(&mut Eval, &tag));
+    let mut gcm = AesGcm::new(&mut Eval, &key, &iv);
+    gcm.update_aad(&mut Eval, &aad);
+    let mut tampered = ciphertext.clone();
+    tampered[10] ^= 1;
+    gcm.decrypt(&mut Eval, &tampered);
+    assert!(!gcm.verify(&mut Eval, &tag));
+
+    // Empty message
+    let gcm = AesGcm::new(&mut Eval, &[0; 16], &[0; 12]);
+    assert_eq!(
+        gcm.finish(&mut Eval)[..],
+        hex("58e2fccefa7e3061367f1d57a4e7455a")
+    );
+}
@@ -0,0 +1,332 @@
+//! Gadgets for the Ascon permutation, and the Ascon-Hash and Ascon-128 AEAD schemes built on it.
+use crate::*;
+use alloc::vec::Vec;
+use array_init::array_init;
+
+/// The number of bytes absorbed by each application of the permutation, for both Ascon-Hash and
+/// Ascon-128.
+const RATE: usize = 8;
+
+/// A system in which Ascon can be computed.
+pub trait SystemAscon:
+    BinarySystem
+    + SystemBits
+    + SystemPack
+    + SystemBitXor<u8>
+    + SystemBitAnd<u64>
+    + SystemBitXor<u64>
+    + SystemBitRotate<u64, u8>
+    + SystemNot<u64>
+{
+    /// Applies the last `rounds` rounds of the Ascon permutation to the given state. Ascon uses
+    /// 12 rounds for initialization and finalization, and 6 rounds between data blocks.
+    fn ascon_permutation(&mut self, state: &mut [Abstract<Self, u64>; 5], rounds: usize) {
+        assert!(rounds <= RC.len(), "Ascon has at most {} rounds", RC.len());
+        for rc in &RC[RC.len() - rounds..] {
+            // Constant addition
+            let rc = SystemRepr::<u64>::constant(self, *rc);
+            state[2] = SystemBitXor::<u64>::xor(self, &state[2], &rc);
+
+            // Substitution layer
+            state[0] = SystemBitXor::<u64>::xor(self, &state[0], &state[4]);
+            state[4] = SystemBitXor::<u64>::xor(self, &state[4], &state[3]);
+            state[2] = SystemBitXor::<u64>::xor(self, &state[2], &state[1]);
+            let t: [_; 5] = array_init(|i| {
+                let t = SystemNot::<u64>::not(self, &state[i]);
+                SystemBitAnd::<u64>::and(self, &t, &state[(i + 1) % 5])
+            });
+            for i in 0..5 {
+                state[i] = SystemBitXor::<u64>::xor(self, &state[i], &t[(i + 1) % 5]);
+            }
+            state[1] = SystemBitXor::<u64>::xor(self, &state[1], &state[0]);
+            state[0] = SystemBitXor::<u64>::xor(self, &state[0], &state[4]);
+            state[3] = SystemBitXor::<u64>::xor(self, &state[3], &state[2]);
+            state[2] = SystemNot::<u64>::not(self, &state[2]);
+
+            // Linear diffusion layer
+            for (word, [a, b]) in state.iter_mut().zip(ROTATIONS) {
+                let x = self.rotr(word, a);
+                let y = self.rotr(word, b);
+                let t = SystemBitXor::<u64>::xor(self, &x, &y);
+                *word = SystemBitXor::<u64>::xor(self, word, &t);
+            }
+        }
+    }
+
+    /// Computes the Ascon-Hash hash of the given data.
+    fn ascon_hash(&mut self, data: &[Abstract<Self, u8>]) -> [Abstract<Self, u8>; 32] {
+        let mut state = [0x00400c0000000100, 0, 0, 0, 0].map(|word: u64| self.constant(word));
+        self.ascon_permutation(&mut state, 12);
+        let mut padded = data.to_vec();
+        padded.push(self.constant(0x80u8));
+        while !padded.len().is_multiple_of(RATE) {
+            padded.push(self.constant(0u8));
+        }
+        for block in padded.chunks(RATE) {
+            let block: [_; RATE] = array_init(|i| block[i].clone());
+            let block = self.pack_be_u64(&block);
+            state[0] = SystemBitXor::<u64>::xor(self, &state[0], &block);
+            self.ascon_permutation(&mut state, 12);
+        }
+        let mut res = Vec::with_capacity(32);
+        for i in 0..32 / RATE {
+            if i > 0 {
+                self.ascon_permutation(&mut state, 12);
+            }
+            res.extend(self.unpack_be_u64(&state[0]));
+        }
+        array_init(|i| res[i].clone())
+    }
+}
+
+impl<
+        S: BinarySystem
+            + SystemBits
+            + SystemPack
+            + SystemBitXor<u8>
+            + SystemBitAnd<u64>
+            + SystemBitXor<u64>
+            + SystemBitRotate<u64, u8>
+            + SystemNot<u64>,
+    > SystemAscon for S
+{
+}
+
+/// Encrypts or decrypts a message with Ascon-128, the primary authenticated encryption scheme of
+/// the Ascon family, while computing its authentication tag.
+pub struct Ascon128<S: SystemRepr<u8> + SystemRepr<u64> + ?Sized> {
+    key: [Abstract<S, u64>; 2],
+    state: [Abstract<S, u64>; 5],
+    pending: Vec<Abstract<S, u8>>,
+    aad_len: u64,
+    text_started: bool,
+}
+
+impl<S: SystemAscon + ?Sized> Ascon128<S> {
+    /// Constructs an [`Ascon128`] with the given key and nonce.
+    pub fn new(sys: &mut S, key: &[Abstract<S, u8>; 16], nonce: &[Abstract<S, u8>; 16]) -> Self {
+        let key = [0, 8].map(|i| sys.pack_be_u64(&array_init(|j| key[i + j].clone())));
+        let nonce = [0, 8].map(|i| sys.pack_be_u64(&array_init(|j| nonce[i + j].clone())));
+        let mut state = [
+            SystemRepr::<u64>::constant(sys, 0x80400c0600000000),
+            key[0].clone(),
+            key[1].clone(),
+            nonce[0].clone(),
+            nonce[1].clone(),
+        ];
+        sys.ascon_permutation(&mut state, 12);
+        state[3] = SystemBitXor::<u64>::xor(sys, &state[3], &key[0]);
+        state[4] = SystemBitXor::<u64>::xor(sys, &state[4], &key[1]);
+        Self {
+            key,
+            state,
+            pending: Vec::new(),
+            aad_len: 0,
+            text_started: false,
+        }
+    }
+
+    /// Provides the next piece of associated data. Panics if any plaintext or ciphertext has
+    /// already been provided.
+    pub fn update_aad(&mut self, sys: &mut S, data: &[Abstract<S, u8>]) {
+        assert!(
+            !self.text_started,
+            "associated data must come before plaintext or ciphertext"
+        );
+        self.aad_len += data.len() as u64;
+        for byte in data {
+            self.pending.push(byte.clone());
+            if self.pending.len() == RATE {
+                self.absorb(sys);
+                sys.ascon_permutation(&mut self.state, 6);
+            }
+        }
+    }
+
+    /// Encrypts the next piece of plaintext.
+    pub fn encrypt(&mut self, sys: &mut S, plaintext: &[Abstract<S, u8>]) -> Vec<Abstract<S, u8>> {
+        self.apply(sys, plaintext, false)
+    }
+
+    /// Decrypts the next piece of ciphertext.
+    pub fn decrypt(&mut self, sys: &mut S, ciphertext: &[Abstract<S, u8>]) -> Vec<Abstract<S, u8>> {
+        self.apply(sys, ciphertext, true)
+    }
+
+    /// Computes the authentication tag for the data provided so far.
+    pub fn finish(mut self, sys: &mut S) -> [Abstract<S, u8>; 16] {
+        self.begin_text(sys);
+        self.pad(sys);
+        self.state[1] = SystemBitXor::<u64>::xor(sys, &self.state[1], &self.key[0]);
+        self.state[2] = SystemBitXor::<u64>::xor(sys, &self.state[2], &self.key[1]);
+        sys.ascon_permutation(&mut self.state, 12);
+        let tag = [0, 1].map(|i| {
+            let word = SystemBitXor::<u64>::xor(sys, &self.state[3 + i], &self.key[i]);
+            sys.unpack_be_u64(&word)
+        });
+        array_init(|i| tag[i / 8][i % 8].clone())
+    }
+
+    /// Determines whether the given authentication tag is correct for the data provided so far.
+    pub fn verify(self, sys: &mut S, tag: &[Abstract<S, u8>; 16]) -> Abstract<S, bool> {
+        let expected = self.finish(sys);
+        let mut res = SystemRepr::<bool>::constant(sys, true);
+        for (a, b) in expected.iter().zip(tag.iter()) {
+            let diff = SystemBitXor::<u8>::xor(sys, a, b);
+            for bit in sys.bits_of_u8(&diff) {
+                let same = SystemNot::<bool>::not(sys, &bit);
+                res = SystemBitAnd::<bool>::and(sys, &res, &same);
+            }
+        }
+        res
+    }
+
+    /// Encrypts or decrypts the next piece of text. Either way, the plaintext is absorbed into
+    /// the state, which turns the corresponding bytes of the rate into ciphertext.
+    fn apply(
+        &mut self,
+        sys: &mut S,
+        input: &[Abstract<S, u8>],
+        decrypt: bool,
+    ) -> Vec<Abstract<S, u8>> {
+        self.begin_text(sys);
+        let mut output = Vec::with_capacity(input.len());
+        let mut keystream = sys.unpack_be_u64(&self.state[0]);
+        for byte in input {
+            let res = SystemBitXor::<u8>::xor(sys, byte, &keystream[self.pending.len()]);
+            self.pending
+                .push(if decrypt { res.clone() } else { byte.clone() });
+            output.push(res);
+            if self.pending.len() == RATE {
+                self.absorb(sys);
+                sys.ascon_permutation(&mut self.state, 6);
+                keystream = sys.unpack_be_u64(&self.state[0]);
+            }
+        }
+        output
+    }
+
+    /// Finishes processing associated data, if this hasn't been done already.
+    fn begin_text(&mut self, sys: &mut S) {
+        if !self.text_started {
+            if self.aad_len > 0 {
+                self.pad(sys);
+                sys.ascon_permutation(&mut self.state, 6);
+            }
+            let separator = SystemRepr::<u64>::constant(sys, 1);
+            self.state[4] = SystemBitXor::<u64>::xor(sys, &self.state[4], &separator);
+            self.text_started = true;
+        }
+    }
+
+    /// Pads the pending data to a full block and absorbs it into the state.
+    fn pad(&mut self, sys: &mut S) {
+        self.pending.push(SystemRepr::<u8>::constant(sys, 0x80));
+        while self.pending.len() < RATE {
+            self.pending.push(SystemRepr::<u8>::constant(sys, 0));
+        }
+        self.absorb(sys);
+    }
+
+    /// Absorbs a full block of pending data into the state.
+    fn absorb(&mut self, sys: &mut S) {
+        let block: [_; RATE] = array_init(|i| self.pending[i].clone());
+        let block = sys.pack_be_u64(&block);
+        self.state[0] = SystemBitXor::<u64>::xor(sys, &self.state[0], &block);
+        self.pending.clear();
+    }
+}
+
+/// The round constants, for the full 12-round permutation.
+const RC: [u64; 12] = [
+    0xf0, 0xe1, 0xd2, 0xc3, 0xb4, 0xa5, 0x96, 0x87, 0x78, 0x69, 0x5a, 0x4b,
+];
+
+/// The right rotation amounts used by the linear diffusion layer, for each word of the state.
+const ROTATIONS: [[u8; 2]; 5] = [[19, 28], [61, 39], [1, 6], [10, 17], [7, 41]];
+
+#[cfg(test)]
+fn hex(str: &str) -> Vec<u8> {
+    (0..str.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&str[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_ascon_hash() {
+    assert_eq!(
+        Eval.ascon_hash(&[])[..],
+        hex("7346bc14f036e87ae03d0997913088f5f68411434b3cf8b54fa796a80d251f91")
+    );
+    let data: Vec<u8> = (0..32).collect();
+    assert_eq!(
+        Eval.ascon_hash(&data)[..],
+        hex("2a4f6f2b6b3ec2a6c47ba08d18c8ea561b493c13ccb35803fa8b9fb00a0f1f35")
+    );
+
+    // Check consistency with a binary system
+    let data: Vec<u8> = (0..10).collect();
+    let mut sys = BinaryEmulate::new(Eval);
+    let abstract_data: Vec<_> = data.iter().map(|b| sys.constant(*b)).collect();
+    let hash = sys.ascon_hash(&abstract_data);
+    let expected = hex("858d66fdd066e4aaef8c62050cea8ca4c681343607db2af541a89d3a05d1f208");
+    assert_eq!(hash, array_init::<_, _, 32>(|i| sys.constant(expected[i])));
+}
+
+#[test]
+fn test_ascon128() {
+    let key: [u8; 16] = array_init(|i| i as u8);
+    let nonce = key;
+    let aad: Vec<u8> = (0..5).collect();
+    let plaintext: Vec<u8> = (0..20).collect();
+    let ciphertext = hex("0e6a8b0ca517f53d3d72e1d8d734511c32ca4415");
+    let tag: [u8; 16] = array_init(|i| hex("e10196cde1c6fd04100c89e73af86dfb")[i]);
+
+    // Encrypt in uneven pieces
+    let mut ascon = Ascon128::new(&mut Eval, &key, &nonce);
+    ascon.update_aad(&mut Eval, &aad[..2]);
+    ascon.update_aad(&mut Eval, &aad[2..]);
+    let mut res = ascon.encrypt(&mut Eval, &plaintext[..3]);
+    res.extend(ascon.encrypt(&mut Eval, &plaintext[3..11]));
+    res.extend(ascon.encrypt(&mut Eval, &plaintext[11..]));
+    assert_eq!(res, ciphertext);
+    assert_eq!(ascon.finish(&mut Eval), tag);
+
+    // Decrypt and verify
+    let mut ascon = Ascon128::new(&mut Eval, &key, &nonce);
+    ascon.update_aad(&mut Eval, &aad);
+    assert_eq!(ascon.decrypt(&mut Eval, &ciphertext), plaintext);
+    assert!(ascon.verify(&mut Eval, &tag));
+    let mut ascon = Ascon128::new(&mut Eval, &key, &nonce);
+    ascon.update_aad(&mut Eval, &aad);
+    let mut tampered = ciphertext.clone();
+    tampered[10] ^= 1;
+    ascon.decrypt(&mut Eval, &tampered);
+    assert!(!ascon.verify(&mut Eval, &tag));
+
+    // Empty message, and block-aligned data, in a binary system
+    let ascon = Ascon128::new(&mut Eval, &key, &nonce);
+    assert_eq!(
+        ascon.finish(&mut Eval)[..],
+        hex("e355159f292911f794cb1432a0103a8a")
+    );
+    let mut sys = BinaryEmulate::new(Eval);
+    let abstract_key = key.map(|b| sys.constant(b));
+    let mut ascon = Ascon128::new(&mut sys, &abstract_key, &abstract_key);
+    let aad: Vec<_> = (0..16).map(|b| sys.constant(b as u8)).collect();
+    ascon.update_aad(&mut sys, &aad);
+    let plaintext: Vec<_> = (0..8).map(|b| sys.constant(b as u8)).collect();
+    let res = ascon.encrypt(&mut sys, &plaintext);
+    let expected = hex("1ee34125fdba1744");
+    assert_eq!(
+        res,
+        expected
+            .iter()
+            .map(|b| sys.constant(*b))
+            .collect::<Vec<_>>()
+    );
+    let tag = ascon.finish(&mut sys);
+    let expected = hex("263ac941c6edefb49505018de9dac9b3");
+    assert_eq!(tag, array_init::<_, _, 16>(|i| sys.constant(expected[i])));
+}
@@ -0,0 +1,303 @@
+//! Gadgets for verifying DKIM-signed emails.
+use crate::bytes::{eq_bytes, AbstractBytes, SystemBytes};
+#[cfg(test)]
+use crate::crypto::hash::SystemSha256Bytes;
+use crate::ram::AbstractRam;
+use crate::*;
+use array_init::array_init;
+
+/// The DER encoding of the `DigestInfo` prefix for SHA-256, as used by EMSA-PKCS1-v1_5.
+const SHA256_DIGEST_INFO: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+
+/// The length of a base64-encoded SHA-256 digest, including padding.
+const BODY_HASH_LEN: usize = 44;
+
+/// A system in which DKIM-signed emails can be verified.
+pub trait SystemEmail: SystemBytes {}
+
+impl<S: SystemBytes> SystemEmail for S {}
+
+/// Decodes base64 text whose length is a multiple of 4, returning the decoded bytes and whether
+/// the text consisted only of valid base64 characters. Padding characters decode as zero bits,
+/// so the result includes a trailing zero byte for each of them.
+pub fn base64_decode<S: SystemEmail + ?Sized>(
+    sys: &mut S,
+    text: &[Abstract<S, u8>],
+) -> (Vec<Abstract<S, u8>>, Abstract<S, bool>) {
+    assert!(
+        text.len().is_multiple_of(4),
+        "base64 text length must be a multiple of 4"
+    );
+    let mut valid = SystemRepr::<bool>::constant(sys, true);
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    for group in text.chunks(4) {
+        // Decode each character to a sextet, and concatenate them as little-endian bits
+        let mut bits = Vec::with_capacity(24);
+        for char in group.iter().rev() {
+            let char = byte_to_u32(sys, char);
+            let ranges: [(u32, u32, u32); 6] = [
+                (b'A' as u32, b'Z' as u32, 0u32.wrapping_sub(b'A' as u32)),
+                (b'a' as u32, b'z' as u32, 26u32.wrapping_sub(b'a' as u32)),
+                (b'0' as u32, b'9' as u32, 52u32.wrapping_sub(b'0' as u32)),
+                (b'+' as u32, b'+' as u32, 62u32.wrapping_sub(b'+' as u32)),
+                (b'/' as u32, b'/' as u32, 63u32.wrapping_sub(b'/' as u32)),
+                (b'=' as u32, b'=' as u32, 0u32.wrapping_sub(b'=' as u32)),
+            ];
+            let mut sextet = SystemRepr::<u32>::constant(sys, 0);
+            let mut known = SystemRepr::<bool>::constant(sys, false);
+            for (lo, hi, offset) in ranges {
+                let lo = SystemRepr::<u32>::constant(sys, lo);
+                let hi = SystemRepr::<u32>::constant(sys, hi);
+                let above = SystemOrd::<u32>::le(sys, &lo, &char);
+                let below = SystemOrd::<u32>::le(sys, &char, &hi);
+                let in_range = SystemBitAnd::<bool>::and(sys, &above, &below);
+                let offset = SystemRepr::<u32>::constant(sys, offset);
+                let value = SystemWrappingAdd::<u32>::wrapping_add(sys, &char, &offset);
+                sextet = SystemSelect::<u32>::select(sys, &in_range, &value, &sextet);
+                known = SystemBitOr::<bool>::or(sys, &known, &in_range);
+            }
+            valid = SystemBitAnd::<bool>::and(sys, &valid, &known);
+            bits.extend_from_slice(&sys.bits_of_u32(&sextet)[..6]);
+        }
+        for i in (0..3).rev() {
+            let byte: [_; 8] = array_init(|j| bits[i * 8 + j].clone());
+            bytes.push(sys.u8_of_bits(&byte));
+        }
+    }
+    (bytes, valid)
+}
+
+/// A header field value found by [`extract_header`].
+pub struct HeaderField<S: SystemRepr<bool> + SystemRepr<u8> + SystemRepr<u32> + ?Sized> {
+    /// The value of the field.
+    pub value: AbstractBytes<S>,
+
+    /// Whether the field was found. If this is false, the other fields are unspecified.
+    pub valid: Abstract<S, bool>,
+}
+
+/// Extracts the value of a header field from a block of headers in relaxed canonical form, where
+/// field names are lowercase and there is no whitespace around the colon. `offset` is the
+/// position of the start of the field, which is typically provided by the prover. The value is
+/// returned with a capacity of `max_len` bytes. It is valid if a field with the given name
+/// begins at the given offset and its value fits within `max_len` bytes.
+pub fn extract_header<S: SystemEmail + ?Sized>(
+    sys: &mut S,
+    headers: &AbstractRam<S, u8>,
+    name: &[u8],
+    offset: &Abstract<S, u32>,
+    max_len: usize,
+) -> HeaderField<S> {
+    // The field must begin a line
+    let at_start = eq_u32_const(sys, offset, 0);
+    let prev = add_const(sys, offset, -2i32 as u32);
+    let after_cr = expect_bytes(sys, headers, &prev, b"\r\n");
+    let mut valid = SystemBitOr::<bool>::or(sys, &at_start, &after_cr);
+
+    // Check the field name
+    let mut name = name.to_vec();
+    name.push(b':');
+    let matches = expect_bytes(sys, headers, offset, &name);
+    valid = SystemBitAnd::<bool>::and(sys, &valid, &matches);
+
+    // Read the value up to the end of the line
+    let start = add_const(sys, offset, name.len() as u32);
+    let zero = SystemRepr::<u8>::constant(sys, 0);
+    let mut ended = SystemRepr::<bool>::constant(sys, false);
+    let mut len = SystemRepr::<u32>::constant(sys, 0);
+    let mut value = Vec::with_capacity(max_len);
+    for i in 0..=max_len {
+        let pos = add_const(sys, &start, i as u32);
+        let byte = read_byte(sys, headers, &pos);
+        let is_cr = eq_u8_const(sys, &byte, b'\r');
+        ended = SystemBitOr::<bool>::or(sys, &ended, &is_cr);
+        if i < max_len {
+            value.push(SystemSelect::<u8>::select(sys, &ended, &zero, &byte));
+            let next = add_const(sys, &len, 1);
+            len = SystemSelect::<u32>::select(sys, &ended, &len, &next);
+        }
+    }
+    valid = SystemBitAnd::<bool>::and(sys, &valid, &ended);
+    let end = SystemWrappingAdd::<u32>::wrapping_add(sys, &start, &len);
+    let limit = SystemRepr::<u32>::constant(sys, headers.len() as u32);
+    let in_bounds = SystemOrd::<u32>::lt(sys, &end, &limit);
+    valid = SystemBitAnd::<bool>::and(sys, &valid, &in_bounds);
+    let value = AbstractBytes::from_raw(value, len);
+    HeaderField { value, valid }
+}
+
+/// The result of [`dkim_verify`].
+pub struct DkimMessage<S: SystemRepr<bool> + SystemRepr<u8> + ?Sized> {
+    /// The EMSA-PKCS1-v1_5 encoding of the digest of the signed headers. The signature is valid
+    /// if and only if it is equal to `s^e mod n`, interpreted as a big-endian integer, where `s`
+    /// is the signature and `(n, e)` is the public key of the signer.
+    pub encoded: Vec<Abstract<S, u8>>,
+
+    /// Whether the body hash in the DKIM-Signature header matches the body.
+    pub valid: Abstract<S, bool>,
+}
+
+/// Checks a canonicalized email against the `bh=` tag of its DKIM-Signature header, and
+/// computes the message representative to be checked against the RSA signature over its headers.
+///
+/// `headers` are the canonicalized signed headers, ending with the DKIM-Signature header with
+/// the value of its `b=` tag removed, exactly as they are hashed by the signer. `bh_offset` is
+/// the position of the `bh=` tag within them. The lengths of the headers and body are fixed at
+/// synthesis time. Only `rsa-sha256` signatures are supported.
+///
+/// This does not check the RSA signature itself, since there is not yet a gadget for big
+/// integer modular arithmetic. Callers must check [`DkimMessage::encoded`] separately.
+pub fn dkim_verify<S: SystemEmail + ?Sized>(
+    sys: &mut S,
+    headers: &[Abstract<S, u8>],
+    body: &[Abstract<S, u8>],
+    bh_offset: &Abstract<S, u32>,
+    modulus_len: usize,
+) -> DkimMessage<S> {
+    assert!(
+        modulus_len >= SHA256_DIGEST_INFO.len() + 32 + 11,
+        "modulus is too short for EMSA-PKCS1-v1_5"
+    );
+
+    // Check the body hash
+    let buf = AbstractRam::new(headers.to_vec());
+    let mut valid = expect_bytes(sys, &buf, bh_offset, b"bh=");
+    let text: Vec<_> = (0..BODY_HASH_LEN)
+        .map(|i| {
+            let pos = add_const(sys, bh_offset, (3 + i) as u32);
+            read_byte(sys, &buf, &pos)
+        })
+        .collect();
+    let (claimed, text_valid) = base64_decode(sys, &text);
+    valid = SystemBitAnd::<bool>::and(sys, &valid, &text_valid);
+    let body_hash = sys.sha256_bytes(body);
+    let hash_eq = eq_bytes(sys, &claimed[..32], &body_hash);
+    valid = SystemBitAnd::<bool>::and(sys, &valid, &hash_eq);
+
+    // Encode the header hash
+    let digest = sys.sha256_bytes(headers);
+    let padding = modulus_len - SHA256_DIGEST_INFO.len() - 32 - 3;
+    let mut encoded = vec![sys.constant(0x00u8), sys.constant(0x01u8)];
+    for _ in 0..padding {
+        encoded.push(sys.constant(0xffu8));
+    }
+    encoded.push(sys.constant(0x00u8));
+    for byte in SHA256_DIGEST_INFO {
+        encoded.push(sys.constant(byte));
+    }
+    encoded.extend(digest);
+    DkimMessage { encoded, valid }
+}
+
+/// Determines whether the given bytes appear at the given offset in a buffer.
+fn expect_bytes<S: SystemEmail + ?Sized>(
+    sys: &mut S,
+    buf: &AbstractRam<S, u8>,
+    offset: &Abstract<S, u32>,
+    expected: &[u8],
+) -> Abstract<S, bool> {
+    let mut res = SystemRepr::<bool>::constant(sys, true);
+    for (i, expected) in expected.iter().enumerate() {
+        let pos = add_const(sys, offset, i as u32);
+        let byte = read_byte(sys, buf, &pos);
+        let eq = eq_u8_const(sys, &byte, *expected);
+        res = SystemBitAnd::<bool>::and(sys, &res, &eq);
+    }
+    let end = add_const(sys, offset, expected.len() as u32);
+    let limit = SystemRepr::<u32>::constant(sys, buf.len() as u32);
+    let in_bounds = SystemOrd::<u32>::le(sys, &end, &limit);
+    let no_overflow = SystemOrd::<u32>::le(sys, offset, &end);
+    let in_bounds = SystemBitAnd::<bool>::and(sys, &in_bounds, &no_overflow);
+    SystemBitAnd::<bool>::and(sys, &res, &in_bounds)
+}
+
+/// Reads the byte at the given offset in a buffer. For offsets outside of the buffer, the result
+/// is unspecified.
+fn read_byte<S: SystemEmail + ?Sized>(
+    sys: &mut S,
+    buf: &AbstractRam<S, u8>,
+    offset: &Abstract<S, u32>,
+) -> Abstract<S, u8> {
+    let bits = sys.bits_of_u32(offset);
+    let addr_bits = (usize::BITS - (buf.len() - 1).leading_zeros()) as usize;
+    buf.read(sys, &bits[..addr_bits])
+}
+
+/// Zero-extends a byte to a word.
+fn byte_to_u32<S: SystemEmail + ?Sized>(sys: &mut S, value: &Abstract<S, u8>) -> Abstract<S, u32> {
+    let bits = sys.bits_of_u8(value);
+    let bits: [_; 32] = array_init(|i| match i {
+        0..=7 => bits[i].clone(),
+        _ => SystemRepr::<bool>::constant(sys, false),
+    });
+    sys.u32_of_bits(&bits)
+}
+
+#[cfg(test)]
+fn base64_encode(data: &[u8]) -> Vec<u8> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut res = Vec::new();
+    for chunk in data.chunks(3) {
+        let mut group = [0u8; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let v = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                res.push(ALPHABET[(v >> (18 - 6 * i) & 63) as usize]);
+            } else {
+                res.push(b'=');
+            }
+        }
+    }
+    res
+}
+
+#[test]
+fn test_base64_decode() {
+    let (bytes, valid) = base64_decode(&mut Eval, b"aGVsbG8=");
+    assert!(valid);
+    assert_eq!(bytes, b"hello\0");
+    let data: Vec<u8> = (0..=255).collect();
+    let (bytes, valid) = base64_decode(&mut Eval, &base64_encode(&data));
+    assert!(valid);
+    assert_eq!(bytes[..256], data[..]);
+    let (_, valid) = base64_decode(&mut Eval, b"aGV*bG8=");
+    assert!(!valid);
+}
+
+#[test]
+fn test_dkim_verify() {
+    let body = b"Hello, world!\r\n".to_vec();
+    let mut headers = b"from:alice@example.com\r\nsubject:hello there\r\n".to_vec();
+    headers.extend(b"dkim-signature:v=1; a=rsa-sha256; d=example.com; s=sel; h=from:subject; ");
+    let bh_offset = headers.len() as u32;
+    headers.extend(b"bh=");
+    headers.extend(base64_encode(&Eval.sha256_bytes(&body)));
+    headers.extend(b"; b=");
+    let res = dkim_verify(&mut Eval, &headers, &body, &bh_offset, 128);
+    assert!(res.valid);
+    assert_eq!(res.encoded.len(), 128);
+    assert_eq!(res.encoded[..3], [0x00, 0x01, 0xff]);
+    assert_eq!(res.encoded[96..], Eval.sha256_bytes(&headers));
+
+    // The body can't be changed
+    let mut tampered = body.clone();
+    tampered[0] = b'J';
+    let res = dkim_verify(&mut Eval, &headers, &tampered, &bh_offset, 128);
+    assert!(!res.valid);
+    let res = dkim_verify(&mut Eval, &headers, &body, &(bh_offset + 1), 128);
+    assert!(!res.valid);
+
+    // Extract the subject
+    let buf = AbstractRam::new(headers);
+    let field = extract_header(&mut Eval, &buf, b"subject", &24, 16);
+    assert!(field.valid);
+    let len = *field.value.len() as usize;
+    assert_eq!(&field.value.data()[..len], b"hello there");
+    assert!(field.value.data()[len..].iter().all(|b| *b == 0));
+    assert!(!extract_header(&mut Eval, &buf, b"subject", &0, 16).valid);
+    assert!(!extract_header(&mut Eval, &buf, b"subject", &24, 8).valid);
+}
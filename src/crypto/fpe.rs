@@ -0,0 +1,273 @@
+//! Gadgets for format-preserving encryption using FF1, as specified in NIST SP 800-38G. This
+//! encrypts strings of digits in some radix to other strings of the same length, so a circuit can
+//! prove statements about the identifier behind a token, such as a card number, without
+//! revealing it.
+use crate::bitset::{add_bits, add_const, div_rem_const, num_bits, Bits};
+use crate::crypto::aes::SystemAes;
+use crate::*;
+use alloc::vec::Vec;
+use array_init::array_init;
+
+/// Encrypts and decrypts strings of digits using FF1 with AES. Each digit is a `u8` which must
+/// be less than the radix; digits which aren't will produce meaningless results.
+pub struct Ff1<S: SystemRepr<u8> + ?Sized> {
+    round_keys: Vec<[Abstract<S, u8>; 16]>,
+    radix: u32,
+}
+
+impl<S: SystemAes + ?Sized> Ff1<S> {
+    /// Constructs an [`Ff1`] cipher for digits in the given radix, which must be between 2 and
+    /// 256. The key must be 16, 24 or 32 bytes long, for AES-128, AES-192 and AES-256
+    /// respectively.
+    pub fn new(sys: &mut S, key: &[Abstract<S, u8>], radix: u32) -> Self {
+        assert!(
+            (2..=256).contains(&radix),
+            "radix must be between 2 and 256"
+        );
+        Self {
+            round_keys: sys.aes_key_schedule(key),
+            radix,
+        }
+    }
+
+    /// Encrypts a string of digits with the given tweak.
+    pub fn encrypt(
+        &self,
+        sys: &mut S,
+        tweak: &[Abstract<S, u8>],
+        digits: &[Abstract<S, u8>],
+    ) -> Vec<Abstract<S, u8>> {
+        self.apply(sys, tweak, digits, false)
+    }
+
+    /// Decrypts a string of digits with the given tweak.
+    pub fn decrypt(
+        &self,
+        sys: &mut S,
+        tweak: &[Abstract<S, u8>],
+        digits: &[Abstract<S, u8>],
+    ) -> Vec<Abstract<S, u8>> {
+        self.apply(sys, tweak, digits, true)
+    }
+
+    /// Applies the FF1 Feistel network to a string of digits, in the forward direction for
+    /// encryption, or in reverse for decryption.
+    fn apply(
+        &self,
+        sys: &mut S,
+        tweak: &[Abstract<S, u8>],
+        digits: &[Abstract<S, u8>],
+        decrypt: bool,
+    ) -> Vec<Abstract<S, u8>> {
+        let n = digits.len();
+        let radix = u128::from(self.radix);
+        assert!(
+            radix
+                .checked_pow(n as u32)
+                .is_none_or(|size| size >= 1_000_000),
+            "FF1 requires at least 1000000 possible inputs"
+        );
+        let (u, v) = (n / 2, n - n / 2);
+        let modulus = |m: usize| {
+            radix
+                .checked_pow(m as u32)
+                .filter(|size| *size < 1 << 126)
+                .expect("too many digits")
+        };
+        let b = num_bits(modulus(v) - 1).div_ceil(8);
+        let d = 4 * b.div_ceil(4) + 4;
+        let t = tweak.len();
+        let mut prefix = Vec::with_capacity(16);
+        prefix.extend_from_slice(&[1, 2, 1]);
+        prefix.extend_from_slice(&self.radix.to_be_bytes()[1..]);
+        prefix.extend_from_slice(&[10, u as u8]);
+        prefix.extend_from_slice(&(n as u32).to_be_bytes());
+        prefix.extend_from_slice(&(t as u32).to_be_bytes());
+        let prefix: [_; 16] = array_init(|i| sys.constant(prefix[i]));
+        let prefix = sys.aes_encrypt_block(&self.round_keys, &prefix);
+
+        // Each half is kept along with its numeral, so that it only needs to be computed once
+        let num = |sys: &mut S, digits: &[Abstract<S, u8>]| {
+            let value = self.num_radix(sys, digits);
+            (digits.to_vec(), value)
+        };
+        let mut a = num(sys, &digits[..u]);
+        let mut b_half = num(sys, &digits[u..]);
+        for round in 0..10u8 {
+            let i = if decrypt { 9 - round } else { round };
+            let source = if decrypt { &a.1 } else { &b_half.1 };
+
+            // Derive the round value from the tweak, the round number and one half
+            let mut q = tweak.to_vec();
+            q.extend((0..(16 - (t + b + 1) % 16) % 16).map(|_| sys.constant(0u8)));
+            q.push(sys.constant(i));
+            q.extend(to_bytes_be(sys, source, b));
+            let mut r = prefix.clone();
+            for chunk in q.chunks(16) {
+                let block = array_init(|j| SystemBitXor::<u8>::xor(sys, &r[j], &chunk[j]));
+                r = sys.aes_encrypt_block(&self.round_keys, &block);
+            }
+            let mut s = r.to_vec();
+            for j in 1..d.div_ceil(16) as u128 {
+                let counter = j.to_be_bytes();
+                let block = array_init(|k| {
+                    let byte = sys.constant(counter[k]);
+                    SystemBitXor::<u8>::xor(sys, &r[k], &byte)
+                });
+                s.extend(sys.aes_encrypt_block(&self.round_keys, &block));
+            }
+            let y: Bits<S> = (s[..d].iter().rev())
+                .flat_map(|byte| sys.bits_of_u8(byte))
+                .collect();
+
+            // Combine the round value with the other half
+            let m = if i % 2 == 0 { u } else { v };
+            let modulus = modulus(m);
+            let c = if decrypt {
+                // Subtract `y`, using the complement of its residue so that all terms are positive
+                let (_, y) = div_rem_const(sys, &y, modulus);
+                let not_y: Bits<S> = y.iter().map(|bit| sys.not(bit)).collect();
+                let excess = (1 << y.len()) % modulus;
+                let sum = add_bits(sys, &b_half.1, &not_y);
+                let sum = add_const(sys, &sum, 1 + (modulus - excess) % modulus);
+                div_rem_const(sys, &sum, modulus).1
+            } else {
+                let sum = add_bits(sys, &a.1, &y);
+                div_rem_const(sys, &sum, modulus).1
+            };
+            let c = (self.str_radix(sys, &c, m), c);
+            if decrypt {
+                b_half = core::mem::replace(&mut a, c);
+            } else {
+                a = core::mem::replace(&mut b_half, c);
+            }
+        }
+        let mut res = a.0;
+        res.extend(b_half.0);
+        res
+    }
+
+    /// Computes the integer represented by a string of digits, most significant first.
+    fn num_radix(&self, sys: &mut S, digits: &[Abstract<S, u8>]) -> Bits<S> {
+        let radix = u128::from(self.radix);
+        let digit_bits = num_bits(radix - 1);
+        let mut res: Bits<S> = Vec::new();
+        let mut bound = 1u128;
+        for digit in digits {
+            // Multiply by the radix by adding shifted copies of the current value
+            let zero = sys.constant(false);
+            let mut shifted = (0..u32::BITS - self.radix.leading_zeros())
+                .filter(|j| (self.radix >> j) & 1 == 1)
+                .map(|j| {
+                    let mut bits = alloc::vec![zero.clone(); j as usize];
+                    bits.extend_from_slice(&res);
+                    bits
+                });
+            let mut acc = shifted.next().unwrap();
+            for bits in shifted {
+                acc = add_bits(sys, &acc, &bits);
+            }
+            let digit = sys.bits_of_u8(digit);
+            res = add_bits(sys, &acc, &digit[..digit_bits]);
+
+            // Discard the high bits, which are zero if the digits are valid
+            bound *= radix;
+            res.truncate(num_bits(bound - 1));
+        }
+        res
+    }
+
+    /// Writes an integer less than `radix^len` as a string of `len` digits, most significant
+    /// first.
+    fn str_radix(
+        &self,
+        sys: &mut S,
+        value: &[Abstract<S, bool>],
+        len: usize,
+    ) -> Vec<Abstract<S, u8>> {
+        let mut value = value.to_vec();
+        let mut res = Vec::with_capacity(len);
+        for _ in 0..len {
+            let (quot, rem) = div_rem_const(sys, &value, u128::from(self.radix));
+            let bits: [_; 8] = array_init(|i| match rem.get(i) {
+                Some(bit) => bit.clone(),
+                None => sys.constant(false),
+            });
+            res.push(sys.u8_of_bits(&bits));
+            value = quot;
+        }
+        res.reverse();
+        res
+    }
+}
+
+/// Writes an integer as a big-endian string of `len` bytes. The integer must fit.
+fn to_bytes_be<S: SystemAes + ?Sized>(
+    sys: &mut S,
+    value: &[Abstract<S, bool>],
+    len: usize,
+) -> Vec<Abstract<S, u8>> {
+    (0..len)
+        .rev()
+        .map(|i| {
+            let bits: [_; 8] = array_init(|j| match value.get(8 * i + j) {
+                Some(bit) => bit.clone(),
+                None => sys.constant(false),
+            });
+            sys.u8_of_bits(&bits)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn hex(str: &str) -> Vec<u8> {
+    (0..str.len() / 2)
+        .map(|i| u8::from_str_radix(&str[i * 2..i * 2 + 2], 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_ff1() {
+    // Samples from NIST, using AES-128
+    let key = hex("2b7e151628aed2a6abf7158809cf4f3c");
+    let alphabet = "0123456789abcdefghijklmnopqrstuvwxyz";
+    let digits = |str: &str| -> Vec<u8> {
+        str.chars()
+            .map(|c| alphabet.find(c).unwrap() as u8)
+            .collect()
+    };
+    let samples = [
+        (10, "", "0123456789", "2433477484"),
+        (10, "39383736353433323130", "0123456789", "6124200773"),
+        (
+            36,
+            "3737373770717273373737",
+            "0123456789abcdefghi",
+            "a9tv40mll9kdu509eum",
+        ),
+    ];
+    for (radix, tweak, plaintext, ciphertext) in samples {
+        let ff1 = Ff1::new(&mut Eval, &key, radix);
+        let tweak = hex(tweak);
+        let res = ff1.encrypt(&mut Eval, &tweak, &digits(plaintext));
+        assert_eq!(res, digits(ciphertext));
+        let res = ff1.decrypt(&mut Eval, &tweak, &digits(ciphertext));
+        assert_eq!(res, digits(plaintext));
+    }
+
+    // Check consistency with a binary system
+    let mut sys = BinaryEmulate::new(Eval);
+    let key: Vec<_> = key.iter().map(|byte| sys.constant(*byte)).collect();
+    let ff1 = Ff1::new(&mut sys, &key, 10);
+    let tweak: Vec<_> = (hex("39383736353433323130").into_iter())
+        .map(|byte| sys.constant(byte))
+        .collect();
+    let plaintext: Vec<_> = (digits("0123456789").into_iter())
+        .map(|digit| sys.constant(digit))
+        .collect();
+    let res = ff1.encrypt(&mut sys, &tweak, &plaintext);
+    let expected: Vec<_> = (digits("6124200773").into_iter())
+        .map(|digit| sys.constant(digit))
+        .collect();
+    assert_eq!(res, expected);
+}
@@ -0,0 +1,28 @@
+use crate::*;
+
+/// A 2-to-1 compression function, which hashes two nodes into one, as used to build Merkle trees
+/// and accumulators. Values of this type hold whatever parameters the function needs.
+pub trait Compress2to1 {
+    /// The type of the nodes hashed by this function.
+    type Node: Clone;
+}
+
+/// A system in which nodes can be hashed using the compression function `H`. Gadgets which are
+/// generic over this trait can be instantiated with whichever function is cheapest for the
+/// system, such as Poseidon for arithmetic systems.
+pub trait SystemCompress2to1<H: Compress2to1>: SystemRepr<H::Node> + SystemRepr<bool> {
+    /// Hashes two nodes into one.
+    fn compress2to1(
+        &mut self,
+        hash: &H,
+        left: &Abstract<Self, H::Node>,
+        right: &Abstract<Self, H::Node>,
+    ) -> Abstract<Self, H::Node>;
+
+    /// Determines whether two nodes are equal.
+    fn node_eq(
+        &mut self,
+        a: &Abstract<Self, H::Node>,
+        b: &Abstract<Self, H::Node>,
+    ) -> Abstract<Self, bool>;
+}
@@ -0,0 +1,175 @@
+use crate::*;
+use crate::crypto::tables;
+use alloc::vec::Vec;
+use array_init::array_init;
+
+/// The number of bytes absorbed by each application of the Keccak-256 permutation.
+const KECCAK_256_RATE: usize = 136;
+
+/// A system in which Keccak hashes can be computed.
+pub trait SystemKeccak:
+    SystemPack + SystemBitAnd<u64> + SystemBitXor<u64> + SystemBitRotate<u64, u8> + SystemNot<u64>
+{
+    /// Applies the Keccak-f\[1600\] permutation to the given state, whose lanes are indexed by
+    /// `x + 5 * y`.
+    fn keccak_f1600(&mut self, state: &mut [Abstract<Self, u64>; 25]) {
+        keccak_f(self, state, &RHO, &RC)
+    }
+
+    /// Computes the Keccak-256 hash of the given data, as used by Ethereum. This is the original
+    /// Keccak submission, which differs from SHA3-256 in its padding.
+    fn keccak256(&mut self, data: &[Abstract<Self, u8>]) -> [Abstract<Self, u8>; 32] {
+        let mut padded = data.to_vec();
+        let pad_len = KECCAK_256_RATE - data.len() % KECCAK_256_RATE;
+        for i in 0..pad_len {
+            let mut byte = 0u8;
+            if i == 0 {
+                byte |= 0x01;
+            }
+            if i == pad_len - 1 {
+                byte |= 0x80;
+            }
+            padded.push(self.constant(byte));
+        }
+        let mut state: [_; 25] = array_init(|_| self.constant(0u64));
+        for block in padded.chunks(KECCAK_256_RATE) {
+            for (lane, bytes) in state.iter_mut().zip(block.chunks(8)) {
+                let bytes: [_; 8] = array_init(|i| bytes[i].clone());
+                let value = self.pack_le_u64(&bytes);
+                *lane = self.xor(lane, &value);
+            }
+            self.keccak_f1600(&mut state);
+        }
+        let mut res = Vec::with_capacity(32);
+        for lane in &state[..4] {
+            res.extend(self.unpack_le_u64(lane));
+        }
+        array_init(|i| res[i].clone())
+    }
+}
+
+impl<
+        S: SystemPack
+            + SystemBitAnd<u64>
+            + SystemBitXor<u64>
+            + SystemBitRotate<u64, u8>
+            + SystemNot<u64>,
+    > SystemKeccak for S
+{
+}
+
+/// A system in which the Keccak-f\[800\] permutation, whose lanes are 32 bits wide, can be
+/// computed. Unlike [`SystemKeccak`], this only needs operations on `u32`, which makes it a
+/// cheaper option for backends where wider words are expensive.
+pub trait SystemKeccak800:
+    SystemBitAnd<u32> + SystemBitXor<u32> + SystemBitRotate<u32, u8> + SystemNot<u32>
+{
+    /// Applies the Keccak-f\[800\] permutation to the given state, whose lanes are indexed by
+    /// `x + 5 * y`.
+    fn keccak_f800(&mut self, state: &mut [Abstract<Self, u32>; 25]) {
+        // The round constants are those of Keccak-f[1600], truncated to the lane width, and
+        // there are two fewer rounds
+        let rho = RHO.map(|offset| offset % 32);
+        let rc: [u32; 22] = array_init(|i| RC[i] as u32);
+        keccak_f(self, state, &rho, &rc)
+    }
+}
+
+impl<S: SystemBitAnd<u32> + SystemBitXor<u32> + SystemBitRotate<u32, u8> + SystemNot<u32>>
+    SystemKeccak800 for S
+{
+}
+
+/// Applies a Keccak-f permutation with lanes of type `T`, using the given rotation offsets and
+/// round constants.
+fn keccak_f<T: Copy, S>(sys: &mut S, state: &mut [Abstract<S, T>; 25], rho: &[u8; 25], rc: &[T])
+where
+    S: SystemBitAnd<T> + SystemBitXor<T> + SystemBitRotate<T, u8> + SystemNot<T> + ?Sized,
+{
+    for rc in rc {
+        // θ step
+        let c: [_; 5] = array_init(|x| {
+            let t = sys.xor(&state[x], &state[x + 5]);
+            let t = sys.xor(&t, &state[x + 10]);
+            let t = sys.xor(&t, &state[x + 15]);
+            sys.xor(&t, &state[x + 20])
+        });
+        for x in 0..5 {
+            let t = sys.rotl(&c[(x + 1) % 5], 1);
+            let d = sys.xor(&c[(x + 4) % 5], &t);
+            for y in 0..5 {
+                state[x + 5 * y] = sys.xor(&state[x + 5 * y], &d);
+            }
+        }
+
+        // ρ and π steps
+        let mut b = state.clone();
+        for x in 0..5 {
+            for y in 0..5 {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] = sys.rotl(&state[x + 5 * y], rho[x + 5 * y]);
+            }
+        }
+
+        // χ step
+        for x in 0..5 {
+            for y in 0..5 {
+                let t = sys.not(&b[(x + 1) % 5 + 5 * y]);
+                let t = sys.and(&t, &b[(x + 2) % 5 + 5 * y]);
+                state[x + 5 * y] = sys.xor(&b[x + 5 * y], &t);
+            }
+        }
+
+        // ι step
+        let rc = sys.constant(*rc);
+        state[0] = sys.xor(&state[0], &rc);
+    }
+}
+
+/// The rotation offsets for the ρ step, indexed by `x + 5 * y`.
+const RHO: [u8; 25] = tables::keccak_rho();
+
+/// The round constants for the ι step.
+const RC: [u64; 24] = tables::keccak_round_constants();
+
+#[cfg(test)]
+fn hex(bytes: &[u8]) -> alloc::string::String {
+    bytes.iter().map(|b| alloc::format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn test_keccak256() {
+    assert_eq!(
+        hex(&Eval.keccak256(&[])),
+        "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+    );
+    assert_eq!(
+        hex(&Eval.keccak256(b"abc")),
+        "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+    );
+
+    // Cross a block boundary
+    let data = [0x61u8; 200];
+    let mut sys = BinaryEmulate::new(Eval);
+    let abstract_data: Vec<_> = data.iter().map(|b| sys.constant(*b)).collect();
+    let hash = sys.keccak256(&abstract_data);
+    let expected = Eval.keccak256(&data);
+    assert_eq!(hash, expected.map(|b| sys.constant(b)));
+}
+
+#[test]
+fn test_keccak_f800() {
+    // Applying the permutation to the all-zero state, as in the KeccakF-800 intermediate values
+    let mut state = [0u32; 25];
+    Eval.keccak_f800(&mut state);
+    assert_eq!(state[..4], [0xe531d45d, 0xf404c6fb, 0x23a0bf99, 0xf1f8452f]);
+    assert_eq!(state[24], 0x11efe996);
+
+    // Binary emulation should agree
+    let mut sys = BinaryEmulate::new(Eval);
+    let mut abstract_state: [_; 25] = array_init(|i| sys.constant(i as u32));
+    sys.keccak_f800(&mut abstract_state);
+    let mut state: [u32; 25] = array_init(|i| i as u32);
+    Eval.keccak_f800(&mut state);
+    assert_eq!(state[0], 0xe42f30cf);
+    assert_eq!(abstract_state, state.map(|lane| sys.constant(lane)));
+}
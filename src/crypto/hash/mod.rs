@@ -1,3 +1,23 @@
+mod compress;
+#[cfg(feature = "keccak")]
+mod keccak;
+#[cfg(feature = "poseidon")]
+mod poseidon;
+#[cfg(feature = "sha2")]
 mod sha2;
+#[cfg(feature = "siphash")]
+mod siphash;
+#[cfg(feature = "xoodoo")]
+mod xoodoo;
 
-pub use sha2::*;
\ No newline at end of file
+pub use compress::*;
+#[cfg(feature = "keccak")]
+pub use keccak::*;
+#[cfg(feature = "poseidon")]
+pub use poseidon::*;
+#[cfg(feature = "sha2")]
+pub use sha2::*;
+#[cfg(feature = "siphash")]
+pub use siphash::*;
+#[cfg(feature = "xoodoo")]
+pub use xoodoo::*;
@@ -0,0 +1,185 @@
+use crate::crypto::hash::{Compress2to1, SystemCompress2to1, SystemSha256Bytes};
+use crate::field::FieldElement;
+use crate::*;
+use alloc::vec::Vec;
+use ff::PrimeField;
+
+/// The number of full rounds in the Poseidon permutation, split evenly before and after the
+/// partial rounds.
+const FULL_ROUNDS: usize = 8;
+
+/// The parameters of a Poseidon permutation over the field `F` with S-box `x^5`, which must be a
+/// permutation of `F`, as it is for the scalar fields of BLS12-381 and BN254.
+///
+/// Round constants are derived by hashing their position with SHA-256, and the MDS matrix is a
+/// Cauchy matrix. These are not the constants of the reference instantiation, so hashes are not
+/// compatible with other Poseidon implementations.
+#[derive(Debug, Clone)]
+pub struct PoseidonParams<F> {
+    partial_rounds: usize,
+    round_constants: Vec<Vec<F>>,
+    mds: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> PoseidonParams<F> {
+    /// Constructs parameters for a permutation of the given width with the given number of
+    /// partial rounds.
+    pub fn new(width: usize, partial_rounds: usize) -> Self {
+        assert!(width >= 2, "width must be at least 2");
+        let round_constants = (0..FULL_ROUNDS + partial_rounds)
+            .map(|round| {
+                (0..width)
+                    .map(|i| {
+                        let mut seed = b"circus poseidon".to_vec();
+                        seed.extend((width as u32).to_le_bytes());
+                        seed.extend((round as u32).to_le_bytes());
+                        seed.extend((i as u32).to_le_bytes());
+                        Eval.sha256_bytes(&seed)
+                            .iter()
+                            .fold(F::zero(), |acc, byte| {
+                                acc * F::from(256) + F::from(*byte as u64)
+                            })
+                    })
+                    .collect()
+            })
+            .collect();
+        let mds = (0..width)
+            .map(|i| {
+                (0..width)
+                    .map(|j| {
+                        let sum = F::from(i as u64) + F::from((width + j) as u64);
+                        sum.invert().unwrap()
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            partial_rounds,
+            round_constants,
+            mds,
+        }
+    }
+
+    /// Constructs the parameters for a width-3 permutation, suitable for 2-to-1 hashing, with
+    /// the number of rounds recommended for 128-bit security over a 255-bit field.
+    pub fn width_3() -> Self {
+        Self::new(3, 57)
+    }
+
+    /// The number of field elements in the state of the permutation.
+    pub fn width(&self) -> usize {
+        self.mds.len()
+    }
+}
+
+/// A system in which Poseidon hashes over the field `F` can be computed.
+pub trait SystemPoseidon<F: PrimeField>:
+    SystemAdd<FieldElement<F>> + SystemMul<FieldElement<F>>
+{
+    /// Applies the Poseidon permutation to the given state.
+    fn poseidon_permute(
+        &mut self,
+        params: &PoseidonParams<F>,
+        state: &mut [Abstract<Self, FieldElement<F>>],
+    ) {
+        assert_eq!(state.len(), params.width(), "state has the wrong width");
+        let half = FULL_ROUNDS / 2;
+        for (round, constants) in params.round_constants.iter().enumerate() {
+            let full = round < half || round >= half + params.partial_rounds;
+            for (i, (value, c)) in state.iter_mut().zip(constants.iter()).enumerate() {
+                let c = self.constant(FieldElement(*c));
+                *value = self.add(value, &c);
+                if full || i == 0 {
+                    let sq = self.mul(value, value);
+                    let quad = self.mul(&sq, &sq);
+                    *value = self.mul(&quad, value);
+                }
+            }
+            let mixed: Vec<_> = params
+                .mds
+                .iter()
+                .map(|row| {
+                    let mut acc = self.constant(FieldElement(F::zero()));
+                    for (value, m) in state.iter().zip(row.iter()) {
+                        let m = self.constant(FieldElement(*m));
+                        let term = self.mul(&m, value);
+                        acc = self.add(&acc, &term);
+                    }
+                    acc
+                })
+                .collect();
+            state.clone_from_slice(&mixed);
+        }
+    }
+
+    /// Hashes two field elements into one using a width-3 permutation.
+    fn poseidon_hash2(
+        &mut self,
+        params: &PoseidonParams<F>,
+        a: &Abstract<Self, FieldElement<F>>,
+        b: &Abstract<Self, FieldElement<F>>,
+    ) -> Abstract<Self, FieldElement<F>> {
+        let mut state = [self.constant(FieldElement(F::zero())), a.clone(), b.clone()];
+        self.poseidon_permute(params, &mut state);
+        let [_, res, _] = state;
+        res
+    }
+}
+
+impl<F: PrimeField, S: SystemAdd<FieldElement<F>> + SystemMul<FieldElement<F>> + ?Sized>
+    SystemPoseidon<F> for S
+{
+}
+
+/// Poseidon parameters of width 3 define a compression function, using
+/// [`SystemPoseidon::poseidon_hash2`].
+impl<F: PrimeField> Compress2to1 for PoseidonParams<F> {
+    type Node = FieldElement<F>;
+}
+
+impl<F: PrimeField, S: SystemPoseidon<F> + SystemInverse<FieldElement<F>> + ?Sized>
+    SystemCompress2to1<PoseidonParams<F>> for S
+{
+    fn compress2to1(
+        &mut self,
+        hash: &PoseidonParams<F>,
+        left: &Abstract<Self, FieldElement<F>>,
+        right: &Abstract<Self, FieldElement<F>>,
+    ) -> Abstract<Self, FieldElement<F>> {
+        self.poseidon_hash2(hash, left, right)
+    }
+
+    fn node_eq(
+        &mut self,
+        a: &Abstract<Self, FieldElement<F>>,
+        b: &Abstract<Self, FieldElement<F>>,
+    ) -> Abstract<Self, bool> {
+        let neg = SystemRepr::<FieldElement<F>>::constant(self, FieldElement(-F::one()));
+        let neg_b = SystemMul::<FieldElement<F>>::mul(self, &neg, b);
+        let diff = SystemAdd::<FieldElement<F>>::add(self, a, &neg_b);
+        SystemInverse::<FieldElement<F>>::is_zero(self, &diff)
+    }
+}
+
+#[cfg(feature = "r1cs")]
+#[test]
+fn test_poseidon() {
+    use crate::r1cs::{ArithmeticSystem, Formula};
+    use bls12_381::Scalar;
+    let params = PoseidonParams::<Scalar>::width_3();
+    let x = FieldElement(Scalar::from(1));
+    let y = FieldElement(Scalar::from(2));
+    let hash = Eval.poseidon_hash2(&params, &x, &y);
+    assert_ne!(hash, Eval.poseidon_hash2(&params, &y, &x));
+    assert_ne!(hash, Eval.poseidon_hash2(&params, &x, &x));
+
+    // The arithmetic system should agree, using 3 constraints per S-box, except for the first
+    // S-box on the capacity element, which is applied to a constant
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let vars = [sys.declare(), sys.declare()];
+    let res = sys.poseidon_hash2(&params, &Formula::from(vars[0]), &Formula::from(vars[1]));
+    assert_eq!(sys.num_vars(), 2 + 3 * (3 * FULL_ROUNDS + 57 - 1));
+    let assignment = sys.solve(&[(vars[0], x.0), (vars[1], y.0)]).unwrap();
+    assert_eq!(sys.check(&assignment), Ok(()));
+    assert_eq!(sys.eval(res, &assignment), hash.0);
+}
@@ -1,10 +1,13 @@
 use crate::*;
+use crate::crypto::hash::{Compress2to1, SystemCompress2to1};
+use crate::crypto::tables;
+use alloc::vec::Vec;
 use array_init::array_init;
 
 /// Encapsulates a SHA-256 digest, or the state of a SHA-256 hasher at a certain point in its
 /// input.
 #[derive(PartialEq, Eq, Clone, Copy)]
-pub struct Sha256([u32; 8]);
+pub struct Sha256(pub(crate) [u32; 8]);
 
 impl Sha256 {
     /// Constructs a SHA-256 hasher in its initial state.
@@ -30,8 +33,8 @@ impl Sha256 {
     }
 }
 
-impl std::fmt::Debug for Sha256 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for Sha256 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
             "{:x}{:x}{:x}{:x}{:x}{:x}{:x}{:x}",
@@ -49,17 +52,31 @@ impl std::fmt::Debug for Sha256 {
 
 impl<S: SystemRepr<u32> + ?Sized> SystemRepr<Sha256> for S {
     type Abstract = [Abstract<Self, u32>; 8];
+    type Error = SystemError<Self, u32>;
     fn constant(&mut self, value: Sha256) -> Self::Abstract {
         value.0.map(|h| self.constant(h))
     }
+
+    fn status(&self) -> Result<(), Self::Error> {
+        SystemRepr::<u32>::status(self)
+    }
+}
+
+impl<S: SystemRead<u32> + ?Sized> SystemRead<Sha256> for S {
+    fn read_value(&self, value: &Abstract<Self, Sha256>) -> Sha256 {
+        Sha256(value.each_ref().map(|h| self.read_value(h)))
+    }
 }
 
-/// A system in which SHA-256 hashes can be computed.
+/// A system in which SHA-256 hashes can be computed. The cost depends on how the system implements
+/// the word operations: `ArithmeticSystem` performs additions in the field, which
+/// makes a block several times cheaper than with [`BinaryEmulate`].
 pub trait SystemSha256:
     SystemRepr<u32>
     + SystemWrappingAdd<u32>
     + SystemBitAnd<u32>
     + SystemBitXor<u32>
+    + SystemBitSelect<u32>
     + SystemBitRotate<u32, u8>
     + SystemBitShift<u32, u8>
     + SystemNot<u32>
@@ -71,86 +88,18 @@ pub trait SystemSha256:
 
     /// Updates a SHA-256 hasher with the next chunk of data.
     fn sha256_update(&mut self, hasher: &mut Abstract<Self, Sha256>, chunk: [u32; 16]) {
-        // Initialize message schedule
-        let mut w: [_; 64] = array_init(|_| self.constant(0));
-        for i in 0..16 {
-            w[i] = self.constant(chunk[i]);
-        }
-        for i in 16..64 {
-            let s0 = &w[i - 15];
-            let t0 = self.rotr(s0, 7);
-            let t1 = self.rotr(s0, 18);
-            let t2 = self.shr(s0, 3);
-            let s0 = self.xor(&t0, &t1);
-            let s0 = self.xor(&s0, &t2);
-            let s1 = &w[i - 2];
-            let t0 = self.rotr(s1, 17);
-            let t1 = self.rotr(s1, 19);
-            let t2 = self.shr(s1, 10);
-            let s1 = self.xor(&t0, &t1);
-            let s1 = self.xor(&s1, &t2);
-            let r = self.wrapping_add(&w[i - 16], &s0);
-            let r = self.wrapping_add(&r, &w[i - 7]);
-            let r = self.wrapping_add(&r, &s1);
-            w[i] = r;
-        }
-
-        // Initialize working variables
-        let mut a = hasher[0].clone();
-        let mut b = hasher[1].clone();
-        let mut c = hasher[2].clone();
-        let mut d = hasher[3].clone();
-        let mut e = hasher[4].clone();
-        let mut f = hasher[5].clone();
-        let mut g = hasher[6].clone();
-        let mut h = hasher[7].clone();
-
-        // Compression function main loop
-        for i in 0..64 {
-            let t0 = self.rotr(&e, 6);
-            let t1 = self.rotr(&e, 11);
-            let t2 = self.rotr(&e, 25);
-            let s1 = self.xor(&t0, &t1);
-            let s1 = self.xor(&s1, &t2);
-            let t0 = self.and(&e, &f);
-            let t1 = self.not(&e);
-            let t2 = self.and(&t1, &g);
-            let ch = self.xor(&t0, &t2);
-            let k = self.constant(K[i]);
-            let temp1 = self.wrapping_add(&h, &s1);
-            let temp1 = self.wrapping_add(&temp1, &ch);
-            let temp1 = self.wrapping_add(&temp1, &k);
-            let temp1 = self.wrapping_add(&temp1, &w[i]);
-            let t0 = self.rotr(&a, 2);
-            let t1 = self.rotr(&a, 13);
-            let t2 = self.rotr(&a, 22);
-            let s0 = self.xor(&t0, &t1);
-            let s0 = self.xor(&s0, &t2);
-            let t0 = self.and(&a, &b);
-            let t1 = self.and(&a, &c);
-            let t2 = self.and(&b, &c);
-            let maj = self.xor(&t0, &t1);
-            let maj = self.xor(&maj, &t2);
-            let temp2 = self.wrapping_add(&s0, &maj);
-            h = g;
-            g = f;
-            f = e;
-            e = self.wrapping_add(&d, &temp1);
-            d = c;
-            c = b;
-            b = a;
-            a = self.wrapping_add(&temp1, &temp2);
-        }
+        let chunk = chunk.map(|word| self.constant(word));
+        self.sha256_update_abstract(hasher, &chunk)
+    }
 
-        // Add to current hash value
-        hasher[0] = self.wrapping_add(&hasher[0], &a);
-        hasher[1] = self.wrapping_add(&hasher[1], &b);
-        hasher[2] = self.wrapping_add(&hasher[2], &c);
-        hasher[3] = self.wrapping_add(&hasher[3], &d);
-        hasher[4] = self.wrapping_add(&hasher[4], &e);
-        hasher[5] = self.wrapping_add(&hasher[5], &f);
-        hasher[6] = self.wrapping_add(&hasher[6], &g);
-        hasher[7] = self.wrapping_add(&hasher[7], &h);
+    /// Updates a SHA-256 hasher with the next chunk of data, given as abstract big-endian words.
+    fn sha256_update_abstract(
+        &mut self,
+        hasher: &mut Abstract<Self, Sha256>,
+        chunk: &[Abstract<Self, u32>; 16],
+    ) {
+        let k = K.map(|k| self.constant(k));
+        compress(self, hasher, chunk, &k)
     }
 }
 
@@ -159,6 +108,7 @@ impl<
             + SystemWrappingAdd<u32>
             + SystemBitAnd<u32>
             + SystemBitXor<u32>
+            + SystemBitSelect<u32>
             + SystemBitRotate<u32, u8>
             + SystemBitShift<u32, u8>
             + SystemNot<u32>,
@@ -166,20 +116,174 @@ impl<
 {
 }
 
-const H: [u32; 8] = [
-    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
-];
-
-const K: [u32; 64] = [
-    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
-    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
-    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
-    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
-    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
-    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
-    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
-    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
-];
+/// A system in which SHA-256 hashes of byte strings can be computed.
+pub trait SystemSha256Bytes: SystemSha256 + SystemPack {
+    /// Computes the SHA-256 digest of the given data. The length of the data is fixed at
+    /// synthesis time.
+    fn sha256_bytes(&mut self, data: &[Abstract<Self, u8>]) -> [Abstract<Self, u8>; 32] {
+        self.sha256_batch(&[data]).pop().unwrap()
+    }
+
+    /// Computes the SHA-256 digests of several independent messages, whose lengths are fixed at
+    /// synthesis time. This is equivalent to calling [`SystemSha256Bytes::sha256_bytes`] on each
+    /// message, except that the round constants, initial state and padding bytes are introduced
+    /// into the system once and shared by every message.
+    fn sha256_batch(
+        &mut self,
+        messages: &[&[Abstract<Self, u8>]],
+    ) -> Vec<[Abstract<Self, u8>; 32]> {
+        let k = K.map(|k| self.constant(k));
+        let init = self.sha256_new();
+        let marker = self.constant(0x80u8);
+        let zero = self.constant(0u8);
+        let mut digests = Vec::with_capacity(messages.len());
+        for data in messages {
+            let mut padded = data.to_vec();
+            padded.push(marker.clone());
+            while padded.len() % 64 != 56 {
+                padded.push(zero.clone());
+            }
+            for byte in ((data.len() as u64) * 8).to_be_bytes() {
+                padded.push(self.constant(byte));
+            }
+            let mut hasher = init.clone();
+            for chunk in padded.chunks(64) {
+                let words: [_; 16] = array_init(|i| {
+                    let bytes: [_; 4] = array_init(|j| chunk[i * 4 + j].clone());
+                    self.pack_be_u32(&bytes)
+                });
+                compress(self, &mut hasher, &words, &k);
+            }
+            let mut res = Vec::with_capacity(32);
+            for word in hasher.iter() {
+                res.extend(self.unpack_be_u32(word));
+            }
+            digests.push(array_init(|i| res[i].clone()));
+        }
+        digests
+    }
+}
+
+impl<S: SystemSha256 + SystemPack> SystemSha256Bytes for S {}
+
+/// The SHA-256 compression function, applied to the initial hasher state with a chunk consisting
+/// of the two nodes. No padding is applied, so this is not the SHA-256 digest of the
+/// concatenated nodes, but it is collision-resistant for fixed-size inputs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Compress;
+
+impl Compress2to1 for Sha256Compress {
+    type Node = [u32; 8];
+}
+
+impl<S: SystemSha256 + SystemBits + BinarySystem> SystemCompress2to1<Sha256Compress> for S {
+    fn compress2to1(
+        &mut self,
+        _: &Sha256Compress,
+        left: &[Abstract<Self, u32>; 8],
+        right: &[Abstract<Self, u32>; 8],
+    ) -> [Abstract<Self, u32>; 8] {
+        let mut hasher = self.sha256_new();
+        let chunk = array_init(|i| if i < 8 { left[i].clone() } else { right[i - 8].clone() });
+        self.sha256_update_abstract(&mut hasher, &chunk);
+        hasher
+    }
+
+    fn node_eq(
+        &mut self,
+        a: &[Abstract<Self, u32>; 8],
+        b: &[Abstract<Self, u32>; 8],
+    ) -> Abstract<Self, bool> {
+        let mut any = SystemRepr::<bool>::constant(self, false);
+        for (a, b) in a.iter().zip(b.iter()) {
+            let diff = SystemBitXor::<u32>::xor(self, a, b);
+            for bit in self.bits_of_u32(&diff).iter() {
+                any = SystemBitOr::<bool>::or(self, &any, bit);
+            }
+        }
+        SystemNot::<bool>::not(self, &any)
+    }
+}
+
+/// Applies the SHA-256 compression function to a hasher state and a chunk of big-endian words,
+/// using the given round constants, which should be `K` introduced into the system.
+fn compress<S: SystemSha256 + ?Sized>(
+    sys: &mut S,
+    hasher: &mut Abstract<S, Sha256>,
+    chunk: &[Abstract<S, u32>; 16],
+    k: &[Abstract<S, u32>; 64],
+) {
+    // Initialize message schedule
+    let mut w: [_; 64] = array_init(|_| sys.constant(0));
+    w[..16].clone_from_slice(chunk);
+    for i in 16..64 {
+        let s0 = &w[i - 15];
+        let t0 = sys.rotr(s0, 7);
+        let t1 = sys.rotr(s0, 18);
+        let t2 = sys.shr(s0, 3);
+        let s0 = sys.xor(&t0, &t1);
+        let s0 = sys.xor(&s0, &t2);
+        let s1 = &w[i - 2];
+        let t0 = sys.rotr(s1, 17);
+        let t1 = sys.rotr(s1, 19);
+        let t2 = sys.shr(s1, 10);
+        let s1 = sys.xor(&t0, &t1);
+        let s1 = sys.xor(&s1, &t2);
+        w[i] = sys.sum_many(&[w[i - 16].clone(), s0, w[i - 7].clone(), s1]);
+    }
+
+    // Initialize working variables
+    let mut a = hasher[0].clone();
+    let mut b = hasher[1].clone();
+    let mut c = hasher[2].clone();
+    let mut d = hasher[3].clone();
+    let mut e = hasher[4].clone();
+    let mut f = hasher[5].clone();
+    let mut g = hasher[6].clone();
+    let mut h = hasher[7].clone();
+
+    // Compression function main loop
+    for i in 0..64 {
+        let t0 = sys.rotr(&e, 6);
+        let t1 = sys.rotr(&e, 11);
+        let t2 = sys.rotr(&e, 25);
+        let s1 = sys.xor(&t0, &t1);
+        let s1 = sys.xor(&s1, &t2);
+        let ch = sys.bit_select(&e, &f, &g);
+        let temp1 = sys.sum_many(&[h, s1, ch, k[i].clone(), w[i].clone()]);
+        let t0 = sys.rotr(&a, 2);
+        let t1 = sys.rotr(&a, 13);
+        let t2 = sys.rotr(&a, 22);
+        let s0 = sys.xor(&t0, &t1);
+        let s0 = sys.xor(&s0, &t2);
+        // Where `a` and `b` differ, the majority is decided by `c`
+        let t0 = sys.xor(&a, &b);
+        let maj = sys.bit_select(&t0, &c, &a);
+        let temp2 = sys.wrapping_add(&s0, &maj);
+        h = g;
+        g = f;
+        f = e;
+        e = sys.wrapping_add(&d, &temp1);
+        d = c;
+        c = b;
+        b = a;
+        a = sys.wrapping_add(&temp1, &temp2);
+    }
+
+    // Add to current hash value
+    hasher[0] = sys.wrapping_add(&hasher[0], &a);
+    hasher[1] = sys.wrapping_add(&hasher[1], &b);
+    hasher[2] = sys.wrapping_add(&hasher[2], &c);
+    hasher[3] = sys.wrapping_add(&hasher[3], &d);
+    hasher[4] = sys.wrapping_add(&hasher[4], &e);
+    hasher[5] = sys.wrapping_add(&hasher[5], &f);
+    hasher[6] = sys.wrapping_add(&hasher[6], &g);
+    hasher[7] = sys.wrapping_add(&hasher[7], &h);
+}
+
+const H: [u32; 8] = tables::sha256_initial_state();
+
+const K: [u32; 64] = tables::sha256_round_constants();
 
 #[test]
 fn test_empty() {
@@ -190,14 +294,48 @@ fn test_empty() {
     assert_eq!(hasher, target);
 }
 
-/*
 #[test]
 fn test_empty_binary_emulate() {
     let mut sys = BinaryEmulate::new(Eval);
-    let mut hasher = sys.constant(Sha256::new());
+    let mut hasher = sys.sha256_new();
     sys.sha256_update(&mut hasher, [0x80000000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
     let target = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
     let target = sys.constant(Sha256::from_str(target).unwrap());
-    sys.assert_eq(hasher, target);
+    assert_eq!(hasher, target);
+}
+
+#[test]
+fn test_sha256_bytes() {
+    let digest = Eval.sha256_bytes(b"abc");
+    let target = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    let target: Vec<u8> = (0..32)
+        .map(|i| u8::from_str_radix(&target[i * 2..i * 2 + 2], 16).unwrap())
+        .collect();
+    assert_eq!(digest[..], target);
+    let mut sys = BinaryEmulate::new(Eval);
+    let data: Vec<u8> = (0..100).collect();
+    let abstract_data: Vec<_> = data.iter().map(|b| sys.constant(*b)).collect();
+    let digest = sys.sha256_bytes(&abstract_data);
+    let expected = Eval.sha256_bytes(&data);
+    assert_eq!(digest, expected.map(|b| sys.constant(b)));
+}
+
+#[test]
+fn test_sha256_batch() {
+    let messages: [Vec<u8>; 3] = [b"abc".to_vec(), Vec::new(), (0..100).collect()];
+    let refs: Vec<&[u8]> = messages.iter().map(|m| m.as_slice()).collect();
+    let digests = Eval.sha256_batch(&refs);
+    assert_eq!(digests.len(), 3);
+    for (message, digest) in messages.iter().zip(digests.iter()) {
+        assert_eq!(*digest, Eval.sha256_bytes(message));
+    }
+    let mut sys = BinaryEmulate::new(Eval);
+    let abstract_messages: Vec<Vec<_>> = (messages.iter())
+        .map(|m| m.iter().map(|b| sys.constant(*b)).collect())
+        .collect();
+    let refs: Vec<&[_]> = abstract_messages.iter().map(|m| m.as_slice()).collect();
+    let digests = sys.sha256_batch(&refs);
+    for (message, digest) in messages.iter().zip(digests) {
+        assert_eq!(digest, Eval.sha256_bytes(message).map(|b| sys.constant(b)));
+    }
 }
-*/
\ No newline at end of file
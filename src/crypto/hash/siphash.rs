@@ -0,0 +1,97 @@
+use crate::*;
+use alloc::vec::Vec;
+use array_init::array_init;
+
+/// A system in which SipHash-2-4 can be computed.
+pub trait SystemSipHash:
+    SystemPack + SystemWrappingAdd<u64> + SystemBitXor<u64> + SystemBitRotate<u64, u8>
+{
+    /// Computes the SipHash-2-4 of the given data with the given 128-bit key, given as two
+    /// little-endian words. The length of the data is fixed at synthesis time.
+    fn siphash24(
+        &mut self,
+        key: &[Abstract<Self, u64>; 2],
+        data: &[Abstract<Self, u8>],
+    ) -> Abstract<Self, u64> {
+        let mut v: [_; 4] = array_init(|i| {
+            let init = self.constant(INIT[i]);
+            self.xor(&key[i % 2], &init)
+        });
+
+        // Process full words, followed by the final word with the length in its top byte
+        let mut words = Vec::with_capacity(data.len() / 8 + 1);
+        for chunk in data.chunks(8) {
+            let bytes: [_; 8] = array_init(|i| match chunk.get(i) {
+                Some(byte) => byte.clone(),
+                None if i == 7 => self.constant(data.len() as u8),
+                None => self.constant(0u8),
+            });
+            words.push(self.pack_le_u64(&bytes));
+        }
+        if data.len().is_multiple_of(8) {
+            words.push(self.constant((data.len() as u64) << 56));
+        }
+        for word in words.iter() {
+            v[3] = self.xor(&v[3], word);
+            sip_round(self, &mut v);
+            sip_round(self, &mut v);
+            v[0] = self.xor(&v[0], word);
+        }
+
+        // Finalization
+        let ff = self.constant(0xffu64);
+        v[2] = self.xor(&v[2], &ff);
+        for _ in 0..4 {
+            sip_round(self, &mut v);
+        }
+        let t = self.xor(&v[0], &v[1]);
+        let t = self.xor(&t, &v[2]);
+        self.xor(&t, &v[3])
+    }
+}
+
+impl<S: SystemPack + SystemWrappingAdd<u64> + SystemBitXor<u64> + SystemBitRotate<u64, u8>>
+    SystemSipHash for S
+{
+}
+
+/// Applies a SipRound to the given state.
+fn sip_round<S: SystemSipHash + ?Sized>(sys: &mut S, v: &mut [Abstract<S, u64>; 4]) {
+    v[0] = sys.wrapping_add(&v[0], &v[1]);
+    v[1] = sys.rotl(&v[1], 13);
+    v[1] = sys.xor(&v[1], &v[0]);
+    v[0] = sys.rotl(&v[0], 32);
+    v[2] = sys.wrapping_add(&v[2], &v[3]);
+    v[3] = sys.rotl(&v[3], 16);
+    v[3] = sys.xor(&v[3], &v[2]);
+    v[0] = sys.wrapping_add(&v[0], &v[3]);
+    v[3] = sys.rotl(&v[3], 21);
+    v[3] = sys.xor(&v[3], &v[0]);
+    v[2] = sys.wrapping_add(&v[2], &v[1]);
+    v[1] = sys.rotl(&v[1], 17);
+    v[1] = sys.xor(&v[1], &v[2]);
+    v[2] = sys.rotl(&v[2], 32);
+}
+
+/// The initial state of SipHash, before it is combined with the key.
+const INIT: [u64; 4] = [
+    0x736f6d6570736575,
+    0x646f72616e646f6d,
+    0x6c7967656e657261,
+    0x7465646279746573,
+];
+
+#[test]
+fn test_siphash24() {
+    let key = [0x0706050403020100, 0x0f0e0d0c0b0a0908];
+    assert_eq!(Eval.siphash24(&key, &[]), 0x726fdb47dd0e0e31);
+    let data: Vec<u8> = (0..15).collect();
+    assert_eq!(Eval.siphash24(&key, &data), 0xa129ca6149be45e5);
+    let data: Vec<u8> = (0..63).collect();
+    assert_eq!(Eval.siphash24(&key, &data), 0x958a324ceb064572);
+    let mut sys = BinaryEmulate::new(Eval);
+    let abstract_key = key.map(|k| sys.constant(k));
+    let abstract_data: Vec<_> = data.iter().map(|b| sys.constant(*b)).collect();
+    let hash = sys.siphash24(&abstract_key, &abstract_data);
+    assert_eq!(hash, sys.constant(0x958a324ceb064572u64));
+}
@@ -0,0 +1,92 @@
+use crate::*;
+use array_init::array_init;
+
+/// A system in which the Xoodoo permutation can be computed. Its state is 384 bits, and it only
+/// needs operations on `u32`, making it a lightweight alternative to Keccak for sponge and
+/// duplex constructions.
+pub trait SystemXoodoo:
+    SystemBitAnd<u32> + SystemBitXor<u32> + SystemBitRotate<u32, u8> + SystemNot<u32>
+{
+    /// Applies the full 12-round Xoodoo permutation to the given state, whose lanes are indexed
+    /// by `x + 4 * y`.
+    fn xoodoo(&mut self, state: &mut [Abstract<Self, u32>; 12]) {
+        self.xoodoo_rounds(state, 12)
+    }
+
+    /// Applies the last `rounds` rounds of the Xoodoo permutation to the given state, as used by
+    /// constructions such as Xoofff, which uses 6 rounds.
+    fn xoodoo_rounds(&mut self, state: &mut [Abstract<Self, u32>; 12], rounds: usize) {
+        assert!(rounds <= RC.len(), "Xoodoo has at most {} rounds", RC.len());
+        for rc in &RC[RC.len() - rounds..] {
+            // θ step
+            let p: [_; 4] = array_init(|x| {
+                let t = self.xor(&state[x], &state[x + 4]);
+                self.xor(&t, &state[x + 8])
+            });
+            let e: [_; 4] = array_init(|x| {
+                let a = self.rotl(&p[(x + 3) % 4], 5);
+                let b = self.rotl(&p[(x + 3) % 4], 14);
+                self.xor(&a, &b)
+            });
+            for (i, lane) in state.iter_mut().enumerate() {
+                *lane = self.xor(lane, &e[i % 4]);
+            }
+
+            // ρ-west step
+            let plane_1 = state.clone();
+            for x in 0..4 {
+                state[x + 4] = plane_1[(x + 3) % 4 + 4].clone();
+                state[x + 8] = self.rotl(&plane_1[x + 8], 11);
+            }
+
+            // ι step
+            let rc = self.constant(*rc);
+            state[0] = self.xor(&state[0], &rc);
+
+            // χ step
+            let a = state.clone();
+            for x in 0..4 {
+                for y in 0..3 {
+                    let t = self.not(&a[x + 4 * ((y + 1) % 3)]);
+                    let t = self.and(&t, &a[x + 4 * ((y + 2) % 3)]);
+                    state[x + 4 * y] = self.xor(&a[x + 4 * y], &t);
+                }
+            }
+
+            // ρ-east step
+            let a = state.clone();
+            for x in 0..4 {
+                state[x + 4] = self.rotl(&a[x + 4], 1);
+                state[x + 8] = self.rotl(&a[(x + 2) % 4 + 8], 8);
+            }
+        }
+    }
+}
+
+impl<S: SystemBitAnd<u32> + SystemBitXor<u32> + SystemBitRotate<u32, u8> + SystemNot<u32>>
+    SystemXoodoo for S
+{
+}
+
+/// The round constants for the ι step, for the full 12-round permutation.
+const RC: [u32; 12] = [
+    0x058, 0x038, 0x3c0, 0x0d0, 0x120, 0x014, 0x060, 0x02c, 0x380, 0x0f0, 0x1a0, 0x012,
+];
+
+#[test]
+fn test_xoodoo() {
+    // Applying the permutation to the all-zero state
+    let mut state = [0u32; 12];
+    Eval.xoodoo(&mut state);
+    assert_eq!(state[..4], [0x89d5d88d, 0xa963fcbf, 0x1b232d19, 0xffa5a014]);
+    assert_eq!(state[11], 0x5e4f4062);
+
+    // Binary emulation should agree, including for reduced-round variants
+    let mut sys = BinaryEmulate::new(Eval);
+    let mut abstract_state: [_; 12] = array_init(|i| sys.constant(i as u32));
+    sys.xoodoo_rounds(&mut abstract_state, 6);
+    let mut state: [u32; 12] = array_init(|i| i as u32);
+    Eval.xoodoo_rounds(&mut state, 6);
+    assert_eq!(state[0], 0x9d5c7171);
+    assert_eq!(abstract_state, state.map(|lane| sys.constant(lane)));
+}
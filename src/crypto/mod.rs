@@ -1 +1,14 @@
-pub mod hash;
\ No newline at end of file
+#[cfg(feature = "ascon")]
+pub mod ascon;
+#[cfg(feature = "aes")]
+pub mod aes;
+#[cfg(feature = "fpe")]
+pub mod fpe;
+#[cfg(feature = "email")]
+pub mod email;
+pub mod hash;
+#[cfg(feature = "prg")]
+pub mod prg;
+pub mod tables;
+#[cfg(feature = "tls")]
+pub mod tls;
@@ -0,0 +1,192 @@
+//! A pseudorandom generator which expands an abstract seed using the ChaCha20 block function
+//! in counter mode.
+use crate::*;
+use alloc::vec::Vec;
+use array_init::array_init;
+
+/// A system in which the ChaCha20 block function, and pseudorandom generators based on it, can
+/// be computed.
+pub trait SystemPrg: SystemWrappingAdd<u32> + SystemBitXor<u32> + SystemBitRotate<u32, u8> {
+    /// Computes the ChaCha20 block function, as specified in RFC 8439, for the given key, block
+    /// counter and 96-bit nonce.
+    fn chacha20_block(
+        &mut self,
+        key: &[Abstract<Self, u32>; 8],
+        counter: &Abstract<Self, u32>,
+        nonce: &[Abstract<Self, u32>; 3],
+    ) -> [Abstract<Self, u32>; 16] {
+        let init = chacha_state(
+            self,
+            key,
+            &[
+                counter.clone(),
+                nonce[0].clone(),
+                nonce[1].clone(),
+                nonce[2].clone(),
+            ],
+        );
+        let mut state = init.clone();
+        chacha_rounds(self, &mut state);
+        array_init(|i| self.wrapping_add(&state[i], &init[i]))
+    }
+
+    /// Computes the HChaCha20 function, which derives a new key from the given key and 128-bit
+    /// nonce. Unlike [`SystemPrg::chacha20_block`], the output is the permuted state, without
+    /// the initial state added back.
+    fn hchacha20(
+        &mut self,
+        key: &[Abstract<Self, u32>; 8],
+        nonce: &[Abstract<Self, u32>; 4],
+    ) -> [Abstract<Self, u32>; 8] {
+        let mut state = chacha_state(self, key, nonce);
+        chacha_rounds(self, &mut state);
+        array_init(|i| state[if i < 4 { i } else { i + 8 }].clone())
+    }
+}
+
+impl<S: SystemWrappingAdd<u32> + SystemBitXor<u32> + SystemBitRotate<u32, u8> + ?Sized> SystemPrg
+    for S
+{
+}
+
+/// Constructs the initial ChaCha state for the given key and the given counter and nonce words.
+fn chacha_state<S: SystemPrg + ?Sized>(
+    sys: &mut S,
+    key: &[Abstract<S, u32>; 8],
+    input: &[Abstract<S, u32>; 4],
+) -> [Abstract<S, u32>; 16] {
+    array_init(|i| match i {
+        0..=3 => sys.constant(SIGMA[i]),
+        4..=11 => key[i - 4].clone(),
+        _ => input[i - 12].clone(),
+    })
+}
+
+/// Applies the 20 rounds of the ChaCha permutation to the given state.
+fn chacha_rounds<S: SystemPrg + ?Sized>(sys: &mut S, state: &mut [Abstract<S, u32>; 16]) {
+    for _ in 0..10 {
+        quarter_round(sys, state, [0, 4, 8, 12]);
+        quarter_round(sys, state, [1, 5, 9, 13]);
+        quarter_round(sys, state, [2, 6, 10, 14]);
+        quarter_round(sys, state, [3, 7, 11, 15]);
+        quarter_round(sys, state, [0, 5, 10, 15]);
+        quarter_round(sys, state, [1, 6, 11, 12]);
+        quarter_round(sys, state, [2, 7, 8, 13]);
+        quarter_round(sys, state, [3, 4, 9, 14]);
+    }
+}
+
+/// Applies the ChaCha quarter round to the given words of the state.
+fn quarter_round<S: SystemPrg + ?Sized>(
+    sys: &mut S,
+    state: &mut [Abstract<S, u32>; 16],
+    [a, b, c, d]: [usize; 4],
+) {
+    for (x, y, z, shift) in [(a, b, d, 16), (c, d, b, 12), (a, b, d, 8), (c, d, b, 7)] {
+        state[x] = sys.wrapping_add(&state[x], &state[y]);
+        let t = SystemBitXor::<u32>::xor(sys, &state[z], &state[x]);
+        state[z] = SystemBitRotate::<u32, u8>::rotl(sys, &t, shift);
+    }
+}
+
+/// The constant words of the ChaCha state, "expand 32-byte k".
+const SIGMA: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+/// A pseudorandom generator which expands an abstract 256-bit seed into an arbitrary number of
+/// abstract words. Block `i` of the output is the ChaCha20 block for the seed with counter `i`
+/// and an all-zero nonce. Blocks are only generated once they are needed, and are cached, so
+/// that words can be requested in any order without repeating work.
+pub struct Prg<S: SystemRepr<u32> + ?Sized> {
+    key: [Abstract<S, u32>; 8],
+    blocks: Vec<Option<[Abstract<S, u32>; 16]>>,
+    pos: usize,
+}
+
+impl<S: SystemPrg + ?Sized> Prg<S> {
+    /// Constructs a [`Prg`] with the given seed.
+    pub fn new(seed: [Abstract<S, u32>; 8]) -> Self {
+        Self {
+            key: seed,
+            blocks: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Constructs a [`Prg`] whose key is derived from the given seed and 128-bit nonce using
+    /// HChaCha20, so that a single seed can be used for many independent streams.
+    pub fn with_nonce(
+        sys: &mut S,
+        seed: &[Abstract<S, u32>; 8],
+        nonce: &[Abstract<S, u32>; 4],
+    ) -> Self {
+        Self::new(sys.hchacha20(seed, nonce))
+    }
+
+    /// Gets the word at the given index in the output, generating its block if needed.
+    pub fn word(&mut self, sys: &mut S, index: usize) -> Abstract<S, u32> {
+        let block = index / 16;
+        if block >= self.blocks.len() {
+            self.blocks.resize(block + 1, None);
+        }
+        let words = self.blocks[block].get_or_insert_with(|| {
+            let counter = sys.constant(u32::try_from(block).expect("PRG output exhausted"));
+            let nonce = [0u32; 3].map(|word| sys.constant(word));
+            sys.chacha20_block(&self.key, &counter, &nonce)
+        });
+        words[index % 16].clone()
+    }
+
+    /// Gets the next `len` words of the output, following the last word returned by this
+    /// method.
+    pub fn next_words(&mut self, sys: &mut S, len: usize) -> Vec<Abstract<S, u32>> {
+        let words = (self.pos..self.pos + len)
+            .map(|index| self.word(sys, index))
+            .collect();
+        self.pos += len;
+        words
+    }
+
+    /// The number of blocks which have been generated so far.
+    pub fn num_blocks(&self) -> usize {
+        self.blocks.iter().filter(|block| block.is_some()).count()
+    }
+}
+
+#[test]
+fn test_chacha20() {
+    // Test vectors from RFC 8439, section 2.3.2, and draft-irtf-cfrg-xchacha, section 2.2.1
+    let key: [u32; 8] = array_init(|i| u32::from_le_bytes(array_init(|j| (4 * i + j) as u8)));
+    let block = Eval.chacha20_block(&key, &1, &[0x09000000, 0x4a000000, 0]);
+    assert_eq!(block[..4], [0xe4e7f110, 0x15593bd1, 0x1fdd0f50, 0xc47120a3]);
+    assert_eq!(block[15], 0x4e3c50a2);
+    let subkey = Eval.hchacha20(&key, &[0x09000000, 0x4a000000, 0, 0x27594131]);
+    assert_eq!(
+        subkey[..4],
+        [0x423b4182, 0xfe7bb227, 0x50420ed3, 0x737d878a]
+    );
+    assert_eq!(subkey[7], 0xdcecd326);
+
+    // Binary emulation should agree
+    let mut sys = BinaryEmulate::new(Eval);
+    let abstract_key = key.map(|word| sys.constant(word));
+    let counter = sys.constant(1u32);
+    let nonce = [0x09000000u32, 0x4a000000, 0].map(|word| sys.constant(word));
+    let abstract_block = sys.chacha20_block(&abstract_key, &counter, &nonce);
+    assert_eq!(
+        SystemRead::<[u32; 16]>::read_value(&sys, &abstract_block),
+        block
+    );
+}
+
+#[test]
+fn test_prg() {
+    let seed = array_init(|i| i as u32);
+    let mut prg = Prg::<Eval>::new(seed);
+    assert_eq!(prg.word(&mut Eval, 37), 0x681eeb5e);
+    assert_eq!(prg.num_blocks(), 1);
+    let words = prg.next_words(&mut Eval, 20);
+    assert_eq!(prg.num_blocks(), 3);
+    assert_eq!(words[..16], Eval.chacha20_block(&seed, &0, &[0; 3]));
+    assert_eq!(prg.next_words(&mut Eval, 20)[17], 0x681eeb5e);
+    assert_eq!(prg.num_blocks(), 3);
+}
@@ -0,0 +1,200 @@
+//! Constant tables used by cryptographic gadgets, derived from their definitions by `const fn`s
+//! at compile time rather than written out as literals. This makes it possible to audit a table
+//! by reading the few lines which define it, and the tables are embedded in the binary without
+//! any runtime cost.
+
+/// The AES S-box: the multiplicative inverse in GF(2^8), with zero mapping to zero, followed by
+/// the affine transformation from FIPS 197.
+pub const fn aes_sbox() -> [u8; 256] {
+    let mut res = [0; 256];
+    let mut x = 0;
+    while x < 256 {
+        let inv = gf256_pow(x as u8, 254);
+        res[x] = inv
+            ^ inv.rotate_left(1)
+            ^ inv.rotate_left(2)
+            ^ inv.rotate_left(3)
+            ^ inv.rotate_left(4)
+            ^ 0x63;
+        x += 1;
+    }
+    res
+}
+
+/// The round constants for the AES key schedule: successive powers of x in GF(2^8).
+pub const fn aes_rcon() -> [u8; 10] {
+    let mut res = [0; 10];
+    let mut rc = 1;
+    let mut i = 0;
+    while i < res.len() {
+        res[i] = rc;
+        rc = gf256_mul(rc, 2);
+        i += 1;
+    }
+    res
+}
+
+/// The initial hash value for SHA-256: the first 32 bits of the fractional parts of the square
+/// roots of the first 8 primes.
+pub const fn sha256_initial_state() -> [u32; 8] {
+    let primes = primes::<8>();
+    let mut res = [0; 8];
+    let mut i = 0;
+    while i < res.len() {
+        // Truncation keeps only the fractional bits
+        res[i] = iroot((primes[i] as u128) << 64, 2) as u32;
+        i += 1;
+    }
+    res
+}
+
+/// The round constants for SHA-256: the first 32 bits of the fractional parts of the cube roots
+/// of the first 64 primes.
+pub const fn sha256_round_constants() -> [u32; 64] {
+    let primes = primes::<64>();
+    let mut res = [0; 64];
+    let mut i = 0;
+    while i < res.len() {
+        res[i] = iroot((primes[i] as u128) << 96, 3) as u32;
+        i += 1;
+    }
+    res
+}
+
+/// The rotation offsets for the ρ step of Keccak-f\[1600\], indexed by `x + 5 * y`. These are
+/// the triangular numbers modulo 64, assigned along the walk `(x, y) -> (y, 2x + 3y)` starting
+/// from `(1, 0)`.
+pub const fn keccak_rho() -> [u8; 25] {
+    let mut res = [0; 25];
+    let (mut x, mut y) = (1, 0);
+    let mut t = 0;
+    while t < 24 {
+        res[x + 5 * y] = (((t + 1) * (t + 2) / 2) % 64) as u8;
+        (x, y) = (y, (2 * x + 3 * y) % 5);
+        t += 1;
+    }
+    res
+}
+
+/// The round constants for the ι step of Keccak-f\[1600\], whose bits at positions `2^j - 1` are
+/// taken from the output of a linear feedback shift register.
+pub const fn keccak_round_constants() -> [u64; 24] {
+    let mut res = [0; 24];
+    let mut round = 0;
+    while round < res.len() {
+        let mut j = 0;
+        while j <= 6 {
+            if keccak_lfsr(j + 7 * round) {
+                res[round] |= 1 << ((1 << j) - 1);
+            }
+            j += 1;
+        }
+        round += 1;
+    }
+    res
+}
+
+/// The output of the Keccak round constant LFSR, with polynomial x^8 + x^6 + x^5 + x^4 + 1, at
+/// step `t`.
+const fn keccak_lfsr(t: usize) -> bool {
+    let mut r: u16 = 1;
+    let mut i = 0;
+    while i < t % 255 {
+        r <<= 1;
+        if r & 0x100 != 0 {
+            r ^= 0x171;
+        }
+        i += 1;
+    }
+    r & 1 != 0
+}
+
+/// Multiplies two elements of GF(2^8), reducing by the AES polynomial x^8 + x^4 + x^3 + x + 1.
+const fn gf256_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut res = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            res ^= a;
+        }
+        a = (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 };
+        b >>= 1;
+    }
+    res
+}
+
+/// Raises an element of GF(2^8) to the given power.
+const fn gf256_pow(mut base: u8, mut exp: u32) -> u8 {
+    let mut res = 1;
+    while exp != 0 {
+        if exp & 1 != 0 {
+            res = gf256_mul(res, base);
+        }
+        base = gf256_mul(base, base);
+        exp >>= 1;
+    }
+    res
+}
+
+/// The first `N` primes.
+const fn primes<const N: usize>() -> [u64; N] {
+    let mut res = [0; N];
+    let mut len = 0;
+    let mut n = 2;
+    while len < N {
+        let mut i = 0;
+        while i < len && n % res[i] != 0 {
+            i += 1;
+        }
+        if i == len {
+            res[len] = n;
+            len += 1;
+        }
+        n += 1;
+    }
+    res
+}
+
+/// The integer `k`th root of a value, rounded down. This is only exact while the `k`th power of
+/// the root fits in a `u128`.
+const fn iroot(value: u128, k: u32) -> u128 {
+    let (mut lo, mut hi) = (0u128, 1u128 << (128 / k));
+    while hi - lo > 1 {
+        let mid = (lo + hi) / 2;
+        if mid.pow(k) <= value {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+#[test]
+fn test_tables() {
+    let sbox = aes_sbox();
+    assert_eq!(sbox[0x00], 0x63);
+    assert_eq!(sbox[0x53], 0xed);
+    assert_eq!(sbox[0xff], 0x16);
+    let mut seen = [false; 256];
+    for x in sbox {
+        assert!(!seen[x as usize], "S-box is not a permutation");
+        seen[x as usize] = true;
+    }
+    assert_eq!(
+        aes_rcon(),
+        [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36]
+    );
+
+    let h = sha256_initial_state();
+    assert_eq!(h[0], 0x6a09e667);
+    assert_eq!(h[7], 0x5be0cd19);
+    let k = sha256_round_constants();
+    assert_eq!(k[0], 0x428a2f98);
+    assert_eq!(k[63], 0xc67178f2);
+
+    assert_eq!(keccak_rho()[..8], [0, 1, 62, 28, 27, 36, 44, 6]);
+    let rc = keccak_round_constants();
+    assert_eq!(rc[0], 0x0000000000000001);
+    assert_eq!(rc[1], 0x0000000000008082);
+    assert_eq!(rc[23], 0x8000000080008008);
+}
@@ -0,0 +1,359 @@
+//! Gadgets for verifying TLS 1.3 handshakes and records which use the `TLS_AES_128_GCM_SHA256`
+//! cipher suite.
+use crate::crypto::aes::{AesGcm, SystemAes};
+use crate::crypto::hash::SystemSha256Bytes;
+use crate::*;
+use array_init::array_init;
+
+/// The block size of SHA-256, in bytes.
+const SHA256_BLOCK_LEN: usize = 64;
+
+/// A system in which TLS 1.3 key derivation and record protection can be performed.
+pub trait SystemTls: SystemSha256Bytes + SystemAes {
+    /// Computes HMAC-SHA256 of the given data.
+    fn hmac_sha256(
+        &mut self,
+        key: &[Abstract<Self, u8>],
+        data: &[Abstract<Self, u8>],
+    ) -> [Abstract<Self, u8>; 32] {
+        let mut key = key.to_vec();
+        if key.len() > SHA256_BLOCK_LEN {
+            key = self.sha256_bytes(&key).to_vec();
+        }
+        while key.len() < SHA256_BLOCK_LEN {
+            key.push(self.constant(0u8));
+        }
+        let pad = |sys: &mut Self, value: u8| -> Vec<_> {
+            let value = SystemRepr::<u8>::constant(sys, value);
+            key.iter()
+                .map(|k| SystemBitXor::<u8>::xor(sys, k, &value))
+                .collect()
+        };
+        let mut inner = pad(self, 0x36);
+        let mut outer = pad(self, 0x5c);
+        inner.extend_from_slice(data);
+        outer.extend(self.sha256_bytes(&inner));
+        self.sha256_bytes(&outer)
+    }
+
+    /// Computes HKDF-Extract with SHA-256.
+    fn hkdf_extract(
+        &mut self,
+        salt: &[Abstract<Self, u8>],
+        ikm: &[Abstract<Self, u8>],
+    ) -> [Abstract<Self, u8>; 32] {
+        self.hmac_sha256(salt, ikm)
+    }
+
+    /// Computes HKDF-Expand with SHA-256, producing `len` bytes of output.
+    fn hkdf_expand(
+        &mut self,
+        prk: &[Abstract<Self, u8>],
+        info: &[Abstract<Self, u8>],
+        len: usize,
+    ) -> Vec<Abstract<Self, u8>> {
+        assert!(len <= 255 * 32, "HKDF output is too long");
+        let mut res = Vec::with_capacity(len);
+        let mut prev = Vec::new();
+        for i in 1..=len.div_ceil(32) {
+            let mut data = prev;
+            data.extend_from_slice(info);
+            data.push(self.constant(i as u8));
+            prev = self.hmac_sha256(prk, &data).to_vec();
+            res.extend_from_slice(&prev);
+        }
+        res.truncate(len);
+        res
+    }
+
+    /// Computes `HKDF-Expand-Label` as defined by TLS 1.3. The label is given without the
+    /// `"tls13 "` prefix.
+    fn hkdf_expand_label(
+        &mut self,
+        secret: &[Abstract<Self, u8>],
+        label: &[u8],
+        context: &[Abstract<Self, u8>],
+        len: usize,
+    ) -> Vec<Abstract<Self, u8>> {
+        let mut full_label = b"tls13 ".to_vec();
+        full_label.extend_from_slice(label);
+        let mut header = (len as u16).to_be_bytes().to_vec();
+        header.push(full_label.len() as u8);
+        header.extend(full_label);
+        header.push(context.len() as u8);
+        let mut info: Vec<_> = header.into_iter().map(|b| self.constant(b)).collect();
+        info.extend_from_slice(context);
+        self.hkdf_expand(secret, &info, len)
+    }
+
+    /// Computes `Derive-Secret` as defined by TLS 1.3, given the hash of the transcript.
+    fn derive_secret(
+        &mut self,
+        secret: &[Abstract<Self, u8>],
+        label: &[u8],
+        transcript_hash: &[Abstract<Self, u8>; 32],
+    ) -> [Abstract<Self, u8>; 32] {
+        let res = self.hkdf_expand_label(secret, label, transcript_hash, 32);
+        array_init(|i| res[i].clone())
+    }
+
+    /// Derives the record protection key and IV from a traffic secret.
+    fn tls_traffic_keys(&mut self, secret: &[Abstract<Self, u8>; 32]) -> TrafficKeys<Self> {
+        let key = self.hkdf_expand_label(secret, b"key", &[], 16);
+        let iv = self.hkdf_expand_label(secret, b"iv", &[], 12);
+        TrafficKeys {
+            key: array_init(|i| key[i].clone()),
+            iv: array_init(|i| iv[i].clone()),
+        }
+    }
+
+    /// Computes the `verify_data` of a Finished message, given the traffic secret of its sender
+    /// and the hash of the transcript up to, but not including, the Finished message.
+    fn tls_finished(
+        &mut self,
+        secret: &[Abstract<Self, u8>; 32],
+        transcript_hash: &[Abstract<Self, u8>; 32],
+    ) -> [Abstract<Self, u8>; 32] {
+        let finished_key = self.hkdf_expand_label(secret, b"finished", &[], 32);
+        self.hmac_sha256(&finished_key, transcript_hash)
+    }
+
+    /// Determines whether the `verify_data` of a Finished message is correct.
+    fn tls_verify_finished(
+        &mut self,
+        secret: &[Abstract<Self, u8>; 32],
+        transcript_hash: &[Abstract<Self, u8>; 32],
+        verify_data: &[Abstract<Self, u8>; 32],
+    ) -> Abstract<Self, bool> {
+        let expected = self.tls_finished(secret, transcript_hash);
+        eq_bytes(self, &expected, verify_data)
+    }
+
+    /// Decrypts a TLS 1.3 application data record, including its 5-byte header, which was
+    /// protected with the given keys and sequence number. The length of the record is fixed at
+    /// synthesis time. Returns the inner plaintext, which ends with the content type and any
+    /// padding, along with whether the header and authentication tag are valid.
+    fn tls_decrypt_record(
+        &mut self,
+        keys: &TrafficKeys<Self>,
+        seq: u64,
+        record: &[Abstract<Self, u8>],
+    ) -> (Vec<Abstract<Self, u8>>, Abstract<Self, bool>) {
+        assert!(record.len() >= 5 + 16, "record is too short");
+        let len = (record.len() - 5) as u16;
+        let [len_hi, len_lo] = len.to_be_bytes();
+        let header = [0x17, 0x03, 0x03, len_hi, len_lo].map(|b| self.constant(b));
+        let header_valid = eq_bytes(self, &header, &record[..5]);
+
+        // The nonce is the IV combined with the sequence number
+        let seq = seq.to_be_bytes();
+        let nonce: [_; 12] = array_init(|i| match i {
+            0..=3 => keys.iv[i].clone(),
+            _ => {
+                let seq = self.constant(seq[i - 4]);
+                SystemBitXor::<u8>::xor(self, &keys.iv[i], &seq)
+            }
+        });
+        let mut gcm = AesGcm::new(self, &keys.key, &nonce);
+        gcm.update_aad(self, &record[..5]);
+        let body = &record[5..record.len() - 16];
+        let plaintext = gcm.decrypt(self, body);
+        let tag: [_; 16] = array_init(|i| record[record.len() - 16 + i].clone());
+        let tag_valid = gcm.verify(self, &tag);
+        let valid = SystemBitAnd::<bool>::and(self, &header_valid, &tag_valid);
+        (plaintext, valid)
+    }
+}
+
+impl<S: SystemSha256Bytes + SystemAes> SystemTls for S {}
+
+/// The record protection key and IV derived from a traffic secret.
+pub struct TrafficKeys<S: SystemRepr<u8> + ?Sized> {
+    pub key: [Abstract<S, u8>; 16],
+    pub iv: [Abstract<S, u8>; 12],
+}
+
+/// Accumulates the handshake messages of a TLS 1.3 connection, so that the transcript hash can
+/// be computed at each point it is needed by the key schedule.
+pub struct TlsTranscript<S: SystemRepr<u8> + ?Sized> {
+    data: Vec<Abstract<S, u8>>,
+}
+
+impl<S: SystemTls + ?Sized> TlsTranscript<S> {
+    /// Constructs an empty [`TlsTranscript`].
+    pub fn new() -> Self {
+        Self { data: Vec::new() }
+    }
+
+    /// Appends a handshake message, including its 4-byte header.
+    pub fn push(&mut self, message: &[Abstract<S, u8>]) {
+        self.data.extend_from_slice(message);
+    }
+
+    /// The bytes of the messages in the transcript so far.
+    pub fn data(&self) -> &[Abstract<S, u8>] {
+        &self.data
+    }
+
+    /// Computes the hash of the transcript so far.
+    pub fn hash(&self, sys: &mut S) -> [Abstract<S, u8>; 32] {
+        sys.sha256_bytes(&self.data)
+    }
+}
+
+impl<S: SystemTls + ?Sized> Default for TlsTranscript<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The secrets of the TLS 1.3 key schedule for a full handshake without a pre-shared key.
+pub struct TlsKeySchedule<S: SystemRepr<u8> + ?Sized> {
+    pub handshake_secret: [Abstract<S, u8>; 32],
+    pub client_handshake_traffic_secret: [Abstract<S, u8>; 32],
+    pub server_handshake_traffic_secret: [Abstract<S, u8>; 32],
+    pub master_secret: [Abstract<S, u8>; 32],
+}
+
+impl<S: SystemTls + ?Sized> TlsKeySchedule<S> {
+    /// Runs the key schedule up to the handshake traffic secrets, given the (EC)DHE shared secret
+    /// and the hash of the transcript up to and including the ServerHello.
+    pub fn new(
+        sys: &mut S,
+        shared_secret: &[Abstract<S, u8>],
+        hello_hash: &[Abstract<S, u8>; 32],
+    ) -> Self {
+        let zeros: Vec<_> = (0..32).map(|_| sys.constant(0u8)).collect();
+        let empty_hash = sys.sha256_bytes(&[]);
+        let early_secret = sys.hkdf_extract(&zeros, &zeros);
+        let salt = sys.derive_secret(&early_secret, b"derived", &empty_hash);
+        let handshake_secret = sys.hkdf_extract(&salt, shared_secret);
+        let client_handshake_traffic_secret =
+            sys.derive_secret(&handshake_secret, b"c hs traffic", hello_hash);
+        let server_handshake_traffic_secret =
+            sys.derive_secret(&handshake_secret, b"s hs traffic", hello_hash);
+        let salt = sys.derive_secret(&handshake_secret, b"derived", &empty_hash);
+        let master_secret = sys.hkdf_extract(&salt, &zeros);
+        Self {
+            handshake_secret,
+            client_handshake_traffic_secret,
+            server_handshake_traffic_secret,
+            master_secret,
+        }
+    }
+
+    /// Derives the client and server application traffic secrets, given the hash of the
+    /// transcript up to and including the server Finished message.
+    pub fn application_traffic_secrets(
+        &self,
+        sys: &mut S,
+        handshake_hash: &[Abstract<S, u8>; 32],
+    ) -> [[Abstract<S, u8>; 32]; 2] {
+        [b"c ap traffic", b"s ap traffic"]
+            .map(|label| sys.derive_secret(&self.master_secret, label, handshake_hash))
+    }
+}
+
+/// Determines whether two byte strings of the same length are equal.
+fn eq_bytes<S: SystemTls + ?Sized>(
+    sys: &mut S,
+    a: &[Abstract<S, u8>],
+    b: &[Abstract<S, u8>],
+) -> Abstract<S, bool> {
+    let mut res = SystemRepr::<bool>::constant(sys, true);
+    for (a, b) in a.iter().zip(b) {
+        let diff = SystemBitXor::<u8>::xor(sys, a, b);
+        for bit in sys.bits_of_u8(&diff) {
+            let same = SystemNot::<bool>::not(sys, &bit);
+            res = SystemBitAnd::<bool>::and(sys, &res, &same);
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+fn hex(str: &str) -> Vec<u8> {
+    (0..str.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&str[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_hkdf() {
+    // RFC 5869, test case 1
+    let ikm = [0x0b; 22];
+    let salt = hex("000102030405060708090a0b0c");
+    let info = hex("f0f1f2f3f4f5f6f7f8f9");
+    let prk = Eval.hkdf_extract(&salt, &ikm);
+    let okm = Eval.hkdf_expand(&prk, &info, 42);
+    assert_eq!(
+        okm,
+        hex(concat!(
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf",
+            "34007208d5b887185865"
+        ))
+    );
+}
+
+#[test]
+fn test_tls_handshake() {
+    let mut sys = Eval;
+    let shared_secret: Vec<u8> = (0..32).collect();
+    let mut transcript = TlsTranscript::new();
+    let mut client_hello = vec![0x01];
+    client_hello.extend(b"client hello".repeat(3));
+    let mut server_hello = vec![0x02];
+    server_hello.extend(b"server hello".repeat(2));
+    transcript.push(&client_hello);
+    transcript.push(&server_hello);
+    let hello_hash = transcript.hash(&mut sys);
+    let schedule = TlsKeySchedule::new(&mut sys, &shared_secret, &hello_hash);
+    assert_eq!(
+        schedule.handshake_secret[..],
+        hex("ddbe37614d014a8c19db0a47955ee6930b3ee727c408386ba274344962b0e015")
+    );
+    assert_eq!(
+        schedule.client_handshake_traffic_secret[..],
+        hex("07219bd866b6dcdbacd04c7b0c999b87373311593e3e36c73ed5ce5f63b2f903")
+    );
+    assert_eq!(
+        schedule.master_secret[..],
+        hex("055796c9a2f048dd920b01351ba00d131ba528efa37749efb03e31b643c615b2")
+    );
+
+    // Server Finished
+    let secret = schedule.server_handshake_traffic_secret;
+    let mut extensions = vec![0x08];
+    extensions.extend(b"encrypted extensions");
+    transcript.push(&extensions);
+    let hash = transcript.hash(&mut sys);
+    let verify_data: [u8; 32] =
+        array_init(|i| hex("e8327650cc86ad86bf64b5ad76538c223a576d03eb7ff8e3272126940d6f4d26")[i]);
+    assert!(sys.tls_verify_finished(&secret, &hash, &verify_data));
+    let mut tampered = verify_data;
+    tampered[31] ^= 1;
+    assert!(!sys.tls_verify_finished(&secret, &hash, &tampered));
+    transcript.push(&[0x14, 0x00, 0x00, 0x20]);
+    transcript.push(&verify_data);
+    let hash = transcript.hash(&mut sys);
+    let [client, _] = schedule.application_traffic_secrets(&mut sys, &hash);
+    assert_eq!(
+        client[..],
+        hex("b761de01533c2bf6be55b368e33a3b9f942275f707c6b30d867be2e4d6c125e6")
+    );
+
+    // Record decryption
+    let keys = sys.tls_traffic_keys(&secret);
+    assert_eq!(keys.key[..], hex("30fb38a5e0b3bd372107ad195e1b265f"));
+    assert_eq!(keys.iv[..], hex("d380975e9c5f5e4c692c38e2"));
+    let record = hex(concat!(
+        "1703030021cbc187a77dec3274a942cbb22b8ffcf3f20562f319f1f3957f53ce",
+        "bcd75ff21af7"
+    ));
+    let (plaintext, valid) = sys.tls_decrypt_record(&keys, 1, &record);
+    assert!(valid);
+    assert_eq!(plaintext, b"GET / HTTP/1.1\r\n\x17");
+    let (_, valid) = sys.tls_decrypt_record(&keys, 0, &record);
+    assert!(!valid);
+}
@@ -0,0 +1,209 @@
+//! Gadgets for converting Unix timestamps to calendar dates and comparing dates.
+use crate::bitset::{
+    add_bits, add_const, add_const_carry, const_bits, div_rem_const, mul_const, sub_bits, Bits,
+};
+use crate::*;
+use alloc::vec::Vec;
+use array_init::array_init;
+
+/// The number of seconds in a day.
+const SECS_PER_DAY: u128 = 86400;
+
+/// The number of days in a 400-year cycle of the Gregorian calendar.
+const DAYS_PER_ERA: u128 = 146097;
+
+/// The number of days from 0000-03-01 to 1970-01-01.
+const EPOCH_OFFSET: u128 = 719468;
+
+/// A system in which calendar dates can be manipulated.
+pub trait SystemDate: BinarySystem + SystemBits {}
+
+impl<S: BinarySystem + SystemBits + ?Sized> SystemDate for S {}
+
+abstract_struct! {
+    /// A date in the proleptic Gregorian calendar. Months and days are numbered from 1.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Date as AbstractDate {
+        pub year: u32,
+        pub month: u8,
+        pub day: u8,
+    }
+}
+
+impl Date {
+    /// Gets the UTC date of the given Unix timestamp, in seconds. The year is truncated to 32
+    /// bits, which only affects timestamps more than four billion years from now.
+    pub fn from_timestamp(timestamp: u64) -> Self {
+        // See https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = timestamp / SECS_PER_DAY as u64 + EPOCH_OFFSET as u64;
+        let era = z / DAYS_PER_ERA as u64;
+        let doe = z % DAYS_PER_ERA as u64;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+        let year = (era * 400 + yoe + (month <= 2) as u64) as u32;
+        Self { year, month, day }
+    }
+}
+
+impl<S: SystemDate + ?Sized> AbstractDate<S> {
+    /// Gets the UTC date of the given Unix timestamp, in seconds. This is the abstract version
+    /// of [`Date::from_timestamp`].
+    pub fn from_timestamp(sys: &mut S, timestamp: &Abstract<S, u64>) -> Self {
+        let bits = sys.bits_of_u64(timestamp);
+        let (days, _) = div_rem_const(sys, &bits, SECS_PER_DAY);
+        let z = add_const(sys, &days, EPOCH_OFFSET);
+        let (era, doe) = div_rem_const(sys, &z, DAYS_PER_ERA);
+
+        // The year of the era is `(doe - doe / 1460 + doe / 36524 - doe / 146096) / 365`
+        let (a, _) = div_rem_const(sys, &doe, 1460);
+        let (b, _) = div_rem_const(sys, &doe, 36524);
+        let (c, _) = div_rem_const(sys, &doe, 146096);
+        let pos = add_bits(sys, &doe, &b);
+        let neg = add_bits(sys, &a, &c);
+        let (t, _) = sub_bits(sys, &pos, &neg);
+        let (yoe, _) = div_rem_const(sys, &t, 365);
+
+        // The day of the year, counting from March 1, is
+        // `doe - (365 * yoe + yoe / 4 - yoe / 100)`
+        let (a, _) = div_rem_const(sys, &yoe, 4);
+        let (b, _) = div_rem_const(sys, &yoe, 100);
+        let year_days = mul_const(sys, &yoe, 365);
+        let neg = add_bits(sys, &year_days, &a);
+        let pos = add_bits(sys, &doe, &b);
+        let (doy, _) = sub_bits(sys, &pos, &neg);
+
+        // The month, counting from March, is `(5 * doy + 2) / 153`, and the day of the month is
+        // `doy - (153 * mp + 2) / 5 + 1`
+        let t = mul_const(sys, &doy, 5);
+        let t = add_const(sys, &t, 2);
+        let (mp, _) = div_rem_const(sys, &t, 153);
+        let t = mul_const(sys, &mp, 153);
+        let t = add_const(sys, &t, 2);
+        let (start, _) = div_rem_const(sys, &t, 5);
+        let doy = add_const(sys, &doy, 1);
+        let (day, _) = sub_bits(sys, &doy, &start);
+
+        // Months from January onward belong to the next year
+        let mp: Bits<S> = (0..4).map(|i| mp[i].clone()).collect();
+        let ten = const_bits(sys, 10, 4);
+        let (_, before_jan) = sub_bits(sys, &mp, &ten);
+        let (spring, _) = add_const_carry(sys, &mp, 3, 4);
+        let (winter, _) = add_const_carry(sys, &mp, 7, 4);
+        let month: Bits<S> = (0..4)
+            .map(|i| select_bit(sys, &before_jan, &spring[i], &winter[i]))
+            .collect();
+        let era_years = mul_const(sys, &era, 400);
+        let year = add_bits(sys, &era_years, &yoe);
+        let after_feb = sys.not(&before_jan);
+        let year = add_bits(sys, &year, &[after_feb]);
+
+        let zero = sys.constant(false);
+        let year: [_; 32] = array_init(|i| year.get(i).unwrap_or(&zero).clone());
+        let month: [_; 8] = array_init(|i| month.get(i).unwrap_or(&zero).clone());
+        let day: [_; 8] = array_init(|i| day.get(i).unwrap_or(&zero).clone());
+        Self {
+            year: sys.u32_of_bits(&year),
+            month: sys.u8_of_bits(&month),
+            day: sys.u8_of_bits(&day),
+        }
+    }
+
+    /// Advances this date by a number of whole years, wrapping the year on overflow. February 29
+    /// is kept as is, so in a year which is not a leap year, it falls between February 28 and
+    /// March 1 when compared against other dates.
+    pub fn add_years(&self, sys: &mut S, years: u32) -> Self {
+        let bits = sys.bits_of_u32(&self.year);
+        let (year, _) = add_const_carry(sys, &bits, years as u128, 32);
+        let year: [_; 32] = array_init(|i| year[i].clone());
+        Self {
+            year: sys.u32_of_bits(&year),
+            month: self.month.clone(),
+            day: self.day.clone(),
+        }
+    }
+
+    /// Determines whether this date is strictly before another. For a credential with an
+    /// expiry date, `expiry.lt(sys, &today)` determines whether it has expired.
+    pub fn lt(&self, sys: &mut S, other: &Self) -> Abstract<S, bool> {
+        let a = self.to_bits(sys);
+        let b = other.to_bits(sys);
+        sub_bits(sys, &a, &b).1
+    }
+
+    /// Determines whether this date is before or the same as another.
+    pub fn le(&self, sys: &mut S, other: &Self) -> Abstract<S, bool> {
+        let after = other.lt(sys, self);
+        sys.not(&after)
+    }
+
+    /// Packs this date into a single little-endian integer which preserves the order of dates.
+    fn to_bits(&self, sys: &mut S) -> Bits<S> {
+        let mut bits = Vec::with_capacity(48);
+        bits.extend(sys.bits_of_u8(&self.day));
+        bits.extend(sys.bits_of_u8(&self.month));
+        bits.extend(sys.bits_of_u32(&self.year));
+        bits
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_date_from_timestamp() {
+    let cases = [
+        (0, (1970, 1, 1)),
+        (86399, (1970, 1, 1)),
+        (86400, (1970, 1, 2)),
+        (951782400, (2000, 2, 29)),
+        (951868800, (2000, 3, 1)),
+        (1700000000, (2023, 11, 14)),
+        (1709164800, (2024, 2, 29)),
+        (4102444799, (2099, 12, 31)),
+        (4107542400, (2100, 3, 1)),
+        (253402300799, (9999, 12, 31)),
+    ];
+    let mut sys = BinaryEmulate::new(Eval);
+    for (timestamp, (year, month, day)) in cases {
+        let expected = Date { year, month, day };
+        assert_eq!(Date::from_timestamp(timestamp), expected, "{}", timestamp);
+        let date = AbstractDate::from_timestamp(&mut Eval, &timestamp);
+        assert_eq!(date.read_value(&Eval), expected, "{}", timestamp);
+        let timestamp = sys.constant(timestamp);
+        let date = AbstractDate::from_timestamp(&mut sys, &timestamp);
+        assert_eq!(date.read_value(&sys), expected);
+    }
+
+    // Consecutive days are consecutive dates
+    Eval::seed_rng(3);
+    for _ in 0..50 {
+        let timestamp = SystemRand::<u64>::rand(&mut Eval) % (1 << 40);
+        let today = AbstractDate::from_timestamp(&mut Eval, &timestamp);
+        let tomorrow = AbstractDate::from_timestamp(&mut Eval, &(timestamp + 86400));
+        assert_eq!(today.read_value(&Eval), Date::from_timestamp(timestamp));
+        assert!(today.lt(&mut Eval, &tomorrow));
+        assert!(!tomorrow.le(&mut Eval, &today));
+    }
+}
+
+#[test]
+fn test_date_cmp() {
+    let dates = [
+        (2023, 12, 31),
+        (2024, 1, 1),
+        (2024, 1, 31),
+        (2024, 2, 1),
+        (2024, 2, 29),
+        (2025, 1, 1),
+    ]
+    .map(|(year, month, day)| Date { year, month, day });
+    for a in dates {
+        for b in dates {
+            let abs_a = AbstractDate::constant(&mut Eval, a);
+            let abs_b = AbstractDate::constant(&mut Eval, b);
+            assert_eq!(abs_a.lt(&mut Eval, &abs_b), a < b);
+            assert_eq!(abs_a.le(&mut Eval, &abs_b), a <= b);
+        }
+    }
+}
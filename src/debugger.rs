@@ -0,0 +1,344 @@
+//! An evaluator for [`Graph`]s which can be paused and inspected, for finding where a large
+//! circuit, such as a hash, first goes wrong.
+use crate::graph::{Graph, Node, Op};
+use crate::*;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Identifies a breakpoint set in a [`Debugger`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BreakpointId(u32);
+
+/// An occurrence of a node taking a value which satisfies the condition of a breakpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hit {
+    pub breakpoint: BreakpointId,
+    pub node: Node,
+    pub value: bool,
+}
+
+/// A breakpoint set in a [`Debugger`].
+struct Breakpoint<'a> {
+    id: BreakpointId,
+    cond: Box<dyn Fn(Node, bool) -> bool + 'a>,
+}
+
+/// Evaluates a [`Graph`] on concrete inputs one topological level at a time, so that the
+/// values of nodes can be inspected as they are computed. Inputs and constants are at level 0,
+/// and every other node is one level above its deepest operand. Calls are evaluated in a single
+/// step, and their outputs are at the same level as the call.
+pub struct Debugger<'a> {
+    graph: &'a Graph,
+
+    /// The nodes of the graph, ordered by level and then by index.
+    order: Vec<Node>,
+
+    /// The position in `order` at which each level starts, followed by the number of nodes.
+    level_starts: Vec<usize>,
+
+    /// The number of levels which have been evaluated.
+    level: usize,
+    inputs: Vec<bool>,
+    values: Vec<Option<bool>>,
+    call_outputs: HashMap<Node, Vec<bool>>,
+    breakpoints: Vec<Breakpoint<'a>>,
+    next_breakpoint: u32,
+}
+
+impl<'a> Debugger<'a> {
+    /// Constructs a [`Debugger`] for the given graph and input values. No nodes are evaluated
+    /// until the first step.
+    pub fn new(graph: &'a Graph, inputs: &[bool]) -> Self {
+        assert_eq!(inputs.len(), graph.num_inputs());
+        let mut levels = Vec::with_capacity(graph.nodes().len());
+        for op in graph.nodes() {
+            let level = match op {
+                Op::Input(_) | Op::Const(_) => 0,
+                Op::Output(call, _) => levels[call.index()],
+                op => {
+                    1 + (graph.operands(op).iter())
+                        .map(|node| levels[node.index()])
+                        .max()
+                        .unwrap_or(0)
+                }
+            };
+            levels.push(level);
+        }
+        let mut order: Vec<_> = (0..levels.len()).map(Node::from_index).collect();
+        order.sort_by_key(|node| levels[node.index()]);
+        let num_levels = levels.iter().max().map_or(0, |level| level + 1);
+        let mut level_starts = vec![0; num_levels + 1];
+        for level in levels {
+            level_starts[level + 1] += 1;
+        }
+        for i in 1..level_starts.len() {
+            level_starts[i] += level_starts[i - 1];
+        }
+        Self {
+            graph,
+            order,
+            level_starts,
+            level: 0,
+            inputs: inputs.to_vec(),
+            values: vec![None; graph.nodes().len()],
+            call_outputs: HashMap::new(),
+            breakpoints: Vec::new(),
+            next_breakpoint: 0,
+        }
+    }
+
+    /// The number of levels in the graph.
+    pub fn num_levels(&self) -> usize {
+        self.level_starts.len() - 1
+    }
+
+    /// The number of levels which have been evaluated so far.
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Indicates whether every level has been evaluated.
+    pub fn is_done(&self) -> bool {
+        self.level == self.num_levels()
+    }
+
+    /// The nodes at the given level.
+    pub fn nodes_at(&self, level: usize) -> &[Node] {
+        &self.order[self.level_starts[level]..self.level_starts[level + 1]]
+    }
+
+    /// Gets the value of the given node, or [`None`] if it has not been evaluated yet.
+    pub fn value(&self, node: Node) -> Option<bool> {
+        self.values[node.index()]
+    }
+
+    /// Sets a breakpoint which is hit whenever a node is evaluated to a value for which `cond`
+    /// returns `true`. For example, `|node, value| node == wire && !value` breaks when `wire` is
+    /// false.
+    pub fn break_when(&mut self, cond: impl Fn(Node, bool) -> bool + 'a) -> BreakpointId {
+        let id = BreakpointId(self.next_breakpoint);
+        self.next_breakpoint += 1;
+        self.breakpoints.push(Breakpoint {
+            id,
+            cond: Box::new(cond),
+        });
+        id
+    }
+
+    /// Removes a breakpoint set using [`Debugger::break_when`].
+    pub fn remove_breakpoint(&mut self, id: BreakpointId) {
+        self.breakpoints.retain(|breakpoint| breakpoint.id != id);
+    }
+
+    /// Evaluates the next level, returning the breakpoints hit by its nodes. Does nothing if
+    /// every level has been evaluated.
+    pub fn step(&mut self) -> Vec<Hit> {
+        let mut hits = Vec::new();
+        if self.is_done() {
+            return hits;
+        }
+        let start = self.level_starts[self.level];
+        let end = self.level_starts[self.level + 1];
+        for i in start..end {
+            let node = self.order[i];
+            let Some(value) = self.eval(node) else {
+                continue;
+            };
+            self.values[node.index()] = Some(value);
+            for breakpoint in self.breakpoints.iter() {
+                if (breakpoint.cond)(node, value) {
+                    hits.push(Hit {
+                        breakpoint: breakpoint.id,
+                        node,
+                        value,
+                    });
+                }
+            }
+        }
+        self.level += 1;
+        hits
+    }
+
+    /// Evaluates levels until a breakpoint is hit or every level has been evaluated, returning
+    /// the breakpoints hit.
+    pub fn run(&mut self) -> Vec<Hit> {
+        while !self.is_done() {
+            let hits = self.step();
+            if !hits.is_empty() {
+                return hits;
+            }
+        }
+        Vec::new()
+    }
+
+    /// Computes the value of a node whose operands have been evaluated. Returns [`None`] for
+    /// [`Op::Call`] nodes, which have no value, after evaluating their outputs.
+    fn eval(&mut self, node: Node) -> Option<bool> {
+        let value = |node: Node| self.values[node.index()].unwrap();
+        Some(match self.graph.op(node) {
+            Op::Input(i) => self.inputs[i as usize],
+            Op::Const(value) => value,
+            Op::And(a, b) => value(a) & value(b),
+            Op::Or(a, b) => value(a) | value(b),
+            Op::Xor(a, b) => value(a) ^ value(b),
+            Op::Not(a) => !value(a),
+            Op::Call(_) => {
+                let call = self.graph.call_info(node).unwrap();
+                let mut inputs: Vec<_> = call.inputs.iter().map(|node| value(*node)).collect();
+                let mut outputs = call.template.instantiate(&mut Eval, &inputs);
+                for _ in 1..call.count {
+                    inputs.splice(..outputs.len(), outputs);
+                    outputs = call.template.instantiate(&mut Eval, &inputs);
+                }
+                self.call_outputs.insert(node, outputs);
+                return None;
+            }
+            Op::Output(call, i) => self.call_outputs[&call][i as usize],
+        })
+    }
+
+    /// The nodes which the value of `node` depends on, including itself, in topological order.
+    /// For an assertion which fails, these are the only nodes which could be at fault.
+    pub fn cone(&self, node: Node) -> Vec<Node> {
+        let needed = self.graph.needed(&[node]);
+        (needed.iter().enumerate())
+            .filter(|(_, needed)| **needed)
+            .map(|(index, _)| Node::from_index(index))
+            .collect()
+    }
+
+    /// Describes the nodes in the [cone](Debugger::cone) of `node`, one per line, with their
+    /// operations and current values. Nodes which have been probed (see [`SystemDebug::probe`])
+    /// are annotated with their labels.
+    pub fn dump_cone(&self, node: Node) -> String {
+        let mut labels = HashMap::new();
+        for (label, nodes) in self.graph.probes() {
+            for (i, node) in nodes.iter().enumerate() {
+                let label = match nodes.len() {
+                    1 => label.clone(),
+                    _ => format!("{}[{}]", label, i),
+                };
+                labels.entry(*node).or_insert(label);
+            }
+        }
+        let mut res = String::new();
+        for node in self.cone(node) {
+            let index = node.index();
+            let op = match self.graph.op(node) {
+                Op::Input(i) => format!("in{}", i),
+                Op::Const(value) => value.to_string(),
+                Op::And(a, b) => format!("and(n{}, n{})", a.index(), b.index()),
+                Op::Or(a, b) => format!("or(n{}, n{})", a.index(), b.index()),
+                Op::Xor(a, b) => format!("xor(n{}, n{})", a.index(), b.index()),
+                Op::Not(a) => format!("not(n{})", a.index()),
+                Op::Call(_) => {
+                    let call = self.graph.call_info(node).unwrap();
+                    let inputs: Vec<_> = (call.inputs.iter())
+                        .map(|node| format!("n{}", node.index()))
+                        .collect();
+                    format!("{}({})", call.name, inputs.join(", "))
+                }
+                Op::Output(call, i) => format!("n{}.out{}", call.index(), i),
+            };
+            write!(res, "n{} = {}", index, op).unwrap();
+            if let Some(value) = self.values[index] {
+                write!(res, " = {}", value).unwrap();
+            }
+            if let Some(label) = labels.get(&node) {
+                write!(res, "  [{}]", label).unwrap();
+            }
+            res.push('\n');
+        }
+        res
+    }
+}
+
+#[test]
+fn test_debugger() {
+    use crate::graph::SubcircuitTemplate;
+    use std::rc::Rc;
+
+    // Check that the sum of two bytes, doubled by a module, is 100
+    let double = Rc::new(SubcircuitTemplate::capture(8, |graph, inputs| {
+        let mut sys = BinaryEmulate::new(std::mem::take(graph));
+        let a: [Node; 8] = array_init::array_init(|i| inputs[i]);
+        let r = SystemWrappingAdd::<u8>::wrapping_add(&mut sys, &a, &a);
+        *graph = sys.into_source();
+        r.to_vec()
+    }));
+    let mut sys = BinaryEmulate::new(Graph::new());
+    let a: [Node; 8] = array_init::array_init(|_| sys.source_mut().input());
+    let b: [Node; 8] = array_init::array_init(|_| sys.source_mut().input());
+    let sum = SystemWrappingAdd::<u8>::wrapping_add(&mut sys, &a, &b);
+    SystemDebug::<u8>::probe(&mut sys, "sum", &sum);
+    let module = sys.source_mut().define("double", double);
+    let doubled = sys.source_mut().call(module, &sum);
+    let doubled: [Node; 8] = array_init::array_init(|i| doubled[i]);
+    let mut wrong = SystemRepr::<bool>::constant(&mut sys, false);
+    for (i, bit) in doubled.iter().enumerate() {
+        let expected = SystemRepr::<bool>::constant(&mut sys, 100 >> i & 1 == 1);
+        let diff = SystemBitXor::<bool>::xor(&mut sys, bit, &expected);
+        wrong = SystemBitOr::<bool>::or(&mut sys, &wrong, &diff);
+    }
+    let check = SystemNot::<bool>::not(&mut sys, &wrong);
+    let unrelated = SystemBitOr::<bool>::or(&mut sys, &a[1], &b[2]);
+    let graph = sys.into_source();
+
+    let inputs = |a: u8, b: u8| -> Vec<bool> {
+        (0..16)
+            .map(|i| {
+                if i < 8 {
+                    a >> i & 1 == 1
+                } else {
+                    b >> (i - 8) & 1 == 1
+                }
+            })
+            .collect()
+    };
+    let mut debugger = Debugger::new(&graph, &inputs(20, 30));
+    let hits = debugger.run();
+    assert!(hits.is_empty());
+    assert!(debugger.is_done());
+    assert_eq!(debugger.value(check), Some(true));
+    let expected = graph.replay(&mut Eval, &inputs(20, 30), &doubled);
+    let values: Vec<_> = doubled.iter().map(|node| debugger.value(*node)).collect();
+    assert_eq!(values, expected.into_iter().map(Some).collect::<Vec<_>>());
+
+    // Break when the check fails, then inspect its cone
+    let mut debugger = Debugger::new(&graph, &inputs(20, 31));
+    let id = debugger.break_when(|node, value| node == check && !value);
+    let mut steps = 0;
+    let hits = loop {
+        steps += 1;
+        let hits = debugger.step();
+        if !hits.is_empty() {
+            break hits;
+        }
+    };
+    assert_eq!(
+        hits,
+        [Hit {
+            breakpoint: id,
+            node: check,
+            value: false
+        }]
+    );
+    assert_eq!(debugger.level(), steps);
+    assert!(debugger.nodes_at(steps - 1).contains(&check));
+    let cone = debugger.cone(check);
+    assert!(cone.contains(&a[0]) && cone.contains(&sum[3]));
+    assert!(!cone.contains(&unrelated));
+    let dump = debugger.dump_cone(check);
+    assert_eq!(dump.lines().count(), cone.len());
+    assert!(dump.contains("[sum[3]]"));
+    assert!(dump.contains("double("));
+    assert!(dump.lines().last().unwrap().ends_with("= false"));
+
+    // Removed breakpoints are not hit
+    let mut debugger = Debugger::new(&graph, &inputs(20, 31));
+    let id = debugger.break_when(|_, value| value);
+    assert!(!debugger.step().is_empty());
+    debugger.remove_breakpoint(id);
+    assert!(debugger.run().is_empty());
+    assert!(debugger.value(unrelated).is_some());
+}
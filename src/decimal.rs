@@ -0,0 +1,174 @@
+//! Gadgets for converting between integers and their decimal representations as ASCII text.
+use crate::bitset::{add_bits, div_rem_const, Bits};
+use crate::bytes::{AbstractBytes, SystemBytes};
+use crate::*;
+use alloc::vec::Vec;
+use array_init::array_init;
+
+/// The maximum number of decimal digits in a `u64`.
+pub const MAX_U64_DIGITS: usize = 20;
+
+/// Parses a string of ASCII decimal digits as a `u64`, returning the value and whether the
+/// string was valid. A valid string is non-empty, consists only of digits, and represents a
+/// value which fits in a `u64`. Leading zeros are allowed. If the string is not valid, the
+/// returned value is unspecified.
+pub fn parse_decimal<S: SystemBytes + ?Sized>(
+    sys: &mut S,
+    text: &AbstractBytes<S>,
+) -> (Abstract<S, u64>, Abstract<S, bool>) {
+    let mask = text.mask(sys);
+    let mut valid = match mask.first() {
+        Some(nonempty) => nonempty.clone(),
+        None => SystemRepr::<bool>::constant(sys, false),
+    };
+    let zero = SystemRepr::<bool>::constant(sys, false);
+    let mut acc: Bits<S> = alloc::vec![zero.clone(); 64];
+    for (byte, keep) in text.data().iter().zip(mask.iter()) {
+        let (digit, is_digit) = digit_of_byte(sys, byte);
+        let skip = SystemNot::<bool>::not(sys, keep);
+        let ok = SystemBitOr::<bool>::or(sys, &skip, &is_digit);
+        valid = SystemBitAnd::<bool>::and(sys, &valid, &ok);
+
+        // Multiply the accumulator by 10 as `(acc << 1) + (acc << 3)`, then add the digit
+        let double: Bits<S> = core::iter::once(zero.clone()).chain(acc.clone()).collect();
+        let octuple: Bits<S> = [zero.clone(), zero.clone(), zero.clone()]
+            .into_iter()
+            .chain(acc.clone())
+            .collect();
+        let product = add_bits(sys, &double, &octuple);
+        let next = add_bits(sys, &product, &digit);
+        let mut overflow = SystemRepr::<bool>::constant(sys, false);
+        for bit in &next[64..] {
+            overflow = SystemBitOr::<bool>::or(sys, &overflow, bit);
+        }
+        let overflow = SystemBitAnd::<bool>::and(sys, keep, &overflow);
+        let fits = SystemNot::<bool>::not(sys, &overflow);
+        valid = SystemBitAnd::<bool>::and(sys, &valid, &fits);
+        acc = (0..64)
+            .map(|i| select_bit(sys, keep, &next[i], &acc[i]))
+            .collect();
+    }
+    let bits: [_; 64] = array_init(|i| acc[i].clone());
+    (sys.u64_of_bits(&bits), valid)
+}
+
+/// Formats a `u64` as a string of ASCII decimal digits, without leading zeros. Zero is formatted
+/// as `"0"`. The capacity of the result is [`MAX_U64_DIGITS`].
+pub fn format_decimal<S: SystemBytes + ?Sized>(
+    sys: &mut S,
+    value: &Abstract<S, u64>,
+) -> AbstractBytes<S> {
+    // Extract digits from least significant to most significant
+    let mut rest: Bits<S> = sys.bits_of_u64(value).to_vec();
+    let mut digits = Vec::with_capacity(MAX_U64_DIGITS);
+    for _ in 0..MAX_U64_DIGITS {
+        let (quot, rem) = div_rem_const(sys, &rest, 10);
+        digits.push(rem);
+        rest = quot;
+    }
+
+    // The string starts at the most significant non-zero digit, or the last digit if there is
+    // none
+    let mut start = SystemRepr::<u32>::constant(sys, MAX_U64_DIGITS as u32 - 1);
+    let mut len = SystemRepr::<u32>::constant(sys, 1);
+    let mut seen = SystemRepr::<bool>::constant(sys, false);
+    let mut seen_at = alloc::vec![seen.clone(); MAX_U64_DIGITS];
+    for (i, digit) in digits.iter().enumerate().skip(1).rev() {
+        for bit in digit {
+            seen = SystemBitOr::<bool>::or(sys, &seen, bit);
+        }
+        seen_at[i] = seen.clone();
+    }
+    for (i, seen) in seen_at.iter().enumerate().skip(1) {
+        let i_start = SystemRepr::<u32>::constant(sys, (MAX_U64_DIGITS - 1 - i) as u32);
+        let i_len = SystemRepr::<u32>::constant(sys, i as u32 + 1);
+        start = SystemSelect::<u32>::select(sys, seen, &i_start, &start);
+        len = SystemSelect::<u32>::select(sys, seen, &i_len, &len);
+    }
+
+    // Write all digits, most significant first, then drop the leading zeros
+    let one = SystemRepr::<bool>::constant(sys, true);
+    let data = (digits.iter().rev())
+        .map(|digit| {
+            let bits: [_; 8] = array_init(|i| match i {
+                0..4 => digit[i].clone(),
+                4 | 5 => one.clone(),
+                _ => SystemRepr::<bool>::constant(sys, false),
+            });
+            sys.u8_of_bits(&bits)
+        })
+        .collect();
+    let full_len = SystemRepr::<u32>::constant(sys, MAX_U64_DIGITS as u32);
+    let full = AbstractBytes::from_raw(data, full_len);
+    let (res, _) = full.slice(sys, &start, &len, MAX_U64_DIGITS);
+    res
+}
+
+/// Interprets a byte as an ASCII decimal digit, returning its value as a little-endian 4-bit
+/// integer and whether it was a digit.
+fn digit_of_byte<S: SystemBytes + ?Sized>(
+    sys: &mut S,
+    byte: &Abstract<S, u8>,
+) -> (Bits<S>, Abstract<S, bool>) {
+    let bits = sys.bits_of_u8(byte);
+
+    // The high nibble must be `0x3`, and the low nibble must be at most 9, so either its top bit
+    // is clear or both of the middle bits are
+    let hi_0 = SystemNot::<bool>::not(sys, &bits[7]);
+    let hi_1 = SystemNot::<bool>::not(sys, &bits[6]);
+    let hi = SystemBitAnd::<bool>::and(sys, &hi_0, &hi_1);
+    let hi_2 = SystemBitAnd::<bool>::and(sys, &bits[5], &bits[4]);
+    let hi = SystemBitAnd::<bool>::and(sys, &hi, &hi_2);
+    let middle = SystemBitOr::<bool>::or(sys, &bits[2], &bits[1]);
+    let lo_small = SystemNot::<bool>::not(sys, &middle);
+    let lo_top = SystemNot::<bool>::not(sys, &bits[3]);
+    let lo = SystemBitOr::<bool>::or(sys, &lo_top, &lo_small);
+    let is_digit = SystemBitAnd::<bool>::and(sys, &hi, &lo);
+    (bits[..4].to_vec(), is_digit)
+}
+
+#[test]
+fn test_parse_decimal() {
+    let sys = &mut Eval;
+    let cases: [(&[u8], Option<u64>); 10] = [
+        (b"0", Some(0)),
+        (b"7", Some(7)),
+        (b"007", Some(7)),
+        (b"1234567890", Some(1234567890)),
+        (b"18446744073709551615", Some(u64::MAX)),
+        (b"18446744073709551616", None),
+        (b"99999999999999999999", None),
+        (b"", None),
+        (b"12a4", None),
+        (b"1:", None),
+    ];
+    for (text, expected) in cases {
+        let bytes = AbstractBytes::from_const(sys, text, 24);
+        let (value, valid) = parse_decimal(sys, &bytes);
+        assert_eq!(valid, expected.is_some(), "{:?}", text);
+        if let Some(expected) = expected {
+            assert_eq!(value, expected);
+        }
+    }
+
+    // Bytes beyond the length are ignored
+    let bytes = AbstractBytes::from_const(sys, b"42", 2).with_capacity(sys, 5);
+    assert_eq!(parse_decimal(sys, &bytes), (42, true));
+}
+
+#[test]
+fn test_format_decimal() {
+    let sys = &mut Eval;
+    for value in [0, 1, 9, 10, 99, 100, 1234567890, 10u64.pow(19), u64::MAX] {
+        let text = format_decimal(sys, &value);
+        let expected = alloc::format!("{}", value);
+        let expected = AbstractBytes::from_const(sys, expected.as_bytes(), MAX_U64_DIGITS);
+        assert!(text.ct_eq(sys, &expected), "{}", value);
+        assert_eq!(parse_decimal(sys, &text), (value, true));
+    }
+    let mut sys = BinaryEmulate::new(Eval);
+    let value = sys.constant(90210u64);
+    let text = format_decimal(&mut sys, &value);
+    assert_eq!(text.data()[..5], b"90210".map(|b| sys.constant(b)));
+    assert_eq!(*text.len(), sys.constant(5u32));
+}
@@ -0,0 +1,326 @@
+use crate::graph::{Graph, Node, Op};
+use std::collections::{BTreeMap, HashMap};
+
+/// The differences between two versions of a circuit, as found by [`diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CircuitDiff {
+    /// The scopes which differ, starting with the top level of the graph (if it differs),
+    /// followed by modules in order of name.
+    pub scopes: Vec<ScopeDiff>,
+}
+
+impl CircuitDiff {
+    /// Determines whether the circuits were found to be equivalent.
+    pub fn is_empty(&self) -> bool {
+        self.scopes.is_empty()
+    }
+}
+
+impl std::fmt::Display for CircuitDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for scope in self.scopes.iter() {
+            match &scope.module {
+                Some(name) => write!(f, "module {:?}", name)?,
+                None => write!(f, "top level")?,
+            }
+            writeln!(
+                f,
+                ": {} added, {} removed, {} with changed constants",
+                scope.added.len(),
+                scope.removed.len(),
+                scope.changed_constants.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The differences within one scope of a circuit: either the top level of a [`Graph`], or the
+/// body of a module it calls. Only gates and calls are compared; inputs, constants and call
+/// outputs are compared by way of the gates that use them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeDiff {
+    /// The name of the module, or [`None`] for the top level.
+    pub module: Option<String>,
+
+    /// The gates and calls which are only in the new version, as nodes of the new scope.
+    pub added: Vec<Node>,
+
+    /// The gates and calls which are only in the old version, as nodes of the old scope.
+    pub removed: Vec<Node>,
+
+    /// The gates and calls whose structure is unchanged apart from the values of the constants
+    /// they depend on, as pairs of corresponding nodes in the old and new scopes.
+    pub changed_constants: Vec<(Node, Node)>,
+}
+
+/// Compares two versions of a circuit structurally, reporting the gates and calls which were
+/// added or removed in each scope, and those which only differ in the values of constants.
+///
+/// Gates are matched by their structure, regardless of the order in which they were created,
+/// so regenerating a circuit with a different synthesis order yields an empty diff. Modules are
+/// identified by name, and a call is considered unchanged if it invokes a module of the same
+/// name with the same inputs, even if the body of the module changed. Changes to module bodies
+/// are reported in the scope of the module.
+pub fn diff(old: &Graph, new: &Graph) -> CircuitDiff {
+    let mut interner = Interner::default();
+    let mut scopes = Vec::new();
+    scopes.extend(diff_scope(&mut interner, None, Some(old), Some(new)));
+    let mut old_modules = BTreeMap::new();
+    let mut new_modules = BTreeMap::new();
+    collect_modules(old, &mut old_modules);
+    collect_modules(new, &mut new_modules);
+    let mut names: Vec<_> = old_modules.keys().chain(new_modules.keys()).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let old = old_modules.get(name).copied();
+        let new = new_modules.get(name).copied();
+        scopes.extend(diff_scope(&mut interner, Some(name), old, new));
+    }
+    CircuitDiff { scopes }
+}
+
+/// Finds the body of every module called, directly or indirectly, by the given graph. If
+/// multiple modules have the same name, the first one found is used.
+fn collect_modules<'a>(graph: &'a Graph, modules: &mut BTreeMap<String, &'a Graph>) {
+    for index in 0..graph.nodes().len() {
+        let Some(info) = graph.call_info(Node::from_index(index)) else {
+            continue;
+        };
+        if !modules.contains_key(info.name) {
+            let body = info.template.graph();
+            modules.insert(info.name.to_owned(), body);
+            collect_modules(body, modules);
+        }
+    }
+}
+
+/// Compares a single scope. A missing version is treated as an empty graph.
+fn diff_scope(
+    interner: &mut Interner,
+    module: Option<&String>,
+    old: Option<&Graph>,
+    new: Option<&Graph>,
+) -> Option<ScopeDiff> {
+    let old = old
+        .map(|graph| interner.signatures(graph))
+        .unwrap_or_default();
+    let new = new
+        .map(|graph| interner.signatures(graph))
+        .unwrap_or_default();
+
+    // Match gates which are structurally identical
+    let mut by_full: HashMap<u32, Vec<Node>> = HashMap::new();
+    for (node, sig) in old.iter().rev() {
+        by_full.entry(sig.full).or_default().push(*node);
+    }
+    let mut unmatched = Vec::new();
+    for (node, sig) in new.iter() {
+        if by_full
+            .get_mut(&sig.full)
+            .and_then(|nodes| nodes.pop())
+            .is_none()
+        {
+            unmatched.push((*node, *sig));
+        }
+    }
+
+    // Match the remaining gates which are identical apart from constants
+    let mut by_shape: HashMap<u32, Vec<Node>> = HashMap::new();
+    for (node, sig) in old.iter().rev() {
+        if by_full[&sig.full].contains(node) {
+            by_shape.entry(sig.shape).or_default().push(*node);
+        }
+    }
+    let mut added = Vec::new();
+    let mut changed_constants = Vec::new();
+    for (node, sig) in unmatched {
+        match by_shape.get_mut(&sig.shape).and_then(|nodes| nodes.pop()) {
+            Some(old) => changed_constants.push((old, node)),
+            None => added.push(node),
+        }
+    }
+    let mut removed: Vec<_> = by_shape.into_values().flatten().collect();
+    removed.sort();
+    if added.is_empty() && removed.is_empty() && changed_constants.is_empty() {
+        return None;
+    }
+    Some(ScopeDiff {
+        module: module.cloned(),
+        added,
+        removed,
+        changed_constants,
+    })
+}
+
+/// The structural signatures of a node.
+#[derive(Debug, Clone, Copy)]
+struct Signature {
+    /// Identifies the structure of the node, including the values of constants.
+    full: u32,
+
+    /// Identifies the structure of the node, ignoring the values of constants.
+    shape: u32,
+}
+
+/// Describes the structure of a node in terms of the signatures of its operands.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Input(u32),
+    Const(Option<bool>),
+    And(u32, u32),
+    Or(u32, u32),
+    Xor(u32, u32),
+    Not(u32),
+    Call(String, u32, Vec<u32>),
+    Output(u32, u32),
+}
+
+/// Assigns signatures to node structures, consistently across graphs.
+#[derive(Default)]
+struct Interner {
+    keys: HashMap<Key, u32>,
+}
+
+impl Interner {
+    /// Gets the signature for the given key.
+    fn intern(&mut self, key: Key) -> u32 {
+        let next = self.keys.len() as u32;
+        *self.keys.entry(key).or_insert(next)
+    }
+
+    /// Computes the signatures of the gates and calls in a graph.
+    fn signatures(&mut self, graph: &Graph) -> Vec<(Node, Signature)> {
+        let mut sigs: Vec<Signature> = Vec::with_capacity(graph.nodes().len());
+        let mut res = Vec::new();
+        for (index, op) in graph.nodes().iter().enumerate() {
+            let node = Node::from_index(index);
+            let full = key(graph, &sigs, node, |sig| sig.full);
+            let shape = key(graph, &sigs, node, |sig| sig.shape);
+            let sig = Signature {
+                full: self.intern(full),
+                shape: self.intern(shape.erase_constant()),
+            };
+            sigs.push(sig);
+            if matches!(
+                op,
+                Op::And(..) | Op::Or(..) | Op::Xor(..) | Op::Not(_) | Op::Call(_)
+            ) {
+                res.push((node, sig));
+            }
+        }
+        res
+    }
+}
+
+impl Key {
+    /// Erases the value of a constant key.
+    fn erase_constant(self) -> Self {
+        match self {
+            Key::Const(_) => Key::Const(None),
+            key => key,
+        }
+    }
+}
+
+/// Describes the structure of a node, given a signature for each preceding node.
+fn key(graph: &Graph, sigs: &[Signature], node: Node, f: impl Fn(&Signature) -> u32) -> Key {
+    let sig = |node: &Node| f(&sigs[node.index()]);
+    match graph.op(node) {
+        Op::Input(i) => Key::Input(i),
+        Op::Const(value) => Key::Const(Some(value)),
+        Op::And(a, b) => commute(Key::And, sig(&a), sig(&b)),
+        Op::Or(a, b) => commute(Key::Or, sig(&a), sig(&b)),
+        Op::Xor(a, b) => commute(Key::Xor, sig(&a), sig(&b)),
+        Op::Not(a) => Key::Not(sig(&a)),
+        Op::Call(_) => {
+            let info = graph.call_info(node).unwrap();
+            let inputs = info.inputs.iter().map(sig).collect();
+            Key::Call(info.name.to_owned(), info.count, inputs)
+        }
+        Op::Output(call, i) => Key::Output(sig(&call), i),
+    }
+}
+
+/// Constructs a key for a commutative operation, with operands in a canonical order.
+fn commute(f: fn(u32, u32) -> Key, a: u32, b: u32) -> Key {
+    f(a.min(b), a.max(b))
+}
+
+#[test]
+fn test_diff() {
+    use crate::graph::SubcircuitTemplate;
+    use crate::*;
+    use std::rc::Rc;
+
+    // Builds a circuit which calls a module on a constant and ORs the result with an input
+    let build = |flip: bool, key: bool, extra: bool, reorder: bool| {
+        let body = Rc::new(SubcircuitTemplate::capture(2, |graph, inputs| {
+            let x = SystemBitXor::<bool>::xor(graph, &inputs[0], &inputs[1]);
+            let x = if flip {
+                SystemNot::<bool>::not(graph, &x)
+            } else {
+                x
+            };
+            vec![x]
+        }));
+        let mut graph = Graph::new();
+        let a = graph.input();
+        let b = graph.input();
+        let module = graph.define("mix", body);
+        let key = graph.constant(key);
+        let (p, q) = if reorder {
+            let q = SystemBitOr::<bool>::or(&mut graph, &b, &a);
+            (SystemBitAnd::<bool>::and(&mut graph, &a, &b), q)
+        } else {
+            (
+                SystemBitAnd::<bool>::and(&mut graph, &a, &b),
+                SystemBitOr::<bool>::or(&mut graph, &a, &b),
+            )
+        };
+        let r = graph.call(module, &[p, key])[0];
+        let mut s = SystemBitXor::<bool>::xor(&mut graph, &r, &q);
+        if extra {
+            s = SystemBitAnd::<bool>::and(&mut graph, &s, &a);
+        }
+        SystemNot::<bool>::not(&mut graph, &s);
+        graph
+    };
+    let base = build(false, false, false, false);
+    assert!(diff(&base, &base).is_empty());
+    assert!(diff(&base, &build(false, false, false, true)).is_empty());
+
+    // Add a gate at the top level
+    let res = diff(&base, &build(false, false, true, false));
+    assert_eq!(res.scopes.len(), 1);
+    assert_eq!(res.scopes[0].module, None);
+    assert_eq!(res.scopes[0].added.len(), 2);
+    assert_eq!(res.scopes[0].removed.len(), 1);
+    let res = diff(&build(false, false, true, false), &base);
+    assert_eq!(res.scopes[0].added.len(), 1);
+    assert_eq!(res.scopes[0].removed.len(), 2);
+
+    // Change the body of the module
+    let res = diff(&base, &build(true, false, false, false));
+    assert_eq!(res.scopes.len(), 1);
+    assert_eq!(res.scopes[0].module.as_deref(), Some("mix"));
+    assert_eq!(res.scopes[0].added.len(), 1);
+    assert!(res.scopes[0].removed.is_empty());
+
+    // Change a constant, which affects the call and the gates depending on it
+    let new = build(false, true, false, false);
+    let res = diff(&base, &new);
+    assert_eq!(res.scopes.len(), 1);
+    let scope = &res.scopes[0];
+    assert!(scope.added.is_empty() && scope.removed.is_empty());
+    assert_eq!(scope.changed_constants.len(), 3);
+    assert!(matches!(
+        new.op(scope.changed_constants[0].1),
+        graph::Op::Call(_)
+    ));
+    assert_eq!(
+        res.to_string(),
+        "top level: 0 added, 0 removed, 3 with changed constants\n"
+    );
+}
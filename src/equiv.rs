@@ -0,0 +1,100 @@
+use crate::bdd::Bdd;
+use crate::graph::SubcircuitTemplate;
+use crate::*;
+
+/// The number of nodes [`equiv_check`] may use before giving up.
+const EQUIV_NODE_LIMIT: usize = 1 << 22;
+
+/// The outcome of [`equiv_check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EquivalenceResult {
+    /// The circuits compute the same function.
+    Equivalent,
+
+    /// The circuits produce different outputs for the given inputs.
+    Counterexample(Vec<bool>),
+
+    /// The circuits were too large to compare. This is typical of circuits which multiply
+    /// their inputs, or mix them thoroughly, as hash functions do.
+    Inconclusive,
+}
+
+/// Determines whether two circuits with the same numbers of inputs and outputs compute the same
+/// function, or otherwise finds an input for which they differ. Unlike [`diff`], which compares
+/// the structure of circuits, this compares their behavior, so it can be used to check a
+/// refactoring of a gadget which changes the gates it produces.
+///
+/// Both circuits are represented as binary decision diagrams over their inputs, which works
+/// well for circuits built from additions, comparisons and bitwise operations. See
+/// [`EquivalenceResult::Inconclusive`].
+pub fn equiv_check(a: &SubcircuitTemplate, b: &SubcircuitTemplate) -> EquivalenceResult {
+    assert_eq!(
+        a.num_inputs(),
+        b.num_inputs(),
+        "circuits must have the same number of inputs"
+    );
+    assert_eq!(
+        a.num_outputs(),
+        b.num_outputs(),
+        "circuits must have the same number of outputs"
+    );
+    let mut bdd = Bdd::with_limit(EQUIV_NODE_LIMIT);
+    let inputs: Vec<_> = (0..a.num_inputs()).map(|_| bdd.var()).collect();
+    let a_outputs = a.instantiate(&mut bdd, &inputs);
+    let b_outputs = b.instantiate(&mut bdd, &inputs);
+    let mut differs = bdd.constant(false);
+    for (a, b) in a_outputs.iter().zip(b_outputs.iter()) {
+        let diff = SystemBitXor::<bool>::xor(&mut bdd, a, b);
+        differs = SystemBitOr::<bool>::or(&mut bdd, &differs, &diff);
+    }
+    if SystemRepr::<bool>::status(&bdd).is_err() {
+        return EquivalenceResult::Inconclusive;
+    }
+    match bdd.satisfy(differs) {
+        Some(inputs) => EquivalenceResult::Counterexample(inputs),
+        None => EquivalenceResult::Equivalent,
+    }
+}
+
+#[test]
+fn test_equiv_check() {
+    use crate::graph::{Graph, Node};
+
+    /// Captures a gadget which operates on two `u8`s.
+    fn capture(
+        gadget: impl FnOnce(&mut BinaryEmulate<Graph>, &[Node; 8], &[Node; 8]) -> [Node; 8],
+    ) -> SubcircuitTemplate {
+        SubcircuitTemplate::capture(16, |graph, inputs| {
+            let mut sys = BinaryEmulate::new(std::mem::take(graph));
+            let x: [Node; 8] = array_init::array_init(|i| inputs[i]);
+            let y: [Node; 8] = array_init::array_init(|i| inputs[8 + i]);
+            let res = gadget(&mut sys, &x, &y);
+            *graph = sys.into_source();
+            res.to_vec()
+        })
+    }
+
+    // `x + y` is `(x ^ y) + 2 * (x & y)`
+    let add = capture(SystemWrappingAdd::<u8>::wrapping_add);
+    let add_alt = capture(|sys, x, y| {
+        let xor = SystemBitXor::<u8>::xor(sys, x, y);
+        let and = SystemBitAnd::<u8>::and(sys, x, y);
+        let double = SystemBitShift::<u8, u8>::shl(sys, &and, 1);
+        SystemWrappingAdd::<u8>::wrapping_add(sys, &xor, &double)
+    });
+    assert_eq!(equiv_check(&add, &add_alt), EquivalenceResult::Equivalent);
+
+    // `x | y` differs from `x ^ y` whenever they share a bit
+    let or = capture(SystemBitOr::<u8>::or);
+    let xor = capture(SystemBitXor::<u8>::xor);
+    let EquivalenceResult::Counterexample(inputs) = equiv_check(&or, &xor) else {
+        panic!("expected a counterexample")
+    };
+    let to_u8 = |bits: &[bool]| (0..8).fold(0u8, |acc, i| acc | (bits[i] as u8) << i);
+    let (x, y) = (to_u8(&inputs[..8]), to_u8(&inputs[8..]));
+    assert_ne!(x | y, x ^ y);
+    assert_ne!(
+        or.instantiate(&mut Eval, &inputs),
+        xor.instantiate(&mut Eval, &inputs)
+    );
+}
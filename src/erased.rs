@@ -0,0 +1,606 @@
+//! A type-erased interface to systems, so that code such as plugins and scripting layers can
+//! construct circuits without being generic over the backend.
+//!
+//! Erasure has a cost: every operation is dispatched through a function pointer, its operands are
+//! checked and downcast at runtime, and every result is allocated behind an [`Rc`]. This is
+//! typically several times slower than calling a concrete system, so gadgets which are written
+//! in Rust should use the generic traits directly.
+use crate::field::FieldElement;
+use crate::*;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::any::Any;
+
+/// A type of value which can be manipulated through a [`DynSystem`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DynType {
+    Bool,
+    U32,
+    U64,
+
+    /// An element of the field `F` of the [`DynSystem`].
+    Field,
+}
+
+impl DynType {
+    /// All types, in the order of their indices.
+    pub const ALL: [DynType; 4] = [DynType::Bool, DynType::U32, DynType::U64, DynType::Field];
+}
+
+/// A concrete value of one of the types supported by a [`DynSystem`] over the field `F`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynConst<F> {
+    Bool(bool),
+    U32(u32),
+    U64(u64),
+    Field(F),
+}
+
+impl<F> DynConst<F> {
+    /// The type of this value.
+    pub fn ty(&self) -> DynType {
+        match self {
+            DynConst::Bool(_) => DynType::Bool,
+            DynConst::U32(_) => DynType::U32,
+            DynConst::U64(_) => DynType::U64,
+            DynConst::Field(_) => DynType::Field,
+        }
+    }
+}
+
+/// An operation which can be applied to values in a [`DynSystem`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DynOp {
+    /// Bitwise AND, for booleans and integers.
+    And,
+
+    /// Bitwise OR, for booleans and integers.
+    Or,
+
+    /// Bitwise XOR, for booleans and integers.
+    Xor,
+
+    /// Bitwise NOT, for booleans and integers. This is the only unary operation.
+    Not,
+
+    /// Wrapping addition for integers, or addition for field elements.
+    Add,
+
+    /// Multiplication of field elements.
+    Mul,
+}
+
+impl DynOp {
+    /// The number of operands this operation takes.
+    pub fn arity(self) -> usize {
+        match self {
+            DynOp::Not => 1,
+            _ => 2,
+        }
+    }
+}
+
+/// An abstract value in a [`DynSystem`], tagged with its type.
+#[derive(Clone)]
+pub struct DynValue {
+    ty: DynType,
+    value: Rc<dyn Any>,
+}
+
+impl DynValue {
+    /// The type of this value.
+    pub fn ty(&self) -> DynType {
+        self.ty
+    }
+
+    /// Gets the underlying abstract value, which has type `Abstract<S, T>` for the system `S`
+    /// that was erased and the type `T` corresponding to [`DynValue::ty`]. This can be used with
+    /// [`DynSystem::system`] to read the value.
+    pub fn downcast_ref<A: 'static>(&self) -> Option<&A> {
+        self.value.downcast_ref()
+    }
+}
+
+impl core::fmt::Debug for DynValue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "DynValue({:?})", self.ty)
+    }
+}
+
+/// The error returned when an operation is not supported by a [`DynSystem`], or is given
+/// operands of the wrong types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynError {
+    /// The system does not support values of the given type.
+    UnsupportedType(DynType),
+
+    /// The system does not support the given operation on values of the given type.
+    UnsupportedOp(DynOp, DynType),
+
+    /// The system does not support assertions.
+    UnsupportedAssert,
+
+    /// An operand had a different type than expected.
+    TypeMismatch { expected: DynType, actual: DynType },
+
+    /// An operation was given the wrong number of operands.
+    WrongArity { expected: usize, actual: usize },
+}
+
+impl core::fmt::Display for DynError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DynError::UnsupportedType(ty) => write!(f, "system does not support {:?} values", ty),
+            DynError::UnsupportedOp(op, ty) => {
+                write!(f, "system does not support {:?} on {:?} values", op, ty)
+            }
+            DynError::UnsupportedAssert => write!(f, "system does not support assertions"),
+            DynError::TypeMismatch { expected, actual } => {
+                write!(f, "expected a {:?} value, but got {:?}", expected, actual)
+            }
+            DynError::WrongArity { expected, actual } => {
+                write!(f, "expected {} operands, but got {}", expected, actual)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DynError {}
+
+type ConstantFn<F> = fn(&mut dyn Any, DynConst<F>) -> Rc<dyn Any>;
+type OpFn = fn(&mut dyn Any, &[&dyn Any]) -> Rc<dyn Any>;
+type SelectFn = fn(&mut dyn Any, &dyn Any, &dyn Any, &dyn Any) -> Rc<dyn Any>;
+type AssertFn = fn(&mut dyn Any, &dyn Any);
+
+/// The operations available on values of a particular type in a [`DynSystem`].
+struct TypeOps<F> {
+    constant: ConstantFn<F>,
+    select: SelectFn,
+    ops: [Option<OpFn>; 6],
+}
+
+/// A system whose type has been erased, which can operate on values of the types listed in
+/// [`DynType`], where field elements belong to the field `F`. Each type, and assertions, must be
+/// enabled by the [`DynBuilder`] used to construct the system, which requires the underlying
+/// system to support the corresponding operations.
+///
+/// Values from one [`DynSystem`] must not be used in another. Doing so may panic.
+pub struct DynSystem<F> {
+    sys: Box<dyn Any>,
+    types: [Option<TypeOps<F>>; 4],
+    assert: Option<AssertFn>,
+}
+
+impl<F: 'static> DynSystem<F> {
+    /// Starts constructing a [`DynSystem`] which erases the given system.
+    pub fn builder<S: 'static>(sys: S) -> DynBuilder<S, F> {
+        DynBuilder {
+            sys,
+            types: [None, None, None, None],
+            assert: None,
+        }
+    }
+
+    /// Determines whether this system supports values of the given type.
+    pub fn supports(&self, ty: DynType) -> bool {
+        self.types[ty as usize].is_some()
+    }
+
+    /// Gets the underlying system, if it has type `S`.
+    pub fn system<S: 'static>(&self) -> Option<&S> {
+        self.sys.downcast_ref()
+    }
+
+    /// Gets the underlying system mutably, if it has type `S`.
+    pub fn system_mut<S: 'static>(&mut self) -> Option<&mut S> {
+        self.sys.downcast_mut()
+    }
+
+    /// Recovers the underlying system, if it has type `S`.
+    pub fn into_inner<S: 'static>(self) -> Option<S> {
+        self.sys.downcast().ok().map(|sys| *sys)
+    }
+
+    /// Introduces a constant value into the system.
+    pub fn constant(&mut self, value: DynConst<F>) -> Result<DynValue, DynError> {
+        let ty = value.ty();
+        let ops = self.type_ops(ty)?;
+        Ok(DynValue {
+            ty,
+            value: (ops.constant)(&mut *self.sys, value),
+        })
+    }
+
+    /// Wraps an abstract value of the underlying system, such as an input declared on it directly.
+    /// The value must have type `Abstract<S, T>` for the system `S` that was erased and the type
+    /// `T` corresponding to `ty`. Otherwise, operations on it will panic.
+    pub fn wrap<A: 'static>(&self, ty: DynType, value: A) -> Result<DynValue, DynError> {
+        self.type_ops(ty)?;
+        Ok(DynValue {
+            ty,
+            value: Rc::new(value),
+        })
+    }
+
+    /// Applies an operation to the given operands, which must all have the same type.
+    pub fn apply(&mut self, op: DynOp, args: &[&DynValue]) -> Result<DynValue, DynError> {
+        if args.len() != op.arity() {
+            return Err(DynError::WrongArity {
+                expected: op.arity(),
+                actual: args.len(),
+            });
+        }
+        let ty = args[0].ty;
+        for arg in args {
+            expect_type(arg, ty)?;
+        }
+        let op_fn = self.type_ops(ty)?.ops[op as usize].ok_or(DynError::UnsupportedOp(op, ty))?;
+        let args: Vec<&dyn Any> = args.iter().map(|arg| &*arg.value).collect();
+        Ok(DynValue {
+            ty,
+            value: op_fn(&mut *self.sys, &args),
+        })
+    }
+
+    /// Returns `a` if `cond` is true, or `b` otherwise. `a` and `b` must have the same type.
+    pub fn select(
+        &mut self,
+        cond: &DynValue,
+        a: &DynValue,
+        b: &DynValue,
+    ) -> Result<DynValue, DynError> {
+        expect_type(cond, DynType::Bool)?;
+        expect_type(b, a.ty)?;
+        let select = self.type_ops(a.ty)?.select;
+        Ok(DynValue {
+            ty: a.ty,
+            value: select(&mut *self.sys, &*cond.value, &*a.value, &*b.value),
+        })
+    }
+
+    /// Asserts that the given boolean value is true.
+    pub fn assert(&mut self, value: &DynValue) -> Result<(), DynError> {
+        expect_type(value, DynType::Bool)?;
+        let assert = self.assert.ok_or(DynError::UnsupportedAssert)?;
+        assert(&mut *self.sys, &*value.value);
+        Ok(())
+    }
+
+    /// Gets the operations for the given type, if it is supported.
+    fn type_ops(&self, ty: DynType) -> Result<&TypeOps<F>, DynError> {
+        self.types[ty as usize]
+            .as_ref()
+            .ok_or(DynError::UnsupportedType(ty))
+    }
+}
+
+/// Checks that a value has the expected type.
+fn expect_type(value: &DynValue, expected: DynType) -> Result<(), DynError> {
+    if value.ty == expected {
+        Ok(())
+    } else {
+        Err(DynError::TypeMismatch {
+            expected,
+            actual: value.ty,
+        })
+    }
+}
+
+/// Constructs a [`DynSystem`] which erases a system of type `S`, enabling the types and
+/// operations that `S` supports.
+pub struct DynBuilder<S, F> {
+    sys: S,
+    types: [Option<TypeOps<F>>; 4],
+    assert: Option<AssertFn>,
+}
+
+impl<S: 'static, F: 'static> DynBuilder<S, F> {
+    /// Enables boolean values.
+    pub fn with_bool(mut self) -> Self
+    where
+        S: BinarySystem + SystemSelect<bool>,
+        Abstract<S, bool>: 'static,
+    {
+        self.types[DynType::Bool as usize] = Some(TypeOps {
+            constant: |sys, value| match value {
+                DynConst::Bool(value) => {
+                    Rc::new(SystemRepr::<bool>::constant(cast::<S>(sys), value))
+                }
+                _ => unreachable!(),
+            },
+            select: select::<S, bool>,
+            ops: [
+                Some(and::<S, bool>),
+                Some(or::<S, bool>),
+                Some(xor::<S, bool>),
+                Some(not::<S, bool>),
+                None,
+                None,
+            ],
+        });
+        self
+    }
+
+    /// Enables 32-bit unsigned integer values.
+    pub fn with_u32(mut self) -> Self
+    where
+        S: SystemInt<u32>,
+        Abstract<S, u32>: 'static,
+    {
+        self.types[DynType::U32 as usize] = Some(TypeOps {
+            constant: |sys, value| match value {
+                DynConst::U32(value) => Rc::new(SystemRepr::<u32>::constant(cast::<S>(sys), value)),
+                _ => unreachable!(),
+            },
+            select: select::<S, u32>,
+            ops: int_ops::<S, u32>(),
+        });
+        self
+    }
+
+    /// Enables 64-bit unsigned integer values.
+    pub fn with_u64(mut self) -> Self
+    where
+        S: SystemInt<u64>,
+        Abstract<S, u64>: 'static,
+    {
+        self.types[DynType::U64 as usize] = Some(TypeOps {
+            constant: |sys, value| match value {
+                DynConst::U64(value) => Rc::new(SystemRepr::<u64>::constant(cast::<S>(sys), value)),
+                _ => unreachable!(),
+            },
+            select: select::<S, u64>,
+            ops: int_ops::<S, u64>(),
+        });
+        self
+    }
+
+    /// Enables field elements.
+    pub fn with_field(mut self) -> Self
+    where
+        S: SystemAdd<FieldElement<F>> + SystemMul<FieldElement<F>> + SystemSelect<FieldElement<F>>,
+        Abstract<S, FieldElement<F>>: 'static,
+    {
+        self.types[DynType::Field as usize] = Some(TypeOps {
+            constant: |sys, value| match value {
+                DynConst::Field(value) => Rc::new(SystemRepr::<FieldElement<F>>::constant(
+                    cast::<S>(sys),
+                    FieldElement(value),
+                )),
+                _ => unreachable!(),
+            },
+            select: select::<S, FieldElement<F>>,
+            ops: [
+                None,
+                None,
+                None,
+                None,
+                Some(|sys, args| {
+                    let sys = cast::<S>(sys);
+                    Rc::new(SystemAdd::<FieldElement<F>>::add(
+                        sys,
+                        arg(args[0]),
+                        arg(args[1]),
+                    ))
+                }),
+                Some(|sys, args| {
+                    let sys = cast::<S>(sys);
+                    Rc::new(SystemMul::<FieldElement<F>>::mul(
+                        sys,
+                        arg(args[0]),
+                        arg(args[1]),
+                    ))
+                }),
+            ],
+        });
+        self
+    }
+
+    /// Enables assertions.
+    pub fn with_assert(mut self) -> Self
+    where
+        S: SystemAssert,
+    {
+        self.assert = Some(|sys, value| cast::<S>(sys).assert(arg(value)));
+        self
+    }
+
+    /// Constructs the [`DynSystem`].
+    pub fn build(self) -> DynSystem<F> {
+        DynSystem {
+            sys: Box::new(self.sys),
+            types: self.types,
+            assert: self.assert,
+        }
+    }
+}
+
+/// A system in which integers of type `T` can be manipulated through a [`DynSystem`].
+pub trait SystemInt<T>:
+    SystemSelect<T>
+    + SystemWrappingAdd<T>
+    + SystemBitAnd<T>
+    + SystemBitOr<T>
+    + SystemBitXor<T>
+    + SystemNot<T>
+{
+}
+
+impl<
+        T,
+        S: SystemSelect<T>
+            + SystemWrappingAdd<T>
+            + SystemBitAnd<T>
+            + SystemBitOr<T>
+            + SystemBitXor<T>
+            + SystemNot<T>
+            + ?Sized,
+    > SystemInt<T> for S
+{
+}
+
+/// Gets the operations for an integer type.
+fn int_ops<S: SystemInt<T> + 'static, T>() -> [Option<OpFn>; 6]
+where
+    Abstract<S, T>: 'static,
+{
+    [
+        Some(and::<S, T>),
+        Some(or::<S, T>),
+        Some(xor::<S, T>),
+        Some(not::<S, T>),
+        Some(|sys, args| {
+            let sys = cast::<S>(sys);
+            Rc::new(SystemWrappingAdd::<T>::wrapping_add(
+                sys,
+                arg(args[0]),
+                arg(args[1]),
+            ))
+        }),
+        None,
+    ]
+}
+
+/// Gets the erased system as its concrete type.
+fn cast<S: 'static>(sys: &mut dyn Any) -> &mut S {
+    sys.downcast_mut().expect("system has the wrong type")
+}
+
+/// Gets an erased abstract value as its concrete type.
+fn arg<A: 'static>(value: &dyn Any) -> &A {
+    value
+        .downcast_ref()
+        .expect("value belongs to a different system")
+}
+
+fn select<S: SystemSelect<T> + 'static, T>(
+    sys: &mut dyn Any,
+    cond: &dyn Any,
+    a: &dyn Any,
+    b: &dyn Any,
+) -> Rc<dyn Any>
+where
+    Abstract<S, T>: 'static,
+    Abstract<S, bool>: 'static,
+{
+    let sys = cast::<S>(sys);
+    Rc::new(SystemSelect::<T>::select(sys, arg(cond), arg(a), arg(b)))
+}
+
+fn and<S: SystemBitAnd<T> + 'static, T>(sys: &mut dyn Any, args: &[&dyn Any]) -> Rc<dyn Any>
+where
+    Abstract<S, T>: 'static,
+{
+    let sys = cast::<S>(sys);
+    Rc::new(SystemBitAnd::<T>::and(sys, arg(args[0]), arg(args[1])))
+}
+
+fn or<S: SystemBitOr<T> + 'static, T>(sys: &mut dyn Any, args: &[&dyn Any]) -> Rc<dyn Any>
+where
+    Abstract<S, T>: 'static,
+{
+    let sys = cast::<S>(sys);
+    Rc::new(SystemBitOr::<T>::or(sys, arg(args[0]), arg(args[1])))
+}
+
+fn xor<S: SystemBitXor<T> + 'static, T>(sys: &mut dyn Any, args: &[&dyn Any]) -> Rc<dyn Any>
+where
+    Abstract<S, T>: 'static,
+{
+    let sys = cast::<S>(sys);
+    Rc::new(SystemBitXor::<T>::xor(sys, arg(args[0]), arg(args[1])))
+}
+
+fn not<S: SystemNot<T> + 'static, T>(sys: &mut dyn Any, args: &[&dyn Any]) -> Rc<dyn Any>
+where
+    Abstract<S, T>: 'static,
+{
+    let sys = cast::<S>(sys);
+    Rc::new(SystemNot::<T>::not(sys, arg(args[0])))
+}
+
+#[test]
+fn test_dyn_system() {
+    use bls12_381::Scalar;
+
+    // A gadget written against the erased interface
+    fn gadget<F: 'static>(sys: &mut DynSystem<F>, a: u32, b: u32) -> Result<DynValue, DynError> {
+        let a = sys.constant(DynConst::U32(a))?;
+        let b = sys.constant(DynConst::U32(b))?;
+        let sum = sys.apply(DynOp::Add, &[&a, &b])?;
+        let mask = sys.constant(DynConst::U32(0xff))?;
+        let low = sys.apply(DynOp::And, &[&sum, &mask])?;
+        let cond = sys.constant(DynConst::Bool(true))?;
+        let cond = sys.apply(DynOp::Not, &[&cond])?;
+        sys.select(&cond, &sum, &low)
+    }
+
+    let mut sys = DynSystem::<Scalar>::builder(Eval)
+        .with_bool()
+        .with_u32()
+        .with_u64()
+        .with_field()
+        .with_assert()
+        .build();
+    assert!(DynType::ALL.iter().all(|ty| sys.supports(*ty)));
+    let res = gadget(&mut sys, 0x1234, 0xff).unwrap();
+    assert_eq!(res.ty(), DynType::U32);
+    assert_eq!(res.downcast_ref::<u32>(), Some(&0x33));
+    let x = sys.constant(DynConst::Field(Scalar::from(6))).unwrap();
+    let y = sys.constant(DynConst::Field(Scalar::from(7))).unwrap();
+    let z = sys.apply(DynOp::Mul, &[&x, &y]).unwrap();
+    assert_eq!(
+        z.downcast_ref::<FieldElement<Scalar>>(),
+        Some(&FieldElement(Scalar::from(42)))
+    );
+    let t = sys.constant(DynConst::Bool(true)).unwrap();
+    sys.assert(&t).unwrap();
+
+    // Errors are reported rather than panicking
+    assert_eq!(
+        sys.apply(DynOp::Mul, &[&res, &res]).unwrap_err(),
+        DynError::UnsupportedOp(DynOp::Mul, DynType::U32)
+    );
+    assert_eq!(
+        sys.apply(DynOp::Add, &[&res, &x]).unwrap_err(),
+        DynError::TypeMismatch {
+            expected: DynType::U32,
+            actual: DynType::Field
+        }
+    );
+    assert_eq!(
+        sys.apply(DynOp::Not, &[&t, &t]).unwrap_err(),
+        DynError::WrongArity {
+            expected: 1,
+            actual: 2
+        }
+    );
+
+    // The same gadget through binary emulation, which doesn't support field elements or
+    // assertions
+    let mut sys = DynSystem::<Scalar>::builder(BinaryEmulate::new(Eval))
+        .with_bool()
+        .with_u32()
+        .build();
+    let res = gadget(&mut sys, 0x1234, 0xff).unwrap();
+    let bits = res.downcast_ref::<[bool; 32]>().unwrap();
+    let sys_ref = sys.system::<BinaryEmulate<Eval>>().unwrap();
+    assert_eq!(SystemRead::<u32>::read_value(sys_ref, bits), 0x33);
+    assert!(!sys.supports(DynType::Field));
+    assert_eq!(
+        sys.constant(DynConst::Field(Scalar::from(1))).unwrap_err(),
+        DynError::UnsupportedType(DynType::Field)
+    );
+    let t = sys.constant(DynConst::Bool(true)).unwrap();
+    assert_eq!(sys.assert(&t).unwrap_err(), DynError::UnsupportedAssert);
+    let x = sys.wrap(DynType::U32, [false; 32]).unwrap();
+    let y = sys.apply(DynOp::Not, &[&x]).unwrap();
+    assert_eq!(y.downcast_ref::<[bool; 32]>(), Some(&[true; 32]));
+    assert_eq!(
+        sys.wrap(DynType::U64, [false; 64]).unwrap_err(),
+        DynError::UnsupportedType(DynType::U64)
+    );
+    assert!(sys.into_inner::<BinaryEmulate<Eval>>().is_some());
+}
@@ -0,0 +1,69 @@
+//! Gadgets for proving facts about Ethereum data structures.
+use crate::crypto::hash::SystemKeccak;
+use crate::ram::AbstractRam;
+use crate::*;
+
+mod mpt;
+mod rlp;
+
+pub use mpt::*;
+pub use rlp::*;
+
+/// A system in which Ethereum data structures can be decoded and verified.
+pub trait SystemEth:
+    BinarySystem
+    + SystemKeccak
+    + SystemBits
+    + SystemSelect<bool>
+    + SystemSelect<u8>
+    + SystemSelect<u32>
+    + SystemWrappingAdd<u32>
+    + SystemBitShift<u32, u8>
+    + SystemOrd<u32>
+{
+}
+
+impl<
+        S: BinarySystem
+            + SystemKeccak
+            + SystemBits
+            + SystemSelect<bool>
+            + SystemSelect<u8>
+            + SystemSelect<u32>
+            + SystemWrappingAdd<u32>
+            + SystemBitShift<u32, u8>
+            + SystemOrd<u32>,
+    > SystemEth for S
+{
+}
+
+/// Reads the byte at the given offset in a buffer. For offsets outside of the buffer, the result
+/// is unspecified.
+fn read_byte<S: SystemEth + ?Sized>(
+    sys: &mut S,
+    buf: &AbstractRam<S, u8>,
+    offset: &Abstract<S, u32>,
+) -> Abstract<S, u8> {
+    let bits = sys.bits_of_u32(offset);
+    let addr_bits = (usize::BITS - (buf.len() - 1).leading_zeros()) as usize;
+    buf.read(sys, &bits[..addr_bits])
+}
+
+/// Zero-extends a byte to a word.
+fn byte_to_u32<S: SystemEth + ?Sized>(sys: &mut S, value: &Abstract<S, u8>) -> Abstract<S, u32> {
+    let bits = sys.bits_of_u8(value);
+    let bits: [_; 32] = array_init::array_init(|i| match i {
+        0..=7 => bits[i].clone(),
+        _ => SystemRepr::<bool>::constant(sys, false),
+    });
+    sys.u32_of_bits(&bits)
+}
+
+/// Computes the conjunction of two booleans.
+fn and<S: SystemEth + ?Sized>(
+    sys: &mut S,
+    a: &Abstract<S, bool>,
+    b: &Abstract<S, bool>,
+) -> Abstract<S, bool> {
+    SystemBitAnd::<bool>::and(sys, a, b)
+}
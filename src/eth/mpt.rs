@@ -0,0 +1,255 @@
+use super::*;
+
+/// The value found by [`mpt_verify`].
+pub struct MptValue<S: SystemRepr<bool> + SystemRepr<u8> + SystemRepr<u32> + ?Sized> {
+    /// The bytes of the value, padded with unspecified bytes up to the length of the leaf node.
+    pub bytes: Vec<Abstract<S, u8>>,
+
+    /// The length of the value, in bytes.
+    pub len: Abstract<S, u32>,
+
+    /// Whether the proof is valid. If this is false, the other fields are unspecified.
+    pub valid: Abstract<S, bool>,
+}
+
+/// Verifies an inclusion proof for an Ethereum Merkle-Patricia trie with the given root hash,
+/// returning the value stored at the given key. `key` is the full 32-byte path, which for the
+/// state and storage tries is the Keccak-256 hash of the account address or slot.
+///
+/// `nodes` are the RLP-encoded nodes along the path from the root, as in the proofs returned by
+/// `eth_getProof`. Their lengths are fixed at synthesis time, so that the shape of the circuit
+/// depends only on the depth of the proof and the sizes of its nodes. All nodes except the last
+/// must be branch nodes, and the last must be a leaf node. Extension nodes and nodes shorter than
+/// 32 bytes (which are embedded in their parent rather than hashed) are not supported, and make
+/// the proof invalid.
+pub fn mpt_verify<S: SystemEth + ?Sized>(
+    sys: &mut S,
+    root: &[Abstract<S, u8>; 32],
+    key: &[Abstract<S, u8>; 32],
+    nodes: &[Vec<Abstract<S, u8>>],
+) -> MptValue<S> {
+    assert!(!nodes.is_empty(), "proof must have at least one node");
+    let depth = nodes.len() - 1;
+    assert!(depth < 64, "proof is too deep for a 32-byte key");
+    let nibbles: Vec<[Abstract<S, bool>; 4]> = key
+        .iter()
+        .flat_map(|byte| {
+            let bits = sys.bits_of_u8(byte);
+            [
+                array_init::array_init(|i| bits[i + 4].clone()),
+                array_init::array_init(|i| bits[i].clone()),
+            ]
+        })
+        .collect();
+
+    // Check the hash of the root node
+    let hash = sys.keccak256(&nodes[0]);
+    let mut valid = eq_bytes(sys, &hash, root);
+
+    // Follow branch nodes
+    for (i, node) in nodes[..depth].iter().enumerate() {
+        let buf = AbstractRam::new(node.clone());
+        let list = whole_item(sys, &buf);
+        let items = rlp_list_items(sys, &buf, &list, 17);
+        let end = items[16].end(sys);
+        let list_end = list.end(sys);
+        let exact = eq_u32(sys, &end, &list_end);
+        valid = and(sys, &valid, &exact);
+        valid = and(sys, &valid, &items[16].valid);
+
+        // Select the child by a binary tree over the bits of the nibble
+        let mut layer = items[..16].to_vec();
+        for bit in nibbles[i].iter() {
+            layer = layer
+                .chunks(2)
+                .map(|pair| RlpItem::select(sys, bit, &pair[1], &pair[0]))
+                .collect();
+        }
+        let child = &layer[0];
+        let is_hash = eq_u32_const(sys, &child.len, 32);
+        let not_list = SystemNot::<bool>::not(sys, &child.is_list);
+        let is_hash = and(sys, &is_hash, &not_list);
+        valid = and(sys, &valid, &is_hash);
+        valid = and(sys, &valid, &child.valid);
+        let child_hash: Vec<_> = (0..32)
+            .map(|j| {
+                let offset = add_const(sys, &child.offset, j);
+                read_byte(sys, &buf, &offset)
+            })
+            .collect();
+        let hash = sys.keccak256(&nodes[i + 1]);
+        let matches = eq_bytes(sys, &hash, &child_hash);
+        valid = and(sys, &valid, &matches);
+    }
+
+    // Check the leaf node
+    let leaf = AbstractRam::new(nodes[depth].clone());
+    let list = whole_item(sys, &leaf);
+    let items = rlp_list_items(sys, &leaf, &list, 2);
+    let end = items[1].end(sys);
+    let list_end = list.end(sys);
+    let exact = eq_u32(sys, &end, &list_end);
+    valid = and(sys, &valid, &exact);
+    for item in items.iter() {
+        let not_list = SystemNot::<bool>::not(sys, &item.is_list);
+        valid = and(sys, &valid, &item.valid);
+        valid = and(sys, &valid, &not_list);
+    }
+
+    // The path of the leaf uses hex-prefix encoding, with a flag nibble of 2 for even lengths or
+    // 3 followed by the first nibble for odd lengths
+    let rest = &nibbles[depth..];
+    let f = SystemRepr::<bool>::constant(sys, false);
+    let t = SystemRepr::<bool>::constant(sys, true);
+    let (first, rest) = if rest.len() % 2 == 1 {
+        (
+            byte_of_nibbles(sys, &[t.clone(), t, f.clone(), f], &rest[0]),
+            &rest[1..],
+        )
+    } else {
+        let zero = [f.clone(), f.clone(), f.clone(), f.clone()];
+        (
+            byte_of_nibbles(sys, &[f.clone(), t, f, zero[0].clone()], &zero),
+            rest,
+        )
+    };
+    let mut path = vec![first];
+    for pair in rest.chunks(2) {
+        path.push(byte_of_nibbles(sys, &pair[0], &pair[1]));
+    }
+    let path_len = eq_u32_const(sys, &items[0].len, path.len() as u32);
+    valid = and(sys, &valid, &path_len);
+    for (j, expected) in path.iter().enumerate() {
+        let offset = add_const(sys, &items[0].offset, j as u32);
+        let actual = read_byte(sys, &leaf, &offset);
+        let matches = eq_u8(sys, &actual, expected);
+        valid = and(sys, &valid, &matches);
+    }
+
+    // Extract the value
+    let value = &items[1];
+    let bytes = (0..leaf.len())
+        .map(|j| {
+            let offset = add_const(sys, &value.offset, j as u32);
+            read_byte(sys, &leaf, &offset)
+        })
+        .collect();
+    MptValue {
+        bytes,
+        len: value.len.clone(),
+        valid,
+    }
+}
+
+/// Decodes the item at the start of a buffer, requiring that it spans the whole buffer.
+fn whole_item<S: SystemEth + ?Sized>(sys: &mut S, buf: &AbstractRam<S, u8>) -> RlpItem<S> {
+    let zero = SystemRepr::<u32>::constant(sys, 0);
+    let item = rlp_item(sys, buf, &zero);
+    let end = item.end(sys);
+    let exact = eq_u32_const(sys, &end, buf.len() as u32);
+    let valid = and(sys, &item.valid, &exact);
+    RlpItem { valid, ..item }
+}
+
+/// Determines whether two byte strings of the same length are equal.
+fn eq_bytes<S: SystemEth + ?Sized>(
+    sys: &mut S,
+    a: &[Abstract<S, u8>],
+    b: &[Abstract<S, u8>],
+) -> Abstract<S, bool> {
+    let mut res = SystemRepr::<bool>::constant(sys, true);
+    for (a, b) in a.iter().zip(b) {
+        let eq = eq_u8(sys, a, b);
+        res = and(sys, &res, &eq);
+    }
+    res
+}
+
+/// Constructs a byte from its high and low nibbles, given as little-endian bits.
+fn byte_of_nibbles<S: SystemEth + ?Sized>(
+    sys: &mut S,
+    hi: &[Abstract<S, bool>; 4],
+    lo: &[Abstract<S, bool>; 4],
+) -> Abstract<S, u8> {
+    let bits: [_; 8] = array_init::array_init(|i| match i {
+        0..=3 => lo[i].clone(),
+        _ => hi[i - 4].clone(),
+    });
+    sys.u8_of_bits(&bits)
+}
+
+#[cfg(test)]
+fn rlp_encode(data: &[u8], is_list: bool) -> Vec<u8> {
+    let (short, long) = if is_list { (0xc0, 0xf7) } else { (0x80, 0xb7) };
+    let mut res = if !is_list && data.len() == 1 && data[0] < 0x80 {
+        Vec::new()
+    } else if data.len() < 56 {
+        vec![short + data.len() as u8]
+    } else if data.len() < 256 {
+        vec![long + 1, data.len() as u8]
+    } else {
+        vec![long + 2, (data.len() >> 8) as u8, data.len() as u8]
+    };
+    res.extend_from_slice(data);
+    res
+}
+
+#[test]
+fn test_mpt_verify() {
+    use crate::crypto::hash::SystemKeccak;
+    let mut sys = Eval;
+    let keys = [[0x12; 32], [0x34; 32]];
+    let values = [vec![0x42; 40], vec![0x99; 3]];
+
+    // Build a trie with a branch at the root and a leaf for each key
+    let leaves: Vec<Vec<u8>> = keys
+        .iter()
+        .zip(values.iter())
+        .map(|(key, value)| {
+            // The remaining path has 63 nibbles, so it is odd-length
+            let mut path = vec![0x30 | (key[0] & 0x0f)];
+            path.extend_from_slice(&key[1..]);
+            let mut payload = rlp_encode(&path, false);
+            payload.extend(rlp_encode(value, false));
+            rlp_encode(&payload, true)
+        })
+        .collect();
+    let mut payload = Vec::new();
+    for nibble in 0..17 {
+        match keys.iter().position(|key| key[0] >> 4 == nibble) {
+            Some(i) => payload.extend(rlp_encode(&sys.keccak256(&leaves[i]), false)),
+            None => payload.push(0x80),
+        }
+    }
+    let branch = rlp_encode(&payload, true);
+    let root = sys.keccak256(&branch);
+
+    for (i, key) in keys.iter().enumerate() {
+        let res = mpt_verify(&mut sys, &root, key, &[branch.clone(), leaves[i].clone()]);
+        assert!(res.valid);
+        assert_eq!(&res.bytes[..res.len as usize], &values[i][..]);
+    }
+
+    // The proof for one key can't be used for another
+    let res = mpt_verify(
+        &mut sys,
+        &root,
+        &keys[0],
+        &[branch.clone(), leaves[1].clone()],
+    );
+    assert!(!res.valid);
+    let mut key = keys[0];
+    key[31] ^= 1;
+    let res = mpt_verify(&mut sys, &root, &key, &[branch.clone(), leaves[0].clone()]);
+    assert!(!res.valid);
+
+    // Tampered nodes are rejected
+    let mut leaf = leaves[0].clone();
+    *leaf.last_mut().unwrap() ^= 1;
+    let res = mpt_verify(&mut sys, &root, &keys[0], &[branch.clone(), leaf]);
+    assert!(!res.valid);
+    let mut root = root;
+    root[0] ^= 1;
+    let res = mpt_verify(&mut sys, &root, &keys[0], &[branch, leaves[0].clone()]);
+    assert!(!res.valid);
+}
@@ -0,0 +1,172 @@
+use super::*;
+
+/// The location of an RLP-encoded item within a buffer, as decoded by [`rlp_item`].
+pub struct RlpItem<S: SystemRepr<bool> + SystemRepr<u32> + ?Sized> {
+    /// Whether the item is a list, rather than a string.
+    pub is_list: Abstract<S, bool>,
+
+    /// The offset of the payload of the item, just after its header.
+    pub offset: Abstract<S, u32>,
+
+    /// The length of the payload of the item, in bytes.
+    pub len: Abstract<S, u32>,
+
+    /// Whether the header of the item could be decoded, and the item lies within its enclosing
+    /// buffer or list. If this is false, the other fields are unspecified.
+    pub valid: Abstract<S, bool>,
+}
+
+impl<S: SystemRepr<bool> + SystemRepr<u32> + ?Sized> Clone for RlpItem<S> {
+    fn clone(&self) -> Self {
+        Self {
+            is_list: self.is_list.clone(),
+            offset: self.offset.clone(),
+            len: self.len.clone(),
+            valid: self.valid.clone(),
+        }
+    }
+}
+
+impl<S: SystemEth + ?Sized> RlpItem<S> {
+    /// Gets the offset just past the end of the payload of this item.
+    pub fn end(&self, sys: &mut S) -> Abstract<S, u32> {
+        SystemWrappingAdd::<u32>::wrapping_add(sys, &self.offset, &self.len)
+    }
+
+    /// Chooses between two items based on a condition.
+    pub fn select(sys: &mut S, cond: &Abstract<S, bool>, a: &Self, b: &Self) -> Self {
+        Self {
+            is_list: SystemSelect::<bool>::select(sys, cond, &a.is_list, &b.is_list),
+            offset: SystemSelect::<u32>::select(sys, cond, &a.offset, &b.offset),
+            len: SystemSelect::<u32>::select(sys, cond, &a.len, &b.len),
+            valid: SystemSelect::<bool>::select(sys, cond, &a.valid, &b.valid),
+        }
+    }
+}
+
+/// Decodes the header of the RLP item at the given offset in a buffer.
+///
+/// Long strings and lists are supported with length prefixes of up to 2 bytes, which covers any
+/// item that fits in a buffer of up to 64KiB. Non-canonical encodings, such as a single byte
+/// below `0x80` encoded as a short string, are accepted.
+pub fn rlp_item<S: SystemEth + ?Sized>(
+    sys: &mut S,
+    buf: &AbstractRam<S, u8>,
+    offset: &Abstract<S, u32>,
+) -> RlpItem<S> {
+    let prefix = read_byte(sys, buf, offset);
+    let prefix = byte_to_u32(sys, &prefix);
+    let below = |sys: &mut S, bound: u32| {
+        let bound = SystemRepr::<u32>::constant(sys, bound);
+        SystemOrd::<u32>::lt(sys, &prefix, &bound)
+    };
+    let is_byte = below(sys, 0x80);
+    let below_b8 = below(sys, 0xb8);
+    let below_c0 = below(sys, 0xc0);
+    let below_f8 = below(sys, 0xf8);
+    let is_list = SystemNot::<bool>::not(sys, &below_c0);
+    let above_b8 = SystemNot::<bool>::not(sys, &below_b8);
+    let is_long_string = and(sys, &above_b8, &below_c0);
+    let is_long_list = SystemNot::<bool>::not(sys, &below_f8);
+    let is_long = SystemBitOr::<bool>::or(sys, &is_long_string, &is_long_list);
+
+    // Payload length for short items
+    let base = [0x80, 0xc0].map(|b| SystemRepr::<u32>::constant(sys, 0u32.wrapping_sub(b)));
+    let base = SystemSelect::<u32>::select(sys, &is_list, &base[1], &base[0]);
+    let short_len = SystemWrappingAdd::<u32>::wrapping_add(sys, &prefix, &base);
+
+    // Length of the length for long items, and the length itself
+    let base = [0xb7, 0xf7].map(|b| SystemRepr::<u32>::constant(sys, 0u32.wrapping_sub(b)));
+    let base = SystemSelect::<u32>::select(sys, &is_list, &base[1], &base[0]);
+    let len_len = SystemWrappingAdd::<u32>::wrapping_add(sys, &prefix, &base);
+    let len_len_1 = eq_u32_const(sys, &len_len, 1);
+    let len_len_2 = eq_u32_const(sys, &len_len, 2);
+    let len_offset = add_const(sys, offset, 1);
+    let hi = read_byte(sys, buf, &len_offset);
+    let hi = byte_to_u32(sys, &hi);
+    let len_offset = add_const(sys, offset, 2);
+    let lo = read_byte(sys, buf, &len_offset);
+    let lo = byte_to_u32(sys, &lo);
+    let long_len = SystemBitShift::<u32, u8>::shl(sys, &hi, 8);
+    let long_len = SystemWrappingAdd::<u32>::wrapping_add(sys, &long_len, &lo);
+    let long_len = SystemSelect::<u32>::select(sys, &len_len_1, &hi, &long_len);
+
+    // Combine cases
+    let one = SystemRepr::<u32>::constant(sys, 1);
+    let zero = SystemRepr::<u32>::constant(sys, 0);
+    let len = SystemSelect::<u32>::select(sys, &is_long, &long_len, &short_len);
+    let len = SystemSelect::<u32>::select(sys, &is_byte, &one, &len);
+    let header = add_const(sys, &len_len, 1);
+    let header = SystemSelect::<u32>::select(sys, &is_long, &header, &one);
+    let header = SystemSelect::<u32>::select(sys, &is_byte, &zero, &header);
+    let payload = SystemWrappingAdd::<u32>::wrapping_add(sys, offset, &header);
+    let item = RlpItem {
+        is_list,
+        offset: payload,
+        len,
+        valid: SystemRepr::<bool>::constant(sys, true),
+    };
+
+    // Validate
+    let len_len_ok = SystemBitOr::<bool>::or(sys, &len_len_1, &len_len_2);
+    let not_long = SystemNot::<bool>::not(sys, &is_long);
+    let len_len_ok = SystemBitOr::<bool>::or(sys, &not_long, &len_len_ok);
+    let end = item.end(sys);
+    let limit = SystemRepr::<u32>::constant(sys, buf.len() as u32);
+    let in_bounds = SystemOrd::<u32>::le(sys, &end, &limit);
+    let no_overflow = SystemOrd::<u32>::le(sys, offset, &end);
+    let in_bounds = and(sys, &in_bounds, &no_overflow);
+    let valid = and(sys, &len_len_ok, &in_bounds);
+    RlpItem { valid, ..item }
+}
+
+/// Decodes the headers of the first `count` items in the payload of an RLP list. Each item is
+/// valid only if the list is, and the item lies within the payload of the list.
+pub fn rlp_list_items<S: SystemEth + ?Sized>(
+    sys: &mut S,
+    buf: &AbstractRam<S, u8>,
+    list: &RlpItem<S>,
+    count: usize,
+) -> Vec<RlpItem<S>> {
+    let list_valid = and(sys, &list.valid, &list.is_list);
+    let list_end = list.end(sys);
+    let mut pos = list.offset.clone();
+    let mut items = Vec::with_capacity(count);
+    for _ in 0..count {
+        let item = rlp_item(sys, buf, &pos);
+        let end = item.end(sys);
+        let in_list = SystemOrd::<u32>::le(sys, &end, &list_end);
+        let valid = and(sys, &item.valid, &in_list);
+        let valid = and(sys, &valid, &list_valid);
+        pos = end;
+        items.push(RlpItem { valid, ..item });
+    }
+    items
+}
+
+#[test]
+fn test_rlp_item() {
+    let mut sys = Eval;
+    let buf = [0xc0 + 8, 0x05, 0x82, 0xab, 0xcd, 0xb8, 0x01, 0xff, 0xc0];
+    let buf = AbstractRam::<Eval, u8>::new(buf.to_vec());
+    let list = rlp_item(&mut sys, &buf, &0);
+    assert!(list.valid && list.is_list);
+    assert_eq!((list.offset, list.len), (1, 8));
+    let items = rlp_list_items(&mut sys, &buf, &list, 5);
+    let decoded: Vec<_> = items
+        .iter()
+        .map(|item| (item.valid, item.is_list, item.offset, item.len))
+        .collect();
+    assert_eq!(
+        decoded[..4],
+        [
+            (true, false, 1, 1),
+            (true, false, 3, 2),
+            (true, false, 7, 1),
+            (true, true, 9, 0)
+        ]
+    );
+
+    // Items past the end of the list are invalid
+    assert!(!items[4].valid);
+}
@@ -0,0 +1,158 @@
+use crate::*;
+use core::ops::{Add, Mul};
+use ff::Field;
+
+/// An element of the field `F`, used as a value type so that systems can work with native field
+/// arithmetic rather than emulating it through integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FieldElement<F>(pub F);
+
+impl<F: Field> Add<&FieldElement<F>> for &FieldElement<F> {
+    type Output = FieldElement<F>;
+    fn add(self, rhs: &FieldElement<F>) -> Self::Output {
+        FieldElement(self.0 + rhs.0)
+    }
+}
+
+impl<F: Field> Mul<&FieldElement<F>> for &FieldElement<F> {
+    type Output = FieldElement<F>;
+    fn mul(self, rhs: &FieldElement<F>) -> Self::Output {
+        FieldElement(self.0 * rhs.0)
+    }
+}
+
+impl<F: Field> SystemRepr<FieldElement<F>> for Eval {
+    type Abstract = FieldElement<F>;
+    type Error = core::convert::Infallible;
+    fn constant(&mut self, value: FieldElement<F>) -> Self::Abstract {
+        value
+    }
+}
+
+impl<F: Field> SystemRead<FieldElement<F>> for Eval {
+    fn read_value(&self, value: &FieldElement<F>) -> FieldElement<F> {
+        *value
+    }
+}
+
+impl<F: Field> SystemSelect<FieldElement<F>> for Eval {
+    #[track_caller]
+    fn select(&mut self, cond: &bool, a: &FieldElement<F>, b: &FieldElement<F>) -> FieldElement<F> {
+        #[cfg(feature = "std")]
+        crate::coverage::record(crate::CoverageKind::Select, usize::from(!cond));
+        if *cond {
+            *a
+        } else {
+            *b
+        }
+    }
+}
+
+impl<F: Field> SystemAdd<FieldElement<F>> for Eval {
+    fn add(&mut self, a: &FieldElement<F>, b: &FieldElement<F>) -> FieldElement<F> {
+        a + b
+    }
+}
+
+impl<F: Field> SystemMul<FieldElement<F>> for Eval {
+    fn mul(&mut self, a: &FieldElement<F>, b: &FieldElement<F>) -> FieldElement<F> {
+        a * b
+    }
+}
+
+impl<F: Field> SystemAssertEq<FieldElement<F>> for Eval {
+    fn assert_eq(&mut self, a: &FieldElement<F>, b: &FieldElement<F>) {
+        assert!(a == b)
+    }
+}
+
+impl<F: Field> SystemInverse<FieldElement<F>> for Eval {
+    fn inverse_unchecked(
+        &mut self,
+        value: &Abstract<Self, FieldElement<F>>,
+    ) -> Abstract<Self, FieldElement<F>> {
+        FieldElement(Option::from(value.0.invert()).expect("inverse of zero"))
+    }
+
+    fn is_zero(&mut self, value: &Abstract<Self, FieldElement<F>>) -> Abstract<Self, bool> {
+        value.0.is_zero_vartime()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: ff::PrimeField> SystemRand<FieldElement<F>> for Eval {
+    fn rand(&mut self) -> FieldElement<F> {
+        // Combine enough random limbs that the result is close to uniform
+        let shift = F::from(u64::MAX) + F::one();
+        let limbs = F::NUM_BITS as usize / 64 + 2;
+        FieldElement((0..limbs).fold(F::zero(), |acc, _| acc * shift + F::from(self.next_u64())))
+    }
+}
+
+/// Computes `a / b + a` in any system which supports native field arithmetic.
+#[cfg(test)]
+fn div_add<F: Field, S>(
+    sys: &mut S,
+    a: &Abstract<S, FieldElement<F>>,
+    b: &Abstract<S, FieldElement<F>>,
+) -> Abstract<S, FieldElement<F>>
+where
+    S: SystemAdd<FieldElement<F>> + SystemMul<FieldElement<F>> + SystemInverse<FieldElement<F>>,
+{
+    let quot = sys.div_unchecked(a, b);
+    sys.add(&quot, a)
+}
+
+#[test]
+fn test_field_eval() {
+    use bls12_381::Scalar;
+    let a = FieldElement(Scalar::from(12));
+    let b = FieldElement(Scalar::from(4));
+    assert_eq!(div_add(&mut Eval, &a, &b), FieldElement(Scalar::from(15)));
+    assert!(!Eval.is_zero(&a));
+    assert!(Eval.is_zero(&FieldElement(Scalar::zero())));
+    SystemAssertEq::<FieldElement<Scalar>>::assert_eq(&mut Eval, &a, &a);
+}
+
+#[cfg(feature = "r1cs")]
+#[test]
+fn test_field_r1cs() {
+    use crate::r1cs::*;
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let a = Formula::from(sys.declare());
+    let b = Formula::from(sys.declare());
+    let r = div_add(&mut sys, &a, &b);
+    let expected = sys.constant(FieldElement(Scalar::from(15)));
+    SystemAssertEq::<FieldElement<Scalar>>::assert_eq(&mut sys, &r, &expected);
+
+    // The inverse of `b` and the quotient each take a variable
+    let inv = Scalar::from(4).invert().unwrap();
+    let mut assignment = vec![Scalar::from(12), Scalar::from(4), inv, Scalar::from(3)];
+    assert!(sys.is_satisfied(&assignment));
+    assignment[0] = Scalar::from(8);
+    assert!(!sys.is_satisfied(&assignment));
+}
+
+#[cfg(feature = "r1cs")]
+#[test]
+fn test_is_zero_r1cs() {
+    use crate::r1cs::*;
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let a = Formula::from(sys.declare());
+    let z = SystemInverse::<FieldElement<Scalar>>::is_zero(&mut sys, &a);
+
+    // The assignment is `[a, a^-1 or 0, z]`
+    let x = Scalar::from(7);
+    let inv = x.invert().unwrap();
+    let assignment = [x, inv, Scalar::zero()];
+    assert!(sys.is_satisfied(&assignment));
+    assert_eq!(sys.eval(z, &assignment), Scalar::zero());
+    assert!(!sys.is_satisfied(&[x, inv, Scalar::one()]));
+    assert!(!sys.is_satisfied(&[x, Scalar::zero(), Scalar::zero()]));
+    let assignment = [Scalar::zero(), Scalar::from(5), Scalar::one()];
+    assert!(sys.is_satisfied(&assignment));
+    assert_eq!(sys.eval(z, &assignment), Scalar::one());
+    assert!(!sys.is_satisfied(&[Scalar::zero(), Scalar::from(5), Scalar::zero()]));
+}
@@ -0,0 +1,287 @@
+//! Gadgets for checking membership in probabilistic set structures, such as allow-lists or
+//! deny-lists committed to as a filter array.
+use crate::crypto::hash::SystemSipHash;
+use crate::ram::AbstractRam;
+use crate::*;
+
+/// A system in which filter membership can be checked.
+pub trait SystemFilter:
+    BinarySystem
+    + SystemSipHash
+    + SystemBits
+    + SystemSelect<bool>
+    + SystemSelect<u8>
+    + SystemBitXor<u8>
+    + SystemWrappingAdd<u32>
+{
+}
+
+impl<
+        S: BinarySystem
+            + SystemSipHash
+            + SystemBits
+            + SystemSelect<bool>
+            + SystemSelect<u8>
+            + SystemBitXor<u8>
+            + SystemWrappingAdd<u32>,
+    > SystemFilter for S
+{
+}
+
+/// A Bloom filter over abstract bits. Each item is hashed to `num_hashes` positions using
+/// SipHash-2-4 with double hashing, and is a possible member if all of those bits are set.
+pub struct BloomFilter<S: SystemRepr<bool> + SystemRepr<u64> + ?Sized> {
+    bits: AbstractRam<S, bool>,
+    key: [Abstract<S, u64>; 2],
+    num_hashes: usize,
+}
+
+impl<S: SystemFilter + ?Sized> BloomFilter<S> {
+    /// Constructs a [`BloomFilter`] with the given bits and hash key. The number of bits must be
+    /// a power of two.
+    pub fn new(
+        bits: Vec<Abstract<S, bool>>,
+        key: [Abstract<S, u64>; 2],
+        num_hashes: usize,
+    ) -> Self {
+        assert!(
+            bits.len().is_power_of_two(),
+            "number of bits must be a power of two"
+        );
+        Self {
+            bits: AbstractRam::new(bits),
+            key,
+            num_hashes,
+        }
+    }
+
+    /// The current bits of this filter.
+    pub fn bits(&self) -> &[Abstract<S, bool>] {
+        self.bits.cells()
+    }
+
+    /// Gets the positions of the bits for the given item, as little-endian bits.
+    pub fn positions(&self, sys: &mut S, item: &[Abstract<S, u8>]) -> Vec<Vec<Abstract<S, bool>>> {
+        let hash = sys.siphash24(&self.key, item);
+        let hash = sys.bits_of_u64(&hash);
+        let true_ = SystemRepr::<bool>::constant(sys, true);
+        let mut pos = sys.u32_of_bits(&array_init::array_init(|i| hash[i].clone()));
+
+        // The step must be odd so that it generates all positions
+        let step = array_init::array_init(|i| match i {
+            0 => true_.clone(),
+            _ => hash[32 + i].clone(),
+        });
+        let step = sys.u32_of_bits(&step);
+        let addr_bits = self.bits.len().trailing_zeros() as usize;
+        let mut res = Vec::with_capacity(self.num_hashes);
+        for _ in 0..self.num_hashes {
+            let bits = sys.bits_of_u32(&pos);
+            res.push(bits[..addr_bits].to_vec());
+            pos = SystemWrappingAdd::<u32>::wrapping_add(sys, &pos, &step);
+        }
+        res
+    }
+
+    /// Inserts an item into this filter if `enable` is true.
+    pub fn insert(&mut self, sys: &mut S, item: &[Abstract<S, u8>], enable: &Abstract<S, bool>) {
+        let true_ = SystemRepr::<bool>::constant(sys, true);
+        for pos in self.positions(sys, item) {
+            self.bits.write(sys, &pos, &true_, enable);
+        }
+    }
+
+    /// Determines whether the given item may be in this filter. If this is false, the item is
+    /// definitely not in the filter.
+    pub fn contains(&self, sys: &mut S, item: &[Abstract<S, u8>]) -> Abstract<S, bool> {
+        let mut res = SystemRepr::<bool>::constant(sys, true);
+        for pos in self.positions(sys, item) {
+            let bit = self.bits.read(sys, &pos);
+            res = SystemBitAnd::<bool>::and(sys, &res, &bit);
+        }
+        res
+    }
+}
+
+/// A cuckoo filter with buckets of `B` 8-bit fingerprints, where a fingerprint of zero marks an
+/// empty slot. Each item has two candidate buckets, related by partial-key cuckoo hashing, and
+/// is a possible member if its fingerprint appears in either of them.
+///
+/// Insertion does not relocate existing fingerprints, so it can fail even when the filter is
+/// not full. Filters which need relocation should be built outside of the circuit.
+pub struct CuckooFilter<S: SystemRepr<u8> + SystemRepr<u64> + ?Sized, const B: usize> {
+    buckets: AbstractRam<S, [u8; B]>,
+    key: [Abstract<S, u64>; 2],
+}
+
+impl<S: SystemFilter + ?Sized, const B: usize> CuckooFilter<S, B> {
+    /// Constructs a [`CuckooFilter`] with the given buckets and hash key. The number of buckets
+    /// must be a power of two.
+    pub fn new(buckets: Vec<[Abstract<S, u8>; B]>, key: [Abstract<S, u64>; 2]) -> Self {
+        assert!(
+            buckets.len().is_power_of_two(),
+            "number of buckets must be a power of two"
+        );
+        Self {
+            buckets: AbstractRam::new(buckets),
+            key,
+        }
+    }
+
+    /// The current buckets of this filter.
+    pub fn buckets(&self) -> &[[Abstract<S, u8>; B]] {
+        self.buckets.cells()
+    }
+
+    /// Gets the fingerprint of the given item, along with its candidate buckets.
+    pub fn locate(&self, sys: &mut S, item: &[Abstract<S, u8>]) -> CuckooLocation<S> {
+        let hash = sys.siphash24(&self.key, item);
+        let hash = sys.bits_of_u64(&hash);
+        let index_bits = self.buckets.len().trailing_zeros() as usize;
+        let first = hash[..index_bits].to_vec();
+
+        // Avoid the zero fingerprint, which marks empty slots
+        let mut nonzero = SystemRepr::<bool>::constant(sys, false);
+        for bit in hash[32..40].iter() {
+            nonzero = SystemBitOr::<bool>::or(sys, &nonzero, bit);
+        }
+        let mut fingerprint: [_; 8] = array_init::array_init(|i| hash[32 + i].clone());
+        let is_zero = SystemNot::<bool>::not(sys, &nonzero);
+        fingerprint[0] = SystemBitOr::<bool>::or(sys, &fingerprint[0], &is_zero);
+        let fingerprint = sys.u8_of_bits(&fingerprint);
+
+        // The second bucket is the first combined with the hash of the fingerprint
+        let offset = sys.siphash24(&self.key, std::slice::from_ref(&fingerprint));
+        let offset = sys.bits_of_u64(&offset);
+        let second = first
+            .iter()
+            .zip(offset.iter())
+            .map(|(a, b)| SystemBitXor::<bool>::xor(sys, a, b))
+            .collect();
+        CuckooLocation {
+            fingerprint,
+            buckets: [first, second],
+        }
+    }
+
+    /// Inserts an item into the first empty slot of its candidate buckets if `enable` is true.
+    /// Returns whether the item was inserted, which is false if `enable` is false or both
+    /// buckets are full.
+    pub fn insert(
+        &mut self,
+        sys: &mut S,
+        item: &[Abstract<S, u8>],
+        enable: &Abstract<S, bool>,
+    ) -> Abstract<S, bool> {
+        let CuckooLocation {
+            fingerprint,
+            buckets: indices,
+        } = self.locate(sys, item);
+        let mut pending = enable.clone();
+        let true_ = SystemRepr::<bool>::constant(sys, true);
+        for index in indices.iter() {
+            let mut bucket = self.buckets.read(sys, index);
+            for slot in bucket.iter_mut() {
+                let empty = eq_zero(sys, slot);
+                let place = SystemBitAnd::<bool>::and(sys, &pending, &empty);
+                *slot = SystemSelect::<u8>::select(sys, &place, &fingerprint, slot);
+                let placed = SystemNot::<bool>::not(sys, &place);
+                pending = SystemBitAnd::<bool>::and(sys, &pending, &placed);
+            }
+            self.buckets.write(sys, index, &bucket, &true_);
+        }
+        let failed = SystemNot::<bool>::not(sys, &pending);
+        SystemBitAnd::<bool>::and(sys, enable, &failed)
+    }
+
+    /// Determines whether the given item may be in this filter. If this is false, the item is
+    /// definitely not in the filter.
+    pub fn contains(&self, sys: &mut S, item: &[Abstract<S, u8>]) -> Abstract<S, bool> {
+        let CuckooLocation {
+            fingerprint,
+            buckets: indices,
+        } = self.locate(sys, item);
+        let mut res = SystemRepr::<bool>::constant(sys, false);
+        for index in indices.iter() {
+            let bucket = self.buckets.read(sys, index);
+            for slot in bucket.iter() {
+                let diff = SystemBitXor::<u8>::xor(sys, slot, &fingerprint);
+                let found = eq_zero(sys, &diff);
+                res = SystemBitOr::<bool>::or(sys, &res, &found);
+            }
+        }
+        res
+    }
+}
+
+/// The fingerprint and candidate buckets of an item in a [`CuckooFilter`].
+pub struct CuckooLocation<S: SystemRepr<bool> + SystemRepr<u8> + ?Sized> {
+    /// The fingerprint of the item, which is never zero.
+    pub fingerprint: Abstract<S, u8>,
+
+    /// The indices of the candidate buckets, as little-endian bits.
+    pub buckets: [Vec<Abstract<S, bool>>; 2],
+}
+
+/// Determines whether a byte is zero.
+fn eq_zero<S: SystemFilter + ?Sized>(sys: &mut S, value: &Abstract<S, u8>) -> Abstract<S, bool> {
+    let mut any = SystemRepr::<bool>::constant(sys, false);
+    for bit in sys.bits_of_u8(value) {
+        any = SystemBitOr::<bool>::or(sys, &any, &bit);
+    }
+    SystemNot::<bool>::not(sys, &any)
+}
+
+#[test]
+fn test_bloom_filter() {
+    let mut sys = Eval;
+    let key = [0x0123456789abcdef, 0xfedcba9876543210];
+    let mut filter = BloomFilter::new(vec![false; 256], key, 3);
+    let members: Vec<[u8; 4]> = (0..10u32).map(|i| (i * 7919).to_le_bytes()).collect();
+    for member in members.iter() {
+        filter.insert(&mut sys, member, &true);
+    }
+    filter.insert(&mut sys, b"not inserted", &false);
+    assert!(filter.bits().iter().filter(|b| **b).count() <= 30);
+    for member in members.iter() {
+        assert!(filter.contains(&mut sys, member));
+    }
+    let false_positives = (1000..1100u32)
+        .filter(|i| filter.contains(&mut sys, &i.to_le_bytes()))
+        .count();
+    assert!(false_positives < 10);
+    assert!(!filter.contains(&mut sys, b"not inserted"));
+
+    // Check consistency with a binary system
+    let mut sys = BinaryEmulate::new(Eval);
+    let bits = filter.bits().iter().map(|b| sys.constant(*b)).collect();
+    let key = key.map(|k| sys.constant(k));
+    let filter = BloomFilter::new(bits, key, 3);
+    let member = members[3].map(|b| sys.constant(b));
+    let res = filter.contains(&mut sys, &member);
+    assert_eq!(res, sys.constant(true));
+}
+
+#[test]
+fn test_cuckoo_filter() {
+    let mut sys = Eval;
+    let key = [0x0123456789abcdef, 0xfedcba9876543210];
+    let mut filter = CuckooFilter::<Eval, 4>::new(vec![[0; 4]; 16], key);
+    let members: Vec<[u8; 4]> = (0..20u32).map(|i| (i * 7919).to_le_bytes()).collect();
+    for member in members.iter() {
+        assert!(filter.insert(&mut sys, member, &true));
+    }
+    assert!(!filter.insert(&mut sys, b"not inserted", &false));
+    for member in members.iter() {
+        assert!(filter.contains(&mut sys, member));
+    }
+    let false_positives = (1000..1100u32)
+        .filter(|i| filter.contains(&mut sys, &i.to_le_bytes()))
+        .count();
+    assert!(false_positives < 10);
+
+    // Insertion fails once both candidate buckets are full
+    let mut filter = CuckooFilter::<Eval, 1>::new(vec![[0; 1]; 1], key);
+    assert!(filter.insert(&mut sys, b"a", &true));
+    assert!(!filter.insert(&mut sys, b"b", &true));
+}
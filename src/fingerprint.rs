@@ -0,0 +1,136 @@
+use crate::crypto::hash::SystemSha256Bytes;
+use crate::Eval;
+use std::fmt;
+use std::str::FromStr;
+
+/// A SHA-256 hash of the canonical serialization of a circuit. Building the same circuit always
+/// gives the same fingerprint, while any change to its structure gives a different one, so
+/// comparing fingerprints at runtime ensures that a prover and verifier were built from the
+/// identical circuit.
+///
+/// Fingerprints are displayed and parsed as hexadecimal strings, so that the expected
+/// fingerprint of a circuit can be embedded in the code of its verifier.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CircuitFingerprint(pub [u8; 32]);
+
+impl CircuitFingerprint {
+    /// Computes the fingerprint of a circuit from its canonical serialization.
+    pub(crate) fn of(data: &[u8]) -> Self {
+        CircuitFingerprint(Eval.sha256_bytes(data))
+    }
+}
+
+impl fmt::Display for CircuitFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CircuitFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CircuitFingerprint({})", self)
+    }
+}
+
+/// The error returned when parsing a [`CircuitFingerprint`] that isn't 64 hexadecimal digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseFingerprintError;
+
+impl fmt::Display for ParseFingerprintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid circuit fingerprint")
+    }
+}
+
+impl std::error::Error for ParseFingerprintError {}
+
+impl FromStr for CircuitFingerprint {
+    type Err = ParseFingerprintError;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        if str.len() != 64 || !str.is_ascii() {
+            return Err(ParseFingerprintError);
+        }
+        let mut res = [0; 32];
+        for (i, byte) in res.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&str[i * 2..i * 2 + 2], 16)
+                .map_err(|_| ParseFingerprintError)?;
+        }
+        Ok(CircuitFingerprint(res))
+    }
+}
+
+#[cfg(feature = "graph")]
+impl crate::graph::Graph {
+    /// Computes the fingerprint of this graph. This covers every node, in order, along with the
+    /// names and bodies of the modules it calls, so it is sensitive to the order in which the
+    /// circuit was synthesized. Nodes which don't contribute to any output are included.
+    pub fn fingerprint(&self) -> CircuitFingerprint {
+        let mut data = Vec::new();
+        self.write_checkpoint(&mut data, &[]).unwrap();
+        CircuitFingerprint::of(&data)
+    }
+}
+
+#[cfg(feature = "r1cs")]
+impl<F: ff::PrimeField> crate::r1cs::ArithmeticSystem<F> {
+    /// Computes the fingerprint of this system. This covers the constraints, in order, the
+    /// number of variables and the public inputs, but not the labels of variables.
+    pub fn fingerprint(&self) -> CircuitFingerprint {
+        use crate::r1cs::{ConstraintSink, ConstraintWriter};
+        let mut writer = ConstraintWriter::new(Vec::new()).unwrap();
+        for constraint in self.constraints() {
+            writer.push(constraint.clone());
+        }
+        let mut data = writer.finish(self.num_vars()).unwrap();
+        for var in self.public_inputs() {
+            data.extend_from_slice(&(var.index() as u64).to_le_bytes());
+        }
+        CircuitFingerprint::of(&data)
+    }
+}
+
+#[cfg(all(feature = "graph", feature = "r1cs"))]
+#[test]
+fn test_fingerprint() {
+    use crate::graph::Graph;
+    use crate::r1cs::ArithmeticSystem;
+    use crate::*;
+    use bls12_381::Scalar;
+
+    // Fingerprints are deterministic, and sensitive to any change in structure
+    let build = |negate: bool| {
+        let mut graph = Graph::new();
+        let a = graph.input();
+        let b = graph.input();
+        let c = SystemBitAnd::<bool>::and(&mut graph, &a, &b);
+        if negate {
+            SystemNot::<bool>::not(&mut graph, &c);
+        }
+        graph
+    };
+    assert_eq!(build(false).fingerprint(), build(false).fingerprint());
+    assert_ne!(build(false).fingerprint(), build(true).fingerprint());
+    let fingerprint = build(true).fingerprint();
+    assert_eq!(fingerprint.to_string().parse(), Ok(fingerprint));
+    assert_eq!(
+        "00".parse::<CircuitFingerprint>(),
+        Err(ParseFingerprintError)
+    );
+
+    let build = |public: bool| {
+        let mut sys = ArithmeticSystem::<Scalar>::new();
+        let a = if public {
+            sys.declare_public()
+        } else {
+            sys.declare()
+        };
+        let b = sys.declare();
+        sys.product(a.into(), b.into());
+        sys
+    };
+    assert_eq!(build(true).fingerprint(), build(true).fingerprint());
+    assert_ne!(build(true).fingerprint(), build(false).fingerprint());
+}
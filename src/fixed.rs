@@ -0,0 +1,149 @@
+use crate::*;
+
+/// A signed fixed-point number with `FRAC` fractional bits. This is represented by an [`i32`]
+/// whose value is the number multiplied by `2^FRAC`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Fixed<const FRAC: u32>(pub i32);
+
+impl<const FRAC: u32> Fixed<FRAC> {
+    /// Constructs the [`Fixed`] nearest to the given value. Out-of-range values saturate.
+    pub fn from_f64(value: f64) -> Self {
+        Self((value * (1u64 << FRAC) as f64).round() as i32)
+    }
+
+    /// Gets the exact value of this [`Fixed`] as an [`f64`].
+    pub fn to_f64(self) -> f64 {
+        f64::from(self.0) / (1u64 << FRAC) as f64
+    }
+}
+
+impl<S: SystemRepr<i32> + ?Sized, const FRAC: u32> SystemRepr<Fixed<FRAC>> for S {
+    type Abstract = Abstract<S, i32>;
+    type Error = SystemError<S, i32>;
+    fn constant(&mut self, value: Fixed<FRAC>) -> Self::Abstract {
+        self.constant(value.0)
+    }
+
+    fn status(&self) -> Result<(), Self::Error> {
+        SystemRepr::<i32>::status(self)
+    }
+}
+
+impl<S: SystemRead<i32> + ?Sized, const FRAC: u32> SystemRead<Fixed<FRAC>> for S {
+    fn read_value(&self, value: &Abstract<Self, Fixed<FRAC>>) -> Fixed<FRAC> {
+        Fixed(self.read_value(value))
+    }
+}
+
+impl<S: SystemSelect<i32> + ?Sized, const FRAC: u32> SystemSelect<Fixed<FRAC>> for S {
+    fn select(
+        &mut self,
+        cond: &Abstract<Self, bool>,
+        a: &Abstract<Self, Fixed<FRAC>>,
+        b: &Abstract<Self, Fixed<FRAC>>,
+    ) -> Abstract<Self, Fixed<FRAC>> {
+        SystemSelect::<i32>::select(self, cond, a, b)
+    }
+}
+
+/// A system in which arithmetic on [`Fixed`] numbers can be performed.
+pub trait SystemFixed: SystemWrappingAdd<i32> + SystemMulShr<i32> + SystemOrd<i32> {
+    /// Adds two [`Fixed`] numbers, with wrapping.
+    fn fixed_add<const FRAC: u32>(
+        &mut self,
+        a: &Abstract<Self, Fixed<FRAC>>,
+        b: &Abstract<Self, Fixed<FRAC>>,
+    ) -> Abstract<Self, Fixed<FRAC>> {
+        self.wrapping_add(a, b)
+    }
+
+    /// Multiplies two [`Fixed`] numbers, with wrapping. The exact product is rounded towards
+    /// negative infinity.
+    fn fixed_mul<const FRAC: u32>(
+        &mut self,
+        a: &Abstract<Self, Fixed<FRAC>>,
+        b: &Abstract<Self, Fixed<FRAC>>,
+    ) -> Abstract<Self, Fixed<FRAC>> {
+        self.mul_shr(a, b, FRAC as u8)
+    }
+
+    /// Determines whether `a` is less than `b`.
+    fn fixed_lt<const FRAC: u32>(
+        &mut self,
+        a: &Abstract<Self, Fixed<FRAC>>,
+        b: &Abstract<Self, Fixed<FRAC>>,
+    ) -> Abstract<Self, bool> {
+        self.lt(a, b)
+    }
+
+    /// Determines whether `a` is less than or equal to `b`.
+    fn fixed_le<const FRAC: u32>(
+        &mut self,
+        a: &Abstract<Self, Fixed<FRAC>>,
+        b: &Abstract<Self, Fixed<FRAC>>,
+    ) -> Abstract<Self, bool> {
+        self.le(a, b)
+    }
+}
+
+impl<S: SystemWrappingAdd<i32> + SystemMulShr<i32> + SystemOrd<i32>> SystemFixed for S {}
+
+#[test]
+fn test_fixed_eval() {
+    let a = Fixed::<16>::from_f64(3.25);
+    let b = Fixed::<16>::from_f64(-1.5);
+    let sum = Eval.fixed_add::<16>(&a.0, &b.0);
+    assert_eq!(Fixed::<16>(sum).to_f64(), 1.75);
+    let prod = Eval.fixed_mul::<16>(&a.0, &b.0);
+    assert_eq!(Fixed::<16>(prod).to_f64(), -4.875);
+    assert!(Eval.fixed_lt::<16>(&b.0, &a.0));
+}
+
+#[test]
+fn test_fixed_binary_emulate() {
+    let mut sys = BinaryEmulate::new(Eval);
+    let values = [-100.0, -3.75, -0.001, 0.0, 0.5, 1.0, 2.125, 181.0];
+    for x in values {
+        for y in values {
+            let (x, y) = (Fixed::<16>::from_f64(x), Fixed::<16>::from_f64(y));
+            let a = sys.constant(x);
+            let b = sys.constant(y);
+            let r = sys.fixed_mul::<16>(&a, &b);
+            assert_eq!(r, sys.constant(Eval.fixed_mul::<16>(&x.0, &y.0)));
+            let r = sys.fixed_add::<16>(&a, &b);
+            assert_eq!(r, sys.constant(Eval.fixed_add::<16>(&x.0, &y.0)));
+            let r = sys.fixed_le::<16>(&a, &b);
+            assert_eq!(r, x <= y);
+        }
+    }
+}
+
+#[cfg(feature = "r1cs")]
+#[test]
+fn test_fixed_mul_r1cs() {
+    use crate::r1cs::*;
+    use bls12_381::Scalar;
+    let embed = |value: i64| {
+        let abs = Scalar::from(value.unsigned_abs());
+        if value < 0 {
+            -abs
+        } else {
+            abs
+        }
+    };
+    for (x, y) in [(3.25, -1.5), (-0.001, -100.0), (181.0, 2.125)] {
+        let (x, y) = (Fixed::<16>::from_f64(x), Fixed::<16>::from_f64(y));
+        let mut sys = ArithmeticSystem::<Scalar>::new();
+        let a = Formula::from(sys.declare());
+        let b = Formula::from(sys.declare());
+        let r = sys.fixed_mul::<16>(&a, &b);
+        let prod = i64::from(x.0) * i64::from(y.0);
+        let mut assignment = vec![embed(x.0.into()), embed(y.0.into()), embed(prod)];
+        assignment.extend((0..64).map(|i| Scalar::from(((prod >> i) & 1) as u64)));
+        assert!(sys.is_satisfied(&assignment));
+        assert_eq!(
+            sys.eval(r, &assignment),
+            embed(Eval.fixed_mul::<16>(&x.0, &y.0).into())
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! Deterministic finite state machines over bytes. A machine is declared concretely, as a set of
+//! states and guarded transitions, and is then stepped over abstract input, with the state
+//! represented as a one-hot vector of bits.
+use crate::*;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+/// A system in which an [`Fsm`] can be run.
+pub trait SystemFsm: BinarySystem + SystemOrd<u8> {}
+
+impl<S: BinarySystem + SystemOrd<u8> + ?Sized> SystemFsm for S {}
+
+/// Identifies a state of an [`Fsm`]. States are numbered in the order they were declared, which
+/// is also the position of their bit in the abstract state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StateId(usize);
+
+impl StateId {
+    /// The position of this state in the abstract state.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A set of input bytes on which a transition may be taken, as a union of inclusive ranges.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Guard {
+    ranges: Vec<(u8, u8)>,
+}
+
+impl Guard {
+    /// The guard which accepts any byte.
+    pub fn any() -> Self {
+        Self {
+            ranges: vec![(0, u8::MAX)],
+        }
+    }
+
+    /// The guard which accepts the bytes in either this guard or another.
+    pub fn or(mut self, other: impl Into<Guard>) -> Self {
+        self.ranges.extend(other.into().ranges);
+        self
+    }
+
+    /// The guard which accepts the bytes not accepted by this guard.
+    pub fn complement(&self) -> Self {
+        let mut ranges = Vec::new();
+        let mut lo = 0;
+        for byte in 0..=u8::MAX {
+            if self.contains(byte) {
+                if lo < byte as u16 {
+                    ranges.push((lo as u8, byte - 1));
+                }
+                lo = byte as u16 + 1;
+            }
+        }
+        if lo <= u8::MAX as u16 {
+            ranges.push((lo as u8, u8::MAX));
+        }
+        Self { ranges }
+    }
+
+    /// Determines whether this guard accepts the given byte.
+    pub fn contains(&self, byte: u8) -> bool {
+        (self.ranges.iter()).any(|(lo, hi)| *lo <= byte && byte <= *hi)
+    }
+}
+
+impl From<u8> for Guard {
+    fn from(byte: u8) -> Self {
+        Self {
+            ranges: vec![(byte, byte)],
+        }
+    }
+}
+
+impl From<RangeInclusive<u8>> for Guard {
+    fn from(range: RangeInclusive<u8>) -> Self {
+        Self {
+            ranges: vec![(*range.start(), *range.end())],
+        }
+    }
+}
+
+/// A transition of an [`Fsm`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Transition {
+    from: StateId,
+    to: StateId,
+    guard: Guard,
+}
+
+/// A deterministic finite state machine which consumes one byte per step. The first state
+/// declared is the initial state. If no transition from the current state accepts a byte, the
+/// machine enters a dead state, in which no bit of the abstract state is set and from which no
+/// transition can be taken.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fsm {
+    names: Vec<String>,
+    accepting: Vec<bool>,
+    transitions: Vec<Transition>,
+}
+
+impl Fsm {
+    /// Constructs an [`Fsm`] with no states.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a state with the given name, which is used only for debugging.
+    pub fn state(&mut self, name: impl Into<String>) -> StateId {
+        self.names.push(name.into());
+        self.accepting.push(false);
+        StateId(self.names.len() - 1)
+    }
+
+    /// Marks a state as accepting.
+    pub fn accept(&mut self, state: StateId) {
+        self.accepting[state.0] = true;
+    }
+
+    /// Declares a transition from one state to another, taken when the input byte is accepted by
+    /// `guard`. Panics if the guard accepts a byte accepted by another transition from the same
+    /// state, since the machine would not be deterministic.
+    pub fn transition(&mut self, from: StateId, to: StateId, guard: impl Into<Guard>) {
+        let guard = guard.into();
+        for other in self.transitions.iter().filter(|t| t.from == from) {
+            if let Some(byte) =
+                (0..=u8::MAX).find(|b| guard.contains(*b) && other.guard.contains(*b))
+            {
+                panic!(
+                    "transitions from {:?} overlap on byte {:#04x}",
+                    self.names[from.0], byte
+                );
+            }
+        }
+        self.transitions.push(Transition { from, to, guard });
+    }
+
+    /// The number of states declared.
+    pub fn num_states(&self) -> usize {
+        self.names.len()
+    }
+
+    /// The name of the given state.
+    pub fn name(&self, state: StateId) -> &str {
+        &self.names[state.0]
+    }
+
+    /// Determines whether the given state is accepting.
+    pub fn is_accepting(&self, state: StateId) -> bool {
+        self.accepting[state.0]
+    }
+
+    /// Gets the state entered by consuming the given byte in the given state, or [`None`] if the
+    /// machine enters the dead state.
+    pub fn next(&self, state: StateId, byte: u8) -> Option<StateId> {
+        (self.transitions.iter())
+            .find(|t| t.from == state && t.guard.contains(byte))
+            .map(|t| t.to)
+    }
+
+    /// Gets the state entered by consuming the given input from the initial state, or [`None`] if
+    /// the machine enters the dead state.
+    pub fn run(&self, input: &[u8]) -> Option<StateId> {
+        assert!(self.num_states() > 0, "no initial state");
+        (input.iter()).try_fold(StateId(0), |state, byte| self.next(state, *byte))
+    }
+
+    /// Gets the abstract representation of the initial state.
+    pub fn initial<S: SystemFsm + ?Sized>(&self, sys: &mut S) -> Vec<Abstract<S, bool>> {
+        assert!(self.num_states() > 0, "no initial state");
+        (0..self.num_states())
+            .map(|i| SystemRepr::<bool>::constant(sys, i == 0))
+            .collect()
+    }
+
+    /// Consumes a byte, giving the next state. Each guard range is compared against the byte
+    /// once, regardless of how many transitions use it.
+    pub fn step<S: SystemFsm + ?Sized>(
+        &self,
+        sys: &mut S,
+        state: &[Abstract<S, bool>],
+        byte: &Abstract<S, u8>,
+    ) -> Vec<Abstract<S, bool>> {
+        assert_eq!(state.len(), self.num_states(), "wrong number of states");
+        let mut ranges: Vec<((u8, u8), Abstract<S, bool>)> = Vec::new();
+        let mut next: Vec<Option<Abstract<S, bool>>> = vec![None; self.num_states()];
+        for t in self.transitions.iter() {
+            let mut accepted: Option<Abstract<S, bool>> = None;
+            for range in t.guard.ranges.iter() {
+                let in_range = match ranges.iter().find(|(r, _)| r == range) {
+                    Some((_, in_range)) => in_range.clone(),
+                    None => {
+                        let in_range = in_range(sys, byte, *range);
+                        ranges.push((*range, in_range.clone()));
+                        in_range
+                    }
+                };
+                accepted = Some(match accepted {
+                    Some(accepted) => SystemBitOr::<bool>::or(sys, &accepted, &in_range),
+                    None => in_range,
+                });
+            }
+            let Some(accepted) = accepted else {
+                continue;
+            };
+            let taken = SystemBitAnd::<bool>::and(sys, &state[t.from.0], &accepted);
+            next[t.to.0] = Some(match next[t.to.0].take() {
+                Some(prev) => SystemBitOr::<bool>::or(sys, &prev, &taken),
+                None => taken,
+            });
+        }
+        (next.into_iter())
+            .map(|bit| bit.unwrap_or_else(|| SystemRepr::<bool>::constant(sys, false)))
+            .collect()
+    }
+
+    /// Consumes each of the given bytes in turn, starting from the initial state.
+    pub fn run_abstract<S: SystemFsm + ?Sized>(
+        &self,
+        sys: &mut S,
+        input: &[Abstract<S, u8>],
+    ) -> Vec<Abstract<S, bool>> {
+        let mut state = self.initial(sys);
+        for byte in input {
+            state = self.step(sys, &state, byte);
+        }
+        state
+    }
+
+    /// Determines whether the given state, as produced by [`Fsm::step`], is not the dead state.
+    pub fn is_live<S: SystemFsm + ?Sized>(
+        &self,
+        sys: &mut S,
+        state: &[Abstract<S, bool>],
+    ) -> Abstract<S, bool> {
+        any(sys, state.iter())
+    }
+
+    /// Asserts that the given state, as produced by [`Fsm::step`], is not the dead state, so that
+    /// every byte consumed so far was accepted by some transition.
+    pub fn assert_live<S: SystemFsm + SystemAssert + ?Sized>(
+        &self,
+        sys: &mut S,
+        state: &[Abstract<S, bool>],
+    ) {
+        let live = self.is_live(sys, state);
+        sys.assert(&live)
+    }
+
+    /// Determines whether the given state is accepting.
+    pub fn accepts<S: SystemFsm + ?Sized>(
+        &self,
+        sys: &mut S,
+        state: &[Abstract<S, bool>],
+    ) -> Abstract<S, bool> {
+        let accepting = (state.iter().zip(self.accepting.iter()))
+            .filter(|(_, accepting)| **accepting)
+            .map(|(bit, _)| bit);
+        any(sys, accepting)
+    }
+}
+
+/// Determines whether a byte is in the given inclusive range.
+fn in_range<S: SystemFsm + ?Sized>(
+    sys: &mut S,
+    byte: &Abstract<S, u8>,
+    (lo, hi): (u8, u8),
+) -> Abstract<S, bool> {
+    let above = (lo > 0).then(|| {
+        let lo = SystemRepr::<u8>::constant(sys, lo);
+        sys.le(&lo, byte)
+    });
+    let below = (hi < u8::MAX).then(|| {
+        let hi = SystemRepr::<u8>::constant(sys, hi);
+        sys.le(byte, &hi)
+    });
+    match (above, below) {
+        (Some(above), Some(below)) => SystemBitAnd::<bool>::and(sys, &above, &below),
+        (Some(bit), None) | (None, Some(bit)) => bit,
+        (None, None) => SystemRepr::<bool>::constant(sys, true),
+    }
+}
+
+/// Determines whether any of the given bits are set.
+fn any<'a, S: SystemFsm + ?Sized>(
+    sys: &mut S,
+    bits: impl Iterator<Item = &'a Abstract<S, bool>>,
+) -> Abstract<S, bool>
+where
+    Abstract<S, bool>: 'a,
+{
+    let mut res: Option<Abstract<S, bool>> = None;
+    for bit in bits {
+        res = Some(match res {
+            Some(res) => SystemBitOr::<bool>::or(sys, &res, bit),
+            None => bit.clone(),
+        });
+    }
+    res.unwrap_or_else(|| SystemRepr::<bool>::constant(sys, false))
+}
+
+#[test]
+fn test_fsm() {
+    // Decimal numbers without leading zeros, optionally negative
+    let mut fsm = Fsm::new();
+    let start = fsm.state("start");
+    let sign = fsm.state("sign");
+    let zero = fsm.state("zero");
+    let digits = fsm.state("digits");
+    fsm.transition(start, sign, b'-');
+    for from in [start, sign] {
+        fsm.transition(from, zero, b'0');
+        fsm.transition(from, digits, b'1'..=b'9');
+    }
+    fsm.transition(digits, digits, b'0'..=b'9');
+    fsm.accept(zero);
+    fsm.accept(digits);
+    assert_eq!(fsm.run(b"-120"), Some(digits));
+    assert_eq!(fsm.run(b"0"), Some(zero));
+    assert_eq!(fsm.run(b"-"), Some(sign));
+    assert_eq!(fsm.run(b"01"), None);
+
+    let cases: [&[u8]; 7] = [b"-120", b"0", b"-", b"01", b"12a", b"", b"907"];
+    for input in cases {
+        let expected = fsm.run(input);
+        let mut sys = BinaryEmulate::new(Eval);
+        let bytes: Vec<_> = (input.iter())
+            .map(|b| SystemRepr::<u8>::constant(&mut sys, *b))
+            .collect();
+        let state = fsm.run_abstract(&mut sys, &bytes);
+        let expected_state: Vec<_> = (0..fsm.num_states())
+            .map(|i| expected.map(|s| s.index()) == Some(i))
+            .collect();
+        assert_eq!(state, expected_state);
+        assert_eq!(fsm.is_live(&mut sys, &state), expected.is_some());
+        let accepts = expected.is_some_and(|s| fsm.is_accepting(s));
+        assert_eq!(fsm.accepts(&mut sys, &state), accepts);
+        if expected.is_some() {
+            fsm.assert_live(&mut Eval, &state);
+        }
+    }
+
+    // Guards can be combined
+    let guard = Guard::from(b'a'..=b'z').or(b'_');
+    assert!(guard.contains(b'_') && guard.contains(b'q') && !guard.contains(b'A'));
+    let complement = guard.complement();
+    assert!((0..=u8::MAX).all(|b| guard.contains(b) != complement.contains(b)));
+    assert_eq!(Guard::any().complement(), Guard::default());
+}
+
+#[test]
+#[should_panic(expected = "overlap")]
+fn test_fsm_overlap() {
+    let mut fsm = Fsm::new();
+    let a = fsm.state("a");
+    let b = fsm.state("b");
+    fsm.transition(a, a, b'0'..=b'9');
+    fsm.transition(a, b, b'5');
+}
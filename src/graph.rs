@@ -0,0 +1,1192 @@
+use crate::*;
+use smallvec::{smallvec, SmallVec};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::io::{self, Read};
+use std::rc::Rc;
+
+/// Identifies a node within a [`Graph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Node(u32);
+
+impl Node {
+    /// The index of this node within its graph.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// The node with the given index.
+    pub(crate) fn from_index(index: usize) -> Self {
+        Self(u32::try_from(index).expect("too many nodes"))
+    }
+}
+
+/// Identifies a module which has been defined in a [`Graph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModuleId(u32);
+
+/// An operation which defines the value of a [`Node`] in a [`Graph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    /// The input with the given index.
+    Input(u32),
+    Const(bool),
+    And(Node, Node),
+    Or(Node, Node),
+    Xor(Node, Node),
+    Not(Node),
+
+    /// An invocation of a module, identified by its index in the call sites of the graph. A call
+    /// may invoke a module repeatedly (see [`Graph::repeat`]). This node has no value itself.
+    /// The outputs of the call are given by [`Op::Output`] nodes.
+    Call(u32),
+
+    /// The output with the given index of an [`Op::Call`] node.
+    Output(Node, u32),
+}
+
+/// A [`SubcircuitTemplate`] which has been defined in a [`Graph`].
+#[derive(Debug, Clone)]
+struct Module {
+    name: String,
+    template: Rc<SubcircuitTemplate>,
+}
+
+/// An invocation of a [`Module`].
+#[derive(Debug, Clone)]
+struct CallSite {
+    module: ModuleId,
+
+    /// The number of times the module is applied. For more than one iteration, the outputs of
+    /// each iteration become the leading inputs of the next.
+    count: u32,
+    inputs: Vec<Node>,
+}
+
+/// Describes an [`Op::Call`] node, for backends which handle calls natively rather than
+/// flattening them.
+#[derive(Debug, Clone, Copy)]
+pub struct CallInfo<'a> {
+    pub module: ModuleId,
+    pub name: &'a str,
+    pub template: &'a SubcircuitTemplate,
+
+    /// The number of times the module is applied. See [`Graph::repeat`].
+    pub count: u32,
+    pub inputs: &'a [Node],
+}
+
+/// Statistics about the use of a module within a [`Graph`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleStats {
+    pub name: String,
+
+    /// The number of times the module is instantiated, counting every iteration of a loop.
+    pub instances: usize,
+
+    /// The number of nodes in the definition of the module, not counting its submodules.
+    pub nodes: usize,
+
+    /// The number of gates in each instance of the module, once fully flattened.
+    pub gates: usize,
+}
+
+/// A [`BinarySystem`] which records boolean operations as a directed acyclic graph, so that they
+/// can later be replayed in another system. Every node is defined after its operands, so the
+/// nodes are always in topological order.
+///
+/// Graphs may be hierarchical: a module defined in a graph can be called any number of times,
+/// while its body is only stored once.
+#[derive(Debug, Clone, Default)]
+pub struct Graph {
+    nodes: Vec<Op>,
+    num_inputs: u32,
+    modules: Vec<Module>,
+    calls: Vec<CallSite>,
+
+    /// The existing node for each operation, used to avoid creating redundant nodes.
+    cache: HashMap<Op, Node>,
+
+    /// The existing [`Op::Call`] node for each module and list of inputs.
+    call_cache: HashMap<(ModuleId, u32, Vec<Node>), Node>,
+
+    /// The values which have been probed using [`SystemDebug::probe`], with their labels.
+    probes: Vec<(String, Vec<Node>)>,
+}
+
+impl Graph {
+    /// Constructs a new, empty [`Graph`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Introduces a new input into this graph.
+    pub fn input(&mut self) -> Node {
+        let index = self.num_inputs;
+        self.num_inputs += 1;
+        self.node(Op::Input(index))
+    }
+
+    /// The number of inputs introduced into this graph.
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs as usize
+    }
+
+    /// The operations defining the nodes of this graph, in order.
+    pub fn nodes(&self) -> &[Op] {
+        &self.nodes
+    }
+
+    /// Gets the operation defining the given node.
+    pub fn op(&self, node: Node) -> Op {
+        self.nodes[node.index()]
+    }
+
+    /// The values which have been probed in this graph, in order, with the nodes holding their
+    /// bits. Probes are not carried over to copies of the graph, such as [`Graph::flatten`].
+    pub fn probes(&self) -> &[(String, Vec<Node>)] {
+        &self.probes
+    }
+
+    /// Defines a module in this graph, which can then be invoked using [`Graph::call`].
+    pub fn define(
+        &mut self,
+        name: impl Into<String>,
+        template: Rc<SubcircuitTemplate>,
+    ) -> ModuleId {
+        let id = ModuleId(self.modules.len() as u32);
+        self.modules.push(Module {
+            name: name.into(),
+            template,
+        });
+        id
+    }
+
+    /// Calls a module with the given inputs, returning its outputs.
+    pub fn call(&mut self, module: ModuleId, inputs: &[Node]) -> Vec<Node> {
+        let call = self.call_node(module, 1, inputs);
+        self.call_outputs(call)
+    }
+
+    /// Applies a module `count` times, as a bounded loop. The module must produce as many
+    /// outputs as there are nodes in `state`, and accept those followed by `params` as inputs.
+    /// The outputs of each iteration are used as the state for the next, while `params` are
+    /// given to every iteration unchanged. Returns the final state.
+    ///
+    /// The body of the loop is stored once, regardless of `count`. Backends may either unroll it
+    /// or map it to a native form of repetition.
+    pub fn repeat(
+        &mut self,
+        count: u32,
+        module: ModuleId,
+        state: &[Node],
+        params: &[Node],
+    ) -> Vec<Node> {
+        assert_eq!(state.len(), self.template(module).num_outputs());
+        if count == 0 {
+            return state.to_vec();
+        }
+        let inputs: Vec<_> = state.iter().chain(params).copied().collect();
+        let call = self.call_node(module, count, &inputs);
+        self.call_outputs(call)
+    }
+
+    /// Gets the output nodes for the given [`Op::Call`] node.
+    fn call_outputs(&mut self, call: Node) -> Vec<Node> {
+        let Op::Call(index) = self.op(call) else {
+            unreachable!()
+        };
+        let module = self.calls[index as usize].module;
+        let num_outputs = self.template(module).num_outputs() as u32;
+        (0..num_outputs)
+            .map(|i| self.node(Op::Output(call, i)))
+            .collect()
+    }
+
+    /// Describes the given [`Op::Call`] node, or returns [`None`] if it is not a call.
+    pub fn call_info(&self, node: Node) -> Option<CallInfo<'_>> {
+        let Op::Call(index) = self.op(node) else {
+            return None;
+        };
+        let call = &self.calls[index as usize];
+        let module = &self.modules[call.module.0 as usize];
+        Some(CallInfo {
+            module: call.module,
+            name: &module.name,
+            template: &module.template,
+            count: call.count,
+            inputs: &call.inputs,
+        })
+    }
+
+    /// Gets the template for the given module.
+    fn template(&self, module: ModuleId) -> &SubcircuitTemplate {
+        &self.modules[module.0 as usize].template
+    }
+
+    /// Gets the [`Op::Call`] node for a call to the given module.
+    fn call_node(&mut self, module: ModuleId, count: u32, inputs: &[Node]) -> Node {
+        assert_eq!(inputs.len(), self.template(module).num_inputs());
+        let key = (module, count, inputs.to_vec());
+        if let Some(node) = self.call_cache.get(&key) {
+            return *node;
+        }
+        let index = u32::try_from(self.calls.len()).expect("too many calls");
+        self.calls.push(CallSite {
+            module,
+            count,
+            inputs: key.2.clone(),
+        });
+        let node = self.push(Op::Call(index));
+        self.call_cache.insert(key, node);
+        node
+    }
+
+    /// Gets the node for the given operation, simplifying it if possible.
+    fn node(&mut self, op: Op) -> Node {
+        let op = match op {
+            Op::And(a, b) | Op::Or(a, b) | Op::Xor(a, b) => {
+                // Put operands in a canonical order so that more nodes can be shared
+                let (a, b) = (a.min(b), a.max(b));
+                match (op, self.op(a), self.op(b)) {
+                    (Op::And(..), Op::Const(false), _) | (Op::Or(..), Op::Const(true), _) => {
+                        return a
+                    }
+                    (Op::And(..), _, Op::Const(false)) | (Op::Or(..), _, Op::Const(true)) => {
+                        return b
+                    }
+                    (Op::And(..) | Op::Or(..), Op::Const(_), _) => return b,
+                    (Op::And(..) | Op::Or(..), _, Op::Const(_)) => return a,
+                    (Op::And(..) | Op::Or(..), _, _) if a == b => return a,
+                    (Op::Xor(..), Op::Const(false), _) => return b,
+                    (Op::Xor(..), _, Op::Const(false)) => return a,
+                    (Op::Xor(..), Op::Const(true), _) => return self.node(Op::Not(b)),
+                    (Op::Xor(..), _, Op::Const(true)) => return self.node(Op::Not(a)),
+                    (Op::Xor(..), _, _) if a == b => Op::Const(false),
+                    (Op::And(..), _, _) => Op::And(a, b),
+                    (Op::Or(..), _, _) => Op::Or(a, b),
+                    _ => Op::Xor(a, b),
+                }
+            }
+            Op::Not(a) => match self.op(a) {
+                Op::Const(value) => Op::Const(!value),
+                Op::Not(b) => return b,
+                _ => op,
+            },
+            _ => op,
+        };
+        if let Some(node) = self.cache.get(&op) {
+            return *node;
+        }
+        let node = self.push(op);
+        self.cache.insert(op, node);
+        node
+    }
+
+    /// Adds a node to this graph, without simplifying or sharing it.
+    fn push(&mut self, op: Op) -> Node {
+        let node = Node(u32::try_from(self.nodes.len()).expect("too many nodes"));
+        self.nodes.push(op);
+        node
+    }
+
+    /// Gets the operand nodes of the given operation.
+    pub(crate) fn operands(&self, op: &Op) -> SmallVec<[Node; 2]> {
+        match *op {
+            Op::Input(_) | Op::Const(_) => SmallVec::new(),
+            Op::And(a, b) | Op::Or(a, b) | Op::Xor(a, b) => smallvec![a, b],
+            Op::Not(a) | Op::Output(a, _) => smallvec![a],
+            Op::Call(index) => SmallVec::from_slice(&self.calls[index as usize].inputs),
+        }
+    }
+
+    /// Determines which nodes are needed to compute the given nodes.
+    pub(crate) fn needed(&self, outputs: &[Node]) -> Vec<bool> {
+        let mut needed = vec![false; self.nodes.len()];
+        for output in outputs {
+            needed[output.index()] = true;
+        }
+        for index in (0..self.nodes.len()).rev() {
+            if needed[index] {
+                for operand in self.operands(&self.nodes[index]) {
+                    needed[operand.index()] = true;
+                }
+            }
+        }
+        needed
+    }
+
+    /// Replays the operations of this graph in the given system, returning the abstract values
+    /// of the given nodes. Only the nodes needed to compute them are replayed. Calls are
+    /// replayed by instantiating the called module.
+    pub fn replay<S: BinarySystem + ?Sized>(
+        &self,
+        sys: &mut S,
+        inputs: &[Abstract<S, bool>],
+        outputs: &[Node],
+    ) -> Vec<Abstract<S, bool>> {
+        assert_eq!(inputs.len(), self.num_inputs());
+        let needed = self.needed(outputs);
+        let mut values: Vec<Option<Abstract<S, bool>>> = vec![None; self.nodes.len()];
+        let mut call_outputs = HashMap::new();
+        for (index, op) in self.nodes.iter().enumerate() {
+            if !needed[index] {
+                continue;
+            }
+            let value = |node: &Node| values[node.index()].as_ref().unwrap();
+            let res = match op {
+                Op::Input(i) => inputs[*i as usize].clone(),
+                Op::Const(value) => sys.constant(*value),
+                Op::And(a, b) => sys.and(value(a), value(b)),
+                Op::Or(a, b) => sys.or(value(a), value(b)),
+                Op::Xor(a, b) => sys.xor(value(a), value(b)),
+                Op::Not(a) => sys.not(value(a)),
+                Op::Call(i) => {
+                    let call = &self.calls[*i as usize];
+                    let mut inputs: Vec<_> = call.inputs.iter().map(|n| value(n).clone()).collect();
+                    let template = self.template(call.module);
+                    let mut outputs = template.instantiate(sys, &inputs);
+                    for _ in 1..call.count {
+                        inputs.splice(..outputs.len(), outputs);
+                        outputs = template.instantiate(sys, &inputs);
+                    }
+                    call_outputs.insert(index, outputs);
+                    continue;
+                }
+                Op::Output(call, i) => call_outputs[&call.index()][*i as usize].clone(),
+            };
+            values[index] = Some(res);
+        }
+        outputs
+            .iter()
+            .map(|output| values[output.index()].clone().unwrap())
+            .collect()
+    }
+
+    /// Constructs a copy of this graph with only the nodes needed to compute the given nodes,
+    /// returning the copy and the corresponding nodes within it. Unlike [`Graph::flatten`],
+    /// calls are preserved. Inputs keep their indices.
+    fn compact(&self, outputs: &[Node]) -> (Graph, Vec<Node>) {
+        let needed = self.needed(outputs);
+        let mut res = Graph {
+            modules: self.modules.clone(),
+            ..Graph::new()
+        };
+        let inputs: Vec<_> = (0..self.num_inputs).map(|_| res.input()).collect();
+        let mut map = vec![None; self.nodes.len()];
+        for (index, op) in self.nodes.iter().enumerate() {
+            if !needed[index] {
+                continue;
+            }
+            let m = |node: Node| map[node.index()].unwrap();
+            map[index] = Some(match *op {
+                Op::Input(i) => inputs[i as usize],
+                Op::Const(value) => res.node(Op::Const(value)),
+                Op::And(a, b) => res.node(Op::And(m(a), m(b))),
+                Op::Or(a, b) => res.node(Op::Or(m(a), m(b))),
+                Op::Xor(a, b) => res.node(Op::Xor(m(a), m(b))),
+                Op::Not(a) => res.node(Op::Not(m(a))),
+                Op::Call(i) => {
+                    let call = &self.calls[i as usize];
+                    let inputs: Vec<_> = call.inputs.iter().map(|node| m(*node)).collect();
+                    res.call_node(call.module, call.count, &inputs)
+                }
+                Op::Output(call, i) => res.node(Op::Output(m(call), i)),
+            });
+        }
+        let outputs = outputs
+            .iter()
+            .map(|node| map[node.index()].unwrap())
+            .collect();
+        (res, outputs)
+    }
+
+    /// Constructs a copy of this graph where every call is replaced by the body of the module it
+    /// invokes, returning the copy and the nodes within it corresponding to `outputs`.
+    pub fn flatten(&self, outputs: &[Node]) -> (Graph, Vec<Node>) {
+        let mut res = Graph::new();
+        let inputs: Vec<_> = (0..self.num_inputs).map(|_| res.input()).collect();
+        let outputs = self.replay(&mut res, &inputs, outputs);
+        (res, outputs)
+    }
+
+    /// Constructs a copy of this graph where each input in `bindings` is fixed to the given
+    /// value, and constants are folded through the nodes needed to compute `outputs`. Returns
+    /// the copy and the nodes within it corresponding to `outputs`. The remaining inputs keep
+    /// their relative order.
+    ///
+    /// Calls which receive a constant input are inlined, so that the constant can be folded
+    /// into the body of the module. Other calls are preserved.
+    pub fn specialize(&self, bindings: &[(usize, bool)], outputs: &[Node]) -> (Graph, Vec<Node>) {
+        let mut values = vec![None; self.num_inputs()];
+        for (index, value) in bindings {
+            values[*index] = Some(*value);
+        }
+        let needed = self.needed(outputs);
+        let mut res = Graph {
+            modules: self.modules.clone(),
+            ..Graph::new()
+        };
+        let inputs: Vec<_> = (values.iter())
+            .map(|value| match value {
+                Some(value) => res.node(Op::Const(*value)),
+                None => res.input(),
+            })
+            .collect();
+        let mut map = vec![None; self.nodes.len()];
+        let mut call_outputs = HashMap::new();
+        for (index, op) in self.nodes.iter().enumerate() {
+            if !needed[index] {
+                continue;
+            }
+            let m = |node: Node| map[node.index()].unwrap();
+            map[index] = Some(match *op {
+                Op::Input(i) => inputs[i as usize],
+                Op::Const(value) => res.node(Op::Const(value)),
+                Op::And(a, b) => res.node(Op::And(m(a), m(b))),
+                Op::Or(a, b) => res.node(Op::Or(m(a), m(b))),
+                Op::Xor(a, b) => res.node(Op::Xor(m(a), m(b))),
+                Op::Not(a) => res.node(Op::Not(m(a))),
+                Op::Call(i) => {
+                    let call = &self.calls[i as usize];
+                    let mut inputs: Vec<_> = call.inputs.iter().map(|node| m(*node)).collect();
+                    let folds = inputs.iter().any(|n| matches!(res.op(*n), Op::Const(_)));
+                    if !folds {
+                        res.call_node(call.module, call.count, &inputs)
+                    } else {
+                        let template = self.template(call.module);
+                        let mut outputs = template.instantiate(&mut res, &inputs);
+                        for _ in 1..call.count {
+                            inputs.splice(..outputs.len(), outputs);
+                            outputs = template.instantiate(&mut res, &inputs);
+                        }
+                        call_outputs.insert(index, outputs);
+                        continue;
+                    }
+                }
+                Op::Output(call, i) => match call_outputs.get(&call.index()) {
+                    Some(outputs) => outputs[i as usize],
+                    None => res.node(Op::Output(m(call), i)),
+                },
+            });
+        }
+        let outputs: Vec<_> = outputs
+            .iter()
+            .map(|node| map[node.index()].unwrap())
+            .collect();
+
+        // Folding may leave behind nodes which are no longer needed
+        res.compact(&outputs)
+    }
+
+    /// The number of gates in this graph, once fully flattened.
+    pub fn num_gates(&self) -> usize {
+        self.nodes
+            .iter()
+            .map(|op| match op {
+                Op::Input(_) | Op::Const(_) | Op::Output(..) => 0,
+                Op::And(..) | Op::Or(..) | Op::Xor(..) | Op::Not(_) => 1,
+                Op::Call(i) => {
+                    let call = &self.calls[*i as usize];
+                    call.count as usize * self.template(call.module).num_gates()
+                }
+            })
+            .sum()
+    }
+
+    /// Gets statistics for each module defined in this graph, in the order they were defined.
+    pub fn module_stats(&self) -> Vec<ModuleStats> {
+        let mut res: Vec<_> = self
+            .modules
+            .iter()
+            .map(|module| ModuleStats {
+                name: module.name.clone(),
+                instances: 0,
+                nodes: module.template.graph.nodes.len(),
+                gates: module.template.num_gates(),
+            })
+            .collect();
+        for call in self.calls.iter() {
+            res[call.module.0 as usize].instances += call.count as usize;
+        }
+        res
+    }
+
+    /// Describes this graph in the Graphviz DOT language, for visualization. Each call is shown
+    /// as a single box, labeled with the name of the module.
+    pub fn to_dot(&self) -> String {
+        let mut res = String::from("digraph {\n");
+        for (index, op) in self.nodes.iter().enumerate() {
+            let (label, shape) = match op {
+                Op::Input(i) => (format!("in{}", i), "invhouse"),
+                Op::Const(value) => (value.to_string(), "plaintext"),
+                Op::And(..) => ("and".to_owned(), "ellipse"),
+                Op::Or(..) => ("or".to_owned(), "ellipse"),
+                Op::Xor(..) => ("xor".to_owned(), "ellipse"),
+                Op::Not(_) => ("not".to_owned(), "ellipse"),
+                Op::Call(i) => {
+                    let call = &self.calls[*i as usize];
+                    let name = &self.modules[call.module.0 as usize].name;
+                    match call.count {
+                        1 => (name.clone(), "box"),
+                        count => (format!("{} x{}", name, count), "box3d"),
+                    }
+                }
+                Op::Output(_, i) => (format!("out{}", i), "point"),
+            };
+            writeln!(res, "  n{} [label={:?}, shape={}];", index, label, shape).unwrap();
+            for operand in self.operands(op) {
+                writeln!(res, "  n{} -> n{};", operand.index(), index).unwrap();
+            }
+        }
+        res.push_str("}\n");
+        res
+    }
+
+    /// Writes a checkpoint of this graph to a stream, so that building can be resumed later
+    /// using [`Graph::resume`]. `handles` are the nodes that building will continue from, which
+    /// are restored along with the graph.
+    pub fn write_checkpoint(&self, mut writer: impl io::Write, handles: &[Node]) -> io::Result<()> {
+        writer.write_all(CHECKPOINT_MAGIC)?;
+        writer.write_all(&[CHECKPOINT_VERSION])?;
+        self.write_to(&mut writer)?;
+        write_nodes(&mut writer, handles)?;
+        writer.flush()
+    }
+
+    /// Resumes a graph from a checkpoint written by [`Graph::write_checkpoint`]. Nodes keep the
+    /// same numbering, and operations continue to be shared with the nodes created before the
+    /// checkpoint. Returns the graph along with the restored handles.
+    ///
+    /// Modules which were shared between templates before the checkpoint are restored as
+    /// separate copies. Since checkpoints may come from untrusted sources, this fails with
+    /// [`io::ErrorKind::InvalidData`] if modules are nested more than [`MAX_CHECKPOINT_DEPTH`]
+    /// deep, or if the graph would have more than [`MAX_CHECKPOINT_GATES`] gates once fully
+    /// flattened.
+    pub fn resume(mut reader: impl io::Read) -> io::Result<(Graph, Vec<Node>)> {
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != CHECKPOINT_MAGIC || header[4] != CHECKPOINT_VERSION {
+            return Err(invalid_data("unrecognized checkpoint header"));
+        }
+        let (graph, _) = Graph::read_from(&mut reader, 0)?;
+        let handles = read_nodes(&mut reader, graph.nodes.len())?;
+        Ok((graph, handles))
+    }
+
+    /// Serializes this graph, including the bodies of its modules.
+    fn write_to(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        write_varint(writer, u64::from(self.num_inputs))?;
+        write_varint(writer, self.modules.len() as u64)?;
+        for module in self.modules.iter() {
+            write_varint(writer, module.name.len() as u64)?;
+            writer.write_all(module.name.as_bytes())?;
+            module.template.graph.write_to(writer)?;
+            write_nodes(writer, &module.template.outputs)?;
+            write_varint(writer, module.template.num_gates as u64)?;
+        }
+        write_varint(writer, self.calls.len() as u64)?;
+        for call in self.calls.iter() {
+            write_varint(writer, u64::from(call.module.0))?;
+            write_varint(writer, u64::from(call.count))?;
+            write_nodes(writer, &call.inputs)?;
+        }
+        write_varint(writer, self.nodes.len() as u64)?;
+        for op in self.nodes.iter() {
+            let (tag, a, b) = match *op {
+                Op::Input(i) => (0, i, 0),
+                Op::Const(value) => (1, value as u32, 0),
+                Op::And(a, b) => (2, a.0, b.0),
+                Op::Or(a, b) => (3, a.0, b.0),
+                Op::Xor(a, b) => (4, a.0, b.0),
+                Op::Not(a) => (5, a.0, 0),
+                Op::Call(i) => (6, i, 0),
+                Op::Output(call, i) => (7, call.0, i),
+            };
+            writer.write_all(&[tag])?;
+            write_varint(writer, u64::from(a))?;
+            if matches!(tag, 2 | 3 | 4 | 7) {
+                write_varint(writer, u64::from(b))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deserializes a graph written by [`Graph::write_to`] at the given nesting `depth`,
+    /// validating its structure and rebuilding its caches. Returns the graph along with its
+    /// cost, which bounds the work needed to flatten it.
+    fn read_from(reader: &mut impl io::Read, depth: usize) -> io::Result<(Graph, usize)> {
+        if depth > MAX_CHECKPOINT_DEPTH {
+            return Err(invalid_data("modules are nested too deeply"));
+        }
+        let mut res = Graph::new();
+        let mut cost: usize = 0;
+        let mut module_costs = Vec::new();
+        res.num_inputs = read_u32(reader)?;
+        for _ in 0..read_varint(reader)? {
+            let len = read_varint(reader)?;
+            let mut name = Vec::new();
+            reader.by_ref().take(len).read_to_end(&mut name)?;
+            if name.len() as u64 != len {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let name = String::from_utf8(name).map_err(|_| invalid_data("name is not UTF-8"))?;
+            let (graph, module_cost) = Graph::read_from(reader, depth + 1)?;
+            module_costs.push(module_cost);
+            let outputs = read_nodes(reader, graph.nodes.len())?;
+            let num_gates = read_varint(reader)? as usize;
+            let template = SubcircuitTemplate {
+                graph,
+                outputs,
+                num_gates,
+            };
+            res.define(name, Rc::new(template));
+        }
+        for _ in 0..read_varint(reader)? {
+            let module = read_u32(reader)?;
+            if module as usize >= res.modules.len() {
+                return Err(invalid_data("invalid module index"));
+            }
+            res.calls.push(CallSite {
+                module: ModuleId(module),
+                count: read_u32(reader)?,
+                inputs: read_nodes(reader, usize::MAX)?,
+            });
+        }
+        for index in 0..read_varint(reader)? {
+            let tag = read_byte(reader)?;
+            let a = read_u32(reader)?;
+            let b = match tag {
+                2 | 3 | 4 | 7 => read_u32(reader)?,
+                _ => 0,
+            };
+            let node = |n: u32| {
+                (u64::from(n) < index)
+                    .then_some(Node(n))
+                    .ok_or_else(|| invalid_data("invalid node index"))
+            };
+            let op = match tag {
+                0 if a < res.num_inputs => Op::Input(a),
+                1 if a < 2 => Op::Const(a == 1),
+                2 => Op::And(node(a)?, node(b)?),
+                3 => Op::Or(node(a)?, node(b)?),
+                4 => Op::Xor(node(a)?, node(b)?),
+                5 => Op::Not(node(a)?),
+                6 if (a as usize) < res.calls.len() => {
+                    let call = &res.calls[a as usize];
+                    let valid = call.inputs.iter().all(|input| node(input.0).is_ok())
+                        && call.inputs.len() == res.template(call.module).num_inputs();
+                    if !valid {
+                        return Err(invalid_data("invalid call"));
+                    }
+                    Op::Call(a)
+                }
+                7 => match res.call_info(node(a)?) {
+                    Some(info) if (b as usize) < info.template.num_outputs() => {
+                        Op::Output(Node(a), b)
+                    }
+                    _ => return Err(invalid_data("invalid call output")),
+                },
+                _ => return Err(invalid_data("invalid operation")),
+            };
+            // Every instance of a module costs at least one gate, since unrolling it takes
+            // work even if it is empty
+            let op_cost = match op {
+                Op::And(..) | Op::Or(..) | Op::Xor(..) | Op::Not(_) => Some(1),
+                Op::Call(i) => {
+                    let call = &res.calls[i as usize];
+                    let module_cost = module_costs[call.module.0 as usize];
+                    (call.count as usize).checked_mul(module_cost.max(1))
+                }
+                _ => Some(0),
+            };
+            cost = (op_cost.and_then(|op_cost| cost.checked_add(op_cost)))
+                .filter(|cost| *cost <= MAX_CHECKPOINT_GATES)
+                .ok_or_else(|| invalid_data("too many gates"))?;
+            let node = res.push(op);
+            match op {
+                Op::Call(i) => {
+                    let call = &res.calls[i as usize];
+                    let key = (call.module, call.count, call.inputs.clone());
+                    res.call_cache.entry(key).or_insert(node);
+                }
+                _ => {
+                    res.cache.entry(op).or_insert(node);
+                }
+            }
+        }
+        Ok((res, cost))
+    }
+}
+
+/// The maximum depth of nested modules accepted by [`Graph::resume`].
+pub const MAX_CHECKPOINT_DEPTH: usize = 256;
+
+/// The maximum number of gates, once fully flattened, of a graph accepted by [`Graph::resume`].
+pub const MAX_CHECKPOINT_GATES: usize = 1 << 28;
+
+/// The bytes at the start of every graph checkpoint.
+const CHECKPOINT_MAGIC: &[u8; 4] = b"GCKP";
+
+/// The version of the graph checkpoint format.
+const CHECKPOINT_VERSION: u8 = 1;
+
+/// Writes an unsigned LEB128 integer.
+fn write_varint(writer: &mut impl io::Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+/// Writes a list of nodes, preceded by its length.
+fn write_nodes(writer: &mut impl io::Write, nodes: &[Node]) -> io::Result<()> {
+    write_varint(writer, nodes.len() as u64)?;
+    for node in nodes {
+        write_varint(writer, u64::from(node.0))?;
+    }
+    Ok(())
+}
+
+fn read_byte(reader: &mut impl io::Read) -> io::Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Reads an unsigned LEB128 integer.
+fn read_varint(reader: &mut impl io::Read) -> io::Result<u64> {
+    let mut res = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_byte(reader)?;
+        res |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(res);
+        }
+    }
+    Err(invalid_data("varint is too long"))
+}
+
+/// Reads an unsigned LEB128 integer which must fit in a [`u32`].
+fn read_u32(reader: &mut impl io::Read) -> io::Result<u32> {
+    u32::try_from(read_varint(reader)?).map_err(|_| invalid_data("integer is too large"))
+}
+
+/// Reads a list of nodes written by [`write_nodes`], each of which must be less than `bound`.
+fn read_nodes(reader: &mut impl io::Read, bound: usize) -> io::Result<Vec<Node>> {
+    let mut res = Vec::new();
+    for _ in 0..read_varint(reader)? {
+        let node = read_u32(reader)?;
+        if node as usize >= bound {
+            return Err(invalid_data("invalid node index"));
+        }
+        res.push(Node(node));
+    }
+    Ok(res)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl SystemRepr<bool> for Graph {
+    type Abstract = Node;
+    type Error = core::convert::Infallible;
+    fn constant(&mut self, value: bool) -> Node {
+        self.node(Op::Const(value))
+    }
+}
+
+impl SystemCapabilities for Graph {}
+
+impl SystemDebug<bool> for Graph {
+    fn probe(&mut self, label: &str, value: &Node) {
+        self.probes.push((label.into(), vec![*value]));
+    }
+}
+
+impl<const N: usize> SystemDebug<[bool; N]> for Graph {
+    fn probe(&mut self, label: &str, value: &[Node; N]) {
+        self.probes.push((label.into(), value.to_vec()));
+    }
+}
+
+impl SystemBitAnd<bool> for Graph {
+    fn and(&mut self, a: &Node, b: &Node) -> Node {
+        self.node(Op::And(*a, *b))
+    }
+}
+
+impl SystemBitOr<bool> for Graph {
+    fn or(&mut self, a: &Node, b: &Node) -> Node {
+        self.node(Op::Or(*a, *b))
+    }
+}
+
+impl SystemBitXor<bool> for Graph {
+    fn xor(&mut self, a: &Node, b: &Node) -> Node {
+        self.node(Op::Xor(*a, *b))
+    }
+}
+
+impl SystemNot<bool> for Graph {
+    fn not(&mut self, value: &Node) -> Node {
+        self.node(Op::Not(*value))
+    }
+}
+
+impl SystemSelect<bool> for Graph {
+    fn select(&mut self, cond: &Node, a: &Node, b: &Node) -> Node {
+        crate::binary::select_bit(self, cond, a, b)
+    }
+}
+
+/// A gadget which has been synthesized once, as a [`Graph`], so that it can be instantiated any
+/// number of times in other systems without running the gadget code again.
+#[derive(Debug, Clone)]
+pub struct SubcircuitTemplate {
+    graph: Graph,
+    outputs: Vec<Node>,
+
+    /// The number of gates in each instance of this template, once fully flattened.
+    num_gates: usize,
+}
+
+impl SubcircuitTemplate {
+    /// Captures a template by synthesizing a gadget with `num_inputs` inputs. `gadget` is given
+    /// the input nodes and returns the output nodes. Nodes which don't contribute to the outputs
+    /// are discarded.
+    pub fn capture(
+        num_inputs: usize,
+        gadget: impl FnOnce(&mut Graph, &[Node]) -> Vec<Node>,
+    ) -> Self {
+        let mut source = Graph::new();
+        let inputs: Vec<_> = (0..num_inputs).map(|_| source.input()).collect();
+        let outputs = gadget(&mut source, &inputs);
+        let (graph, outputs) = source.compact(&outputs);
+        let num_gates = graph.num_gates();
+        Self {
+            graph,
+            outputs,
+            num_gates,
+        }
+    }
+
+    /// The number of inputs this template accepts.
+    pub fn num_inputs(&self) -> usize {
+        self.graph.num_inputs()
+    }
+
+    /// The number of outputs this template produces.
+    pub fn num_outputs(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// The number of gates in each instance of this template, once fully flattened.
+    pub fn num_gates(&self) -> usize {
+        self.num_gates
+    }
+
+    /// Constructs an equivalent template where every call has been flattened.
+    pub fn flatten(&self) -> Self {
+        let (graph, outputs) = self.graph.flatten(&self.outputs);
+        Self {
+            graph,
+            outputs,
+            num_gates: self.num_gates,
+        }
+    }
+
+    /// Constructs a smaller template by fixing each input in `bindings` to the given value. The
+    /// remaining inputs keep their relative order. See [`Graph::specialize`].
+    pub fn specialize(&self, bindings: &[(usize, bool)]) -> Self {
+        let (graph, outputs) = self.graph.specialize(bindings, &self.outputs);
+        let num_gates = graph.num_gates();
+        Self {
+            graph,
+            outputs,
+            num_gates,
+        }
+    }
+
+    /// The graph this template was captured as.
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    /// The nodes of [`SubcircuitTemplate::graph`] which are the outputs of this template.
+    pub fn outputs(&self) -> &[Node] {
+        &self.outputs
+    }
+
+    /// Instantiates this template in the given system, with the given inputs, returning its
+    /// outputs.
+    pub fn instantiate<S: BinarySystem + ?Sized>(
+        &self,
+        sys: &mut S,
+        inputs: &[Abstract<S, bool>],
+    ) -> Vec<Abstract<S, bool>> {
+        self.graph.replay(sys, inputs, &self.outputs)
+    }
+
+    /// Writes this template to a stream, in the same format as [`Graph::write_checkpoint`],
+    /// with the outputs as handles.
+    pub fn write(&self, writer: impl io::Write) -> io::Result<()> {
+        self.graph.write_checkpoint(writer, &self.outputs)
+    }
+
+    /// Reads a template from a stream written by [`SubcircuitTemplate::write`], subject to the
+    /// same limits as [`Graph::resume`].
+    pub fn read(reader: impl io::Read) -> io::Result<Self> {
+        let (graph, outputs) = Graph::resume(reader)?;
+        let num_gates = graph.num_gates();
+        Ok(Self {
+            graph,
+            outputs,
+            num_gates,
+        })
+    }
+}
+
+#[test]
+fn test_simplify() {
+    let mut graph = Graph::new();
+    let a = graph.input();
+    let b = graph.input();
+    let t = graph.constant(true);
+    assert_eq!(SystemBitAnd::<bool>::and(&mut graph, &a, &t), a);
+    assert_eq!(
+        SystemBitXor::<bool>::xor(&mut graph, &a, &a),
+        graph.constant(false)
+    );
+    let x = SystemBitXor::<bool>::xor(&mut graph, &a, &b);
+    assert_eq!(SystemBitXor::<bool>::xor(&mut graph, &b, &a), x);
+    let not_x = SystemBitXor::<bool>::xor(&mut graph, &x, &t);
+    assert_eq!(SystemNot::<bool>::not(&mut graph, &not_x), x);
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn test_sha256_template() {
+    use crate::crypto::hash::*;
+    let chunk = [0x80000000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    let template = SubcircuitTemplate::capture(256, |graph, inputs| {
+        let mut sys = BinaryEmulate::new(std::mem::take(graph));
+        let mut hasher: [[Node; 32]; 8] =
+            array_init::array_init(|i| array_init::array_init(|j| inputs[i * 32 + j]));
+        sys.sha256_update(&mut hasher, chunk);
+        *graph = sys.into_source();
+        hasher.iter().flatten().copied().collect()
+    });
+    assert_eq!(template.num_outputs(), 256);
+
+    // Instantiate the template twice, to hash two blocks
+    let mut state: Vec<bool> = Eval
+        .sha256_new()
+        .iter()
+        .flat_map(|h| crate::system::bits_of::<32>(u64::from(*h)))
+        .collect();
+    let mut hasher = Eval.sha256_new();
+    for _ in 0..2 {
+        state = template.instantiate(&mut Eval, &state);
+        Eval.sha256_update(&mut hasher, chunk);
+    }
+    let expected: Vec<bool> = hasher
+        .iter()
+        .flat_map(|h| crate::system::bits_of::<32>(u64::from(*h)))
+        .collect();
+    assert_eq!(state, expected);
+}
+
+#[test]
+fn test_modules() {
+    // A module which adds two bytes
+    let add = SubcircuitTemplate::capture(16, |graph, inputs| {
+        let mut sys = BinaryEmulate::new(std::mem::take(graph));
+        let a: [Node; 8] = array_init::array_init(|i| inputs[i]);
+        let b: [Node; 8] = array_init::array_init(|i| inputs[8 + i]);
+        let r = SystemWrappingAdd::<u8>::wrapping_add(&mut sys, &a, &b);
+        *graph = sys.into_source();
+        r.to_vec()
+    });
+    let add = Rc::new(add);
+
+    // A module which adds three bytes by calling the first module twice
+    let add3 = SubcircuitTemplate::capture(24, |graph, inputs| {
+        let module = graph.define("add", add.clone());
+        let ab = graph.call(module, &inputs[..16]);
+        let inputs: Vec<_> = ab.iter().chain(&inputs[16..]).copied().collect();
+        graph.call(module, &inputs)
+    });
+    assert_eq!(add3.num_gates(), 2 * add.num_gates());
+    let stats = add3.graph().module_stats();
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0].instances, 2);
+    assert_eq!(stats[0].gates, add.num_gates());
+    assert!(add3.graph().to_dot().contains("[label=\"add\", shape=box]"));
+    let flat = add3.flatten();
+    assert!(flat.graph().module_stats().is_empty());
+    assert!(flat.graph().num_gates() <= add3.num_gates());
+
+    for (a, b, c) in [(0u8, 0u8, 0u8), (200, 100, 7), (255, 1, 255)] {
+        let inputs: Vec<bool> = [a, b, c]
+            .iter()
+            .flat_map(|x| crate::system::bits_of::<8>(u64::from(*x)))
+            .collect();
+        let expected = crate::system::bits_of::<8>(u64::from(a.wrapping_add(b).wrapping_add(c)));
+        assert_eq!(add3.instantiate(&mut Eval, &inputs), expected);
+        assert_eq!(flat.instantiate(&mut Eval, &inputs), expected);
+    }
+}
+
+#[test]
+fn test_repeat() {
+    // A round which rotates a 4-bit state and XORs in a parameter bit
+    let round = SubcircuitTemplate::capture(5, |graph, inputs| {
+        let mut state = vec![inputs[3], inputs[0], inputs[1], inputs[2]];
+        state[0] = SystemBitXor::<bool>::xor(graph, &state[0], &inputs[4]);
+        state
+    });
+    let round = Rc::new(round);
+    let template = SubcircuitTemplate::capture(5, |graph, inputs| {
+        let module = graph.define("round", round.clone());
+        graph.repeat(6, module, &inputs[..4], &inputs[4..])
+    });
+    assert_eq!(template.num_gates(), 6);
+    assert_eq!(template.graph().module_stats()[0].instances, 6);
+    let call = template
+        .graph()
+        .nodes()
+        .iter()
+        .position(|op| matches!(op, Op::Call(_)))
+        .unwrap();
+    let info = template.graph().call_info(Node(call as u32)).unwrap();
+    assert_eq!((info.name, info.count, info.inputs.len()), ("round", 6, 5));
+
+    // Compare against a manual unrolling
+    let inputs = [true, false, false, true, true];
+    let mut state = inputs[..4].to_vec();
+    for _ in 0..6 {
+        state.rotate_right(1);
+        state[0] ^= inputs[4];
+    }
+    assert_eq!(template.instantiate(&mut Eval, &inputs), state);
+    let flat = template.flatten();
+    assert_eq!(flat.instantiate(&mut Eval, &inputs), state);
+}
+
+#[test]
+fn test_specialize() {
+    let add = Rc::new(SubcircuitTemplate::capture(16, |graph, inputs| {
+        let mut sys = BinaryEmulate::new(std::mem::take(graph));
+        let a: [Node; 8] = array_init::array_init(|i| inputs[i]);
+        let b: [Node; 8] = array_init::array_init(|i| inputs[8 + i]);
+        let r = SystemWrappingAdd::<u8>::wrapping_add(&mut sys, &a, &b);
+        *graph = sys.into_source();
+        r.to_vec()
+    }));
+
+    // Adds two bytes, then adds a third byte, using a module for each addition
+    let add3 = SubcircuitTemplate::capture(24, |graph, inputs| {
+        let module = graph.define("add", add.clone());
+        let ab = graph.call(module, &inputs[..16]);
+        let inputs: Vec<_> = ab.iter().chain(&inputs[16..]).copied().collect();
+        graph.call(module, &inputs)
+    });
+
+    // Fix the third byte to 1. Only the second call is inlined.
+    let c = 1u8;
+    let bindings: Vec<_> = (0..8).map(|i| (16 + i, (c >> i) & 1 == 1)).collect();
+    let spec = add3.specialize(&bindings);
+    assert_eq!(spec.num_inputs(), 16);
+    assert!(spec.num_gates() < add3.num_gates());
+    let stats = spec.graph().module_stats();
+    assert_eq!(stats[0].instances, 1);
+    for (a, b) in [(0u8, 0u8), (200, 100), (255, 0), (37, 91)] {
+        let inputs: Vec<bool> = [a, b]
+            .iter()
+            .flat_map(|x| crate::system::bits_of::<8>(u64::from(*x)))
+            .collect();
+        let expected = crate::system::bits_of::<8>(u64::from(a.wrapping_add(b).wrapping_add(c)));
+        assert_eq!(spec.instantiate(&mut Eval, &inputs), expected);
+    }
+
+    // Fixing every input leaves a constant circuit
+    let bindings: Vec<_> = (0..24).map(|i| (i, i % 3 == 0)).collect();
+    let spec = add3.specialize(&bindings);
+    assert_eq!(spec.num_inputs(), 0);
+    assert_eq!(spec.num_gates(), 0);
+    let inputs: Vec<_> = (0..24).map(|i| i % 3 == 0).collect();
+    assert_eq!(
+        spec.instantiate(&mut Eval, &[]),
+        add3.instantiate(&mut Eval, &inputs)
+    );
+}
+
+#[test]
+fn test_checkpoint() {
+    let round = Rc::new(SubcircuitTemplate::capture(2, |graph, inputs| {
+        vec![SystemBitXor::<bool>::xor(graph, &inputs[0], &inputs[1])]
+    }));
+    let start = |graph: &mut Graph| {
+        let a = graph.input();
+        let b = graph.input();
+        let module = graph.define("round", round.clone());
+        let x = SystemBitAnd::<bool>::and(graph, &a, &b);
+        let y = graph.repeat(3, module, &[x], &[b])[0];
+        vec![a, b, y]
+    };
+    let finish = |graph: &mut Graph, nodes: &[Node]| {
+        // Redundant operations should still be shared with nodes from before the checkpoint
+        let x = SystemBitAnd::<bool>::and(graph, &nodes[1], &nodes[0]);
+        let z = SystemBitOr::<bool>::or(graph, &x, &nodes[2]);
+        SystemNot::<bool>::not(graph, &z)
+    };
+    let mut direct = Graph::new();
+    let nodes = start(&mut direct);
+    let out = finish(&mut direct, &nodes);
+
+    let mut graph = Graph::new();
+    let nodes = start(&mut graph);
+    let mut data = Vec::new();
+    graph.write_checkpoint(&mut data, &nodes).unwrap();
+    let (mut resumed, handles) = Graph::resume(&data[..]).unwrap();
+    assert_eq!(handles, nodes);
+    assert_eq!(finish(&mut resumed, &handles), out);
+    assert_eq!(resumed.nodes(), direct.nodes());
+    assert_eq!(resumed.module_stats(), direct.module_stats());
+    for inputs in [[false, true], [true, true]] {
+        let expected = direct.replay(&mut Eval, &inputs, &[out]);
+        assert_eq!(resumed.replay(&mut Eval, &inputs, &[out]), expected);
+    }
+
+    // Corrupted checkpoints are rejected
+    assert!(Graph::resume(&data[..data.len() - 1]).is_err());
+    data[0] = b'X';
+    assert!(Graph::resume(&data[..]).is_err());
+}
+
+#[test]
+fn test_probe() {
+    let mut sys = BinaryEmulate::new(Graph::new());
+    let a = sys.source_mut().input();
+    let b: [Node; 32] = array_init::array_init(|_| sys.source_mut().input());
+    let c = SystemWrappingAdd::<u32>::wrapping_add(&mut sys, &b, &b);
+    let num_nodes = sys.source().nodes().len();
+    SystemDebug::<bool>::probe(&mut sys, "a", &a);
+    SystemDebug::<u32>::probe(&mut sys, "c", &c);
+    let graph = sys.into_source();
+    assert_eq!(graph.nodes().len(), num_nodes);
+    assert_eq!(graph.probes()[0], ("a".into(), vec![a]));
+    assert_eq!(graph.probes()[1], ("c".into(), c.to_vec()));
+
+    // Evaluation systems only log probed values
+    SystemDebug::<u32>::probe(&mut Eval, "value", &5);
+    let mut sys = BinaryEmulate::new(Eval);
+    let value = sys.constant(5u32);
+    SystemDebug::<u32>::probe(&mut sys, "value", &value);
+}
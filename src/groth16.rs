@@ -0,0 +1,174 @@
+//! The Groth16 verification equation, generic over the operations of a pairing-friendly curve.
+//!
+//! The curve arithmetic is not provided here: it must come from an external implementation of
+//! [`SystemPairing`], and the only implementation in this crate is a toy pairing used for
+//! testing. In particular, this does not verify BN254 proofs inside a circuit, since there is no
+//! non-native field or elliptic curve arithmetic to implement [`SystemPairing`] with.
+use crate::*;
+
+/// Describes the groups of a pairing-friendly elliptic curve.
+pub trait Pairing {
+    /// The scalar field of the curve.
+    type Scalar;
+
+    /// The first source group.
+    type G1: Clone;
+
+    /// The second source group.
+    type G2: Clone;
+}
+
+/// A system which supports the group operations and pairing of the curve `E`.
+pub trait SystemPairing<E: Pairing>:
+    SystemRepr<E::Scalar> + SystemRepr<E::G1> + SystemRepr<E::G2> + SystemRepr<bool>
+{
+    /// Adds two points in the first group.
+    fn g1_add(
+        &mut self,
+        a: &Abstract<Self, E::G1>,
+        b: &Abstract<Self, E::G1>,
+    ) -> Abstract<Self, E::G1>;
+
+    /// Negates a point in the first group.
+    fn g1_neg(&mut self, a: &Abstract<Self, E::G1>) -> Abstract<Self, E::G1>;
+
+    /// Multiplies a point in the first group by a scalar.
+    fn g1_mul(
+        &mut self,
+        point: &Abstract<Self, E::G1>,
+        scalar: &Abstract<Self, E::Scalar>,
+    ) -> Abstract<Self, E::G1>;
+
+    /// Determines whether the product of the pairings of the given pairs of points is the
+    /// identity in the target group.
+    fn pairing_product_is_one(
+        &mut self,
+        g1: &[Abstract<Self, E::G1>],
+        g2: &[Abstract<Self, E::G2>],
+    ) -> Abstract<Self, bool>;
+}
+
+/// A Groth16 verifying key. Since it is fixed for a given circuit, it is given as constants.
+#[derive(Debug, Clone)]
+pub struct VerifyingKey<E: Pairing> {
+    pub alpha_g1: E::G1,
+    pub beta_g2: E::G2,
+    pub gamma_g2: E::G2,
+    pub delta_g2: E::G2,
+
+    /// The bases for the public inputs, with the constant term first.
+    pub ic: Vec<E::G1>,
+}
+
+/// A Groth16 proof.
+pub struct Proof<S: SystemPairing<E> + ?Sized, E: Pairing> {
+    pub a: Abstract<S, E::G1>,
+    pub b: Abstract<S, E::G2>,
+    pub c: Abstract<S, E::G1>,
+}
+
+/// Determines whether a Groth16 proof is valid for the given verifying key and public inputs,
+/// by checking that `e(A, B) = e(alpha, beta) * e(L, gamma) * e(C, delta)`, where `L` is the
+/// combination of the input bases weighted by the public inputs.
+pub fn groth16_verify<S: SystemPairing<E> + ?Sized, E: Pairing>(
+    sys: &mut S,
+    vk: &VerifyingKey<E>,
+    proof: &Proof<S, E>,
+    inputs: &[Abstract<S, E::Scalar>],
+) -> Abstract<S, bool> {
+    assert_eq!(
+        inputs.len() + 1,
+        vk.ic.len(),
+        "wrong number of public inputs"
+    );
+    let mut acc = SystemRepr::<E::G1>::constant(sys, vk.ic[0].clone());
+    for (input, base) in inputs.iter().zip(vk.ic[1..].iter()) {
+        let base = SystemRepr::<E::G1>::constant(sys, base.clone());
+        let term = sys.g1_mul(&base, input);
+        acc = sys.g1_add(&acc, &term);
+    }
+    let alpha = SystemRepr::<E::G1>::constant(sys, vk.alpha_g1.clone());
+    let g1 = [
+        proof.a.clone(),
+        sys.g1_neg(&alpha),
+        sys.g1_neg(&acc),
+        sys.g1_neg(&proof.c),
+    ];
+    let g2 = [
+        proof.b.clone(),
+        SystemRepr::<E::G2>::constant(sys, vk.beta_g2.clone()),
+        SystemRepr::<E::G2>::constant(sys, vk.gamma_g2.clone()),
+        SystemRepr::<E::G2>::constant(sys, vk.delta_g2.clone()),
+    ];
+    sys.pairing_product_is_one(&g1, &g2)
+}
+
+/// A toy "curve" over a prime field, where every group is the additive group of the field and
+/// the pairing is multiplication. This is insecure, but satisfies the algebraic properties the
+/// verifier depends on.
+#[cfg(test)]
+pub(crate) struct ToyPairing;
+
+#[cfg(test)]
+pub(crate) type ToyElement = crate::field::FieldElement<bls12_381::Scalar>;
+
+#[cfg(test)]
+impl Pairing for ToyPairing {
+    type Scalar = ToyElement;
+    type G1 = ToyElement;
+    type G2 = ToyElement;
+}
+
+#[cfg(test)]
+impl SystemPairing<ToyPairing> for Eval {
+    fn g1_add(&mut self, a: &ToyElement, b: &ToyElement) -> ToyElement {
+        a + b
+    }
+
+    fn g1_neg(&mut self, a: &ToyElement) -> ToyElement {
+        crate::field::FieldElement(-a.0)
+    }
+
+    fn g1_mul(&mut self, point: &ToyElement, scalar: &ToyElement) -> ToyElement {
+        point * scalar
+    }
+
+    fn pairing_product_is_one(&mut self, g1: &[ToyElement], g2: &[ToyElement]) -> bool {
+        let sum = g1
+            .iter()
+            .zip(g2.iter())
+            .fold(bls12_381::Scalar::zero(), |acc, (a, b)| acc + a.0 * b.0);
+        sum == bls12_381::Scalar::zero()
+    }
+}
+
+#[test]
+fn test_groth16_verify() {
+    use crate::field::FieldElement;
+    use bls12_381::Scalar;
+    let x = |n: u64| FieldElement(Scalar::from(n));
+    let vk = VerifyingKey::<ToyPairing> {
+        alpha_g1: x(3),
+        beta_g2: x(5),
+        gamma_g2: x(7),
+        delta_g2: x(11),
+        ic: vec![x(13), x(17), x(19)],
+    };
+
+    // Construct a proof by solving for C
+    let inputs = [x(2), x(9)];
+    let a = x(23);
+    let b = x(29);
+    let l = 13 + 17 * 2 + 19 * 9;
+    let c = (Scalar::from(23 * 29) - Scalar::from(3 * 5) - Scalar::from(l * 7))
+        * Scalar::from(11).invert().unwrap();
+    let proof = Proof::<Eval, ToyPairing> {
+        a,
+        b,
+        c: FieldElement(c),
+    };
+    assert!(groth16_verify(&mut Eval, &vk, &proof, &inputs));
+    assert!(!groth16_verify(&mut Eval, &vk, &proof, &[x(2), x(10)]));
+    let proof = Proof::<Eval, ToyPairing> { a: x(24), ..proof };
+    assert!(!groth16_verify(&mut Eval, &vk, &proof, &inputs));
+}
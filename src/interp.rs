@@ -0,0 +1,315 @@
+use crate::binary::select_bit;
+use crate::ram::AbstractRam;
+use crate::*;
+
+/// An instruction for a [`Machine`]. Registers are identified by indices from 0 to 3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instr {
+    /// Stops execution. Further steps have no effect.
+    Halt,
+    /// `rd = rs1 + rs2`, with wrapping.
+    Add {
+        rd: u8,
+        rs1: u8,
+        rs2: u8,
+    },
+    /// `rd = rs1 - rs2`, with wrapping.
+    Sub {
+        rd: u8,
+        rs1: u8,
+        rs2: u8,
+    },
+    And {
+        rd: u8,
+        rs1: u8,
+        rs2: u8,
+    },
+    Or {
+        rd: u8,
+        rs1: u8,
+        rs2: u8,
+    },
+    Xor {
+        rd: u8,
+        rs1: u8,
+        rs2: u8,
+    },
+    /// `rd = 1` if `rs1 < rs2`, or `rd = 0` otherwise.
+    Slt {
+        rd: u8,
+        rs1: u8,
+        rs2: u8,
+    },
+    /// `rd = imm`.
+    LoadImm {
+        rd: u8,
+        imm: u16,
+    },
+    /// `rd = mem[rs1]`.
+    Load {
+        rd: u8,
+        rs1: u8,
+    },
+    /// `mem[rs1] = rs2`.
+    Store {
+        rs1: u8,
+        rs2: u8,
+    },
+    /// Jumps to the instruction at index `target` if `rs1` is non-zero.
+    JumpNonZero {
+        rs1: u8,
+        target: u16,
+    },
+}
+
+/// Opcodes for each kind of [`Instr`], in the order of their one-hot selectors.
+const OP_HALT: usize = 0;
+const OP_ADD: usize = 1;
+const OP_SUB: usize = 2;
+const OP_AND: usize = 3;
+const OP_OR: usize = 4;
+const OP_XOR: usize = 5;
+const OP_SLT: usize = 6;
+const OP_LOAD_IMM: usize = 7;
+const OP_LOAD: usize = 8;
+const OP_STORE: usize = 9;
+const OP_JUMP_NON_ZERO: usize = 10;
+const NUM_OPS: usize = 11;
+
+impl Instr {
+    /// Encodes this instruction as a word. The opcode occupies bits 0 to 3, followed by 2 bits
+    /// each for `rd`, `rs1` and `rs2`. The immediate value occupies the upper 16 bits.
+    pub fn encode(self) -> u32 {
+        let (op, rd, rs1, rs2, imm) = match self {
+            Instr::Halt => (OP_HALT, 0, 0, 0, 0),
+            Instr::Add { rd, rs1, rs2 } => (OP_ADD, rd, rs1, rs2, 0),
+            Instr::Sub { rd, rs1, rs2 } => (OP_SUB, rd, rs1, rs2, 0),
+            Instr::And { rd, rs1, rs2 } => (OP_AND, rd, rs1, rs2, 0),
+            Instr::Or { rd, rs1, rs2 } => (OP_OR, rd, rs1, rs2, 0),
+            Instr::Xor { rd, rs1, rs2 } => (OP_XOR, rd, rs1, rs2, 0),
+            Instr::Slt { rd, rs1, rs2 } => (OP_SLT, rd, rs1, rs2, 0),
+            Instr::LoadImm { rd, imm } => (OP_LOAD_IMM, rd, 0, 0, imm),
+            Instr::Load { rd, rs1 } => (OP_LOAD, rd, rs1, 0, 0),
+            Instr::Store { rs1, rs2 } => (OP_STORE, 0, rs1, rs2, 0),
+            Instr::JumpNonZero { rs1, target } => (OP_JUMP_NON_ZERO, 0, rs1, 0, target),
+        };
+        assert!(rd < 4 && rs1 < 4 && rs2 < 4, "invalid register");
+        op as u32
+            | u32::from(rd) << 4
+            | u32::from(rs1) << 6
+            | u32::from(rs2) << 8
+            | u32::from(imm) << 16
+    }
+}
+
+/// A system in which a [`Machine`] can be simulated.
+pub trait SystemInterp:
+    BinarySystem
+    + SystemBits
+    + SystemSelect<u32>
+    + SystemWrappingAdd<u32>
+    + SystemBitAnd<u32>
+    + SystemBitOr<u32>
+    + SystemBitXor<u32>
+    + SystemNot<u32>
+    + SystemOrd<u32>
+{
+}
+
+impl<
+        S: BinarySystem
+            + SystemBits
+            + SystemSelect<u32>
+            + SystemWrappingAdd<u32>
+            + SystemBitAnd<u32>
+            + SystemBitOr<u32>
+            + SystemBitXor<u32>
+            + SystemNot<u32>
+            + SystemOrd<u32>,
+    > SystemInterp for S
+{
+}
+
+/// A small register machine whose state is abstract, so that its execution, including control
+/// flow, can be proven. Each step fetches an instruction from the program memory at the
+/// program counter, decodes it, and executes it. Since the instruction is abstract, a step
+/// synthesizes the logic for every kind of instruction and selects between their effects.
+///
+/// The machine has 4 word-sized registers, a program memory and a data memory. Memory addresses
+/// are taken from the low bits of a register.
+pub struct Machine<S: SystemRepr<u32> + SystemRepr<bool> + ?Sized> {
+    program: AbstractRam<S, u32>,
+    regs: AbstractRam<S, u32>,
+    memory: AbstractRam<S, u32>,
+    pc: Vec<Abstract<S, bool>>,
+    halted: Abstract<S, bool>,
+}
+
+impl<S: SystemInterp + ?Sized> Machine<S> {
+    /// Constructs a [`Machine`] with the given program and initial data memory. Registers are
+    /// initially zero, and execution starts at the first instruction.
+    pub fn new(sys: &mut S, program: Vec<Abstract<S, u32>>, memory: Vec<Abstract<S, u32>>) -> Self {
+        let zero = SystemRepr::<u32>::constant(sys, 0);
+        let pc_bits = addr_bits(program.len());
+        Self {
+            program: AbstractRam::new(program),
+            regs: AbstractRam::new(vec![zero; 4]),
+            memory: AbstractRam::new(memory),
+            pc: vec![SystemRepr::<bool>::constant(sys, false); pc_bits],
+            halted: SystemRepr::<bool>::constant(sys, false),
+        }
+    }
+
+    /// The current values of the registers.
+    pub fn registers(&self) -> &[Abstract<S, u32>] {
+        self.regs.cells()
+    }
+
+    /// The current contents of the data memory.
+    pub fn memory(&self) -> &[Abstract<S, u32>] {
+        self.memory.cells()
+    }
+
+    /// The index of the next instruction to execute, as a little-endian string of bits.
+    pub fn pc(&self) -> &[Abstract<S, bool>] {
+        &self.pc
+    }
+
+    /// Indicates whether the machine has halted.
+    pub fn halted(&self) -> &Abstract<S, bool> {
+        &self.halted
+    }
+
+    /// Executes `steps` instructions, or fewer if the machine halts.
+    pub fn run(&mut self, sys: &mut S, steps: usize) {
+        for _ in 0..steps {
+            self.step(sys);
+        }
+    }
+
+    /// Executes a single instruction, unless the machine has halted.
+    pub fn step(&mut self, sys: &mut S) {
+        let active = SystemNot::<bool>::not(sys, &self.halted);
+        let instr = self.program.read(sys, &self.pc);
+        let bits = sys.bits_of_u32(&instr);
+        let ops = one_hot(sys, &bits[0..4], NUM_OPS);
+        let rd = &bits[4..6];
+        let a = self.regs.read(sys, &bits[6..8]);
+        let b = self.regs.read(sys, &bits[8..10]);
+        let zero = SystemRepr::<u32>::constant(sys, 0);
+        let imm: [_; 32] = array_init::array_init(|i| match i {
+            0..=15 => bits[16 + i].clone(),
+            _ => SystemRepr::<bool>::constant(sys, false),
+        });
+        let imm = sys.u32_of_bits(&imm);
+
+        // Compute the result of every kind of instruction, then select the relevant one
+        let sum = SystemWrappingAdd::<u32>::wrapping_add(sys, &a, &b);
+        let not_b = SystemNot::<u32>::not(sys, &b);
+        let one = SystemRepr::<u32>::constant(sys, 1);
+        let neg_b = SystemWrappingAdd::<u32>::wrapping_add(sys, &not_b, &one);
+        let diff = SystemWrappingAdd::<u32>::wrapping_add(sys, &a, &neg_b);
+        let and = SystemBitAnd::<u32>::and(sys, &a, &b);
+        let or = SystemBitOr::<u32>::or(sys, &a, &b);
+        let xor = SystemBitXor::<u32>::xor(sys, &a, &b);
+        let lt = SystemOrd::<u32>::lt(sys, &a, &b);
+        let lt = SystemSelect::<u32>::select(sys, &lt, &one, &zero);
+        let mem_addr = sys.bits_of_u32(&a);
+        let mem_addr = &mem_addr[..addr_bits(self.memory.len())];
+        let loaded = self.memory.read(sys, mem_addr);
+        let results = [
+            (OP_ADD, sum),
+            (OP_SUB, diff),
+            (OP_AND, and),
+            (OP_OR, or),
+            (OP_XOR, xor),
+            (OP_SLT, lt),
+            (OP_LOAD_IMM, imm.clone()),
+            (OP_LOAD, loaded),
+        ];
+        let mut res = zero;
+        let mut writes_reg = SystemRepr::<bool>::constant(sys, false);
+        for (op, value) in results.iter() {
+            res = SystemSelect::<u32>::select(sys, &ops[*op], value, &res);
+            writes_reg = SystemBitOr::<bool>::or(sys, &writes_reg, &ops[*op]);
+        }
+        let writes_reg = SystemBitAnd::<bool>::and(sys, &writes_reg, &active);
+        self.regs.write(sys, rd, &res, &writes_reg);
+        let store = SystemBitAnd::<bool>::and(sys, &ops[OP_STORE], &active);
+        self.memory.write(sys, mem_addr, &b, &store);
+
+        // Update the program counter
+        let a_bits = sys.bits_of_u32(&a);
+        let non_zero = a_bits.iter().skip(1).fold(a_bits[0].clone(), |acc, bit| {
+            SystemBitOr::<bool>::or(sys, &acc, bit)
+        });
+        let jump = SystemBitAnd::<bool>::and(sys, &ops[OP_JUMP_NON_ZERO], &non_zero);
+        let jump = SystemBitAnd::<bool>::and(sys, &jump, &active);
+        let halt = SystemBitAnd::<bool>::and(sys, &ops[OP_HALT], &active);
+        let advance = SystemNot::<bool>::not(sys, &jump);
+        let advance = SystemBitAnd::<bool>::and(sys, &advance, &active);
+        let not_halt = SystemNot::<bool>::not(sys, &halt);
+        let advance = SystemBitAnd::<bool>::and(sys, &advance, &not_halt);
+        let mut carry = advance;
+        for (i, bit) in self.pc.iter_mut().enumerate() {
+            let next = SystemBitXor::<bool>::xor(sys, bit, &carry);
+            carry = SystemBitAnd::<bool>::and(sys, bit, &carry);
+            *bit = select_bit(sys, &jump, &bits[16 + i], &next);
+        }
+        self.halted = SystemBitOr::<bool>::or(sys, &self.halted, &halt);
+    }
+}
+
+/// The number of address bits needed to index a memory with `len` cells.
+fn addr_bits(len: usize) -> usize {
+    (usize::BITS - len.saturating_sub(1).leading_zeros()) as usize
+}
+
+/// A program which sums the integers from 1 to `mem[0]`, storing the result in `mem[1]`.
+#[cfg(test)]
+fn sum_program() -> Vec<u32> {
+    [
+        Instr::LoadImm { rd: 2, imm: 1 },
+        Instr::Load { rd: 0, rs1: 3 },
+        Instr::Add {
+            rd: 1,
+            rs1: 1,
+            rs2: 0,
+        },
+        Instr::Sub {
+            rd: 0,
+            rs1: 0,
+            rs2: 2,
+        },
+        Instr::JumpNonZero { rs1: 0, target: 2 },
+        Instr::LoadImm { rd: 3, imm: 1 },
+        Instr::Store { rs1: 3, rs2: 1 },
+        Instr::Halt,
+    ]
+    .iter()
+    .map(|instr| instr.encode())
+    .collect()
+}
+
+#[test]
+fn test_interp_eval() {
+    let mut sys = Eval;
+    let mut machine = Machine::new(&mut sys, sum_program(), vec![5, 0, 0, 0]);
+    machine.run(&mut sys, 17);
+    assert!(!machine.halted());
+    machine.run(&mut sys, 5);
+    assert!(machine.halted());
+    assert_eq!(machine.memory(), &[5, 15, 0, 0]);
+    assert_eq!(machine.registers(), &[0, 15, 1, 1]);
+}
+
+#[test]
+fn test_interp_binary_emulate() {
+    let mut sys = BinaryEmulate::new(Eval);
+    let program = sum_program().into_iter().map(|x| sys.constant(x)).collect();
+    let memory = [3u32, 0].into_iter().map(|x| sys.constant(x)).collect();
+    let mut machine = Machine::new(&mut sys, program, memory);
+    machine.run(&mut sys, 16);
+    assert!(*machine.halted());
+    assert_eq!(machine.memory()[1], sys.constant(6u32));
+}
@@ -0,0 +1,122 @@
+//! Files containing circuits in the crate's own intermediate representation, a
+//! [`SubcircuitTemplate`]. A circuit can be synthesized once, by a build service, saved, and
+//! later loaded and lowered to any backend without running the gadget code again.
+use crate::graph::SubcircuitTemplate;
+use crate::*;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Saves a circuit to the given path, replacing any existing file.
+pub fn save(path: impl AsRef<Path>, circuit: &SubcircuitTemplate) -> io::Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    circuit.write(&mut writer)?;
+    writer.flush()
+}
+
+/// Loads a circuit saved using [`save`]. Fails with [`io::ErrorKind::InvalidData`] if the file
+/// isn't a circuit.
+pub fn load(path: impl AsRef<Path>) -> io::Result<SubcircuitTemplate> {
+    SubcircuitTemplate::read(BufReader::new(fs::File::open(path)?))
+}
+
+/// Lowers a circuit to the given system, with the given inputs, returning its outputs. This is
+/// [`SubcircuitTemplate::instantiate`], checking the number of inputs first so that a mismatch
+/// between a loaded circuit and the caller is reported as an error rather than a panic.
+pub fn lower<S: BinarySystem + ?Sized>(
+    circuit: &SubcircuitTemplate,
+    sys: &mut S,
+    inputs: &[Abstract<S, bool>],
+) -> Result<Vec<Abstract<S, bool>>, InputCountMismatch> {
+    if inputs.len() != circuit.num_inputs() {
+        return Err(InputCountMismatch {
+            expected: circuit.num_inputs(),
+            actual: inputs.len(),
+        });
+    }
+    Ok(circuit.instantiate(sys, inputs))
+}
+
+/// The error returned by [`lower`] when given the wrong number of inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputCountMismatch {
+    pub expected: usize,
+    pub actual: usize,
+}
+
+impl std::fmt::Display for InputCountMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "circuit expects {} inputs, but {} were given",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for InputCountMismatch {}
+
+#[cfg(feature = "r1cs")]
+#[test]
+fn test_ir() {
+    use crate::graph::Node;
+    use crate::r1cs::{ArithmeticSystem, Formula};
+    use bls12_381::Scalar;
+    use std::rc::Rc;
+
+    // A circuit which adds three bytes, using a module
+    let add = Rc::new(SubcircuitTemplate::capture(16, |graph, inputs| {
+        let mut sys = BinaryEmulate::new(std::mem::take(graph));
+        let a: [Node; 8] = array_init::array_init(|i| inputs[i]);
+        let b: [Node; 8] = array_init::array_init(|i| inputs[8 + i]);
+        let r = SystemWrappingAdd::<u8>::wrapping_add(&mut sys, &a, &b);
+        *graph = sys.into_source();
+        r.to_vec()
+    }));
+    let circuit = SubcircuitTemplate::capture(24, |graph, inputs| {
+        let module = graph.define("add", add.clone());
+        let ab = graph.call(module, &inputs[..16]);
+        let inputs: Vec<_> = ab.iter().chain(&inputs[16..]).copied().collect();
+        graph.call(module, &inputs)
+    });
+
+    // Round-trip through a file
+    let path = std::env::temp_dir().join(format!("circus-ir-test-{}.circuit", std::process::id()));
+    save(&path, &circuit).unwrap();
+    let loaded = load(&path).unwrap();
+    fs::write(&path, b"garbage").unwrap();
+    assert_eq!(load(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.num_inputs(), 24);
+    assert_eq!(loaded.num_gates(), circuit.num_gates());
+    assert_eq!(loaded.graph().fingerprint(), circuit.graph().fingerprint());
+
+    // Lower the loaded circuit to evaluation and to R1CS
+    let (a, b, c) = (200u8, 100u8, 7u8);
+    let inputs: Vec<bool> = [a, b, c]
+        .iter()
+        .flat_map(|x| crate::system::bits_of::<8>(u64::from(*x)))
+        .collect();
+    let expected = crate::system::bits_of::<8>(u64::from(a.wrapping_add(b).wrapping_add(c)));
+    assert_eq!(lower(&loaded, &mut Eval, &inputs).unwrap(), expected);
+    assert_eq!(
+        lower(&loaded, &mut Eval, &inputs[..16]),
+        Err(InputCountMismatch {
+            expected: 24,
+            actual: 16
+        })
+    );
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let vars: Vec<_> = (0..24).map(|_| sys.declare()).collect();
+    let formulas: Vec<_> = vars.iter().map(|v| Formula::from(*v)).collect();
+    let outputs = lower(&loaded, &mut sys, &formulas).unwrap();
+    let known: Vec<_> = (vars.iter().zip(inputs.iter()))
+        .map(|(v, b)| (*v, Scalar::from(*b as u64)))
+        .collect();
+    let assignment = sys.solve(&known).unwrap();
+    assert!(sys.is_satisfied(&assignment));
+    let values: Vec<_> = (outputs.iter())
+        .map(|o| sys.eval(*o, &assignment) == Scalar::one())
+        .collect();
+    assert_eq!(values, expected);
+}
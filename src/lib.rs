@@ -1,7 +1,123 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+
+// Allows code generated by `circus-derive` to refer to this crate as `::circus` within it
+extern crate self as circus;
+
 mod system;
+mod reflect;
+#[cfg(feature = "std")]
+mod coverage;
+#[cfg(feature = "std")]
+mod shrink;
+#[cfg(feature = "binary")]
 mod binary;
+#[cfg(feature = "graph")]
+mod diff;
+#[cfg(feature = "graph")]
+mod equiv;
+#[cfg(all(feature = "sha2", any(feature = "graph", feature = "r1cs")))]
+mod fingerprint;
+#[cfg(all(feature = "sha2", any(feature = "graph", feature = "r1cs")))]
+mod cache;
+#[cfg(any(feature = "graph", feature = "r1cs"))]
+mod manifest;
+#[cfg(any(feature = "proptest", feature = "quickcheck"))]
+mod arbitrary;
+#[cfg(feature = "r1cs")]
 pub mod r1cs;
+#[cfg(feature = "graph")]
+pub mod graph;
+#[cfg(feature = "graph")]
+pub mod bitslice;
+#[cfg(feature = "graph")]
+pub mod bdd;
+#[cfg(feature = "graph")]
+pub mod ir;
+#[cfg(feature = "graph")]
+pub mod debugger;
+#[cfg(feature = "graph")]
+pub mod report;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "binary")]
+pub mod bitset;
+#[cfg(feature = "binary")]
+pub mod date;
+#[cfg(feature = "binary")]
+pub mod alu;
+#[cfg(feature = "binary")]
+pub mod fsm;
+#[cfg(feature = "binary")]
+pub mod erased;
+#[cfg(feature = "fixed")]
+pub mod fixed;
+#[cfg(feature = "ram")]
+pub mod ram;
+#[cfg(feature = "bytes")]
+pub mod bytes;
+#[cfg(feature = "bytes")]
+pub mod wire;
+#[cfg(feature = "bytes")]
+pub mod decimal;
+#[cfg(feature = "interp")]
+pub mod interp;
+#[cfg(feature = "riscv")]
+pub mod riscv;
+#[cfg(feature = "eth")]
+pub mod eth;
+#[cfg(feature = "bitcoin")]
+pub mod bitcoin;
+#[cfg(feature = "credentials")]
+pub mod credentials;
+#[cfg(feature = "filter")]
+pub mod filter;
+#[cfg(feature = "protocol")]
+pub mod protocol;
+#[cfg(feature = "groth16")]
+pub mod groth16;
+#[cfg(feature = "bls")]
+pub mod bls;
+#[cfg(feature = "merkle")]
+pub mod merkle;
+#[cfg(feature = "shamir")]
+pub mod shamir;
+#[cfg(feature = "transcript")]
+pub mod transcript;
+#[cfg(feature = "sumcheck")]
+pub mod sumcheck;
+pub mod field;
 pub mod crypto;
+pub mod prelude;
 
 pub use system::*;
-pub use binary::*;
\ No newline at end of file
+
+#[cfg(feature = "derive")]
+pub use circus_derive::AbstractEnum;
+#[cfg(feature = "std")]
+pub use coverage::*;
+#[cfg(feature = "std")]
+pub use shrink::*;
+#[cfg(feature = "binary")]
+pub use binary::*;
+#[cfg(feature = "graph")]
+pub use diff::*;
+#[cfg(feature = "graph")]
+pub use equiv::*;
+#[cfg(all(feature = "sha2", any(feature = "graph", feature = "r1cs")))]
+pub use fingerprint::*;
+#[cfg(all(feature = "sha2", any(feature = "graph", feature = "r1cs")))]
+pub use cache::*;
+#[cfg(any(feature = "graph", feature = "r1cs"))]
+pub use manifest::*;
+
+/// Items used by the expansions of the macros of this crate, so that they work in crates without
+/// `std` or an `extern crate alloc`. Not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::vec::Vec;
+}
@@ -0,0 +1,267 @@
+use std::fmt::{self, Write};
+
+/// Whether a [`Port`] is consumed or produced by a circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Input,
+    Output,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Input => write!(f, "input"),
+            Direction::Output => write!(f, "output"),
+        }
+    }
+}
+
+/// The type of each element of a [`Port`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PortType {
+    Bool,
+
+    /// An unsigned integer with the given number of bits.
+    UInt(u32),
+
+    /// An element of a prime field whose modulus has the given number of bits.
+    Field(u32),
+}
+
+impl PortType {
+    /// The number of bits needed to represent a value of this type.
+    pub fn bits(self) -> u32 {
+        match self {
+            PortType::Bool => 1,
+            PortType::UInt(bits) | PortType::Field(bits) => bits,
+        }
+    }
+}
+
+impl fmt::Display for PortType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortType::Bool => write!(f, "bool"),
+            PortType::UInt(bits) => write!(f, "u{}", bits),
+            PortType::Field(_) => write!(f, "field"),
+        }
+    }
+}
+
+/// A named input or output of a circuit, consisting of an array of `len` elements of type `ty`.
+///
+/// Each element is carried by the same number of consecutive wires: either a single wire
+/// holding its value, or one wire per bit, least significant bit first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Port {
+    pub name: String,
+    pub direction: Direction,
+    pub ty: PortType,
+    pub len: usize,
+
+    /// The index of each wire carrying the port, or `None` if the wire was eliminated from the
+    /// circuit, in which case its value doesn't matter.
+    pub wires: Vec<Option<usize>>,
+}
+
+impl Port {
+    /// The number of wires carrying each element of this port.
+    pub fn wires_per_element(&self) -> usize {
+        self.wires.len() / self.len.max(1)
+    }
+}
+
+/// Describes the named inputs and outputs of an exported circuit, so that tooling can bind
+/// values to them without depending on the order in which the circuit was synthesized. Wire
+/// indices are those of the backend: nodes of a [`Graph`](crate::graph::Graph), or variables of
+/// an [`ArithmeticSystem`](crate::r1cs::ArithmeticSystem).
+///
+/// A manifest is stable as long as its ports are, so it can serve as the ABI of a circuit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    ports: Vec<Port>,
+}
+
+impl Manifest {
+    /// Constructs a new [`Manifest`] with no ports.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an input port, carried by the given wires.
+    pub fn input(
+        &mut self,
+        name: impl Into<String>,
+        ty: PortType,
+        len: usize,
+        wires: impl IntoIterator<Item = usize>,
+    ) -> &mut Self {
+        self.push(name.into(), Direction::Input, ty, len, wires)
+    }
+
+    /// Adds an output port, carried by the given wires.
+    pub fn output(
+        &mut self,
+        name: impl Into<String>,
+        ty: PortType,
+        len: usize,
+        wires: impl IntoIterator<Item = usize>,
+    ) -> &mut Self {
+        self.push(name.into(), Direction::Output, ty, len, wires)
+    }
+
+    fn push(
+        &mut self,
+        name: String,
+        direction: Direction,
+        ty: PortType,
+        len: usize,
+        wires: impl IntoIterator<Item = usize>,
+    ) -> &mut Self {
+        assert!(self.port(&name).is_none(), "duplicate port {:?}", name);
+        let wires: Vec<_> = wires.into_iter().map(Some).collect();
+        let per_element = wires.len() / len.max(1);
+        assert!(
+            wires.len() == len * per_element
+                && (per_element == 1 || per_element == ty.bits() as usize),
+            "port {:?} must have 1 or {} wires per element",
+            name,
+            ty.bits()
+        );
+        self.ports.push(Port {
+            name,
+            direction,
+            ty,
+            len,
+            wires,
+        });
+        self
+    }
+
+    /// The ports of the circuit, in the order they were added.
+    pub fn ports(&self) -> &[Port] {
+        &self.ports
+    }
+
+    /// Gets the port with the given name.
+    pub fn port(&self, name: &str) -> Option<&Port> {
+        self.ports.iter().find(|port| port.name == name)
+    }
+
+    /// Constructs a copy of this manifest with every wire translated by `f`, which returns `None`
+    /// for wires that were eliminated. This is used to follow a circuit through a renumbering.
+    pub fn map_wires(&self, mut f: impl FnMut(usize) -> Option<usize>) -> Self {
+        let ports = (self.ports.iter())
+            .map(|port| Port {
+                wires: port
+                    .wires
+                    .iter()
+                    .map(|wire| wire.and_then(&mut f))
+                    .collect(),
+                ..port.clone()
+            })
+            .collect();
+        Self { ports }
+    }
+
+    /// Serializes this manifest as JSON. The result is an object with a `ports` array, where each
+    /// port is an object with the fields of [`Port`], along with `bits`, the bit width of each
+    /// element. Eliminated wires are `null`.
+    pub fn to_json(&self) -> String {
+        let mut res = String::from("{\"ports\": [");
+        for (i, port) in self.ports.iter().enumerate() {
+            if i > 0 {
+                res.push(',');
+            }
+            res.push_str("\n  {\"name\": ");
+            write_json_str(&mut res, &port.name);
+            write!(
+                res,
+                ", \"direction\": \"{}\", \"type\": \"{}\", \"bits\": {}, \"len\": {}, \"wires\": [",
+                port.direction,
+                port.ty,
+                port.ty.bits(),
+                port.len
+            )
+            .unwrap();
+            for (j, wire) in port.wires.iter().enumerate() {
+                if j > 0 {
+                    res.push_str(", ");
+                }
+                match wire {
+                    Some(wire) => write!(res, "{}", wire).unwrap(),
+                    None => res.push_str("null"),
+                }
+            }
+            res.push_str("]}");
+        }
+        if !self.ports.is_empty() {
+            res.push('\n');
+        }
+        res.push_str("]}\n");
+        res
+    }
+}
+
+/// Writes a string as a JSON string literal.
+pub(crate) fn write_json_str(res: &mut String, str: &str) {
+    res.push('"');
+    for ch in str.chars() {
+        match ch {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            ch if (ch as u32) < 0x20 => write!(res, "\\u{:04x}", ch as u32).unwrap(),
+            ch => res.push(ch),
+        }
+    }
+    res.push('"');
+}
+
+#[cfg(all(feature = "graph", feature = "r1cs"))]
+#[test]
+fn test_manifest() {
+    use crate::field::FieldElement;
+    use crate::graph::Graph;
+    use crate::r1cs::{ArithmeticSystem, Formula};
+    use crate::*;
+    use bls12_381::Scalar;
+
+    // A graph which computes the XOR of two 2-bit inputs, one wire per bit
+    let mut graph = Graph::new();
+    let a = [graph.input(), graph.input()];
+    let b = [graph.input(), graph.input()];
+    let c = SystemBitXor::<[bool; 2]>::xor(&mut graph, &a, &b);
+    let mut manifest = Manifest::new();
+    manifest
+        .input("a", PortType::UInt(2), 1, a.iter().map(|n| n.index()))
+        .input("b\"", PortType::Bool, 2, b.iter().map(|n| n.index()))
+        .output("c", PortType::UInt(2), 1, c.iter().map(|n| n.index()));
+    assert_eq!(manifest.port("c").unwrap().wires_per_element(), 2);
+    assert_eq!(
+        manifest.to_json(),
+        "{\"ports\": [\n  \
+        {\"name\": \"a\", \"direction\": \"input\", \"type\": \"u2\", \"bits\": 2, \"len\": 1, \"wires\": [0, 1]},\n  \
+        {\"name\": \"b\\\"\", \"direction\": \"input\", \"type\": \"bool\", \"bits\": 1, \"len\": 2, \"wires\": [2, 3]},\n  \
+        {\"name\": \"c\", \"direction\": \"output\", \"type\": \"u2\", \"bits\": 2, \"len\": 1, \"wires\": [4, 5]}\n\
+        ]}\n"
+    );
+
+    // A constraint system with a public output, which follows the variables through
+    // finalization
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let x = sys.declare();
+    let unused = sys.declare();
+    let y = sys.declare_public();
+    let mut manifest = Manifest::new();
+    manifest
+        .input("x", PortType::Field(255), 2, [x.index(), unused.index()])
+        .output("y", PortType::Field(255), 1, [y.index()]);
+    let square = SystemMul::<FieldElement<Scalar>>::mul(&mut sys, &x.into(), &x.into());
+    SystemAssertEq::<FieldElement<Scalar>>::assert_eq(&mut sys, &square, &Formula::from(y));
+    let (_, renumbering) = sys.finalize();
+    let manifest = renumbering.apply_manifest(&manifest);
+    assert_eq!(manifest.port("x").unwrap().wires, [Some(1), None]);
+    assert_eq!(manifest.port("y").unwrap().wires, [Some(0)]);
+    assert!(manifest.to_json().contains("\"wires\": [1, null]"));
+}
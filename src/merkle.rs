@@ -0,0 +1,310 @@
+//! An append-only Merkle tree, which can verify the insertion of batches of leaves. This is the
+//! usual building block for rollup-style circuits, where the old and new roots of the tree are
+//! public inputs. Nodes may be hashed with any [`Compress2to1`] function, such as Poseidon over
+//! field elements.
+use crate::crypto::hash::{Compress2to1, PoseidonParams, SystemCompress2to1};
+use crate::field::FieldElement;
+use crate::*;
+use ff::PrimeField;
+
+/// A system in which [`MerkleAccumulator`] insertions using the compression function `H` can be
+/// verified.
+pub trait SystemMerkle<H: Compress2to1>:
+    SystemCompress2to1<H> + SystemSelect<H::Node> + SystemBitAnd<bool>
+{
+}
+
+impl<
+        H: Compress2to1,
+        S: SystemCompress2to1<H> + SystemSelect<H::Node> + SystemBitAnd<bool> + ?Sized,
+    > SystemMerkle<H> for S
+{
+}
+
+/// Describes an append-only Merkle tree of fixed depth, whose nodes are hashed using the
+/// compression function `H`. Leaves are inserted in aligned batches of `2^batch_bits`, so that
+/// each batch forms a subtree and only one authentication path needs to be checked for it.
+#[derive(Debug, Clone)]
+pub struct MerkleAccumulator<H: Compress2to1> {
+    hash: H,
+    depth: usize,
+    batch_bits: usize,
+
+    /// The root of an empty subtree of each height, starting with an empty leaf.
+    empty: Vec<H::Node>,
+}
+
+impl<F: PrimeField> MerkleAccumulator<PoseidonParams<F>> {
+    /// Constructs a [`MerkleAccumulator`] with the given depth and batch size, hashed with a
+    /// width-3 Poseidon permutation, whose empty leaves are zero.
+    pub fn new(depth: usize, batch_bits: usize) -> Self {
+        Self::with_hash(
+            PoseidonParams::width_3(),
+            FieldElement(F::zero()),
+            depth,
+            batch_bits,
+        )
+    }
+
+    /// The parameters of the hash used for internal nodes.
+    pub fn params(&self) -> &PoseidonParams<F> {
+        &self.hash
+    }
+}
+
+impl<H: Compress2to1> MerkleAccumulator<H> {
+    /// Constructs a [`MerkleAccumulator`] with the given compression function, empty leaf, depth
+    /// and batch size.
+    pub fn with_hash(hash: H, empty_leaf: H::Node, depth: usize, batch_bits: usize) -> Self
+    where
+        Eval: SystemCompress2to1<H> + SystemRead<H::Node>,
+    {
+        assert!(batch_bits <= depth, "batches can't be larger than the tree");
+        let mut empty = vec![empty_leaf];
+        for i in 0..depth {
+            let node = Eval.constant(empty[i].clone());
+            let parent = Eval.compress2to1(&hash, &node, &node);
+            empty.push(Eval.read_value(&parent));
+        }
+        Self {
+            hash,
+            depth,
+            batch_bits,
+            empty,
+        }
+    }
+
+    /// The compression function used for internal nodes.
+    pub fn hash(&self) -> &H {
+        &self.hash
+    }
+
+    /// The depth of the tree.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The number of leaves in each batch.
+    pub fn batch_size(&self) -> usize {
+        1 << self.batch_bits
+    }
+
+    /// The root of the tree before any leaves are inserted.
+    pub fn empty_root(&self) -> H::Node {
+        self.empty[self.depth].clone()
+    }
+
+    /// Computes the root of a complete subtree with the given leaves, whose number must be a
+    /// power of two.
+    pub fn subtree_root<S: SystemMerkle<H> + ?Sized>(
+        &self,
+        sys: &mut S,
+        leaves: &[Abstract<S, H::Node>],
+    ) -> Abstract<S, H::Node> {
+        assert!(
+            leaves.len().is_power_of_two(),
+            "number of leaves must be a power of two"
+        );
+        let mut layer = leaves.to_vec();
+        while layer.len() > 1 {
+            layer = layer
+                .chunks(2)
+                .map(|pair| sys.compress2to1(&self.hash, &pair[0], &pair[1]))
+                .collect();
+        }
+        layer.pop().unwrap()
+    }
+
+    /// Computes the root of the tree from a node and its authentication path. `index` gives the
+    /// position of the node within its level as little-endian bits, and `siblings` are ordered
+    /// from the bottom up.
+    pub fn path_root<S: SystemMerkle<H> + ?Sized>(
+        &self,
+        sys: &mut S,
+        node: &Abstract<S, H::Node>,
+        index: &[Abstract<S, bool>],
+        siblings: &[Abstract<S, H::Node>],
+    ) -> Abstract<S, H::Node> {
+        assert_eq!(index.len(), siblings.len(), "path lengths must match");
+        let mut node = node.clone();
+        for (is_right, sibling) in index.iter().zip(siblings.iter()) {
+            let left = SystemSelect::<H::Node>::select(sys, is_right, sibling, &node);
+            let right = SystemSelect::<H::Node>::select(sys, is_right, &node, sibling);
+            node = sys.compress2to1(&self.hash, &left, &right);
+        }
+        node
+    }
+
+    /// Determines whether inserting a batch of leaves into a tree with root `old_root` gives a
+    /// tree with root `new_root`. The batch is inserted at the position given by `batch_index`,
+    /// as little-endian bits, and the leaves there must previously have been empty. `siblings`
+    /// is the authentication path for the batch, which is shared between the old and new trees.
+    pub fn verify_batch<S: SystemMerkle<H> + ?Sized>(
+        &self,
+        sys: &mut S,
+        old_root: &Abstract<S, H::Node>,
+        new_root: &Abstract<S, H::Node>,
+        batch_index: &[Abstract<S, bool>],
+        siblings: &[Abstract<S, H::Node>],
+        leaves: &[Abstract<S, H::Node>],
+    ) -> Abstract<S, bool> {
+        let levels = self.depth - self.batch_bits;
+        assert_eq!(batch_index.len(), levels, "wrong number of index bits");
+        assert_eq!(siblings.len(), levels, "wrong number of siblings");
+        assert_eq!(leaves.len(), self.batch_size(), "wrong number of leaves");
+        let empty = SystemRepr::<H::Node>::constant(sys, self.empty[self.batch_bits].clone());
+        let old = self.path_root(sys, &empty, batch_index, siblings);
+        let subtree = self.subtree_root(sys, leaves);
+        let new = self.path_root(sys, &subtree, batch_index, siblings);
+        let old_valid = sys.node_eq(&old, old_root);
+        let new_valid = sys.node_eq(&new, new_root);
+        SystemBitAnd::<bool>::and(sys, &old_valid, &new_valid)
+    }
+}
+
+#[cfg(feature = "r1cs")]
+#[test]
+fn test_merkle_accumulator() {
+    use crate::crypto::hash::SystemPoseidon;
+    use crate::r1cs::{ArithmeticSystem, Formula, Variable};
+    use bls12_381::Scalar;
+    let acc = MerkleAccumulator::<PoseidonParams<Scalar>>::new(4, 1);
+    let x = |n: u64| FieldElement(Scalar::from(n));
+
+    // Computes the root of a tree with the given leaves, and the siblings for a batch
+    let tree = |leaves: &[u64], batch: usize| {
+        let mut layer: Vec<_> = (0..16)
+            .map(|i| x(leaves.get(i).copied().unwrap_or(0)))
+            .collect();
+        let mut siblings = Vec::new();
+        let mut index = batch * 2;
+        for level in 0..4 {
+            if level >= 1 {
+                siblings.push(layer[index ^ 1]);
+            }
+            layer = layer
+                .chunks(2)
+                .map(|pair| Eval.poseidon_hash2(acc.params(), &pair[0], &pair[1]))
+                .collect();
+            index /= 2;
+        }
+        (layer[0], siblings)
+    };
+    let (root, _) = tree(&[], 0);
+    assert_eq!(root, acc.empty_root());
+
+    // Insert batches one at a time
+    let mut leaves: Vec<u64> = Vec::new();
+    for batch in 0..3 {
+        let (old_root, siblings) = tree(&leaves, batch);
+        let new_leaves = [batch as u64 * 10 + 1, batch as u64 * 10 + 2];
+        leaves.extend(new_leaves);
+        let (new_root, _) = tree(&leaves, batch);
+        let index = [batch & 1 != 0, batch & 2 != 0, batch & 4 != 0];
+        let new_leaves = new_leaves.map(x);
+        assert!(acc.verify_batch(
+            &mut Eval,
+            &old_root,
+            &new_root,
+            &index,
+            &siblings,
+            &new_leaves
+        ));
+
+        // Batches can't be inserted in the wrong place, or over existing leaves
+        let wrong = [!index[0], index[1], index[2]];
+        assert!(!acc.verify_batch(
+            &mut Eval,
+            &old_root,
+            &new_root,
+            &wrong,
+            &siblings,
+            &new_leaves
+        ));
+        if batch > 0 {
+            let (old_root, siblings) = tree(&leaves, 0);
+            let res = acc.verify_batch(
+                &mut Eval,
+                &old_root,
+                &new_root,
+                &[false; 3],
+                &siblings,
+                &new_leaves,
+            );
+            assert!(!res);
+        }
+    }
+
+    // Check the same insertion in an arithmetic system
+    let (old_root, siblings) = tree(&leaves[..2], 1);
+    let (new_root, _) = tree(&leaves[..4], 1);
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let vars: Vec<_> = (0..10).map(|_| sys.declare()).collect();
+    let f = |i: usize| Formula::from(vars[i]);
+    let res = acc.verify_batch(
+        &mut sys,
+        &f(0),
+        &f(1),
+        &[f(2), f(3), f(4)],
+        &[f(5), f(6), f(7)],
+        &[f(8), f(9)],
+    );
+    let values = [
+        old_root.0,
+        new_root.0,
+        Scalar::one(),
+        Scalar::zero(),
+        Scalar::zero(),
+        siblings[0].0,
+        siblings[1].0,
+        siblings[2].0,
+        Scalar::from(11),
+        Scalar::from(12),
+    ];
+    let known: Vec<(Variable, Scalar)> = vars.iter().copied().zip(values).collect();
+    let assignment = sys.solve(&known).unwrap();
+    assert_eq!(sys.check(&assignment), Ok(()));
+    assert_eq!(sys.eval(res, &assignment), Scalar::one());
+}
+
+#[test]
+fn test_merkle_sha256() {
+    use crate::crypto::hash::Sha256Compress;
+    let acc = MerkleAccumulator::with_hash(Sha256Compress, [0; 8], 3, 1);
+    let hash = |a: [u32; 8], b: [u32; 8]| Eval.compress2to1(&Sha256Compress, &a, &b);
+    let empty_1 = hash([0; 8], [0; 8]);
+    let empty_2 = hash(empty_1, empty_1);
+    assert_eq!(acc.empty_root(), hash(empty_2, empty_2));
+
+    // Insert a batch into the second position
+    let leaves = [[1; 8], [2; 8]];
+    let old_root = acc.empty_root();
+    let new_root = hash(hash(empty_1, hash(leaves[0], leaves[1])), empty_2);
+    let siblings = [empty_1, empty_2];
+    let sys = &mut Eval;
+    assert!(acc.verify_batch(
+        sys,
+        &old_root,
+        &new_root,
+        &[true, false],
+        &siblings,
+        &leaves
+    ));
+    assert!(!acc.verify_batch(
+        sys,
+        &old_root,
+        &new_root,
+        &[false, false],
+        &siblings,
+        &leaves
+    ));
+
+    // The same insertion, with emulated words
+    let sys = &mut BinaryEmulate::new(Eval);
+    let [old_root, new_root] = [old_root, new_root].map(|root| sys.constant(root));
+    let siblings = siblings.map(|node| sys.constant(node));
+    let leaves = leaves.map(|node| sys.constant(node));
+    let index = [true, false];
+    let res = acc.verify_batch(sys, &old_root, &new_root, &index, &siblings, &leaves);
+    assert!(res);
+}
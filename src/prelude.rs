@@ -0,0 +1,66 @@
+//! Re-exports the traits needed to build circuits, so that a single glob import brings every
+//! enabled gadget into scope:
+//!
+//! ```
+//! use circus::prelude::*;
+//! ```
+pub use crate::{
+    Abstract, Eval, SystemAdd, SystemAssert, SystemAssertEq, SystemAssertIf, SystemBitAnd,
+    SystemBitOr, SystemBitRotate, SystemBitSelect, SystemBitShift, SystemBitXor, SystemBits,
+    SystemCapabilities, SystemDebug, SystemDivRem, SystemError, SystemInverse, SystemModArith,
+    SystemMul, SystemMulFull, SystemMulShr, SystemNot, SystemOrd, SystemPack, SystemRand,
+    SystemRead, SystemRepr, SystemSelect, SystemWrappingAdd, SystemWrappingMul,
+};
+
+#[cfg(feature = "binary")]
+pub use crate::{BinaryEmulate, BinarySystem};
+
+pub use crate::crypto::hash::SystemCompress2to1;
+#[cfg(feature = "poseidon")]
+pub use crate::crypto::hash::SystemPoseidon;
+#[cfg(feature = "siphash")]
+pub use crate::crypto::hash::SystemSipHash;
+#[cfg(feature = "xoodoo")]
+pub use crate::crypto::hash::SystemXoodoo;
+#[cfg(feature = "keccak")]
+pub use crate::crypto::hash::{SystemKeccak, SystemKeccak800};
+#[cfg(feature = "sha2")]
+pub use crate::crypto::hash::{SystemSha256, SystemSha256Bytes};
+
+#[cfg(feature = "aes")]
+pub use crate::crypto::aes::SystemAes;
+#[cfg(feature = "ascon")]
+pub use crate::crypto::ascon::SystemAscon;
+#[cfg(feature = "email")]
+pub use crate::crypto::email::SystemEmail;
+#[cfg(feature = "prg")]
+pub use crate::crypto::prg::SystemPrg;
+#[cfg(feature = "tls")]
+pub use crate::crypto::tls::SystemTls;
+
+#[cfg(feature = "graph")]
+pub use crate::graph::Graph;
+#[cfg(feature = "r1cs")]
+pub use crate::r1cs::{ArithmeticSystem, ConstraintSink};
+
+#[cfg(feature = "bytes")]
+pub use crate::bytes::SystemBytes;
+#[cfg(feature = "fixed")]
+pub use crate::fixed::SystemFixed;
+#[cfg(feature = "interp")]
+pub use crate::interp::SystemInterp;
+#[cfg(feature = "bytes")]
+pub use crate::wire::SystemWire;
+
+#[cfg(feature = "eth")]
+pub use crate::eth::SystemEth;
+#[cfg(feature = "filter")]
+pub use crate::filter::SystemFilter;
+#[cfg(feature = "groth16")]
+pub use crate::groth16::SystemPairing;
+#[cfg(feature = "merkle")]
+pub use crate::merkle::SystemMerkle;
+#[cfg(feature = "protocol")]
+pub use crate::protocol::SystemProtocol;
+#[cfg(feature = "shamir")]
+pub use crate::shamir::SystemShamir;
@@ -0,0 +1,391 @@
+//! Building blocks for shielded pools in the style of Zcash and Tornado Cash, where notes are
+//! committed to in a Merkle tree and spent by revealing a nullifier. Hashing uses SHA-256 with a
+//! distinct prefix byte for each purpose.
+use crate::crypto::hash::SystemSha256Bytes;
+use crate::*;
+
+/// A system in which shielded pool gadgets can be synthesized.
+pub trait SystemProtocol:
+    BinarySystem
+    + SystemSha256Bytes
+    + SystemBits
+    + SystemSelect<bool>
+    + SystemSelect<u8>
+    + SystemWrappingAdd<u64>
+    + SystemOrd<u64>
+{
+}
+
+impl<
+        S: BinarySystem
+            + SystemSha256Bytes
+            + SystemBits
+            + SystemSelect<bool>
+            + SystemSelect<u8>
+            + SystemWrappingAdd<u64>
+            + SystemOrd<u64>,
+    > SystemProtocol for S
+{
+}
+
+/// The prefix byte for note commitments.
+const COMMITMENT_PREFIX: u8 = 0;
+
+/// The prefix byte for nullifiers.
+const NULLIFIER_PREFIX: u8 = 1;
+
+/// The prefix byte for internal nodes of the commitment tree.
+const NODE_PREFIX: u8 = 2;
+
+/// A note, which represents ownership of some value within a shielded pool.
+pub struct Note<S: SystemRepr<u8> + SystemRepr<u64> + ?Sized> {
+    /// A secret known only to the owner of the note, which is needed to spend it.
+    pub secret: [Abstract<S, u8>; 32],
+
+    /// A random value which distinguishes this note from others, and from which its nullifier
+    /// is derived.
+    pub nullifier_seed: [Abstract<S, u8>; 32],
+
+    /// The value of the note.
+    pub value: Abstract<S, u64>,
+}
+
+impl<S: SystemProtocol + ?Sized> Note<S> {
+    /// Computes the commitment to this note, which is its leaf in the commitment tree.
+    pub fn commitment(&self, sys: &mut S) -> [Abstract<S, u8>; 32] {
+        let mut data = vec![sys.constant(COMMITMENT_PREFIX)];
+        data.extend_from_slice(&self.secret);
+        data.extend_from_slice(&self.nullifier_seed);
+        data.extend(sys.unpack_le_u64(&self.value));
+        sys.sha256_bytes(&data)
+    }
+
+    /// Computes the nullifier for this note, which is revealed when it is spent. The nullifier
+    /// can't be linked to the commitment without knowing the secret.
+    pub fn nullifier(&self, sys: &mut S) -> [Abstract<S, u8>; 32] {
+        let mut data = vec![sys.constant(NULLIFIER_PREFIX)];
+        data.extend_from_slice(&self.nullifier_seed);
+        data.extend_from_slice(&self.secret);
+        sys.sha256_bytes(&data)
+    }
+}
+
+/// An entry in the authentication path for a leaf in a commitment tree.
+pub struct PathEntry<S: SystemRepr<bool> + SystemRepr<u8> + ?Sized> {
+    /// The sibling of the node on the path at this level.
+    pub sibling: [Abstract<S, u8>; 32],
+
+    /// Whether the sibling is on the left.
+    pub is_left: Abstract<S, bool>,
+}
+
+/// Computes the root of a commitment tree given a leaf and its authentication path, ordered from
+/// the leaf upwards.
+pub fn merkle_root<S: SystemProtocol + ?Sized>(
+    sys: &mut S,
+    leaf: &[Abstract<S, u8>; 32],
+    path: &[PathEntry<S>],
+) -> [Abstract<S, u8>; 32] {
+    let mut node = leaf.clone();
+    for PathEntry { sibling, is_left } in path {
+        let left: [_; 32] = SystemSelect::<[u8; 32]>::select(sys, is_left, sibling, &node);
+        let right: [_; 32] = SystemSelect::<[u8; 32]>::select(sys, is_left, &node, sibling);
+        let mut data = vec![sys.constant(NODE_PREFIX)];
+        data.extend(left);
+        data.extend(right);
+        node = sys.sha256_bytes(&data);
+    }
+    node
+}
+
+/// The parameters of a [`ShieldedTransfer`].
+#[derive(Debug, Clone, Copy)]
+pub struct TransferConfig {
+    /// The depth of the commitment tree.
+    pub depth: usize,
+
+    /// The number of notes spent by a transfer.
+    pub num_inputs: usize,
+
+    /// The number of notes created by a transfer.
+    pub num_outputs: usize,
+
+    /// The number of bits allowed in the value of each note. Values must be below `2^value_bits`.
+    pub value_bits: usize,
+}
+
+/// A note to be spent in a [`ShieldedTransfer`], along with the authentication path for its
+/// commitment.
+pub struct SpendInput<S: SystemRepr<bool> + SystemRepr<u8> + SystemRepr<u64> + ?Sized> {
+    pub note: Note<S>,
+    pub path: Vec<PathEntry<S>>,
+}
+
+/// The public outputs of a [`ShieldedTransfer`].
+pub struct Transfer<S: SystemRepr<bool> + SystemRepr<u8> + ?Sized> {
+    /// The nullifiers of the spent notes.
+    pub nullifiers: Vec<[Abstract<S, u8>; 32]>,
+
+    /// The commitments to the created notes.
+    pub commitments: Vec<[Abstract<S, u8>; 32]>,
+
+    /// Whether all spent notes are in the tree, all values are in range, and the values of the
+    /// spent notes equal the values of the created notes plus the fee.
+    pub valid: Abstract<S, bool>,
+}
+
+/// A gadget which spends a fixed number of notes from a commitment tree and creates a fixed
+/// number of new notes, revealing a public fee.
+pub struct ShieldedTransfer {
+    config: TransferConfig,
+}
+
+impl ShieldedTransfer {
+    /// Constructs a [`ShieldedTransfer`] with the given configuration. Panics if the sum of the
+    /// values on either side of a transfer could overflow a `u64`.
+    pub fn new(config: TransferConfig) -> Self {
+        // The created side also includes the fee
+        let terms = config.num_inputs.max(config.num_outputs + 1);
+        let carry_bits = terms.next_power_of_two().trailing_zeros() as usize;
+        assert!(
+            config.value_bits + carry_bits <= 64,
+            "value bits must leave room for the sum"
+        );
+        Self { config }
+    }
+
+    /// The configuration of this gadget.
+    pub fn config(&self) -> &TransferConfig {
+        &self.config
+    }
+
+    /// Synthesizes a transfer against the given commitment tree root.
+    pub fn synthesize<S: SystemProtocol + ?Sized>(
+        &self,
+        sys: &mut S,
+        root: &[Abstract<S, u8>; 32],
+        inputs: &[SpendInput<S>],
+        outputs: &[Note<S>],
+        fee: &Abstract<S, u64>,
+    ) -> Transfer<S> {
+        let config = &self.config;
+        assert_eq!(inputs.len(), config.num_inputs, "wrong number of inputs");
+        assert_eq!(outputs.len(), config.num_outputs, "wrong number of outputs");
+        let mut valid = SystemRepr::<bool>::constant(sys, true);
+
+        // Check membership of spent notes
+        let mut nullifiers = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            assert_eq!(input.path.len(), config.depth, "wrong path length");
+            let leaf = input.note.commitment(sys);
+            let computed = merkle_root(sys, &leaf, &input.path);
+            let computed: Vec<_> = computed
+                .iter()
+                .flat_map(|byte| sys.bits_of_u8(byte))
+                .collect();
+            let root: Vec<_> = root.iter().flat_map(|byte| sys.bits_of_u8(byte)).collect();
+            let matches = eq_bits(sys, &computed, &root);
+            valid = SystemBitAnd::<bool>::and(sys, &valid, &matches);
+            nullifiers.push(input.note.nullifier(sys));
+        }
+
+        // Check value balance. Since each value is range-checked, and `new` ensures there are
+        // enough spare bits for the number of terms, the sums can't overflow.
+        let commitments = outputs.iter().map(|note| note.commitment(sys)).collect();
+        let mut spent = SystemRepr::<u64>::constant(sys, 0);
+        for input in inputs {
+            let in_range = self.in_range(sys, &input.note.value);
+            valid = SystemBitAnd::<bool>::and(sys, &valid, &in_range);
+            spent = SystemWrappingAdd::<u64>::wrapping_add(sys, &spent, &input.note.value);
+        }
+        let in_range = self.in_range(sys, fee);
+        valid = SystemBitAnd::<bool>::and(sys, &valid, &in_range);
+        let mut created = fee.clone();
+        for note in outputs {
+            let in_range = self.in_range(sys, &note.value);
+            valid = SystemBitAnd::<bool>::and(sys, &valid, &in_range);
+            created = SystemWrappingAdd::<u64>::wrapping_add(sys, &created, &note.value);
+        }
+        let below = SystemOrd::<u64>::le(sys, &spent, &created);
+        let above = SystemOrd::<u64>::le(sys, &created, &spent);
+        let balanced = SystemBitAnd::<bool>::and(sys, &below, &above);
+        valid = SystemBitAnd::<bool>::and(sys, &valid, &balanced);
+        Transfer {
+            nullifiers,
+            commitments,
+            valid,
+        }
+    }
+
+    /// Determines whether a value is below `2^value_bits`.
+    fn in_range<S: SystemProtocol + ?Sized>(
+        &self,
+        sys: &mut S,
+        value: &Abstract<S, u64>,
+    ) -> Abstract<S, bool> {
+        // With a single term on each side, `new` allows every `u64` value
+        let Some(bound) = 1u64.checked_shl(self.config.value_bits as u32) else {
+            return SystemRepr::<bool>::constant(sys, true);
+        };
+        let bound = SystemRepr::<u64>::constant(sys, bound);
+        SystemOrd::<u64>::lt(sys, value, &bound)
+    }
+}
+
+#[cfg(test)]
+fn test_note(index: u8, value: u64) -> Note<Eval> {
+    Note {
+        secret: array_init::array_init(|i| index.wrapping_mul(31).wrapping_add(i as u8)),
+        nullifier_seed: array_init::array_init(|i| index.wrapping_mul(17) ^ (i as u8)),
+        value,
+    }
+}
+
+#[test]
+fn test_shielded_transfer() {
+    let mut sys = Eval;
+
+    // Build a tree of depth 2 with four notes
+    let notes: Vec<_> = [10, 20, 30, 40]
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| test_note(i as u8, value))
+        .collect();
+    let leaves: Vec<_> = notes.iter().map(|note| note.commitment(&mut sys)).collect();
+    let node = |sys: &mut Eval, left: &[u8; 32], right: &[u8; 32]| {
+        let mut data = vec![NODE_PREFIX];
+        data.extend(left);
+        data.extend(right);
+        sys.sha256_bytes(&data)
+    };
+    let left = node(&mut sys, &leaves[0], &leaves[1]);
+    let right = node(&mut sys, &leaves[2], &leaves[3]);
+    let root = node(&mut sys, &left, &right);
+    let path = |index: usize| {
+        let sibling = if index < 2 { right } else { left };
+        vec![
+            PathEntry {
+                sibling: leaves[index ^ 1],
+                is_left: index % 2 == 1,
+            },
+            PathEntry {
+                sibling,
+                is_left: index >= 2,
+            },
+        ]
+    };
+    assert_eq!(merkle_root(&mut sys, &leaves[2], &path(2)), root);
+
+    let transfer = ShieldedTransfer::new(TransferConfig {
+        depth: 2,
+        num_inputs: 2,
+        num_outputs: 2,
+        value_bits: 32,
+    });
+    let spend = |index: usize, value: u64| SpendInput {
+        note: test_note(index as u8, value),
+        path: path(index),
+    };
+    let outputs = || [test_note(10, 25), test_note(11, 20)];
+    let res = transfer.synthesize(
+        &mut sys,
+        &root,
+        &[spend(1, 20), spend(2, 30)],
+        &outputs(),
+        &5,
+    );
+    assert!(res.valid);
+    assert_eq!(res.nullifiers[0], notes[1].nullifier(&mut sys));
+    assert_ne!(res.nullifiers[0], res.nullifiers[1]);
+    assert_eq!(res.commitments[1], test_note(11, 20).commitment(&mut sys));
+
+    // Values must balance
+    let res = transfer.synthesize(
+        &mut sys,
+        &root,
+        &[spend(1, 20), spend(2, 30)],
+        &outputs(),
+        &6,
+    );
+    assert!(!res.valid);
+
+    // Notes must be in the tree, with their committed values
+    let res = transfer.synthesize(
+        &mut sys,
+        &root,
+        &[spend(1, 21), spend(2, 29)],
+        &outputs(),
+        &5,
+    );
+    assert!(!res.valid);
+
+    // Values must be in range, so they can't wrap around
+    let res = transfer.synthesize(
+        &mut sys,
+        &root,
+        &[spend(1, 20), spend(2, 30)],
+        &[test_note(10, 1 << 32), test_note(11, 45)],
+        &5u64.wrapping_sub(1 << 32),
+    );
+    assert!(!res.valid);
+}
+
+#[test]
+fn test_shielded_transfer_overflow() {
+    let mut sys = Eval;
+    let note = test_note(0, 10);
+    let root = note.commitment(&mut sys);
+    let transfer = ShieldedTransfer::new(TransferConfig {
+        depth: 0,
+        num_inputs: 1,
+        num_outputs: 3,
+        value_bits: 62,
+    });
+    let max = (1 << 62) - 1;
+    let spend = SpendInput {
+        note,
+        path: Vec::new(),
+    };
+    let res = transfer.synthesize(
+        &mut sys,
+        &root,
+        &[spend],
+        &[test_note(1, max), test_note(2, max), test_note(3, max)],
+        &max,
+    );
+    assert!(!res.valid);
+}
+
+#[test]
+fn test_shielded_transfer_full_range() {
+    // A single input and no outputs leave room for the full range of `u64`
+    let mut sys = Eval;
+    let note = test_note(0, u64::MAX);
+    let root = note.commitment(&mut sys);
+    let transfer = ShieldedTransfer::new(TransferConfig {
+        depth: 0,
+        num_inputs: 1,
+        num_outputs: 0,
+        value_bits: 64,
+    });
+    let spend = || SpendInput {
+        note: test_note(0, u64::MAX),
+        path: Vec::new(),
+    };
+    let res = transfer.synthesize(&mut sys, &root, &[spend()], &[], &u64::MAX);
+    assert!(res.valid);
+    let res = transfer.synthesize(&mut sys, &root, &[spend()], &[], &(u64::MAX - 1));
+    assert!(!res.valid);
+}
+
+#[test]
+#[should_panic(expected = "room for the sum")]
+fn test_shielded_transfer_overflow_config() {
+    // Four outputs of `2^62 - 1` along with a fee could wrap the sum back around to a small value
+    ShieldedTransfer::new(TransferConfig {
+        depth: 0,
+        num_inputs: 1,
+        num_outputs: 4,
+        value_bits: 62,
+    });
+}
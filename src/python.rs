@@ -0,0 +1,706 @@
+//! `pyo3` bindings for building circuits and exporting them from Python, so that circuits can be
+//! scripted without writing Rust. To produce an importable `circus` module, link this crate into
+//! a `cdylib` with the `extension-module` feature of `pyo3` enabled, as done by `maturin`.
+//!
+//! A `Circuit` wraps a [`DynSystem`] over one of two backends: `"graph"`, a boolean circuit built
+//! by [`BinaryEmulate`] over a [`Graph`], or `"r1cs"`, an [`ArithmeticSystem`] over the
+//! BLS12-381 scalar field. Values have one of the types `"bool"`, `"u32"`, `"u64"` and `"field"`,
+//! as in [`DynType`], and are combined using the operations of [`DynOp`], named in lowercase.
+//! Inputs and outputs are named, and are described by a [`Manifest`] when the circuit is
+//! exported. Witnesses assign every wire of the manifest, as for the `capi` feature.
+
+// The code generated by `pymethods` converts every returned error into a `PyErr`
+#![allow(clippy::useless_conversion)]
+use crate::crypto::hash::{PoseidonParams, SystemSha256};
+use crate::erased::*;
+use crate::graph::{Graph, Node, SubcircuitTemplate};
+use crate::merkle::MerkleAccumulator;
+use crate::r1cs::{ArithmeticSystem, Formula, Variable, Word};
+use crate::*;
+use bls12_381::Scalar;
+use ff::PrimeField;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+
+/// The system underlying a `graph` circuit.
+type GraphSystem = BinaryEmulate<Graph>;
+
+/// The system underlying an `r1cs` circuit.
+type R1csSystem = ArithmeticSystem<Scalar>;
+
+/// The backend of a [`PyCircuit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Graph,
+    R1cs,
+}
+
+/// A named input or output of a [`PyCircuit`], and the wires carrying it. For a graph, input
+/// wires are input indices and output wires are nodes of the graph as built. For an arithmetic
+/// system, wires are variables.
+#[derive(Debug, Clone)]
+struct PyPort {
+    name: String,
+    ty: DynType,
+    len: usize,
+    wires: Vec<usize>,
+}
+
+/// A circuit being built from Python.
+#[pyclass(name = "Circuit", unsendable)]
+pub struct PyCircuit {
+    sys: DynSystem<Scalar>,
+    backend: Backend,
+    inputs: Vec<PyPort>,
+    outputs: Vec<PyPort>,
+}
+
+/// An abstract value in a [`PyCircuit`].
+#[pyclass(name = "Value", unsendable)]
+#[derive(Clone)]
+pub struct PyValue {
+    value: DynValue,
+}
+
+#[pymethods]
+impl PyValue {
+    /// The type of this value.
+    #[getter]
+    fn ty(&self) -> &'static str {
+        type_name(self.value.ty())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Value({})", type_name(self.value.ty()))
+    }
+}
+
+#[pymethods]
+impl PyCircuit {
+    /// Constructs an empty circuit for the given backend, either `"graph"` or `"r1cs"`.
+    #[new]
+    #[pyo3(signature = (backend = "graph"))]
+    fn new(backend: &str) -> PyResult<Self> {
+        let (sys, backend) = match backend {
+            "graph" => (
+                DynSystem::builder(GraphSystem::new(Graph::new()))
+                    .with_bool()
+                    .with_u32()
+                    .with_u64()
+                    .build(),
+                Backend::Graph,
+            ),
+            "r1cs" => (
+                DynSystem::builder(R1csSystem::new())
+                    .with_bool()
+                    .with_u32()
+                    .with_field()
+                    .with_assert()
+                    .build(),
+                Backend::R1cs,
+            ),
+            _ => return Err(error(format!("unknown backend: {}", backend))),
+        };
+        Ok(Self {
+            sys,
+            backend,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        })
+    }
+
+    /// The backend of this circuit.
+    #[getter]
+    fn backend(&self) -> &'static str {
+        match self.backend {
+            Backend::Graph => "graph",
+            Backend::R1cs => "r1cs",
+        }
+    }
+
+    /// Declares a named input consisting of `len` values of the given type.
+    #[pyo3(signature = (name, ty, len = 1))]
+    fn input(&mut self, name: &str, ty: &str, len: usize) -> PyResult<Vec<PyValue>> {
+        let ty = parse_type(ty)?;
+        self.check_name(name)?;
+        if !self.sys.supports(ty) {
+            return Err(error(DynError::UnsupportedType(ty)));
+        }
+        let mut wires = Vec::new();
+        let mut values = Vec::new();
+        for _ in 0..len {
+            let value = match self.backend {
+                Backend::Graph => self.graph_input(ty, &mut wires),
+                Backend::R1cs => self.r1cs_input(ty, &mut wires),
+            };
+            values.push(PyValue {
+                value: value.map_err(error)?,
+            });
+        }
+        self.inputs.push(PyPort {
+            name: name.to_owned(),
+            ty,
+            len,
+            wires,
+        });
+        Ok(values)
+    }
+
+    /// Introduces a constant of the given type. Field elements are given as non-negative
+    /// integers less than `2^128`.
+    fn constant(&mut self, ty: &str, value: u128) -> PyResult<PyValue> {
+        let value = match parse_type(ty)? {
+            DynType::Bool => DynConst::Bool(value != 0),
+            DynType::U32 => DynConst::U32(convert(value)?),
+            DynType::U64 => DynConst::U64(convert(value)?),
+            DynType::Field => DynConst::Field(field_from_u128(value)),
+        };
+        wrap(self.sys.constant(value))
+    }
+
+    /// Applies an operation, one of `"and"`, `"or"`, `"xor"`, `"not"`, `"add"` and `"mul"`, to
+    /// the given operands, which must all have the same type.
+    fn apply(&mut self, op: &str, args: Vec<PyRef<'_, PyValue>>) -> PyResult<PyValue> {
+        let op = match op {
+            "and" => DynOp::And,
+            "or" => DynOp::Or,
+            "xor" => DynOp::Xor,
+            "not" => DynOp::Not,
+            "add" => DynOp::Add,
+            "mul" => DynOp::Mul,
+            _ => return Err(error(format!("unknown operation: {}", op))),
+        };
+        let args: Vec<&DynValue> = args.iter().map(|arg| &arg.value).collect();
+        wrap(self.sys.apply(op, &args))
+    }
+
+    /// Returns `a` if `cond` is true, or `b` otherwise.
+    fn select(&mut self, cond: &PyValue, a: &PyValue, b: &PyValue) -> PyResult<PyValue> {
+        wrap(self.sys.select(&cond.value, &a.value, &b.value))
+    }
+
+    /// Asserts that the given boolean value is true.
+    fn assert_true(&mut self, value: &PyValue) -> PyResult<()> {
+        self.sys.assert(&value.value).map_err(error)
+    }
+
+    /// Determines whether `a` is less than `b`, where both are integers of the same type.
+    fn less_than(&mut self, a: &PyValue, b: &PyValue) -> PyResult<PyValue> {
+        let ty = a.value.ty();
+        expect_type(&b.value, ty)?;
+        match (self.backend, ty) {
+            (Backend::Graph, DynType::U32) => self.lt::<GraphSystem, u32>(a, b),
+            (Backend::Graph, DynType::U64) => self.lt::<GraphSystem, u64>(a, b),
+            (Backend::R1cs, DynType::U32) => self.lt::<R1csSystem, u32>(a, b),
+            _ => Err(error(format!(
+                "values of type {} can't be compared",
+                type_name(ty)
+            ))),
+        }
+    }
+
+    /// Applies the SHA-256 compression function to the given `u32` words, 16 for each block, and
+    /// returns the 8 words of the resulting state. Padding is the responsibility of the caller.
+    fn sha256(&mut self, words: Vec<PyRef<'_, PyValue>>) -> PyResult<Vec<PyValue>> {
+        if !words.len().is_multiple_of(16) {
+            return Err(error("the number of words must be a multiple of 16"));
+        }
+        for word in words.iter() {
+            expect_type(&word.value, DynType::U32)?;
+        }
+        let words: Vec<&DynValue> = words.iter().map(|word| &word.value).collect();
+        let state = match self.backend {
+            Backend::Graph => self.sha256_in::<GraphSystem>(&words),
+            Backend::R1cs => self.sha256_in::<R1csSystem>(&words),
+        };
+        state.into_iter().map(wrap).collect()
+    }
+
+    /// Computes the root of a Merkle tree hashed with Poseidon, from a `field` leaf and its
+    /// authentication path. `index` gives the position of the leaf as little-endian `bool`
+    /// values, and `siblings` are ordered from the bottom up. See
+    /// [`MerkleAccumulator::path_root`].
+    fn merkle_root(
+        &mut self,
+        leaf: &PyValue,
+        index: Vec<PyRef<'_, PyValue>>,
+        siblings: Vec<PyRef<'_, PyValue>>,
+    ) -> PyResult<PyValue> {
+        if self.backend != Backend::R1cs {
+            return Err(error("merkle_root requires the r1cs backend"));
+        }
+        if index.len() != siblings.len() {
+            return Err(error("path lengths must match"));
+        }
+        expect_type(&leaf.value, DynType::Field)?;
+        let index: Vec<Formula> = (index.iter())
+            .map(|bit| downcast(&bit.value, DynType::Bool))
+            .collect::<PyResult<_>>()?;
+        let siblings: Vec<Formula> = (siblings.iter())
+            .map(|node| downcast(&node.value, DynType::Field))
+            .collect::<PyResult<_>>()?;
+        let leaf: Formula = downcast(&leaf.value, DynType::Field)?;
+        let acc = MerkleAccumulator::<PoseidonParams<Scalar>>::new(siblings.len(), 0);
+        let sys = self.system_mut::<R1csSystem>();
+        let root = acc.path_root(sys, &leaf, &index, &siblings);
+        wrap(self.sys.wrap(DynType::Field, root))
+    }
+
+    /// Declares a named output carrying the given values, which must all have the same type.
+    fn output(&mut self, name: &str, values: Vec<PyRef<'_, PyValue>>) -> PyResult<()> {
+        self.check_name(name)?;
+        let Some(first) = values.first() else {
+            return Err(error("an output must have at least one value"));
+        };
+        let ty = first.value.ty();
+        let mut wires = Vec::new();
+        for value in values.iter() {
+            expect_type(&value.value, ty)?;
+            match self.backend {
+                Backend::Graph => wires.extend(graph_wires(&value.value)),
+                Backend::R1cs => {
+                    let formulas = r1cs_formulas(self.system_mut::<R1csSystem>(), &value.value);
+                    for formula in formulas {
+                        wires.push(materialize(self.system_mut::<R1csSystem>(), formula));
+                    }
+                }
+            }
+        }
+        self.outputs.push(PyPort {
+            name: name.to_owned(),
+            ty,
+            len: values.len(),
+            wires,
+        });
+        Ok(())
+    }
+
+    /// Describes the inputs and outputs of this circuit as JSON, in the format of
+    /// [`Manifest::to_json`]. For a graph, wires are the nodes of the exported circuit.
+    fn manifest(&self) -> String {
+        match self.backend {
+            Backend::Graph => self.graph_manifest(&self.template()).to_json(),
+            Backend::R1cs => self.r1cs_manifest().to_json(),
+        }
+    }
+
+    /// Returns the exported graph in the Graphviz DOT language.
+    fn to_dot(&self) -> PyResult<String> {
+        self.expect_backend(Backend::Graph)?;
+        Ok(self.template().graph().to_dot())
+    }
+
+    /// Writes the exported graph to a file in the format of [`crate::ir::save`].
+    fn write_ir(&self, path: &str) -> PyResult<()> {
+        self.expect_backend(Backend::Graph)?;
+        Ok(crate::ir::save(path, &self.template())?)
+    }
+
+    /// Writes the constraints of the arithmetic system to a file in the format of
+    /// [`ArithmeticSystem::save`].
+    fn write_r1cs(&self, path: &str) -> PyResult<()> {
+        self.expect_backend(Backend::R1cs)?;
+        let writer = BufWriter::new(File::create(path)?);
+        Ok(self.system::<R1csSystem>().save(writer, &[])?)
+    }
+
+    /// Generates a witness from the values of every input, given as a dictionary from input
+    /// names to lists of integers. For a graph, the witness gives the value of each node of the
+    /// exported circuit as a `bool`. For an arithmetic system, it gives the value of each
+    /// variable as 32 little-endian bytes.
+    fn witness(&self, py: Python<'_>, inputs: HashMap<String, Vec<u128>>) -> PyResult<PyObject> {
+        for port in self.inputs.iter() {
+            match inputs.get(&port.name) {
+                Some(values) if values.len() == port.len => (),
+                Some(_) => {
+                    return Err(error(format!(
+                        "expected {} values for {}",
+                        port.len, port.name
+                    )))
+                }
+                None => return Err(error(format!("missing input {}", port.name))),
+            }
+        }
+        if let Some(name) = inputs.keys().find(|name| !self.is_input(name)) {
+            return Err(error(format!("unknown input {}", name)));
+        }
+        match self.backend {
+            Backend::Graph => {
+                let template = self.template();
+                let mut bits = vec![false; template.num_inputs()];
+                for port in self.inputs.iter() {
+                    let bits_per_value = port.wires.len() / port.len;
+                    for (i, value) in inputs[&port.name].iter().enumerate() {
+                        let wires = &port.wires[i * bits_per_value..][..bits_per_value];
+                        for (j, wire) in wires.iter().enumerate() {
+                            bits[*wire] = (value >> j) & 1 == 1;
+                        }
+                    }
+                }
+                let graph = template.graph();
+                let nodes: Vec<_> = (0..graph.nodes().len()).map(Node::from_index).collect();
+                Ok(graph.replay(&mut Eval, &bits, &nodes).into_py(py))
+            }
+            Backend::R1cs => {
+                let mut known = Vec::new();
+                for port in self.inputs.iter() {
+                    let values = &inputs[&port.name];
+                    for (wire, value) in port.wires.iter().zip(values) {
+                        known.push((Variable::from_index(*wire), field_from_u128(*value)));
+                    }
+                }
+                let sys = self.system::<R1csSystem>();
+                let assignment = sys.solve(&known).map_err(error)?;
+                let values: Vec<_> = (assignment.iter())
+                    .map(|value| PyBytes::new_bound(py, value.to_repr().as_ref()))
+                    .collect();
+                Ok(values.into_py(py))
+            }
+        }
+    }
+}
+
+impl PyCircuit {
+    /// Gets the underlying system, which must have type `S`.
+    fn system<S: 'static>(&self) -> &S {
+        self.sys.system().expect("wrong system type")
+    }
+
+    /// Gets the underlying system mutably, which must have type `S`.
+    fn system_mut<S: 'static>(&mut self) -> &mut S {
+        self.sys.system_mut().expect("wrong system type")
+    }
+
+    /// Checks that this circuit uses the given backend.
+    fn expect_backend(&self, backend: Backend) -> PyResult<()> {
+        if self.backend != backend {
+            return Err(error(format!(
+                "operation requires the {:?} backend",
+                backend
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that no input or output with the given name exists.
+    fn check_name(&self, name: &str) -> PyResult<()> {
+        if self.is_input(name) || self.outputs.iter().any(|port| port.name == name) {
+            return Err(error(format!("duplicate port {}", name)));
+        }
+        Ok(())
+    }
+
+    /// Determines whether an input with the given name exists.
+    fn is_input(&self, name: &str) -> bool {
+        self.inputs.iter().any(|port| port.name == name)
+    }
+
+    /// Declares an input value in a graph, adding its wires to `wires`.
+    fn graph_input(&mut self, ty: DynType, wires: &mut Vec<usize>) -> Result<DynValue, DynError> {
+        let graph = self.system_mut::<GraphSystem>().source_mut();
+        let mut input = || {
+            wires.push(graph.num_inputs());
+            graph.input()
+        };
+        match ty {
+            DynType::Bool => {
+                let bit = input();
+                self.sys.wrap(ty, bit)
+            }
+            DynType::U32 => {
+                let bits: [Node; 32] = array_init::array_init(|_| input());
+                self.sys.wrap(ty, bits)
+            }
+            DynType::U64 => {
+                let bits: [Node; 64] = array_init::array_init(|_| input());
+                self.sys.wrap(ty, bits)
+            }
+            DynType::Field => Err(DynError::UnsupportedType(ty)),
+        }
+    }
+
+    /// Declares an input value in an arithmetic system, adding its variable to `wires`.
+    fn r1cs_input(&mut self, ty: DynType, wires: &mut Vec<usize>) -> Result<DynValue, DynError> {
+        let sys = self.system_mut::<R1csSystem>();
+        match ty {
+            DynType::Bool => {
+                let bit = sys.declare_bool();
+                wires.push(sys.num_vars() - 1);
+                self.sys.wrap(ty, bit)
+            }
+            DynType::U32 => {
+                let var = sys.declare();
+                wires.push(var.index());
+                let bits = sys.decompose(var.into(), 32, false);
+                let word: Word = sys.word_of_bits(&array_init::array_init(|i| bits[i]));
+                self.sys.wrap(ty, word)
+            }
+            DynType::Field => {
+                let var = sys.declare();
+                wires.push(var.index());
+                self.sys.wrap(ty, Formula::from(var))
+            }
+            DynType::U64 => Err(DynError::UnsupportedType(ty)),
+        }
+    }
+
+    /// Determines whether `a` is less than `b` in the underlying system `S`.
+    fn lt<S: SystemOrd<T> + 'static, T>(&mut self, a: &PyValue, b: &PyValue) -> PyResult<PyValue>
+    where
+        Abstract<S, T>: 'static,
+        Abstract<S, bool>: 'static,
+    {
+        let a = a.value.downcast_ref().expect("wrong value type");
+        let b = b.value.downcast_ref().expect("wrong value type");
+        let res = SystemOrd::<T>::lt(self.system_mut::<S>(), a, b);
+        wrap(self.sys.wrap(DynType::Bool, res))
+    }
+
+    /// Applies the SHA-256 compression function in the underlying system `S`.
+    fn sha256_in<S: SystemSha256 + 'static>(
+        &mut self,
+        words: &[&DynValue],
+    ) -> Vec<Result<DynValue, DynError>>
+    where
+        Abstract<S, u32>: 'static,
+    {
+        let words: Vec<Abstract<S, u32>> = (words.iter())
+            .map(|word| {
+                word.downcast_ref::<Abstract<S, u32>>()
+                    .expect("wrong value type")
+            })
+            .cloned()
+            .collect();
+        let sys = self.system_mut::<S>();
+        let mut hasher = sys.sha256_new();
+        for block in words.chunks(16) {
+            let block: [_; 16] = array_init::array_init(|i| block[i].clone());
+            sys.sha256_update_abstract(&mut hasher, &block);
+        }
+        (hasher.into_iter())
+            .map(|word| self.sys.wrap(DynType::U32, word))
+            .collect()
+    }
+
+    /// Captures the graph of this circuit as a flattened template, with one output for each
+    /// output wire, in order. Nodes which don't contribute to the outputs are discarded.
+    fn template(&self) -> SubcircuitTemplate {
+        let graph = self.system::<GraphSystem>().source();
+        let outputs: Vec<Node> = (self.outputs.iter())
+            .flat_map(|port| port.wires.iter().map(|wire| Node::from_index(*wire)))
+            .collect();
+        SubcircuitTemplate::capture(graph.num_inputs(), |target, inputs| {
+            graph.replay(target, inputs, &outputs)
+        })
+        .flatten()
+    }
+
+    /// Constructs the manifest for the given template of this circuit. Inputs of the template
+    /// are its first nodes.
+    fn graph_manifest(&self, template: &SubcircuitTemplate) -> Manifest {
+        let mut manifest = Manifest::new();
+        for port in self.inputs.iter() {
+            manifest.input(&port.name, port_type(port.ty), port.len, port.wires.clone());
+        }
+        let mut outputs = template.outputs().iter();
+        for port in self.outputs.iter() {
+            let wires = (0..port.wires.len()).map(|_| outputs.next().unwrap().index());
+            manifest.output(&port.name, port_type(port.ty), port.len, wires);
+        }
+        manifest
+    }
+
+    /// Constructs the manifest for this circuit, whose wires are variables.
+    fn r1cs_manifest(&self) -> Manifest {
+        let mut manifest = Manifest::new();
+        for port in self.inputs.iter() {
+            manifest.input(&port.name, port_type(port.ty), port.len, port.wires.clone());
+        }
+        for port in self.outputs.iter() {
+            manifest.output(&port.name, port_type(port.ty), port.len, port.wires.clone());
+        }
+        manifest
+    }
+}
+
+/// Gets the nodes carrying a value in a graph.
+fn graph_wires(value: &DynValue) -> Vec<usize> {
+    let nodes: &[Node] = match value.ty() {
+        DynType::Bool => std::slice::from_ref(value.downcast_ref::<Node>().unwrap()),
+        DynType::U32 => value.downcast_ref::<[Node; 32]>().unwrap(),
+        DynType::U64 => value.downcast_ref::<[Node; 64]>().unwrap(),
+        DynType::Field => unreachable!(),
+    };
+    nodes.iter().map(|node| node.index()).collect()
+}
+
+/// Gets the formulas for a value in an arithmetic system, one for each wire.
+fn r1cs_formulas(sys: &mut R1csSystem, value: &DynValue) -> Vec<Formula> {
+    match value.ty() {
+        DynType::Bool | DynType::Field => vec![*value.downcast_ref::<Formula>().unwrap()],
+        DynType::U32 => vec![sys.word_value(value.downcast_ref::<Word>().unwrap())],
+        DynType::U64 => unreachable!(),
+    }
+}
+
+/// Gets a variable equal to the given formula, introducing one if needed.
+fn materialize(sys: &mut R1csSystem, formula: Formula) -> usize {
+    let dim = sys.formula(formula).dim();
+    if dim > 0 {
+        let var = Variable::from_index(dim - 1);
+        if *sys.formula(formula) == *sys.formula(var.into()) {
+            return var.index();
+        }
+    }
+    let var = sys.declare();
+    let diff = sys.linear_combination(&[(Scalar::one(), formula), (-Scalar::one(), var.into())]);
+    sys.assert_zero(diff);
+    var.index()
+}
+
+/// Gets the abstract value underlying a value of the given type.
+fn downcast<A: Clone + 'static>(value: &DynValue, ty: DynType) -> PyResult<A> {
+    expect_type(value, ty)?;
+    Ok(value.downcast_ref::<A>().expect("wrong value type").clone())
+}
+
+/// Checks that a value has the given type.
+fn expect_type(value: &DynValue, ty: DynType) -> PyResult<()> {
+    if value.ty() != ty {
+        return Err(error(DynError::TypeMismatch {
+            expected: ty,
+            actual: value.ty(),
+        }));
+    }
+    Ok(())
+}
+
+/// Parses the name of a type.
+fn parse_type(name: &str) -> PyResult<DynType> {
+    DynType::ALL
+        .into_iter()
+        .find(|ty| type_name(*ty) == name)
+        .ok_or_else(|| error(format!("unknown type: {}", name)))
+}
+
+/// The name of a type, as used in Python.
+fn type_name(ty: DynType) -> &'static str {
+    match ty {
+        DynType::Bool => "bool",
+        DynType::U32 => "u32",
+        DynType::U64 => "u64",
+        DynType::Field => "field",
+    }
+}
+
+/// The [`PortType`] for values of the given type.
+fn port_type(ty: DynType) -> PortType {
+    match ty {
+        DynType::Bool => PortType::Bool,
+        DynType::U32 => PortType::UInt(32),
+        DynType::U64 => PortType::UInt(64),
+        DynType::Field => PortType::Field(Scalar::NUM_BITS),
+    }
+}
+
+/// Converts an integer to a narrower type, failing if it is out of range.
+fn convert<T: TryFrom<u128>>(value: u128) -> PyResult<T> {
+    T::try_from(value).map_err(|_| error(format!("{} is out of range", value)))
+}
+
+/// Converts an integer to a field element.
+fn field_from_u128(value: u128) -> Scalar {
+    let shift = Scalar::from(u64::MAX) + Scalar::one();
+    Scalar::from((value >> 64) as u64) * shift + Scalar::from(value as u64)
+}
+
+/// Wraps the result of an operation on a [`DynSystem`].
+fn wrap(res: Result<DynValue, DynError>) -> PyResult<PyValue> {
+    res.map(|value| PyValue { value }).map_err(error)
+}
+
+/// Constructs a Python `ValueError` with the given message.
+fn error(err: impl ToString) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+/// The `circus` Python module.
+#[pymodule]
+fn circus(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyCircuit>()?;
+    m.add_class::<PyValue>()?;
+    Ok(())
+}
+
+#[test]
+fn test_python() {
+    use crate::field::FieldElement;
+    use pyo3::types::PyDict;
+    let acc = MerkleAccumulator::<PoseidonParams<Scalar>>::new(2, 0);
+    let [leaf, left, right] = [1, 2, 3].map(|x| FieldElement(Scalar::from(x)));
+    let root = acc.path_root(&mut Eval, &leaf, &[true, false], &[left, right]);
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let module = PyModule::new_bound(py, "circus").unwrap();
+        circus(&module).unwrap();
+        let globals = PyDict::new_bound(py);
+        globals.set_item("circus", module).unwrap();
+        let root = PyBytes::new_bound(py, root.0.to_repr().as_ref());
+        globals.set_item("expected_root", root).unwrap();
+        let code = r#"
+import json
+
+def port(circuit, name):
+    return next(p for p in json.loads(circuit.manifest())["ports"] if p["name"] == name)
+
+def bits_value(witness, wires):
+    return sum(int(witness[wire]) << i for i, wire in enumerate(wires))
+
+# Integer arithmetic, comparisons and SHA-256 in a boolean circuit
+c = circus.Circuit("graph")
+a, b = c.input("a", "u32", 2)
+c.output("sum", [c.apply("add", [a, b])])
+c.output("lt", [c.less_than(a, b), c.less_than(b, a)])
+words = c.input("block", "u32", 16)
+c.output("state", c.sha256(words))
+assert c.to_dot().startswith("digraph")
+w = c.witness({"a": [5, 7], "block": [0] * 16})
+assert bits_value(w, port(c, "sum")["wires"]) == 12
+assert bits_value(w, port(c, "lt")["wires"]) == 0b01
+wires = port(c, "state")["wires"]
+state = [bits_value(w, wires[i * 32:(i + 1) * 32]) for i in range(8)]
+assert state[0] == 0xda5698be, hex(state[0])
+try:
+    c.input("x", "field")
+    assert False
+except ValueError as err:
+    assert "Field" in str(err)
+
+# The same hash and a Merkle path in an arithmetic system
+c = circus.Circuit("r1cs")
+words = c.input("block", "u32", 16)
+c.output("state", c.sha256(words))
+(leaf,) = c.input("leaf", "field")
+index = c.input("index", "bool", 2)
+siblings = c.input("siblings", "field", 2)
+c.output("root", [c.merkle_root(leaf, index, siblings)])
+c.output("lt", [c.less_than(words[0], words[1])])
+w = c.witness({"block": [0] * 16, "leaf": [1], "index": [1, 0], "siblings": [2, 3]})
+values = [int.from_bytes(w[wire], "little") for wire in port(c, "state")["wires"]]
+assert values == state
+assert w[port(c, "root")["wires"][0]] == expected_root
+assert w[port(c, "lt")["wires"][0]] == bytes(32)
+assert port(c, "root")["type"] == "field"
+try:
+    c.witness({"block": [0] * 16})
+    assert False
+except ValueError as err:
+    assert "missing input" in str(err)
+"#;
+        py.run_bound(code, Some(&globals), None).unwrap();
+    });
+}
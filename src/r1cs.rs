@@ -1,17 +1,60 @@
+use crate::field::FieldElement;
 use crate::*;
 use ff::{Field, PrimeField};
+use smallvec::{smallvec, SmallVec};
+use std::borrow::{Borrow, Cow};
 use std::cmp::max;
-use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::ops::{Add, Sub};
 
+mod budget;
+mod checkpoint;
+mod dedup;
+mod finalize;
+mod lint;
+mod parallel;
+mod ram;
+mod stream;
+mod witness;
+mod word;
+
+pub use budget::*;
+pub use dedup::*;
+pub use finalize::*;
+pub use lint::*;
+pub use ram::*;
+pub use stream::*;
+pub use witness::*;
+pub use word::*;
+
 /// An indexed variable within a constraint system.
-pub struct Variable(usize);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Variable(u32);
+
+impl Variable {
+    /// The index of this variable within its system.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// The variable with the given index.
+    pub(crate) fn from_index(index: usize) -> Self {
+        Self(u32::try_from(index).expect("too many variables"))
+    }
+}
+
+/// The number of terms a [`LinearFormula`] can hold before it needs a heap allocation. Bit-level
+/// gadgets produce huge numbers of formulas with only a few terms, so this avoids most
+/// allocations.
+const INLINE_TERMS: usize = 3;
 
 /// A linear combination of indexed variables.
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LinearFormula<F> {
     constant_term: F,
-    coeffs: BTreeMap<usize, F>,
+
+    /// The coefficients of the formula, sorted by variable index. All coefficients are non-zero.
+    coeffs: SmallVec<[(u32, F); INLINE_TERMS]>,
 }
 
 impl<F> LinearFormula<F> {
@@ -19,39 +62,152 @@ impl<F> LinearFormula<F> {
     pub fn constant(value: F) -> Self {
         LinearFormula {
             constant_term: value,
-            coeffs: BTreeMap::new(),
+            coeffs: SmallVec::new(),
         }
     }
 
     /// One more than the last variable index referenced in this formula.
     pub fn dim(&self) -> usize {
-        self.coeffs.keys().rev().next().map(|k| k + 1).unwrap_or(0)
+        self.coeffs
+            .last()
+            .map(|(k, _)| *k as usize + 1)
+            .unwrap_or(0)
+    }
+}
+
+impl<F: Field> LinearFormula<F> {
+    /// Multiplies all terms of this formula by the given constant.
+    pub fn scale(&self, factor: F) -> Self {
+        if factor.is_zero_vartime() {
+            return LinearFormula::constant(F::zero());
+        }
+        LinearFormula {
+            constant_term: self.constant_term * factor,
+            coeffs: self.coeffs.iter().map(|(k, v)| (*k, *v * factor)).collect(),
+        }
+    }
+
+    /// Evaluates this formula for the given assignment of variable values.
+    pub fn eval(&self, assignment: &[F]) -> F {
+        self.coeffs.iter().fold(self.constant_term, |acc, (k, v)| {
+            acc + assignment[*k as usize] * v
+        })
+    }
+
+    /// Constructs the sum of the given formulas, each multiplied by the corresponding factor. Unlike
+    /// repeated addition, this merges all terms in a single pass.
+    fn combine<B: Borrow<LinearFormula<F>>>(terms: impl IntoIterator<Item = (F, B)>) -> Self {
+        let mut constant_term = F::zero();
+        let mut all_coeffs: SmallVec<[(u32, F); INLINE_TERMS]> = SmallVec::new();
+        for (factor, formula) in terms {
+            let formula = formula.borrow();
+            constant_term += formula.constant_term * factor;
+            all_coeffs.extend(formula.coeffs.iter().map(|(k, v)| (*k, *v * factor)));
+        }
+        all_coeffs.sort_unstable_by_key(|(k, _)| *k);
+        let mut coeffs: SmallVec<[(u32, F); INLINE_TERMS]> = SmallVec::new();
+        for (k, v) in all_coeffs {
+            match coeffs.last_mut() {
+                Some((last_k, last_v)) if *last_k == k => *last_v += v,
+                _ => coeffs.push((k, v)),
+            }
+        }
+        coeffs.retain(|(_, v)| !v.is_zero_vartime());
+        LinearFormula {
+            constant_term,
+            coeffs,
+        }
+    }
+
+    /// Adds the given multiple of `rhs` to this formula.
+    fn add_scaled(&mut self, rhs: &LinearFormula<F>, factor: F) {
+        self.constant_term += rhs.constant_term * factor;
+        let lhs = std::mem::take(&mut self.coeffs);
+        let (mut i, mut j) = (0, 0);
+        while i < lhs.len() || j < rhs.coeffs.len() {
+            let (k, v) = match (lhs.get(i), rhs.coeffs.get(j)) {
+                (Some(l), Some(r)) if l.0 == r.0 => {
+                    i += 1;
+                    j += 1;
+                    (l.0, l.1 + r.1 * factor)
+                }
+                (Some(l), Some(r)) if l.0 > r.0 => {
+                    j += 1;
+                    (r.0, r.1 * factor)
+                }
+                (Some(l), _) => {
+                    i += 1;
+                    *l
+                }
+                (None, Some(r)) => {
+                    j += 1;
+                    (r.0, r.1 * factor)
+                }
+                (None, None) => unreachable!(),
+            };
+            if !v.is_zero_vartime() {
+                self.coeffs.push((k, v));
+            }
+        }
+    }
+}
+
+impl<F: Field> From<Variable> for LinearFormula<F> {
+    fn from(var: Variable) -> Self {
+        LinearFormula {
+            constant_term: F::zero(),
+            coeffs: smallvec![(var.0, F::one())],
+        }
     }
 }
 
-impl<F: Add<F, Output = F>> Add<&LinearFormula<F>> for &LinearFormula<F> {
+impl<F: Field> Add<&LinearFormula<F>> for &LinearFormula<F> {
     type Output = LinearFormula<F>;
     fn add(self, rhs: &LinearFormula<F>) -> Self::Output {
-        todo!()
+        let mut res = self.clone();
+        res.add_scaled(rhs, F::one());
+        res
     }
 }
 
-impl<F: Add<F, Output = F>> Sub<&LinearFormula<F>> for &LinearFormula<F> {
+impl<F: Field> Sub<&LinearFormula<F>> for &LinearFormula<F> {
     type Output = LinearFormula<F>;
     fn sub(self, rhs: &LinearFormula<F>) -> Self::Output {
-        todo!()
+        let mut res = self.clone();
+        res.add_scaled(rhs, -F::one());
+        res
     }
 }
 
 /// A constraint which specifies that the product of two [`LinearFormula`] is a particular
 /// [`LinearFormula`].
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ProductConstraint<F> {
     pub operand_a: LinearFormula<F>,
     pub operand_b: LinearFormula<F>,
     pub result: LinearFormula<F>,
 }
 
+impl<F: Field> ProductConstraint<F> {
+    /// Determines whether the given assignment of variable values satisfies this constraint.
+    pub fn is_satisfied(&self, assignment: &[F]) -> bool {
+        self.operand_a.eval(assignment) * self.operand_b.eval(assignment)
+            == self.result.eval(assignment)
+    }
+}
+
 impl<F> ProductConstraint<F> {
+    /// The variables referenced by this constraint, in order of index.
+    pub fn vars(&self) -> Vec<Variable> {
+        let mut res: Vec<_> = [&self.operand_a, &self.operand_b, &self.result]
+            .iter()
+            .flat_map(|formula| formula.coeffs.iter().map(|(k, _)| Variable(*k)))
+            .collect();
+        res.sort();
+        res.dedup();
+        res
+    }
+
     /// One more than the last variable index referenced in this constraint.
     pub fn dim(&self) -> usize {
         max(
@@ -61,77 +217,1179 @@ impl<F> ProductConstraint<F> {
     }
 }
 
-/// A constraint system consisting of [`ProductConstraint`]s.
-pub struct ArithmeticSystem<F> {
-    num_vars: usize,
-    constraints: Vec<ProductConstraint<F>>,
+/// Describes a constraint which is not satisfied by an assignment of variable values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsatisfied {
+    /// The index of the constraint.
+    pub index: usize,
+
+    /// The names of the variables referenced by the constraint, as given by
+    /// [`ArithmeticSystem::var_name`].
+    pub vars: Vec<String>,
 }
 
-impl<F> ArithmeticSystem<F> {
-    /// Constructs a new [`ArithmeticSystem`].
-    pub fn new() -> Self {
-        ArithmeticSystem {
-            num_vars: 0,
-            constraints: Vec::new(),
-        }
+impl std::fmt::Display for Unsatisfied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "constraint {} is not satisfied (involving {})",
+            self.index,
+            self.vars.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for Unsatisfied {}
+
+/// A lightweight handle to a [`LinearFormula`] stored in an [`ArithmeticSystem`]. This is the
+/// abstract representation of all values in the system, and can be copied freely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Formula(FormulaRef);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum FormulaRef {
+    /// A formula consisting of a single variable with a coefficient of 1. These are common enough
+    /// that they aren't stored in the arena.
+    Var(u32),
+
+    /// The formula at the given index in the arena.
+    Stored(u32),
+}
+
+impl From<Variable> for Formula {
+    fn from(var: Variable) -> Self {
+        Formula(FormulaRef::Var(var.0))
+    }
+}
+
+/// The handle for the constant formula 0, which is always stored first in the arena.
+const ZERO: Formula = Formula(FormulaRef::Stored(0));
+
+/// The handle for the constant formula 1, which is always stored second in the arena.
+const ONE: Formula = Formula(FormulaRef::Stored(1));
+
+/// A destination for the constraints introduced into an [`ArithmeticSystem`].
+pub trait ConstraintSink<F> {
+    /// The type of failure which the sink may encounter, such as an I/O error.
+    type Error;
+
+    /// Accepts the next constraint of the system.
+    fn push(&mut self, constraint: ProductConstraint<F>);
+
+    /// Informs the sink of the label for a newly-declared variable. By default, this does nothing.
+    fn label(&mut self, var: Variable, name: &str) {
+        let _ = (var, name);
+    }
+
+    /// Informs the sink that the constraints which follow are introduced within the given stack
+    /// of scopes, outermost first. By default, this does nothing.
+    fn scope(&mut self, stack: &[String]) {
+        let _ = stack;
+    }
+
+    /// Reports the first failure the sink has encountered, if any. By default, this always
+    /// succeeds.
+    fn status(&self) -> Result<(), Self::Error> {
+        Ok(())
     }
+}
+
+impl<F> ConstraintSink<F> for Vec<ProductConstraint<F>> {
+    type Error = std::convert::Infallible;
+    fn push(&mut self, constraint: ProductConstraint<F>) {
+        Vec::push(self, constraint)
+    }
+}
+
+/// A constraint system consisting of [`ProductConstraint`]s. By default, the constraints are kept
+/// in memory, but they may be sent to any [`ConstraintSink`] instead.
+pub struct ArithmeticSystem<F, C = Vec<ProductConstraint<F>>> {
+    num_vars: usize,
+    constraints: C,
+
+    /// The number of constraints sent to the [`ConstraintSink`].
+    num_constraints: usize,
+
+    /// The labels given to variables declared with [`ArithmeticSystem::declare_labeled`].
+    labels: HashMap<u32, String>,
+
+    /// The variables declared with [`ArithmeticSystem::declare_public`], in declaration order.
+    public: Vec<Variable>,
 
+    /// The arena of formulas referenced by [`Formula`] handles. Formulas are never modified once
+    /// they are stored.
+    formulas: Vec<LinearFormula<F>>,
+
+    /// The properties which gadgets have assumed of formulas, recorded using
+    /// [`ArithmeticSystem::assume`].
+    assumptions: Vec<(Formula, Assumption)>,
+
+    /// The names of the scopes entered using [`ArithmeticSystem::push_scope`], innermost last.
+    scope_stack: Vec<String>,
+
+    /// The index of the first constraint introduced in each scope, along with its full path.
+    /// These are sorted by index, and later entries take precedence.
+    scopes: Vec<(usize, String)>,
+
+    /// The bits of the [`Word`]s which have been decomposed, keyed by the formula for their
+    /// value, so that each word is only decomposed once.
+    word_bits: HashMap<Formula, [Formula; 32]>,
+
+    /// The gadget instances marked as independent using [`ArithmeticSystem::independent`], in
+    /// the order they were built.
+    instances: Vec<parallel::Instance>,
+}
+
+impl<F, C: ConstraintSink<F>> ArithmeticSystem<F, C> {
     /// Declares a new variable in this system.
     pub fn declare(&mut self) -> Variable {
-        let index = self.num_vars;
+        let index = u32::try_from(self.num_vars).expect("too many variables");
         self.num_vars += 1;
         Variable(index)
     }
 
+    /// Declares a new variable in this system with the given label. Labels are purely
+    /// informational, but can make constraints much easier to debug.
+    pub fn declare_labeled(&mut self, name: impl Into<String>) -> Variable {
+        let var = self.declare();
+        let name = name.into();
+        self.constraints.label(var, &name);
+        self.labels.insert(var.0, name);
+        var
+    }
+
+    /// Declares a new variable in this system whose value is a public input, i.e. part of the
+    /// instance rather than the witness.
+    pub fn declare_public(&mut self) -> Variable {
+        let var = self.declare();
+        self.public.push(var);
+        var
+    }
+
+    /// The public input variables of this system, in the order they were declared.
+    pub fn public_inputs(&self) -> &[Variable] {
+        &self.public
+    }
+
+    /// Gets the label for the given variable, if it has one.
+    pub fn label(&self, var: Variable) -> Option<&str> {
+        self.labels.get(&var.0).map(|name| name.as_str())
+    }
+
+    /// Gets a human-readable name for the given variable. This is its label, if it has one.
+    pub fn var_name(&self, var: Variable) -> String {
+        match self.label(var) {
+            Some(name) => name.to_owned(),
+            None => format!("v{}", var.0),
+        }
+    }
+
     /// Introduces a constraint into this system.
     pub fn satisfy(&mut self, constraint: ProductConstraint<F>) {
         assert!(constraint.dim() <= self.num_vars);
+        self.num_constraints += 1;
         self.constraints.push(constraint)
     }
+
+    /// The number of constraints introduced into this system. For a system resumed from a
+    /// checkpoint, this only counts the constraints introduced since then.
+    pub fn num_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    /// The number of variables declared in this system.
+    pub fn num_vars(&self) -> usize {
+        self.num_vars
+    }
+
+    /// Gets the [`ConstraintSink`] for this system.
+    pub fn sink(&self) -> &C {
+        &self.constraints
+    }
+
+    /// Gets the [`ConstraintSink`] for this system, discarding everything else.
+    pub fn into_sink(self) -> C {
+        self.constraints
+    }
+
+    /// Enters a named scope. Constraints introduced until the matching
+    /// [`ArithmeticSystem::pop_scope`] are attributed to it, which can make it much easier to
+    /// find the gadget responsible for a constraint. Scopes may be nested.
+    pub fn push_scope(&mut self, name: impl Into<String>) {
+        self.scope_stack.push(name.into());
+        self.mark_scope();
+    }
+
+    /// Exits the innermost scope entered using [`ArithmeticSystem::push_scope`].
+    pub fn pop_scope(&mut self) {
+        self.scope_stack.pop().expect("no scope to exit");
+        self.mark_scope();
+    }
+
+    /// Records that the current scope applies from the next constraint onwards.
+    fn mark_scope(&mut self) {
+        let start = self.num_constraints;
+        let path = self.scope_stack.join("/");
+        if matches!(self.scopes.last(), Some((last, _)) if *last == start) {
+            self.scopes.pop();
+        }
+        self.scopes.push((start, path));
+        self.constraints.scope(&self.scope_stack);
+    }
 }
 
-impl<F: Field> SystemRepr<bool> for ArithmeticSystem<F> {
-    type Abstract = LinearFormula<F>;
+impl<F> ArithmeticSystem<F> {
+    /// The constraints introduced into this system, in the order they were introduced.
+    pub fn constraints(&self) -> &[ProductConstraint<F>] {
+        &self.constraints
+    }
+
+    /// Gets the path of the scope in which the constraint at the given index was introduced,
+    /// with the names of nested scopes separated by `/`. This is empty for constraints introduced
+    /// outside of any scope.
+    pub fn scope(&self, index: usize) -> &str {
+        let end = self.scopes.partition_point(|(start, _)| *start <= index);
+        match end.checked_sub(1) {
+            Some(i) => &self.scopes[i].1,
+            None => "",
+        }
+    }
+}
+
+impl<F: Field> Default for ArithmeticSystem<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Field> ArithmeticSystem<F> {
+    /// Constructs a new [`ArithmeticSystem`] which keeps its constraints in memory.
+    pub fn new() -> Self {
+        Self::with_sink(Vec::new())
+    }
+
+    /// Determines whether the given assignment of variable values satisfies all constraints in
+    /// this system.
+    pub fn is_satisfied(&self, assignment: &[F]) -> bool {
+        self.check(assignment).is_ok()
+    }
+
+    /// Checks whether the given assignment of variable values satisfies all constraints in this
+    /// system, describing the first constraint that isn't satisfied.
+    pub fn check(&self, assignment: &[F]) -> Result<(), Unsatisfied> {
+        assert_eq!(assignment.len(), self.num_vars);
+        match self
+            .constraints
+            .iter()
+            .position(|c| !c.is_satisfied(assignment))
+        {
+            Some(index) => {
+                let vars = self.constraints[index].vars();
+                Err(Unsatisfied {
+                    index,
+                    vars: vars.into_iter().map(|var| self.var_name(var)).collect(),
+                })
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<F: Field, C: ConstraintSink<F>> ArithmeticSystem<F, C> {
+    /// Constructs a new [`ArithmeticSystem`] which sends its constraints to the given
+    /// [`ConstraintSink`].
+    pub fn with_sink(sink: C) -> Self {
+        ArithmeticSystem {
+            num_vars: 0,
+            constraints: sink,
+            num_constraints: 0,
+            labels: HashMap::new(),
+            public: Vec::new(),
+            formulas: vec![
+                LinearFormula::constant(F::zero()),
+                LinearFormula::constant(F::one()),
+            ],
+            assumptions: Vec::new(),
+            scope_stack: Vec::new(),
+            scopes: Vec::new(),
+            word_bits: HashMap::new(),
+            instances: Vec::new(),
+        }
+    }
+
+    /// Stores the given formula in this system, returning a handle to it.
+    pub fn alloc(&mut self, formula: LinearFormula<F>) -> Formula {
+        match formula.coeffs.as_slice() {
+            [] if formula.constant_term.is_zero_vartime() => return ZERO,
+            [] if formula.constant_term == F::one() => return ONE,
+            [(var, coeff)] if formula.constant_term.is_zero_vartime() && *coeff == F::one() => {
+                return Formula(FormulaRef::Var(*var))
+            }
+            _ => (),
+        }
+        let index = u32::try_from(self.formulas.len()).expect("too many formulas");
+        self.formulas.push(formula);
+        Formula(FormulaRef::Stored(index))
+    }
+
+    /// Gets the formula referenced by the given handle.
+    pub fn formula(&self, formula: Formula) -> Cow<'_, LinearFormula<F>> {
+        match formula.0 {
+            FormulaRef::Var(var) => Cow::Owned(Variable(var).into()),
+            FormulaRef::Stored(index) => Cow::Borrowed(&self.formulas[index as usize]),
+        }
+    }
+
+    /// Replaces `formula` with a handle to a modified copy of the formula it references. Other
+    /// handles to the original formula are unaffected.
+    pub fn update(&mut self, formula: &mut Formula, op: impl FnOnce(&mut LinearFormula<F>)) {
+        let mut value = self.formula(*formula).into_owned();
+        op(&mut value);
+        *formula = self.alloc(value);
+    }
+
+    /// Gets the value of the given formula, if it is constant.
+    fn as_constant(&self, formula: Formula) -> Option<F> {
+        match formula.0 {
+            FormulaRef::Var(_) => None,
+            FormulaRef::Stored(index) => {
+                let formula = &self.formulas[index as usize];
+                formula.coeffs.is_empty().then_some(formula.constant_term)
+            }
+        }
+    }
+
+    /// Records that a gadget relies on the given formula having some property, without
+    /// constraining it to have that property. This has no effect on the constraints, but allows
+    /// [`ArithmeticSystem::lint`] to check that the property is enforced elsewhere. Assumptions
+    /// about constant formulas are ignored.
+    pub fn assume(&mut self, formula: Formula, assumption: Assumption) {
+        if self.as_constant(formula).is_none() {
+            self.assumptions.push((formula, assumption));
+        }
+    }
+
+    /// Evaluates the given formula for the given assignment of variable values.
+    pub fn eval(&self, formula: Formula, assignment: &[F]) -> F {
+        self.formula(formula).eval(assignment)
+    }
+
+    /// Introduces a constraint that the product of `a` and `b` is `result`.
+    fn constrain(&mut self, a: Formula, b: Formula, result: Formula) {
+        let constraint = ProductConstraint {
+            operand_a: self.formula(a).into_owned(),
+            operand_b: self.formula(b).into_owned(),
+            result: self.formula(result).into_owned(),
+        };
+        self.satisfy(constraint);
+    }
+
+    /// Declares a new variable which is constrained to be either 0 or 1.
+    pub fn declare_bool(&mut self) -> Formula {
+        let var = self.declare().into();
+        self.constrain(var, var, var);
+        var
+    }
+
+    /// Constructs a formula for the product of the given formulas. This introduces a new
+    /// variable and constraint, unless one of the formulas is constant.
+    pub fn product(&mut self, a: Formula, b: Formula) -> Formula {
+        if let Some(a) = self.as_constant(a) {
+            return self.linear_combination(&[(a, b)]);
+        }
+        if let Some(b) = self.as_constant(b) {
+            return self.linear_combination(&[(b, a)]);
+        }
+        let res = self.declare().into();
+        self.constrain(a, b, res);
+        res
+    }
+
+    /// Constructs the sum of the given formulas, each multiplied by the corresponding factor. This
+    /// is much cheaper than folding the terms together one at a time.
+    pub fn linear_combination(&mut self, terms: &[(F, Formula)]) -> Formula {
+        let res = LinearFormula::combine(
+            terms
+                .iter()
+                .map(|(factor, formula)| (*factor, self.formula(*formula))),
+        );
+        self.alloc(res)
+    }
+
+    /// Constructs the sum of the given formulas. See [`ArithmeticSystem::linear_combination`].
+    pub fn sum(&mut self, terms: &[Formula]) -> Formula {
+        let res = LinearFormula::combine(
+            terms
+                .iter()
+                .map(|formula| (F::one(), self.formula(*formula))),
+        );
+        self.alloc(res)
+    }
+
+    /// Constructs the difference of the given formulas.
+    fn diff(&mut self, a: Formula, b: Formula) -> Formula {
+        self.linear_combination(&[(F::one(), a), (-F::one(), b)])
+    }
+
+    /// Constructs a formula which is `a` if `cond` is 1, or `b` if `cond` is 0.
+    fn select(&mut self, cond: Formula, a: Formula, b: Formula) -> Formula {
+        self.assume(cond, Assumption::Bool);
+        let diff = self.diff(a, b);
+        let offset = self.product(cond, diff);
+        self.sum(&[b, offset])
+    }
+
+    /// Constrains the given formula to be equal to zero.
+    pub fn assert_zero(&mut self, value: Formula) {
+        self.constrain(value, ONE, ZERO);
+    }
+
+    /// Constructs the integer represented by the given bits, least significant first, using two's
+    /// complement if `signed` is set.
+    pub fn recompose(&mut self, bits: &[Formula], signed: bool) -> Formula {
+        let mut weight = F::one();
+        let terms: Vec<_> = bits
+            .iter()
+            .enumerate()
+            .map(|(i, bit)| {
+                let factor = if signed && i + 1 == bits.len() {
+                    -weight
+                } else {
+                    weight
+                };
+                weight = weight.double();
+                (factor, *bit)
+            })
+            .collect();
+        self.linear_combination(&terms)
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> ArithmeticSystem<F, C> {
+    /// Constrains the given formula to be representable as a `bits`-bit integer, using two's
+    /// complement if `signed` is set, and returns the bits of that representation, least
+    /// significant first. This serves as a range check.
+    pub fn decompose(&mut self, value: Formula, bits: usize, signed: bool) -> Vec<Formula> {
+        assert!(
+            bits <= F::CAPACITY as usize,
+            "field is too small to decompose into {} bits",
+            bits
+        );
+        let res: Vec<_> = (0..bits).map(|_| self.declare_bool()).collect();
+        let recomposed = self.recompose(&res, signed);
+        let value = self.diff(recomposed, value);
+        self.assert_zero(value);
+        res
+    }
+
+    /// Divides the given formula by a constant, returning the quotient and remainder. `value`
+    /// must be a non-negative integer no greater than `bound`. The quotient and remainder are
+    /// new variables, range-checked so that they are the only solution.
+    pub fn div_rem_const(
+        &mut self,
+        value: Formula,
+        bound: u128,
+        divisor: u64,
+    ) -> (Formula, Formula) {
+        assert!(divisor > 0, "division by zero");
+        let num_bits = |value: u128| (u128::BITS - value.leading_zeros()) as usize;
+        assert!(
+            num_bits(bound) < F::CAPACITY as usize,
+            "field is too small to divide {}-bit integers",
+            num_bits(bound)
+        );
+        if let Some(value) = self.as_constant(value).and_then(lint::small_int) {
+            let value = u64::try_from(value).expect("negative dividend");
+            let quot = self.alloc(LinearFormula::constant(F::from(value / divisor)));
+            let rem = self.alloc(LinearFormula::constant(F::from(value % divisor)));
+            return (quot, rem);
+        }
+        self.assume(
+            value,
+            Assumption::Range {
+                bits: num_bits(bound),
+                signed: false,
+            },
+        );
+
+        // The solver recognizes this constraint as a division, so it must come before the range
+        // checks
+        let quot = self.declare().into();
+        let rem = self.declare().into();
+        let divisor_formula = self.alloc(LinearFormula::constant(F::from(divisor)));
+        let diff = self.diff(value, rem);
+        self.constrain(quot, divisor_formula, diff);
+        self.decompose(quot, num_bits(bound / divisor as u128), false);
+        let rem_bits = num_bits(divisor as u128 - 1);
+        self.decompose(rem, rem_bits, false);
+        if !divisor.is_power_of_two() {
+            let max = self.alloc(LinearFormula::constant(F::from(divisor - 1)));
+            let slack = self.diff(max, rem);
+            self.decompose(slack, rem_bits, false);
+        }
+        (quot, rem)
+    }
+
+    /// Divides the given formula by another, returning the quotient and remainder. `value` and
+    /// `divisor` must be non-negative integers no greater than `bound` and `divisor_bound`. The
+    /// remainder is checked to be less than the divisor, which also asserts that the divisor is
+    /// nonzero.
+    pub fn div_rem(
+        &mut self,
+        value: Formula,
+        bound: u128,
+        divisor: Formula,
+        divisor_bound: u128,
+    ) -> (Formula, Formula) {
+        let constant = self.as_constant(divisor).and_then(lint::small_int);
+        if let Some(divisor) = constant
+            .and_then(|d| u64::try_from(d).ok())
+            .filter(|d| *d > 0)
+        {
+            return self.div_rem_const(value, bound, divisor);
+        }
+        let num_bits = |value: u128| (u128::BITS - value.leading_zeros()) as usize;
+        let (value_bits, divisor_bits) = (num_bits(bound), num_bits(divisor_bound));
+        assert!(
+            value_bits + divisor_bits < F::CAPACITY as usize,
+            "field is too small to divide {}-bit integers by {}-bit integers",
+            value_bits,
+            divisor_bits
+        );
+        self.assume(
+            value,
+            Assumption::Range {
+                bits: value_bits,
+                signed: false,
+            },
+        );
+        self.assume(
+            divisor,
+            Assumption::Range {
+                bits: divisor_bits,
+                signed: false,
+            },
+        );
+
+        // The solver recognizes this constraint as a division, so it must come before the range
+        // checks
+        let quot = self.declare().into();
+        let rem = self.declare().into();
+        let diff = self.diff(value, rem);
+        self.constrain(quot, divisor, diff);
+        self.decompose(quot, value_bits, false);
+        self.decompose(rem, divisor_bits, false);
+        let slack =
+            self.linear_combination(&[(F::one(), divisor), (-F::one(), rem), (-F::one(), ONE)]);
+        self.decompose(slack, divisor_bits, false);
+        (quot, rem)
+    }
+
+    /// Records that a gadget relies on the given formula being a signed `bits`-bit integer.
+    fn assume_int(&mut self, value: Formula, bits: usize) {
+        self.assume(value, Assumption::Range { bits, signed: true });
+    }
+}
+
+impl<F: Field, C: ConstraintSink<F>> SystemRepr<FieldElement<F>> for ArithmeticSystem<F, C> {
+    type Abstract = Formula;
+    type Error = C::Error;
+    fn constant(&mut self, value: FieldElement<F>) -> Self::Abstract {
+        self.alloc(LinearFormula::constant(value.0))
+    }
+
+    fn status(&self) -> Result<(), Self::Error> {
+        self.constraints.status()
+    }
+}
+
+impl<F: Field, C: ConstraintSink<F>> SystemAdd<FieldElement<F>> for ArithmeticSystem<F, C> {
+    fn add(
+        &mut self,
+        a: &Abstract<Self, FieldElement<F>>,
+        b: &Abstract<Self, FieldElement<F>>,
+    ) -> Abstract<Self, FieldElement<F>> {
+        self.sum(&[*a, *b])
+    }
+}
+
+impl<F: Field, C: ConstraintSink<F>> SystemMul<FieldElement<F>> for ArithmeticSystem<F, C> {
+    fn mul(
+        &mut self,
+        a: &Abstract<Self, FieldElement<F>>,
+        b: &Abstract<Self, FieldElement<F>>,
+    ) -> Abstract<Self, FieldElement<F>> {
+        self.product(*a, *b)
+    }
+}
+
+impl<F: Field, C: ConstraintSink<F>> SystemInverse<FieldElement<F>> for ArithmeticSystem<F, C> {
+    fn inverse_unchecked(
+        &mut self,
+        value: &Abstract<Self, FieldElement<F>>,
+    ) -> Abstract<Self, FieldElement<F>> {
+        if let Some(value) = self.as_constant(*value) {
+            let inv = Option::from(value.invert()).expect("inverse of zero");
+            return self.alloc(LinearFormula::constant(inv));
+        }
+        let res = self.declare().into();
+        self.constrain(*value, res, ONE);
+        res
+    }
+
+    fn is_zero(&mut self, value: &Abstract<Self, FieldElement<F>>) -> Abstract<Self, bool> {
+        if let Some(value) = self.as_constant(*value) {
+            return self.constant(value.is_zero_vartime());
+        }
+
+        // With `inv` being the inverse of `value` when it exists, the constraints
+        // `value * inv = 1 - res` and `value * res = 0` force `res` to be 1 exactly when `value`
+        // is zero. Note that `res` need not be constrained to be boolean separately.
+        let inv = self.declare().into();
+        let res = self.declare().into();
+        let not_res = self.diff(ONE, res);
+        self.constrain(*value, inv, not_res);
+        self.constrain(*value, res, ZERO);
+        res
+    }
+}
+
+impl<F: Field, C: ConstraintSink<F>> SystemAssertEq<FieldElement<F>> for ArithmeticSystem<F, C> {
+    fn assert_eq(
+        &mut self,
+        a: &Abstract<Self, FieldElement<F>>,
+        b: &Abstract<Self, FieldElement<F>>,
+    ) {
+        let diff = self.diff(*a, *b);
+        self.assert_zero(diff)
+    }
+}
+
+impl<F: Field, C: ConstraintSink<F>> SystemAssert for ArithmeticSystem<F, C> {
+    fn assert(&mut self, value: &Abstract<Self, bool>) {
+        self.assume(*value, Assumption::Bool);
+        let not = SystemNot::<bool>::not(self, value);
+        self.assert_zero(not)
+    }
+
+    fn assert_all(&mut self, values: &[Abstract<Self, bool>]) {
+        // Each negation is 0 or 1, so their sum is only 0 if all of them are, as long as there
+        // are fewer values than the characteristic of the field
+        let nots: Vec<_> = (values.iter())
+            .map(|value| {
+                self.assume(*value, Assumption::Bool);
+                SystemNot::<bool>::not(self, value)
+            })
+            .collect();
+        let sum = self.sum(&nots);
+        self.assert_zero(sum)
+    }
+}
+
+impl<F: Field, C: ConstraintSink<F>> SystemAssertIf for ArithmeticSystem<F, C> {
+    fn assert_if(&mut self, cond: &Abstract<Self, bool>, claim: &Abstract<Self, bool>) {
+        // `cond * (1 - claim) = 0`
+        self.assume(*cond, Assumption::Bool);
+        self.assume(*claim, Assumption::Bool);
+        let not = SystemNot::<bool>::not(self, claim);
+        self.constrain(*cond, not, ZERO)
+    }
+}
+
+impl<F: Field, C: ConstraintSink<F>> SystemRepr<bool> for ArithmeticSystem<F, C> {
+    type Abstract = Formula;
+    type Error = C::Error;
     fn constant(&mut self, value: bool) -> Self::Abstract {
-        LinearFormula::constant(if value { F::one() } else { F::zero() })
+        if value {
+            ONE
+        } else {
+            ZERO
+        }
+    }
+
+    fn status(&self) -> Result<(), Self::Error> {
+        self.constraints.status()
     }
 }
 
-impl<F: Field> SystemBitAnd<bool> for ArithmeticSystem<F> {
+impl<F: PrimeField, C: ConstraintSink<F>> SystemCapabilities for ArithmeticSystem<F, C> {
+    const NATIVE_FIELD_BITS: u32 = F::CAPACITY;
+}
+
+impl<F: Field, C: ConstraintSink<F>> SystemBitAnd<bool> for ArithmeticSystem<F, C> {
     fn and(&mut self, a: &Abstract<Self, bool>, b: &Abstract<Self, bool>) -> Abstract<Self, bool> {
-        todo!()
+        self.assume(*a, Assumption::Bool);
+        self.assume(*b, Assumption::Bool);
+        self.product(*a, *b)
     }
 }
 
-impl<F: Field> SystemBitOr<bool> for ArithmeticSystem<F> {
+impl<F: Field, C: ConstraintSink<F>> SystemBitOr<bool> for ArithmeticSystem<F, C> {
     fn or(&mut self, a: &Abstract<Self, bool>, b: &Abstract<Self, bool>) -> Abstract<Self, bool> {
-        let a = self.not(a);
-        let b = self.not(b);
-        let r = self.and(&a, &b);
-        self.not(&r)
+        let a = SystemNot::<bool>::not(self, a);
+        let b = SystemNot::<bool>::not(self, b);
+        let r = SystemBitAnd::<bool>::and(self, &a, &b);
+        SystemNot::<bool>::not(self, &r)
     }
 }
 
-impl<F: Field> SystemBitXor<bool> for ArithmeticSystem<F> {
+impl<F: Field, C: ConstraintSink<F>> SystemBitXor<bool> for ArithmeticSystem<F, C> {
     fn xor(&mut self, a: &Abstract<Self, bool>, b: &Abstract<Self, bool>) -> Abstract<Self, bool> {
+        let (a, b) = (*a, *b);
+        self.assume(a, Assumption::Bool);
+        self.assume(b, Assumption::Bool);
         if (F::one() + F::one()).is_zero_vartime() {
-            a + b
+            self.sum(&[a, b])
+        } else if self.as_constant(a).is_some() || self.as_constant(b).is_some() {
+            let ab = self.product(a, b);
+            self.linear_combination(&[(F::one(), a), (F::one(), b), (-F::one().double(), ab)])
         } else {
-            todo!()
+            // Introduce a variable for the result, so that formulas don't grow along chains of
+            // XORs: `2a * b = a + b - res`
+            let res = self.declare().into();
+            let operand_a = self.linear_combination(&[(F::one().double(), a)]);
+            let result = self.linear_combination(&[(F::one(), a), (F::one(), b), (-F::one(), res)]);
+            self.constrain(operand_a, b, result);
+            res
         }
     }
 }
 
-impl<F: Field> SystemNot<bool> for ArithmeticSystem<F> {
+impl<F: Field, C: ConstraintSink<F>> SystemNot<bool> for ArithmeticSystem<F, C> {
     fn not(&mut self, value: &Abstract<Self, bool>) -> Abstract<Self, bool> {
-        &LinearFormula::constant(F::one()) - value
+        self.diff(ONE, *value)
     }
 }
 
+/// Implements [`SystemSelect`] on [`ArithmeticSystem`] for a type represented by a single
+/// [`Formula`].
+macro_rules! impl_select {
+    ($t:ty, $bound:ident) => {
+        impl<F: $bound, C: ConstraintSink<F>> SystemSelect<$t> for ArithmeticSystem<F, C> {
+            fn select(
+                &mut self,
+                cond: &Abstract<Self, bool>,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+            ) -> Abstract<Self, $t> {
+                ArithmeticSystem::select(self, *cond, *a, *b)
+            }
+        }
+    };
+}
+
+impl_select!(FieldElement<F>, Field);
+impl_select!(bool, Field);
+impl_select!(u8, PrimeField);
+impl_select!(i8, PrimeField);
+impl_select!(i16, PrimeField);
+impl_select!(i32, PrimeField);
+impl_select!(i64, PrimeField);
+
+/// Implements [`SystemDebug`] on [`ArithmeticSystem`], where probing has no effect.
+macro_rules! impl_debug {
+    ($t:ty, $bound:ident) => {
+        impl<F: $bound, C: ConstraintSink<F>> SystemDebug<$t> for ArithmeticSystem<F, C> {}
+    };
+}
+
+impl_debug!(FieldElement<F>, Field);
+impl_debug!(bool, Field);
+impl_debug!(u8, PrimeField);
+impl_debug!(i8, PrimeField);
+impl_debug!(i16, PrimeField);
+impl_debug!(i32, PrimeField);
+impl_debug!(i64, PrimeField);
+
+/// Implements integer operations on [`ArithmeticSystem`] for a signed integer type, represented by
+/// its value in the field.
+macro_rules! impl_signed {
+    ($t:ty, $bits:literal) => {
+        impl<F: PrimeField, C: ConstraintSink<F>> SystemRepr<$t> for ArithmeticSystem<F, C> {
+            type Abstract = Formula;
+            type Error = C::Error;
+            fn constant(&mut self, value: $t) -> Self::Abstract {
+                let abs = F::from(value.unsigned_abs() as u64);
+                self.alloc(LinearFormula::constant(if value < 0 { -abs } else { abs }))
+            }
+
+            fn status(&self) -> Result<(), Self::Error> {
+                self.constraints.status()
+            }
+        }
+
+        impl<F: PrimeField, C: ConstraintSink<F>> SystemBitShift<$t, u8>
+            for ArithmeticSystem<F, C>
+        {
+            fn shl(&mut self, a: &Abstract<Self, $t>, b: u8) -> Abstract<Self, $t> {
+                let bits = self.decompose(*a, $bits, true);
+                let b = (b as usize).min($bits);
+                let shifted: Vec<_> = std::iter::repeat(ZERO)
+                    .take(b)
+                    .chain(bits[..($bits - b)].iter().copied())
+                    .collect();
+                self.recompose(&shifted, true)
+            }
+
+            fn shr(&mut self, a: &Abstract<Self, $t>, b: u8) -> Abstract<Self, $t> {
+                let bits = self.decompose(*a, $bits, true);
+                let b = (b as usize).min($bits - 1);
+                self.recompose(&bits[b..], true)
+            }
+        }
+
+        impl<F: PrimeField, C: ConstraintSink<F>> SystemWrappingAdd<$t> for ArithmeticSystem<F, C> {
+            fn wrapping_add(
+                &mut self,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+            ) -> Abstract<Self, $t> {
+                self.assume_int(*a, $bits);
+                self.assume_int(*b, $bits);
+                let sum = self.sum(&[*a, *b]);
+                let bits = self.decompose(sum, $bits + 1, true);
+                self.recompose(&bits[..$bits], true)
+            }
+        }
+
+        impl<F: PrimeField, C: ConstraintSink<F>> SystemMulShr<$t> for ArithmeticSystem<F, C> {
+            fn mul_shr(
+                &mut self,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+                shift: u8,
+            ) -> Abstract<Self, $t> {
+                self.assume_int(*a, $bits);
+                self.assume_int(*b, $bits);
+                let prod = self.product(*a, *b);
+                let bits = self.decompose(prod, 2 * $bits, true);
+                let shifted: Vec<_> = (0..$bits)
+                    .map(|i| bits[(i + shift as usize).min(2 * $bits - 1)])
+                    .collect();
+                self.recompose(&shifted, true)
+            }
+        }
+
+        impl<F: PrimeField, C: ConstraintSink<F>> SystemOrd<$t> for ArithmeticSystem<F, C> {
+            fn lt(
+                &mut self,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+            ) -> Abstract<Self, bool> {
+                // The difference is always representable with one more bit, and its sign bit
+                // determines the result
+                self.assume_int(*a, $bits);
+                self.assume_int(*b, $bits);
+                let diff = self.diff(*a, *b);
+                let bits = self.decompose(diff, $bits + 1, true);
+                bits[$bits]
+            }
+
+            fn le(
+                &mut self,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+            ) -> Abstract<Self, bool> {
+                let gt = SystemOrd::<$t>::lt(self, b, a);
+                SystemNot::<bool>::not(self, &gt)
+            }
+        }
+    };
+}
+
+impl_signed!(i8, 8);
+impl_signed!(i16, 16);
+impl_signed!(i32, 32);
+impl_signed!(i64, 64);
+
 // TODO: Require `F::CAPACITY >= 8`
-impl<F: PrimeField> SystemRepr<u8> for ArithmeticSystem<F> {
-    type Abstract = LinearFormula<F>;
+impl<F: PrimeField, C: ConstraintSink<F>> SystemRepr<u8> for ArithmeticSystem<F, C> {
+    type Abstract = Formula;
+    type Error = C::Error;
     fn constant(&mut self, value: u8) -> Self::Abstract {
-        LinearFormula::constant(F::from(u64::from(value)))
+        self.alloc(LinearFormula::constant(F::from(u64::from(value))))
+    }
+
+    fn status(&self) -> Result<(), Self::Error> {
+        self.constraints.status()
+    }
+}
+
+impl<F: Field, C: ConstraintSink<F>> SystemRand<FieldElement<F>> for ArithmeticSystem<F, C> {
+    fn rand(&mut self) -> Abstract<Self, FieldElement<F>> {
+        self.declare().into()
+    }
+}
+
+impl<F: Field, C: ConstraintSink<F>> SystemRand<bool> for ArithmeticSystem<F, C> {
+    fn rand(&mut self) -> Abstract<Self, bool> {
+        self.declare_bool()
+    }
+}
+
+/// Implements [`SystemRand`] on [`ArithmeticSystem`] for an integer type, by range-checking a new
+/// variable.
+macro_rules! impl_rand {
+    ($t:ty, $bits:literal, $signed:literal) => {
+        impl<F: PrimeField, C: ConstraintSink<F>> SystemRand<$t> for ArithmeticSystem<F, C> {
+            fn rand(&mut self) -> Abstract<Self, $t> {
+                let var = self.declare().into();
+                self.decompose(var, $bits, $signed);
+                var
+            }
+        }
+    };
+}
+
+impl_rand!(u8, 8, false);
+impl_rand!(i8, 8, true);
+impl_rand!(i16, 16, true);
+impl_rand!(i32, 32, true);
+impl_rand!(i64, 64, true);
+
+#[cfg(test)]
+fn embed_i64<F: PrimeField>(value: i64) -> F {
+    let abs = F::from(value.unsigned_abs());
+    if value < 0 {
+        -abs
+    } else {
+        abs
+    }
+}
+
+#[test]
+fn test_assert_all() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let vars: Vec<_> = (0..4).map(|_| sys.declare()).collect();
+    let claims: Vec<_> = vars.iter().map(|var| Formula::from(*var)).collect();
+    for claim in claims.iter() {
+        sys.decompose(*claim, 1, false);
+    }
+    let before = sys.num_constraints();
+    SystemAssert::assert_all(&mut sys, &claims);
+    assert_eq!(sys.num_constraints(), before + 1);
+    let solve = |values: [u64; 4]| {
+        let known: Vec<_> = (vars.iter().zip(values))
+            .map(|(var, value)| (*var, Scalar::from(value)))
+            .collect();
+        sys.solve(&known)
+            .is_ok_and(|assignment| sys.is_satisfied(&assignment))
+    };
+    assert!(solve([1, 1, 1, 1]));
+    assert!(!solve([1, 0, 1, 1]));
+
+    // Evaluation reports every false value
+    let err = std::panic::catch_unwind(|| {
+        SystemAssert::assert_all(&mut Eval, &[true, false, true, false]);
+    })
+    .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<String>().unwrap(),
+        "assertions [1, 3] failed"
+    );
+}
+
+#[test]
+fn test_div_rem() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let value = sys.declare();
+    let divisor = sys.declare();
+    let (quot, rem) = sys.div_rem(value.into(), 1000, divisor.into(), 100);
+    let solve = |sys: &ArithmeticSystem<Scalar>, a: u64, b: u64| {
+        let known = [(value, Scalar::from(a)), (divisor, Scalar::from(b))];
+        sys.solve(&known).ok()
+    };
+    for (a, b) in [(0, 1), (999, 7), (1000, 100), (42, 43), (57, 57)] {
+        let assignment = solve(&sys, a, b).unwrap();
+        assert!(sys.is_satisfied(&assignment));
+        assert_eq!(sys.eval(quot, &assignment), Scalar::from(a / b));
+        assert_eq!(sys.eval(rem, &assignment), Scalar::from(a % b));
+    }
+
+    // A zero divisor can't be satisfied, even with a forged quotient and remainder
+    assert!(solve(&sys, 5, 0).is_none_or(|assignment| !sys.is_satisfied(&assignment)));
+    let [q, r] = [quot, rem].map(|f| Variable(sys.formula(f).coeffs[0].0));
+    let known: Vec<_> = [(value, 5), (divisor, 0), (q, 0), (r, 5)]
+        .map(|(var, value)| (var, Scalar::from(value)))
+        .to_vec();
+    let assignment = sys.solve(&known).unwrap();
+    assert!(!sys.is_satisfied(&assignment));
+}
+
+#[test]
+fn test_signed_shift() {
+    use bls12_381::Scalar;
+    for x in [-128i8, -77, -1, 0, 1, 77, 127] {
+        let mut sys = ArithmeticSystem::<Scalar>::new();
+        let a = Formula::from(sys.declare());
+        let shr = SystemBitShift::<i8, u8>::shr(&mut sys, &a, 3);
+        let shl = SystemBitShift::<i8, u8>::shl(&mut sys, &a, 3);
+        let mut assignment = vec![embed_i64(x.into())];
+        for _ in 0..2 {
+            assignment.extend((0..8).map(|i| Scalar::from(((x >> i) & 1) as u64)));
+        }
+        assert!(sys.is_satisfied(&assignment));
+        assert_eq!(sys.eval(shr, &assignment), embed_i64((x >> 3).into()));
+        assert_eq!(sys.eval(shl, &assignment), embed_i64((x << 3).into()));
+    }
+}
+
+#[test]
+fn test_signed_range() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let a = Formula::from(sys.declare());
+    sys.decompose(a, 8, true);
+
+    // 128 has the same bits as -128, but is out of range
+    let bits = (0..8).map(|i| Scalar::from((128u64 >> i) & 1));
+    let assignment: Vec<_> = std::iter::once(Scalar::from(128)).chain(bits).collect();
+    assert!(!sys.is_satisfied(&assignment));
+    let assignment: Vec<_> = std::iter::once(embed_i64(-128))
+        .chain(assignment[1..].iter().copied())
+        .collect();
+    assert!(sys.is_satisfied(&assignment));
+}
+
+#[test]
+fn test_linear_combination() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let vars: Vec<LinearFormula<Scalar>> = (0..4).map(|_| sys.declare().into()).collect();
+    let a = &vars[0] + &vars[1];
+    let b = &(&vars[2] - &vars[1]) + &LinearFormula::constant(Scalar::from(5));
+    let c = &vars[3] + &vars[0];
+    let (a, b, c) = (sys.alloc(a), sys.alloc(b), sys.alloc(c));
+    let two = Scalar::from(2);
+    let combined = sys.linear_combination(&[(two, a), (two, b), (-Scalar::one(), c)]);
+
+    // The terms for `vars[1]` cancel out
+    let formula = sys.formula(combined);
+    assert_eq!(formula.dim(), 4);
+    assert!(formula.coeffs.iter().all(|(k, _)| *k != 1));
+    let assignment: Vec<_> = (1..=4).map(Scalar::from).collect();
+    let eval = |f| sys.eval(f, &assignment);
+    let expected = (eval(a) + eval(b)) * two - eval(c);
+    assert_eq!(eval(combined), expected);
+    let sum = sys.sum(&[a, b, c]);
+    let expected = sys.eval(a, &assignment) + sys.eval(b, &assignment) + sys.eval(c, &assignment);
+    assert_eq!(sys.eval(sum, &assignment), expected);
+}
+
+#[test]
+fn test_formula_handles() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let x = Formula::from(sys.declare());
+    let y = LinearFormula::from(sys.declare());
+
+    // Variables and boolean constants don't need to be stored in the arena
+    let num_formulas = sys.formulas.len();
+    assert_eq!(sys.alloc(y), Formula::from(Variable(1)));
+    let one = sys.alloc(LinearFormula::constant(Scalar::one()));
+    assert_eq!(one, sys.constant(true));
+    assert_eq!(sys.formulas.len(), num_formulas);
+
+    // Updating a formula copies it, leaving other handles to it untouched
+    let mut y = x;
+    sys.update(&mut y, |f| *f = f.scale(Scalar::from(3)));
+    let assignment = [Scalar::from(5), Scalar::zero()];
+    assert_eq!(sys.eval(x, &assignment), Scalar::from(5));
+    assert_eq!(sys.eval(y, &assignment), Scalar::from(15));
+}
+
+#[test]
+fn test_labels() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let x = sys.declare_labeled("sha256.round[12].carry");
+    let y = sys.declare();
+    let (x, y) = (Formula::from(x), Formula::from(y));
+    let r = SystemBitAnd::<bool>::and(&mut sys, &x, &y);
+    SystemAssertEq::<FieldElement<Scalar>>::assert_eq(&mut sys, &r, &ONE);
+    let one = Scalar::one();
+    assert!(sys.check(&[one, one, one]).is_ok());
+    let err = sys.check(&[one, Scalar::zero(), one]).unwrap_err();
+    assert_eq!(err.index, 0);
+    assert_eq!(err.vars, ["sha256.round[12].carry", "v1", "v2"]);
+}
+
+#[test]
+fn test_select() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let cond = sys.declare_bool();
+    let a = Formula::from(sys.declare());
+    let b = Formula::from(sys.declare());
+    let r = SystemSelect::<FieldElement<Scalar>>::cond_select(
+        &mut sys,
+        &cond,
+        |sys| SystemMul::<FieldElement<Scalar>>::mul(sys, &a, &b),
+        |sys| SystemAdd::<FieldElement<Scalar>>::add(sys, &a, &b),
+    );
+
+    // Division by `b` is only required when `cond` is set
+    let is_zero = SystemInverse::<FieldElement<Scalar>>::is_zero(&mut sys, &b);
+    let nonzero = SystemNot::<bool>::not(&mut sys, &is_zero);
+    sys.assert_if(&cond, &nonzero);
+
+    // The assignment is `[cond, a, b, a * b, cond * (a * b - (a + b)), b^-1 or 0, is_zero]`
+    let (x, y) = (Scalar::from(6), Scalar::from(7));
+    let assignment = [
+        Scalar::one(),
+        x,
+        y,
+        x * y,
+        Scalar::from(29),
+        y.invert().unwrap(),
+        Scalar::zero(),
+    ];
+    assert_eq!(sys.check(&assignment), Ok(()));
+    assert_eq!(sys.eval(r, &assignment), x * y);
+    let assignment = [
+        Scalar::zero(),
+        x,
+        Scalar::zero(),
+        Scalar::zero(),
+        Scalar::zero(),
+        Scalar::zero(),
+        Scalar::one(),
+    ];
+    assert_eq!(sys.check(&assignment), Ok(()));
+    assert_eq!(sys.eval(r, &assignment), x);
+    let assignment = [
+        Scalar::one(),
+        x,
+        Scalar::zero(),
+        Scalar::zero(),
+        -x,
+        Scalar::zero(),
+        Scalar::one(),
+    ];
+    assert!(!sys.is_satisfied(&assignment));
+}
+
+#[test]
+fn test_rand() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let a = SystemRand::<u8>::rand(&mut sys);
+    let b = SystemRand::<FieldElement<Scalar>>::rand(&mut sys);
+    let c = SystemRand::<bool>::rand(&mut sys);
+    let sum = sys.sum(&[a, b, c]);
+
+    // Each value is a new variable, and the integer also has its bits
+    assert_eq!(sys.num_vars(), 11);
+    Eval::seed_rng(1);
+    for _ in 0..10 {
+        let x = Scalar::from(SystemRand::<u8>::rand(&mut Eval) as u64);
+        let y = SystemRand::<FieldElement<Scalar>>::rand(&mut Eval).0;
+        let z = Scalar::from(SystemRand::<bool>::rand(&mut Eval) as u64);
+        let known = [(Variable(0), x), (Variable(9), y), (Variable(10), z)];
+        let assignment = sys.solve(&known).unwrap();
+        assert_eq!(sys.check(&assignment), Ok(()));
+        assert_eq!(sys.eval(sum, &assignment), x + y + z);
     }
 }
@@ -0,0 +1,209 @@
+use super::*;
+
+/// An [`ArithmeticSystem`] which fails once it has introduced more than a fixed number of
+/// constraints.
+pub type BudgetedArithmeticSystem<F, C = Vec<ProductConstraint<F>>> =
+    ArithmeticSystem<F, Budget<C>>;
+
+/// A [`ConstraintSink`] which forwards at most a fixed number of constraints to another sink.
+/// Once the budget is exceeded, further constraints are discarded and the sink reports
+/// [`BudgetError::Exceeded`], so that circuits generated from untrusted parameters can't use
+/// unbounded resources. Gadgets which report failures, such as [`SystemSelect::try_select`],
+/// will abort as soon as this happens.
+pub struct Budget<C> {
+    inner: C,
+    limit: usize,
+    num_constraints: usize,
+
+    /// The stack of scopes which the next constraint will be introduced in.
+    scope: Vec<String>,
+
+    /// Describes where the budget was first exceeded, if it has been.
+    exceeded: Option<BudgetExceeded>,
+
+    /// Invoked when the budget is first exceeded.
+    on_exceeded: Option<BudgetCallback>,
+}
+
+/// A callback invoked when a [`Budget`] is first exceeded.
+type BudgetCallback = Box<dyn FnMut(&BudgetExceeded)>;
+
+/// Describes where a [`Budget`] was exceeded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetExceeded {
+    /// The maximum number of constraints allowed by the budget.
+    pub limit: usize,
+
+    /// The stack of scopes, outermost first, in which the first constraint beyond the budget
+    /// was introduced. See [`ArithmeticSystem::push_scope`].
+    pub scope: Vec<String>,
+}
+
+impl std::fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "exceeded budget of {} constraints", self.limit)?;
+        if !self.scope.is_empty() {
+            write!(f, " in scope {}", self.scope.join("/"))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
+/// A failure reported by a [`Budget`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BudgetError<E> {
+    /// The budget was exceeded.
+    Exceeded(BudgetExceeded),
+
+    /// The underlying sink failed.
+    Sink(E),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for BudgetError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetError::Exceeded(exceeded) => exceeded.fmt(f),
+            BudgetError::Sink(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for BudgetError<E> {}
+
+impl<C> Budget<C> {
+    /// Constructs a [`Budget`] which forwards at most `limit` constraints to the given sink.
+    pub fn new(inner: C, limit: usize) -> Self {
+        Self {
+            inner,
+            limit,
+            num_constraints: 0,
+            scope: Vec::new(),
+            exceeded: None,
+            on_exceeded: None,
+        }
+    }
+
+    /// Sets a callback to be invoked when the budget is first exceeded. This may panic to abort
+    /// synthesis immediately, even within gadgets that don't report failures.
+    pub fn with_callback(mut self, callback: impl FnMut(&BudgetExceeded) + 'static) -> Self {
+        self.on_exceeded = Some(Box::new(callback));
+        self
+    }
+
+    /// Describes where the budget was first exceeded, if it has been.
+    pub fn exceeded(&self) -> Option<&BudgetExceeded> {
+        self.exceeded.as_ref()
+    }
+
+    /// Gets the sink which constraints are forwarded to.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Gets the sink which constraints are forwarded to, discarding this wrapper.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<F, C: ConstraintSink<F>> ConstraintSink<F> for Budget<C> {
+    type Error = BudgetError<C::Error>;
+    fn push(&mut self, constraint: ProductConstraint<F>) {
+        if self.num_constraints < self.limit {
+            self.num_constraints += 1;
+            self.inner.push(constraint)
+        } else if self.exceeded.is_none() {
+            let exceeded = BudgetExceeded {
+                limit: self.limit,
+                scope: self.scope.clone(),
+            };
+            if let Some(callback) = self.on_exceeded.as_mut() {
+                callback(&exceeded);
+            }
+            self.exceeded = Some(exceeded);
+        }
+    }
+
+    fn label(&mut self, var: Variable, name: &str) {
+        self.inner.label(var, name)
+    }
+
+    fn scope(&mut self, stack: &[String]) {
+        self.scope.clear();
+        self.scope.extend_from_slice(stack);
+        self.inner.scope(stack)
+    }
+
+    /// Reports where the budget was first exceeded, or otherwise any failure of the underlying
+    /// sink.
+    fn status(&self) -> Result<(), Self::Error> {
+        match &self.exceeded {
+            Some(exceeded) => Err(BudgetError::Exceeded(exceeded.clone())),
+            None => self.inner.status().map_err(BudgetError::Sink),
+        }
+    }
+}
+
+impl<F: Field> BudgetedArithmeticSystem<F> {
+    /// Constructs a new [`ArithmeticSystem`] which keeps at most `limit` constraints in memory,
+    /// and fails once more are introduced.
+    pub fn with_budget(limit: usize) -> Self {
+        Self::with_sink(Budget::new(Vec::new(), limit))
+    }
+
+    /// The constraints introduced into this system within its budget, in the order they were
+    /// introduced.
+    pub fn constraints(&self) -> &[ProductConstraint<F>] {
+        self.sink().inner()
+    }
+}
+
+#[test]
+fn test_budget() {
+    use bls12_381::Scalar;
+    let mut sys = BudgetedArithmeticSystem::<Scalar>::with_budget(10);
+    let a: Formula = sys.declare().into();
+    let b: Formula = sys.declare().into();
+    sys.push_scope("outer");
+    sys.push_scope("inner");
+    let mut acc = a;
+    let err = loop {
+        match SystemMul::<FieldElement<Scalar>>::try_mul(&mut sys, &acc, &b) {
+            Ok(r) => acc = r,
+            Err(err) => break err,
+        }
+    };
+    let expected = BudgetExceeded {
+        limit: 10,
+        scope: vec!["outer".to_owned(), "inner".to_owned()],
+    };
+    assert_eq!(err, BudgetError::Exceeded(expected));
+    assert_eq!(
+        err.to_string(),
+        "exceeded budget of 10 constraints in scope outer/inner"
+    );
+    assert_eq!(sys.constraints().len(), 10);
+    assert_eq!(sys.num_constraints(), 11);
+
+    // The callback is invoked only once, at the first constraint beyond the budget
+    let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+    let sink = Budget::new(Vec::new(), 1).with_callback({
+        let calls = calls.clone();
+        move |_| calls.set(calls.get() + 1)
+    });
+    let mut sys = ArithmeticSystem::<Scalar, _>::with_sink(sink);
+    for _ in 0..3 {
+        let a: Formula = sys.declare().into();
+        let b: Formula = sys.declare().into();
+        SystemBitAnd::<bool>::and(&mut sys, &a, &b);
+    }
+    assert_eq!(calls.get(), 1);
+    let expected = BudgetExceeded {
+        limit: 1,
+        scope: Vec::new(),
+    };
+    assert_eq!(sys.sink().exceeded(), Some(&expected));
+    assert!(SystemRepr::<bool>::status(&sys).is_err());
+}
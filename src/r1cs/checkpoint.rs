@@ -0,0 +1,202 @@
+use super::stream::{invalid_data, write_formula, write_varint};
+use super::*;
+use std::io::{self, Read, Write};
+
+/// The bytes at the start of every checkpoint.
+const MAGIC: &[u8; 4] = b"RCKP";
+
+/// The version of the checkpoint format.
+const VERSION: u8 = 1;
+
+/// Handle tags.
+const HANDLE_VAR: u8 = 0;
+const HANDLE_STORED: u8 = 1;
+
+impl<F: PrimeField, C: ConstraintSink<F>> ArithmeticSystem<F, C> {
+    /// Writes a checkpoint of this system to a stream, so that building can be resumed later
+    /// using [`ArithmeticSystem::resume`]. `handles` are the formulas that building will
+    /// continue from, which are restored along with the system.
+    ///
+    /// The checkpoint does not include the constraints which have already been sent to the
+    /// [`ConstraintSink`]. For a [`StreamingArithmeticSystem`], those are already on disk, and
+    /// the resumed system should write to a new segment. For systems which keep their
+    /// constraints in memory, use [`ArithmeticSystem::save`] instead. Assumptions recorded with
+    /// [`ArithmeticSystem::assume`], scopes and independent instances are not included, and
+    /// [`Word`]s will be decomposed again if their bits are needed.
+    pub fn write_checkpoint(&self, mut writer: impl Write, handles: &[Formula]) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        write_varint(&mut writer, self.num_vars as u64)?;
+        let mut labels: Vec<_> = self.labels.iter().collect();
+        labels.sort();
+        write_varint(&mut writer, labels.len() as u64)?;
+        for (var, name) in labels {
+            write_varint(&mut writer, u64::from(*var))?;
+            write_varint(&mut writer, name.len() as u64)?;
+            writer.write_all(name.as_bytes())?;
+        }
+        write_varint(&mut writer, self.public.len() as u64)?;
+        for var in self.public.iter() {
+            write_varint(&mut writer, u64::from(var.0))?;
+        }
+
+        // The first two formulas are always the constants 0 and 1
+        write_varint(&mut writer, (self.formulas.len() - 2) as u64)?;
+        for formula in self.formulas[2..].iter() {
+            write_formula(&mut writer, formula)?;
+        }
+        write_varint(&mut writer, handles.len() as u64)?;
+        for handle in handles {
+            let (tag, index) = match handle.0 {
+                FormulaRef::Var(index) => (HANDLE_VAR, index),
+                FormulaRef::Stored(index) => (HANDLE_STORED, index),
+            };
+            writer.write_all(&[tag])?;
+            write_varint(&mut writer, u64::from(index))?;
+        }
+        writer.flush()
+    }
+
+    /// Resumes a system from a checkpoint written by [`ArithmeticSystem::write_checkpoint`],
+    /// sending new constraints to the given [`ConstraintSink`]. Variables and formulas keep the
+    /// same numbering, so building continues exactly as if it had not been interrupted. Returns
+    /// the system along with the restored handles.
+    pub fn resume(reader: impl Read, sink: C) -> io::Result<(Self, Vec<Formula>)> {
+        let mut reader = ConstraintReader::<F, _>::headerless(reader);
+        let mut header = [0; 5];
+        for byte in header.iter_mut() {
+            *byte = reader.read_byte()?;
+        }
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(invalid_data("unrecognized checkpoint header"));
+        }
+        let mut res = Self::with_sink(sink);
+        res.num_vars = u32::try_from(reader.read_varint()?)
+            .map_err(|_| invalid_data("too many variables"))? as usize;
+        let in_range = |var: u64| {
+            (var < res.num_vars as u64)
+                .then_some(var as u32)
+                .ok_or_else(|| invalid_data("invalid variable index"))
+        };
+        for _ in 0..reader.read_varint()? {
+            reader.read_label()?;
+        }
+        for (var, name) in reader.labels() {
+            let var = in_range(*var as u64)?;
+            res.constraints.label(Variable(var), name);
+            res.labels.insert(var, name.clone());
+        }
+        for _ in 0..reader.read_varint()? {
+            let var = in_range(reader.read_varint()?)?;
+            res.public.push(Variable(var));
+        }
+        for _ in 0..reader.read_varint()? {
+            let formula = reader.read_formula()?;
+            if formula.dim() > res.num_vars {
+                return Err(invalid_data("invalid variable index"));
+            }
+            res.formulas.push(formula);
+        }
+        let mut handles = Vec::new();
+        for _ in 0..reader.read_varint()? {
+            let tag = reader.read_byte()?;
+            let index = reader.read_varint()?;
+            handles.push(Formula(match tag {
+                HANDLE_VAR => FormulaRef::Var(in_range(index)?),
+                HANDLE_STORED if index < res.formulas.len() as u64 => {
+                    FormulaRef::Stored(index as u32)
+                }
+                HANDLE_STORED => return Err(invalid_data("invalid formula index")),
+                _ => return Err(invalid_data("unrecognized handle tag")),
+            }));
+        }
+        Ok((res, handles))
+    }
+}
+
+impl<F: PrimeField> ArithmeticSystem<F> {
+    /// Writes a checkpoint of this system, including its constraints, to a stream, so that it
+    /// can be restored later using [`ArithmeticSystem::load`]. `handles` are the formulas that
+    /// building will continue from, which are restored along with the system.
+    pub fn save(&self, mut writer: impl Write, handles: &[Formula]) -> io::Result<()> {
+        self.write_checkpoint(&mut writer, handles)?;
+        let mut constraints = ConstraintWriter::headerless(writer);
+        for constraint in self.constraints.iter() {
+            constraints.push(constraint.clone());
+        }
+        constraints.finish(self.num_vars)?;
+        Ok(())
+    }
+
+    /// Restores a system from a checkpoint written by [`ArithmeticSystem::save`], returning
+    /// the system along with the restored handles.
+    pub fn load(mut reader: impl Read) -> io::Result<(Self, Vec<Formula>)> {
+        let (mut res, handles) = Self::resume(&mut reader, Vec::new())?;
+        let mut constraints = ConstraintReader::headerless(reader);
+        for constraint in constraints.by_ref() {
+            let constraint = constraint?;
+            if constraint.dim() > res.num_vars {
+                return Err(invalid_data("invalid variable index"));
+            }
+            res.constraints.push(constraint);
+        }
+        res.num_constraints = res.constraints.len();
+        if constraints.num_vars() != Some(res.num_vars) {
+            return Err(invalid_data("inconsistent number of variables"));
+        }
+        Ok((res, handles))
+    }
+}
+
+#[test]
+fn test_checkpoint() {
+    use bls12_381::Scalar;
+
+    // Builds a chain of products, allowing for a checkpoint in the middle
+    fn start<C: ConstraintSink<Scalar>>(sys: &mut ArithmeticSystem<Scalar, C>) -> [Formula; 2] {
+        let x = Formula::from(sys.declare_labeled("x"));
+        let y = Formula::from(sys.declare_public());
+        let sum = sys.sum(&[x, y]);
+        [sys.product(x, sum), sum]
+    }
+    fn finish(sys: &mut ArithmeticSystem<Scalar>, [mut acc, sum]: [Formula; 2]) {
+        for _ in 0..4 {
+            acc = sys.product(acc, sum);
+            let bit = sys.declare_bool();
+            acc = sys.sum(&[acc, bit]);
+        }
+    }
+    let mut direct = ArithmeticSystem::new();
+    let handles = start(&mut direct);
+    finish(&mut direct, handles);
+
+    // Resume from an in-memory checkpoint
+    let mut sys = ArithmeticSystem::new();
+    let handles = start(&mut sys);
+    let mut data = Vec::new();
+    sys.save(&mut data, &handles).unwrap();
+    let (mut resumed, handles) = ArithmeticSystem::<Scalar>::load(&data[..]).unwrap();
+    assert_eq!(resumed.constraints(), sys.constraints());
+    assert_eq!(resumed.label(Variable(0)), Some("x"));
+    assert_eq!(resumed.public_inputs(), [Variable(1)]);
+    finish(&mut resumed, handles.try_into().unwrap());
+    assert_eq!(resumed.num_vars(), direct.num_vars());
+    assert_eq!(resumed.constraints(), direct.constraints());
+
+    // Corrupted checkpoints are rejected
+    assert!(ArithmeticSystem::<Scalar>::load(&data[..data.len() - 1]).is_err());
+    data[0] = b'X';
+    assert!(ArithmeticSystem::<Scalar>::load(&data[..]).is_err());
+
+    // Resume a streaming system into a new segment
+    let mut first = StreamingArithmeticSystem::<Scalar, _>::streaming(Vec::new()).unwrap();
+    let handles = start(&mut first);
+    let mut data = Vec::new();
+    first.write_checkpoint(&mut data, &handles).unwrap();
+    let segment = ConstraintWriter::new(Vec::new()).unwrap();
+    let (second, resumed) =
+        StreamingArithmeticSystem::<Scalar, _>::resume(&data[..], segment).unwrap();
+    assert_eq!(resumed, handles);
+    assert_eq!(second.num_vars(), first.num_vars());
+    assert_eq!(second.sink().num_constraints(), 0);
+}
@@ -0,0 +1,203 @@
+use super::*;
+use std::collections::HashSet;
+
+/// Describes the simplifications made by [`ArithmeticSystem::deduplicate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DedupReport {
+    /// The number of variables which were replaced by another variable they were constrained to
+    /// be equal to.
+    pub merged_vars: usize,
+
+    /// The number of constraints which were removed.
+    pub eliminated: usize,
+}
+
+impl<F: PrimeField> ArithmeticSystem<F> {
+    /// Simplifies the constraints of this system by merging variables which are constrained to
+    /// be equal (by constraints of the form `x - y = 0`) and removing exact duplicate constraints.
+    ///
+    /// Merged variables are no longer referenced by any constraint, but are not removed. They can
+    /// be dropped using [`ArithmeticSystem::finalize`]. Any assignment which satisfied the system
+    /// before will still satisfy it.
+    pub fn deduplicate(&mut self) -> DedupReport {
+        let mut report = DedupReport::default();
+
+        // Find equalities between variables, preferring to keep public inputs and earlier
+        // variables as representatives
+        let mut parent: Vec<u32> = (0..self.num_vars as u32).collect();
+        let mut is_public = vec![false; self.num_vars];
+        for var in self.public.iter() {
+            is_public[var.index()] = true;
+        }
+        for constraint in self.constraints.iter() {
+            if let Some((x, y)) = constraint.as_equality() {
+                let x = find(&mut parent, x);
+                let y = find(&mut parent, y);
+                if x == y || (is_public[x as usize] && is_public[y as usize]) {
+                    continue;
+                }
+                let (rep, other) = match (is_public[x as usize], is_public[y as usize]) {
+                    (true, false) => (x, y),
+                    (false, true) => (y, x),
+                    _ => (x.min(y), x.max(y)),
+                };
+                parent[other as usize] = rep;
+                report.merged_vars += 1;
+            }
+        }
+
+        // Substitute representatives and drop trivial or repeated constraints
+        let mut seen = HashSet::new();
+        let num_constraints = self.constraints.len();
+        let constraints = std::mem::take(&mut self.constraints);
+        let mut num_kept = Vec::with_capacity(num_constraints + 1);
+        for constraint in constraints {
+            num_kept.push(self.constraints.len());
+            let constraint = ProductConstraint {
+                operand_a: substitute(&mut parent, constraint.operand_a),
+                operand_b: substitute(&mut parent, constraint.operand_b),
+                result: substitute(&mut parent, constraint.result),
+            };
+            let is_trivial = constraint.dim() == 0 && constraint.is_satisfied(&[]);
+            if !is_trivial && seen.insert(constraint.key()) {
+                self.constraints.push(constraint);
+            }
+        }
+        num_kept.push(self.constraints.len());
+        report.eliminated = num_constraints - self.constraints.len();
+        self.num_constraints = self.constraints.len();
+
+        // Scopes start at the first remaining constraint at or after their original start. Later
+        // scopes take precedence, so those left empty are hidden.
+        for (start, _) in self.scopes.iter_mut() {
+            *start = num_kept[*start];
+        }
+
+        // Assumptions should refer to the representatives, since merged variables are no longer
+        // constrained
+        let assumptions = std::mem::take(&mut self.assumptions);
+        for (formula, assumption) in assumptions {
+            let formula = substitute(&mut parent, self.formula(formula).into_owned());
+            let formula = self.alloc(formula);
+            self.assumptions.push((formula, assumption));
+        }
+
+        // For the same reason, words which are used later must be decomposed again
+        self.word_bits.clear();
+
+        // Constraints have moved, so instances can no longer be solved independently
+        self.instances.clear();
+        report
+    }
+}
+
+impl<F: PrimeField> ProductConstraint<F> {
+    /// If this constraint is equivalent to `x = y` for variables `x` and `y`, returns those
+    /// variables.
+    fn as_equality(&self) -> Option<(u32, u32)> {
+        let (factor, other) = match (
+            self.operand_a.coeffs.is_empty(),
+            self.operand_b.coeffs.is_empty(),
+        ) {
+            (true, _) => (self.operand_a.constant_term, &self.operand_b),
+            (_, true) => (self.operand_b.constant_term, &self.operand_a),
+            _ => return None,
+        };
+        if factor.is_zero_vartime() {
+            return None;
+        }
+        let diff = LinearFormula::combine([(factor, other), (-F::one(), &self.result)]);
+        match diff.coeffs.as_slice() {
+            [(x, a), (y, b)] if diff.constant_term.is_zero_vartime() && *a == -*b => Some((*x, *y)),
+            _ => None,
+        }
+    }
+
+    /// A byte string which uniquely identifies this constraint.
+    fn key(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        for formula in [&self.operand_a, &self.operand_b, &self.result] {
+            formula.write_key(&mut res);
+        }
+        res
+    }
+}
+
+impl<F: PrimeField> LinearFormula<F> {
+    /// Appends a byte string which uniquely identifies this formula to `res`.
+    pub(super) fn write_key(&self, res: &mut Vec<u8>) {
+        res.extend_from_slice(self.constant_term.to_repr().as_ref());
+        res.extend_from_slice(&(self.coeffs.len() as u32).to_le_bytes());
+        for (k, v) in self.coeffs.iter() {
+            res.extend_from_slice(&k.to_le_bytes());
+            res.extend_from_slice(v.to_repr().as_ref());
+        }
+    }
+}
+
+/// Finds the representative of the given variable, compressing paths along the way.
+fn find(parent: &mut [u32], var: u32) -> u32 {
+    let mut root = var;
+    while parent[root as usize] != root {
+        root = parent[root as usize];
+    }
+    let mut var = var;
+    while parent[var as usize] != root {
+        let next = parent[var as usize];
+        parent[var as usize] = root;
+        var = next;
+    }
+    root
+}
+
+/// Replaces all variables in the given formula with their representatives.
+fn substitute<F: Field>(parent: &mut [u32], formula: LinearFormula<F>) -> LinearFormula<F> {
+    if formula
+        .coeffs
+        .iter()
+        .all(|(k, _)| parent[*k as usize] == *k)
+    {
+        return formula;
+    }
+    let vars: Vec<_> = formula
+        .coeffs
+        .iter()
+        .map(|(k, v)| (*v, LinearFormula::from(Variable(find(parent, *k)))))
+        .collect();
+    let constant = LinearFormula::constant(formula.constant_term);
+    LinearFormula::combine(
+        vars.iter()
+            .map(|(v, f)| (*v, f))
+            .chain(std::iter::once((F::one(), &constant))),
+    )
+}
+
+#[test]
+fn test_deduplicate() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let a = Formula::from(sys.declare());
+    let b = Formula::from(sys.declare());
+    let c = Formula::from(sys.declare());
+    let d = Formula::from(sys.declare());
+
+    // `c` and `d` are both the product of `a` and `b`, and are asserted to be equal
+    sys.constrain(a, b, c);
+    sys.constrain(a, b, d);
+    sys.constrain(a, b, c);
+    SystemAssertEq::<FieldElement<Scalar>>::assert_eq(&mut sys, &c, &d);
+    assert_eq!(sys.constraints().len(), 4);
+    let report = sys.deduplicate();
+    assert_eq!(
+        report,
+        DedupReport {
+            merged_vars: 1,
+            eliminated: 3
+        }
+    );
+    assert_eq!(sys.constraints().len(), 1);
+    let assignment: Vec<_> = [3, 5, 15, 15].into_iter().map(Scalar::from).collect();
+    assert!(sys.is_satisfied(&assignment));
+    let assignment: Vec<_> = [3, 5, 14, 15].into_iter().map(Scalar::from).collect();
+    assert!(!sys.is_satisfied(&assignment));
+}
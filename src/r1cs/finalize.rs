@@ -0,0 +1,139 @@
+use super::*;
+
+/// Describes how the variables of an [`ArithmeticSystem`] were renumbered by
+/// [`ArithmeticSystem::finalize`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Renumbering {
+    /// The new index for each original variable, or `None` if it was dropped.
+    map: Vec<Option<u32>>,
+    num_public: usize,
+    num_vars: usize,
+}
+
+impl Renumbering {
+    /// Gets the new variable corresponding to the given original variable, or `None` if it was
+    /// dropped.
+    pub fn get(&self, var: Variable) -> Option<Variable> {
+        self.map[var.index()].map(Variable)
+    }
+
+    /// The number of public input variables. These occupy the first indices of the renumbered
+    /// system.
+    pub fn num_public(&self) -> usize {
+        self.num_public
+    }
+
+    /// Translates an assignment for the original system into an assignment for the renumbered
+    /// system.
+    pub fn apply<T: Clone>(&self, assignment: &[T]) -> Vec<T> {
+        assert_eq!(assignment.len(), self.map.len());
+        let mut res = vec![None; self.num_vars];
+        for (value, index) in assignment.iter().zip(self.map.iter()) {
+            if let Some(index) = index {
+                res[*index as usize] = Some(value.clone());
+            }
+        }
+        res.into_iter().map(|value| value.unwrap()).collect()
+    }
+
+    /// Translates the wires of a [`Manifest`](crate::Manifest) describing the original system.
+    /// Wires whose variables were dropped become `None`.
+    pub fn apply_manifest(&self, manifest: &crate::Manifest) -> crate::Manifest {
+        manifest.map_wires(|wire| self.map[wire].map(|index| index as usize))
+    }
+
+    /// Translates a formula whose variables all survive renumbering.
+    fn rename<F: Field>(&self, formula: LinearFormula<F>) -> LinearFormula<F> {
+        let mut coeffs: SmallVec<_> = formula
+            .coeffs
+            .into_iter()
+            .map(|(k, v)| (self.map[k as usize].unwrap(), v))
+            .collect();
+        coeffs.sort_unstable_by_key(|(k, _)| *k);
+        LinearFormula {
+            constant_term: formula.constant_term,
+            coeffs,
+        }
+    }
+}
+
+impl<F: Field> ArithmeticSystem<F> {
+    /// Renumbers the variables of this system so that public inputs come first, in declaration
+    /// order, followed by the remaining variables which are referenced by some constraint.
+    /// Unreferenced variables which aren't public inputs are dropped.
+    ///
+    /// Since [`Formula`] handles and [`Variable`]s refer to the original numbering, this consumes
+    /// the system. The returned [`Renumbering`] can be used to translate them. Assumptions
+    /// recorded with [`ArithmeticSystem::assume`] and independent instances are discarded.
+    pub fn finalize(self) -> (Self, Renumbering) {
+        let mut used = vec![false; self.num_vars];
+        for constraint in self.constraints.iter() {
+            for var in constraint.vars() {
+                used[var.index()] = true;
+            }
+        }
+        let mut map = vec![None; self.num_vars];
+        let mut num_vars = 0;
+        for var in self.public.iter() {
+            map[var.index()] = Some(num_vars);
+            num_vars += 1;
+        }
+        for (index, used) in used.into_iter().enumerate() {
+            if used && map[index].is_none() {
+                map[index] = Some(num_vars);
+                num_vars += 1;
+            }
+        }
+        let renumbering = Renumbering {
+            map,
+            num_public: self.public.len(),
+            num_vars: num_vars as usize,
+        };
+
+        let mut res = ArithmeticSystem::new();
+        res.num_vars = renumbering.num_vars;
+        res.public = (0..self.public.len() as u32).map(Variable).collect();
+        res.labels = self
+            .labels
+            .into_iter()
+            .filter_map(|(var, name)| Some((renumbering.map[var as usize]?, name)))
+            .collect();
+        res.constraints = self
+            .constraints
+            .into_iter()
+            .map(|c| ProductConstraint {
+                operand_a: renumbering.rename(c.operand_a),
+                operand_b: renumbering.rename(c.operand_b),
+                result: renumbering.rename(c.result),
+            })
+            .collect();
+        res.num_constraints = self.num_constraints;
+        res.scopes = self.scopes;
+        (res, renumbering)
+    }
+}
+
+#[test]
+fn test_finalize() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let a = Formula::from(sys.declare());
+    let _unused = sys.declare();
+    let out = sys.declare_public();
+    let b = Formula::from(sys.declare_labeled("b"));
+    let prod = SystemMul::<FieldElement<Scalar>>::mul(&mut sys, &a, &b);
+    let out_formula = Formula::from(out);
+    SystemAssertEq::<FieldElement<Scalar>>::assert_eq(&mut sys, &prod, &out_formula);
+
+    let assignment: Vec<_> = [3, 0, 21, 7, 21].into_iter().map(Scalar::from).collect();
+    assert!(sys.is_satisfied(&assignment));
+    let (sys, renumbering) = sys.finalize();
+    assert_eq!(renumbering.num_public(), 1);
+    assert_eq!(renumbering.get(out), Some(Variable(0)));
+    assert_eq!(renumbering.get(Variable(1)), None);
+    assert_eq!(sys.num_vars(), 4);
+    assert_eq!(sys.label(Variable(2)), Some("b"));
+    let assignment = renumbering.apply(&assignment);
+    assert_eq!(assignment[0], Scalar::from(21));
+    assert!(sys.is_satisfied(&assignment));
+}
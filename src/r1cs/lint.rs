@@ -0,0 +1,517 @@
+use super::*;
+use std::collections::HashSet;
+
+/// A property of a formula which a gadget relies on, but does not enforce itself. These are
+/// recorded using [`ArithmeticSystem::assume`] and checked by [`ArithmeticSystem::lint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Assumption {
+    /// The formula is either 0 or 1.
+    Bool,
+
+    /// The formula is a `bits`-bit integer, using two's complement if `signed` is set.
+    Range { bits: usize, signed: bool },
+}
+
+impl Assumption {
+    /// The smallest and largest integers satisfying this assumption.
+    fn bounds(self) -> Interval {
+        match self {
+            Assumption::Bool => (0, 1),
+            Assumption::Range {
+                bits,
+                signed: false,
+            } => (0, (1 << bits.min(126)) - 1),
+            Assumption::Range { bits, signed: true } => {
+                let half = 1 << bits.saturating_sub(1).min(126);
+                (-half, half - 1)
+            }
+        }
+    }
+}
+
+/// Describes the potential soundness problems found by [`ArithmeticSystem::lint`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LintReport {
+    /// Variables which aren't public inputs and aren't referenced by any constraint, so they may
+    /// take any value.
+    pub unconstrained: Vec<Variable>,
+
+    /// Formulas which a gadget assumed to have some property, such as being a boolean or a
+    /// range-limited integer, which the constraints don't enforce. Each formula and assumption
+    /// is listed once, in the order they were first recorded.
+    pub unchecked: Vec<(Formula, Assumption)>,
+}
+
+impl LintReport {
+    /// Determines whether no problems were found.
+    pub fn is_clean(&self) -> bool {
+        self.unconstrained.is_empty() && self.unchecked.is_empty()
+    }
+}
+
+/// An inclusive range of integers.
+type Interval = (i128, i128);
+
+impl<F: PrimeField> ArithmeticSystem<F> {
+    /// Checks this system for the classic under-constraining bugs: witness variables which
+    /// appear in no constraint, and formulas which gadgets use as booleans or range-limited
+    /// integers (see [`ArithmeticSystem::assume`]) without those properties being enforced.
+    ///
+    /// The analysis infers the possible integer values of variables from the constraint patterns
+    /// produced by this crate's gadgets, such as booleanity constraints, products and bit
+    /// decompositions. Everything it infers is guaranteed by the constraints, but it may report
+    /// false positives for properties enforced in ways it doesn't recognize. Since assumptions
+    /// aren't preserved by [`ArithmeticSystem::finalize`] or checkpoints, this should be used
+    /// beforehand.
+    pub fn lint(&self) -> LintReport {
+        let mut used = vec![false; self.num_vars];
+        for constraint in self.constraints.iter() {
+            for var in constraint.vars() {
+                used[var.index()] = true;
+            }
+        }
+        for var in self.public.iter() {
+            used[var.index()] = true;
+        }
+        let unconstrained = (0..self.num_vars as u32)
+            .filter(|var| !used[*var as usize])
+            .map(Variable)
+            .collect();
+
+        // Propagate ranges through the constraints until nothing changes. Ranges only ever
+        // shrink, so this terminates.
+        let mut analysis = Analysis {
+            ranges: vec![None; self.num_vars],
+            defs: (0..self.num_vars).map(|_| None).collect(),
+            limit: 1 << (F::CAPACITY as usize).saturating_sub(1).min(120),
+        };
+        for var in self.zero_tests() {
+            analysis.ranges[var as usize] = Some((0, 1));
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for constraint in self.constraints.iter() {
+                let Some((var, (lo, hi), def)) = analysis.infer(constraint) else {
+                    continue;
+                };
+                let current = &mut analysis.ranges[var as usize];
+                let new = match *current {
+                    Some((cur_lo, cur_hi)) => (cur_lo.max(lo), cur_hi.min(hi)),
+                    None => (lo, hi),
+                };
+                if *current != Some(new) {
+                    *current = Some(new);
+                    changed = true;
+                }
+                if def.is_some() {
+                    analysis.defs[var as usize] = def;
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut unchecked = Vec::new();
+        for (formula, assumption) in self.assumptions.iter() {
+            if !seen.insert((*formula, *assumption)) {
+                continue;
+            }
+            let (lo, hi) = assumption.bounds();
+            let range = IntFormula::new(&self.formula(*formula))
+                .and_then(|formula| analysis.range(&Def::linear(formula)));
+            if !matches!(range, Some((min, max)) if lo <= min && max <= hi) {
+                unchecked.push((*formula, *assumption));
+            }
+        }
+        LintReport {
+            unconstrained,
+            unchecked,
+        }
+    }
+
+    /// Finds the results of [`SystemInverse::is_zero`], which are constrained to be boolean by
+    /// the pair of constraints `value * res = 0` and `value * inv = 1 - res`.
+    fn zero_tests(&self) -> Vec<u32> {
+        let key = |a: &LinearFormula<F>, r: &LinearFormula<F>| {
+            let mut res = Vec::new();
+            a.write_key(&mut res);
+            r.write_key(&mut res);
+            res
+        };
+        let products: HashSet<_> = self
+            .constraints
+            .iter()
+            .map(|c| key(&c.operand_a, &c.result))
+            .collect();
+        let mut res = Vec::new();
+        for c in self.constraints.iter() {
+            match c.operand_b.coeffs.as_slice() {
+                [(var, coeff)]
+                    if *coeff == F::one()
+                        && c.operand_b.constant_term.is_zero_vartime()
+                        && c.result == LinearFormula::constant(F::zero()) =>
+                {
+                    let not =
+                        &LinearFormula::constant(F::one()) - &LinearFormula::from(Variable(*var));
+                    if products.contains(&key(&c.operand_a, &not)) {
+                        res.push(*var);
+                    }
+                }
+                _ => (),
+            }
+        }
+        res
+    }
+}
+
+/// The maximum depth to which [`Def`]s are expanded when computing ranges.
+const MAX_DEPTH: usize = 3;
+
+/// The maximum number of combinations of variable values which are enumerated when computing
+/// ranges.
+const MAX_COMBINATIONS: i128 = 256;
+
+/// The maximum number of variables whose values are enumerated when computing ranges.
+const MAX_LEAVES: usize = 16;
+
+/// The state of the range analysis performed by [`ArithmeticSystem::lint`].
+struct Analysis {
+    /// The known range of each variable.
+    ranges: Vec<Option<Interval>>,
+
+    /// The definitions of variables which were inferred from product constraints. Ranges of
+    /// formulas involving these variables can be computed more precisely by expanding them, since
+    /// interval arithmetic alone forgets that two terms depend on the same variable.
+    defs: Vec<Option<Def>>,
+
+    /// The maximum magnitude of an integer whose field representation is unambiguous.
+    limit: i128,
+}
+
+/// An expression of the form `sign * (a * b - rest)`.
+struct Def {
+    a: IntFormula,
+    b: IntFormula,
+    rest: IntFormula,
+    sign: i128,
+}
+
+impl Def {
+    /// Constructs a [`Def`] for the value of a linear formula.
+    fn linear(formula: IntFormula) -> Self {
+        Def {
+            a: formula,
+            b: IntFormula {
+                constant: 1,
+                terms: Vec::new(),
+            },
+            rest: IntFormula {
+                constant: 0,
+                terms: Vec::new(),
+            },
+            sign: 1,
+        }
+    }
+}
+
+impl Analysis {
+    /// Tries to infer the range of a variable from the given constraint, using the known ranges of
+    /// other variables. Also returns the definition of the variable, if it has one.
+    fn infer<F: PrimeField>(
+        &self,
+        c: &ProductConstraint<F>,
+    ) -> Option<(u32, Interval, Option<Def>)> {
+        if let Some(var) = booleanity(c) {
+            return Some((var, (0, 1), None));
+        }
+        let unknown: Vec<u32> = c
+            .vars()
+            .into_iter()
+            .map(|var| var.0)
+            .filter(|var| self.ranges[*var as usize].is_none())
+            .collect();
+        let [var] = unknown[..] else {
+            return None;
+        };
+        let constant =
+            |formula: &LinearFormula<F>| formula.coeffs.is_empty().then_some(formula.constant_term);
+        let factor = constant(&c.operand_a)
+            .map(|a| (a, &c.operand_b))
+            .or_else(|| constant(&c.operand_b).map(|b| (b, &c.operand_a)));
+        if let Some((factor, other)) = factor {
+            // A linear constraint `k * var + rest = 0`, so `var = -rest / k`
+            let sum = LinearFormula::combine([(factor, other), (-F::one(), &c.result)]);
+            let coeff = sum.coeffs.iter().find(|(k, _)| *k == var)?.1;
+            let inv: F = Option::from(coeff.invert())?;
+            let solved = IntFormula::new(&sum.scale(-inv))?.without(var);
+            let range = self.range(&Def::linear(solved))?;
+            Some((var, range, None))
+        } else {
+            // A product `a * b = k * var + rest` with `k = ±1`, so `var = k * (a * b - rest)`
+            if c.operand_a.coeffs.iter().any(|(k, _)| *k == var)
+                || c.operand_b.coeffs.iter().any(|(k, _)| *k == var)
+            {
+                return None;
+            }
+            let result = IntFormula::new(&c.result)?;
+            let sign = result.terms.iter().find(|(k, _)| *k == var)?.1;
+            if sign.abs() != 1 {
+                return None;
+            }
+            let def = Def {
+                a: IntFormula::new(&c.operand_a)?,
+                b: IntFormula::new(&c.operand_b)?,
+                rest: result.without(var),
+                sign,
+            };
+            let range = self.range(&def)?;
+            Some((var, range, Some(def)))
+        }
+    }
+
+    /// Computes a range containing all values of the given expression. This starts with
+    /// interval arithmetic, then refines the result by enumerating the values of the variables
+    /// the expression depends on, expanding their definitions more deeply each time, until the
+    /// range is narrow enough or there are too many combinations to enumerate.
+    fn range(&self, def: &Def) -> Option<Interval> {
+        let mut range = self.interval(def);
+        for depth in 0..=MAX_DEPTH {
+            if matches!(range, Some((lo, hi)) if hi - lo <= 1) {
+                break;
+            }
+            let mut leaves = Vec::new();
+            self.leaves(def, depth, &mut leaves);
+            if leaves.len() > MAX_LEAVES {
+                break;
+            }
+            leaves.sort_unstable();
+            leaves.dedup();
+            let Some(domains) = leaves
+                .iter()
+                .map(|var| self.ranges[*var as usize])
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            let combinations = domains.iter().try_fold(1i128, |acc, (lo, hi)| {
+                acc.checked_mul(hi.checked_sub(*lo)?.checked_add(1)?)
+                    .filter(|size| *size <= MAX_COMBINATIONS)
+            });
+            if combinations.is_none() {
+                break;
+            }
+            if let Some((lo, hi)) = self.enumerate(def, depth, &leaves, &domains) {
+                range = Some(match range {
+                    Some((cur_lo, cur_hi)) => (cur_lo.max(lo), cur_hi.min(hi)),
+                    None => (lo, hi),
+                });
+            }
+        }
+        range
+    }
+
+    /// Computes a range containing all values of the given expression using interval
+    /// arithmetic.
+    fn interval(&self, def: &Def) -> Option<Interval> {
+        let (a_lo, a_hi) = def.a.range(&self.ranges, self.limit)?;
+        let (b_lo, b_hi) = def.b.range(&self.ranges, self.limit)?;
+        let (r_lo, r_hi) = def.rest.range(&self.ranges, self.limit)?;
+        let corners = [
+            a_lo.checked_mul(b_lo)?,
+            a_lo.checked_mul(b_hi)?,
+            a_hi.checked_mul(b_lo)?,
+            a_hi.checked_mul(b_hi)?,
+        ];
+        let lo = corners.iter().min().unwrap().checked_sub(r_hi)?;
+        let hi = corners.iter().max().unwrap().checked_sub(r_lo)?;
+        let (lo, hi) = if def.sign < 0 { (-hi, -lo) } else { (lo, hi) };
+        (-self.limit < lo && hi < self.limit).then_some((lo, hi))
+    }
+
+    /// Collects the variables which the given expression depends on, expanding definitions to
+    /// the given depth. This stops early once more than [`MAX_LEAVES`] variables are found.
+    fn leaves(&self, def: &Def, depth: usize, res: &mut Vec<u32>) {
+        for formula in [&def.a, &def.b, &def.rest] {
+            for (var, _) in formula.terms.iter() {
+                if res.len() > MAX_LEAVES {
+                    return;
+                }
+                match &self.defs[*var as usize] {
+                    Some(def) if depth > 0 => self.leaves(def, depth - 1, res),
+                    _ => res.push(*var),
+                }
+            }
+        }
+    }
+
+    /// Computes the range of the given expression by enumerating all combinations of values for
+    /// the given leaves.
+    fn enumerate(
+        &self,
+        def: &Def,
+        depth: usize,
+        leaves: &[u32],
+        domains: &[Interval],
+    ) -> Option<Interval> {
+        let mut values: Vec<i128> = domains.iter().map(|(lo, _)| *lo).collect();
+        let mut lo = i128::MAX;
+        let mut hi = i128::MIN;
+        loop {
+            let value = self.eval(def, depth, leaves, &values)?;
+            lo = lo.min(value);
+            hi = hi.max(value);
+
+            // Advance to the next combination of values
+            let Some(index) = (0..leaves.len()).find(|i| values[*i] < domains[*i].1) else {
+                break;
+            };
+            values[index] += 1;
+            for (value, (lo, _)) in values[..index].iter_mut().zip(domains.iter()) {
+                *value = *lo;
+            }
+        }
+        (-self.limit < lo && hi < self.limit).then_some((lo, hi))
+    }
+
+    /// Evaluates the given expression for the given values of its leaves.
+    fn eval(&self, def: &Def, depth: usize, leaves: &[u32], values: &[i128]) -> Option<i128> {
+        let eval = |formula: &IntFormula| {
+            formula
+                .terms
+                .iter()
+                .try_fold(formula.constant, |acc, (var, coeff)| {
+                    let value = match &self.defs[*var as usize] {
+                        Some(def) if depth > 0 => self.eval(def, depth - 1, leaves, values)?,
+                        _ => values[leaves.binary_search(var).unwrap()],
+                    };
+                    acc.checked_add(coeff.checked_mul(value)?)
+                })
+        };
+        let value = eval(&def.a)?
+            .checked_mul(eval(&def.b)?)?
+            .checked_sub(eval(&def.rest)?)?;
+        value.checked_mul(def.sign)
+    }
+}
+
+/// If the given constraint is a non-zero multiple of `x * x = x` for some variable `x`, returns
+/// that variable.
+fn booleanity<F: PrimeField>(c: &ProductConstraint<F>) -> Option<u32> {
+    let (x, k1) = match c.operand_a.coeffs.as_slice() {
+        [(x, k1)] => (*x, *k1),
+        _ => return None,
+    };
+    let k2 = match c.operand_b.coeffs.as_slice() {
+        [(y, k2)] if *y == x => *k2,
+        _ => return None,
+    };
+    let k3 = match c.result.coeffs.as_slice() {
+        [] => F::zero(),
+        [(y, k3)] if *y == x => *k3,
+        _ => return None,
+    };
+    let (c1, c2, c3) = (
+        c.operand_a.constant_term,
+        c.operand_b.constant_term,
+        c.result.constant_term,
+    );
+
+    // `(c1 + k1 x) (c2 + k2 x) - (c3 + k3 x)` must equal `k1 k2 (x^2 - x)`
+    (c1 * c2 == c3 && c1 * k2 + c2 * k1 - k3 == -(k1 * k2)).then_some(x)
+}
+
+/// A [`LinearFormula`] whose constant and coefficients are all small integers.
+struct IntFormula {
+    constant: i128,
+    terms: Vec<(u32, i128)>,
+}
+
+impl IntFormula {
+    /// Converts a [`LinearFormula`] into an [`IntFormula`], if possible.
+    fn new<F: PrimeField>(formula: &LinearFormula<F>) -> Option<Self> {
+        Some(IntFormula {
+            constant: small_int(formula.constant_term)?,
+            terms: formula
+                .coeffs
+                .iter()
+                .map(|(k, v)| Some((*k, small_int(*v)?)))
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    /// Removes the term for the given variable.
+    fn without(mut self, var: u32) -> Self {
+        self.terms.retain(|(k, _)| *k != var);
+        self
+    }
+
+    /// Computes the range of values of this formula, given the ranges of its variables. Fails
+    /// if any variable has an unknown range, or if the result may exceed `limit` in magnitude,
+    /// in which case it could wrap around the field modulus.
+    fn range(&self, ranges: &[Option<Interval>], limit: i128) -> Option<Interval> {
+        let (lo, hi) =
+            self.terms
+                .iter()
+                .try_fold((self.constant, self.constant), |(lo, hi), (k, v)| {
+                    let (var_lo, var_hi) = ranges[*k as usize]?;
+                    let (a, b) = (v.checked_mul(var_lo)?, v.checked_mul(var_hi)?);
+                    Some((lo.checked_add(a.min(b))?, hi.checked_add(a.max(b))?))
+                })?;
+        (-limit < lo && hi < limit).then_some((lo, hi))
+    }
+}
+
+/// Interprets a field element as a small integer, positive or negative, assuming the usual
+/// little-endian representation.
+pub(super) fn small_int<F: PrimeField>(value: F) -> Option<i128> {
+    let to_u64 = |value: F| {
+        let repr = value.to_repr();
+        let bytes = repr.as_ref();
+        let (low, high) = bytes.split_at(bytes.len().min(8));
+        if high.iter().any(|byte| *byte != 0) {
+            return None;
+        }
+        let mut buf = [0; 8];
+        buf[..low.len()].copy_from_slice(low);
+        Some(i128::from(u64::from_le_bytes(buf)))
+    };
+    to_u64(value).or_else(|| to_u64(-value).map(|value| -value))
+}
+
+#[test]
+fn test_lint() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let a = sys.declare_bool();
+    let b: Formula = SystemRand::<bool>::rand(&mut sys);
+    let and = SystemBitAnd::<bool>::and(&mut sys, &a, &b);
+    let xor = SystemBitXor::<bool>::xor(&mut sys, &a, &b);
+    let or = SystemBitOr::<bool>::or(&mut sys, &and, &xor);
+    let x: Formula = SystemRand::<i32>::rand(&mut sys);
+    let zero = SystemInverse::<FieldElement<Scalar>>::is_zero(&mut sys, &x);
+    let sum = SystemWrappingAdd::<i32>::wrapping_add(&mut sys, &x, &x);
+    let lt = SystemOrd::<i32>::lt(&mut sys, &x, &sum);
+    let cond = SystemSelect::<bool>::select(&mut sys, &or, &zero, &lt);
+    SystemAssert::assert(&mut sys, &cond);
+    assert!(sys.lint().is_clean());
+
+    // Raw variables have no guaranteed range, and the range of a bit decomposition isn't enough
+    // to guarantee the range of an operand
+    let unused = sys.declare();
+    let raw = Formula::from(sys.declare());
+    let y = SystemSelect::<i32>::select(&mut sys, &raw, &x, &sum);
+    SystemOrd::<i32>::lt(&mut sys, &y, &x);
+    let z = Formula::from(sys.declare());
+    SystemOrd::<i32>::lt(&mut sys, &x, &z);
+    SystemOrd::<i32>::lt(&mut sys, &x, &z);
+    let report = sys.lint();
+    let range = Assumption::Range {
+        bits: 32,
+        signed: true,
+    };
+    assert_eq!(report.unconstrained, vec![unused]);
+    assert_eq!(
+        report.unchecked,
+        vec![(raw, Assumption::Bool), (y, range), (z, range)]
+    );
+}
@@ -0,0 +1,423 @@
+use super::witness::{propagate, Values};
+use super::*;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A gadget instance marked using [`ArithmeticSystem::independent`], whose witness can be
+/// generated separately from the rest of the system once its inputs are known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Instance {
+    /// The constraints introduced by the gadget.
+    constraints: Range<usize>,
+
+    /// The variables declared by the gadget.
+    vars: Range<usize>,
+}
+
+/// A gadget built on a worker thread by [`ArithmeticSystem::build_parallel`], waiting to be
+/// merged into the main system.
+struct Local<F> {
+    sys: ArithmeticSystem<F>,
+
+    /// The formulas in the main system for the inputs which are represented by placeholder
+    /// variables in the local system. The placeholder for `imports[i]` is the variable `i`.
+    imports: Vec<Formula>,
+
+    /// The outputs of the gadget, in terms of the local system.
+    outputs: Vec<Formula>,
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> ArithmeticSystem<F, C> {
+    /// Builds a gadget once for each of the given lists of inputs, on worker threads, and returns
+    /// the outputs of each instance. This can greatly speed up the construction of wide circuits,
+    /// such as those which verify many independent Merkle paths.
+    ///
+    /// Each instance is built in its own in-memory system, where non-constant inputs are replaced
+    /// by placeholder variables. The instances are then merged into this system in order, so the
+    /// resulting constraints and variable numbering don't depend on how the work was scheduled.
+    /// Instances don't share any cached state, such as the bits of [`Word`]s, so the result may
+    /// have more constraints than building them sequentially would. Scopes entered by the gadget
+    /// are nested within the current scope of this system.
+    pub fn build_parallel(
+        &mut self,
+        tasks: &[Vec<Formula>],
+        build: impl Fn(&mut ArithmeticSystem<F>, &[Formula]) -> Vec<Formula> + Sync,
+    ) -> Vec<Vec<Formula>> {
+        // Only constant inputs are visible to the gadget, so that it can simplify them as usual
+        let tasks: Vec<Vec<Result<F, Formula>>> = tasks
+            .iter()
+            .map(|inputs| {
+                inputs
+                    .iter()
+                    .map(|input| self.as_constant(*input).ok_or(*input))
+                    .collect()
+            })
+            .collect();
+        let num_threads = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(tasks.len());
+        let next = AtomicUsize::new(0);
+        let mut locals: Vec<Option<Local<F>>> = (0..tasks.len()).map(|_| None).collect();
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..num_threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut res = Vec::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(inputs) = tasks.get(index) else {
+                                break res;
+                            };
+                            res.push((index, Local::build(inputs, &build)));
+                        }
+                    })
+                })
+                .collect();
+            for worker in workers {
+                for (index, local) in worker.join().expect("worker thread panicked") {
+                    locals[index] = Some(local);
+                }
+            }
+        });
+        locals
+            .into_iter()
+            .map(|local| self.independent(|sys| sys.merge(local.expect("task was not built"))))
+            .collect()
+    }
+
+    /// Builds a gadget using the given function, marking it as an independent instance whose
+    /// witness can be generated in parallel with others by [`ArithmeticSystem::solve_parallel`].
+    /// [`ArithmeticSystem::build_parallel`] marks each of its instances this way.
+    ///
+    /// The gadget may read variables declared before it, but its constraints must not be needed
+    /// to determine their values, since an instance is only solved once all of its inputs are
+    /// known. Instances marked within the gadget are merged into it.
+    pub fn independent<R>(&mut self, build: impl FnOnce(&mut Self) -> R) -> R {
+        let constraints = self.num_constraints;
+        let vars = self.num_vars;
+        let res = build(self);
+        let instance = Instance {
+            constraints: constraints..self.num_constraints,
+            vars: vars..self.num_vars,
+        };
+        while matches!(self.instances.last(), Some(last) if last.constraints.start >= constraints) {
+            self.instances.pop();
+        }
+        self.instances.push(instance);
+        res
+    }
+
+    /// Merges a gadget built by [`ArithmeticSystem::build_parallel`] into this system, returning
+    /// its outputs.
+    fn merge(&mut self, local: Local<F>) -> Vec<Formula> {
+        let Local {
+            sys,
+            imports,
+            outputs,
+        } = local;
+        let num_imports = imports.len() as u32;
+        let offset = self.num_vars as u32;
+        self.num_vars += sys.num_vars - imports.len();
+        let rename = |this: &Self, formula: &LinearFormula<F>| {
+            let mut res = LinearFormula {
+                constant_term: formula.constant_term,
+                coeffs: formula
+                    .coeffs
+                    .iter()
+                    .filter(|(var, _)| *var >= num_imports)
+                    .map(|(var, coeff)| (var - num_imports + offset, *coeff))
+                    .collect(),
+            };
+            for (var, coeff) in formula.coeffs.iter() {
+                if let Some(import) = imports.get(*var as usize) {
+                    res.add_scaled(&this.formula(*import), *coeff);
+                }
+            }
+            res
+        };
+        let rename_var = |var: u32| Variable(var - num_imports + offset);
+        for (local_var, name) in sys.labels.iter() {
+            let var = rename_var(*local_var);
+            self.constraints.label(var, name);
+            self.labels.insert(var.0, name.clone());
+        }
+        self.public
+            .extend(sys.public.iter().map(|var| rename_var(var.0)));
+
+        // Nest the scopes of the gadget within the current scope
+        let base = self.scope_stack.clone();
+        let mut scope = "";
+        for (index, constraint) in sys.constraints.iter().enumerate() {
+            if sys.scope(index) != scope {
+                scope = sys.scope(index);
+                self.scope_stack.truncate(base.len());
+                if !scope.is_empty() {
+                    self.scope_stack.extend(scope.split('/').map(str::to_owned));
+                }
+                self.mark_scope();
+            }
+            let constraint = ProductConstraint {
+                operand_a: rename(self, &constraint.operand_a),
+                operand_b: rename(self, &constraint.operand_b),
+                result: rename(self, &constraint.result),
+            };
+            self.satisfy(constraint);
+        }
+        if !scope.is_empty() {
+            self.scope_stack = base;
+            self.mark_scope();
+        }
+
+        for (formula, assumption) in sys.assumptions.iter() {
+            let formula = rename(self, &sys.formula(*formula));
+            let formula = self.alloc(formula);
+            self.assume(formula, *assumption);
+        }
+        outputs
+            .iter()
+            .map(|output| {
+                let formula = rename(self, &sys.formula(*output));
+                self.alloc(formula)
+            })
+            .collect()
+    }
+}
+
+impl<F: PrimeField> ArithmeticSystem<F> {
+    /// Generates a complete assignment for this system like [`ArithmeticSystem::solve`], but
+    /// solves the instances marked by [`ArithmeticSystem::independent`] on worker threads. The
+    /// rest of the system is solved on the calling thread, and each instance is solved as soon as
+    /// all of its inputs are known. As with [`ArithmeticSystem::build_parallel`], the result
+    /// doesn't depend on how the work was scheduled.
+    pub fn solve_parallel(&self, known: &[(Variable, F)]) -> Result<Vec<F>, Unsolved> {
+        let mut values = vec![None; self.num_vars];
+        for (var, value) in known {
+            values[var.index()] = Some(*value);
+        }
+
+        // Find the constraints outside of every instance, and the inputs of each instance
+        let mut outside = Vec::new();
+        let mut start = 0;
+        for instance in self.instances.iter() {
+            outside.extend(&self.constraints[start..instance.constraints.start]);
+            start = instance.constraints.end;
+        }
+        outside.extend(&self.constraints[start..]);
+        let inputs: Vec<Vec<usize>> = (self.instances.iter())
+            .map(|instance| {
+                let mut inputs: Vec<usize> = self.constraints[instance.constraints.clone()]
+                    .iter()
+                    .flat_map(|c| c.vars())
+                    .map(|var| var.index())
+                    .filter(|var| !instance.vars.contains(var))
+                    .collect();
+                inputs.sort_unstable();
+                inputs.dedup();
+                inputs
+            })
+            .collect();
+
+        let mut pending: Vec<usize> = (0..self.instances.len()).collect();
+        loop {
+            let mut shared = Values::new(&[], 0, values);
+            propagate(outside.iter().copied(), &mut shared);
+            values = shared.local;
+            let (ready, rest): (Vec<usize>, Vec<usize>) = pending
+                .into_iter()
+                .partition(|i| inputs[*i].iter().all(|var| values[*var].is_some()));
+            pending = rest;
+            if ready.is_empty() {
+                break;
+            }
+
+            // Solve the instances which are ready on worker threads, then write their values
+            // back in order
+            let num_threads = std::thread::available_parallelism()
+                .map_or(1, |n| n.get())
+                .min(ready.len());
+            let next = AtomicUsize::new(0);
+            let mut solved: Vec<Option<Vec<Option<F>>>> = (0..ready.len()).map(|_| None).collect();
+            std::thread::scope(|scope| {
+                let workers: Vec<_> = (0..num_threads)
+                    .map(|_| {
+                        scope.spawn(|| {
+                            let mut res = Vec::new();
+                            loop {
+                                let index = next.fetch_add(1, Ordering::Relaxed);
+                                let Some(instance) = ready.get(index) else {
+                                    break res;
+                                };
+                                let instance = &self.instances[*instance];
+                                let mut local = Values::new(
+                                    &values,
+                                    instance.vars.start,
+                                    values[instance.vars.clone()].to_vec(),
+                                );
+                                propagate(
+                                    self.constraints[instance.constraints.clone()].iter(),
+                                    &mut local,
+                                );
+                                res.push((index, local.local));
+                            }
+                        })
+                    })
+                    .collect();
+                for worker in workers {
+                    for (index, local) in worker.join().expect("worker thread panicked") {
+                        solved[index] = Some(local);
+                    }
+                }
+            });
+            for (instance, local) in ready.iter().zip(solved) {
+                let vars = self.instances[*instance].vars.clone();
+                values[vars].copy_from_slice(&local.expect("instance was not solved"));
+            }
+        }
+
+        // Instances which never became ready may still be solvable along with the rest of the
+        // system, as by `solve`
+        if values.iter().any(Option::is_none) {
+            let mut all = Values::new(&[], 0, values);
+            propagate(self.constraints.iter(), &mut all);
+            values = all.local;
+        }
+        self.complete(values)
+    }
+}
+
+impl<F: PrimeField> Local<F> {
+    /// Builds a gadget for the given inputs, each of which is either a constant or a formula of
+    /// the main system.
+    fn build(
+        inputs: &[Result<F, Formula>],
+        build: &impl Fn(&mut ArithmeticSystem<F>, &[Formula]) -> Vec<Formula>,
+    ) -> Self {
+        let mut sys = ArithmeticSystem::new();
+        let mut imports = Vec::new();
+        let inputs: Vec<_> = inputs
+            .iter()
+            .map(|input| match input {
+                Ok(value) => sys.alloc(LinearFormula::constant(*value)),
+                Err(formula) => {
+                    imports.push(*formula);
+                    sys.declare().into()
+                }
+            })
+            .collect();
+        let outputs = build(&mut sys, &inputs);
+        Self {
+            sys,
+            imports,
+            outputs,
+        }
+    }
+}
+
+#[test]
+fn test_build_parallel() {
+    use bls12_381::Scalar;
+    fn gadget<C: ConstraintSink<Scalar>>(
+        sys: &mut ArithmeticSystem<Scalar, C>,
+        inputs: &[Formula],
+    ) -> Vec<Formula> {
+        let [a, b, c] = inputs else { unreachable!() };
+        sys.push_scope("gadget");
+        let prod = SystemMul::<FieldElement<Scalar>>::mul(sys, a, b);
+        let sum = sys.sum(&[prod, *c]);
+        let bits = sys.decompose(sum, 8, false);
+        sys.pop_scope();
+        let label = sys.declare_labeled("extra");
+        vec![sum, bits[0], label.into()]
+    }
+
+    // Build the same gadgets sequentially and in parallel, with some shared and constant inputs
+    let build = |parallel: bool| {
+        let mut sys = ArithmeticSystem::<Scalar>::new();
+        let shared: Formula = sys.declare_public().into();
+        let tasks: Vec<Vec<Formula>> = (0..20u64)
+            .map(|i| {
+                let a = sys.declare().into();
+                let c = sys.constant(FieldElement(Scalar::from(i)));
+                vec![a, shared, c]
+            })
+            .collect();
+        sys.push_scope("outer");
+        let outputs = if parallel {
+            sys.build_parallel(&tasks, gadget)
+        } else {
+            tasks
+                .iter()
+                .map(|inputs| gadget(&mut sys, inputs))
+                .collect()
+        };
+        sys.pop_scope();
+        let outputs: Vec<Vec<_>> = outputs
+            .iter()
+            .map(|outputs| {
+                outputs
+                    .iter()
+                    .map(|f| sys.formula(*f).into_owned())
+                    .collect()
+            })
+            .collect();
+        (sys, outputs)
+    };
+    let (seq, seq_outputs) = build(false);
+    let (par, par_outputs) = build(true);
+    assert_eq!(par.num_vars(), seq.num_vars());
+    assert_eq!(par.constraints(), seq.constraints());
+    assert_eq!(par_outputs, seq_outputs);
+    assert_eq!(par.public_inputs(), seq.public_inputs());
+    for index in 0..seq.constraints().len() {
+        assert_eq!(par.scope(index), seq.scope(index));
+    }
+    assert_eq!(par.scope(seq.constraints().len() - 1), "outer/gadget");
+    let var = Variable(seq.num_vars() as u32 - 1);
+    assert_eq!(par.label(var), Some("extra"));
+    assert_eq!(par.assumptions.len(), seq.assumptions.len());
+}
+
+#[test]
+fn test_solve_parallel() {
+    use bls12_381::Scalar;
+    fn gadget<C: ConstraintSink<Scalar>>(
+        sys: &mut ArithmeticSystem<Scalar, C>,
+        inputs: &[Formula],
+    ) -> Vec<Formula> {
+        let [a, b] = inputs else { unreachable!() };
+        let prod = SystemMul::<FieldElement<Scalar>>::mul(sys, a, b);
+        let is_zero = SystemInverse::<FieldElement<Scalar>>::is_zero(sys, &prod);
+        let bits = sys.decompose(prod, 16, false);
+        vec![sys.sum(&[bits[3], is_zero])]
+    }
+
+    // The second layer of instances depends on the outputs of the first, through constraints
+    // outside of every instance
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let shared = sys.declare();
+    let inputs: Vec<Variable> = (0..12).map(|_| sys.declare()).collect();
+    let tasks: Vec<Vec<Formula>> = (inputs.iter())
+        .map(|input| vec![(*input).into(), shared.into()])
+        .collect();
+    let outputs = sys.build_parallel(&tasks, gadget);
+    let total = sys.sum(&outputs.iter().map(|o| o[0]).collect::<Vec<_>>());
+    let total = SystemMul::<FieldElement<Scalar>>::mul(&mut sys, &total, &shared.into());
+    sys.independent(|sys| {
+        let inner = sys.independent(|sys| gadget(sys, &[total, total]));
+        gadget(sys, &[inner[0], shared.into()])
+    });
+    assert_eq!(sys.instances.len(), 13);
+    assert_eq!(sys.instances[12].constraints.end, sys.num_constraints());
+
+    let mut known = vec![(shared, Scalar::from(3))];
+    known.extend((inputs.iter()).map(|var| (*var, Scalar::from(var.index() as u64 * 5))));
+    let witness = sys.solve_parallel(&known).unwrap();
+    assert_eq!(witness, sys.solve(&known).unwrap());
+    assert_eq!(sys.check(&witness), Ok(()));
+
+    // Missing inputs are handled the same way
+    assert_eq!(sys.solve_parallel(&known[..1]), sys.solve(&known[..1]));
+    sys.declare_labeled("free");
+    let err = sys.solve_parallel(&known).unwrap_err();
+    assert_eq!(err.vars, ["free"]);
+}
@@ -0,0 +1,244 @@
+use super::*;
+
+/// A random-access memory for an [`ArithmeticSystem`], which checks the consistency of its
+/// accesses using a sorted transcript rather than scanning the whole memory on each access.
+///
+/// Each access is recorded with its address, value and time. [`TranscriptRam::finish`] then
+/// introduces a copy of the transcript sorted by address and time, proves it to be a permutation
+/// of the original using a randomized grand-product argument, and checks that each read in the
+/// sorted transcript returns the value of the preceding write to the same address. The cost of
+/// an access is thus independent of the size of the memory.
+#[derive(Debug, Clone)]
+pub struct TranscriptRam {
+    size: usize,
+    accesses: Vec<Access>,
+}
+
+/// An entry in the transcript of a [`TranscriptRam`]. Its time is its index in the transcript.
+#[derive(Debug, Clone, Copy)]
+struct Access {
+    addr: Formula,
+    value: Formula,
+    is_write: bool,
+}
+
+impl TranscriptRam {
+    /// Constructs a [`TranscriptRam`] with the given initial contents.
+    pub fn new<F: PrimeField, C: ConstraintSink<F>>(
+        sys: &mut ArithmeticSystem<F, C>,
+        init: &[Formula],
+    ) -> Self {
+        let accesses = init
+            .iter()
+            .enumerate()
+            .map(|(addr, value)| Access {
+                addr: sys.alloc(LinearFormula::constant(F::from(addr as u64))),
+                value: *value,
+                is_write: true,
+            })
+            .collect();
+        Self {
+            size: init.len(),
+            accesses,
+        }
+    }
+
+    /// The number of cells in this memory.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Determines whether this memory has no cells.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// The number of entries in the transcript so far, including the initial writes.
+    pub fn num_accesses(&self) -> usize {
+        self.accesses.len()
+    }
+
+    /// Reads the value at the given address. The result is a new variable, which must be
+    /// assigned the value most recently written to the address. Accessing an address outside of
+    /// the memory makes the system unsatisfiable.
+    pub fn read<F: PrimeField, C: ConstraintSink<F>>(
+        &mut self,
+        sys: &mut ArithmeticSystem<F, C>,
+        addr: Formula,
+    ) -> Formula {
+        let value = sys.declare().into();
+        self.accesses.push(Access {
+            addr,
+            value,
+            is_write: false,
+        });
+        value
+    }
+
+    /// Writes a value to the given address if `enable` is 1. This reads the existing value first,
+    /// as with [`TranscriptRam::read`], so that it can be written back if `enable` is 0.
+    pub fn write<F: PrimeField, C: ConstraintSink<F>>(
+        &mut self,
+        sys: &mut ArithmeticSystem<F, C>,
+        addr: Formula,
+        value: Formula,
+        enable: Formula,
+    ) {
+        let old = self.read(sys, addr);
+        let value = sys.select(enable, value, old);
+        self.accesses.push(Access {
+            addr,
+            value,
+            is_write: true,
+        });
+    }
+
+    /// Introduces the constraints which check the consistency of the transcript. `challenges`
+    /// must be chosen at random after the assignment for the transcript is fixed, for example as
+    /// public inputs derived using the Fiat-Shamir heuristic.
+    ///
+    /// The sorted transcript is declared first, as 4 consecutive variables per access: its
+    /// address, time, value and whether it is a write. Accesses are sorted by address, then time.
+    pub fn finish<F: PrimeField, C: ConstraintSink<F>>(
+        self,
+        sys: &mut ArithmeticSystem<F, C>,
+        challenges: [Formula; 2],
+    ) {
+        let sorted: Vec<_> = self
+            .accesses
+            .iter()
+            .map(|_| {
+                let addr = sys.declare().into();
+                let time = sys.declare().into();
+                let value = sys.declare().into();
+                let is_write = sys.declare_bool();
+                (addr, time, value, is_write)
+            })
+            .collect();
+
+        // Check that the sorted transcript is a permutation of the original by comparing the
+        // products of `beta - fingerprint` for each entry
+        let [alpha, beta] = challenges;
+        let alpha_2 = sys.product(alpha, alpha);
+        let alpha_3 = sys.product(alpha_2, alpha);
+        let fingerprint = |sys: &mut ArithmeticSystem<F, C>, addr, time, value, is_write| {
+            let value = sys.product(alpha_2, value);
+            let is_write = sys.product(alpha_3, is_write);
+            let time = sys.product(alpha, time);
+            let sum = sys.sum(&[addr, time, value, is_write]);
+            sys.diff(beta, sum)
+        };
+        let mut original = ONE;
+        for (time, access) in self.accesses.iter().enumerate() {
+            let time = sys.alloc(LinearFormula::constant(F::from(time as u64)));
+            let is_write = sys.constant(access.is_write);
+            let factor = fingerprint(sys, access.addr, time, access.value, is_write);
+            original = sys.product(original, factor);
+        }
+        let mut permuted = ONE;
+        for (addr, time, value, is_write) in sorted.iter() {
+            let factor = fingerprint(sys, *addr, *time, *value, *is_write);
+            permuted = sys.product(permuted, factor);
+        }
+        let diff = sys.diff(original, permuted);
+        sys.assert_zero(diff);
+
+        // Check ordering and consistency between adjacent entries. Since the initial writes
+        // cover the whole memory, the first access to each address must be a write.
+        let bits = [self.size, self.accesses.len()]
+            .iter()
+            .map(|n| (usize::BITS - n.leading_zeros()) as usize)
+            .max()
+            .unwrap();
+        if let Some((_, _, _, is_write)) = sorted.first() {
+            sys.assert(is_write);
+        }
+        for pair in sorted.windows(2) {
+            let (prev_addr, prev_time, prev_value, _) = pair[0];
+            let (addr, time, value, is_write) = pair[1];
+            let addr_delta = sys.diff(addr, prev_addr);
+            let same = SystemInverse::<FieldElement<F>>::is_zero(sys, &addr_delta);
+
+            // Either the address increases, or it stays the same and the time increases
+            let time_delta = sys.diff(time, prev_time);
+            let delta = sys.select(same, time_delta, addr_delta);
+            let delta = sys.diff(delta, ONE);
+            sys.decompose(delta, bits, false);
+            let new = SystemNot::<bool>::not(sys, &same);
+            sys.assert_if(&new, &is_write);
+
+            // Reads return the previous value at the same address
+            let is_read = SystemNot::<bool>::not(sys, &is_write);
+            let cond = sys.product(same, is_read);
+            let value_delta = sys.diff(value, prev_value);
+            sys.constrain(cond, value_delta, ZERO);
+        }
+    }
+}
+
+#[test]
+fn test_transcript_ram() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let init: Vec<Formula> = (0..3).map(|_| sys.declare().into()).collect();
+    let mut ram = TranscriptRam::new(&mut sys, &init);
+    let addr_a = Formula::from(sys.declare());
+    let addr_b = Formula::from(sys.declare());
+    let value = Formula::from(sys.declare());
+    let r_0 = ram.read(&mut sys, addr_a);
+    ram.write(&mut sys, addr_a, value, ONE);
+    let r_1 = ram.read(&mut sys, addr_b);
+    let alpha = Formula::from(sys.declare_public());
+    let beta = Formula::from(sys.declare_public());
+    let first_sorted = Variable(sys.num_vars() as u32);
+    let accesses = ram.num_accesses();
+    ram.finish(&mut sys, [alpha, beta]);
+
+    // Builds the assignment for the given memory contents and accesses
+    let witness = |cells: [u64; 3], a: u64, b: u64, v: u64, reads: [u64; 3]| {
+        let mut transcript: Vec<(u64, u64, u64, bool)> =
+            (0..3).map(|i| (i, i, cells[i as usize], true)).collect();
+        transcript.extend([
+            (a, 3, reads[0], false),
+            (a, 4, reads[1], false),
+            (a, 5, v, true),
+            (b, 6, reads[2], false),
+        ]);
+        transcript.sort();
+        let mut known: Vec<(Variable, Scalar)> = cells
+            .iter()
+            .chain(&[a, b, v, reads[0], reads[1], reads[2], 12345, 67890])
+            .enumerate()
+            .map(|(i, x)| (Variable(i as u32), Scalar::from(*x)))
+            .collect();
+        for (i, (addr, time, value, is_write)) in transcript.into_iter().enumerate() {
+            for (j, x) in [addr, time, value, is_write as u64].into_iter().enumerate() {
+                known.push((
+                    Variable(first_sorted.0 + (i * 4 + j) as u32),
+                    Scalar::from(x),
+                ));
+            }
+        }
+        sys.solve(&known).unwrap()
+    };
+    assert_eq!(accesses, 7);
+
+    // The arguments are the initial cells, the addresses and value written, and the three values
+    // read, including the one read by the write
+    let assignment = witness([10, 20, 30], 1, 1, 99, [20, 20, 99]);
+    assert_eq!(sys.check(&assignment), Ok(()));
+    assert_eq!(sys.eval(r_0, &assignment), Scalar::from(20));
+    assert_eq!(sys.eval(r_1, &assignment), Scalar::from(99));
+    let assignment = witness([10, 20, 30], 2, 0, 5, [30, 30, 10]);
+    assert_eq!(sys.check(&assignment), Ok(()));
+
+    // Reads must return the most recently written value
+    let assignment = witness([10, 20, 30], 1, 1, 99, [20, 20, 20]);
+    assert!(!sys.is_satisfied(&assignment));
+    let assignment = witness([10, 20, 30], 1, 0, 99, [21, 21, 10]);
+    assert!(!sys.is_satisfied(&assignment));
+
+    // Addresses must be in range
+    let assignment = witness([10, 20, 30], 3, 0, 99, [0, 0, 10]);
+    assert!(!sys.is_satisfied(&assignment));
+}
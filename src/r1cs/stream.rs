@@ -0,0 +1,386 @@
+use super::*;
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::marker::PhantomData;
+
+/// An [`ArithmeticSystem`] which writes its constraints to a stream as they are introduced,
+/// rather than keeping them in memory.
+pub type StreamingArithmeticSystem<F, W> = ArithmeticSystem<F, ConstraintWriter<W>>;
+
+/// The bytes at the start of every constraint stream.
+const MAGIC: &[u8; 4] = b"R1CS";
+
+/// The version of the constraint stream format.
+const VERSION: u8 = 1;
+
+/// Frame tag for a constraint.
+const FRAME_CONSTRAINT: u8 = 0;
+
+/// Frame tag for the end of the stream.
+const FRAME_END: u8 = 1;
+
+/// Frame tag for a variable label.
+const FRAME_LABEL: u8 = 2;
+
+/// Coefficient tags. Most coefficients in bit-level circuits are small, so the common ones get
+/// their own tag instead of a full field representation.
+const COEFF_ZERO: u8 = 0;
+const COEFF_ONE: u8 = 1;
+const COEFF_NEG_ONE: u8 = 2;
+const COEFF_FULL: u8 = 3;
+
+/// A [`ConstraintSink`] which encodes constraints into a [`Write`] stream. Since constraints
+/// are written individually, the stream should be buffered.
+pub struct ConstraintWriter<W> {
+    writer: W,
+    num_constraints: usize,
+
+    /// The first error encountered while writing, which will be reported by
+    /// [`ConstraintWriter::finish`].
+    error: Option<io::Error>,
+}
+
+impl<W: Write> ConstraintWriter<W> {
+    /// Constructs a [`ConstraintWriter`] over the given stream, writing the stream header
+    /// immediately.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        Ok(Self::headerless(writer))
+    }
+
+    /// Constructs a [`ConstraintWriter`] over the given stream, without writing a header. This
+    /// is used to embed constraints in other formats.
+    pub(super) fn headerless(writer: W) -> Self {
+        Self {
+            writer,
+            num_constraints: 0,
+            error: None,
+        }
+    }
+
+    /// The number of constraints written so far.
+    pub fn num_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    /// Writes the end of the stream, recording the total number of variables, and returns the
+    /// underlying writer. This reports the first error encountered while writing constraints.
+    pub fn finish(mut self, num_vars: usize) -> io::Result<W> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        self.writer.write_all(&[FRAME_END])?;
+        write_varint(&mut self.writer, num_vars as u64)?;
+        write_varint(&mut self.writer, self.num_constraints as u64)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<F: PrimeField, W: Write> ConstraintSink<F> for ConstraintWriter<W> {
+    type Error = io::Error;
+
+    fn push(&mut self, constraint: ProductConstraint<F>) {
+        if self.error.is_some() {
+            return;
+        }
+        let res = (|| {
+            self.writer.write_all(&[FRAME_CONSTRAINT])?;
+            write_formula(&mut self.writer, &constraint.operand_a)?;
+            write_formula(&mut self.writer, &constraint.operand_b)?;
+            write_formula(&mut self.writer, &constraint.result)
+        })();
+        match res {
+            Ok(()) => self.num_constraints += 1,
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    fn label(&mut self, var: Variable, name: &str) {
+        if self.error.is_some() {
+            return;
+        }
+        let res = (|| {
+            self.writer.write_all(&[FRAME_LABEL])?;
+            write_varint(&mut self.writer, u64::from(var.0))?;
+            write_varint(&mut self.writer, name.len() as u64)?;
+            self.writer.write_all(name.as_bytes())
+        })();
+        if let Err(err) = res {
+            self.error = Some(err);
+        }
+    }
+
+    /// Reports the first error encountered while writing. The error will also be reported by
+    /// [`ConstraintWriter::finish`], and no further constraints are written once it occurs.
+    fn status(&self) -> io::Result<()> {
+        match &self.error {
+            Some(err) => Err(io::Error::new(err.kind(), err.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<F: PrimeField, W: Write> StreamingArithmeticSystem<F, W> {
+    /// Constructs a new [`StreamingArithmeticSystem`] which writes its constraints to the given
+    /// stream.
+    pub fn streaming(writer: W) -> io::Result<Self> {
+        Ok(Self::with_sink(ConstraintWriter::new(writer)?))
+    }
+
+    /// Completes the constraint stream for this system and returns the underlying writer.
+    pub fn finish(self) -> io::Result<W> {
+        let num_vars = self.num_vars();
+        self.into_sink().finish(num_vars)
+    }
+}
+
+/// Writes an unsigned LEB128 integer.
+pub(super) fn write_varint(writer: &mut impl Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn write_coeff<F: PrimeField>(writer: &mut impl Write, coeff: &F) -> io::Result<()> {
+    if coeff.is_zero_vartime() {
+        writer.write_all(&[COEFF_ZERO])
+    } else if *coeff == F::one() {
+        writer.write_all(&[COEFF_ONE])
+    } else if *coeff == -F::one() {
+        writer.write_all(&[COEFF_NEG_ONE])
+    } else {
+        writer.write_all(&[COEFF_FULL])?;
+        writer.write_all(coeff.to_repr().as_ref())
+    }
+}
+
+/// Writes a formula, with variable indices encoded as deltas from the previous term.
+pub(super) fn write_formula<F: PrimeField>(
+    writer: &mut impl Write,
+    formula: &LinearFormula<F>,
+) -> io::Result<()> {
+    write_coeff(writer, &formula.constant_term)?;
+    write_varint(writer, formula.coeffs.len() as u64)?;
+    let mut last = 0;
+    for (var, coeff) in formula.coeffs.iter() {
+        write_varint(writer, u64::from(var - last))?;
+        write_coeff(writer, coeff)?;
+        last = *var;
+    }
+    Ok(())
+}
+
+/// Decodes the constraints written by a [`ConstraintWriter`] from a [`Read`] stream.
+pub struct ConstraintReader<F, R> {
+    reader: R,
+
+    /// The number of variables in the system, known once the end of the stream is reached.
+    num_vars: Option<usize>,
+
+    /// The labels for variables encountered so far, by index.
+    labels: BTreeMap<usize, String>,
+    _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField, R: Read> ConstraintReader<F, R> {
+    /// Constructs a [`ConstraintReader`] over the given stream, validating its header.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(invalid_data("unrecognized constraint stream header"));
+        }
+        Ok(Self::headerless(reader))
+    }
+
+    /// Constructs a [`ConstraintReader`] over the given stream, without reading a header. This
+    /// is used to decode constraints embedded in other formats.
+    pub(super) fn headerless(reader: R) -> Self {
+        Self {
+            reader,
+            num_vars: None,
+            labels: BTreeMap::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// The number of variables in the system, available once all constraints have been read.
+    pub fn num_vars(&self) -> Option<usize> {
+        self.num_vars
+    }
+
+    /// The labels for the variables declared so far, by index. Labels are always declared before
+    /// the variable they refer to is used in a constraint.
+    pub fn labels(&self) -> &BTreeMap<usize, String> {
+        &self.labels
+    }
+
+    pub(super) fn read_byte(&mut self) -> io::Result<u8> {
+        let mut byte = [0];
+        self.reader.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    pub(super) fn read_varint(&mut self) -> io::Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid_data("varint is too long"))
+    }
+
+    fn read_coeff(&mut self) -> io::Result<F> {
+        match self.read_byte()? {
+            COEFF_ZERO => Ok(F::zero()),
+            COEFF_ONE => Ok(F::one()),
+            COEFF_NEG_ONE => Ok(-F::one()),
+            COEFF_FULL => {
+                let mut repr = F::Repr::default();
+                self.reader.read_exact(repr.as_mut())?;
+                Option::from(F::from_repr(repr))
+                    .ok_or_else(|| invalid_data("non-canonical field element"))
+            }
+            _ => Err(invalid_data("unrecognized coefficient tag")),
+        }
+    }
+
+    pub(super) fn read_formula(&mut self) -> io::Result<LinearFormula<F>> {
+        let mut res = LinearFormula::constant(self.read_coeff()?);
+        let len = self.read_varint()?;
+        let mut last: u32 = 0;
+        for i in 0..len {
+            let delta = self.read_varint()?;
+            let var = u32::try_from(u64::from(last) + delta)
+                .ok()
+                .filter(|var| i == 0 || *var > last)
+                .ok_or_else(|| invalid_data("invalid variable index"))?;
+            let coeff = self.read_coeff()?;
+            if coeff.is_zero_vartime() {
+                return Err(invalid_data("zero coefficient"));
+            }
+            res.coeffs.push((var, coeff));
+            last = var;
+        }
+        Ok(res)
+    }
+
+    pub(super) fn read_label(&mut self) -> io::Result<()> {
+        let var = self.read_varint()? as usize;
+        let len = self.read_varint()?;
+        let mut name = Vec::new();
+        self.reader.by_ref().take(len).read_to_end(&mut name)?;
+        if name.len() as u64 != len {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        let name = String::from_utf8(name).map_err(|_| invalid_data("label is not UTF-8"))?;
+        self.labels.insert(var, name);
+        Ok(())
+    }
+
+    fn read_frame(&mut self) -> io::Result<Option<ProductConstraint<F>>> {
+        loop {
+            match self.read_byte()? {
+                FRAME_CONSTRAINT => {
+                    return Ok(Some(ProductConstraint {
+                        operand_a: self.read_formula()?,
+                        operand_b: self.read_formula()?,
+                        result: self.read_formula()?,
+                    }))
+                }
+                FRAME_LABEL => self.read_label()?,
+                FRAME_END => {
+                    let num_vars = self.read_varint()?;
+                    self.read_varint()?;
+                    self.num_vars = Some(num_vars as usize);
+                    return Ok(None);
+                }
+                _ => return Err(invalid_data("unrecognized frame tag")),
+            }
+        }
+    }
+}
+
+impl<F: PrimeField, R: Read> Iterator for ConstraintReader<F, R> {
+    type Item = io::Result<ProductConstraint<F>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.num_vars.is_some() {
+            return None;
+        }
+        self.read_frame().transpose()
+    }
+}
+
+pub(super) fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+#[test]
+fn test_stream_roundtrip() {
+    use bls12_381::Scalar;
+    fn build<C: ConstraintSink<Scalar>>(sys: &mut ArithmeticSystem<Scalar, C>) {
+        let a = Formula::from(sys.declare_labeled("a"));
+        let b = Formula::from(sys.declare());
+        let r = SystemMulShr::<i32>::mul_shr(sys, &a, &b, 7);
+        let is_zero = SystemInverse::<FieldElement<Scalar>>::is_zero(sys, &r);
+        let c = sys.constant(FieldElement(Scalar::from(12345)));
+        let c = sys.linear_combination(&[(Scalar::from(3), c), (-Scalar::one(), is_zero)]);
+        let d = SystemInverse::<FieldElement<Scalar>>::inverse_unchecked(sys, &a);
+        sys.constrain(c, d, b);
+    }
+    let mut mem = ArithmeticSystem::new();
+    build(&mut mem);
+    let mut streaming = StreamingArithmeticSystem::streaming(Vec::new()).unwrap();
+    build(&mut streaming);
+    let data = streaming.finish().unwrap();
+
+    let mut reader = ConstraintReader::<Scalar, _>::new(&data[..]).unwrap();
+    let read: Vec<_> = reader.by_ref().collect::<io::Result<_>>().unwrap();
+    assert_eq!(read, mem.constraints());
+    assert_eq!(reader.num_vars(), Some(mem.num_vars()));
+    assert_eq!(reader.labels().get(&0).map(|s| s.as_str()), Some("a"));
+
+    // A truncated stream is an error
+    let mut reader = ConstraintReader::<Scalar, _>::new(&data[..data.len() - 1]).unwrap();
+    assert!(reader.any(|c| c.is_err()));
+}
+
+#[test]
+fn test_stream_error() {
+    use bls12_381::Scalar;
+
+    // A stream with room for only a few constraints
+    let mut buf = [0u8; 64];
+    let mut sys = StreamingArithmeticSystem::<Scalar, _>::streaming(&mut buf[..]).unwrap();
+    let a: Formula = sys.declare().into();
+    let b: Formula = sys.declare().into();
+    let mut acc = a;
+    let err = loop {
+        match SystemMul::<FieldElement<Scalar>>::try_mul(&mut sys, &acc, &b) {
+            Ok(r) => acc = r,
+            Err(err) => break err,
+        }
+    };
+    assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+    assert!(SystemBitAnd::<bool>::try_and(&mut sys, &a, &b).is_err());
+    assert!(sys.finish().is_err());
+
+    // Systems which can't fail always succeed
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let a: Formula = sys.declare().into();
+    assert!(SystemAssertEq::<FieldElement<Scalar>>::try_assert_eq(&mut sys, &a, &a).is_ok());
+    assert_eq!(
+        SystemWrappingAdd::<u8>::try_wrapping_add(&mut Eval, &200, &100),
+        Ok(44)
+    );
+}
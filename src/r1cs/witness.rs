@@ -0,0 +1,403 @@
+use super::lint::small_int;
+use super::*;
+use std::fmt;
+
+/// Describes the variables whose values couldn't be determined by
+/// [`ArithmeticSystem::solve`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsolved {
+    /// The names of the unsolved variables, as given by [`ArithmeticSystem::var_name`].
+    pub vars: Vec<String>,
+}
+
+impl fmt::Display for Unsolved {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "could not solve for {} variables (including {})",
+            self.vars.len(),
+            self.vars[..self.vars.len().min(8)].join(", ")
+        )
+    }
+}
+
+impl std::error::Error for Unsolved {}
+
+/// Describes a constraint which is not satisfied by an assignment of variable values, in enough
+/// detail to track down the gadget responsible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample<F> {
+    /// The index of the constraint.
+    pub index: usize,
+
+    /// The scope in which the constraint was introduced. See [`ArithmeticSystem::scope`].
+    pub scope: String,
+
+    /// The operands and result of the constraint, written in terms of variable names.
+    pub operands: [String; 3],
+
+    /// The values of the operands and result of the constraint.
+    pub values: [F; 3],
+
+    /// The names and values of the variables referenced by the constraint.
+    pub vars: Vec<(String, F)>,
+}
+
+impl<F: PrimeField> fmt::Display for Counterexample<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "constraint {} ", self.index)?;
+        if !self.scope.is_empty() {
+            write!(f, "in {} ", self.scope)?;
+        }
+        let [a, b, r] = &self.operands;
+        let [a_value, b_value, r_value] = self.values.map(show);
+        writeln!(f, "is not satisfied: ({}) * ({}) = {}", a, b, r)?;
+        write!(
+            f,
+            "  where the operands are {} and {}, but the result is {}",
+            a_value, b_value, r_value
+        )?;
+        for (name, value) in self.vars.iter() {
+            write!(f, "\n  {} = {}", name, show(*value))?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> std::error::Error for Counterexample<F> {}
+
+/// Describes why [`ArithmeticSystem::check_completeness`] failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletenessError<F> {
+    /// The witness couldn't be generated.
+    Unsolved(Unsolved),
+
+    /// The witness doesn't satisfy some constraint.
+    Unsatisfied(Box<Counterexample<F>>),
+}
+
+impl<F: PrimeField> fmt::Display for CompletenessError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompletenessError::Unsolved(err) => err.fmt(f),
+            CompletenessError::Unsatisfied(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<F: PrimeField> std::error::Error for CompletenessError<F> {}
+
+impl<F: PrimeField> ArithmeticSystem<F> {
+    /// Generates a complete assignment for this system by propagating the values of `known`
+    /// variables, typically the inputs of a gadget, through its constraints. This only handles
+    /// the patterns produced by the gadgets in this crate: products, inverses and bit
+    /// decompositions. The resulting assignment need not satisfy the system.
+    pub fn solve(&self, known: &[(Variable, F)]) -> Result<Vec<F>, Unsolved> {
+        let mut values = Values::new(&[], 0, vec![None; self.num_vars]);
+        for (var, value) in known {
+            values.local[var.index()] = Some(*value);
+        }
+        propagate(self.constraints.iter(), &mut values);
+        self.complete(values.local)
+    }
+
+    /// Converts a partial assignment into a complete one, or describes the variables which are
+    /// missing from it.
+    pub(super) fn complete(&self, values: Vec<Option<F>>) -> Result<Vec<F>, Unsolved> {
+        let unsolved: Vec<String> = (0..self.num_vars)
+            .filter(|i| values[*i].is_none())
+            .map(|i| self.var_name(Variable::from_index(i)))
+            .collect();
+        if !unsolved.is_empty() {
+            return Err(Unsolved { vars: unsolved });
+        }
+        Ok(values.into_iter().map(|value| value.unwrap()).collect())
+    }
+
+    /// Checks whether the given assignment of variable values satisfies all constraints in this
+    /// system, describing the first constraint that isn't satisfied. This gives more detail than
+    /// [`ArithmeticSystem::check`].
+    pub fn diagnose(&self, assignment: &[F]) -> Result<(), Box<Counterexample<F>>> {
+        let Err(Unsatisfied { index, .. }) = self.check(assignment) else {
+            return Ok(());
+        };
+        let c = &self.constraints[index];
+        let formulas = [&c.operand_a, &c.operand_b, &c.result];
+        Err(Box::new(Counterexample {
+            index,
+            scope: self.scope(index).to_owned(),
+            operands: formulas.map(|formula| self.show_formula(formula)),
+            values: formulas.map(|formula| formula.eval(assignment)),
+            vars: c
+                .vars()
+                .into_iter()
+                .map(|var| (self.var_name(var), assignment[var.index()]))
+                .collect(),
+        }))
+    }
+
+    /// Checks that this system is complete for a concrete input: that the witness generated for
+    /// it by [`ArithmeticSystem::solve`] satisfies every constraint. `known` gives the values
+    /// of the input variables. If the gadget which built this system succeeds on the same input
+    /// when run with [`Eval`], any failure indicates a bug in the constraints of that gadget.
+    ///
+    /// Returns the witness if it satisfies the system.
+    pub fn check_completeness(
+        &self,
+        known: &[(Variable, F)],
+    ) -> Result<Vec<F>, CompletenessError<F>> {
+        let assignment = self.solve(known).map_err(CompletenessError::Unsolved)?;
+        self.diagnose(&assignment)
+            .map_err(CompletenessError::Unsatisfied)?;
+        Ok(assignment)
+    }
+
+    /// Writes a formula in terms of variable names.
+    fn show_formula(&self, formula: &LinearFormula<F>) -> String {
+        let mut terms: Vec<(F, String)> = formula
+            .coeffs
+            .iter()
+            .map(|(var, coeff)| (*coeff, self.var_name(Variable(*var))))
+            .collect();
+        if !formula.constant_term.is_zero_vartime() || terms.is_empty() {
+            terms.push((formula.constant_term, String::new()));
+        }
+        let mut res = String::new();
+        for (i, (coeff, name)) in terms.into_iter().enumerate() {
+            let (negative, coeff) = match small_int(coeff) {
+                Some(value) if value < 0 => (true, -coeff),
+                _ => (false, coeff),
+            };
+            res.push_str(match (i, negative) {
+                (0, false) => "",
+                (0, true) => "-",
+                (_, false) => " + ",
+                (_, true) => " - ",
+            });
+            match (coeff == F::one(), name.is_empty()) {
+                (true, false) => res.push_str(&name),
+                (_, true) => res.push_str(&show(coeff)),
+                (false, false) => res.push_str(&format!("{}*{}", show(coeff), name)),
+            }
+        }
+        res
+    }
+}
+
+/// The values of variables while solving a system, where only a contiguous range of variables
+/// can be assigned. The rest are read from a shared, partial assignment.
+pub(super) struct Values<'a, F> {
+    shared: &'a [Option<F>],
+
+    /// The index of the first variable in `local`.
+    offset: usize,
+
+    /// The values of the variables which can be assigned.
+    pub local: Vec<Option<F>>,
+}
+
+impl<'a, F: Copy> Values<'a, F> {
+    /// Creates a store where the variables starting at `offset` take their values from `local`.
+    pub fn new(shared: &'a [Option<F>], offset: usize, local: Vec<Option<F>>) -> Self {
+        Self {
+            shared,
+            offset,
+            local,
+        }
+    }
+
+    /// Gets the value of a variable, if known.
+    fn get(&self, var: usize) -> Option<F> {
+        match var.checked_sub(self.offset) {
+            Some(i) if i < self.local.len() => self.local[i],
+            _ => self.shared.get(var).copied().flatten(),
+        }
+    }
+
+    /// Assigns a value to a variable, returning false if it is outside the assignable range.
+    fn set(&mut self, var: usize, value: F) -> bool {
+        match var.checked_sub(self.offset) {
+            Some(i) if i < self.local.len() => {
+                self.local[i] = Some(value);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Propagates known values through the given constraints until no more variables can be solved
+/// for. See [`ArithmeticSystem::solve`].
+pub(super) fn propagate<'a, F: PrimeField>(
+    constraints: impl Iterator<Item = &'a ProductConstraint<F>> + Clone,
+    values: &mut Values<F>,
+) {
+    // Splits a formula into the value of its known terms and its unknown terms
+    let split = |values: &Values<F>, formula: &LinearFormula<F>| {
+        let mut known = formula.constant_term;
+        let mut unknown = Vec::new();
+        for (var, coeff) in formula.coeffs.iter() {
+            match values.get(*var as usize) {
+                Some(value) => known += value * coeff,
+                None => unknown.push((*var as usize, *coeff)),
+            }
+        }
+        (known, unknown)
+    };
+    let powers: Vec<F> = std::iter::successors(Some(F::one()), |x| Some(x.double()))
+        .take(F::NUM_BITS as usize)
+        .collect();
+    let mut progress = true;
+    while progress {
+        progress = false;
+        for c in constraints.clone() {
+            let (a, a_unknown) = split(values, &c.operand_a);
+            let (b, b_unknown) = split(values, &c.operand_b);
+            let (r, r_unknown) = split(values, &c.result);
+            let mut assign = |var: usize, value: F| {
+                progress |= values.set(var, value);
+            };
+            let inv = |x: F| Option::<F>::from(x.invert());
+            match (&a_unknown[..], &b_unknown[..], &r_unknown[..]) {
+                ([], [], [(v, c)]) => assign(*v, (a * b - r) * inv(*c).unwrap()),
+                ([], [(v, c)], []) if !a.is_zero_vartime() => {
+                    assign(*v, (r * inv(a).unwrap() - b) * inv(*c).unwrap())
+                }
+                ([(v, c)], [], []) if !b.is_zero_vartime() => {
+                    assign(*v, (r * inv(b).unwrap() - a) * inv(*c).unwrap())
+                }
+                ([], [(vb, cb)], [(vr, cr)]) => {
+                    // Assume the result variable is zero when possible, as for `is_zero`
+                    if let Some(a_inv) = inv(a) {
+                        assign(*vr, F::zero());
+                        assign(*vb, (r * a_inv - b) * inv(*cb).unwrap());
+                    } else {
+                        assign(*vr, -r * inv(*cr).unwrap());
+                        assign(*vb, F::zero());
+                    }
+                }
+                ([(vq, cq)], [], [(vr, cr)])
+                    if a.is_zero_vartime() && *cq == F::one() && *cr == -F::one() =>
+                {
+                    // A division by a constant, as for `ArithmeticSystem::div_rem_const`
+                    let (Some(value), Some(divisor)) = (to_u128(r), to_u128(b)) else {
+                        continue;
+                    };
+                    if divisor == 0 {
+                        continue;
+                    }
+                    assign(*vq, from_u128(value / divisor));
+                    assign(*vr, from_u128(value % divisor));
+                }
+                (bits, [], []) if !bits.is_empty() && r.is_zero_vartime() && b == F::one() => {
+                    // A bit decomposition. The sign bit of a two's complement decomposition
+                    // has a negative weight, so it is solved through its complement.
+                    let mut target = -a;
+                    let weights: Option<Vec<_>> = bits
+                        .iter()
+                        .map(|(v, c)| {
+                            if let Some(i) = powers.iter().position(|p| p == c) {
+                                return Some((*v, i, false));
+                            }
+                            let i = powers.iter().position(|p| *p == -*c)?;
+                            target += powers[i];
+                            Some((*v, i, true))
+                        })
+                        .collect();
+                    let Some(weights) = weights else {
+                        continue;
+                    };
+                    let target = target.to_repr();
+                    for (v, i, negated) in weights {
+                        let bit = (target.as_ref()[i / 8] >> (i % 8)) & 1;
+                        let bit = if negated { 1 - bit } else { bit };
+                        assign(v, F::from(u64::from(bit)));
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Writes a field element, as a signed integer if it is small enough.
+fn show<F: PrimeField>(value: F) -> String {
+    match small_int(value) {
+        Some(value) => value.to_string(),
+        None => format!("{:?}", value),
+    }
+}
+
+/// Interprets a field element as an integer, if it is less than `2^128`.
+fn to_u128<F: PrimeField>(value: F) -> Option<u128> {
+    let repr = value.to_repr();
+    let bytes = repr.as_ref();
+    let (low, high) = bytes.split_at(bytes.len().min(16));
+    if high.iter().any(|byte| *byte != 0) {
+        return None;
+    }
+    let mut buf = [0; 16];
+    buf[..low.len()].copy_from_slice(low);
+    Some(u128::from_le_bytes(buf))
+}
+
+/// Converts an integer to a field element, assuming the field is large enough to represent it.
+fn from_u128<F: PrimeField>(value: u128) -> F {
+    let shift = F::from(u64::MAX) + F::one();
+    F::from((value >> 64) as u64) * shift + F::from(value as u64)
+}
+
+#[test]
+fn test_check_completeness() {
+    use bls12_381::Scalar;
+
+    // Range-checks a value which is supposed to fit in 6 bits, but only allows 4 bits for it
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let x = sys.declare_labeled("x");
+    let y = sys.declare_labeled("y");
+    sys.push_scope("gadget");
+    let sum = sys.sum(&[x.into(), y.into()]);
+    sys.push_scope("range");
+    sys.decompose(sum, 4, false);
+    sys.pop_scope();
+    let product = sys.product(x.into(), y.into());
+    sys.pop_scope();
+    let witness = sys
+        .check_completeness(&[(x, Scalar::from(3)), (y, Scalar::from(5))])
+        .unwrap();
+    assert_eq!(sys.eval(product, &witness), Scalar::from(15));
+    assert_eq!(sys.scope(0), "gadget/range");
+    assert_eq!(sys.scope(sys.constraints().len() - 1), "gadget");
+
+    // An input on which evaluation would succeed pinpoints the range check
+    let err = sys.check_completeness(&[(x, Scalar::from(30)), (y, Scalar::from(4))]);
+    let Err(CompletenessError::Unsatisfied(counterexample)) = err else {
+        panic!("expected an unsatisfied constraint");
+    };
+    assert_eq!(counterexample.index, 4);
+    assert_eq!(counterexample.scope, "gadget/range");
+    assert_eq!(
+        counterexample.operands,
+        [
+            "-x - y + v2 + 2*v3 + 4*v4 + 8*v5".to_owned(),
+            "1".to_owned(),
+            "0".to_owned()
+        ]
+    );
+    assert_eq!(counterexample.values[0], -Scalar::from(32));
+    assert_eq!(counterexample.vars[0], ("x".to_owned(), Scalar::from(30)));
+    assert!(counterexample.to_string().contains("but the result is 0"));
+
+    // Signed decompositions are solved too, and unconstrained variables are reported
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let x = sys.declare();
+    sys.decompose(x.into(), 8, true);
+    sys.declare_labeled("free");
+    let err = sys.check_completeness(&[(x, -Scalar::from(100))]);
+    assert_eq!(
+        err,
+        Err(CompletenessError::Unsolved(Unsolved {
+            vars: vec!["free".to_owned()]
+        }))
+    );
+}
@@ -0,0 +1,444 @@
+use super::lint::small_int;
+use super::*;
+
+/// The abstract representation of a `u32` in an [`ArithmeticSystem`]: a formula for its value,
+/// which may exceed `u32::MAX`, because the carries from additions are only discarded when the
+/// bits of the word are needed. A chain of additions thus costs a single bit decomposition,
+/// rather than one for each addition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Word {
+    value: Formula,
+
+    /// An upper bound on the value. The word is normalized if this is at most `u32::MAX`.
+    bound: u64,
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> ArithmeticSystem<F, C> {
+    /// Constructs a [`Word`] from its bits, least significant first, which are assumed to be
+    /// either 0 or 1.
+    pub fn word_of_bits(&mut self, bits: &[Formula; 32]) -> Word {
+        for bit in bits {
+            self.assume(*bit, Assumption::Bool);
+        }
+        self.word_from_bits(*bits)
+    }
+
+    /// Gets the bits of the given [`Word`], least significant first. This decomposes the word
+    /// the first time its bits are needed.
+    pub fn bits_of_word(&mut self, word: &Word) -> [Formula; 32] {
+        if let Some(bits) = self.word_bits.get(&word.value) {
+            return *bits;
+        }
+        let bits = if let Some(value) = self.as_constant(word.value) {
+            let value = small_int(value).expect("constant word out of range") as u64;
+            crate::system::bits_of(value).map(|bit| if bit { ONE } else { ZERO })
+        } else {
+            let num_bits = (u64::BITS - word.bound.leading_zeros()).max(32);
+            let bits = self.decompose(word.value, num_bits as usize, false);
+            array_init::array_init(|i| bits[i])
+        };
+        self.word_bits.insert(word.value, bits);
+        bits
+    }
+
+    /// Gets a formula for the value of the given [`Word`], with any pending carries discarded.
+    pub fn word_value(&mut self, word: &Word) -> Formula {
+        self.normalize(word).value
+    }
+
+    /// Discards the pending carries of the given [`Word`].
+    fn normalize(&mut self, word: &Word) -> Word {
+        if word.bound <= u32::MAX as u64 {
+            return *word;
+        }
+        let bits = self.bits_of_word(word);
+        self.word_from_bits(bits)
+    }
+
+    /// Reduces a non-negative integer no greater than `bound` modulo `modulus`.
+    fn reduce_word(&mut self, value: Formula, bound: u128, modulus: u32) -> Word {
+        let (_, rem) = self.div_rem_const(value, bound, modulus.into());
+        Word {
+            value: rem,
+            bound: u64::from(modulus - 1),
+        }
+    }
+
+    /// Constructs a [`Word`] from bits which are known to be either 0 or 1.
+    fn word_from_bits(&mut self, bits: [Formula; 32]) -> Word {
+        let value = self.recompose(&bits, false);
+        self.word_bits.insert(value, bits);
+        Word {
+            value,
+            bound: u32::MAX as u64,
+        }
+    }
+
+    /// Applies a binary operation to each corresponding pair of bits in `a` and `b`.
+    fn zip_word_bits(
+        &mut self,
+        a: &Word,
+        b: &Word,
+        mut op: impl FnMut(&mut Self, Formula, Formula) -> Formula,
+    ) -> Word {
+        let a = self.bits_of_word(a);
+        let b = self.bits_of_word(b);
+        let bits = array_init::array_init(|i| op(self, a[i], b[i]));
+        self.word_from_bits(bits)
+    }
+
+    /// Rearranges the bits of the given [`Word`], where `source` gives the index of the bit
+    /// which each bit of the result is taken from, or `None` for a zero bit.
+    fn permute_word_bits(&mut self, word: &Word, source: impl Fn(usize) -> Option<usize>) -> Word {
+        let bits = self.bits_of_word(word);
+        let bits = array_init::array_init(|i| source(i).map_or(ZERO, |j| bits[j]));
+        self.word_from_bits(bits)
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemRepr<u32> for ArithmeticSystem<F, C> {
+    type Abstract = Word;
+    type Error = C::Error;
+    fn constant(&mut self, value: u32) -> Self::Abstract {
+        Word {
+            value: self.alloc(LinearFormula::constant(F::from(u64::from(value)))),
+            bound: value.into(),
+        }
+    }
+
+    fn status(&self) -> Result<(), Self::Error> {
+        self.constraints.status()
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemWrappingAdd<u32> for ArithmeticSystem<F, C> {
+    fn wrapping_add(&mut self, a: &Word, b: &Word) -> Word {
+        SystemWrappingAdd::<u32>::sum_many(self, &[*a, *b])
+    }
+
+    fn sum_many(&mut self, terms: &[Word]) -> Word {
+        // Terms which have already been decomposed might as well have their carries discarded.
+        // Otherwise, carries can accumulate until the value might no longer fit in the field.
+        let terms: Vec<_> = terms
+            .iter()
+            .map(|term| match self.word_bits.contains_key(&term.value) {
+                true => self.normalize(term),
+                false => *term,
+            })
+            .collect();
+        let max_bits = F::CAPACITY.min(u64::BITS);
+        let bound = terms
+            .iter()
+            .try_fold(0u64, |acc, term| acc.checked_add(term.bound))
+            .filter(|bound| u64::BITS - bound.leading_zeros() <= max_bits);
+        let (terms, bound) = match bound {
+            Some(bound) => (terms, bound),
+            None => {
+                let bound = u32::MAX as u64 * terms.len() as u64;
+                (
+                    terms.iter().map(|term| self.normalize(term)).collect(),
+                    bound,
+                )
+            }
+        };
+        let values: Vec<_> = terms.iter().map(|term| term.value).collect();
+        Word {
+            value: self.sum(&values),
+            bound,
+        }
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemBitAnd<u32> for ArithmeticSystem<F, C> {
+    fn and(&mut self, a: &Word, b: &Word) -> Word {
+        self.zip_word_bits(a, b, |sys, a, b| SystemBitAnd::<bool>::and(sys, &a, &b))
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemBitOr<u32> for ArithmeticSystem<F, C> {
+    fn or(&mut self, a: &Word, b: &Word) -> Word {
+        self.zip_word_bits(a, b, |sys, a, b| SystemBitOr::<bool>::or(sys, &a, &b))
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemBitXor<u32> for ArithmeticSystem<F, C> {
+    fn xor(&mut self, a: &Word, b: &Word) -> Word {
+        self.zip_word_bits(a, b, |sys, a, b| SystemBitXor::<bool>::xor(sys, &a, &b))
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemBitSelect<u32> for ArithmeticSystem<F, C> {
+    fn bit_select(&mut self, mask: &Word, a: &Word, b: &Word) -> Word {
+        let mask = self.bits_of_word(mask);
+        let a = self.bits_of_word(a);
+        let b = self.bits_of_word(b);
+        let bits = array_init::array_init(|i| ArithmeticSystem::select(self, mask[i], a[i], b[i]));
+        self.word_from_bits(bits)
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemNot<u32> for ArithmeticSystem<F, C> {
+    fn not(&mut self, value: &Word) -> Word {
+        let bits = self.bits_of_word(value);
+        let bits = bits.map(|bit| SystemNot::<bool>::not(self, &bit));
+        self.word_from_bits(bits)
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemBitShift<u32, u8> for ArithmeticSystem<F, C> {
+    fn shl(&mut self, a: &Word, b: u8) -> Word {
+        self.permute_word_bits(a, |i| i.checked_sub(b as usize))
+    }
+
+    fn shr(&mut self, a: &Word, b: u8) -> Word {
+        self.permute_word_bits(a, |i| Some(i + b as usize).filter(|j| *j < 32))
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemBitRotate<u32, u8> for ArithmeticSystem<F, C> {
+    fn rotl(&mut self, a: &Word, b: u8) -> Word {
+        self.permute_word_bits(a, |i| Some((i + 32 - b as usize % 32) % 32))
+    }
+
+    fn rotr(&mut self, a: &Word, b: u8) -> Word {
+        self.permute_word_bits(a, |i| Some((i + b as usize) % 32))
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemSelect<u32> for ArithmeticSystem<F, C> {
+    fn select(&mut self, cond: &Formula, a: &Word, b: &Word) -> Word {
+        Word {
+            value: ArithmeticSystem::select(self, *cond, a.value, b.value),
+            bound: a.bound.max(b.bound),
+        }
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemOrd<u32> for ArithmeticSystem<F, C> {
+    fn lt(&mut self, a: &Word, b: &Word) -> Formula {
+        // As for signed integers, the sign bit of the difference determines the result
+        let (a, b) = (self.normalize(a), self.normalize(b));
+        let diff = self.diff(a.value, b.value);
+        self.decompose(diff, 33, true)[32]
+    }
+
+    fn le(&mut self, a: &Word, b: &Word) -> Formula {
+        let gt = SystemOrd::<u32>::lt(self, b, a);
+        SystemNot::<bool>::not(self, &gt)
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemModArith<u32> for ArithmeticSystem<F, C> {
+    fn add_mod(&mut self, a: &Word, b: &Word, modulus: u32) -> Word {
+        let (a, b) = (self.normalize(a), self.normalize(b));
+        let sum = self.sum(&[a.value, b.value]);
+        self.reduce_word(sum, (a.bound + b.bound).into(), modulus)
+    }
+
+    fn mul_mod(&mut self, a: &Word, b: &Word, modulus: u32) -> Word {
+        let (a, b) = (self.normalize(a), self.normalize(b));
+        let prod = self.product(a.value, b.value);
+        self.reduce_word(prod, u128::from(a.bound) * u128::from(b.bound), modulus)
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemDivRem<u32> for ArithmeticSystem<F, C> {
+    fn div_rem(&mut self, a: &Word, b: &Word) -> (Word, Word) {
+        let (a, b) = (self.normalize(a), self.normalize(b));
+        let (quot, rem) =
+            ArithmeticSystem::div_rem(self, a.value, a.bound.into(), b.value, b.bound.into());
+        let quot = Word {
+            value: quot,
+            bound: a.bound,
+        };
+        let rem = Word {
+            value: rem,
+            bound: b.bound.saturating_sub(1),
+        };
+        (quot, rem)
+    }
+}
+
+impl<F: PrimeField, C: ConstraintSink<F>> SystemRand<u32> for ArithmeticSystem<F, C> {
+    fn rand(&mut self) -> Word {
+        let var = self.declare().into();
+        let bits = self.decompose(var, 32, false);
+        self.word_bits
+            .insert(var, array_init::array_init(|i| bits[i]));
+        Word {
+            value: var,
+            bound: u32::MAX as u64,
+        }
+    }
+}
+
+#[test]
+fn test_word_ops() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let a = SystemRand::<u32>::rand(&mut sys);
+    let b = SystemRand::<u32>::rand(&mut sys);
+    let three = SystemRepr::<u32>::constant(&mut sys, 3);
+    let sum = SystemWrappingAdd::<u32>::sum_many(&mut sys, &[a, b, three]);
+    let xor = SystemBitXor::<u32>::xor(&mut sys, &sum, &a);
+    let rot = SystemBitRotate::<u32, u8>::rotr(&mut sys, &xor, 7);
+    let shifted = SystemBitShift::<u32, u8>::shr(&mut sys, &sum, 30);
+    let not = SystemNot::<u32>::not(&mut sys, &shifted);
+    let select = SystemBitSelect::<u32>::bit_select(&mut sys, &a, &rot, &not);
+    let res = SystemWrappingAdd::<u32>::wrapping_add(&mut sys, &select, &sum);
+    let res = sys.word_value(&res);
+
+    // The sum is only decomposed once, with two extra bits for its carries
+    let num_constraints = sys.constraints().len();
+    let sum_bits = sys.bits_of_word(&sum);
+    assert_eq!(sys.constraints().len(), num_constraints);
+    assert_eq!(sum_bits[0], Formula::from(Variable(66)));
+    let xor_bits = sys.bits_of_word(&xor);
+    assert_eq!(xor_bits[0], Formula::from(Variable(66 + 34)));
+    let lt = SystemOrd::<u32>::lt(&mut sys, &b, &a);
+    let le = SystemOrd::<u32>::le(&mut sys, &sum, &a);
+    for (x, y) in [(0, 0), (0xdeadbeef, 0x12345678), (u32::MAX, u32::MAX - 1)] {
+        let known = [
+            (Variable(0), Scalar::from(x as u64)),
+            (Variable(33), Scalar::from(y as u64)),
+        ];
+        let assignment = sys.check_completeness(&known).unwrap();
+        let sum = x.wrapping_add(y).wrapping_add(3);
+        let expected = (x & (sum ^ x).rotate_right(7)) | (!x & !(sum >> 30));
+        let expected = expected.wrapping_add(sum);
+        assert_eq!(sys.eval(res, &assignment), Scalar::from(expected as u64));
+        assert_eq!(sys.eval(lt, &assignment), Scalar::from((y < x) as u64));
+        assert_eq!(sys.eval(le, &assignment), Scalar::from((sum <= x) as u64));
+    }
+}
+
+#[test]
+fn test_word_mod() {
+    use bls12_381::Scalar;
+    let moduli = [1, 2, 1 << 16, 65521, (1 << 31) - 1, u32::MAX];
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let a = SystemRand::<u32>::rand(&mut sys);
+    let b = SystemRand::<u32>::rand(&mut sys);
+
+    // Inputs with pending carries are normalized first
+    let three = SystemRepr::<u32>::constant(&mut sys, 3);
+    let c = SystemWrappingAdd::<u32>::wrapping_add(&mut sys, &a, &three);
+    let res: Vec<_> = moduli
+        .iter()
+        .map(|m| {
+            let sum = SystemModArith::<u32>::add_mod(&mut sys, &a, &b, *m);
+            let prod = SystemModArith::<u32>::mul_mod(&mut sys, &c, &b, *m);
+            (sys.word_value(&sum), sys.word_value(&prod))
+        })
+        .collect();
+    assert!(sys.lint().is_clean());
+    for (x, y) in [(0, 0), (0xdeadbeef, 0x12345678), (u32::MAX, u32::MAX - 1)] {
+        let known = [
+            (Variable(0), Scalar::from(x as u64)),
+            (Variable(33), Scalar::from(y as u64)),
+        ];
+        let assignment = sys.check_completeness(&known).unwrap();
+        let z = x.wrapping_add(3);
+        for (m, (sum, prod)) in moduli.iter().zip(res.iter()) {
+            let expected_sum = Eval.add_mod(&x, &y, *m);
+            let expected_prod = Eval.mul_mod(&z, &y, *m);
+            assert_eq!(
+                sys.eval(*sum, &assignment),
+                Scalar::from(expected_sum as u64)
+            );
+            assert_eq!(
+                sys.eval(*prod, &assignment),
+                Scalar::from(expected_prod as u64)
+            );
+        }
+    }
+
+    // The remainder can't be replaced by a larger value with the same residue
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let a = SystemRand::<u32>::rand(&mut sys);
+    let b = SystemRand::<u32>::rand(&mut sys);
+    let sum = SystemModArith::<u32>::add_mod(&mut sys, &a, &b, 1000);
+    let sum = sys.word_value(&sum);
+    let known = [
+        (Variable(0), Scalar::from(700)),
+        (Variable(33), Scalar::from(500)),
+    ];
+    let assignment = sys.check_completeness(&known).unwrap();
+    assert_eq!(sys.eval(sum, &assignment), Scalar::from(200));
+    let Formula(FormulaRef::Var(rem)) = sum else {
+        unreachable!()
+    };
+    let mut known = known.to_vec();
+    known.push((Variable(rem - 1), Scalar::zero()));
+    known.push((Variable(rem), Scalar::from(1200)));
+    let assignment = sys.solve(&known).unwrap();
+    assert!(sys.check(&assignment).is_err());
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn test_sha256_words() {
+    use crate::crypto::hash::SystemSha256;
+    use bls12_381::Scalar;
+
+    // Since additions are performed in the field, a block costs far fewer constraints than with
+    // bitwise emulation
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let words: [_; 24] = array_init::array_init(|_| SystemRand::<u32>::rand(&mut sys));
+    let num_constraints = sys.constraints().len();
+    let mut hasher: [_; 8] = array_init::array_init(|i| words[i]);
+    sys.sha256_update_abstract(&mut hasher, &array_init::array_init(|i| words[8 + i]));
+    let digest = hasher.map(|word| sys.word_value(&word));
+    assert!(sys.constraints().len() - num_constraints < 26500);
+    assert!(sys.lint().is_clean());
+
+    // Each word is a variable followed by its bits
+    let mut chunk = [0; 16];
+    chunk[0] = 0x61626380;
+    chunk[15] = 24;
+    let known: Vec<_> = Eval
+        .sha256_new()
+        .iter()
+        .chain(chunk.iter())
+        .enumerate()
+        .map(|(i, word)| (Variable((i * 33) as u32), Scalar::from(*word as u64)))
+        .collect();
+    let assignment = sys.check_completeness(&known).unwrap();
+    let mut expected = Eval.sha256_new();
+    Eval.sha256_update(&mut expected, chunk);
+    for (formula, word) in digest.iter().zip(expected.iter()) {
+        assert_eq!(sys.eval(*formula, &assignment), Scalar::from(*word as u64));
+    }
+}
+
+#[test]
+fn test_word_div_rem() {
+    use bls12_381::Scalar;
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let a = SystemRand::<u32>::rand(&mut sys);
+    let b = SystemRand::<u32>::rand(&mut sys);
+    let (quot, rem) = SystemDivRem::<u32>::div_rem(&mut sys, &a, &b);
+    let (quot, rem) = (sys.word_value(&quot), sys.word_value(&rem));
+    assert!(sys.lint().is_clean());
+    let solve = |x: u32, y: u32| {
+        let known = [
+            (Variable(0), Scalar::from(x as u64)),
+            (Variable(33), Scalar::from(y as u64)),
+        ];
+        sys.solve(&known).ok()
+    };
+    for (x, y) in [
+        (0, 1),
+        (0xdeadbeef, 0x1234),
+        (u32::MAX, u32::MAX),
+        (7, u32::MAX),
+    ] {
+        let assignment = solve(x, y).unwrap();
+        assert!(sys.is_satisfied(&assignment));
+        assert_eq!(sys.eval(quot, &assignment), Scalar::from((x / y) as u64));
+        assert_eq!(sys.eval(rem, &assignment), Scalar::from((x % y) as u64));
+    }
+
+    // The divisor must be nonzero
+    assert!(solve(5, 0).is_none_or(|assignment| !sys.is_satisfied(&assignment)));
+}
@@ -0,0 +1,315 @@
+use crate::*;
+
+/// A random-access memory of values of type `T` within a system of type `S`, which can be read
+/// and written at abstract addresses. Addresses are given as little-endian strings of abstract
+/// bits.
+///
+/// Each access is lowered to a linear scan over the memory, so its cost is proportional to the
+/// size of the memory. For arithmetic systems, [`crate::r1cs::TranscriptRam`] is usually cheaper.
+pub struct AbstractRam<S: SystemRepr<T> + ?Sized, T> {
+    cells: Vec<Abstract<S, T>>,
+}
+
+impl<S: BinarySystem + SystemSelect<T> + ?Sized, T> AbstractRam<S, T> {
+    /// Constructs an [`AbstractRam`] with the given initial contents. Panics if `cells` is empty.
+    pub fn new(cells: Vec<Abstract<S, T>>) -> Self {
+        assert!(!cells.is_empty(), "memory must have at least one cell");
+        Self { cells }
+    }
+
+    /// The number of cells in this memory.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Always returns `false`, since a memory has at least one cell.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The current contents of this memory.
+    pub fn cells(&self) -> &[Abstract<S, T>] {
+        &self.cells
+    }
+
+    /// Reads the value at the given address. For addresses outside of the memory, the result is
+    /// unspecified.
+    pub fn read(&self, sys: &mut S, addr: &[Abstract<S, bool>]) -> Abstract<S, T> {
+        sys.select_from(addr, &self.cells)
+    }
+
+    /// Writes a value to the given address if `enable` is true. For addresses outside of the
+    /// memory, this has no effect.
+    pub fn write(
+        &mut self,
+        sys: &mut S,
+        addr: &[Abstract<S, bool>],
+        value: &Abstract<S, T>,
+        enable: &Abstract<S, bool>,
+    ) {
+        let sel = one_hot(sys, addr, self.cells.len());
+        for (sel, cell) in sel.iter().zip(self.cells.iter_mut()) {
+            let sel = sys.and(sel, enable);
+            *cell = sys.select(&sel, value, cell);
+        }
+    }
+}
+
+/// A bounded stack of values of type `T` within a system of type `S`, supporting pushes and pops
+/// under abstract enable flags. This is backed by an [`AbstractRam`].
+pub struct AbstractStack<S: SystemRepr<T> + SystemRepr<bool> + ?Sized, T> {
+    ram: AbstractRam<S, T>,
+    len: Vec<Abstract<S, bool>>,
+    error: Abstract<S, bool>,
+}
+
+impl<S: BinarySystem + SystemSelect<T> + ?Sized, T> AbstractStack<S, T> {
+    /// Constructs an empty [`AbstractStack`] which can hold up to `capacity` values. `fill` is
+    /// the initial value of the underlying memory, which is returned by invalid pops.
+    pub fn new(sys: &mut S, capacity: usize, fill: Abstract<S, T>) -> Self {
+        Self {
+            ram: AbstractRam::new(vec![fill; capacity]),
+            len: counter(sys, capacity),
+            error: sys.constant(false),
+        }
+    }
+
+    /// The maximum number of values this stack can hold.
+    pub fn capacity(&self) -> usize {
+        self.ram.len()
+    }
+
+    /// The number of values on this stack, as a little-endian string of bits.
+    pub fn len(&self) -> &[Abstract<S, bool>] {
+        &self.len
+    }
+
+    /// Determines whether this stack is empty.
+    pub fn is_empty(&self, sys: &mut S) -> Abstract<S, bool> {
+        eq_bits_const(sys, &self.len, 0)
+    }
+
+    /// Indicates whether a push to a full stack or a pop from an empty stack has been attempted.
+    /// Such operations have no effect on the stack.
+    pub fn error(&self) -> &Abstract<S, bool> {
+        &self.error
+    }
+
+    /// Pushes a value onto this stack if `enable` is true.
+    pub fn push(&mut self, sys: &mut S, value: &Abstract<S, T>, enable: &Abstract<S, bool>) {
+        let full = eq_bits_const(sys, &self.len, self.capacity() as u64);
+        let enable = guard(sys, &mut self.error, enable, &full);
+        self.ram.write(sys, &self.len, value, &enable);
+        self.len = increment(sys, &self.len, &enable);
+    }
+
+    /// Pops a value from this stack if `enable` is true, returning it. If no value is popped,
+    /// the result is unspecified.
+    pub fn pop(&mut self, sys: &mut S, enable: &Abstract<S, bool>) -> Abstract<S, T> {
+        let empty = self.is_empty(sys);
+        let enable = guard(sys, &mut self.error, enable, &empty);
+        self.len = decrement(sys, &self.len, &enable);
+        self.ram.read(sys, &self.len)
+    }
+}
+
+/// A bounded first-in first-out queue of values of type `T` within a system of type `S`,
+/// supporting pushes and pops under abstract enable flags. This is backed by an [`AbstractRam`],
+/// used as a ring buffer.
+pub struct AbstractQueue<S: SystemRepr<T> + SystemRepr<bool> + ?Sized, T> {
+    ram: AbstractRam<S, T>,
+    head: Vec<Abstract<S, bool>>,
+    len: Vec<Abstract<S, bool>>,
+    error: Abstract<S, bool>,
+}
+
+impl<S: BinarySystem + SystemSelect<T> + ?Sized, T> AbstractQueue<S, T> {
+    /// Constructs an empty [`AbstractQueue`] which can hold up to `capacity` values, which must
+    /// be a power of two. `fill` is the initial value of the underlying memory, which is
+    /// returned by invalid pops.
+    pub fn new(sys: &mut S, capacity: usize, fill: Abstract<S, T>) -> Self {
+        assert!(
+            capacity.is_power_of_two(),
+            "capacity must be a power of two"
+        );
+        let index_bits = capacity.trailing_zeros() as usize;
+        Self {
+            ram: AbstractRam::new(vec![fill; capacity]),
+            head: vec![sys.constant(false); index_bits],
+            len: counter(sys, capacity),
+            error: sys.constant(false),
+        }
+    }
+
+    /// The maximum number of values this queue can hold.
+    pub fn capacity(&self) -> usize {
+        self.ram.len()
+    }
+
+    /// The number of values in this queue, as a little-endian string of bits.
+    pub fn len(&self) -> &[Abstract<S, bool>] {
+        &self.len
+    }
+
+    /// Determines whether this queue is empty.
+    pub fn is_empty(&self, sys: &mut S) -> Abstract<S, bool> {
+        eq_bits_const(sys, &self.len, 0)
+    }
+
+    /// Indicates whether a push to a full queue or a pop from an empty queue has been attempted.
+    /// Such operations have no effect on the queue.
+    pub fn error(&self) -> &Abstract<S, bool> {
+        &self.error
+    }
+
+    /// Pushes a value onto the back of this queue if `enable` is true.
+    pub fn push(&mut self, sys: &mut S, value: &Abstract<S, T>, enable: &Abstract<S, bool>) {
+        let full = eq_bits_const(sys, &self.len, self.capacity() as u64);
+        let enable = guard(sys, &mut self.error, enable, &full);
+
+        // The tail index is `head + len`, wrapping around the buffer
+        let mut tail = add_bits(sys, &self.head, &self.len);
+        tail.truncate(self.head.len());
+        self.ram.write(sys, &tail, value, &enable);
+        self.len = increment(sys, &self.len, &enable);
+    }
+
+    /// Pops a value from the front of this queue if `enable` is true, returning it. If no value
+    /// is popped, the result is unspecified.
+    pub fn pop(&mut self, sys: &mut S, enable: &Abstract<S, bool>) -> Abstract<S, T> {
+        let empty = self.is_empty(sys);
+        let enable = guard(sys, &mut self.error, enable, &empty);
+        let res = self.ram.read(sys, &self.head);
+        self.head = increment(sys, &self.head, &enable);
+        self.len = decrement(sys, &self.len, &enable);
+        res
+    }
+}
+
+/// Constructs a zero counter with enough bits to count up to `max`.
+fn counter<S: BinarySystem + ?Sized>(sys: &mut S, max: usize) -> Vec<Abstract<S, bool>> {
+    let bits = (usize::BITS - max.leading_zeros()) as usize;
+    vec![sys.constant(false); bits]
+}
+
+/// Determines whether an operation enabled by `enable` may proceed, given that it is invalid if
+/// `invalid` is true. Attempts at invalid operations are recorded in `error`.
+fn guard<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    error: &mut Abstract<S, bool>,
+    enable: &Abstract<S, bool>,
+    invalid: &Abstract<S, bool>,
+) -> Abstract<S, bool> {
+    let failed = sys.and(enable, invalid);
+    *error = sys.or(error, &failed);
+    let valid = sys.not(invalid);
+    sys.and(enable, &valid)
+}
+
+/// Adds `enable` to a little-endian string of bits, with wrapping.
+fn increment<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    bits: &[Abstract<S, bool>],
+    enable: &Abstract<S, bool>,
+) -> Vec<Abstract<S, bool>> {
+    let mut carry = enable.clone();
+    bits.iter()
+        .map(|bit| {
+            let res = sys.xor(bit, &carry);
+            carry = sys.and(bit, &carry);
+            res
+        })
+        .collect()
+}
+
+/// Subtracts `enable` from a little-endian string of bits, with wrapping.
+fn decrement<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    bits: &[Abstract<S, bool>],
+    enable: &Abstract<S, bool>,
+) -> Vec<Abstract<S, bool>> {
+    let mut borrow = enable.clone();
+    bits.iter()
+        .map(|bit| {
+            let res = sys.xor(bit, &borrow);
+            let not_bit = sys.not(bit);
+            borrow = sys.and(&not_bit, &borrow);
+            res
+        })
+        .collect()
+}
+
+/// Adds two little-endian strings of bits, where `b` may be longer than `a`. The result has the
+/// length of `b`, with the final carry discarded.
+fn add_bits<S: BinarySystem + ?Sized>(
+    sys: &mut S,
+    a: &[Abstract<S, bool>],
+    b: &[Abstract<S, bool>],
+) -> Vec<Abstract<S, bool>> {
+    let mut carry = sys.constant(false);
+    b.iter()
+        .enumerate()
+        .map(|(i, b)| match a.get(i) {
+            Some(a) => {
+                let p = sys.xor(a, b);
+                let res = sys.xor(&p, &carry);
+                let g = sys.and(a, b);
+                let c = sys.and(&p, &carry);
+                carry = sys.or(&g, &c);
+                res
+            }
+            None => {
+                let res = sys.xor(b, &carry);
+                carry = sys.and(b, &carry);
+                res
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_ram() {
+    let mut sys = Eval;
+    let mut ram = AbstractRam::<_, u32>::new((0..5).map(|i| i * 100).collect());
+    let bits = |addr: u64| crate::system::bits_of::<4>(addr);
+    assert_eq!(ram.read(&mut sys, &bits(3)), 300);
+    ram.write(&mut sys, &bits(3), &7, &true);
+    ram.write(&mut sys, &bits(4), &8, &false);
+    ram.write(&mut sys, &bits(12), &9, &true);
+    assert_eq!(ram.cells(), &[0, 100, 200, 7, 400]);
+    for addr in 0..5 {
+        assert_eq!(ram.read(&mut sys, &bits(addr)), ram.cells()[addr as usize]);
+    }
+}
+
+#[test]
+fn test_stack_queue() {
+    let mut sys = Eval;
+    let mut stack = AbstractStack::<_, u32>::new(&mut sys, 3, 0);
+    let mut queue = AbstractQueue::<_, u32>::new(&mut sys, 4, 0);
+    for value in [1, 2, 3] {
+        stack.push(&mut sys, &value, &true);
+        queue.push(&mut sys, &value, &true);
+    }
+    stack.push(&mut sys, &4, &false);
+    assert!(!stack.error());
+    stack.push(&mut sys, &4, &true);
+    assert!(stack.error());
+    assert_eq!(stack.pop(&mut sys, &true), 3);
+    assert_eq!(stack.pop(&mut sys, &true), 2);
+    assert_eq!(stack.len(), &[true, false]);
+
+    // Wrap around the end of the queue's buffer
+    assert_eq!(queue.pop(&mut sys, &true), 1);
+    queue.pop(&mut sys, &false);
+    for value in [4, 5] {
+        queue.push(&mut sys, &value, &true);
+    }
+    assert!(!queue.error());
+    for value in [2, 3, 4, 5] {
+        assert_eq!(queue.pop(&mut sys, &true), value);
+    }
+    assert!(queue.is_empty(&mut sys));
+    queue.pop(&mut sys, &true);
+    assert!(queue.error());
+}
@@ -0,0 +1,551 @@
+//! Reflection of user-defined structs and enums into abstract representations, so that complex
+//! gadget inputs can be introduced into a system with one call rather than one per field.
+
+/// Defines a struct of concrete values along with its abstract representation, whose fields are
+/// the abstract representations of the corresponding concrete fields in a system `S`:
+///
+/// ```
+/// use circus::*;
+///
+/// abstract_struct! {
+///     /// A credential issued to a user.
+///     #[derive(Debug, Clone, PartialEq, Eq)]
+///     pub struct Credential as AbstractCredential {
+///         pub id: u32,
+///         pub expiry: u64,
+///         pub key: [u8; 16],
+///     }
+/// }
+///
+/// let credential = Credential { id: 7, expiry: 1700000000, key: [3; 16] };
+/// let value = AbstractCredential::constant(&mut Eval, credential.clone());
+/// assert_eq!(value.read_value(&Eval), credential);
+/// ```
+///
+/// The abstract struct provides:
+///  * `constant`, which introduces a known value, such as a public input.
+///  * `rand`, which introduces an arbitrary value. In constraint systems, each field becomes a
+///    fresh witness, constrained only to be a valid representation of its type.
+///  * `select` and `assert_eq`, which apply [`SystemSelect`] and [`SystemAssertEq`] fieldwise.
+///  * `read_value`, which recovers the concrete value using [`SystemRead`].
+///
+/// Each method is available when the system supports the corresponding operation for every
+/// field type. Fields may have any type with a [`SystemRepr`] implementation, but not other
+/// types defined by this macro.
+#[macro_export]
+macro_rules! abstract_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident as $abs:ident {
+            $($(#[$fmeta:meta])* $fvis:vis $field:ident: $ty:ty),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$fmeta])* $fvis $field: $ty,)+
+        }
+
+        #[doc = concat!("The abstract representation of a [`", stringify!($name), "`].")]
+        $vis struct $abs<S: ?Sized $(+ $crate::SystemRepr<$ty>)+> {
+            $($(#[$fmeta])* $fvis $field: $crate::Abstract<S, $ty>,)+
+        }
+
+        impl<S: ?Sized $(+ $crate::SystemRepr<$ty>)+> Clone for $abs<S> {
+            fn clone(&self) -> Self {
+                Self {
+                    $($field: self.$field.clone(),)+
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        impl<S: ?Sized $(+ $crate::SystemRepr<$ty>)+> $abs<S> {
+            /// Introduces a known value into the system.
+            pub fn constant(sys: &mut S, value: $name) -> Self {
+                Self {
+                    $($field: <S as $crate::SystemRepr<$ty>>::constant(sys, value.$field),)+
+                }
+            }
+
+            /// Introduces an arbitrary value into the system. See `SystemRand`.
+            pub fn rand(sys: &mut S) -> Self
+            where
+                S: $($crate::SystemRand<$ty> +)+
+            {
+                Self {
+                    $($field: <S as $crate::SystemRand<$ty>>::rand(sys),)+
+                }
+            }
+
+            /// Returns `a` if `cond` is true, or `b` otherwise.
+            pub fn select(
+                sys: &mut S,
+                cond: &$crate::Abstract<S, bool>,
+                a: &Self,
+                b: &Self,
+            ) -> Self
+            where
+                S: $($crate::SystemSelect<$ty> +)+
+            {
+                Self {
+                    $($field: <S as $crate::SystemSelect<$ty>>::select(
+                        sys,
+                        cond,
+                        &a.$field,
+                        &b.$field,
+                    ),)+
+                }
+            }
+
+            /// Asserts that this value is equal to another.
+            pub fn assert_eq(&self, sys: &mut S, other: &Self)
+            where
+                S: $($crate::SystemAssertEq<$ty> +)+
+            {
+                $(<S as $crate::SystemAssertEq<$ty>>::assert_eq(sys, &self.$field, &other.$field);)+
+            }
+
+            /// Gets the concrete value of this abstract value.
+            pub fn read_value(&self, sys: &S) -> $name
+            where
+                S: $($crate::SystemRead<$ty> +)+
+            {
+                $name {
+                    $($field: <S as $crate::SystemRead<$ty>>::read_value(sys, &self.$field),)+
+                }
+            }
+        }
+    };
+}
+
+/// Defines an enum of concrete values along with its abstract representation in a system `S`.
+/// Variants may have named fields, which must be distinct across all variants:
+///
+/// ```
+/// use circus::*;
+///
+/// abstract_enum! {
+///     /// The state of a connection.
+///     #[derive(Debug, Clone, PartialEq, Eq)]
+///     pub enum Conn as AbstractConn {
+///         Closed,
+///         Open { seq: u32 },
+///         Failed { code: u8, retry: bool },
+///     }
+/// }
+///
+/// let value = AbstractConn::constant(&mut Eval, Conn::Open { seq: 5 });
+/// assert_eq!(value.tag, [false, true, false]);
+/// assert_eq!(value.read_value(&Eval), Conn::Open { seq: 5 });
+/// ```
+///
+/// The abstract representation is a struct with a one-hot `tag`, which has a bit for each
+/// variant in declaration order, along with a field for every field of every variant. Fields of
+/// variants other than the active one hold their default value, so that the representation of a
+/// constant is unique. Its fields are public, so a value of a variant with abstract fields can be
+/// built by introducing a constant of that variant and replacing its fields.
+///
+/// The abstract struct provides:
+///  * `constant`, `select`, `assert_eq` and `read_value`, as for [`abstract_struct!`].
+///  * `rand`, which introduces an arbitrary value, asserting that its tag is one-hot.
+///  * `is_valid`, which determines whether the tag is one-hot.
+///  * `match_abstract`, which selects among per-variant results according to the tag, as a
+///    `match` on the concrete value would.
+///
+/// Field types must implement [`Default`] in addition to the requirements of
+/// [`abstract_struct!`].
+///
+/// With the `derive` feature, the abstract representation of an enum defined elsewhere can be
+/// obtained with `#[derive(AbstractEnum)]` instead, naming it with `#[circus(name = ...)]`:
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use circus::*;
+///
+/// #[derive(Debug, Clone, PartialEq, Eq, AbstractEnum)]
+/// #[circus(name = AbstractConn)]
+/// pub enum Conn {
+///     Closed,
+///     Open { seq: u32 },
+/// }
+///
+/// let value = AbstractConn::constant(&mut Eval, Conn::Open { seq: 5 });
+/// assert_eq!(value.read_value(&Eval), Conn::Open { seq: 5 });
+/// # }
+/// ```
+#[macro_export]
+macro_rules! abstract_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident as $abs:ident {
+            $(
+                $(#[$vmeta:meta])*
+                $variant:ident $({
+                    $($(#[$fmeta:meta])* $field:ident: $ty:ty),* $(,)?
+                })?
+            ),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $(
+                $(#[$vmeta])*
+                $variant $({
+                    $($(#[$fmeta])* $field: $ty,)*
+                })?,
+            )+
+        }
+
+        $crate::abstract_enum! {
+            @impl $vis $name as $abs {
+                $($variant $({ $($(#[$fmeta])* $field: $ty),* })?),+
+            }
+        }
+    };
+    (
+        @impl $vis:vis $name:ident as $abs:ident {
+            $(
+                $variant:ident $({
+                    $($(#[$fmeta:meta])* $field:ident: $ty:ty),* $(,)?
+                })?
+            ),+ $(,)?
+        }
+    ) => {
+        impl $name {
+            /// The number of variants of this enum.
+            #[allow(dead_code)]
+            pub const NUM_VARIANTS: usize = [$(stringify!($variant)),+].len();
+
+            /// The index of the variant of this value, in declaration order.
+            #[allow(dead_code, unused_assignments)]
+            pub fn variant_index(&self) -> usize {
+                let mut index = 0;
+                $(
+                    if let $name::$variant { .. } = self {
+                        return index;
+                    }
+                    index += 1;
+                )+
+                unreachable!()
+            }
+        }
+
+        #[doc = concat!("The abstract representation of a [`", stringify!($name), "`].")]
+        $vis struct $abs<S: ?Sized + $crate::SystemRepr<bool> $($($(+ $crate::SystemRepr<$ty>)*)?)+> {
+            /// A bit for each variant, of which exactly one is set.
+            $vis tag: $crate::__private::Vec<$crate::Abstract<S, bool>>,
+            $($($($(#[$fmeta])* $vis $field: $crate::Abstract<S, $ty>,)*)?)+
+        }
+
+        impl<S: ?Sized + $crate::SystemRepr<bool> $($($(+ $crate::SystemRepr<$ty>)*)?)+> Clone
+            for $abs<S>
+        {
+            fn clone(&self) -> Self {
+                Self {
+                    tag: self.tag.clone(),
+                    $($($($field: self.$field.clone(),)*)?)+
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        impl<S: ?Sized + $crate::SystemRepr<bool> $($($(+ $crate::SystemRepr<$ty>)*)?)+> $abs<S> {
+            /// Introduces a known value into the system.
+            pub fn constant(sys: &mut S, value: $name) -> Self {
+                let index = value.variant_index();
+                Self {
+                    tag: (0..$name::NUM_VARIANTS)
+                        .map(|i| <S as $crate::SystemRepr<bool>>::constant(sys, i == index))
+                        .collect(),
+                    $($($($field: <S as $crate::SystemRepr<$ty>>::constant(
+                        sys,
+                        match &value {
+                            $name::$variant { $field, .. } => $field.clone(),
+                            #[allow(unreachable_patterns)]
+                            _ => Default::default(),
+                        },
+                    ),)*)?)+
+                }
+            }
+
+            /// Introduces an arbitrary value into the system, asserting that it has a valid tag.
+            /// See `SystemRand`.
+            pub fn rand(sys: &mut S) -> Self
+            where
+                S: $crate::SystemRand<bool>
+                    + $crate::SystemAssert
+                    + $crate::SystemBitAnd<bool>
+                    + $crate::SystemBitOr<bool>
+                    + $crate::SystemNot<bool>
+                    $($($(+ $crate::SystemRand<$ty>)*)?)+
+            {
+                let res = Self {
+                    tag: (0..$name::NUM_VARIANTS)
+                        .map(|_| <S as $crate::SystemRand<bool>>::rand(sys))
+                        .collect(),
+                    $($($($field: <S as $crate::SystemRand<$ty>>::rand(sys),)*)?)+
+                };
+                let valid = res.is_valid(sys);
+                sys.assert(&valid);
+                res
+            }
+
+            /// Determines whether exactly one bit of the tag is set.
+            pub fn is_valid(&self, sys: &mut S) -> $crate::Abstract<S, bool>
+            where
+                S: $crate::SystemBitAnd<bool> + $crate::SystemBitOr<bool> + $crate::SystemNot<bool>,
+            {
+                let mut any = <S as $crate::SystemRepr<bool>>::constant(sys, false);
+                let mut many = <S as $crate::SystemRepr<bool>>::constant(sys, false);
+                for bit in self.tag.iter() {
+                    let dup = <S as $crate::SystemBitAnd<bool>>::and(sys, &any, bit);
+                    many = <S as $crate::SystemBitOr<bool>>::or(sys, &many, &dup);
+                    any = <S as $crate::SystemBitOr<bool>>::or(sys, &any, bit);
+                }
+                let single = <S as $crate::SystemNot<bool>>::not(sys, &many);
+                <S as $crate::SystemBitAnd<bool>>::and(sys, &any, &single)
+            }
+
+            /// Selects the entry of `arms` corresponding to the variant of this value, where
+            /// `arms` has an entry for each variant in declaration order.
+            pub fn match_abstract<T>(
+                &self,
+                sys: &mut S,
+                arms: &[$crate::Abstract<S, T>],
+            ) -> $crate::Abstract<S, T>
+            where
+                S: $crate::SystemSelect<T>,
+            {
+                assert_eq!(arms.len(), $name::NUM_VARIANTS, "wrong number of arms");
+                let (last, arms) = arms.split_last().unwrap();
+                let mut res = last.clone();
+                for (bit, arm) in self.tag.iter().zip(arms).rev() {
+                    res = <S as $crate::SystemSelect<T>>::select(sys, bit, arm, &res);
+                }
+                res
+            }
+
+            /// Returns `a` if `cond` is true, or `b` otherwise.
+            pub fn select(
+                sys: &mut S,
+                cond: &$crate::Abstract<S, bool>,
+                a: &Self,
+                b: &Self,
+            ) -> Self
+            where
+                S: $crate::SystemSelect<bool> $($($(+ $crate::SystemSelect<$ty>)*)?)+
+            {
+                Self {
+                    tag: (a.tag.iter().zip(b.tag.iter()))
+                        .map(|(a, b)| <S as $crate::SystemSelect<bool>>::select(sys, cond, a, b))
+                        .collect(),
+                    $($($($field: <S as $crate::SystemSelect<$ty>>::select(
+                        sys,
+                        cond,
+                        &a.$field,
+                        &b.$field,
+                    ),)*)?)+
+                }
+            }
+
+            /// Asserts that this value is equal to another, including the fields of inactive
+            /// variants.
+            pub fn assert_eq(&self, sys: &mut S, other: &Self)
+            where
+                S: $crate::SystemAssertEq<bool> $($($(+ $crate::SystemAssertEq<$ty>)*)?)+
+            {
+                for (a, b) in self.tag.iter().zip(other.tag.iter()) {
+                    <S as $crate::SystemAssertEq<bool>>::assert_eq(sys, a, b);
+                }
+                $($($(<S as $crate::SystemAssertEq<$ty>>::assert_eq(
+                    sys,
+                    &self.$field,
+                    &other.$field,
+                );)*)?)+
+            }
+
+            /// Gets the concrete value of this abstract value. Panics if the tag is not one-hot.
+            #[allow(unused_assignments)]
+            pub fn read_value(&self, sys: &S) -> $name
+            where
+                S: $crate::SystemRead<bool> $($($(+ $crate::SystemRead<$ty>)*)?)+
+            {
+                let tag: $crate::__private::Vec<bool> = (self.tag.iter())
+                    .map(|bit| <S as $crate::SystemRead<bool>>::read_value(sys, bit))
+                    .collect();
+                assert_eq!(tag.iter().filter(|bit| **bit).count(), 1, "invalid tag");
+                let mut index = 0;
+                $(
+                    if tag[index] {
+                        return $name::$variant $({
+                            $($field: <S as $crate::SystemRead<$ty>>::read_value(
+                                sys,
+                                &self.$field,
+                            ),)*
+                        })?;
+                    }
+                    index += 1;
+                )+
+                unreachable!()
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+abstract_struct! {
+    /// A payment between two accounts.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Payment as AbstractPayment {
+        from: u8,
+        to: u8,
+        amount: i32,
+        refund: bool,
+    }
+}
+
+#[cfg(all(feature = "binary", feature = "r1cs"))]
+#[test]
+fn test_abstract_struct() {
+    use crate::r1cs::ArithmeticSystem;
+    use crate::*;
+    use bls12_381::Scalar;
+
+    let a = Payment {
+        from: 1,
+        to: 2,
+        amount: -500,
+        refund: true,
+    };
+    let b = Payment {
+        from: 3,
+        to: 4,
+        amount: 1000,
+        refund: false,
+    };
+
+    // Evaluate directly, and through binary emulation
+    let abs_a = AbstractPayment::constant(&mut Eval, a);
+    let abs_b = AbstractPayment::constant(&mut Eval, b);
+    let res = AbstractPayment::select(&mut Eval, &false, &abs_a, &abs_b);
+    res.assert_eq(&mut Eval, &abs_b);
+    assert_eq!(res.read_value(&Eval), b);
+    let mut sys = BinaryEmulate::new(Eval);
+    let abs_a = AbstractPayment::constant(&mut sys, a);
+    assert_eq!(abs_a.read_value(&sys), a);
+
+    // Bind a witness in an arithmetic system, range-checking every field
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let witness = AbstractPayment::rand(&mut sys);
+    assert_eq!(sys.num_vars(), (1 + 8) * 2 + (1 + 32) + 1);
+    let known = AbstractPayment::constant(&mut sys, b);
+    let cond = witness.refund;
+    AbstractPayment::select(&mut sys, &cond, &witness, &known);
+}
+
+#[cfg(test)]
+abstract_enum! {
+    /// A message in a simple transfer protocol.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Message as AbstractMessage {
+        Ping,
+        Data { len: u32, last: bool },
+        Ack { seq: u8 },
+    }
+}
+
+#[cfg(feature = "binary")]
+#[test]
+fn test_abstract_enum() {
+    use crate::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    let values = [
+        Message::Ping,
+        Message::Data {
+            len: 300,
+            last: true,
+        },
+        Message::Ack { seq: 7 },
+    ];
+    assert_eq!(Message::NUM_VARIANTS, 3);
+    for (i, value) in values.into_iter().enumerate() {
+        assert_eq!(value.variant_index(), i);
+
+        // Evaluate directly, and through binary emulation
+        let abs = AbstractMessage::constant(&mut Eval, value);
+        assert_eq!(abs.read_value(&Eval), value);
+        assert!(abs.is_valid(&mut Eval));
+        let size = abs.match_abstract::<u32>(&mut Eval, &[0, abs.len, 1]);
+        assert_eq!(size, [0, 300, 1][i]);
+        let mut sys = BinaryEmulate::new(Eval);
+        let abs = AbstractMessage::constant(&mut sys, value);
+        assert_eq!(abs.read_value(&sys), value);
+        let arms: Vec<_> = (0..3)
+            .map(|j| SystemRepr::<u8>::constant(&mut sys, 10 * j))
+            .collect();
+        let res = abs.match_abstract::<u8>(&mut sys, &arms);
+        assert_eq!(SystemRead::<u8>::read_value(&sys, &res), 10 * i as u8);
+    }
+
+    // Fields of inactive variants are zero
+    let abs = AbstractMessage::constant(&mut Eval, values[2]);
+    assert_eq!((abs.len, abs.last, abs.seq), (0, false, 7));
+    let other = AbstractMessage::constant(&mut Eval, values[1]);
+    let res = AbstractMessage::select(&mut Eval, &false, &abs, &other);
+    res.assert_eq(&mut Eval, &other);
+
+    // Replacing fields to build a value with abstract fields
+    let mut abs = AbstractMessage::constant(&mut Eval, Message::Ack { seq: 0 });
+    abs.seq = 42;
+    assert_eq!(abs.read_value(&Eval), Message::Ack { seq: 42 });
+    abs.tag = vec![true, true, false];
+    assert!(!abs.is_valid(&mut Eval));
+    abs.tag = vec![false; 3];
+    assert!(!abs.is_valid(&mut Eval));
+}
+
+#[cfg(all(test, feature = "derive"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, crate::AbstractEnum)]
+enum Command {
+    Halt,
+    Jump {
+        /// The destination of the jump.
+        target: u32,
+    },
+    Store {
+        addr: u32,
+        value: u8,
+    },
+}
+
+#[cfg(all(feature = "derive", feature = "binary"))]
+#[test]
+fn test_derive_abstract_enum() {
+    use crate::*;
+    use alloc::vec::Vec;
+
+    let values = [
+        Command::Halt,
+        Command::Jump { target: 1000 },
+        Command::Store { addr: 5, value: 9 },
+    ];
+    assert_eq!(Command::NUM_VARIANTS, 3);
+    for (i, value) in values.into_iter().enumerate() {
+        assert_eq!(value.variant_index(), i);
+        let abs = AbstractCommand::constant(&mut Eval, value);
+        assert_eq!(abs.read_value(&Eval), value);
+        assert!(abs.is_valid(&mut Eval));
+        let mut sys = BinaryEmulate::new(Eval);
+        let abs = AbstractCommand::constant(&mut sys, value);
+        assert_eq!(abs.read_value(&sys), value);
+        let arms: Vec<_> = (0..3)
+            .map(|j| SystemRepr::<u8>::constant(&mut sys, 10 * j))
+            .collect();
+        let res = abs.match_abstract::<u8>(&mut sys, &arms);
+        assert_eq!(SystemRead::<u8>::read_value(&sys, &res), 10 * i as u8);
+    }
+    let abs = AbstractCommand::constant(&mut Eval, values[2]);
+    assert_eq!((abs.target, abs.addr, abs.value), (0, 5, 9));
+}
@@ -0,0 +1,320 @@
+//! Measurements of the cost of gadgets in each backend, so that a backend can be chosen on the
+//! basis of data. Gadgets are given as [`SubcircuitTemplate`]s, which can be lowered to any
+//! backend without running the gadget code again.
+use crate::graph::{Graph, Op, SubcircuitTemplate};
+use crate::manifest::write_json_str;
+use crate::*;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// The number of gates of each type in a circuit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GateCounts {
+    pub and: usize,
+    pub or: usize,
+    pub xor: usize,
+    pub not: usize,
+}
+
+impl GateCounts {
+    /// The total number of gates.
+    pub fn total(&self) -> usize {
+        self.and + self.or + self.xor + self.not
+    }
+}
+
+/// The cost of a gadget in a particular backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Measurement {
+    /// The number of constraints, for backends which produce constraints.
+    pub constraints: Option<usize>,
+
+    /// The number of gates of each type, for backends which produce gates.
+    pub gates: Option<GateCounts>,
+
+    /// The time taken to lower the gadget to the backend.
+    pub synth_time: Duration,
+
+    /// The time taken to compute the values of every wire of the lowered gadget, given values
+    /// for its inputs.
+    pub witness_time: Duration,
+}
+
+/// A backend in which the cost of gadgets can be measured.
+pub trait Backend {
+    /// The name of the backend, as it appears in a [`Matrix`].
+    fn name(&self) -> String;
+
+    /// Lowers the given gadget to this backend and measures the result. Inputs are all zero
+    /// when computing the witness.
+    fn measure(&self, gadget: &SubcircuitTemplate) -> Measurement;
+}
+
+/// A [`Backend`] which lowers gadgets to a flat [`Graph`] of boolean gates, and computes
+/// witnesses by evaluating it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GraphBackend;
+
+impl Backend for GraphBackend {
+    fn name(&self) -> String {
+        "graph".to_owned()
+    }
+
+    fn measure(&self, gadget: &SubcircuitTemplate) -> Measurement {
+        let start = Instant::now();
+        let mut graph = Graph::new();
+        let inputs: Vec<_> = (0..gadget.num_inputs()).map(|_| graph.input()).collect();
+        let outputs = gadget.instantiate(&mut graph, &inputs);
+        let synth_time = start.elapsed();
+
+        let mut gates = GateCounts::default();
+        for op in graph.nodes() {
+            match op {
+                Op::And(..) => gates.and += 1,
+                Op::Or(..) => gates.or += 1,
+                Op::Xor(..) => gates.xor += 1,
+                Op::Not(_) => gates.not += 1,
+                _ => {}
+            }
+        }
+        let start = Instant::now();
+        graph.replay(&mut Eval, &vec![false; inputs.len()], &outputs);
+        Measurement {
+            constraints: None,
+            gates: Some(gates),
+            synth_time,
+            witness_time: start.elapsed(),
+        }
+    }
+}
+
+/// A [`Backend`] which lowers gadgets to an [`ArithmeticSystem`](crate::r1cs::ArithmeticSystem)
+/// over the field `F`, and computes witnesses using
+/// [`ArithmeticSystem::solve`](crate::r1cs::ArithmeticSystem::solve).
+#[cfg(feature = "r1cs")]
+pub struct R1csBackend<F> {
+    name: String,
+    _marker: std::marker::PhantomData<F>,
+}
+
+#[cfg(feature = "r1cs")]
+impl<F> R1csBackend<F> {
+    /// Constructs an [`R1csBackend`], with a name identifying the field.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "r1cs")]
+impl<F: ff::PrimeField> Backend for R1csBackend<F> {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn measure(&self, gadget: &SubcircuitTemplate) -> Measurement {
+        use crate::r1cs::ArithmeticSystem;
+        let start = Instant::now();
+        let mut sys = ArithmeticSystem::<F>::new();
+        let vars: Vec<_> = (0..gadget.num_inputs()).map(|_| sys.declare()).collect();
+        let inputs: Vec<_> = vars.iter().map(|var| (*var).into()).collect();
+        gadget.instantiate(&mut sys, &inputs);
+        let synth_time = start.elapsed();
+
+        let start = Instant::now();
+        let known: Vec<_> = vars.iter().map(|var| (*var, F::zero())).collect();
+        let _ = sys.solve(&known);
+        Measurement {
+            constraints: Some(sys.num_constraints()),
+            gates: None,
+            synth_time,
+            witness_time: start.elapsed(),
+        }
+    }
+}
+
+/// A [`Measurement`] of a gadget in a backend, as an entry in a [`Matrix`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatrixEntry {
+    pub gadget: String,
+    pub backend: String,
+    pub measurement: Measurement,
+}
+
+/// The costs of a set of gadgets in a set of backends, as produced by [`matrix`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Matrix {
+    /// An entry for every pair of gadget and backend, ordered by gadget and then by backend.
+    pub entries: Vec<MatrixEntry>,
+}
+
+impl Matrix {
+    /// Gets the entry for the given gadget and backend.
+    pub fn get(&self, gadget: &str, backend: &str) -> Option<&MatrixEntry> {
+        (self.entries.iter()).find(|entry| entry.gadget == gadget && entry.backend == backend)
+    }
+
+    /// Serializes this matrix as CSV, with a header row. Fields which don't apply to a backend
+    /// are empty, and times are in nanoseconds.
+    pub fn to_csv(&self) -> String {
+        let mut res =
+            String::from("gadget,backend,constraints,and,or,xor,not,synth_ns,witness_ns\n");
+        for entry in self.entries.iter() {
+            let m = &entry.measurement;
+            let opt = |value: Option<usize>| value.map_or(String::new(), |v| v.to_string());
+            let gates = m.gates.as_ref();
+            writeln!(
+                res,
+                "{},{},{},{},{},{},{},{},{}",
+                csv_str(&entry.gadget),
+                csv_str(&entry.backend),
+                opt(m.constraints),
+                opt(gates.map(|g| g.and)),
+                opt(gates.map(|g| g.or)),
+                opt(gates.map(|g| g.xor)),
+                opt(gates.map(|g| g.not)),
+                m.synth_time.as_nanos(),
+                m.witness_time.as_nanos()
+            )
+            .unwrap();
+        }
+        res
+    }
+
+    /// Serializes this matrix as JSON. The result is an object with an `entries` array, where
+    /// each entry is an object with the names of the gadget and backend along with the fields of
+    /// its [`Measurement`]. Fields which don't apply to a backend are `null`, and times are in
+    /// nanoseconds.
+    pub fn to_json(&self) -> String {
+        let mut res = String::from("{\"entries\": [");
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i > 0 {
+                res.push(',');
+            }
+            let m = &entry.measurement;
+            res.push_str("\n  {\"gadget\": ");
+            write_json_str(&mut res, &entry.gadget);
+            res.push_str(", \"backend\": ");
+            write_json_str(&mut res, &entry.backend);
+            res.push_str(", \"constraints\": ");
+            match m.constraints {
+                Some(constraints) => write!(res, "{}", constraints).unwrap(),
+                None => res.push_str("null"),
+            }
+            res.push_str(", \"gates\": ");
+            match &m.gates {
+                Some(g) => write!(
+                    res,
+                    "{{\"and\": {}, \"or\": {}, \"xor\": {}, \"not\": {}}}",
+                    g.and, g.or, g.xor, g.not
+                )
+                .unwrap(),
+                None => res.push_str("null"),
+            }
+            write!(
+                res,
+                ", \"synth_ns\": {}, \"witness_ns\": {}}}",
+                m.synth_time.as_nanos(),
+                m.witness_time.as_nanos()
+            )
+            .unwrap();
+        }
+        if !self.entries.is_empty() {
+            res.push('\n');
+        }
+        res.push_str("]}\n");
+        res
+    }
+}
+
+/// Quotes a string for use as a CSV field, if needed.
+fn csv_str(str: &str) -> String {
+    if str.contains([',', '"', '\n']) {
+        format!("\"{}\"", str.replace('"', "\"\""))
+    } else {
+        str.to_owned()
+    }
+}
+
+/// Measures each of the given named gadgets in each of the given backends.
+pub fn matrix(gadgets: &[(&str, &SubcircuitTemplate)], backends: &[&dyn Backend]) -> Matrix {
+    let mut entries = Vec::with_capacity(gadgets.len() * backends.len());
+    for (name, gadget) in gadgets {
+        for backend in backends {
+            entries.push(MatrixEntry {
+                gadget: (*name).to_owned(),
+                backend: backend.name(),
+                measurement: backend.measure(gadget),
+            });
+        }
+    }
+    Matrix { entries }
+}
+
+#[cfg(feature = "r1cs")]
+#[test]
+fn test_matrix() {
+    use crate::r1cs::ArithmeticSystem;
+    use bls12_381::Scalar;
+
+    let add = SubcircuitTemplate::capture(16, |graph, inputs| {
+        let mut sys = BinaryEmulate::new(std::mem::take(graph));
+        let a: [_; 8] = array_init::array_init(|i| inputs[i]);
+        let b: [_; 8] = array_init::array_init(|i| inputs[8 + i]);
+        let r = SystemWrappingAdd::<u8>::wrapping_add(&mut sys, &a, &b);
+        *graph = sys.into_source();
+        r.to_vec()
+    });
+    let xor = SubcircuitTemplate::capture(2, |graph, inputs| {
+        vec![SystemBitXor::<bool>::xor(graph, &inputs[0], &inputs[1])]
+    });
+    let r1cs = R1csBackend::<Scalar>::new("r1cs-bls12-381");
+    let matrix = matrix(&[("add, u8", &add), ("xor", &xor)], &[&GraphBackend, &r1cs]);
+    assert_eq!(matrix.entries.len(), 4);
+    let names: Vec<_> = (matrix.entries.iter())
+        .map(|entry| (entry.gadget.as_str(), entry.backend.as_str()))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ("add, u8", "graph"),
+            ("add, u8", "r1cs-bls12-381"),
+            ("xor", "graph"),
+            ("xor", "r1cs-bls12-381")
+        ]
+    );
+
+    let graph = &matrix.get("add, u8", "graph").unwrap().measurement;
+    assert_eq!(graph.gates.unwrap().total(), add.num_gates());
+    assert_eq!(graph.constraints, None);
+    let xor_gates = matrix.get("xor", "graph").unwrap().measurement.gates;
+    assert_eq!(
+        xor_gates,
+        Some(GateCounts {
+            xor: 1,
+            ..GateCounts::default()
+        })
+    );
+
+    // The constraint counts match lowering directly
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let inputs: Vec<_> = (0..16).map(|_| sys.declare().into()).collect();
+    add.instantiate(&mut sys, &inputs);
+    let r1cs = &matrix.get("add, u8", "r1cs-bls12-381").unwrap().measurement;
+    assert_eq!(r1cs.constraints, Some(sys.num_constraints()));
+    assert_eq!(r1cs.gates, None);
+
+    let csv = matrix.to_csv();
+    assert_eq!(csv.lines().count(), 5);
+    assert!(csv
+        .lines()
+        .nth(3)
+        .unwrap()
+        .starts_with("xor,graph,,0,0,1,0,"));
+    assert!(csv.contains("\"add, u8\",r1cs-bls12-381,"));
+    let json = matrix.to_json();
+    assert_eq!(json.matches("\"gadget\"").count(), 4);
+    assert!(json.contains("\"gates\": {\"and\": 0, \"or\": 0, \"xor\": 1, \"not\": 0}"));
+}
@@ -0,0 +1,541 @@
+//! Semantics of the RV32I base instruction set, for proving the execution of small programs.
+use crate::alu::{Alu32, AluOp};
+use crate::binary::select_bit;
+use crate::bitset::{add_bits, add_const, const_bits, sub_bits, Bits};
+use crate::ram::AbstractRam;
+use crate::*;
+
+/// Major opcodes of RV32I, given by bits 2 to 6 of an instruction.
+const OP_LOAD: usize = 0b00000;
+const OP_MISC_MEM: usize = 0b00011;
+const OP_OP_IMM: usize = 0b00100;
+const OP_AUIPC: usize = 0b00101;
+const OP_STORE: usize = 0b01000;
+const OP_OP: usize = 0b01100;
+const OP_LUI: usize = 0b01101;
+const OP_BRANCH: usize = 0b11000;
+const OP_JALR: usize = 0b11001;
+const OP_JAL: usize = 0b11011;
+const OP_SYSTEM: usize = 0b11100;
+
+/// The ALU operations for each value of `funct3` in register and immediate arithmetic
+/// instructions, and the alternatives selected by bit 30 of the instruction.
+const ALU_OPS: [(AluOp, AluOp); 8] = [
+    (AluOp::Add, AluOp::Sub),
+    (AluOp::Sll, AluOp::Sll),
+    (AluOp::Slt, AluOp::Slt),
+    (AluOp::SltU, AluOp::SltU),
+    (AluOp::Xor, AluOp::Xor),
+    (AluOp::Srl, AluOp::Sra),
+    (AluOp::Or, AluOp::Or),
+    (AluOp::And, AluOp::And),
+];
+
+/// A system in which an [`Rv32i`] machine can be simulated.
+pub trait SystemRiscv: BinarySystem + SystemBits + SystemSelect<u32> {}
+
+impl<S: BinarySystem + SystemBits + SystemSelect<u32> + ?Sized> SystemRiscv for S {}
+
+/// The fields of an abstract RV32I instruction, as produced by [`decode`]. Register indices and
+/// `funct3` are little-endian strings of bits, and immediates are sign-extended to 32 bits.
+pub struct Decoded<S: SystemRepr<bool> + ?Sized> {
+    pub rd: Bits<S>,
+    pub rs1: Bits<S>,
+    pub rs2: Bits<S>,
+    pub funct3: Bits<S>,
+
+    /// Bit 30 of the instruction, which distinguishes `SUB` from `ADD` and `SRA` from `SRL`.
+    pub alt: Abstract<S, bool>,
+
+    pub imm_i: Bits<S>,
+    pub imm_s: Bits<S>,
+    pub imm_b: Bits<S>,
+    pub imm_u: Bits<S>,
+    pub imm_j: Bits<S>,
+
+    pub is_load: Abstract<S, bool>,
+    pub is_fence: Abstract<S, bool>,
+    pub is_op_imm: Abstract<S, bool>,
+    pub is_auipc: Abstract<S, bool>,
+    pub is_store: Abstract<S, bool>,
+    pub is_op: Abstract<S, bool>,
+    pub is_lui: Abstract<S, bool>,
+    pub is_branch: Abstract<S, bool>,
+    pub is_jalr: Abstract<S, bool>,
+    pub is_jal: Abstract<S, bool>,
+
+    /// Whether this is an `ECALL` or `EBREAK` instruction.
+    pub is_system: Abstract<S, bool>,
+
+    /// Whether the instruction has one of the major opcodes above. Other fields of instructions
+    /// with reserved encodings within those opcodes are not checked.
+    pub valid: Abstract<S, bool>,
+}
+
+/// Decodes an abstract RV32I instruction into its fields.
+pub fn decode<S: SystemRiscv + ?Sized>(sys: &mut S, instr: &Abstract<S, u32>) -> Decoded<S> {
+    let bits = sys.bits_of_u32(instr);
+    let sign = &bits[31];
+    let zero = sys.constant(false);
+    let imm = |parts: &[(usize, core::ops::Range<usize>)]| -> Bits<S> {
+        let mut res = vec![sign.clone(); 32];
+        res[..parts[0].0].fill(zero.clone());
+        for (start, range) in parts {
+            for (i, j) in range.clone().enumerate() {
+                res[start + i] = bits[j].clone();
+            }
+        }
+        res
+    };
+    let imm_i = imm(&[(0, 20..31)]);
+    let imm_s = imm(&[(0, 7..12), (5, 25..31)]);
+    let imm_b = imm(&[(1, 8..12), (5, 25..31), (11, 7..8)]);
+    let imm_j = imm(&[(1, 21..31), (11, 20..21), (12, 12..20)]);
+    let mut imm_u = vec![zero.clone(); 12];
+    imm_u.extend_from_slice(&bits[12..32]);
+
+    let ops = one_hot(sys, &bits[2..7], 32);
+    let quadrant = sys.and(&bits[0], &bits[1]);
+    let mut valid = zero.clone();
+    for op in [
+        OP_LOAD,
+        OP_MISC_MEM,
+        OP_OP_IMM,
+        OP_AUIPC,
+        OP_STORE,
+        OP_OP,
+        OP_LUI,
+        OP_BRANCH,
+        OP_JALR,
+        OP_JAL,
+        OP_SYSTEM,
+    ] {
+        valid = sys.or(&valid, &ops[op]);
+    }
+    let valid = sys.and(&valid, &quadrant);
+    Decoded {
+        rd: bits[7..12].to_vec(),
+        rs1: bits[15..20].to_vec(),
+        rs2: bits[20..25].to_vec(),
+        funct3: bits[12..15].to_vec(),
+        alt: bits[30].clone(),
+        imm_i,
+        imm_s,
+        imm_b,
+        imm_u,
+        imm_j,
+        is_load: ops[OP_LOAD].clone(),
+        is_fence: ops[OP_MISC_MEM].clone(),
+        is_op_imm: ops[OP_OP_IMM].clone(),
+        is_auipc: ops[OP_AUIPC].clone(),
+        is_store: ops[OP_STORE].clone(),
+        is_op: ops[OP_OP].clone(),
+        is_lui: ops[OP_LUI].clone(),
+        is_branch: ops[OP_BRANCH].clone(),
+        is_jalr: ops[OP_JALR].clone(),
+        is_jal: ops[OP_JAL].clone(),
+        is_system: ops[OP_SYSTEM].clone(),
+        valid,
+    }
+}
+
+/// A machine implementing the RV32I base instruction set, whose state is abstract, so that the
+/// execution of a program can be proven. Each step fetches, decodes and executes one
+/// instruction, synthesizing the logic for every kind of instruction and selecting between their
+/// effects.
+///
+/// The program and data memories are separate, and both consist of words. Byte addresses are
+/// mapped to them by taking the low bits of the word index, so addresses beyond the end of a
+/// memory wrap around. Accesses which are not naturally aligned, or which cross a word
+/// boundary, have unspecified results. `ECALL`, `EBREAK` and instructions with unrecognized
+/// opcodes halt the machine, and `FENCE` has no effect.
+pub struct Rv32i<S: SystemRepr<u32> + SystemRepr<bool> + ?Sized> {
+    alu: Alu32,
+    program: AbstractRam<S, u32>,
+    regs: AbstractRam<S, u32>,
+    memory: AbstractRam<S, u32>,
+    pc: Bits<S>,
+    halted: Abstract<S, bool>,
+}
+
+impl<S: SystemRiscv + ?Sized> Rv32i<S> {
+    /// Constructs an [`Rv32i`] machine with the given program and initial data memory.
+    /// Registers are initially zero, and execution starts at address zero.
+    pub fn new(sys: &mut S, program: Vec<Abstract<S, u32>>, memory: Vec<Abstract<S, u32>>) -> Self {
+        let zero = SystemRepr::<u32>::constant(sys, 0);
+        let ops: Vec<_> = ALU_OPS.iter().flat_map(|(a, b)| [*a, *b]).collect();
+        Self {
+            alu: Alu32::new(&ops),
+            program: AbstractRam::new(program),
+            regs: AbstractRam::new(vec![zero; 32]),
+            memory: AbstractRam::new(memory),
+            pc: const_bits(sys, 0, 32),
+            halted: SystemRepr::<bool>::constant(sys, false),
+        }
+    }
+
+    /// The current values of the registers, starting with `x0`.
+    pub fn registers(&self) -> &[Abstract<S, u32>] {
+        self.regs.cells()
+    }
+
+    /// The current contents of the data memory.
+    pub fn memory(&self) -> &[Abstract<S, u32>] {
+        self.memory.cells()
+    }
+
+    /// The byte address of the next instruction to execute, as a little-endian string of bits.
+    pub fn pc(&self) -> &[Abstract<S, bool>] {
+        &self.pc
+    }
+
+    /// Indicates whether the machine has halted.
+    pub fn halted(&self) -> &Abstract<S, bool> {
+        &self.halted
+    }
+
+    /// Executes `steps` instructions, or fewer if the machine halts.
+    pub fn run(&mut self, sys: &mut S, steps: usize) {
+        for _ in 0..steps {
+            self.step(sys);
+        }
+    }
+
+    /// Executes a single instruction, unless the machine has halted.
+    pub fn step(&mut self, sys: &mut S) {
+        let active = SystemNot::<bool>::not(sys, &self.halted);
+        let instr = self
+            .program
+            .read(sys, word_addr(&self.pc, self.program.len()));
+        let d = decode(sys, &instr);
+        let rs1 = self.regs.read(sys, &d.rs1);
+        let rs2 = self.regs.read(sys, &d.rs2);
+        let rs1 = sys.bits_of_u32(&rs1).to_vec();
+        let rs2 = sys.bits_of_u32(&rs2).to_vec();
+        let link = add_const(sys, &self.pc, 4);
+
+        // Arithmetic
+        let is_arith = SystemBitOr::<bool>::or(sys, &d.is_op, &d.is_op_imm);
+        let alu_b = select_bits(sys, &d.is_op, &rs2, &d.imm_i);
+        let alu_opcode = self.alu_opcode(sys, &d);
+        let alu_a = to_word(sys, &rs1);
+        let alu_b = to_word(sys, &alu_b);
+        let alu_res = self.alu.apply(sys, &alu_opcode, &alu_a, &alu_b);
+        let alu_res = sys.bits_of_u32(&alu_res).to_vec();
+
+        // Targets of jumps and branches, which share an adder with `AUIPC`
+        let base = select_bits(sys, &d.is_jalr, &rs1, &self.pc);
+        let offset = select_bits(sys, &d.is_branch, &d.imm_b, &d.imm_i);
+        let offset = select_bits(sys, &d.is_jal, &d.imm_j, &offset);
+        let offset = select_bits(sys, &d.is_auipc, &d.imm_u, &offset);
+        let target = add_bits(sys, &base, &offset);
+        let mut target = target[..32].to_vec();
+        let not_jalr = SystemNot::<bool>::not(sys, &d.is_jalr);
+        target[0] = SystemBitAnd::<bool>::and(sys, &target[0], &not_jalr);
+
+        // Branch conditions
+        let (diff, ltu) = sub_bits(sys, &rs1, &rs2);
+        let signs = SystemBitXor::<bool>::xor(sys, &rs1[31], &rs2[31]);
+        let lt = select_bit(sys, &signs, &rs1[31], &ltu);
+        let ne = diff.iter().skip(1).fold(diff[0].clone(), |acc, bit| {
+            SystemBitOr::<bool>::or(sys, &acc, bit)
+        });
+        let eq = SystemNot::<bool>::not(sys, &ne);
+        let cmp = select_bit(sys, &d.funct3[1], &ltu, &lt);
+        let cmp = select_bit(sys, &d.funct3[2], &cmp, &eq);
+        let taken = SystemBitXor::<bool>::xor(sys, &cmp, &d.funct3[0]);
+        let taken = SystemBitAnd::<bool>::and(sys, &taken, &d.is_branch);
+
+        // Memory accesses, which share an address between loads and stores
+        let mem_offset = select_bits(sys, &d.is_store, &d.imm_s, &d.imm_i);
+        let addr = add_bits(sys, &rs1, &mem_offset);
+        let index = word_addr(&addr, self.memory.len());
+        let word = self.memory.read(sys, index);
+        let word = sys.bits_of_u32(&word).to_vec();
+        let loaded = load(sys, &word, &addr[..2], &d.funct3);
+        let stored = store(sys, &word, &rs2, &addr[..2], &d.funct3);
+        let stored = to_word(sys, &stored);
+        let store = SystemBitAnd::<bool>::and(sys, &d.is_store, &active);
+        self.memory.write(sys, index, &stored, &store);
+
+        // Write the destination register, except for `x0`
+        let is_jump = SystemBitOr::<bool>::or(sys, &d.is_jal, &d.is_jalr);
+        let mut res = alu_res;
+        for (cond, value) in [
+            (&d.is_lui, &d.imm_u),
+            (&d.is_auipc, &target),
+            (&is_jump, &link),
+            (&d.is_load, &loaded),
+        ] {
+            res = select_bits(sys, cond, value, &res);
+        }
+        let mut writes = [&is_arith, &d.is_lui, &d.is_auipc, &is_jump, &d.is_load]
+            .into_iter()
+            .fold(SystemRepr::<bool>::constant(sys, false), |acc, flag| {
+                SystemBitOr::<bool>::or(sys, &acc, flag)
+            });
+        let rd_nonzero = d.rd.iter().skip(1).fold(d.rd[0].clone(), |acc, bit| {
+            SystemBitOr::<bool>::or(sys, &acc, bit)
+        });
+        writes = SystemBitAnd::<bool>::and(sys, &writes, &rd_nonzero);
+        writes = SystemBitAnd::<bool>::and(sys, &writes, &active);
+        let res = to_word(sys, &res);
+        self.regs.write(sys, &d.rd, &res, &writes);
+
+        // Update the program counter, unless halting
+        let jumps = SystemBitOr::<bool>::or(sys, &is_jump, &taken);
+        let next = select_bits(sys, &jumps, &target, &link[..32]);
+        let invalid = SystemNot::<bool>::not(sys, &d.valid);
+        let halt = SystemBitOr::<bool>::or(sys, &d.is_system, &invalid);
+        let halt = SystemBitAnd::<bool>::and(sys, &halt, &active);
+        let not_halt = SystemNot::<bool>::not(sys, &halt);
+        let advance = SystemBitAnd::<bool>::and(sys, &active, &not_halt);
+        self.pc = select_bits(sys, &advance, &next, &self.pc);
+        self.halted = SystemBitOr::<bool>::or(sys, &self.halted, &halt);
+    }
+
+    /// Determines the [`AluOp`] opcode for an arithmetic instruction. Bit 30 selects the
+    /// alternative operation for shifts, and for register-register instructions only, for
+    /// additions.
+    fn alu_opcode(&self, sys: &mut S, d: &Decoded<S>) -> Abstract<S, u8> {
+        let sel = one_hot(sys, &d.funct3, 8);
+        let alt_add = SystemBitAnd::<bool>::and(sys, &d.alt, &d.is_op);
+        let mut res = const_bits(sys, 0, 8);
+        for (i, (op, alt_op)) in ALU_OPS.iter().enumerate() {
+            let alt = if i == 0 { &alt_add } else { &d.alt };
+            let op = const_bits(sys, *op as u128, 8);
+            let alt_op = const_bits(sys, *alt_op as u128, 8);
+            let code = select_bits(sys, alt, &alt_op, &op);
+            res = (0..8)
+                .map(|j| {
+                    let bit = SystemBitAnd::<bool>::and(sys, &sel[i], &code[j]);
+                    SystemBitXor::<bool>::xor(sys, &res[j], &bit)
+                })
+                .collect();
+        }
+        let res: [_; 8] = array_init::array_init(|i| res[i].clone());
+        sys.u8_of_bits(&res)
+    }
+}
+
+/// Loads a byte, halfword or word, according to `funct3`, from the given word at the given byte
+/// offset.
+fn load<S: SystemRiscv + ?Sized>(
+    sys: &mut S,
+    word: &[Abstract<S, bool>],
+    offset: &[Abstract<S, bool>],
+    funct3: &[Abstract<S, bool>],
+) -> Bits<S> {
+    let zero = sys.constant(false);
+    let mut value = word.to_vec();
+    for (k, bit) in offset.iter().enumerate() {
+        let n = 8 << k;
+        value = (0..32)
+            .map(|i| select_bit(sys, bit, value.get(i + n).unwrap_or(&zero), &value[i]))
+            .collect();
+    }
+    let sign = select_bit(sys, &funct3[0], &value[15], &value[7]);
+    let not_unsigned = SystemNot::<bool>::not(sys, &funct3[2]);
+    let ext = SystemBitAnd::<bool>::and(sys, &sign, &not_unsigned);
+    let wide = SystemBitOr::<bool>::or(sys, &funct3[0], &funct3[1]);
+    (0..32)
+        .map(|i| match i {
+            0..8 => value[i].clone(),
+            8..16 => select_bit(sys, &wide, &value[i], &ext),
+            _ => select_bit(sys, &funct3[1], &value[i], &ext),
+        })
+        .collect()
+}
+
+/// Stores the low byte, halfword or word of `value`, according to `funct3`, into the given word
+/// at the given byte offset, returning the updated word.
+fn store<S: SystemRiscv + ?Sized>(
+    sys: &mut S,
+    word: &[Abstract<S, bool>],
+    value: &[Abstract<S, bool>],
+    offset: &[Abstract<S, bool>],
+    funct3: &[Abstract<S, bool>],
+) -> Bits<S> {
+    // Build a mask of the bits to replace, then shift it and the value into place
+    let one = sys.constant(true);
+    let zero = sys.constant(false);
+    let wide = SystemBitOr::<bool>::or(sys, &funct3[0], &funct3[1]);
+    let mut mask: Bits<S> = (0..32)
+        .map(|i| match i {
+            0..8 => one.clone(),
+            8..16 => wide.clone(),
+            _ => funct3[1].clone(),
+        })
+        .collect();
+    let mut value = value.to_vec();
+    for (k, bit) in offset.iter().enumerate() {
+        let n = 8 << k;
+        let shift = |sys: &mut S, bits: &[Abstract<S, bool>]| -> Bits<S> {
+            (0..32)
+                .map(|i: usize| {
+                    let shifted = i.checked_sub(n).map_or(&zero, |j| &bits[j]);
+                    select_bit(sys, bit, shifted, &bits[i])
+                })
+                .collect()
+        };
+        mask = shift(sys, &mask);
+        value = shift(sys, &value);
+    }
+    (0..32)
+        .map(|i| select_bit(sys, &mask[i], &value[i], &word[i]))
+        .collect()
+}
+
+/// Gets the bits of a byte address which identify a word within a memory of `len` words.
+fn word_addr<T>(addr: &[T], len: usize) -> &[T] {
+    let bits = (usize::BITS - len.saturating_sub(1).leading_zeros()) as usize;
+    &addr[2..2 + bits]
+}
+
+/// Returns `a` if `cond` is true, or `b` otherwise, for each corresponding pair of bits.
+fn select_bits<S: SystemRiscv + ?Sized>(
+    sys: &mut S,
+    cond: &Abstract<S, bool>,
+    a: &[Abstract<S, bool>],
+    b: &[Abstract<S, bool>],
+) -> Bits<S> {
+    (a.iter().zip(b.iter()))
+        .map(|(a, b)| select_bit(sys, cond, a, b))
+        .collect()
+}
+
+/// Assembles a word from the first 32 of the given bits.
+fn to_word<S: SystemRiscv + ?Sized>(sys: &mut S, bits: &[Abstract<S, bool>]) -> Abstract<S, u32> {
+    let bits: [_; 32] = array_init::array_init(|i| bits[i].clone());
+    sys.u32_of_bits(&bits)
+}
+
+/// Encoders for each instruction format, for assembling test programs.
+#[cfg(test)]
+mod asm {
+    pub fn r(funct7: u32, rs2: u32, rs1: u32, funct3: u32, rd: u32, op: u32) -> u32 {
+        funct7 << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | op
+    }
+
+    pub fn i(imm: i32, rs1: u32, funct3: u32, rd: u32, op: u32) -> u32 {
+        (imm as u32) << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | op
+    }
+
+    pub fn s(imm: i32, rs2: u32, rs1: u32, funct3: u32) -> u32 {
+        let imm = imm as u32;
+        (imm >> 5) << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | (imm & 31) << 7 | 0x23
+    }
+
+    pub fn b(imm: i32, rs2: u32, rs1: u32, funct3: u32) -> u32 {
+        let imm = imm as u32;
+        let hi = (imm >> 12 & 1) << 6 | (imm >> 5 & 63);
+        let lo = (imm >> 1 & 15) << 1 | (imm >> 11 & 1);
+        hi << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | lo << 7 | 0x63
+    }
+
+    pub fn u(imm: u32, rd: u32, op: u32) -> u32 {
+        imm << 12 | rd << 7 | op
+    }
+
+    pub fn j(imm: i32, rd: u32) -> u32 {
+        let imm = imm as u32;
+        let bits = (imm >> 20 & 1) << 19
+            | (imm >> 1 & 1023) << 9
+            | (imm >> 11 & 1) << 8
+            | (imm >> 12 & 255);
+        bits << 12 | rd << 7 | 0x6f
+    }
+}
+
+/// A program exercising every kind of instruction. It sums the integers from 1 to 10 in a loop,
+/// then stores and loads bytes and halfwords, and jumps around an instruction which must not be
+/// executed.
+#[cfg(test)]
+fn test_program() -> Vec<u32> {
+    use asm::*;
+    vec![
+        i(10, 0, 0, 1, 0x13),       // 0: addi x1, x0, 10
+        i(0, 0, 0, 2, 0x13),        // 4: addi x2, x0, 0
+        r(0, 1, 2, 0, 2, 0x33),     // 8: add x2, x2, x1
+        i(-1, 1, 0, 1, 0x13),       // 12: addi x1, x1, -1
+        b(-8, 0, 1, 1),             // 16: bne x1, x0, -8
+        s(4, 2, 0, 2),              // 20: sw x2, 4(x0)
+        u(0x12345, 3, 0x37),        // 24: lui x3, 0x12345
+        i(0x678, 3, 0, 3, 0x13),    // 28: addi x3, x3, 0x678
+        s(9, 3, 0, 0),              // 32: sb x3, 9(x0)
+        i(-128, 0, 0, 5, 0x13),     // 36: addi x5, x0, -128
+        s(10, 5, 0, 0),             // 40: sb x5, 10(x0)
+        i(10, 0, 0, 6, 0x03),       // 44: lb x6, 10(x0)
+        i(10, 0, 4, 7, 0x03),       // 48: lbu x7, 10(x0)
+        i(8, 0, 5, 8, 0x03),        // 52: lhu x8, 8(x0)
+        j(8, 9),                    // 56: jal x9, +8
+        i(1, 0, 0, 10, 0x13),       // 60: addi x10, x0, 1
+        u(0, 11, 0x17),             // 64: auipc x11, 0
+        i(0x404, 5, 5, 12, 0x13),   // 68: srai x12, x5, 4
+        r(0, 0, 5, 2, 13, 0x33),    // 72: slt x13, x5, x0
+        r(0, 0, 5, 3, 14, 0x33),    // 76: sltu x14, x5, x0
+        r(0x20, 3, 2, 0, 15, 0x33), // 80: sub x15, x2, x3
+        i(5, 0, 0, 0, 0x13),        // 84: addi x0, x0, 5
+        i(12, 11, 0, 17, 0x13),     // 88: addi x17, x11, 12
+        i(25, 17, 0, 16, 0x67),     // 92: jalr x16, 25(x17)
+        i(1, 0, 0, 10, 0x13),       // 96: addi x10, x0, 1
+        b(8, 2, 1, 5),              // 100: bge x1, x2, +8
+        s(0, 2, 0, 1),              // 104: sh x2, 0(x0)
+        0x73,                       // 108: ecall
+        i(1, 0, 0, 10, 0x13),       // 112: addi x10, x0, 1
+    ]
+}
+
+/// The registers and memory after running [`test_program`].
+#[cfg(test)]
+fn test_expected() -> ([u32; 18], [u32; 4]) {
+    let mut regs = [0; 18];
+    regs[2] = 55;
+    regs[3] = 0x12345678;
+    regs[5] = -128i32 as u32;
+    regs[6] = -128i32 as u32;
+    regs[7] = 0x80;
+    regs[8] = 0x7800;
+    regs[9] = 60;
+    regs[11] = 64;
+    regs[12] = -8i32 as u32;
+    regs[13] = 1;
+    regs[15] = 55u32.wrapping_sub(0x12345678);
+    regs[16] = 96;
+    regs[17] = 76;
+    (regs, [55, 55, 0x807800, 0])
+}
+
+#[test]
+fn test_rv32i_eval() {
+    let mut sys = Eval;
+    let mut machine = Rv32i::new(&mut sys, test_program(), vec![0; 4]);
+    let (regs, memory) = test_expected();
+    machine.run(&mut sys, 60);
+    assert!(*machine.halted());
+    assert_eq!(machine.registers()[..18], regs);
+    assert!(machine.registers()[18..].iter().all(|reg| *reg == 0));
+    assert_eq!(machine.memory(), memory);
+    let pc = (machine.pc().iter().enumerate()).fold(0, |acc, (i, bit)| acc | (*bit as u32) << i);
+    assert_eq!(pc, 108);
+}
+
+#[test]
+fn test_rv32i_binary_emulate() {
+    let mut sys = BinaryEmulate::new(Eval);
+    let program = test_program()
+        .into_iter()
+        .map(|x| sys.constant(x))
+        .collect();
+    let memory = [0u32; 4].into_iter().map(|x| sys.constant(x)).collect();
+    let mut machine = Rv32i::new(&mut sys, program, memory);
+    let (regs, memory) = test_expected();
+    machine.run(&mut sys, 60);
+    assert!(*machine.halted());
+    for (reg, expected) in machine.registers().iter().zip(regs) {
+        assert_eq!(SystemRead::<u32>::read_value(&sys, reg), expected);
+    }
+    for (word, expected) in machine.memory().iter().zip(memory) {
+        assert_eq!(SystemRead::<u32>::read_value(&sys, word), expected);
+    }
+}
@@ -0,0 +1,209 @@
+//! Shamir secret sharing over field elements, with polynomials committed to by hashing their
+//! coefficients with Poseidon. This supports the proofs needed for threshold signatures and
+//! distributed key generation, where a dealer shows that each share lies on the same committed
+//! polynomial, and a secret is recovered from a threshold of shares.
+use crate::crypto::hash::{PoseidonParams, SystemPoseidon};
+use crate::field::FieldElement;
+use crate::*;
+use ff::PrimeField;
+
+/// A system in which [`ShamirScheme`] shares can be verified and reconstructed.
+pub trait SystemShamir<F: PrimeField>:
+    SystemPoseidon<F> + SystemInverse<FieldElement<F>> + SystemBitAnd<bool> + SystemNot<bool>
+{
+}
+
+impl<
+        F: PrimeField,
+        S: SystemPoseidon<F> + SystemInverse<FieldElement<F>> + SystemBitAnd<bool> + SystemNot<bool>,
+    > SystemShamir<F> for S
+{
+}
+
+/// Describes a Shamir secret sharing scheme with a fixed threshold. A secret is shared using a
+/// polynomial with `threshold` coefficients, whose constant term is the secret. The share for
+/// index `x` is the value of the polynomial at `x`, which must be non-zero, so that any
+/// `threshold` shares with distinct indices determine the secret.
+#[derive(Debug, Clone)]
+pub struct ShamirScheme<F> {
+    params: PoseidonParams<F>,
+    threshold: usize,
+}
+
+impl<F: PrimeField> ShamirScheme<F> {
+    /// Constructs a [`ShamirScheme`] with the given threshold.
+    pub fn new(threshold: usize) -> Self {
+        assert!(threshold >= 1, "threshold must be at least 1");
+        Self {
+            params: PoseidonParams::width_3(),
+            threshold,
+        }
+    }
+
+    /// The number of shares needed to reconstruct a secret.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// The parameters of the hash used for commitments.
+    pub fn params(&self) -> &PoseidonParams<F> {
+        &self.params
+    }
+
+    /// Computes the commitment to a polynomial, given its coefficients with the constant term
+    /// first. The coefficients are absorbed one at a time into a Poseidon hash chain starting
+    /// from zero.
+    pub fn commit<S: SystemShamir<F> + ?Sized>(
+        &self,
+        sys: &mut S,
+        coeffs: &[Abstract<S, FieldElement<F>>],
+    ) -> Abstract<S, FieldElement<F>> {
+        assert_eq!(coeffs.len(), self.threshold, "wrong number of coefficients");
+        let mut acc = SystemRepr::<FieldElement<F>>::constant(sys, FieldElement(F::zero()));
+        for coeff in coeffs {
+            acc = sys.poseidon_hash2(&self.params, &acc, coeff);
+        }
+        acc
+    }
+
+    /// Evaluates a polynomial, given its coefficients with the constant term first, at the
+    /// given point.
+    pub fn evaluate<S: SystemShamir<F> + ?Sized>(
+        &self,
+        sys: &mut S,
+        coeffs: &[Abstract<S, FieldElement<F>>],
+        x: &Abstract<S, FieldElement<F>>,
+    ) -> Abstract<S, FieldElement<F>> {
+        assert_eq!(coeffs.len(), self.threshold, "wrong number of coefficients");
+        let (last, rest) = coeffs.split_last().unwrap();
+        let mut acc = last.clone();
+        for coeff in rest.iter().rev() {
+            let term = SystemMul::<FieldElement<F>>::mul(sys, &acc, x);
+            acc = SystemAdd::<FieldElement<F>>::add(sys, &term, coeff);
+        }
+        acc
+    }
+
+    /// Determines whether `share` is the share for `index` of the polynomial with the given
+    /// coefficients, and whether those coefficients match `commitment`. `index` must be non-zero,
+    /// since the share for zero is the secret itself.
+    pub fn verify_share<S: SystemShamir<F> + ?Sized>(
+        &self,
+        sys: &mut S,
+        commitment: &Abstract<S, FieldElement<F>>,
+        coeffs: &[Abstract<S, FieldElement<F>>],
+        index: &Abstract<S, FieldElement<F>>,
+        share: &Abstract<S, FieldElement<F>>,
+    ) -> Abstract<S, bool> {
+        let actual_commitment = self.commit(sys, coeffs);
+        let commitment_valid = eq(sys, &actual_commitment, commitment);
+        let actual_share = self.evaluate(sys, coeffs, index);
+        let share_valid = eq(sys, &actual_share, share);
+        let index_zero = SystemInverse::<FieldElement<F>>::is_zero(sys, index);
+        let index_valid = SystemNot::<bool>::not(sys, &index_zero);
+        let valid = SystemBitAnd::<bool>::and(sys, &commitment_valid, &share_valid);
+        SystemBitAnd::<bool>::and(sys, &valid, &index_valid)
+    }
+
+    /// Reconstructs a secret from `threshold` shares, using Lagrange interpolation at zero.
+    /// The indices of the shares must be distinct and non-zero. For constraint systems, this is
+    /// not checked separately, but otherwise the system is unsatisfiable. See
+    /// [`SystemInverse::inverse_unchecked`].
+    pub fn reconstruct<S: SystemShamir<F> + ?Sized>(
+        &self,
+        sys: &mut S,
+        indices: &[Abstract<S, FieldElement<F>>],
+        shares: &[Abstract<S, FieldElement<F>>],
+    ) -> Abstract<S, FieldElement<F>> {
+        assert_eq!(indices.len(), self.threshold, "wrong number of indices");
+        assert_eq!(shares.len(), self.threshold, "wrong number of shares");
+        let mut secret = SystemRepr::<FieldElement<F>>::constant(sys, FieldElement(F::zero()));
+        for (i, (x_i, y_i)) in indices.iter().zip(shares.iter()).enumerate() {
+            // The Lagrange basis polynomial for `x_i`, evaluated at zero, is the product of
+            // `x_j / (x_j - x_i)` over all other indices
+            let mut num = y_i.clone();
+            let mut den = SystemRepr::<FieldElement<F>>::constant(sys, FieldElement(F::one()));
+            for (j, x_j) in indices.iter().enumerate() {
+                if i != j {
+                    num = SystemMul::<FieldElement<F>>::mul(sys, &num, x_j);
+                    let diff = sub(sys, x_j, x_i);
+                    den = SystemMul::<FieldElement<F>>::mul(sys, &den, &diff);
+                }
+            }
+            let term = SystemInverse::<FieldElement<F>>::div_unchecked(sys, &num, &den);
+            secret = SystemAdd::<FieldElement<F>>::add(sys, &secret, &term);
+        }
+        secret
+    }
+}
+
+/// Subtracts `b` from `a`.
+fn sub<F: PrimeField, S: SystemShamir<F> + ?Sized>(
+    sys: &mut S,
+    a: &Abstract<S, FieldElement<F>>,
+    b: &Abstract<S, FieldElement<F>>,
+) -> Abstract<S, FieldElement<F>> {
+    let neg = SystemRepr::<FieldElement<F>>::constant(sys, FieldElement(-F::one()));
+    let neg_b = SystemMul::<FieldElement<F>>::mul(sys, &neg, b);
+    SystemAdd::<FieldElement<F>>::add(sys, a, &neg_b)
+}
+
+/// Determines whether two field elements are equal.
+fn eq<F: PrimeField, S: SystemShamir<F> + ?Sized>(
+    sys: &mut S,
+    a: &Abstract<S, FieldElement<F>>,
+    b: &Abstract<S, FieldElement<F>>,
+) -> Abstract<S, bool> {
+    let diff = sub(sys, a, b);
+    SystemInverse::<FieldElement<F>>::is_zero(sys, &diff)
+}
+
+#[cfg(feature = "r1cs")]
+#[test]
+fn test_shamir() {
+    use crate::r1cs::{ArithmeticSystem, Formula, Variable};
+    use bls12_381::Scalar;
+    let scheme = ShamirScheme::<Scalar>::new(3);
+    let x = |n: u64| FieldElement(Scalar::from(n));
+
+    // Share a secret of 42 among 5 parties, and recover it from any 3 of them
+    let coeffs = [x(42), x(7), x(1000)];
+    let commitment = scheme.commit(&mut Eval, &coeffs);
+    let shares: Vec<_> = (1..=5)
+        .map(|i| scheme.evaluate(&mut Eval, &coeffs, &x(i)))
+        .collect();
+    assert_eq!(shares[1], x(42 + 7 * 2 + 1000 * 4));
+    for i in 0..5 {
+        assert!(scheme.verify_share(
+            &mut Eval,
+            &commitment,
+            &coeffs,
+            &x(i + 1),
+            &shares[i as usize]
+        ));
+    }
+    assert!(!scheme.verify_share(&mut Eval, &commitment, &coeffs, &x(1), &shares[1]));
+    let other = [x(42), x(8), x(1000)];
+    assert!(!scheme.verify_share(&mut Eval, &commitment, &other, &x(1), &shares[0]));
+
+    // The share for index zero is the secret, so it must not be accepted
+    assert_eq!(scheme.evaluate(&mut Eval, &coeffs, &x(0)), x(42));
+    assert!(!scheme.verify_share(&mut Eval, &commitment, &coeffs, &x(0), &x(42)));
+    let secret = scheme.reconstruct(
+        &mut Eval,
+        &[x(5), x(2), x(3)],
+        &[shares[4], shares[1], shares[2]],
+    );
+    assert_eq!(secret, x(42));
+
+    // Check reconstruction in an arithmetic system
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let vars: Vec<_> = (0..6).map(|_| sys.declare()).collect();
+    let f = |i: usize| Formula::from(vars[i]);
+    let res = scheme.reconstruct(&mut sys, &[f(0), f(1), f(2)], &[f(3), f(4), f(5)]);
+    let values = [x(1), x(3), x(4), shares[0], shares[2], shares[3]].map(|v| v.0);
+    let known: Vec<(Variable, Scalar)> = vars.iter().copied().zip(values).collect();
+    let assignment = sys.solve(&known).unwrap();
+    assert_eq!(sys.check(&assignment), Ok(()));
+    assert_eq!(sys.eval(res, &assignment), Scalar::from(42));
+}
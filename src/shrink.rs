@@ -0,0 +1,87 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Searches for a simpler input which still makes a gadget fail, starting from a failing
+/// `input`. `fails` runs the gadget on a candidate input, typically under [`Eval`](crate::Eval),
+/// and determines whether it fails; see [`eval_fails`]. The length of the input is preserved,
+/// since gadgets usually take inputs of a fixed size.
+///
+/// The search first tries zeroing each byte, then clearing each set bit, repeating until
+/// neither makes progress. The result is a failing input in which no single byte can be zeroed
+/// and no single bit can be cleared while still failing, which usually leaves only the bits
+/// relevant to the failure.
+pub fn shrink_input(input: &[u8], mut fails: impl FnMut(&[u8]) -> bool) -> Vec<u8> {
+    assert!(fails(input), "the initial input must fail");
+    let mut input = input.to_vec();
+    let zero = vec![0; input.len()];
+    if input != zero && fails(&zero) {
+        return zero;
+    }
+    loop {
+        let mut progress = false;
+        for i in 0..input.len() {
+            if input[i] != 0 {
+                let byte = std::mem::take(&mut input[i]);
+                if fails(&input) {
+                    progress = true;
+                } else {
+                    input[i] = byte;
+                }
+            }
+        }
+        for i in 0..input.len() {
+            for bit in (0..8).rev() {
+                let mask = 1 << bit;
+                if input[i] & mask != 0 {
+                    input[i] ^= mask;
+                    if fails(&input) {
+                        progress = true;
+                    } else {
+                        input[i] ^= mask;
+                    }
+                }
+            }
+        }
+        if !progress {
+            return input;
+        }
+    }
+}
+
+/// Runs a gadget, returning `true` if it panics, as [`Eval`](crate::Eval) does when an
+/// assertion fails. This can be used as the predicate for [`shrink_input`]. The panic is caught,
+/// but its message is still reported by the panic hook.
+pub fn eval_fails(gadget: impl FnOnce()) -> bool {
+    catch_unwind(AssertUnwindSafe(gadget)).is_err()
+}
+
+#[test]
+fn test_shrink_input() {
+    use crate::*;
+
+    // A gadget which fails when the sum of the first two bytes overflows, and the last byte is
+    // odd
+    let fails = |input: &[u8]| input[0] as u16 + input[1] as u16 >= 256 && input[3] & 1 == 1;
+    let input = [0xf3, 0xa7, 0x5c, 0x2b];
+    assert!(fails(&input));
+    let shrunk = shrink_input(&input, fails);
+    assert!(fails(&shrunk));
+    assert_eq!(shrunk[2], 0);
+    assert_eq!(shrunk[3], 1);
+    assert!(shrunk[0] as u16 + shrunk[1] as u16 >= 256);
+
+    // Inputs which fail even when zeroed shrink to zero
+    assert_eq!(shrink_input(&[1, 2, 3], |_| true), [0, 0, 0]);
+
+    // Through assertions in `Eval`
+    let gadget = |input: &[u8]| {
+        let mut sys = Eval;
+        let a = u32::from_le_bytes(input.try_into().unwrap());
+        let high = SystemBitAnd::<u32>::and(&mut sys, &a, &0xff00_0000);
+        let ok = SystemOrd::<u32>::lt(&mut sys, &high, &0x1000_0000);
+        sys.assert(&ok);
+    };
+    let shrunk = shrink_input(&[0xff, 0x12, 0x34, 0xf6], |input| {
+        eval_fails(|| gadget(input))
+    });
+    assert_eq!(shrunk, [0, 0, 0, 0x10]);
+}
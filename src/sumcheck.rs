@@ -0,0 +1,282 @@
+//! Verification of sumcheck proofs, which reduce a claim about the sum of a multivariate
+//! polynomial over the boolean hypercube to a claim about its value at a single random point.
+//! This is the core of GKR and Spartan-style proof systems, so it's needed to verify them
+//! recursively.
+//!
+//! Challenges are derived from a [`PoseidonTranscript`], so a prover running the same protocol
+//! in [`Eval`] will agree with the verifier about them.
+use crate::crypto::hash::SystemPoseidon;
+use crate::field::FieldElement;
+use crate::transcript::{PoseidonTranscript, Transcript};
+use crate::*;
+use alloc::vec::Vec;
+use ff::PrimeField;
+
+/// A system in which sumcheck proofs over the field `F` can be verified.
+pub trait SystemSumcheck<F: PrimeField>:
+    SystemPoseidon<F> + SystemAssertEq<FieldElement<F>>
+{
+}
+
+impl<F: PrimeField, S: SystemPoseidon<F> + SystemAssertEq<FieldElement<F>> + ?Sized>
+    SystemSumcheck<F> for S
+{
+}
+
+/// Describes an instance of the sumcheck protocol, which proves that the sum of a polynomial
+/// `g`, over all points in `{0, 1}^num_vars`, is some claimed value. `g` must have degree at
+/// most `degree` in each of its variables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sumcheck {
+    pub num_vars: usize,
+    pub degree: usize,
+}
+
+/// The claim which remains after verifying a sumcheck proof: that `g(point) = value`. The
+/// verifier must check this independently, typically by evaluating `g` directly or by handing
+/// the claim to another protocol.
+pub struct SumcheckSubclaim<S: SystemRepr<FieldElement<F>> + ?Sized, F> {
+    pub point: Vec<Abstract<S, FieldElement<F>>>,
+    pub value: Abstract<S, FieldElement<F>>,
+}
+
+impl Sumcheck {
+    /// Constructs a [`Sumcheck`] for a polynomial with the given number of variables and degree
+    /// in each variable.
+    pub fn new(num_vars: usize, degree: usize) -> Self {
+        Self { num_vars, degree }
+    }
+
+    /// Absorbs the shape of this instance and the claimed sum into the transcript. This binds
+    /// every challenge to the claim, so that a prover can't choose the claim after seeing the
+    /// challenges. [`Sumcheck::verify`] does this first, and a prover must do the same.
+    pub fn absorb_instance<S: SystemSumcheck<F> + ?Sized, F: PrimeField>(
+        &self,
+        sys: &mut S,
+        transcript: &mut PoseidonTranscript<S, F>,
+        claim: &Abstract<S, FieldElement<F>>,
+    ) {
+        let num_vars = sys.constant(FieldElement(F::from(self.num_vars as u64)));
+        let degree = sys.constant(FieldElement(F::from(self.degree as u64)));
+        transcript.absorb(sys, &[num_vars, degree, claim.clone()]);
+    }
+
+    /// Verifies a sumcheck proof for the given claimed sum, returning the resulting
+    /// [`SumcheckSubclaim`]. The instance and claim are absorbed into the transcript first, using
+    /// [`Sumcheck::absorb_instance`]. Each round of the proof consists of the evaluations of the
+    /// round polynomial at `0, 1, ..., degree`. These are absorbed into the transcript, and the
+    /// challenge for the round is then squeezed from it.
+    pub fn verify<S: SystemSumcheck<F> + ?Sized, F: PrimeField>(
+        &self,
+        sys: &mut S,
+        transcript: &mut PoseidonTranscript<S, F>,
+        claim: &Abstract<S, FieldElement<F>>,
+        rounds: &[Vec<Abstract<S, FieldElement<F>>>],
+    ) -> SumcheckSubclaim<S, F> {
+        assert_eq!(rounds.len(), self.num_vars, "wrong number of rounds");
+        self.absorb_instance(sys, transcript, claim);
+        let mut point = Vec::with_capacity(self.num_vars);
+        let mut value = claim.clone();
+        for evals in rounds {
+            assert_eq!(evals.len(), self.degree + 1, "wrong number of evaluations");
+            let sum = sys.add(&evals[0], &evals[1]);
+            sys.assert_eq(&sum, &value);
+            transcript.absorb(sys, evals);
+            let challenge = transcript.squeeze(sys);
+            value = interpolate(sys, evals, &challenge);
+            point.push(challenge);
+        }
+        SumcheckSubclaim { point, value }
+    }
+
+    /// Verifies a sumcheck proof for the given claimed sum, then checks the resulting
+    /// [`SumcheckSubclaim`] using `eval`, which evaluates the polynomial at a point. Returns the
+    /// point.
+    pub fn verify_with<S: SystemSumcheck<F> + ?Sized, F: PrimeField>(
+        &self,
+        sys: &mut S,
+        transcript: &mut PoseidonTranscript<S, F>,
+        claim: &Abstract<S, FieldElement<F>>,
+        rounds: &[Vec<Abstract<S, FieldElement<F>>>],
+        eval: impl FnOnce(&mut S, &[Abstract<S, FieldElement<F>>]) -> Abstract<S, FieldElement<F>>,
+    ) -> Vec<Abstract<S, FieldElement<F>>> {
+        let subclaim = self.verify(sys, transcript, claim, rounds);
+        let value = eval(sys, &subclaim.point);
+        sys.assert_eq(&value, &subclaim.value);
+        subclaim.point
+    }
+}
+
+/// Evaluates the univariate polynomial with the given evaluations at `0, 1, ..., n - 1` at
+/// `point`, where the polynomial has degree less than `n`. This costs about `3n`
+/// multiplications.
+pub fn interpolate<
+    S: SystemAdd<FieldElement<F>> + SystemMul<FieldElement<F>> + ?Sized,
+    F: PrimeField,
+>(
+    sys: &mut S,
+    evals: &[Abstract<S, FieldElement<F>>],
+    point: &Abstract<S, FieldElement<F>>,
+) -> Abstract<S, FieldElement<F>> {
+    let n = evals.len();
+    let diffs: Vec<_> = (0..n as u64)
+        .map(|j| {
+            let neg_j = sys.constant(FieldElement(-F::from(j)));
+            sys.add(point, &neg_j)
+        })
+        .collect();
+
+    // The Lagrange basis polynomial for `i` is `w_i * prefix[i] * suffix[i + 1]`, where `prefix`
+    // and `suffix` are the products of the differences before and after `i`
+    let one = sys.constant(FieldElement(F::one()));
+    let mut prefix = Vec::with_capacity(n + 1);
+    prefix.push(one.clone());
+    for diff in diffs.iter() {
+        let next = sys.mul(prefix.last().unwrap(), diff);
+        prefix.push(next);
+    }
+    let mut suffix = alloc::vec![one; n + 1];
+    for i in (0..n).rev() {
+        suffix[i] = sys.mul(&suffix[i + 1], &diffs[i]);
+    }
+    let mut res = sys.constant(FieldElement(F::zero()));
+    for (i, eval) in evals.iter().enumerate() {
+        // `w_i` is the inverse of `i! * (n - 1 - i)! * (-1)^(n - 1 - i)`
+        let denom = (0..n as u64)
+            .filter(|j| *j != i as u64)
+            .fold(F::one(), |acc, j| acc * (F::from(i as u64) - F::from(j)));
+        let weight = sys.constant(FieldElement(denom.invert().unwrap()));
+        let basis = sys.mul(&prefix[i], &suffix[i + 1]);
+        let basis = sys.mul(&basis, &weight);
+        let term = sys.mul(eval, &basis);
+        res = sys.add(&res, &term);
+    }
+    res
+}
+
+#[cfg(feature = "r1cs")]
+#[test]
+fn test_sumcheck() {
+    use crate::crypto::hash::PoseidonParams;
+    use crate::r1cs::{ArithmeticSystem, Formula, Variable};
+    use bls12_381::Scalar;
+
+    /// Fixes the first variable of a multilinear polynomial, given by its evaluations over the
+    /// hypercube with the first variable as the least significant bit of the index.
+    fn fold<S: SystemAdd<FieldElement<Scalar>> + SystemMul<FieldElement<Scalar>> + ?Sized>(
+        sys: &mut S,
+        table: &[Abstract<S, FieldElement<Scalar>>],
+        r: &Abstract<S, FieldElement<Scalar>>,
+    ) -> Vec<Abstract<S, FieldElement<Scalar>>> {
+        let neg_one = sys.constant(FieldElement(-Scalar::one()));
+        (table.chunks(2))
+            .map(|pair| {
+                let neg = sys.mul(&pair[0], &neg_one);
+                let diff = sys.add(&pair[1], &neg);
+                let step = sys.mul(r, &diff);
+                sys.add(&pair[0], &step)
+            })
+            .collect()
+    }
+
+    // Prove the sum of the product of two multilinear polynomials in 3 variables
+    let x = |n: u64| FieldElement(Scalar::from(n));
+    let a: Vec<_> = [3, 1, 4, 1, 5, 9, 2, 6].map(x).to_vec();
+    let b: Vec<_> = [2, 7, 1, 8, 2, 8, 1, 8].map(x).to_vec();
+    let sumcheck = Sumcheck::new(3, 2);
+    let claim = (a.iter().zip(b.iter())).fold(x(0), |acc, (a, b)| &acc + &(a * b));
+    let mut transcript =
+        PoseidonTranscript::new(&mut Eval, PoseidonParams::width_3(), Scalar::from(1));
+    sumcheck.absorb_instance(&mut Eval, &mut transcript, &claim);
+    let (mut ta, mut tb) = (a.clone(), b.clone());
+    let mut rounds = Vec::new();
+    for _ in 0..sumcheck.num_vars {
+        let evals: Vec<_> = (0..=sumcheck.degree as u64)
+            .map(|t| {
+                let fa = fold(&mut Eval, &ta, &x(t));
+                let fb = fold(&mut Eval, &tb, &x(t));
+                (fa.iter().zip(fb.iter())).fold(x(0), |acc, (a, b)| &acc + &(a * b))
+            })
+            .collect();
+        transcript.absorb(&mut Eval, &evals);
+        let r = transcript.squeeze(&mut Eval);
+        ta = fold(&mut Eval, &ta, &r);
+        tb = fold(&mut Eval, &tb, &r);
+        rounds.push(evals);
+    }
+
+    // Verify the proof in Eval
+    let eval = |sys: &mut Eval, point: &[FieldElement<Scalar>]| {
+        let (mut ta, mut tb) = (a.clone(), b.clone());
+        for r in point {
+            ta = fold(sys, &ta, r);
+            tb = fold(sys, &tb, r);
+        }
+        &ta[0] * &tb[0]
+    };
+    let mut transcript =
+        PoseidonTranscript::new(&mut Eval, PoseidonParams::width_3(), Scalar::from(1));
+    let subclaim = sumcheck.verify(&mut Eval, &mut transcript, &claim, &rounds);
+    assert_eq!(subclaim.value, eval(&mut Eval, &subclaim.point));
+
+    // Verify the proof in an arithmetic system, with the claim and rounds as witnesses
+    let verify = |claim: FieldElement<Scalar>| {
+        let mut sys = ArithmeticSystem::<Scalar>::new();
+        let mut known: Vec<(Variable, Scalar)> = Vec::new();
+        let mut witness = |sys: &mut ArithmeticSystem<Scalar>, value: &FieldElement<Scalar>| {
+            let var = sys.declare();
+            known.push((var, value.0));
+            Formula::from(var)
+        };
+        let claim_var = witness(&mut sys, &claim);
+        let round_vars: Vec<Vec<_>> = (rounds.iter())
+            .map(|evals| evals.iter().map(|e| witness(&mut sys, e)).collect())
+            .collect();
+        let mut transcript =
+            PoseidonTranscript::new(&mut sys, PoseidonParams::width_3(), Scalar::from(1));
+        sumcheck.verify_with(
+            &mut sys,
+            &mut transcript,
+            &claim_var,
+            &round_vars,
+            |sys, point| {
+                let (mut ta, mut tb): (Vec<_>, Vec<_>) = (
+                    a.iter().map(|v| sys.constant(*v)).collect(),
+                    b.iter().map(|v| sys.constant(*v)).collect(),
+                );
+                for r in point {
+                    ta = fold(sys, &ta, r);
+                    tb = fold(sys, &tb, r);
+                }
+                SystemMul::<FieldElement<Scalar>>::mul(sys, &ta[0], &tb[0])
+            },
+        );
+        let assignment = sys.solve(&known).unwrap();
+        sys.check(&assignment)
+    };
+    assert_eq!(verify(claim), Ok(()));
+    assert!(verify(&claim + &x(1)).is_err());
+
+    // Challenges are bound to the claim, so it can't be chosen after seeing them
+    let first_challenge = |claim: &FieldElement<Scalar>| {
+        let mut transcript =
+            PoseidonTranscript::new(&mut Eval, PoseidonParams::width_3(), Scalar::from(1));
+        sumcheck.absorb_instance(&mut Eval, &mut transcript, claim);
+        transcript.absorb(&mut Eval, &rounds[0]);
+        transcript.squeeze(&mut Eval)
+    };
+    assert_ne!(first_challenge(&claim), first_challenge(&(&claim + &x(1))));
+}
+
+#[test]
+fn test_interpolate() {
+    use bls12_381::Scalar;
+    let x = |n: u64| FieldElement(Scalar::from(n));
+
+    // `p(t) = t^3 + 2t + 5`
+    let p = |t: u64| x(t * t * t + 2 * t + 5);
+    let evals: Vec<_> = (0..4).map(p).collect();
+    for t in 0..10 {
+        assert_eq!(interpolate(&mut Eval, &evals, &x(t)), p(t));
+    }
+}
@@ -1,44 +1,242 @@
-use std::ops::*;
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::ops::*;
 
 /// A system in which values of type `T` can be represented.
 pub trait SystemRepr<T> {
     /// An abstract representation of a value of a certain type in the system.
     type Abstract: Clone;
 
+    /// The type of failure which the system may encounter while operating on values of type
+    /// `T`, such as an I/O error in a backend which streams its output. Systems which can't fail
+    /// use [`Infallible`].
+    type Error;
+
     /// Constructs an [`Abstract`] wrapper over the given constant value.
     fn constant(&mut self, value: T) -> Self::Abstract;
+
+    /// Reports the first failure the system has encountered, if any. Operations can't fail
+    /// individually, so once a system has failed, it continues to produce placeholder values
+    /// until the failure is reported. The `try_` variants of operations check this after each
+    /// operation.
+    fn status(&self) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 /// An abstract representation of a value of type `T` within a system of type `S`.
 pub type Abstract<S, T> = <S as SystemRepr<T>>::Abstract;
 
+/// The type of failure which a system of type `S` may encounter while operating on values of
+/// type `T`.
+pub type SystemError<S, T> = <S as SystemRepr<T>>::Error;
+
 /// A system in which abstract values of type `T` can be added together. If an implementation of
 /// [`Add`] exists for `T`, this must be consistent with it.
 pub trait SystemAdd<T>: SystemRepr<T> {
     fn add(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, T>;
+
+    /// Like [`SystemAdd::add`], but reports any failure of the system.
+    fn try_add(
+        &mut self,
+        a: &Abstract<Self, T>,
+        b: &Abstract<Self, T>,
+    ) -> Result<Abstract<Self, T>, SystemError<Self, T>> {
+        let res = self.add(a, b);
+        SystemRepr::<T>::status(self)?;
+        Ok(res)
+    }
+}
+
+/// A system in which abstract values of type `T` can be multiplied together. If an implementation
+/// of [`Mul`] exists for `T`, this must be consistent with it.
+pub trait SystemMul<T>: SystemRepr<T> {
+    fn mul(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, T>;
+
+    /// Like [`SystemMul::mul`], but reports any failure of the system.
+    fn try_mul(
+        &mut self,
+        a: &Abstract<Self, T>,
+        b: &Abstract<Self, T>,
+    ) -> Result<Abstract<Self, T>, SystemError<Self, T>> {
+        let res = self.mul(a, b);
+        SystemRepr::<T>::status(self)?;
+        Ok(res)
+    }
+}
+
+/// A system in which abstract values of type `T` have multiplicative inverses.
+pub trait SystemInverse<T>: SystemMul<T> + SystemRepr<bool> {
+    /// Computes the multiplicative inverse of the given value, which must be non-zero. For
+    /// constraint systems, a zero value makes the system unsatisfiable, whereas for evaluation
+    /// systems, this may panic.
+    fn inverse_unchecked(&mut self, value: &Abstract<Self, T>) -> Abstract<Self, T>;
+
+    /// Determines whether the given value is zero.
+    fn is_zero(&mut self, value: &Abstract<Self, T>) -> Abstract<Self, bool>;
+
+    /// Divides `a` by `b`, which must be non-zero. See [`SystemInverse::inverse_unchecked`].
+    fn div_unchecked(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, T> {
+        let inv = self.inverse_unchecked(b);
+        self.mul(a, &inv)
+    }
 }
 
 /// A system in which abstract values of type `T` can be added together, with wrapping.
 pub trait SystemWrappingAdd<T>: SystemRepr<T> {
     fn wrapping_add(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, T>;
+
+    /// Like [`SystemWrappingAdd::wrapping_add`], but reports any failure of the system.
+    fn try_wrapping_add(
+        &mut self,
+        a: &Abstract<Self, T>,
+        b: &Abstract<Self, T>,
+    ) -> Result<Abstract<Self, T>, SystemError<Self, T>> {
+        let res = self.wrapping_add(a, b);
+        SystemRepr::<T>::status(self)?;
+        Ok(res)
+    }
+
+    /// Adds together all of the given values, with wrapping. Systems may override this if there
+    /// is a cheaper alternative to a sequence of [`SystemWrappingAdd::wrapping_add`]s. Panics if
+    /// `terms` is empty.
+    fn sum_many(&mut self, terms: &[Abstract<Self, T>]) -> Abstract<Self, T> {
+        let (first, rest) = terms.split_first().expect("sum of no terms");
+        rest.iter()
+            .fold(first.clone(), |acc, term| self.wrapping_add(&acc, term))
+    }
+}
+
+/// A system in which abstract values of type `T` can be multiplied together, with wrapping.
+pub trait SystemWrappingMul<T>: SystemRepr<T> {
+    fn wrapping_mul(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, T>;
+
+    /// Like [`SystemWrappingMul::wrapping_mul`], but reports any failure of the system.
+    fn try_wrapping_mul(
+        &mut self,
+        a: &Abstract<Self, T>,
+        b: &Abstract<Self, T>,
+    ) -> Result<Abstract<Self, T>, SystemError<Self, T>> {
+        let res = self.wrapping_mul(a, b);
+        SystemRepr::<T>::status(self)?;
+        Ok(res)
+    }
+}
+
+/// A system in which abstract values of type `T` can be multiplied together, with the full
+/// product being shifted right before it is truncated back to `T`. This is the essential operation
+/// of fixed-point multiplication.
+pub trait SystemMulShr<T>: SystemRepr<T> {
+    /// Computes `(a * b) >> shift` without any intermediate overflow, then truncates the result
+    /// to `T`, with wrapping. For signed types, the shift is arithmetic.
+    fn mul_shr(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>, shift: u8)
+        -> Abstract<Self, T>;
+}
+
+/// A system in which abstract values of type `A` can be multiplied together to get a product of
+/// type `B`, which is wide enough that it can not overflow.
+pub trait SystemMulFull<A, B>: SystemRepr<A> + SystemRepr<B> {
+    /// Computes the full product of `a` and `b`.
+    fn mul_full(&mut self, a: &Abstract<Self, A>, b: &Abstract<Self, A>) -> Abstract<Self, B>;
+
+    /// Computes the full value of `a * b + c`. Like [`SystemMulFull::mul_full`], this can not
+    /// overflow.
+    fn mul_add_full(
+        &mut self,
+        a: &Abstract<Self, A>,
+        b: &Abstract<Self, A>,
+        c: &Abstract<Self, A>,
+    ) -> Abstract<Self, B>;
+}
+
+/// A system in which abstract values of type `T` can be added and multiplied modulo a constant,
+/// such as `2^32 - 1` or a prime less than `2^64`. This is needed to verify checksums and linear
+/// congruential generators, and to emulate protocols which use these moduli.
+pub trait SystemModArith<T>: SystemRepr<T> {
+    /// Computes `(a + b) % modulus`, without any intermediate overflow. Panics if `modulus` is
+    /// zero.
+    fn add_mod(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>, modulus: T)
+        -> Abstract<Self, T>;
+
+    /// Computes `(a * b) % modulus`, without any intermediate overflow. Panics if `modulus` is
+    /// zero.
+    fn mul_mod(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>, modulus: T)
+        -> Abstract<Self, T>;
+}
+
+/// A system in which an abstract value of type `T` can be divided by another. This is needed to
+/// parse decimal strings and compute fees in-circuit.
+pub trait SystemDivRem<T>: SystemRepr<T> {
+    /// Computes `a / b` and `a % b`. For constraint systems, this asserts that `b` is nonzero,
+    /// whereas for evaluation systems, this panics if `b` is zero.
+    fn div_rem(
+        &mut self,
+        a: &Abstract<Self, T>,
+        b: &Abstract<Self, T>,
+    ) -> (Abstract<Self, T>, Abstract<Self, T>);
 }
 
 /// A system in which abstract values of type `T` can be bitwise-ANDed together. If an
 /// implementation of [`BitAnd`] exists for `T`, this must be consistent with it.
 pub trait SystemBitAnd<T>: SystemRepr<T> {
     fn and(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, T>;
+
+    /// Like [`SystemBitAnd::and`], but reports any failure of the system.
+    fn try_and(
+        &mut self,
+        a: &Abstract<Self, T>,
+        b: &Abstract<Self, T>,
+    ) -> Result<Abstract<Self, T>, SystemError<Self, T>> {
+        let res = self.and(a, b);
+        SystemRepr::<T>::status(self)?;
+        Ok(res)
+    }
 }
 
 /// A system in which abstract values of type `T` can be bitwise-ORed together. If an
 /// implementation of [`BitOr`] exists for `T`, this must be consistent with it.
 pub trait SystemBitOr<T>: SystemRepr<T> {
     fn or(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, T>;
+
+    /// Like [`SystemBitOr::or`], but reports any failure of the system.
+    fn try_or(
+        &mut self,
+        a: &Abstract<Self, T>,
+        b: &Abstract<Self, T>,
+    ) -> Result<Abstract<Self, T>, SystemError<Self, T>> {
+        let res = self.or(a, b);
+        SystemRepr::<T>::status(self)?;
+        Ok(res)
+    }
 }
 
 /// A system in which abstract values of type `T` can be bitwise-XORed together. If an
 /// implementation of [`BitXor`] exists for `T`, this must be consistent with it.
 pub trait SystemBitXor<T>: SystemRepr<T> {
     fn xor(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, T>;
+
+    /// Like [`SystemBitXor::xor`], but reports any failure of the system.
+    fn try_xor(
+        &mut self,
+        a: &Abstract<Self, T>,
+        b: &Abstract<Self, T>,
+    ) -> Result<Abstract<Self, T>, SystemError<Self, T>> {
+        let res = self.xor(a, b);
+        SystemRepr::<T>::status(self)?;
+        Ok(res)
+    }
+}
+
+/// A system in which abstract values of type `T` can be combined bitwise, taking each bit from `a`
+/// where the corresponding bit of `mask` is set, and from `b` otherwise. This is equivalent to
+/// `(mask & a) | (!mask & b)`, but may be much cheaper.
+pub trait SystemBitSelect<T>: SystemRepr<T> {
+    fn bit_select(
+        &mut self,
+        mask: &Abstract<Self, T>,
+        a: &Abstract<Self, T>,
+        b: &Abstract<Self, T>,
+    ) -> Abstract<Self, T>;
 }
 
 /// A system in which abstract values of type `A` can be bit-shifted by constant values
@@ -60,6 +258,92 @@ pub trait SystemBitRotate<A, B>: SystemRepr<A> {
 /// implementation of [`Not`] exists for `T`, this must be consistent with it.
 pub trait SystemNot<T>: SystemRepr<T> {
     fn not(&mut self, value: &Abstract<Self, T>) -> Abstract<Self, T>;
+
+    /// Like [`SystemNot::not`], but reports any failure of the system.
+    fn try_not(
+        &mut self,
+        value: &Abstract<Self, T>,
+    ) -> Result<Abstract<Self, T>, SystemError<Self, T>> {
+        let res = self.not(value);
+        SystemRepr::<T>::status(self)?;
+        Ok(res)
+    }
+}
+
+/// A system in which abstract values of type `T` can be compared. If an implementation of [`Ord`]
+/// exists for `T`, this must be consistent with it.
+pub trait SystemOrd<T>: SystemRepr<T> + SystemRepr<bool> {
+    /// Determines whether `a` is less than `b`.
+    fn lt(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, bool>;
+
+    /// Determines whether `a` is less than or equal to `b`.
+    fn le(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, bool>;
+}
+
+/// A system in which abstract integers can be assembled from, and split into, their byte
+/// representations.
+pub trait SystemPack: SystemRepr<u8> + SystemRepr<u32> + SystemRepr<u64> {
+    /// Assembles a `u32` from its big-endian byte representation.
+    fn pack_be_u32(&mut self, bytes: &Abstract<Self, [u8; 4]>) -> Abstract<Self, u32>;
+
+    /// Splits a `u32` into its big-endian byte representation.
+    fn unpack_be_u32(&mut self, value: &Abstract<Self, u32>) -> Abstract<Self, [u8; 4]>;
+
+    /// Assembles a `u64` from its big-endian byte representation.
+    fn pack_be_u64(&mut self, bytes: &Abstract<Self, [u8; 8]>) -> Abstract<Self, u64>;
+
+    /// Splits a `u64` into its big-endian byte representation.
+    fn unpack_be_u64(&mut self, value: &Abstract<Self, u64>) -> Abstract<Self, [u8; 8]>;
+
+    /// Assembles a `u32` from its little-endian byte representation.
+    fn pack_le_u32(&mut self, bytes: &Abstract<Self, [u8; 4]>) -> Abstract<Self, u32> {
+        let mut bytes = bytes.clone();
+        bytes.reverse();
+        self.pack_be_u32(&bytes)
+    }
+
+    /// Splits a `u32` into its little-endian byte representation.
+    fn unpack_le_u32(&mut self, value: &Abstract<Self, u32>) -> Abstract<Self, [u8; 4]> {
+        let mut bytes = self.unpack_be_u32(value);
+        bytes.reverse();
+        bytes
+    }
+
+    /// Assembles a `u64` from its little-endian byte representation.
+    fn pack_le_u64(&mut self, bytes: &Abstract<Self, [u8; 8]>) -> Abstract<Self, u64> {
+        let mut bytes = bytes.clone();
+        bytes.reverse();
+        self.pack_be_u64(&bytes)
+    }
+
+    /// Splits a `u64` into its little-endian byte representation.
+    fn unpack_le_u64(&mut self, value: &Abstract<Self, u64>) -> Abstract<Self, [u8; 8]> {
+        let mut bytes = self.unpack_be_u64(value);
+        bytes.reverse();
+        bytes
+    }
+}
+
+/// A system in which abstract integers can be converted to and from their binary
+/// representations. Bits are ordered from least significant to most significant.
+pub trait SystemBits: SystemRepr<bool> + SystemRepr<u8> + SystemRepr<u32> + SystemRepr<u64> {
+    /// Splits a `u8` into its bits.
+    fn bits_of_u8(&mut self, value: &Abstract<Self, u8>) -> Abstract<Self, [bool; 8]>;
+
+    /// Assembles a `u8` from its bits.
+    fn u8_of_bits(&mut self, bits: &Abstract<Self, [bool; 8]>) -> Abstract<Self, u8>;
+
+    /// Splits a `u32` into its bits.
+    fn bits_of_u32(&mut self, value: &Abstract<Self, u32>) -> Abstract<Self, [bool; 32]>;
+
+    /// Assembles a `u32` from its bits.
+    fn u32_of_bits(&mut self, bits: &Abstract<Self, [bool; 32]>) -> Abstract<Self, u32>;
+
+    /// Splits a `u64` into its bits.
+    fn bits_of_u64(&mut self, value: &Abstract<Self, u64>) -> Abstract<Self, [bool; 64]>;
+
+    /// Assembles a `u64` from its bits.
+    fn u64_of_bits(&mut self, bits: &Abstract<Self, [bool; 64]>) -> Abstract<Self, u64>;
 }
 
 /// A system in which abstract boolean values can be "asserted".
@@ -67,6 +351,32 @@ pub trait SystemAssert: SystemRepr<bool> {
     /// Asserts that the given value is true. For constraint systems, this imposes a constraint,
     /// whereas for evaluation systems, this may panic if the assertion is false.
     fn assert(&mut self, value: &Abstract<Self, bool>);
+
+    /// Like [`SystemAssert::assert`], but reports any failure of the system. This does not report
+    /// the assertion itself being false.
+    fn try_assert(&mut self, value: &Abstract<Self, bool>) -> Result<(), SystemError<Self, bool>> {
+        self.assert(value);
+        SystemRepr::<bool>::status(self)
+    }
+
+    /// Asserts that all of the given values are true. Constraint systems may enforce these with a
+    /// single constraint, whereas evaluation systems may report every false value, rather than
+    /// only the first.
+    fn assert_all(&mut self, values: &[Abstract<Self, bool>]) {
+        for value in values {
+            self.assert(value);
+        }
+    }
+
+    /// Like [`SystemAssert::assert_all`], but reports any failure of the system. This does not
+    /// report the assertions themselves being false.
+    fn try_assert_all(
+        &mut self,
+        values: &[Abstract<Self, bool>],
+    ) -> Result<(), SystemError<Self, bool>> {
+        self.assert_all(values);
+        SystemRepr::<bool>::status(self)
+    }
 }
 
 /// A system in which abstract boolean values can be "asserted".
@@ -74,114 +384,666 @@ pub trait SystemAssertEq<T>: SystemRepr<T> {
     /// Asserts that the given values are equal. For constraint systems, this imposes a constraint,
     /// whereas for evaluation systems, this may panic if the assertion is false.
     fn assert_eq(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>);
+
+    /// Like [`SystemAssertEq::assert_eq`], but reports any failure of the system. This does not
+    /// report the assertion itself being false.
+    fn try_assert_eq(
+        &mut self,
+        a: &Abstract<Self, T>,
+        b: &Abstract<Self, T>,
+    ) -> Result<(), SystemError<Self, T>> {
+        self.assert_eq(a, b);
+        SystemRepr::<T>::status(self)
+    }
+}
+
+/// A system in which boolean values can be asserted conditionally.
+pub trait SystemAssertIf: SystemAssert {
+    /// Asserts that `claim` is true if `cond` is true. This is typically cheaper than asserting
+    /// `!cond | claim`.
+    fn assert_if(&mut self, cond: &Abstract<Self, bool>, claim: &Abstract<Self, bool>);
+
+    /// Like [`SystemAssertIf::assert_if`], but reports any failure of the system. This does not
+    /// report the assertion itself being false.
+    fn try_assert_if(
+        &mut self,
+        cond: &Abstract<Self, bool>,
+        claim: &Abstract<Self, bool>,
+    ) -> Result<(), SystemError<Self, bool>> {
+        self.assert_if(cond, claim);
+        SystemRepr::<bool>::status(self)
+    }
+}
+
+/// A system in which one of two abstract values of type `T` can be chosen using an abstract
+/// condition.
+pub trait SystemSelect<T>: SystemRepr<T> + SystemRepr<bool> {
+    /// Returns `a` if `cond` is true, or `b` otherwise.
+    fn select(
+        &mut self,
+        cond: &Abstract<Self, bool>,
+        a: &Abstract<Self, T>,
+        b: &Abstract<Self, T>,
+    ) -> Abstract<Self, T>;
+
+    /// Like [`SystemSelect::select`], but reports any failure of the system.
+    fn try_select(
+        &mut self,
+        cond: &Abstract<Self, bool>,
+        a: &Abstract<Self, T>,
+        b: &Abstract<Self, T>,
+    ) -> Result<Abstract<Self, T>, SystemError<Self, T>> {
+        let res = self.select(cond, a, b);
+        SystemRepr::<T>::status(self)?;
+        Ok(res)
+    }
+
+    /// Synthesizes both branches of a conditional and selects between their outputs. Since both
+    /// branches are always synthesized, they shouldn't make assertions that only hold when their
+    /// branch is taken. Use [`SystemAssertIf::assert_if`] for those instead.
+    fn cond_select(
+        &mut self,
+        cond: &Abstract<Self, bool>,
+        then_branch: impl FnOnce(&mut Self) -> Abstract<Self, T>,
+        else_branch: impl FnOnce(&mut Self) -> Abstract<Self, T>,
+    ) -> Abstract<Self, T>
+    where
+        Self: Sized,
+    {
+        let a = then_branch(self);
+        let b = else_branch(self);
+        self.select(cond, &a, &b)
+    }
+
+    /// Returns the option at the given index, which is given as a little-endian string of bits.
+    /// By default, this is lowered to a balanced tree of `options.len() - 1` selections. For
+    /// indices beyond the last option, the result is unspecified.
+    fn select_from(
+        &mut self,
+        index: &[Abstract<Self, bool>],
+        options: &[Abstract<Self, T>],
+    ) -> Abstract<Self, T> {
+        assert!(!options.is_empty(), "there must be at least one option");
+        let mut layer = options.to_vec();
+        for bit in index {
+            if layer.len() == 1 {
+                break;
+            }
+            layer = layer
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => self.select(bit, b, a),
+                    [a] => a.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+        layer.swap_remove(0)
+    }
+
+    /// Like [`SystemSelect::select_from`], but selects between options consisting of many
+    /// values each, all of the same length, so that the index is shared between every value.
+    fn select_many_from(
+        &mut self,
+        index: &[Abstract<Self, bool>],
+        options: &[&[Abstract<Self, T>]],
+    ) -> Vec<Abstract<Self, T>> {
+        assert!(!options.is_empty(), "there must be at least one option");
+        let len = options[0].len();
+        assert!(
+            options.iter().all(|option| option.len() == len),
+            "options must have the same length"
+        );
+        let mut layer: Vec<Vec<_>> = options.iter().map(|option| option.to_vec()).collect();
+        for bit in index {
+            if layer.len() == 1 {
+                break;
+            }
+            layer = layer
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => (0..len).map(|i| self.select(bit, &b[i], &a[i])).collect(),
+                    [a] => a.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+        layer.swap_remove(0)
+    }
 }
 
 impl<S: SystemRepr<T> + ?Sized, T, const N: usize> SystemRepr<[T; N]> for S {
     type Abstract = [Abstract<S, T>; N];
+    type Error = SystemError<S, T>;
     fn constant(&mut self, value: [T; N]) -> Self::Abstract {
         value.map(|v| self.constant(v))
     }
-}
 
-/// A "system" that directly evaluates values.
-pub struct Eval;
+    fn status(&self) -> Result<(), Self::Error> {
+        SystemRepr::<T>::status(self)
+    }
+}
 
-impl SystemRepr<bool> for Eval {
-    type Abstract = bool;
-    fn constant(&mut self, value: bool) -> bool {
-        value
+impl<S: SystemSelect<T> + ?Sized, T, const N: usize> SystemSelect<[T; N]> for S {
+    fn select(
+        &mut self,
+        cond: &Abstract<Self, bool>,
+        a: &Abstract<Self, [T; N]>,
+        b: &Abstract<Self, [T; N]>,
+    ) -> Abstract<Self, [T; N]> {
+        array_init::array_init(|i| self.select(cond, &a[i], &b[i]))
     }
 }
 
-impl SystemRepr<u32> for Eval {
-    type Abstract = u32;
-    fn constant(&mut self, value: u32) -> u32 {
-        value
+impl<S: SystemAdd<T> + ?Sized, T, const N: usize> SystemAdd<[T; N]> for S {
+    fn add(
+        &mut self,
+        a: &Abstract<Self, [T; N]>,
+        b: &Abstract<Self, [T; N]>,
+    ) -> Abstract<Self, [T; N]> {
+        array_init::array_init(|i| self.add(&a[i], &b[i]))
     }
 }
 
-impl<T> SystemAdd<T> for Eval
-where
-    for<'a, 'b> &'a T: Add<&'b T, Output = T>,
-    Eval: SystemRepr<T, Abstract = T>,
-{
-    fn add(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, T> {
-        a + b
+impl<S: SystemMul<T> + ?Sized, T, const N: usize> SystemMul<[T; N]> for S {
+    fn mul(
+        &mut self,
+        a: &Abstract<Self, [T; N]>,
+        b: &Abstract<Self, [T; N]>,
+    ) -> Abstract<Self, [T; N]> {
+        array_init::array_init(|i| self.mul(&a[i], &b[i]))
     }
 }
 
-impl SystemWrappingAdd<u32> for Eval {
+impl<S: SystemWrappingAdd<T> + ?Sized, T, const N: usize> SystemWrappingAdd<[T; N]> for S {
     fn wrapping_add(
         &mut self,
-        a: &Abstract<Self, u32>,
-        b: &Abstract<Self, u32>,
-    ) -> Abstract<Self, u32> {
-        a.wrapping_add(*b)
+        a: &Abstract<Self, [T; N]>,
+        b: &Abstract<Self, [T; N]>,
+    ) -> Abstract<Self, [T; N]> {
+        array_init::array_init(|i| self.wrapping_add(&a[i], &b[i]))
     }
 }
 
-impl<T> SystemBitAnd<T> for Eval
-where
-    for<'a, 'b> &'a T: BitAnd<&'b T, Output = T>,
-    Eval: SystemRepr<T, Abstract = T>,
-{
-    fn and(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, T> {
-        a & b
+impl<S: SystemWrappingMul<T> + ?Sized, T, const N: usize> SystemWrappingMul<[T; N]> for S {
+    fn wrapping_mul(
+        &mut self,
+        a: &Abstract<Self, [T; N]>,
+        b: &Abstract<Self, [T; N]>,
+    ) -> Abstract<Self, [T; N]> {
+        array_init::array_init(|i| self.wrapping_mul(&a[i], &b[i]))
     }
 }
 
-impl<T> SystemBitOr<T> for Eval
-where
-    for<'a, 'b> &'a T: BitOr<&'b T, Output = T>,
-    Eval: SystemRepr<T, Abstract = T>,
+impl<S: SystemBitAnd<T> + ?Sized, T, const N: usize> SystemBitAnd<[T; N]> for S {
+    fn and(
+        &mut self,
+        a: &Abstract<Self, [T; N]>,
+        b: &Abstract<Self, [T; N]>,
+    ) -> Abstract<Self, [T; N]> {
+        array_init::array_init(|i| self.and(&a[i], &b[i]))
+    }
+}
+
+impl<S: SystemBitOr<T> + ?Sized, T, const N: usize> SystemBitOr<[T; N]> for S {
+    fn or(
+        &mut self,
+        a: &Abstract<Self, [T; N]>,
+        b: &Abstract<Self, [T; N]>,
+    ) -> Abstract<Self, [T; N]> {
+        array_init::array_init(|i| self.or(&a[i], &b[i]))
+    }
+}
+
+impl<S: SystemBitXor<T> + ?Sized, T, const N: usize> SystemBitXor<[T; N]> for S {
+    fn xor(
+        &mut self,
+        a: &Abstract<Self, [T; N]>,
+        b: &Abstract<Self, [T; N]>,
+    ) -> Abstract<Self, [T; N]> {
+        array_init::array_init(|i| self.xor(&a[i], &b[i]))
+    }
+}
+
+impl<S: SystemBitSelect<T> + ?Sized, T, const N: usize> SystemBitSelect<[T; N]> for S {
+    fn bit_select(
+        &mut self,
+        mask: &Abstract<Self, [T; N]>,
+        a: &Abstract<Self, [T; N]>,
+        b: &Abstract<Self, [T; N]>,
+    ) -> Abstract<Self, [T; N]> {
+        array_init::array_init(|i| self.bit_select(&mask[i], &a[i], &b[i]))
+    }
+}
+
+impl<S: SystemNot<T> + ?Sized, T, const N: usize> SystemNot<[T; N]> for S {
+    fn not(&mut self, value: &Abstract<Self, [T; N]>) -> Abstract<Self, [T; N]> {
+        array_init::array_init(|i| self.not(&value[i]))
+    }
+}
+
+impl<S: SystemBitShift<A, B> + ?Sized, A, B: Copy, const N: usize> SystemBitShift<[A; N], B> for S {
+    fn shl(&mut self, a: &Abstract<Self, [A; N]>, b: B) -> Abstract<Self, [A; N]> {
+        array_init::array_init(|i| self.shl(&a[i], b))
+    }
+
+    fn shr(&mut self, a: &Abstract<Self, [A; N]>, b: B) -> Abstract<Self, [A; N]> {
+        array_init::array_init(|i| self.shr(&a[i], b))
+    }
+}
+
+impl<S: SystemBitRotate<A, B> + ?Sized, A, B: Copy, const N: usize> SystemBitRotate<[A; N], B>
+    for S
 {
-    fn or(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, T> {
-        a | b
+    fn rotl(&mut self, a: &Abstract<Self, [A; N]>, b: B) -> Abstract<Self, [A; N]> {
+        array_init::array_init(|i| self.rotl(&a[i], b))
+    }
+
+    fn rotr(&mut self, a: &Abstract<Self, [A; N]>, b: B) -> Abstract<Self, [A; N]> {
+        array_init::array_init(|i| self.rotr(&a[i], b))
     }
 }
 
-impl<T> SystemBitXor<T> for Eval
+impl<S: SystemAssertEq<T> + ?Sized, T, const N: usize> SystemAssertEq<[T; N]> for S {
+    fn assert_eq(&mut self, a: &Abstract<Self, [T; N]>, b: &Abstract<Self, [T; N]>) {
+        for (a, b) in a.iter().zip(b.iter()) {
+            self.assert_eq(a, b);
+        }
+    }
+}
+
+/// A system which can introduce arbitrary values of type `T`. For evaluation systems, these are
+/// random, which allows gadgets to be tested on many inputs. For constraint systems, these are
+/// fresh witness variables, constrained only as needed to be valid representations of `T`.
+pub trait SystemRand<T>: SystemRepr<T> {
+    /// Introduces an arbitrary value.
+    fn rand(&mut self) -> Abstract<Self, T>;
+}
+
+/// A system in which the concrete value of an abstract value of type `T` can be recovered. This
+/// is typically only possible for evaluation systems.
+pub trait SystemRead<T>: SystemRepr<T> {
+    /// Gets the concrete value of the given abstract value.
+    fn read_value(&self, value: &Abstract<Self, T>) -> T;
+}
+
+impl<S: SystemRead<T> + ?Sized, T, const N: usize> SystemRead<[T; N]> for S {
+    fn read_value(&self, value: &Abstract<Self, [T; N]>) -> [T; N] {
+        array_init::array_init(|i| self.read_value(&value[i]))
+    }
+}
+
+/// A system in which abstract values of type `T` can be probed, so that intermediate values of
+/// a gadget can be inspected without changing the circuit it produces. Evaluation systems log
+/// the concrete value, and graph systems record the nodes holding it. For other systems, probing
+/// has no effect, which is the default.
+pub trait SystemDebug<T>: SystemRepr<T> {
+    /// Probes the given value, identifying it by `label`.
+    fn probe(&mut self, label: &str, value: &Abstract<Self, T>) {
+        let _ = (label, value);
+    }
+}
+
+/// Advertises the relative costs of operations in a system, so that gadgets can choose between
+/// equivalent constructions without the user having to pick one. Every capability defaults to
+/// the most conservative value, so implementing this trait with an empty body is always valid.
+pub trait SystemCapabilities {
+    /// Whether the system can look up values in a table at a cost independent of the size of
+    /// the table.
+    const HAS_LOOKUP: bool = false;
+
+    /// The number of bits which can be stored in a native field element of the system without
+    /// overflow, so that sums of values with fewer bits than this are free. This is zero for
+    /// systems which don't work over a field.
+    const NATIVE_FIELD_BITS: u32 = 0;
+
+    /// Whether XOR and NOT on booleans are free, so that the cost of a construction is
+    /// determined only by its non-linear operations.
+    const FREE_XOR: bool = false;
+}
+
+/// A "system" that directly evaluates values.
+pub struct Eval;
+
+/// Gets the option selected by [`SystemSelect::select_from`] for a concrete index, so that the
+/// options can be indexed directly rather than through a tree of selections. Indices beyond the
+/// last option select the last option.
+fn eval_index(index: &[bool], len: usize) -> usize {
+    assert!(len > 0, "there must be at least one option");
+    let bits = len.next_power_of_two().trailing_zeros() as usize;
+    let in_range = index[bits.min(index.len())..].iter().all(|bit| !bit);
+    let index = index
+        .iter()
+        .take(bits)
+        .enumerate()
+        .fold(0, |acc, (i, bit)| acc | (usize::from(*bit) << i));
+    if in_range {
+        index.min(len - 1)
+    } else {
+        len - 1
+    }
+}
+
+/// Implements [`SystemSelect`] on [`Eval`] for a type whose values are their own abstract
+/// representation.
+macro_rules! impl_eval_select {
+    ($t:ty) => {
+        impl SystemSelect<$t> for Eval {
+            #[track_caller]
+            fn select(&mut self, cond: &bool, a: &$t, b: &$t) -> $t {
+                #[cfg(feature = "std")]
+                crate::coverage::record(crate::CoverageKind::Select, usize::from(!cond));
+                if *cond {
+                    *a
+                } else {
+                    *b
+                }
+            }
+
+            #[track_caller]
+            fn select_from(&mut self, index: &[bool], options: &[$t]) -> $t {
+                let index = eval_index(index, options.len());
+                #[cfg(feature = "std")]
+                crate::coverage::record(crate::CoverageKind::SelectFrom(options.len()), index);
+                options[index]
+            }
+        }
+    };
+}
+
+impl_eval_select!(bool);
+impl_eval_select!(u8);
+impl_eval_select!(u32);
+impl_eval_select!(u64);
+impl_eval_select!(i8);
+impl_eval_select!(i16);
+impl_eval_select!(i32);
+impl_eval_select!(i64);
+
+/// Implements the bitwise operations and equality assertions of [`Eval`] for a type whose values
+/// are their own abstract representation.
+macro_rules! impl_eval_bitwise {
+    ($t:ty) => {
+        impl SystemBitAnd<$t> for Eval {
+            fn and(&mut self, a: &$t, b: &$t) -> $t {
+                a & b
+            }
+        }
+
+        impl SystemBitOr<$t> for Eval {
+            fn or(&mut self, a: &$t, b: &$t) -> $t {
+                a | b
+            }
+        }
+
+        impl SystemBitXor<$t> for Eval {
+            fn xor(&mut self, a: &$t, b: &$t) -> $t {
+                a ^ b
+            }
+        }
+
+        impl SystemNot<$t> for Eval {
+            fn not(&mut self, value: &$t) -> $t {
+                !value
+            }
+        }
+
+        impl SystemBitSelect<$t> for Eval {
+            fn bit_select(&mut self, mask: &$t, a: &$t, b: &$t) -> $t {
+                (mask & a) | (!mask & b)
+            }
+        }
+
+        impl SystemAssertEq<$t> for Eval {
+            fn assert_eq(&mut self, a: &$t, b: &$t) {
+                assert!(a == b)
+            }
+        }
+    };
+}
+
+impl_eval_bitwise!(bool);
+impl_eval_bitwise!(u8);
+impl_eval_bitwise!(u32);
+impl_eval_bitwise!(u64);
+impl_eval_bitwise!(i8);
+impl_eval_bitwise!(i16);
+impl_eval_bitwise!(i32);
+impl_eval_bitwise!(i64);
+
+impl SystemCapabilities for Eval {}
+
+/// Logs probed values to standard error. Without the `std` feature, probing has no effect.
+impl<T: core::fmt::Debug> SystemDebug<T> for Eval
 where
-    for<'a, 'b> &'a T: BitXor<&'b T, Output = T>,
     Eval: SystemRepr<T, Abstract = T>,
 {
-    fn xor(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, T> {
-        a ^ b
+    fn probe(&mut self, label: &str, value: &T) {
+        #[cfg(feature = "std")]
+        std::eprintln!("{}: {:?}", label, value);
+        #[cfg(not(feature = "std"))]
+        let _ = (label, value);
     }
 }
 
-impl<A, B> SystemBitShift<A, B> for Eval
-where
-    for<'a> &'a A: Shl<B, Output = A>,
-    for<'a> &'a A: Shr<B, Output = A>,
-    Eval: SystemRepr<A, Abstract = A>,
-{
-    fn shl(&mut self, a: &Abstract<Self, A>, b: B) -> Abstract<Self, A> {
-        a << b
+impl SystemRepr<bool> for Eval {
+    type Abstract = bool;
+    type Error = Infallible;
+    fn constant(&mut self, value: bool) -> bool {
+        value
     }
+}
 
-    fn shr(&mut self, a: &Abstract<Self, A>, b: B) -> Abstract<Self, A> {
-        a >> b
+impl SystemRead<bool> for Eval {
+    fn read_value(&self, value: &bool) -> bool {
+        *value
     }
 }
 
-impl SystemBitRotate<u32, u8> for Eval {
-    fn rotl(&mut self, a: &Abstract<Self, u32>, b: u8) -> Abstract<Self, u32> {
-        a.rotate_left(b as u32)
+/// Implements the non-generic operations of [`Eval`] for a primitive integer type.
+macro_rules! impl_eval_int {
+    ($t:ty, $wide:ty) => {
+        impl SystemRepr<$t> for Eval {
+            type Abstract = $t;
+            type Error = Infallible;
+            fn constant(&mut self, value: $t) -> $t {
+                value
+            }
+        }
+
+        impl SystemRead<$t> for Eval {
+            fn read_value(&self, value: &$t) -> $t {
+                *value
+            }
+        }
+
+        impl SystemAdd<$t> for Eval {
+            fn add(&mut self, a: &$t, b: &$t) -> $t {
+                a + b
+            }
+        }
+
+        impl SystemMul<$t> for Eval {
+            fn mul(&mut self, a: &$t, b: &$t) -> $t {
+                a * b
+            }
+        }
+
+        impl<B> SystemBitShift<$t, B> for Eval
+        where
+            $t: Shl<B, Output = $t> + Shr<B, Output = $t>,
+        {
+            fn shl(&mut self, a: &$t, b: B) -> $t {
+                *a << b
+            }
+
+            fn shr(&mut self, a: &$t, b: B) -> $t {
+                *a >> b
+            }
+        }
+
+        impl SystemWrappingAdd<$t> for Eval {
+            fn wrapping_add(
+                &mut self,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+            ) -> Abstract<Self, $t> {
+                <$t>::wrapping_add(*a, *b)
+            }
+        }
+
+        impl SystemWrappingMul<$t> for Eval {
+            fn wrapping_mul(
+                &mut self,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+            ) -> Abstract<Self, $t> {
+                <$t>::wrapping_mul(*a, *b)
+            }
+        }
+
+        impl SystemMulShr<$t> for Eval {
+            fn mul_shr(
+                &mut self,
+                a: &Abstract<Self, $t>,
+                b: &Abstract<Self, $t>,
+                shift: u8,
+            ) -> Abstract<Self, $t> {
+                let prod = *a as $wide * *b as $wide;
+                // Shifting beyond the product leaves only its sign, or zero if unsigned
+                let fill = if <$wide>::MIN == 0 { 0 } else { prod >> (<$wide>::BITS - 1) };
+                prod.checked_shr(shift.into()).unwrap_or(fill) as $t
+            }
+        }
+
+        impl SystemBitRotate<$t, u8> for Eval {
+            fn rotl(&mut self, a: &Abstract<Self, $t>, b: u8) -> Abstract<Self, $t> {
+                a.rotate_left(b as u32)
+            }
+
+            fn rotr(&mut self, a: &Abstract<Self, $t>, b: u8) -> Abstract<Self, $t> {
+                a.rotate_right(b as u32)
+            }
+        }
+    };
+}
+
+impl_eval_int!(u8, u128);
+impl_eval_int!(u32, u128);
+impl_eval_int!(u64, u128);
+impl_eval_int!(i8, i128);
+impl_eval_int!(i16, i128);
+impl_eval_int!(i32, i128);
+impl_eval_int!(i64, i128);
+
+/// Implements [`SystemModArith`] and [`SystemDivRem`] on [`Eval`] for an unsigned integer type.
+macro_rules! impl_eval_mod {
+    ($t:ty) => {
+        impl SystemModArith<$t> for Eval {
+            fn add_mod(&mut self, a: &$t, b: &$t, modulus: $t) -> $t {
+                ((*a as u128 + *b as u128) % modulus as u128) as $t
+            }
+
+            fn mul_mod(&mut self, a: &$t, b: &$t, modulus: $t) -> $t {
+                ((*a as u128 * *b as u128) % modulus as u128) as $t
+            }
+        }
+
+        impl SystemDivRem<$t> for Eval {
+            fn div_rem(&mut self, a: &$t, b: &$t) -> ($t, $t) {
+                (*a / *b, *a % *b)
+            }
+        }
+    };
+}
+
+impl_eval_mod!(u8);
+impl_eval_mod!(u32);
+impl_eval_mod!(u64);
+
+impl SystemMulFull<u32, u64> for Eval {
+    fn mul_full(&mut self, a: &Abstract<Self, u32>, b: &Abstract<Self, u32>) -> Abstract<Self, u64> {
+        u64::from(*a) * u64::from(*b)
     }
 
-    fn rotr(&mut self, a: &Abstract<Self, u32>, b: u8) -> Abstract<Self, u32> {
-        a.rotate_right(b as u32)
+    fn mul_add_full(
+        &mut self,
+        a: &Abstract<Self, u32>,
+        b: &Abstract<Self, u32>,
+        c: &Abstract<Self, u32>,
+    ) -> Abstract<Self, u64> {
+        u64::from(*a) * u64::from(*b) + u64::from(*c)
     }
 }
 
-impl<T> SystemNot<T> for Eval
+impl<T: Ord> SystemOrd<T> for Eval
 where
-    for<'a> &'a T: Not<Output = T>,
     Eval: SystemRepr<T, Abstract = T>,
 {
-    fn not(&mut self, value: &Abstract<Self, T>) -> Abstract<Self, T> {
-        !value
+    fn lt(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, bool> {
+        a < b
+    }
+
+    fn le(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) -> Abstract<Self, bool> {
+        a <= b
+    }
+}
+
+impl SystemPack for Eval {
+    fn pack_be_u32(&mut self, bytes: &Abstract<Self, [u8; 4]>) -> Abstract<Self, u32> {
+        u32::from_be_bytes(*bytes)
+    }
+
+    fn unpack_be_u32(&mut self, value: &Abstract<Self, u32>) -> Abstract<Self, [u8; 4]> {
+        value.to_be_bytes()
+    }
+
+    fn pack_be_u64(&mut self, bytes: &Abstract<Self, [u8; 8]>) -> Abstract<Self, u64> {
+        u64::from_be_bytes(*bytes)
+    }
+
+    fn unpack_be_u64(&mut self, value: &Abstract<Self, u64>) -> Abstract<Self, [u8; 8]> {
+        value.to_be_bytes()
+    }
+}
+
+/// Gets the `N` least significant bits of the given value, least significant first.
+pub(crate) fn bits_of<const N: usize>(value: u64) -> [bool; N] {
+    assert!(N <= 64);
+    array_init::array_init(|i| (value >> i) & 1 == 1)
+}
+
+/// Assembles a value from its `N` least significant bits, least significant first.
+pub(crate) fn of_bits<const N: usize>(bits: &[bool; N]) -> u64 {
+    assert!(N <= 64);
+    bits.iter()
+        .enumerate()
+        .fold(0, |acc, (i, bit)| acc | (u64::from(*bit) << i))
+}
+
+impl SystemBits for Eval {
+    fn bits_of_u8(&mut self, value: &Abstract<Self, u8>) -> Abstract<Self, [bool; 8]> {
+        bits_of(u64::from(*value))
+    }
+
+    fn u8_of_bits(&mut self, bits: &Abstract<Self, [bool; 8]>) -> Abstract<Self, u8> {
+        of_bits(bits) as u8
+    }
+
+    fn bits_of_u32(&mut self, value: &Abstract<Self, u32>) -> Abstract<Self, [bool; 32]> {
+        bits_of(u64::from(*value))
+    }
+
+    fn u32_of_bits(&mut self, bits: &Abstract<Self, [bool; 32]>) -> Abstract<Self, u32> {
+        of_bits(bits) as u32
+    }
+
+    fn bits_of_u64(&mut self, value: &Abstract<Self, u64>) -> Abstract<Self, [bool; 64]> {
+        bits_of(*value)
+    }
+
+    fn u64_of_bits(&mut self, bits: &Abstract<Self, [bool; 64]>) -> Abstract<Self, u64> {
+        of_bits(bits)
     }
 }
 
@@ -189,14 +1051,134 @@ impl SystemAssert for Eval {
     fn assert(&mut self, value: &Abstract<Self, bool>) {
         assert!(value)
     }
+
+    fn assert_all(&mut self, values: &[Abstract<Self, bool>]) {
+        let failed: Vec<usize> = (values.iter().enumerate())
+            .filter(|(_, value)| !**value)
+            .map(|(i, _)| i)
+            .collect();
+        assert!(failed.is_empty(), "assertions {:?} failed", failed)
+    }
 }
 
-impl<T> SystemAssertEq<T> for Eval
-where
-    for<'a> &'a T: Eq,
-    Eval: SystemRepr<T, Abstract = T>
-{
-    fn assert_eq(&mut self, a: &Abstract<Self, T>, b: &Abstract<Self, T>) {
-        assert!(a == b)
+impl SystemAssertIf for Eval {
+    #[track_caller]
+    fn assert_if(&mut self, cond: &Abstract<Self, bool>, claim: &Abstract<Self, bool>) {
+        #[cfg(feature = "std")]
+        crate::coverage::record(crate::CoverageKind::AssertIf, usize::from(!cond));
+        assert!(!cond || *claim)
+    }
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    /// The state of the random number generator used by [`Eval`].
+    static EVAL_RNG: std::cell::Cell<u64> = const { std::cell::Cell::new(0) };
+}
+
+#[cfg(feature = "std")]
+impl Eval {
+    /// Seeds the random number generator used by [`SystemRand`] for [`Eval`] on the current
+    /// thread, so that the sequence of values it produces is reproducible. Each thread starts
+    /// with a seed of 0.
+    pub fn seed_rng(seed: u64) {
+        EVAL_RNG.with(|state| state.set(seed))
+    }
+
+    /// Gets the next 64 random bits, using the SplitMix64 generator.
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        EVAL_RNG.with(|state| {
+            let next = state.get().wrapping_add(0x9e3779b97f4a7c15);
+            state.set(next);
+            let z = (next ^ (next >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            let z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        })
+    }
+}
+
+/// Implements [`SystemRand`] on [`Eval`] for a primitive integer type.
+macro_rules! impl_eval_rand {
+    ($t:ty) => {
+        #[cfg(feature = "std")]
+        impl SystemRand<$t> for Eval {
+            fn rand(&mut self) -> $t {
+                self.next_u64() as $t
+            }
+        }
+    };
+}
+
+impl_eval_rand!(u8);
+impl_eval_rand!(u32);
+impl_eval_rand!(u64);
+impl_eval_rand!(i8);
+impl_eval_rand!(i16);
+impl_eval_rand!(i32);
+impl_eval_rand!(i64);
+
+#[cfg(feature = "std")]
+impl SystemRand<bool> for Eval {
+    fn rand(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+}
+
+impl<S: SystemRand<T> + ?Sized, T, const N: usize> SystemRand<[T; N]> for S {
+    fn rand(&mut self) -> Abstract<Self, [T; N]> {
+        array_init::array_init(|_| self.rand())
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_eval_rand() {
+    Eval::seed_rng(42);
+    let a = SystemRand::<[u64; 4]>::rand(&mut Eval);
+    let b = SystemRand::<u32>::rand(&mut Eval);
+    Eval::seed_rng(42);
+    assert_eq!(SystemRand::<[u64; 4]>::rand(&mut Eval), a);
+    assert_eq!(SystemRand::<u32>::rand(&mut Eval), b);
+    assert!(a.windows(2).all(|w| w[0] != w[1]));
+}
+
+#[test]
+fn test_array_ops() {
+    let a = [0x12345678u32, 0xdeadbeef];
+    let b = [0x0f0f0f0fu32, 0xffffffff];
+    assert_eq!(SystemBitXor::<[u32; 2]>::xor(&mut Eval, &a, &b), [a[0] ^ b[0], a[1] ^ b[1]]);
+    assert_eq!(
+        SystemWrappingAdd::<[u32; 2]>::wrapping_add(&mut Eval, &a, &b),
+        [a[0].wrapping_add(b[0]), a[1].wrapping_add(b[1])]
+    );
+    assert_eq!(
+        SystemBitRotate::<[u32; 2], u8>::rotl(&mut Eval, &a, 8),
+        [a[0].rotate_left(8), a[1].rotate_left(8)]
+    );
+    assert_eq!(SystemNot::<[bool; 2]>::not(&mut Eval, &[true, false]), [false, true]);
+    SystemAssertEq::<[[u8; 2]; 2]>::assert_eq(&mut Eval, &[[1, 2], [3, 4]], &[[1, 2], [3, 4]]);
+}
+
+#[cfg(feature = "binary")]
+#[test]
+fn test_select_from() {
+    use alloc::vec;
+
+    // The direct lowering for `Eval` should agree with the tree lowering used by other systems
+    let mut sys = crate::BinaryEmulate::new(Eval);
+    for len in 1..10 {
+        let options: Vec<u32> = (0..len).map(|i| i * 11).collect();
+        let abstract_options: Vec<_> = options.iter().map(|o| sys.constant(*o)).collect();
+        let rows: Vec<Vec<_>> = abstract_options.iter().map(|o| vec![*o; 2]).collect();
+        let rows: Vec<&[_]> = rows.iter().map(|row| row.as_slice()).collect();
+        for index in 0..len {
+            let bits = bits_of::<4>(u64::from(index));
+            let expected = SystemSelect::<u32>::select_from(&mut Eval, &bits, &options);
+            assert_eq!(expected, index * 11);
+            let res = SystemSelect::<u32>::select_from(&mut sys, &bits, &abstract_options);
+            assert_eq!(SystemRead::<u32>::read_value(&sys, &res), expected);
+            let res = SystemSelect::<u32>::select_many_from(&mut sys, &bits, &rows);
+            assert_eq!(res, vec![sys.constant(expected); 2]);
+        }
     }
 }
@@ -0,0 +1,248 @@
+//! Fiat-Shamir transcripts, which derive the challenges of an interactive protocol by hashing
+//! the messages sent so far. These are the basis for in-circuit verifiers of protocols like
+//! sumcheck and folding schemes.
+//!
+//! Since the transcripts are gadgets, the prover computes the same challenges by running them in
+//! [`Eval`], so the two sides can't disagree about the encoding of messages.
+//!
+//! Every [`Transcript`] encodes its history injectively: each message is prefixed with its
+//! length, so that distinct sequences of messages and challenges never lead to the same
+//! challenge.
+use crate::crypto::hash::{PoseidonParams, SystemPoseidon, SystemSha256Bytes};
+use crate::field::FieldElement;
+use crate::*;
+use alloc::vec::Vec;
+use ff::PrimeField;
+
+/// A Fiat-Shamir transcript in the system `S`, which absorbs messages and derives challenges
+/// from every message absorbed so far.
+pub trait Transcript<S: ?Sized> {
+    /// The values which messages are made of.
+    type Element;
+
+    /// The type of challenges derived from the transcript.
+    type Challenge;
+
+    /// Absorbs a message into the transcript.
+    fn absorb(&mut self, sys: &mut S, message: &[Self::Element]);
+
+    /// Derives a challenge from all of the messages absorbed so far, including previous
+    /// challenges.
+    fn squeeze(&mut self, sys: &mut S) -> Self::Challenge;
+}
+
+/// A transcript over field elements, implemented as a Poseidon sponge with a width-3 permutation,
+/// absorbing and squeezing up to two elements per permutation. Each message is preceded by its
+/// length, and each squeeze pads the pending elements with a one, so that trailing zeros are
+/// never lost.
+pub struct PoseidonTranscript<S: SystemRepr<FieldElement<F>> + ?Sized, F: PrimeField> {
+    params: PoseidonParams<F>,
+
+    /// The state of the sponge. The first element is the capacity, and the others are the rate.
+    state: [Abstract<S, FieldElement<F>>; 3],
+
+    /// The elements absorbed since the last permutation.
+    pending: Vec<Abstract<S, FieldElement<F>>>,
+}
+
+impl<S: SystemPoseidon<F> + ?Sized, F: PrimeField> PoseidonTranscript<S, F> {
+    /// Constructs an empty transcript using the given permutation, which must have width 3. The
+    /// capacity is initialized to `domain`, so that transcripts for different protocols never
+    /// produce the same challenges.
+    pub fn new(sys: &mut S, params: PoseidonParams<F>, domain: F) -> Self {
+        assert_eq!(params.width(), 3, "permutation must have width 3");
+        let zero = sys.constant(FieldElement(F::zero()));
+        let domain = sys.constant(FieldElement(domain));
+        Self {
+            params,
+            state: [domain, zero.clone(), zero],
+            pending: Vec::new(),
+        }
+    }
+
+    /// Adds an element to the pending elements, applying the permutation once the rate is full.
+    fn push(&mut self, sys: &mut S, value: Abstract<S, FieldElement<F>>) {
+        self.pending.push(value);
+        if self.pending.len() == 2 {
+            self.permute(sys);
+        }
+    }
+
+    /// Adds the pending elements into the rate of the sponge, then applies the permutation.
+    fn permute(&mut self, sys: &mut S) {
+        for (value, state) in self.pending.drain(..).zip(self.state[1..].iter_mut()) {
+            *state = sys.add(state, &value);
+        }
+        sys.poseidon_permute(&self.params, &mut self.state);
+    }
+}
+
+impl<S: SystemPoseidon<F> + ?Sized, F: PrimeField> Transcript<S> for PoseidonTranscript<S, F> {
+    type Element = Abstract<S, FieldElement<F>>;
+    type Challenge = Abstract<S, FieldElement<F>>;
+
+    fn absorb(&mut self, sys: &mut S, message: &[Abstract<S, FieldElement<F>>]) {
+        let len = sys.constant(FieldElement(F::from(message.len() as u64)));
+        self.push(sys, len);
+        for value in message {
+            self.push(sys, value.clone());
+        }
+    }
+
+    fn squeeze(&mut self, sys: &mut S) -> Abstract<S, FieldElement<F>> {
+        // Pad with a one, so that absorbing a zero is never the same as absorbing nothing
+        let one = sys.constant(FieldElement(F::one()));
+        self.pending.push(one);
+        self.permute(sys);
+        self.state[1].clone()
+    }
+}
+
+/// A transcript over bytes, implemented as a SHA-256 hash chain. The transcript starts with the
+/// digest of its label, and each challenge is the digest of the previous one followed by the
+/// messages absorbed since then, each preceded by its length as a big-endian `u64`.
+pub struct Sha256Transcript<S: SystemRepr<u8> + ?Sized> {
+    /// The most recent challenge, or the digest of the label if there isn't one.
+    digest: [Abstract<S, u8>; 32],
+
+    /// The bytes absorbed since the last challenge.
+    pending: Vec<Abstract<S, u8>>,
+}
+
+impl<S: SystemSha256Bytes + ?Sized> Sha256Transcript<S> {
+    /// Constructs an empty transcript with the given label, so that transcripts for different
+    /// protocols never produce the same challenges.
+    pub fn new(sys: &mut S, label: &[u8]) -> Self {
+        let digest = Eval.sha256_bytes(label).map(|byte| sys.constant(byte));
+        Self {
+            digest,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<S: SystemSha256Bytes + ?Sized> Transcript<S> for Sha256Transcript<S> {
+    type Element = Abstract<S, u8>;
+    type Challenge = [Abstract<S, u8>; 32];
+
+    fn absorb(&mut self, sys: &mut S, message: &[Abstract<S, u8>]) {
+        let len = (message.len() as u64).to_be_bytes();
+        self.pending.extend(len.map(|byte| sys.constant(byte)));
+        self.pending.extend_from_slice(message);
+    }
+
+    fn squeeze(&mut self, sys: &mut S) -> [Abstract<S, u8>; 32] {
+        let mut data = self.digest.to_vec();
+        data.append(&mut self.pending);
+        self.digest = sys.sha256_bytes(&data);
+        self.digest.clone()
+    }
+}
+
+#[cfg(feature = "r1cs")]
+#[test]
+fn test_poseidon_transcript() {
+    use crate::r1cs::{ArithmeticSystem, Formula, Variable};
+    use bls12_381::Scalar;
+    let x = |n: u64| FieldElement(Scalar::from(n));
+
+    // Challenges depend on every message, and on their order
+    let challenges = |messages: &[&[u64]]| {
+        let mut transcript =
+            PoseidonTranscript::new(&mut Eval, PoseidonParams::width_3(), 7.into());
+        let mut res = Vec::new();
+        for message in messages {
+            let message: Vec<_> = message.iter().map(|n| x(*n)).collect();
+            transcript.absorb(&mut Eval, &message);
+            res.push(transcript.squeeze(&mut Eval));
+        }
+        res.push(transcript.squeeze(&mut Eval));
+        res
+    };
+    let base = challenges(&[&[1, 2, 3], &[], &[4]]);
+    assert_ne!(base[1], base[2]);
+    assert_ne!(base[2], base[3]);
+    assert_ne!(base, challenges(&[&[1, 2, 3], &[], &[5]]));
+    assert_ne!(base, challenges(&[&[2, 1, 3], &[], &[4]]));
+    assert_ne!(base, challenges(&[&[1, 2], &[3], &[4]]));
+
+    // Trailing zeros and empty messages are not lost
+    assert_ne!(challenges(&[&[1, 2]]), challenges(&[&[1, 2, 0]]));
+    assert_ne!(challenges(&[&[]]), challenges(&[&[0]]));
+    let absorb_empty = |count: usize| {
+        let mut transcript =
+            PoseidonTranscript::new(&mut Eval, PoseidonParams::width_3(), 7.into());
+        transcript.absorb(&mut Eval, &[x(5)]);
+        for _ in 0..count {
+            transcript.absorb(&mut Eval, &[]);
+        }
+        transcript.squeeze(&mut Eval)
+    };
+    assert_ne!(absorb_empty(0), absorb_empty(1));
+    assert_ne!(absorb_empty(1), absorb_empty(2));
+
+    // The same challenges are derived in an arithmetic system
+    let mut sys = ArithmeticSystem::<Scalar>::new();
+    let vars: Vec<_> = (0..4).map(|_| sys.declare()).collect();
+    let f = |i: usize| Formula::from(vars[i]);
+    let mut transcript = PoseidonTranscript::new(&mut sys, PoseidonParams::width_3(), 7.into());
+    let mut res = Vec::new();
+    for message in [&[f(0), f(1), f(2)][..], &[], &[f(3)]] {
+        transcript.absorb(&mut sys, message);
+        res.push(transcript.squeeze(&mut sys));
+    }
+    res.push(transcript.squeeze(&mut sys));
+    let known: Vec<(Variable, Scalar)> = (vars.iter().copied())
+        .zip([1, 2, 3, 4].map(Scalar::from))
+        .collect();
+    let assignment = sys.solve(&known).unwrap();
+    assert_eq!(sys.check(&assignment), Ok(()));
+    let res: Vec<_> = res
+        .iter()
+        .map(|challenge| FieldElement(sys.eval(*challenge, &assignment)))
+        .collect();
+    assert_eq!(res, base);
+}
+
+#[test]
+fn test_sha256_transcript() {
+    let hex = |str: &str| -> Vec<u8> {
+        (0..str.len() / 2)
+            .map(|i| u8::from_str_radix(&str[i * 2..i * 2 + 2], 16).unwrap())
+            .collect()
+    };
+
+    // Challenges are `sha256(sha256("test") || len || "abc")` and then `sha256(previous)`
+    let mut transcript = Sha256Transcript::new(&mut Eval, b"test");
+    transcript.absorb(&mut Eval, b"abc");
+    let first = transcript.squeeze(&mut Eval);
+    let second = transcript.squeeze(&mut Eval);
+    assert_eq!(
+        first.to_vec(),
+        hex("f9234665afe075793785553d183e0881716fd1d16c9702be7be8b4132078c9b3")
+    );
+    assert_eq!(second.to_vec(), Eval.sha256_bytes(&first).to_vec());
+
+    // The same challenges are derived with emulated bytes
+    let sys = &mut BinaryEmulate::new(Eval);
+    let mut transcript = Sha256Transcript::new(sys, b"test");
+    let message = b"abc".map(|byte| sys.constant(byte));
+    transcript.absorb(sys, &message);
+    let challenge = transcript.squeeze(sys);
+    assert_eq!(SystemRead::<[u8; 32]>::read_value(sys, &challenge), first);
+    let challenge = transcript.squeeze(sys);
+    assert_eq!(SystemRead::<[u8; 32]>::read_value(sys, &challenge), second);
+}
+
+#[test]
+fn test_sha256_transcript_boundaries() {
+    let challenge = |messages: &[&[u8]]| {
+        let mut transcript = Sha256Transcript::new(&mut Eval, b"test");
+        for message in messages {
+            transcript.absorb(&mut Eval, message);
+        }
+        transcript.squeeze(&mut Eval)
+    };
+    assert_ne!(challenge(&[b"ab", b"c"]), challenge(&[b"abc"]));
+    assert_ne!(challenge(&[b"abc"]), challenge(&[b"abc", b""]));
+}
@@ -0,0 +1,122 @@
+//! `wasm_bindgen` wrappers for loading circuits and generating witnesses in the browser, so that
+//! witnesses never need to leave the client. A circuit is a [`SubcircuitTemplate`] serialized
+//! with [`SubcircuitTemplate::write`], passed as a `Uint8Array`. Inputs, outputs and wire values
+//! are also exchanged as `Uint8Array`s, with one byte per bit.
+//!
+//! The wires of a witness are the nodes of the circuit once fully flattened, in the same order as
+//! [`Graph::nodes`](crate::graph::Graph::nodes), as in the `capi` feature.
+use crate::graph::{Node, SubcircuitTemplate};
+use crate::*;
+use wasm_bindgen::prelude::*;
+
+/// A circuit which witnesses can be generated for.
+#[wasm_bindgen(js_name = Circuit)]
+pub struct WasmCircuit {
+    template: SubcircuitTemplate,
+}
+
+#[wasm_bindgen(js_class = Circuit)]
+impl WasmCircuit {
+    /// Loads a circuit from the bytes written by [`SubcircuitTemplate::write`].
+    #[wasm_bindgen(constructor)]
+    pub fn load(data: &[u8]) -> Result<WasmCircuit, JsError> {
+        let template =
+            SubcircuitTemplate::read(data).map_err(|err| JsError::new(&err.to_string()))?;
+        Ok(Self {
+            template: template.flatten(),
+        })
+    }
+
+    /// The number of inputs to this circuit.
+    #[wasm_bindgen(getter, js_name = numInputs)]
+    pub fn num_inputs(&self) -> usize {
+        self.template.num_inputs()
+    }
+
+    /// The number of outputs of this circuit.
+    #[wasm_bindgen(getter, js_name = numOutputs)]
+    pub fn num_outputs(&self) -> usize {
+        self.template.num_outputs()
+    }
+
+    /// The number of wires in a witness for this circuit.
+    #[wasm_bindgen(getter, js_name = numWires)]
+    pub fn num_wires(&self) -> usize {
+        self.template.graph().nodes().len()
+    }
+
+    /// Generates a witness from the given inputs, where each nonzero byte is true. There must be
+    /// exactly one byte for each input.
+    #[wasm_bindgen(js_name = generateWitness)]
+    pub fn generate_witness(&self, inputs: &[u8]) -> Result<WasmWitness, JsError> {
+        if inputs.len() != self.num_inputs() {
+            return Err(JsError::new(&format!(
+                "expected {} inputs, got {}",
+                self.num_inputs(),
+                inputs.len()
+            )));
+        }
+        Ok(self.witness(inputs))
+    }
+}
+
+impl WasmCircuit {
+    /// Generates a witness from the given inputs, which must have the right length.
+    fn witness(&self, inputs: &[u8]) -> WasmWitness {
+        let inputs: Vec<bool> = inputs.iter().map(|value| *value != 0).collect();
+        let graph = self.template.graph();
+        let nodes: Vec<_> = (0..graph.nodes().len()).map(Node::from_index).collect();
+        let wires = graph.replay(&mut Eval, &inputs, &nodes);
+        WasmWitness {
+            outputs: (self.template.outputs().iter())
+                .map(|node| u8::from(wires[node.index()]))
+                .collect(),
+            wires: wires.into_iter().map(u8::from).collect(),
+        }
+    }
+}
+
+/// The values of the outputs and wires of a [`WasmCircuit`] for a particular set of inputs.
+#[wasm_bindgen(js_name = Witness)]
+pub struct WasmWitness {
+    outputs: Vec<u8>,
+    wires: Vec<u8>,
+}
+
+#[wasm_bindgen(js_class = Witness)]
+impl WasmWitness {
+    /// The value of each output, as a byte which is 0 or 1.
+    #[wasm_bindgen(getter)]
+    pub fn outputs(&self) -> Vec<u8> {
+        self.outputs.clone()
+    }
+
+    /// The value of each wire, as a byte which is 0 or 1.
+    #[wasm_bindgen(getter)]
+    pub fn wires(&self) -> Vec<u8> {
+        self.wires.clone()
+    }
+}
+
+#[test]
+fn test_wasm_witness() {
+    let gadget = SubcircuitTemplate::capture(3, |graph, inputs| {
+        let ab = SystemBitXor::<bool>::xor(graph, &inputs[0], &inputs[1]);
+        let abc = SystemBitXor::<bool>::xor(graph, &ab, &inputs[2]);
+        let maj = SystemBitAnd::<bool>::and(graph, &ab, &inputs[2]);
+        vec![abc, maj]
+    });
+    let mut data = Vec::new();
+    gadget.write(&mut data).unwrap();
+    let circuit = WasmCircuit::load(&data).ok().unwrap();
+    assert_eq!(circuit.num_inputs(), 3);
+    assert_eq!(circuit.num_outputs(), 2);
+
+    // Errors can only be constructed on wasm targets, so only the success path is tested here
+    let witness = circuit.generate_witness(&[1, 0, 1]).ok().unwrap();
+    assert_eq!(witness.outputs(), [0, 1]);
+    let witness = circuit.generate_witness(&[1, 1, 7]).ok().unwrap();
+    assert_eq!(witness.outputs(), [1, 0]);
+    assert_eq!(witness.wires().len(), circuit.num_wires());
+    assert_eq!(witness.wires()[..3], [1, 1, 1]);
+}
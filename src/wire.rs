@@ -0,0 +1,572 @@
+//! Gadgets for reading and writing binary messages.
+use crate::bytes::{AbstractBytes, SystemBytes};
+use crate::ram::AbstractRam;
+use crate::*;
+use array_init::array_init;
+use std::marker::PhantomData;
+
+/// A system in which binary messages can be read and written.
+pub trait SystemWire: SystemBytes + SystemSelect<u64> + SystemOrd<u64> {}
+
+impl<S: SystemBytes + SystemSelect<u64> + SystemOrd<u64>> SystemWire for S {}
+
+/// Reads values sequentially from a byte string, tracking whether all reads so far were within
+/// the string. Values read beyond the end of the string are unspecified.
+pub struct Reader<S: SystemWire + ?Sized> {
+    bytes: AbstractBytes<S>,
+    buf: AbstractRam<S, u8>,
+    pos: Abstract<S, u32>,
+    valid: Abstract<S, bool>,
+}
+
+impl<S: SystemWire + ?Sized> Reader<S> {
+    /// Constructs a [`Reader`] positioned at the start of the given string. Panics if the
+    /// capacity of the string is zero.
+    pub fn new(sys: &mut S, bytes: &AbstractBytes<S>) -> Self {
+        Self {
+            bytes: bytes.clone(),
+            buf: AbstractRam::new(bytes.data().to_vec()),
+            pos: sys.constant(0u32),
+            valid: sys.constant(true),
+        }
+    }
+
+    /// The offset of the next byte to be read.
+    pub fn pos(&self) -> &Abstract<S, u32> {
+        &self.pos
+    }
+
+    /// Whether all reads so far were within the string.
+    pub fn valid(&self) -> &Abstract<S, bool> {
+        &self.valid
+    }
+
+    /// Determines whether all reads so far were within the string, and the entire string has
+    /// been read.
+    pub fn finish(&self, sys: &mut S) -> Abstract<S, bool> {
+        let at_end = eq_u32(sys, &self.pos, self.bytes.len());
+        SystemBitAnd::<bool>::and(sys, &self.valid, &at_end)
+    }
+
+    /// Advances the position of this reader by the given number of bytes.
+    pub fn skip(&mut self, sys: &mut S, len: &Abstract<S, u32>) {
+        let end = SystemWrappingAdd::<u32>::wrapping_add(sys, &self.pos, len);
+        let no_overflow = SystemOrd::<u32>::le(sys, &self.pos, &end);
+        let within = SystemOrd::<u32>::le(sys, &end, self.bytes.len());
+        let in_bounds = SystemBitAnd::<bool>::and(sys, &no_overflow, &within);
+        self.valid = SystemBitAnd::<bool>::and(sys, &self.valid, &in_bounds);
+        self.pos = end;
+    }
+
+    /// Performs reads using `f`, but only advances this reader if `enable` is true. If `enable`
+    /// is false, the values returned by `f` are unspecified and the validity of this reader is
+    /// unaffected.
+    pub fn read_if<T>(
+        &mut self,
+        sys: &mut S,
+        enable: &Abstract<S, bool>,
+        f: impl FnOnce(&mut S, &mut Self) -> T,
+    ) -> T {
+        let pos = self.pos.clone();
+        let valid = self.valid.clone();
+        let res = f(sys, self);
+        self.pos = SystemSelect::<u32>::select(sys, enable, &self.pos, &pos);
+        self.valid = SystemSelect::<bool>::select(sys, enable, &self.valid, &valid);
+        res
+    }
+
+    /// Reads the next byte without advancing.
+    pub fn peek_u8(&self, sys: &mut S) -> Abstract<S, u8> {
+        self.peek(sys, 0)
+    }
+
+    /// Reads the byte at the given offset from the current position, without advancing.
+    fn peek(&self, sys: &mut S, offset: usize) -> Abstract<S, u8> {
+        let offset = SystemRepr::<u32>::constant(sys, offset as u32);
+        let pos = SystemWrappingAdd::<u32>::wrapping_add(sys, &self.pos, &offset);
+        let bits = sys.bits_of_u32(&pos);
+        let addr_bits = (usize::BITS - (self.buf.len() - 1).leading_zeros()) as usize;
+        self.buf.read(sys, &bits[..addr_bits])
+    }
+
+    /// Reads a fixed number of bytes.
+    pub fn read_bytes<const N: usize>(&mut self, sys: &mut S) -> [Abstract<S, u8>; N] {
+        let res = array_init(|i| self.peek(sys, i));
+        let len = SystemRepr::<u32>::constant(sys, N as u32);
+        self.skip(sys, &len);
+        res
+    }
+
+    /// Reads a string of the given length, with a buffer of the given capacity. If the length
+    /// exceeds the capacity, the reader becomes invalid.
+    pub fn read_slice(
+        &mut self,
+        sys: &mut S,
+        len: &Abstract<S, u32>,
+        capacity: usize,
+    ) -> AbstractBytes<S> {
+        let (res, valid) = self.bytes.slice(sys, &self.pos, len, capacity);
+        self.valid = SystemBitAnd::<bool>::and(sys, &self.valid, &valid);
+        self.skip(sys, len);
+        res
+    }
+
+    /// Reads a string preceded by its length in the format `L`, with a buffer of the given
+    /// capacity. If the length exceeds the capacity, the reader becomes invalid.
+    pub fn read_prefixed<L: Format<S, Value = Abstract<S, u64>>>(
+        &mut self,
+        sys: &mut S,
+        capacity: usize,
+    ) -> AbstractBytes<S> {
+        let len = L::read(sys, self);
+        let bits = sys.bits_of_u64(&len);
+        let low: [_; 32] = array_init(|i| bits[i].clone());
+        let mut fits = SystemRepr::<bool>::constant(sys, true);
+        for bit in &bits[32..] {
+            let zero = SystemNot::<bool>::not(sys, bit);
+            fits = SystemBitAnd::<bool>::and(sys, &fits, &zero);
+        }
+        self.valid = SystemBitAnd::<bool>::and(sys, &self.valid, &fits);
+        let len = sys.u32_of_bits(&low);
+        self.read_slice(sys, &len, capacity)
+    }
+
+    /// Reads a byte.
+    pub fn read_u8(&mut self, sys: &mut S) -> Abstract<S, u8> {
+        let [res] = self.read_bytes(sys);
+        res
+    }
+
+    /// Reads a big-endian 32-bit integer.
+    pub fn read_u32_be(&mut self, sys: &mut S) -> Abstract<S, u32> {
+        let bytes = self.read_bytes(sys);
+        sys.pack_be_u32(&bytes)
+    }
+
+    /// Reads a little-endian 32-bit integer.
+    pub fn read_u32_le(&mut self, sys: &mut S) -> Abstract<S, u32> {
+        let bytes = self.read_bytes(sys);
+        sys.pack_le_u32(&bytes)
+    }
+
+    /// Reads a big-endian 64-bit integer.
+    pub fn read_u64_be(&mut self, sys: &mut S) -> Abstract<S, u64> {
+        let bytes = self.read_bytes(sys);
+        sys.pack_be_u64(&bytes)
+    }
+
+    /// Reads a little-endian 64-bit integer.
+    pub fn read_u64_le(&mut self, sys: &mut S) -> Abstract<S, u64> {
+        let bytes = self.read_bytes(sys);
+        sys.pack_le_u64(&bytes)
+    }
+
+    /// Reads an unsigned LEB128 varint, as used by protobuf. Varints must be at most 10 bytes
+    /// long, and bits beyond the 64th are ignored.
+    pub fn read_varint(&mut self, sys: &mut S) -> Abstract<S, u64> {
+        let mut active = SystemRepr::<bool>::constant(sys, true);
+        let mut len = SystemRepr::<u32>::constant(sys, 0);
+        let mut bits: [_; 64] = array_init(|_| SystemRepr::<bool>::constant(sys, false));
+        for i in 0..10 {
+            let byte = self.peek(sys, i);
+            let byte = sys.bits_of_u8(&byte);
+            for j in 0..7 {
+                if 7 * i + j < 64 {
+                    bits[7 * i + j] = SystemBitAnd::<bool>::and(sys, &active, &byte[j]);
+                }
+            }
+            let next = add_const(sys, &len, 1);
+            len = SystemSelect::<u32>::select(sys, &active, &next, &len);
+            active = SystemBitAnd::<bool>::and(sys, &active, &byte[7]);
+        }
+        let ended = SystemNot::<bool>::not(sys, &active);
+        self.valid = SystemBitAnd::<bool>::and(sys, &self.valid, &ended);
+        self.skip(sys, &len);
+        sys.u64_of_bits(&bits)
+    }
+
+    /// Reads a Bitcoin `CompactSize` integer.
+    pub fn read_compact_size(&mut self, sys: &mut S) -> Abstract<S, u64> {
+        let bytes: [_; 9] = array_init(|i| self.peek(sys, i));
+        let zero = SystemRepr::<u8>::constant(sys, 0);
+        let widen = |sys: &mut S, bytes: &[Abstract<S, u8>]| {
+            let le: [_; 8] = array_init(|i| bytes.get(i).unwrap_or(&zero).clone());
+            sys.pack_le_u64(&le)
+        };
+        let small = widen(sys, &bytes[..1]);
+        let u16 = widen(sys, &bytes[1..3]);
+        let u32 = widen(sys, &bytes[1..5]);
+        let u64 = widen(sys, &bytes[1..9]);
+        let is_u16 = eq_u8_const(sys, &bytes[0], 0xfd);
+        let is_u32 = eq_u8_const(sys, &bytes[0], 0xfe);
+        let is_u64 = eq_u8_const(sys, &bytes[0], 0xff);
+        let mut value = small;
+        let mut len = SystemRepr::<u32>::constant(sys, 1);
+        for (cond, wide, wide_len) in [(is_u16, u16, 3), (is_u32, u32, 5), (is_u64, u64, 9)] {
+            value = SystemSelect::<u64>::select(sys, &cond, &wide, &value);
+            let wide_len = SystemRepr::<u32>::constant(sys, wide_len);
+            len = SystemSelect::<u32>::select(sys, &cond, &wide_len, &len);
+        }
+        self.skip(sys, &len);
+        value
+    }
+}
+
+/// Writes values sequentially to a byte string.
+pub struct Writer<S: SystemWire + ?Sized> {
+    bytes: AbstractBytes<S>,
+
+    /// The length of the string, if it is known at synthesis time.
+    fixed_len: Option<usize>,
+}
+
+impl<S: SystemWire + ?Sized> Writer<S> {
+    /// Constructs an empty [`Writer`].
+    pub fn new(sys: &mut S) -> Self {
+        Self {
+            bytes: AbstractBytes::from_const(sys, &[], 0),
+            fixed_len: Some(0),
+        }
+    }
+
+    /// Gets the string written so far.
+    pub fn finish(self) -> AbstractBytes<S> {
+        self.bytes
+    }
+
+    /// Writes a fixed number of bytes.
+    pub fn write_bytes(&mut self, sys: &mut S, bytes: &[Abstract<S, u8>]) {
+        match self.fixed_len {
+            Some(len) => {
+                let mut data = self.bytes.data().to_vec();
+                data.extend_from_slice(bytes);
+                let len = SystemRepr::<u32>::constant(sys, (len + bytes.len()) as u32);
+                self.bytes = AbstractBytes::from_raw(data, len);
+                self.fixed_len = Some(self.bytes.capacity());
+            }
+            None => {
+                let len = SystemRepr::<u32>::constant(sys, bytes.len() as u32);
+                let bytes = AbstractBytes::from_raw(bytes.to_vec(), len);
+                self.bytes = self.bytes.concat(sys, &bytes);
+            }
+        }
+    }
+
+    /// Writes a string of abstract length.
+    pub fn write_slice(&mut self, sys: &mut S, bytes: &AbstractBytes<S>) {
+        self.bytes = self.bytes.concat(sys, bytes);
+        self.fixed_len = None;
+    }
+
+    /// Writes a string preceded by its length in the format `L`.
+    pub fn write_prefixed<L: Format<S, Value = Abstract<S, u64>>>(
+        &mut self,
+        sys: &mut S,
+        bytes: &AbstractBytes<S>,
+    ) {
+        let bits = sys.bits_of_u32(bytes.len());
+        let f = SystemRepr::<bool>::constant(sys, false);
+        let bits: [_; 64] = array_init(|i| bits.get(i).cloned().unwrap_or_else(|| f.clone()));
+        let len = sys.u64_of_bits(&bits);
+        L::write(sys, self, &len);
+        self.write_slice(sys, bytes)
+    }
+
+    /// Writes a byte.
+    pub fn write_u8(&mut self, sys: &mut S, value: &Abstract<S, u8>) {
+        self.write_bytes(sys, std::slice::from_ref(value))
+    }
+
+    /// Writes a big-endian 32-bit integer.
+    pub fn write_u32_be(&mut self, sys: &mut S, value: &Abstract<S, u32>) {
+        let bytes = sys.unpack_be_u32(value);
+        self.write_bytes(sys, &bytes)
+    }
+
+    /// Writes a little-endian 32-bit integer.
+    pub fn write_u32_le(&mut self, sys: &mut S, value: &Abstract<S, u32>) {
+        let bytes = sys.unpack_le_u32(value);
+        self.write_bytes(sys, &bytes)
+    }
+
+    /// Writes a big-endian 64-bit integer.
+    pub fn write_u64_be(&mut self, sys: &mut S, value: &Abstract<S, u64>) {
+        let bytes = sys.unpack_be_u64(value);
+        self.write_bytes(sys, &bytes)
+    }
+
+    /// Writes a little-endian 64-bit integer.
+    pub fn write_u64_le(&mut self, sys: &mut S, value: &Abstract<S, u64>) {
+        let bytes = sys.unpack_le_u64(value);
+        self.write_bytes(sys, &bytes)
+    }
+
+    /// Writes an unsigned LEB128 varint, as used by protobuf, using the shortest encoding.
+    pub fn write_varint(&mut self, sys: &mut S, value: &Abstract<S, u64>) {
+        let bits = sys.bits_of_u64(value);
+        let f = SystemRepr::<bool>::constant(sys, false);
+
+        // Determine which groups are followed by a non-zero group
+        let mut more = vec![f.clone(); 10];
+        for i in (0..9).rev() {
+            let mut nonzero = more[i + 1].clone();
+            for bit in bits.iter().skip(7 * (i + 1)).take(7) {
+                nonzero = SystemBitOr::<bool>::or(sys, &nonzero, bit);
+            }
+            more[i] = nonzero;
+        }
+        let mut len = SystemRepr::<u32>::constant(sys, 1);
+        let mut data = Vec::with_capacity(10);
+        for (i, more) in more.iter().enumerate() {
+            let byte: [_; 8] = array_init(|j| match j {
+                7 => more.clone(),
+                _ => bits.get(7 * i + j).cloned().unwrap_or_else(|| f.clone()),
+            });
+            data.push(sys.u8_of_bits(&byte));
+            let next = add_const(sys, &len, 1);
+            len = SystemSelect::<u32>::select(sys, more, &next, &len);
+        }
+        let bytes = AbstractBytes::new(sys, data, len);
+        self.write_slice(sys, &bytes)
+    }
+
+    /// Writes a Bitcoin `CompactSize` integer, using the shortest encoding.
+    pub fn write_compact_size(&mut self, sys: &mut S, value: &Abstract<S, u64>) {
+        let le = sys.unpack_le_u64(value);
+        let mut first = le[0].clone();
+        let mut len = SystemRepr::<u32>::constant(sys, 1);
+        for (limit, prefix, wide_len) in [(0xfd, 0xfd, 3), (0x10000, 0xfe, 5), (1 << 32, 0xff, 9)] {
+            let limit = SystemRepr::<u64>::constant(sys, limit);
+            let wide = SystemOrd::<u64>::le(sys, &limit, value);
+            let prefix = SystemRepr::<u8>::constant(sys, prefix);
+            first = SystemSelect::<u8>::select(sys, &wide, &prefix, &first);
+            let wide_len = SystemRepr::<u32>::constant(sys, wide_len);
+            len = SystemSelect::<u32>::select(sys, &wide, &wide_len, &len);
+        }
+        let mut data = vec![first];
+        data.extend(le);
+        let bytes = AbstractBytes::new(sys, data, len);
+        self.write_slice(sys, &bytes)
+    }
+}
+
+/// A format in which values can be read from and written to binary messages. Formats can be
+/// composed using tuples and [`Array`], so that the layout of a message can be declared as a
+/// type.
+pub trait Format<S: SystemWire + ?Sized> {
+    /// The type of the abstract value represented by this format.
+    type Value;
+
+    /// Reads a value in this format.
+    fn read(sys: &mut S, reader: &mut Reader<S>) -> Self::Value;
+
+    /// Writes a value in this format.
+    fn write(sys: &mut S, writer: &mut Writer<S>, value: &Self::Value);
+}
+
+/// Declares a [`Format`] implemented by a pair of [`Reader`] and [`Writer`] methods.
+macro_rules! impl_format {
+    ($(#[$doc:meta])* $name:ident, $t:ty, $read:ident, $write:ident) => {
+        $(#[$doc])*
+        pub struct $name;
+
+        impl<S: SystemWire + ?Sized> Format<S> for $name {
+            type Value = Abstract<S, $t>;
+            fn read(sys: &mut S, reader: &mut Reader<S>) -> Self::Value {
+                reader.$read(sys)
+            }
+            fn write(sys: &mut S, writer: &mut Writer<S>, value: &Self::Value) {
+                writer.$write(sys, value)
+            }
+        }
+    };
+}
+
+impl_format!(
+    /// The [`Format`] for a byte.
+    U8, u8, read_u8, write_u8
+);
+impl_format!(
+    /// The [`Format`] for a big-endian 32-bit integer.
+    U32Be, u32, read_u32_be, write_u32_be
+);
+impl_format!(
+    /// The [`Format`] for a little-endian 32-bit integer.
+    U32Le, u32, read_u32_le, write_u32_le
+);
+impl_format!(
+    /// The [`Format`] for a big-endian 64-bit integer.
+    U64Be, u64, read_u64_be, write_u64_be
+);
+impl_format!(
+    /// The [`Format`] for a little-endian 64-bit integer.
+    U64Le, u64, read_u64_le, write_u64_le
+);
+impl_format!(
+    /// The [`Format`] for an unsigned LEB128 varint.
+    Varint, u64, read_varint, write_varint
+);
+impl_format!(
+    /// The [`Format`] for a Bitcoin `CompactSize` integer.
+    CompactSize, u64, read_compact_size, write_compact_size
+);
+
+/// The [`Format`] for a fixed number of bytes.
+pub struct Bytes<const N: usize>;
+
+impl<S: SystemWire + ?Sized, const N: usize> Format<S> for Bytes<N> {
+    type Value = [Abstract<S, u8>; N];
+    fn read(sys: &mut S, reader: &mut Reader<S>) -> Self::Value {
+        reader.read_bytes(sys)
+    }
+    fn write(sys: &mut S, writer: &mut Writer<S>, value: &Self::Value) {
+        writer.write_bytes(sys, value)
+    }
+}
+
+/// The [`Format`] for a string preceded by its length in the format `L`, with a buffer of
+/// capacity `CAP`. Reading a string whose length exceeds the capacity makes the reader invalid.
+pub struct Prefixed<L, const CAP: usize>(PhantomData<L>);
+
+impl<S: SystemWire + ?Sized, L: Format<S, Value = Abstract<S, u64>>, const CAP: usize> Format<S>
+    for Prefixed<L, CAP>
+{
+    type Value = AbstractBytes<S>;
+    fn read(sys: &mut S, reader: &mut Reader<S>) -> Self::Value {
+        reader.read_prefixed::<L>(sys, CAP)
+    }
+    fn write(sys: &mut S, writer: &mut Writer<S>, value: &Self::Value) {
+        writer.write_prefixed::<L>(sys, value)
+    }
+}
+
+/// The [`Format`] for a fixed number of values in the format `F`.
+pub struct Array<F, const N: usize>(PhantomData<F>);
+
+impl<S: SystemWire + ?Sized, F: Format<S>, const N: usize> Format<S> for Array<F, N> {
+    type Value = [F::Value; N];
+    fn read(sys: &mut S, reader: &mut Reader<S>) -> Self::Value {
+        array_init(|_| F::read(sys, reader))
+    }
+    fn write(sys: &mut S, writer: &mut Writer<S>, value: &Self::Value) {
+        for value in value {
+            F::write(sys, writer, value)
+        }
+    }
+}
+
+/// Implements [`Format`] for a tuple of formats, which are read and written in sequence.
+macro_rules! impl_format_tuple {
+    ($($f:ident $i:tt),*) => {
+        impl<S: SystemWire + ?Sized, $($f: Format<S>),*> Format<S> for ($($f,)*) {
+            type Value = ($($f::Value,)*);
+            fn read(sys: &mut S, reader: &mut Reader<S>) -> Self::Value {
+                ($($f::read(sys, reader),)*)
+            }
+            fn write(sys: &mut S, writer: &mut Writer<S>, value: &Self::Value) {
+                $($f::write(sys, writer, &value.$i);)*
+            }
+        }
+    };
+}
+
+impl_format_tuple!(A 0, B 1);
+impl_format_tuple!(A 0, B 1, C 2);
+impl_format_tuple!(A 0, B 1, C 2, D 3);
+impl_format_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_format_tuple!(A 0, B 1, C 2, D 3, E 4, G 5);
+
+#[test]
+fn test_wire_ints() {
+    let data = [
+        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+        0x10,
+    ];
+    let bytes = AbstractBytes::from_const(&mut Eval, &data, 20);
+    let mut reader = Reader::new(&mut Eval, &bytes);
+    assert_eq!(reader.read_u32_be(&mut Eval), 0x01020304);
+    assert_eq!(reader.read_u32_le(&mut Eval), 0x08070605);
+    assert_eq!(reader.read_u64_be(&mut Eval), 0x090a0b0c0d0e0f10);
+    assert!(reader.finish(&mut Eval));
+    assert_eq!(reader.read_u8(&mut Eval), 0);
+    assert!(!reader.valid());
+
+    let mut writer = Writer::new(&mut Eval);
+    writer.write_u32_be(&mut Eval, &0x01020304);
+    writer.write_u64_le(&mut Eval, &0x0807060504030201);
+    let bytes = writer.finish();
+    assert_eq!(*bytes.len(), 12);
+    assert_eq!(bytes.data(), [1, 2, 3, 4, 1, 2, 3, 4, 5, 6, 7, 8]);
+}
+
+#[test]
+fn test_wire_varints() {
+    let cases: [(u64, &[u8], &[u8]); 6] = [
+        (0, &[0x00], &[0x00]),
+        (0x7f, &[0x7f], &[0x7f]),
+        (300, &[0xac, 0x02], &[0xfd, 0x2c, 0x01]),
+        (0xfd, &[0xfd, 0x01], &[0xfd, 0xfd, 0x00]),
+        (
+            0x12345678,
+            &[0xf8, 0xac, 0xd1, 0x91, 0x01],
+            &[0xfe, 0x78, 0x56, 0x34, 0x12],
+        ),
+        (
+            u64::MAX,
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+        ),
+    ];
+    for (value, varint, compact) in cases {
+        let mut writer = Writer::new(&mut Eval);
+        writer.write_varint(&mut Eval, &value);
+        writer.write_compact_size(&mut Eval, &value);
+        let bytes = writer.finish();
+        let mut expected = varint.to_vec();
+        expected.extend_from_slice(compact);
+        assert_eq!(*bytes.len() as usize, expected.len());
+        assert_eq!(bytes.data()[..expected.len()], expected[..]);
+
+        let mut reader = Reader::new(&mut Eval, &bytes);
+        assert_eq!(reader.read_varint(&mut Eval), value);
+        assert_eq!(reader.read_compact_size(&mut Eval), value);
+        assert!(reader.finish(&mut Eval));
+    }
+
+    // Unterminated varint
+    let bytes = AbstractBytes::from_const(&mut Eval, &[0x80, 0x80], 4);
+    let mut reader = Reader::new(&mut Eval, &bytes);
+    reader.read_varint(&mut Eval);
+    assert!(!reader.valid());
+}
+
+#[test]
+fn test_wire_format() {
+    type Message = (U32Le, Prefixed<CompactSize, 8>, Array<U8, 2>, Bytes<3>);
+    let data = [
+        0x78, 0x56, 0x34, 0x12, 0x05, b'h', b'e', b'l', b'l', b'o', 0xaa, 0xbb, b'x', b'y', b'z',
+    ];
+    let mut sys = BinaryEmulate::new(Eval);
+    let bytes = AbstractBytes::from_const(&mut sys, &data, 16);
+    let mut reader = Reader::new(&mut sys, &bytes);
+    let message = Message::read(&mut sys, &mut reader);
+    assert!(reader.finish(&mut sys));
+    assert_eq!(message.0, sys.constant(0x12345678u32));
+    let hello = AbstractBytes::from_const(&mut sys, b"hello", 8);
+    assert!(message.1.ct_eq(&mut sys, &hello));
+    assert_eq!(message.2, [0xaau8, 0xbb].map(|b| sys.constant(b)));
+
+    // Round trip
+    let mut writer = Writer::new(&mut sys);
+    Message::write(&mut sys, &mut writer, &message);
+    let written = writer.finish();
+    assert!(written.ct_eq(&mut sys, &bytes));
+
+    // Lengths beyond the capacity are rejected
+    let mut data = data;
+    data[4] = 9;
+    let bytes = AbstractBytes::from_const(&mut Eval, &data, 16);
+    let mut reader = Reader::new(&mut Eval, &bytes);
+    Message::read(&mut Eval, &mut reader);
+    assert!(!reader.valid());
+}